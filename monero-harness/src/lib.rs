@@ -279,7 +279,7 @@ impl<'c> MoneroWalletRpc {
         let client = wallet::Client::localhost(wallet_rpc_port)?;
 
         client
-            .create_wallet(name.to_owned(), "English".to_owned())
+            .create_wallet(name.to_owned(), "English".to_owned(), String::new())
             .await?;
 
         Ok((
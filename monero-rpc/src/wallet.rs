@@ -5,23 +5,47 @@ use rust_decimal::Decimal;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
 
+/// Makes the wallet use its default ring size when passed as `ring_size`.
+pub const DEFAULT_RING_SIZE: u32 = 0;
+
 #[jsonrpc_client::api(version = "2.0")]
 pub trait MoneroWalletRpc {
     async fn get_address(&self, account_index: u32) -> GetAddress;
     async fn get_balance(&self, account_index: u32) -> GetBalance;
     async fn create_account(&self, label: String) -> CreateAccount;
+    async fn create_address(&self, account_index: u32, label: String) -> CreateAddress;
     async fn get_accounts(&self, tag: String) -> GetAccounts;
-    async fn open_wallet(&self, filename: String) -> WalletOpened;
+    async fn open_wallet(&self, filename: String, password: String) -> WalletOpened;
     async fn close_wallet(&self) -> WalletClosed;
-    async fn create_wallet(&self, filename: String, language: String) -> WalletCreated;
+    async fn create_wallet(
+        &self,
+        filename: String,
+        language: String,
+        password: String,
+    ) -> WalletCreated;
+    async fn change_wallet_password(
+        &self,
+        old_password: String,
+        new_password: String,
+    ) -> WalletPasswordChanged;
     async fn transfer(
         &self,
         account_index: u32,
         destinations: Vec<Destination>,
         get_tx_key: bool,
+        do_not_relay: bool,
+        ring_size: u32,
     ) -> Transfer;
     async fn get_height(&self) -> BlockHeight;
     async fn check_tx_key(&self, txid: String, tx_key: String, address: String) -> CheckTxKey;
+    async fn get_tx_proof(&self, txid: String, address: String, message: String) -> GetTxProof;
+    async fn check_tx_proof(
+        &self,
+        txid: String,
+        address: String,
+        message: String,
+        signature: String,
+    ) -> CheckTxProof;
     #[allow(clippy::too_many_arguments)]
     async fn generate_from_keys(
         &self,
@@ -34,7 +58,7 @@ pub trait MoneroWalletRpc {
         autosave_current: bool,
     ) -> GenerateFromKeys;
     async fn refresh(&self) -> Refreshed;
-    async fn sweep_all(&self, address: String) -> SweepAll;
+    async fn sweep_all(&self, address: String, ring_size: u32) -> SweepAll;
     async fn get_version(&self) -> Version;
 }
 
@@ -65,7 +89,8 @@ impl Client {
         })
     }
 
-    /// Transfers `amount` monero from `account_index` to `address`.
+    /// Transfers `amount` monero from `account_index` to `address` with the
+    /// wallet's default ring size.
     pub async fn transfer_single(
         &self,
         account_index: u32,
@@ -77,13 +102,45 @@ impl Client {
             address: address.to_owned(),
         }];
 
-        Ok(self.transfer(account_index, dest, true).await?)
+        Ok(self
+            .transfer(account_index, dest, true, false, DEFAULT_RING_SIZE)
+            .await?)
+    }
+
+    /// Builds, but does not publish, a transfer of `amount` monero from
+    /// `account_index` to `address` and returns its fee.
+    pub async fn estimate_transfer_fee(
+        &self,
+        account_index: u32,
+        amount: u64,
+        address: &str,
+    ) -> Result<u64> {
+        let dest = vec![Destination {
+            amount,
+            address: address.to_owned(),
+        }];
+
+        Ok(self
+            .transfer(account_index, dest, false, true, DEFAULT_RING_SIZE)
+            .await?
+            .fee)
     }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetAddress {
     pub address: String,
+    /// The subaddresses of the account.
+    #[serde(default)]
+    pub addresses: Vec<SubAddress>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SubAddress {
+    pub address: String,
+    pub address_index: u32,
+    pub label: String,
+    pub used: bool,
 }
 
 #[derive(Deserialize, Debug, Clone, Copy)]
@@ -121,6 +178,12 @@ pub struct CreateAccount {
     pub address: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateAddress {
+    pub address: String,
+    pub address_index: u32,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct GetAccounts {
     pub subaddress_accounts: Vec<SubAddressAccount>,
@@ -193,6 +256,19 @@ impl From<CheckTxKeyResponse> for CheckTxKey {
     }
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetTxProof {
+    pub signature: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct CheckTxProof {
+    pub good: bool,
+    pub in_pool: bool,
+    pub confirmations: u64,
+    pub received: u64,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct GenerateFromKeys {
     pub address: String,
@@ -208,6 +284,8 @@ pub struct Refreshed {
 #[derive(Debug, Clone, Deserialize)]
 pub struct SweepAll {
     pub tx_hash_list: Vec<String>,
+    #[serde(default)]
+    pub fee_list: Vec<u64>,
 }
 
 #[derive(Debug, Copy, Clone, Deserialize)]
@@ -218,6 +296,7 @@ pub struct Version {
 pub type WalletCreated = Empty;
 pub type WalletClosed = Empty;
 pub type WalletOpened = Empty;
+pub type WalletPasswordChanged = Empty;
 
 /// Zero-sized struct to allow serde to deserialize an empty JSON object.
 ///
@@ -276,4 +355,47 @@ mod tests {
 
         let _: Response<WalletCreated> = serde_json::from_str(response).unwrap();
     }
+
+    #[test]
+    fn can_deserialize_check_tx_proof() {
+        let result = r#"{
+          "confirmations": 482,
+          "good": true,
+          "in_pool": false,
+          "received": 1000000000000
+        }"#;
+
+        let check: CheckTxProof = serde_json::from_str(result).unwrap();
+
+        assert!(check.good);
+        assert!(!check.in_pool);
+        assert_eq!(check.received, 1_000_000_000_000);
+    }
+
+    #[test]
+    fn can_deserialize_labelled_subaddresses_of_get_address() {
+        let result = r#"{
+          "address": "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt",
+          "addresses": [{
+            "address": "55LTR8KniP4LQGJSPtbYDacR7dz8RBFnsfAKMaMuwUNYX6aQbBcovzDPyrQF9KXF9tVU6Xk3K8no1BywnJX6GvZX8yJsXvt",
+            "address_index": 0,
+            "label": "Primary account",
+            "used": true
+          }, {
+            "address": "7BnERTpvL5MbCLtj5n9No7J5oE5hHiB3tVCK5cjSvCsYWD2WRJLFuWeKTLiXo5QJqt2ZwUaLy2Vh1Ad51K7FNgqcHgjW85o",
+            "address_index": 1,
+            "label": "swap-ea030832-3be9-454f-bb98-5ea9a788406b",
+            "used": false
+          }]
+        }"#;
+
+        let get_address: GetAddress = serde_json::from_str(result).unwrap();
+
+        assert_eq!(get_address.addresses.len(), 2);
+        assert_eq!(get_address.addresses[1].address_index, 1);
+        assert_eq!(
+            get_address.addresses[1].label,
+            "swap-ea030832-3be9-454f-bb98-5ea9a788406b"
+        );
+    }
 }
@@ -0,0 +1,5 @@
+CREATE TABLE if NOT EXISTS swap_checkpoints
+(
+    swap_id     TEXT    PRIMARY KEY NOT NULL,
+    checkpoint  TEXT                NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE if NOT EXISTS swap_keys
+(
+    swap_id     TEXT    PRIMARY KEY NOT NULL,
+    keys        TEXT                NOT NULL
+);
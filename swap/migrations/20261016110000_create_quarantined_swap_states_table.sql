@@ -0,0 +1,8 @@
+CREATE TABLE if NOT EXISTS quarantined_swap_states
+(
+    id          INTEGER PRIMARY KEY NOT NULL,
+    swap_id     TEXT                NOT NULL,
+    entered_at  TEXT                NOT NULL,
+    state       TEXT                NOT NULL,
+    reason      TEXT                NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE if NOT EXISTS transfer_proofs
+(
+    swap_id     TEXT    PRIMARY KEY NOT NULL,
+    proof       TEXT                NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE if NOT EXISTS swap_metadata
+(
+    swap_id     TEXT    PRIMARY KEY NOT NULL,
+    metadata    TEXT                NOT NULL
+);
@@ -2,18 +2,27 @@ pub mod command;
 pub mod config;
 mod event_loop;
 mod network;
+mod pending_timelocks;
+mod quote_limiter;
 mod rate;
 mod recovery;
 pub mod tracing;
 
-pub use event_loop::{EventLoop, EventLoopHandle, FixedRate, KrakenRate, LatestRate};
+pub use event_loop::{
+    available_for_quote, AbortHandle, EventLoop, EventLoopHandle, FixedRate, KrakenRate,
+    LatestRate, PauseHandle, SWAP_CHANNEL_CAPACITY,
+};
 pub use network::behaviour::{Behaviour, OutEvent};
 pub use network::rendezvous::RendezvousNode;
 pub use network::transport;
+pub use pending_timelocks::{pending_timelocks, PendingTimelock, TimelockWaiter};
 pub use rate::Rate;
 pub use recovery::cancel::cancel;
+pub use recovery::emergency_shutdown::{
+    emergency_shutdown, shut_down_swap, ShutdownAction, ShutdownReport,
+};
 pub use recovery::punish::punish;
-pub use recovery::redeem::{redeem, Finality};
+pub use recovery::redeem::{confirm_redeem_broadcast, manual_broadcast_redeem, redeem, Finality};
 pub use recovery::refund::refund;
 pub use recovery::safely_abort::safely_abort;
 pub use recovery::{cancel, refund};
@@ -68,6 +68,30 @@ where
             env_config: env_config(testnet),
             cmd: Command::Config,
         },
+        RawCommand::CheckDatabase { repair } => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::CheckDatabase { repair },
+        },
+        RawCommand::EmergencyShutdown => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::EmergencyShutdown,
+        },
+        RawCommand::PendingTimelocks => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::PendingTimelocks,
+        },
         RawCommand::ExportBitcoinWallet => Arguments {
             testnet,
             json,
@@ -79,6 +103,7 @@ where
         RawCommand::ManualRecovery(ManualRecovery::Redeem {
             redeem_params: RecoverCommandParams { swap_id },
             do_not_await_finality,
+            manual_broadcast,
         }) => Arguments {
             testnet,
             json,
@@ -89,8 +114,19 @@ where
                 swap_id,
 
                 do_not_await_finality,
+                manual_broadcast,
             },
         },
+        RawCommand::ManualRecovery(ManualRecovery::ConfirmRedeemBroadcast {
+            confirm_params: RecoverCommandParams { swap_id },
+        }) => Arguments {
+            testnet,
+            json,
+            disable_timestamp,
+            config_path: config_path(config, testnet)?,
+            env_config: env_config(testnet),
+            cmd: Command::ConfirmRedeemBroadcast { swap_id },
+        },
         RawCommand::ManualRecovery(ManualRecovery::Cancel {
             cancel_params: RecoverCommandParams { swap_id },
         }) => Arguments {
@@ -205,6 +241,10 @@ pub enum Command {
     Redeem {
         swap_id: Uuid,
         do_not_await_finality: bool,
+        manual_broadcast: bool,
+    },
+    ConfirmRedeemBroadcast {
+        swap_id: Uuid,
     },
     Cancel {
         swap_id: Uuid,
@@ -219,6 +259,11 @@ pub enum Command {
         swap_id: Uuid,
     },
     ExportBitcoinWallet,
+    CheckDatabase {
+        repair: bool,
+    },
+    EmergencyShutdown,
+    PendingTimelocks,
 }
 
 #[derive(structopt::StructOpt, Debug)]
@@ -288,6 +333,24 @@ pub enum RawCommand {
     Balance,
     #[structopt(about = "Print the internal bitcoin wallet descriptor.")]
     ExportBitcoinWallet,
+    #[structopt(
+        about = "Checks the database for records that cannot be read, e.g. after a crash."
+    )]
+    CheckDatabase {
+        #[structopt(
+            long = "repair",
+            help = "Move unreadable swap states to a quarantine table and delete orphaned peer addresses, so that the remaining swaps can be resumed."
+        )]
+        repair: bool,
+    },
+    #[structopt(
+        about = "Safely aborts all swaps that did not lock Monero yet and checkpoints the others, printing the steps to recover them. Stop the ASB before running this."
+    )]
+    EmergencyShutdown,
+    #[structopt(
+        about = "Lists the swaps waiting on their cancel or punish timelock, soonest-expiring first."
+    )]
+    PendingTimelocks,
     #[structopt(about = "Contains sub-commands for recovering a swap manually.")]
     ManualRecovery(ManualRecovery),
 }
@@ -306,6 +369,19 @@ pub enum ManualRecovery {
             help = "If this flag is present we exit directly after publishing the redeem transaction without waiting for the transaction to be included in a block"
         )]
         do_not_await_finality: bool,
+
+        #[structopt(
+            long = "manual-broadcast",
+            help = "Print the signed redeem transaction instead of publishing it. Run `confirm-redeem-broadcast` once you have broadcast it yourself."
+        )]
+        manual_broadcast: bool,
+    },
+    #[structopt(
+        about = "Records that the redeem transaction printed by `redeem --manual-broadcast` was broadcast. Waits until the transaction is seen."
+    )]
+    ConfirmRedeemBroadcast {
+        #[structopt(flatten)]
+        confirm_params: RecoverCommandParams,
     },
     #[structopt(
         about = "Publishes the Bitcoin cancel transaction. By default, the cancel timelock will be enforced. A confirmed cancel transaction enables refund and punish."
@@ -393,6 +469,60 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn ensure_check_database_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![BINARY_NAME, "check-database", "--repair"];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::CheckDatabase { repair: true },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_emergency_shutdown_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![BINARY_NAME, "emergency-shutdown"];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::EmergencyShutdown,
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_pending_timelocks_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![BINARY_NAME, "pending-timelocks"];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::PendingTimelocks,
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
     #[test]
     fn ensure_balance_command_mapping_mainnet() {
         let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
@@ -514,6 +644,61 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn ensure_redeem_with_manual_broadcast_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![
+            BINARY_NAME,
+            "manual-recovery",
+            "redeem",
+            "--swap-id",
+            SWAP_ID,
+            "--manual-broadcast",
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::Redeem {
+                swap_id: Uuid::parse_str(SWAP_ID).unwrap(),
+                do_not_await_finality: false,
+                manual_broadcast: true,
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn ensure_confirm_redeem_broadcast_command_mapping_mainnet() {
+        let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
+        let mainnet_env_config = env::Mainnet::get_config();
+
+        let raw_ars = vec![
+            BINARY_NAME,
+            "manual-recovery",
+            "confirm-redeem-broadcast",
+            "--swap-id",
+            SWAP_ID,
+        ];
+        let expected_args = Arguments {
+            testnet: false,
+            json: false,
+            disable_timestamp: false,
+            config_path: default_mainnet_conf_path,
+            env_config: mainnet_env_config,
+            cmd: Command::ConfirmRedeemBroadcast {
+                swap_id: Uuid::parse_str(SWAP_ID).unwrap(),
+            },
+        };
+        let args = parse_args(raw_ars).unwrap();
+        assert_eq!(expected_args, args);
+    }
+
     #[test]
     fn ensure_safely_abort_command_mapping_mainnet() {
         let default_mainnet_conf_path = env::Mainnet::getConfigFileDefaults().unwrap().config_path;
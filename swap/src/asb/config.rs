@@ -1,4 +1,4 @@
-use crate::env::{Mainnet, Testnet};
+use crate::env::{Mainnet, NotPositive, Testnet};
 use crate::fs::{ensure_directory_exists, system_config_dir, system_data_dir};
 use crate::tor::{DEFAULT_CONTROL_PORT, DEFAULT_SOCKS5_PORT};
 use anyhow::{bail, Context, Result};
@@ -138,6 +138,9 @@ pub struct Network {
     pub rendezvous_point: Vec<Multiaddr>,
     #[serde(default, deserialize_with = "addr_list::deserialize")]
     pub external_addresses: Vec<Multiaddr>,
+    /// Whether messages to peers are compressed if they support compression
+    /// too, on by default.
+    pub compression: Option<bool>,
 }
 
 mod addr_list {
@@ -185,8 +188,27 @@ mod addr_list {
 #[serde(deny_unknown_fields)]
 pub struct Bitcoin {
     pub electrum_rpc_url: Url,
+    /// Electrum servers to fail over to, in order, if the one at
+    /// `electrum_rpc_url` is busy or unreachable.
+    #[serde(default)]
+    pub electrum_fallback_rpc_urls: Vec<Url>,
     pub target_block: usize,
     pub finality_confirmations: Option<u32>,
+    /// How many blocks to wait after the punish timelock expired before
+    /// punishing automatically. Manual punishing is not delayed.
+    pub punish_grace_blocks: Option<u32>,
+    /// After how many consecutive unused addresses a wallet sync stops.
+    pub sync_stop_gap: Option<usize>,
+    /// If set, Bitcoin transactions of the protocol are only broadcast if
+    /// their fee is within this many satoshi of the intended fee.
+    pub strict_fee_tolerance_sat: Option<u64>,
+    /// Whether to agree to an anchor output on the cancel transaction if the
+    /// CLI asks for one. Disabled if not set.
+    pub cancel_anchor: Option<bool>,
+    /// Up to how many seconds are randomly added to the delay before a failed
+    /// request to the Electrum server is retried or the connection is
+    /// re-established.
+    pub reconnect_jitter_secs: Option<u64>,
     #[serde(with = "crate::bitcoin::network")]
     pub network: bitcoin::Network,
 }
@@ -196,6 +218,27 @@ pub struct Bitcoin {
 pub struct Monero {
     pub wallet_rpc_url: Url,
     pub finality_confirmations: Option<u64>,
+    /// How many times Alice attempts the transfer that locks her Monero
+    /// before failing the swap.
+    pub lock_transfer_attempts: Option<u32>,
+    /// After how many seconds an attempt to transfer the Monero lock is given
+    /// up on.
+    pub lock_transfer_timeout_secs: Option<u64>,
+    /// Ring size of the Monero transfers, the default of `monero-wallet-rpc`
+    /// if not set.
+    pub ring_size: Option<crate::monero::RingSize>,
+    /// Index of the account of the Monero wallet that is used for swaps, the
+    /// primary account if not set.
+    pub account_index: Option<u32>,
+    /// Password the wallet is opened with, or created with if it does not
+    /// exist yet. The wallet is not protected by a password if not set.
+    ///
+    /// Never serialized, so printing the config does not reveal it.
+    #[serde(skip_serializing)]
+    pub wallet_password: Option<String>,
+    /// Up to how many seconds are randomly added to the delay before a failed
+    /// request to `monero-wallet-rpc` is retried or the wallet is reopened.
+    pub reconnect_jitter_secs: Option<u64>,
     #[serde(with = "crate::monero::network")]
     pub network: monero::Network,
 }
@@ -217,6 +260,67 @@ pub struct Maker {
     pub ask_spread: Decimal,
     pub price_ticker_ws_url: Url,
     pub external_bitcoin_redeem_address: Option<bitcoin::Address>,
+    pub quote_rate_limit: Option<QuoteRateLimit>,
+    /// Swaps are declined while the estimated fee of the Monero lock
+    /// transaction exceeds this amount (in piconero).
+    pub max_monero_lock_fee: Option<crate::monero::Amount>,
+    /// Swaps are declined if the Monero amount for the requested Bitcoin
+    /// amount is below this amount (in piconero), as such small outputs
+    /// cost more in fees to spend than they are worth.
+    pub min_monero_output: Option<crate::monero::Amount>,
+    /// Monero (in piconero) that is kept out of quotes, on top of the Monero
+    /// that swaps in progress still have to lock.
+    pub monero_reserve: Option<crate::monero::Amount>,
+}
+
+impl Maker {
+    /// Checks that the amounts allow for any swap at all.
+    pub fn validate(&self) -> Result<()> {
+        if self.max_buy_btc == bitcoin::Amount::ZERO {
+            bail!(NotPositive {
+                option: "max_buy_btc"
+            });
+        }
+
+        if self.min_buy_btc > self.max_buy_btc {
+            bail!(MinBuyAboveMaxBuy {
+                min_buy: self.min_buy_btc,
+                max_buy: self.max_buy_btc,
+            });
+        }
+
+        if self.ask_spread.is_sign_negative() {
+            bail!(NegativeAskSpread {
+                ask_spread: self.ask_spread,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("min_buy_btc of {min_buy} must not be above max_buy_btc of {max_buy}")]
+pub struct MinBuyAboveMaxBuy {
+    pub min_buy: bitcoin::Amount,
+    pub max_buy: bitcoin::Amount,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("ask_spread of {ask_spread} must not be negative")]
+pub struct NegativeAskSpread {
+    pub ask_spread: Decimal,
+}
+
+/// How many quote requests a single peer may send.
+///
+/// Every peer has a bucket of `burst` tokens that refills with
+/// `requests_per_minute` tokens per minute, a quote request takes one token.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuoteRateLimit {
+    pub requests_per_minute: u32,
+    pub burst: u32,
 }
 
 impl Default for TorConf {
@@ -244,6 +348,9 @@ pub fn read_config(config_path: PathBuf) -> Result<Result<Config, ConfigNotIniti
 
     let file = Config::read(&config_path)
         .with_context(|| format!("Failed to read config file at {}", config_path.display()))?;
+    file.maker
+        .validate()
+        .with_context(|| format!("Invalid config file at {}", config_path.display()))?;
 
     Ok(Ok(file))
 }
@@ -377,16 +484,29 @@ pub fn query_user_for_initial_config(testnet: bool) -> Result<Config> {
             listen: listen_addresses,
             rendezvous_point: rendezvous_points, // keeping the singular key name for backcompat
             external_addresses: vec![],
+            compression: None,
         },
         bitcoin: Bitcoin {
             electrum_rpc_url,
+            electrum_fallback_rpc_urls: vec![],
             target_block,
             finality_confirmations: None,
+            punish_grace_blocks: None,
+            sync_stop_gap: None,
+            strict_fee_tolerance_sat: None,
+            cancel_anchor: None,
+            reconnect_jitter_secs: None,
             network: bitcoin_network,
         },
         monero: Monero {
             wallet_rpc_url: monero_wallet_rpc_url,
             finality_confirmations: None,
+            lock_transfer_attempts: None,
+            lock_transfer_timeout_secs: None,
+            ring_size: None,
+            account_index: None,
+            wallet_password: None,
+            reconnect_jitter_secs: None,
             network: monero_network,
         },
         tor: TorConf {
@@ -399,6 +519,10 @@ pub fn query_user_for_initial_config(testnet: bool) -> Result<Config> {
             ask_spread,
             price_ticker_ws_url: defaults.price_ticker_ws_url,
             external_bitcoin_redeem_address: None,
+            quote_rate_limit: None,
+            max_monero_lock_fee: None,
+            min_monero_output: None,
+            monero_reserve: None,
         },
     })
 }
@@ -424,18 +548,31 @@ mod tests {
             },
             bitcoin: Bitcoin {
                 electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_fallback_rpc_urls: vec![],
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
+                punish_grace_blocks: None,
+                sync_stop_gap: None,
+                strict_fee_tolerance_sat: None,
+                cancel_anchor: None,
+                reconnect_jitter_secs: None,
                 network: bitcoin::Network::Testnet,
             },
             network: Network {
                 listen: vec![defaults.listen_address_tcp, defaults.listen_address_ws],
                 rendezvous_point: vec![],
                 external_addresses: vec![],
+                compression: None,
             },
             monero: Monero {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
+                lock_transfer_attempts: None,
+                lock_transfer_timeout_secs: None,
+                ring_size: None,
+                account_index: None,
+                wallet_password: None,
+                reconnect_jitter_secs: None,
                 network: monero::Network::Stagenet,
             },
             tor: Default::default(),
@@ -445,6 +582,10 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                quote_rate_limit: None,
+                max_monero_lock_fee: None,
+                min_monero_output: None,
+                monero_reserve: None,
             },
         };
 
@@ -468,18 +609,31 @@ mod tests {
             },
             bitcoin: Bitcoin {
                 electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_fallback_rpc_urls: vec![],
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
+                punish_grace_blocks: None,
+                sync_stop_gap: None,
+                strict_fee_tolerance_sat: None,
+                cancel_anchor: None,
+                reconnect_jitter_secs: None,
                 network: bitcoin::Network::Bitcoin,
             },
             network: Network {
                 listen: vec![defaults.listen_address_tcp, defaults.listen_address_ws],
                 rendezvous_point: vec![],
                 external_addresses: vec![],
+                compression: None,
             },
             monero: Monero {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
+                lock_transfer_attempts: None,
+                lock_transfer_timeout_secs: None,
+                ring_size: None,
+                account_index: None,
+                wallet_password: None,
+                reconnect_jitter_secs: None,
                 network: monero::Network::Mainnet,
             },
             tor: Default::default(),
@@ -489,6 +643,10 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                quote_rate_limit: None,
+                max_monero_lock_fee: None,
+                min_monero_output: None,
+                monero_reserve: None,
             },
         };
 
@@ -522,18 +680,31 @@ mod tests {
             data: Data { dir },
             bitcoin: Bitcoin {
                 electrum_rpc_url: defaults.electrum_rpc_url,
+                electrum_fallback_rpc_urls: vec![],
                 target_block: defaults.bitcoin_confirmation_target,
                 finality_confirmations: None,
+                punish_grace_blocks: None,
+                sync_stop_gap: None,
+                strict_fee_tolerance_sat: None,
+                cancel_anchor: None,
+                reconnect_jitter_secs: None,
                 network: bitcoin::Network::Bitcoin,
             },
             network: Network {
                 listen,
                 rendezvous_point: vec![],
                 external_addresses,
+                compression: None,
             },
             monero: Monero {
                 wallet_rpc_url: defaults.monero_wallet_rpc_url,
                 finality_confirmations: None,
+                lock_transfer_attempts: None,
+                lock_transfer_timeout_secs: None,
+                ring_size: None,
+                account_index: None,
+                wallet_password: None,
+                reconnect_jitter_secs: None,
                 network: monero::Network::Mainnet,
             },
             tor: Default::default(),
@@ -543,6 +714,10 @@ mod tests {
                 ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
                 price_ticker_ws_url: defaults.price_ticker_ws_url,
                 external_bitcoin_redeem_address: None,
+                quote_rate_limit: None,
+                max_monero_lock_fee: None,
+                min_monero_output: None,
+                monero_reserve: None,
             },
         };
 
@@ -554,4 +729,107 @@ mod tests {
         std::env::remove_var("ASB__NETWORK__EXTERNAL_ADDRESSES");
         std::env::remove_var("ASB__NETWORK__LISTEN");
     }
+
+    #[test]
+    #[serial]
+    fn sample_config_with_env_override_gives_env_config() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            r#"
+[data]
+dir = "/tmp/asb"
+
+[network]
+listen = ["/ip4/0.0.0.0/tcp/9939"]
+compression = false
+
+[bitcoin]
+electrum_rpc_url = "ssl://blockstream.info:700"
+target_block = 3
+finality_confirmations = 2
+punish_grace_blocks = 6
+sync_stop_gap = 50
+cancel_anchor = true
+reconnect_jitter_secs = 30
+network = "Mainnet"
+
+[monero]
+wallet_rpc_url = "http://127.0.0.1:18083/json_rpc"
+lock_transfer_attempts = 5
+lock_transfer_timeout_secs = 60
+wallet_password = "correct horse"
+reconnect_jitter_secs = 20
+network = "Mainnet"
+
+[tor]
+control_port = 9051
+socks5_port = 9050
+
+[maker]
+min_buy_btc = 0.001
+max_buy_btc = 0.1
+ask_spread = 0.03
+price_ticker_ws_url = "wss://ws.kraken.com"
+"#,
+        )
+        .unwrap();
+        std::env::set_var("ASB__BITCOIN__FINALITY_CONFIRMATIONS", "3");
+
+        let config = read_config(config_path).unwrap().unwrap();
+        let env_config = crate::env::new(false, &config).unwrap();
+        std::env::remove_var("ASB__BITCOIN__FINALITY_CONFIRMATIONS");
+
+        assert_eq!(
+            config.bitcoin.electrum_rpc_url,
+            Url::parse("ssl://blockstream.info:700").unwrap()
+        );
+        assert_eq!(
+            config.monero.wallet_password.as_deref(),
+            Some("correct horse")
+        );
+        assert_eq!(
+            config.maker.max_buy_btc,
+            bitcoin::Amount::from_btc(0.1).unwrap()
+        );
+        assert_eq!(env_config.bitcoin_network, bitcoin::Network::Bitcoin);
+        assert_eq!(env_config.bitcoin_finality_confirmations, 3);
+        assert_eq!(
+            env_config.bitcoin_reconnect_jitter,
+            std::time::Duration::from_secs(30)
+        );
+        assert_eq!(
+            env_config.monero_reconnect_jitter,
+            std::time::Duration::from_secs(20)
+        );
+        assert!(!env_config.message_compression);
+        assert!(env_config.cancel_anchor);
+        assert_eq!(env_config.punish_grace_blocks, 6);
+        assert_eq!(env_config.bitcoin_sync_stop_gap, 50);
+        assert_eq!(env_config.monero_lock_transfer_attempts, 5);
+        assert_eq!(
+            env_config.monero_lock_transfer_timeout,
+            std::time::Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn maker_with_min_buy_above_max_buy_is_invalid() {
+        let maker = Maker {
+            min_buy_btc: bitcoin::Amount::from_btc(0.1).unwrap(),
+            max_buy_btc: bitcoin::Amount::from_btc(0.01).unwrap(),
+            ask_spread: Decimal::from_f64(DEFAULT_SPREAD).unwrap(),
+            price_ticker_ws_url: Url::parse("wss://ws.kraken.com").unwrap(),
+            external_bitcoin_redeem_address: None,
+            quote_rate_limit: None,
+            max_monero_lock_fee: None,
+            min_monero_output: None,
+            monero_reserve: None,
+        };
+
+        let error = maker.validate().unwrap_err();
+
+        assert!(error.downcast_ref::<MinBuyAboveMaxBuy>().is_some());
+    }
 }
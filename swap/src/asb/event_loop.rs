@@ -1,12 +1,16 @@
+use crate::asb::config::QuoteRateLimit;
+use crate::asb::quote_limiter::QuoteLimiter;
 use crate::asb::{Behaviour, OutEvent, Rate};
 use crate::monero::Amount;
+use crate::network::price_curve::{self, PriceCurve};
 use crate::network::quote::BidQuote;
+use crate::network::resend_transfer_proof;
 use crate::network::swap_setup::alice::WalletSnapshot;
 use crate::network::transfer_proof;
 use crate::protocol::alice::{AliceState, State3, Swap};
-use crate::protocol::{Database, State};
+use crate::protocol::{Database, Role, State};
 use crate::{bitcoin, env, kraken, monero};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use futures::future;
 use futures::future::{BoxFuture, FutureExt};
 use futures::stream::{FuturesUnordered, StreamExt};
@@ -17,19 +21,30 @@ use rust_decimal::Decimal;
 use std::collections::HashMap;
 use std::convert::{Infallible, TryInto};
 use std::fmt::Debug;
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
 use uuid::Uuid;
 
-/// A future that resolves to a tuple of `PeerId`, `transfer_proof::Request` and
-/// `Responder`.
+/// How many started swaps wait to be picked up before the event loop stops
+/// accepting new ones.
+pub const SWAP_CHANNEL_CAPACITY: usize = 100;
+
+/// A future that resolves to the id of the swap and a tuple of `PeerId`,
+/// `transfer_proof::Request` and `Responder`.
 ///
 /// When this future resolves, the `transfer_proof::Request` shall be sent to
 /// the peer identified by the `PeerId`. Once the request has been acknowledged
 /// by the peer, i.e. a `()` response has been received, the `Responder` shall
 /// be used to let the original sender know about the successful transfer.
-type OutgoingTransferProof =
-    BoxFuture<'static, Result<(PeerId, transfer_proof::Request, bmrng::Responder<()>)>>;
+type OutgoingTransferProof = BoxFuture<
+    'static,
+    (
+        Uuid,
+        Result<(PeerId, transfer_proof::Request, bmrng::Responder<()>)>,
+    ),
+>;
 
 #[allow(missing_debug_implementations)]
 pub struct EventLoop<LR>
@@ -44,7 +59,26 @@ where
     latest_rate: LR,
     min_buy: bitcoin::Amount,
     max_buy: bitcoin::Amount,
+    /// Quotes never go below the Bitcoin this buys, Alice declines swaps for
+    /// less Monero anyway.
+    min_monero_output: Option<monero::Amount>,
     external_redeem_address: Option<bitcoin::Address>,
+    /// Monero kept out of quotes on top of what in-flight swaps still have to
+    /// lock.
+    monero_reserve: Option<monero::Amount>,
+    /// The Monero every swap in progress agreed on but has not locked yet.
+    ///
+    /// Kept up to date as swaps are set up and send their transfer proof, so
+    /// quotes don't have to go through all swaps in the database.
+    xmr_in_flight: HashMap<Uuid, monero::Amount>,
+    /// Shared with the swap setup behaviour, see [`PauseHandle`].
+    paused: Arc<AtomicBool>,
+    /// Shared with every [`AbortHandle`], one entry per running swap.
+    abort_requests: AbortRequests,
+    quote_limiter: Option<QuoteLimiter>,
+    /// Set while no rate can be fetched, quote requests are rejected until a
+    /// rate is available again.
+    rate_unavailable: bool,
 
     swap_sender: mpsc::Sender<Swap>,
 
@@ -77,9 +111,14 @@ where
         latest_rate: LR,
         min_buy: bitcoin::Amount,
         max_buy: bitcoin::Amount,
+        min_monero_output: Option<monero::Amount>,
         external_redeem_address: Option<bitcoin::Address>,
+        quote_rate_limit: Option<QuoteRateLimit>,
+        monero_reserve: Option<monero::Amount>,
+        swap_channel_capacity: usize,
     ) -> Result<(Self, mpsc::Receiver<Swap>)> {
-        let swap_channel = MpscChannels::default();
+        let swap_channel = MpscChannels::new(swap_channel_capacity);
+        let paused = swarm.behaviour().swap_setup.paused();
 
         let event_loop = EventLoop {
             swarm,
@@ -91,7 +130,14 @@ where
             swap_sender: swap_channel.sender,
             min_buy,
             max_buy,
+            min_monero_output,
             external_redeem_address,
+            monero_reserve,
+            xmr_in_flight: Default::default(),
+            paused,
+            abort_requests: Default::default(),
+            quote_limiter: quote_rate_limit.map(QuoteLimiter::new),
+            rate_unavailable: false,
             recv_encrypted_signature: Default::default(),
             inflight_encrypted_signatures: Default::default(),
             send_transfer_proof: Default::default(),
@@ -105,6 +151,63 @@ where
         *Swarm::local_peer_id(&self.swarm)
     }
 
+    pub fn pause_handle(&self) -> PauseHandle {
+        PauseHandle {
+            paused: self.paused.clone(),
+        }
+    }
+
+    pub fn abort_handle(&self) -> AbortHandle {
+        AbortHandle {
+            swaps: self.abort_requests.clone(),
+        }
+    }
+
+    /// Whether `peer` sent too many quote requests and has to slow down.
+    fn is_throttled(&mut self, peer: PeerId) -> bool {
+        match self.quote_limiter.as_mut() {
+            Some(limiter) => !limiter.try_acquire(peer),
+            None => false,
+        }
+    }
+
+    /// Whether the consumer of the swap channel falls behind, i.e. as many
+    /// swaps as the channel holds wait to be picked up.
+    ///
+    /// New swaps are not accepted until it catches up, swaps that completed
+    /// their setup are still handed over.
+    fn is_backlogged(&self) -> bool {
+        self.swap_sender.capacity() == 0
+    }
+
+    /// Whether a rate to make quotes from is available, e.g. it is not while
+    /// the exchange the rate is taken from cannot be reached. Logs when this
+    /// changes.
+    fn is_rate_available(&mut self) -> bool {
+        match self.latest_rate.latest_rate() {
+            Ok(_) => {
+                if self.rate_unavailable {
+                    tracing::info!("Rate is available again, serving quotes");
+                    self.rate_unavailable = false;
+                }
+
+                true
+            }
+            Err(error) => {
+                if !self.rate_unavailable {
+                    tracing::warn!(
+                        "Rate is unavailable, rejecting quote requests until it is back: {}",
+                        error
+                    );
+                    self.rate_unavailable = true;
+                }
+
+                false
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "event_loop", skip_all, fields(role = %Role::Alice))]
     pub async fn run(mut self) {
         // ensure that these streams are NEVER empty, otherwise it will
         // terminate forever.
@@ -135,6 +238,11 @@ where
             };
 
             let handle = self.new_handle(peer_id, swap_id);
+            let state: AliceState = state.try_into().expect("Alice state loaded from db");
+
+            if let Some(xmr) = state.xmr_to_lock() {
+                self.xmr_in_flight.insert(swap_id, xmr);
+            }
 
             let swap = Swap {
                 event_loop_handle: handle,
@@ -142,7 +250,7 @@ where
                 monero_wallet: self.monero_wallet.clone(),
                 env_config: self.env_config,
                 db: self.db.clone(),
-                state: state.try_into().expect("Alice state loaded from db"),
+                state,
                 swap_id,
             };
 
@@ -158,17 +266,23 @@ where
             tokio::select! {
                 swarm_event = self.swarm.select_next_some() => {
                     match swarm_event {
+                        SwarmEvent::Behaviour(OutEvent::SwapSetupInitiated { .. }) if self.is_backlogged() => {
+                            tracing::warn!("Swap request will be ignored because the started swaps are not picked up fast enough");
+                        }
                         SwarmEvent::Behaviour(OutEvent::SwapSetupInitiated { mut send_wallet_snapshot }) => {
 
-                            let (btc, responder) = match send_wallet_snapshot.recv().await {
-                                Ok((btc, responder)) => (btc, responder),
+                            let ((btc, xmr), responder) = match send_wallet_snapshot.recv().await {
+                                Ok(request) => request,
                                 Err(error) => {
-                                    tracing::error!("Swap request will be ignored because of a failure when requesting information for the wallet snapshot: {:#}", error);
+                                    // The request is declined before a snapshot is needed if it fails the checks against the quote
+                                    tracing::debug!("No wallet snapshot was requested for the swap request: {:#}", error);
                                     continue;
                                 }
                             };
 
-                            let wallet_snapshot = match WalletSnapshot::capture(&self.bitcoin_wallet, &self.monero_wallet, &self.external_redeem_address, btc).await {
+                            let reserved_in_flight = self.xmr_reserved_in_flight();
+                            let reserve = self.monero_reserve.unwrap_or(Amount::ZERO);
+                            let wallet_snapshot = match WalletSnapshot::capture(&self.bitcoin_wallet, &self.monero_wallet, &self.external_redeem_address, btc, xmr, reserved_in_flight, reserve).await {
                                 Ok(wallet_snapshot) => wallet_snapshot,
                                 Err(error) => {
                                     tracing::error!("Swap request will be ignored because we were unable to create wallet snapshot for swap: {:#}", error);
@@ -185,6 +299,58 @@ where
                         SwarmEvent::Behaviour(OutEvent::SwapDeclined { peer, error }) => {
                             tracing::warn!(%peer, "Ignoring spot price request: {}", error);
                         }
+                        SwarmEvent::Behaviour(OutEvent::QuoteRequested { channel, peer }) if self.paused.load(Ordering::SeqCst) => {
+                            tracing::debug!(%peer, "Responding with zero quote because we are paused");
+
+                            let quote = BidQuote {
+                                price: bitcoin::Amount::ZERO,
+                                min_quantity: bitcoin::Amount::ZERO,
+                                max_quantity: bitcoin::Amount::ZERO,
+                            };
+
+                            if self.swarm.behaviour_mut().quote.send_response(channel, quote).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with quote");
+                            }
+                        }
+                        SwarmEvent::Behaviour(OutEvent::QuoteRequested { channel, peer }) if self.is_throttled(peer) => {
+                            tracing::debug!(%peer, "Responding with zero quote because peer exceeds the quote rate limit");
+
+                            let quote = BidQuote {
+                                price: bitcoin::Amount::ZERO,
+                                min_quantity: bitcoin::Amount::ZERO,
+                                max_quantity: bitcoin::Amount::ZERO,
+                            };
+
+                            if self.swarm.behaviour_mut().quote.send_response(channel, quote).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with quote");
+                            }
+                        }
+                        SwarmEvent::Behaviour(OutEvent::QuoteRequested { channel, peer }) if self.is_backlogged() => {
+                            tracing::debug!(%peer, "Responding with zero quote because the started swaps are not picked up fast enough");
+
+                            let quote = BidQuote {
+                                price: bitcoin::Amount::ZERO,
+                                min_quantity: bitcoin::Amount::ZERO,
+                                max_quantity: bitcoin::Amount::ZERO,
+                            };
+
+                            if self.swarm.behaviour_mut().quote.send_response(channel, quote).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with quote");
+                            }
+                        }
+                        SwarmEvent::Behaviour(OutEvent::QuoteRequested { channel, peer }) if !self.is_rate_available() => {
+                            tracing::debug!(%peer, "Responding with zero quote because no rate is available");
+
+                            let quote = BidQuote {
+                                price: bitcoin::Amount::ZERO,
+                                min_quantity: bitcoin::Amount::ZERO,
+                                max_quantity: bitcoin::Amount::ZERO,
+                            };
+
+                            if self.swarm.behaviour_mut().quote.send_response(channel, quote).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with quote");
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::QuoteRequested { channel, peer }) => {
                             let quote = match self.make_quote(self.min_buy, self.max_buy).await {
                                 Ok(quote) => quote,
@@ -198,12 +364,46 @@ where
                                 tracing::debug!(%peer, "Failed to respond with quote");
                             }
                         }
+                        SwarmEvent::Behaviour(OutEvent::PriceCurveRequested { channel, peer, .. }) if self.is_throttled(peer) => {
+                            tracing::debug!(%peer, "Responding with empty price curve because peer exceeds the quote rate limit");
+
+                            if self.swarm.behaviour_mut().price_curve.send_response(channel, PriceCurve::default()).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with price curve");
+                            }
+                        }
+                        SwarmEvent::Behaviour(OutEvent::PriceCurveRequested { request, channel, peer }) => {
+                            let curve = match self.make_price_curve(request).await {
+                                Ok(curve) => curve,
+                                Err(error) => {
+                                    tracing::warn!(%peer, "Failed to make price curve: {:#}", error);
+                                    continue;
+                                }
+                            };
+
+                            if self.swarm.behaviour_mut().price_curve.send_response(channel, curve).is_err() {
+                                tracing::debug!(%peer, "Failed to respond with price curve");
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::TransferProofAcknowledged { peer, id }) => {
                             tracing::debug!(%peer, "Bob acknowledged transfer proof");
                             if let Some(responder) = self.inflight_transfer_proofs.remove(&id) {
                                 let _ = responder.respond(());
                             }
                         }
+                        SwarmEvent::Behaviour(OutEvent::TransferProofResendRequested { swap_id, channel, peer }) => {
+                            let tx_lock_proof = match self.stored_transfer_proof(swap_id, peer).await {
+                                Ok(tx_lock_proof) => tx_lock_proof,
+                                Err(error) => {
+                                    tracing::warn!(%swap_id, %peer, "Not resending transfer proof: {:#}", error);
+                                    None
+                                }
+                            };
+
+                            let response = resend_transfer_proof::Response { tx_lock_proof };
+                            if self.swarm.behaviour_mut().resend_transfer_proof.send_response(channel, response).is_err() {
+                                tracing::debug!(%peer, "Failed to resend transfer proof");
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::EncryptedSignatureReceived{ msg, channel, peer }) => {
                             let swap_id = msg.swap_id;
                             let swap_peer = self.db.get_peer_id(swap_id).await;
@@ -259,6 +459,9 @@ where
                         SwarmEvent::Behaviour(OutEvent::Rendezvous(libp2p::rendezvous::client::Event::RegisterFailed(error))) => {
                             tracing::error!("Registration with rendezvous node failed: {:?}", error);
                         }
+                        SwarmEvent::Behaviour(OutEvent::PeerIdentified { peer, agent_version }) => {
+                            tracing::debug!(%peer, %agent_version, "Peer identified");
+                        }
                         SwarmEvent::Behaviour(OutEvent::Failure {peer, error}) => {
                             tracing::error!(
                                 %peer,
@@ -291,9 +494,12 @@ where
                         _ => {}
                     }
                 },
-                next_transfer_proof = self.send_transfer_proof.next() => {
+                Some((swap_id, next_transfer_proof)) = self.send_transfer_proof.next() => {
+                    // Either the Monero is locked or the swap stopped, in both cases it may no longer need the Monero it reserved
+                    self.update_xmr_in_flight(swap_id).await;
+
                     match next_transfer_proof {
-                        Some(Ok((peer, transfer_proof, responder))) => {
+                        Ok((peer, transfer_proof, responder)) => {
                             if !self.swarm.behaviour_mut().transfer_proof.is_connected(&peer) {
                                 tracing::warn!(%peer, "No active connection to peer, buffering transfer proof");
                                 self.buffered_transfer_proofs.entry(peer).or_insert_with(Vec::new).push((transfer_proof, responder));
@@ -303,11 +509,8 @@ where
                             let id = self.swarm.behaviour_mut().transfer_proof.send_request(&peer, transfer_proof);
                             self.inflight_transfer_proofs.insert(id, responder);
                         },
-                        Some(Err(error)) => {
-                            tracing::debug!("A swap stopped without sending a transfer proof: {:#}", error);
-                        }
-                        None => {
-                            unreachable!("stream of transfer proof receivers must never terminate")
+                        Err(error) => {
+                            tracing::debug!(%swap_id, "A swap stopped without sending a transfer proof: {:#}", error);
                         }
                     }
                 }
@@ -323,23 +526,35 @@ where
         min_buy: bitcoin::Amount,
         max_buy: bitcoin::Amount,
     ) -> Result<BidQuote> {
-        let ask_price = self
+        let rate = self
             .latest_rate
             .latest_rate()
-            .context("Failed to get latest rate")?
-            .ask()
-            .context("Failed to compute asking price")?;
+            .context("Failed to get latest rate")?;
+        let ask_price = rate.ask().context("Failed to compute asking price")?;
+
+        let min_buy = match self.min_monero_output {
+            Some(min_monero_output) => min_buy.max(
+                rate.min_btc_for(min_monero_output)
+                    .context("Failed to compute minimum quantity")?,
+            ),
+            None => min_buy,
+        };
 
         let balance = self.monero_wallet.get_balance().await?;
+        let reserved_in_flight = self.xmr_reserved_in_flight();
 
-        // use unlocked monero balance for quote
-        let xmr = Amount::from_piconero(balance.unlocked_balance);
+        // use unlocked monero balance for quote, minus what is already promised
+        let xmr = available_for_quote(
+            Amount::from_piconero(balance.unlocked_balance),
+            reserved_in_flight,
+            self.monero_reserve.unwrap_or(Amount::ZERO),
+        );
 
         let max_bitcoin_for_monero = xmr.max_bitcoin_for_price(ask_price).ok_or_else(|| {
             anyhow::anyhow!("Bitcoin price ({}) x Monero ({}) overflow", ask_price, xmr)
         })?;
 
-        tracing::debug!(%ask_price, %xmr, %max_bitcoin_for_monero);
+        tracing::debug!(%ask_price, %xmr, %reserved_in_flight, %max_bitcoin_for_monero);
 
         if min_buy > max_bitcoin_for_monero {
             tracing::warn!(
@@ -373,6 +588,66 @@ where
         })
     }
 
+    /// The Monero that swaps in progress agreed on but have not locked yet.
+    fn xmr_reserved_in_flight(&self) -> Amount {
+        self.xmr_in_flight
+            .values()
+            .fold(Amount::ZERO, |reserved, xmr| reserved + *xmr)
+    }
+
+    /// Updates the Monero reserved for a swap from its latest state in the
+    /// database, e.g. a swap that failed before locking its Monero keeps its
+    /// reservation because it is resumed on the next start.
+    async fn update_xmr_in_flight(&mut self, swap_id: Uuid) {
+        match self.db.get_state(swap_id).await {
+            Ok(State::Alice(state)) => match state.xmr_to_lock() {
+                Some(xmr) => {
+                    self.xmr_in_flight.insert(swap_id, xmr);
+                }
+                None => {
+                    self.xmr_in_flight.remove(&swap_id);
+                }
+            },
+            Ok(State::Bob(_)) => {
+                self.xmr_in_flight.remove(&swap_id);
+            }
+            Err(error) => {
+                tracing::debug!(%swap_id, "Releasing the Monero of a swap without state: {:#}", error);
+                self.xmr_in_flight.remove(&swap_id);
+            }
+        }
+    }
+
+    /// Looks up the transfer proof of the swap in the database, provided the
+    /// swap is one with `peer`.
+    async fn stored_transfer_proof(
+        &self,
+        swap_id: Uuid,
+        peer: PeerId,
+    ) -> Result<Option<monero::TransferProof>> {
+        let swap_peer = self.db.get_peer_id(swap_id).await.context("Unknown swap")?;
+
+        if swap_peer != peer {
+            bail!("Swap {} is not a swap with peer {}", swap_id, peer);
+        }
+
+        let state: AliceState = self.db.get_state(swap_id).await?.try_into()?;
+
+        Ok(state.transfer_proof().cloned())
+    }
+
+    /// Samples the price curve for the requested range, limited to what
+    /// [`make_quote`](Self::make_quote) would currently offer.
+    async fn make_price_curve(&mut self, request: price_curve::Request) -> Result<PriceCurve> {
+        let quote = self.make_quote(self.min_buy, self.max_buy).await?;
+        let rate = self
+            .latest_rate
+            .latest_rate()
+            .context("Failed to get latest rate")?;
+
+        PriceCurve::sample(&rate, &quote, &request)
+    }
+
     async fn handle_execution_setup_done(
         &mut self,
         bob_peer_id: PeerId,
@@ -380,10 +655,12 @@ where
         state3: State3,
     ) {
         let handle = self.new_handle(bob_peer_id, swap_id);
+        let swap_keys = state3.swap_keys();
 
         let initial_state = AliceState::Started {
             state3: Box::new(state3),
         };
+        let xmr_to_lock = initial_state.xmr_to_lock();
 
         let swap = Swap {
             event_loop_handle: handle,
@@ -397,11 +674,17 @@ where
 
         // TODO: Consider adding separate components for start/resume of swaps
 
+        if let Err(error) = self.db.insert_swap_keys(swap_id, swap_keys).await {
+            tracing::warn!(%swap_id, "Unable to save public keys in database: {}", error);
+        }
+
         // swaps save peer id so we can resume
         match self.db.insert_peer_id(swap_id, bob_peer_id).await {
             Ok(_) => {
                 if let Err(error) = self.swap_sender.send(swap).await {
                     tracing::warn!(%swap_id, "Failed to start swap: {}", error);
+                } else if let Some(xmr) = xmr_to_lock {
+                    self.xmr_in_flight.insert(swap_id, xmr);
                 }
             }
             Err(error) => {
@@ -424,21 +707,38 @@ where
 
         self.send_transfer_proof.push(
             async move {
-                let (transfer_proof, responder) = transfer_proof_receiver.recv().await?;
+                let result =
+                    transfer_proof_receiver
+                        .recv()
+                        .await
+                        .map(|(transfer_proof, responder)| {
+                            let request = transfer_proof::Request {
+                                swap_id,
+                                tx_lock_proof: transfer_proof,
+                            };
 
-                let request = transfer_proof::Request {
-                    swap_id,
-                    tx_lock_proof: transfer_proof,
-                };
+                            (peer, request, responder)
+                        });
 
-                Ok((peer, request, responder))
+                (swap_id, result.map_err(anyhow::Error::from))
             }
             .boxed(),
         );
 
+        let (abort_sender, abort_receiver) = watch::channel(false);
+        {
+            let mut abort_requests = self
+                .abort_requests
+                .lock()
+                .expect("abort requests are never poisoned");
+            abort_requests.retain(|_, sender| !sender.is_closed());
+            abort_requests.insert(swap_id, abort_sender);
+        }
+
         EventLoopHandle {
             recv_encrypted_signature: Some(encrypted_signature.1),
             send_transfer_proof: Some(transfer_proof_sender),
+            abort: abort_receiver,
         }
     }
 }
@@ -505,13 +805,101 @@ impl LatestRate for KrakenRate {
     }
 }
 
+/// Pauses and resumes accepting new swaps while the event loop is running.
+///
+/// While paused, quote requests are answered with a zero quote and swap
+/// setups are declined as temporarily unavailable. Swaps that are already
+/// running are not affected.
+#[derive(Debug, Clone)]
+pub struct PauseHandle {
+    paused: Arc<AtomicBool>,
+}
+
+impl PauseHandle {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        tracing::info!("Paused, not accepting new swaps");
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        tracing::info!("Resumed accepting new swaps");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+type AbortRequests = Arc<Mutex<HashMap<Uuid, watch::Sender<bool>>>>;
+
+/// Aborts a single running swap by its id while the event loop is running.
+#[derive(Debug, Clone)]
+pub struct AbortHandle {
+    swaps: AbortRequests,
+}
+
+impl AbortHandle {
+    /// Stops the swap `swap_id` without affecting any other swap.
+    ///
+    /// A swap that did not lock the Monero yet is safely aborted, all other
+    /// swaps keep their state with the confirmations of the Bitcoin lock
+    /// transaction checkpointed, the same as with an emergency shutdown. A
+    /// swap that is currently sending the Monero is stopped once the transfer
+    /// is recorded.
+    ///
+    /// Returns `false` if no swap with that id is running.
+    pub fn abort_swap(&self, swap_id: Uuid) -> bool {
+        let mut swaps = self
+            .swaps
+            .lock()
+            .expect("abort requests are never poisoned");
+
+        match swaps.remove(&swap_id) {
+            Some(sender) => sender.send(true).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// The Monero Alice can still quote for: her `unlocked` balance minus what
+/// swaps in progress still have to lock and minus the `reserve` she keeps,
+/// zero if that is more than she has.
+pub fn available_for_quote(
+    unlocked: monero::Amount,
+    reserved_in_flight: monero::Amount,
+    reserve: monero::Amount,
+) -> monero::Amount {
+    let available = unlocked
+        .as_piconero()
+        .saturating_sub(reserved_in_flight.as_piconero())
+        .saturating_sub(reserve.as_piconero());
+
+    monero::Amount::from_piconero(available)
+}
+
 #[derive(Debug)]
 pub struct EventLoopHandle {
     recv_encrypted_signature: Option<bmrng::RequestReceiver<bitcoin::EncryptedSignature, ()>>,
     send_transfer_proof: Option<bmrng::RequestSender<monero::TransferProof, ()>>,
+    abort: watch::Receiver<bool>,
 }
 
 impl EventLoopHandle {
+    /// Completes once the swap was aborted through an [`AbortHandle`], never
+    /// completes otherwise.
+    pub fn aborted(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut abort = self.abort.clone();
+
+        async move {
+            while !*abort.borrow_and_update() {
+                if abort.changed().await.is_err() {
+                    future::pending::<()>().await;
+                }
+            }
+        }
+    }
+
     pub async fn recv_encrypted_signature(&mut self) -> Result<bitcoin::EncryptedSignature> {
         let (tx_redeem_encsig, responder) = self
             .recv_encrypted_signature
@@ -545,9 +933,9 @@ struct MpscChannels<T> {
     receiver: mpsc::Receiver<T>,
 }
 
-impl<T> Default for MpscChannels<T> {
-    fn default() -> Self {
-        let (sender, receiver) = mpsc::channel(100);
+impl<T> MpscChannels<T> {
+    fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
         MpscChannels { sender, receiver }
     }
 }
@@ -1,19 +1,19 @@
 use crate::asb::event_loop::LatestRate;
-use crate::env;
+use crate::network::price_curve::{self, PriceCurve};
 use crate::network::quote::BidQuote;
 use crate::network::rendezvous::XmrBtcNamespace;
 use crate::network::swap_setup::alice;
 use crate::network::swap_setup::alice::WalletSnapshot;
 use crate::network::transport::authenticate_and_multiplex;
-use crate::network::{encrypted_signature, quote, transfer_proof};
+use crate::network::{encrypted_signature, identify, quote, resend_transfer_proof, transfer_proof};
 use crate::protocol::alice::State3;
+use crate::{env, monero};
 use anyhow::{anyhow, Error, Result};
 use futures::FutureExt;
 use libp2p::core::connection::ConnectionId;
 use libp2p::core::muxing::StreamMuxerBox;
 use libp2p::core::transport::Boxed;
 use libp2p::dns::TokioDnsConfig;
-use libp2p::identify::{Identify, IdentifyConfig, IdentifyEvent};
 use libp2p::ping::{Ping, PingConfig, PingEvent};
 use libp2p::request_response::{RequestId, ResponseChannel};
 use libp2p::swarm::dial_opts::PeerCondition;
@@ -52,7 +52,8 @@ pub mod behaviour {
     #[derive(Debug)]
     pub enum OutEvent {
         SwapSetupInitiated {
-            send_wallet_snapshot: bmrng::RequestReceiver<bitcoin::Amount, WalletSnapshot>,
+            send_wallet_snapshot:
+                bmrng::RequestReceiver<(bitcoin::Amount, monero::Amount), WalletSnapshot>,
         },
         SwapSetupCompleted {
             peer_id: PeerId,
@@ -67,16 +68,30 @@ pub mod behaviour {
             channel: ResponseChannel<BidQuote>,
             peer: PeerId,
         },
+        PriceCurveRequested {
+            request: price_curve::Request,
+            channel: ResponseChannel<PriceCurve>,
+            peer: PeerId,
+        },
         TransferProofAcknowledged {
             peer: PeerId,
             id: RequestId,
         },
+        TransferProofResendRequested {
+            swap_id: Uuid,
+            channel: ResponseChannel<resend_transfer_proof::Response>,
+            peer: PeerId,
+        },
         EncryptedSignatureReceived {
             msg: encrypted_signature::Request,
             channel: ResponseChannel<()>,
             peer: PeerId,
         },
         Rendezvous(libp2p::rendezvous::client::Event),
+        PeerIdentified {
+            peer: PeerId,
+            agent_version: String,
+        },
         Failure {
             peer: PeerId,
             error: Error,
@@ -112,10 +127,12 @@ pub mod behaviour {
     {
         pub rendezvous: Toggle<rendezvous::Behaviour>,
         pub quote: quote::Behaviour,
+        pub price_curve: price_curve::Behaviour,
         pub swap_setup: alice::Behaviour<LR>,
         pub transfer_proof: transfer_proof::Behaviour,
+        pub resend_transfer_proof: resend_transfer_proof::Behaviour,
         pub encrypted_signature: encrypted_signature::Behaviour,
-        pub identify: Identify,
+        pub identify: identify::Behaviour,
 
         /// Ping behaviour that ensures that the underlying network connection
         /// is still alive. If the ping fails a connection close event
@@ -127,9 +144,12 @@ pub mod behaviour {
     where
         LR: LatestRate + Send + 'static,
     {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             min_buy: bitcoin::Amount,
             max_buy: bitcoin::Amount,
+            max_monero_lock_fee: Option<monero::Amount>,
+            min_monero_output: Option<monero::Amount>,
             latest_rate: LR,
             resume_only: bool,
             env_config: env::Config,
@@ -137,10 +157,7 @@ pub mod behaviour {
             rendezvous_nodes: Vec<RendezvousNode>,
         ) -> Self {
             let (identity, namespace) = identify_params;
-            let agent_version = format!("asb/{} ({})", env!("CARGO_PKG_VERSION"), namespace);
-            let protocol_version = "/comit/xmr/btc/1.0.0".to_string();
-            let identifyConfig = IdentifyConfig::new(protocol_version, identity.public())
-                .with_agent_version(agent_version);
+            let identify = identify::asb(&identity, namespace);
 
             let behaviour = if rendezvous_nodes.is_empty() {
                 None
@@ -151,17 +168,21 @@ pub mod behaviour {
             Self {
                 rendezvous: Toggle::from(behaviour),
                 quote: quote::asb(),
+                price_curve: price_curve::asb(env_config.message_compression),
                 swap_setup: alice::Behaviour::new(
                     min_buy,
                     max_buy,
+                    max_monero_lock_fee,
+                    min_monero_output,
                     env_config,
                     latest_rate,
                     resume_only,
                 ),
-                transfer_proof: transfer_proof::alice(),
-                encrypted_signature: encrypted_signature::alice(),
+                transfer_proof: transfer_proof::alice(env_config.message_compression),
+                resend_transfer_proof: resend_transfer_proof::alice(env_config.message_compression),
+                encrypted_signature: encrypted_signature::alice(env_config.message_compression),
                 ping: Ping::new(PingConfig::new().with_keep_alive(true)),
-                identify: Identify::new(identifyConfig),
+                identify,
             }
         }
     }
@@ -172,12 +193,6 @@ pub mod behaviour {
         }
     }
 
-    impl From<IdentifyEvent> for OutEvent {
-        fn from(_: IdentifyEvent) -> Self {
-            OutEvent::Other
-        }
-    }
-
     impl From<libp2p::rendezvous::client::Event> for OutEvent {
         fn from(event: libp2p::rendezvous::client::Event) -> Self {
             OutEvent::Rendezvous(event)
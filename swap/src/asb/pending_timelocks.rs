@@ -0,0 +1,224 @@
+use crate::bitcoin::{ScriptStatus, TimelockStatus, Wallet};
+use crate::protocol::alice::AliceState;
+use crate::protocol::{Database, State};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The timelock a swap is waiting on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PendingTimelock {
+    /// Relative to the lock transaction, the swap becomes cancellable once it
+    /// expired.
+    Cancel,
+    /// Relative to the cancel transaction, the swap becomes punishable once
+    /// it expired.
+    Punish,
+}
+
+impl fmt::Display for PendingTimelock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PendingTimelock::Cancel => write!(f, "cancel"),
+            PendingTimelock::Punish => write!(f, "punish"),
+        }
+    }
+}
+
+/// The swaps that are blocked on the same timelock, which expires for all of
+/// them in the same block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelockWaiter {
+    pub timelock: PendingTimelock,
+    pub status: TimelockStatus,
+    /// Sorted, so listing the same swaps twice gives the same output.
+    pub swap_ids: Vec<Uuid>,
+}
+
+/// Lists the swaps with a confirmed Bitcoin lock that have not been redeemed,
+/// refunded or punished yet, grouped by the timelock they are waiting on and
+/// the blocks remaining until it expires, soonest-expiring first.
+///
+/// Swaps whose status cannot be fetched from the Bitcoin backend are skipped
+/// with a warning.
+pub async fn pending_timelocks(
+    db: Arc<dyn Database>,
+    bitcoin_wallet: Arc<Wallet>,
+) -> Result<Vec<TimelockWaiter>> {
+    let mut statuses = Vec::new();
+    // Both checks below need the status of the cancel transaction, it is only
+    // fetched once
+    let mut operation = bitcoin_wallet.operation();
+
+    for (swap_id, state) in db.all().await? {
+        let state3 = match state {
+            State::Alice(
+                AliceState::BtcLocked { state3 }
+                | AliceState::XmrLockTransactionSent { state3, .. }
+                | AliceState::XmrLocked { state3, .. }
+                | AliceState::XmrLockTransferProofSent { state3, .. }
+                | AliceState::EncSigLearned { state3, .. }
+                | AliceState::BtcRedeemReadyToBroadcast { state3, .. }
+                | AliceState::CancelTimelockExpired { state3, .. }
+                | AliceState::BtcCancelled { state3, .. }
+                | AliceState::BtcPunishable { state3, .. },
+            ) => state3,
+            _ => continue,
+        };
+
+        let tx_cancel = state3.tx_cancel();
+        let status = match operation.status_of_script(&tx_cancel).await {
+            Ok(ScriptStatus::Unseen) => operation
+                .timelock_status(&state3.tx_lock, state3.cancel_timelock)
+                .await
+                .map(|status| (PendingTimelock::Cancel, status)),
+            Ok(_) => operation
+                .timelock_status(&tx_cancel, state3.punish_timelock)
+                .await
+                .map(|status| (PendingTimelock::Punish, status)),
+            Err(error) => Err(error),
+        };
+
+        match status {
+            Ok((timelock, status)) => statuses.push((swap_id, timelock, status)),
+            Err(error) => {
+                tracing::warn!(%swap_id, "Failed to get timelock status, skipping swap: {:#}", error);
+            }
+        }
+    }
+
+    Ok(group_soonest_first(statuses))
+}
+
+fn group_soonest_first(
+    statuses: Vec<(Uuid, PendingTimelock, TimelockStatus)>,
+) -> Vec<TimelockWaiter> {
+    let mut groups = BTreeMap::new();
+
+    for (swap_id, timelock, status) in statuses {
+        groups
+            .entry((status.blocks_remaining(), timelock))
+            .or_insert_with(|| TimelockWaiter {
+                timelock,
+                status,
+                swap_ids: Vec::new(),
+            })
+            .swap_ids
+            .push(swap_id);
+    }
+
+    groups
+        .into_values()
+        .map(|mut waiter| {
+            waiter.swap_ids.sort();
+            waiter
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::BlockHeight;
+    use std::time::Duration;
+
+    const TEN_MINUTES: Duration = Duration::from_secs(600);
+    const CANCEL_TIMELOCK: u32 = 72;
+    const PUNISH_TIMELOCK: u32 = 72;
+
+    #[test]
+    fn given_swaps_at_different_lock_heights_then_soonest_expiring_group_comes_first() {
+        let latest_block = BlockHeight::new(200);
+        let status = |lock_height, timelock| {
+            let blocks = match timelock {
+                PendingTimelock::Cancel => CANCEL_TIMELOCK,
+                PendingTimelock::Punish => PUNISH_TIMELOCK,
+            };
+
+            TimelockStatus::new(
+                BlockHeight::new(lock_height),
+                latest_block,
+                blocks,
+                TEN_MINUTES,
+            )
+        };
+        let mut swap_ids = (0..5).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        swap_ids.sort();
+
+        let waiters = group_soonest_first(vec![
+            (
+                swap_ids[4],
+                PendingTimelock::Cancel,
+                status(195, PendingTimelock::Cancel),
+            ),
+            (
+                swap_ids[3],
+                PendingTimelock::Cancel,
+                status(130, PendingTimelock::Cancel),
+            ),
+            (
+                swap_ids[0],
+                PendingTimelock::Cancel,
+                status(130, PendingTimelock::Cancel),
+            ),
+            (
+                swap_ids[1],
+                PendingTimelock::Cancel,
+                status(100, PendingTimelock::Cancel),
+            ),
+            (
+                swap_ids[2],
+                PendingTimelock::Punish,
+                status(150, PendingTimelock::Punish),
+            ),
+        ]);
+
+        assert_eq!(
+            waiters
+                .iter()
+                .map(|waiter| (
+                    waiter.timelock,
+                    waiter.status.blocks_remaining(),
+                    waiter.swap_ids.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (PendingTimelock::Cancel, 0, vec![swap_ids[1]]),
+                (PendingTimelock::Cancel, 1, vec![swap_ids[0], swap_ids[3]]),
+                (PendingTimelock::Punish, 21, vec![swap_ids[2]]),
+                (PendingTimelock::Cancel, 66, vec![swap_ids[4]]),
+            ]
+        );
+        assert_eq!(waiters[0].status, TimelockStatus::Expired);
+    }
+
+    #[test]
+    fn given_equal_remaining_blocks_on_different_timelocks_then_groups_are_separate() {
+        let status = TimelockStatus::not_started(CANCEL_TIMELOCK, TEN_MINUTES);
+        let cancel = Uuid::new_v4();
+        let punish = Uuid::new_v4();
+
+        let waiters = group_soonest_first(vec![
+            (punish, PendingTimelock::Punish, status),
+            (cancel, PendingTimelock::Cancel, status),
+        ]);
+
+        assert_eq!(
+            waiters,
+            vec![
+                TimelockWaiter {
+                    timelock: PendingTimelock::Cancel,
+                    status,
+                    swap_ids: vec![cancel],
+                },
+                TimelockWaiter {
+                    timelock: PendingTimelock::Punish,
+                    status,
+                    swap_ids: vec![punish],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::asb::config::QuoteRateLimit;
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Token bucket rate limiter for quote requests, keeping one bucket per peer.
+#[derive(Debug)]
+pub struct QuoteLimiter {
+    limit: QuoteRateLimit,
+    buckets: HashMap<PeerId, Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl QuoteLimiter {
+    pub fn new(limit: QuoteRateLimit) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Takes a token from the bucket of `peer`, returns `false` if the peer
+    /// has none left and should be throttled.
+    pub fn try_acquire(&mut self, peer: PeerId) -> bool {
+        self.try_acquire_at(peer, Instant::now())
+    }
+
+    fn try_acquire_at(&mut self, peer: PeerId, now: Instant) -> bool {
+        if !self.buckets.contains_key(&peer) {
+            self.forget_full_buckets(now);
+        }
+
+        let limit = self.limit;
+        let bucket = self.buckets.entry(peer).or_insert(Bucket {
+            tokens: f64::from(limit.burst),
+            last_refill: now,
+        });
+        bucket.refill(limit, now);
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+
+        true
+    }
+
+    /// Peers with a full bucket are indistinguishable from peers we have
+    /// never seen, forgetting them keeps the map from growing with every peer
+    /// that ever requested a quote.
+    fn forget_full_buckets(&mut self, now: Instant) {
+        let limit = self.limit;
+
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(limit, now);
+            bucket.tokens < f64::from(limit.burst)
+        });
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: QuoteRateLimit, now: Instant) {
+        let per_second = f64::from(limit.requests_per_minute) / 60.0;
+        let elapsed = now.saturating_duration_since(self.last_refill);
+
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * per_second).min(f64::from(limit.burst));
+        self.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const LIMIT: QuoteRateLimit = QuoteRateLimit {
+        requests_per_minute: 60,
+        burst: 3,
+    };
+
+    #[test]
+    fn given_peer_exceeds_burst_then_only_that_peer_is_throttled() {
+        let mut limiter = QuoteLimiter::new(LIMIT);
+        let (spammer, other) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(spammer, now));
+        }
+        assert!(!limiter.try_acquire_at(spammer, now));
+
+        assert!(limiter.try_acquire_at(other, now));
+    }
+
+    #[test]
+    fn given_throttled_peer_when_tokens_refill_then_peer_is_served_again() {
+        let mut limiter = QuoteLimiter::new(LIMIT);
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(peer, now));
+        }
+        assert!(!limiter.try_acquire_at(peer, now));
+
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.try_acquire_at(peer, later));
+        assert!(!limiter.try_acquire_at(peer, later));
+    }
+
+    #[test]
+    fn refill_never_exceeds_burst() {
+        let mut limiter = QuoteLimiter::new(LIMIT);
+        let peer = PeerId::random();
+        let now = Instant::now();
+        assert!(limiter.try_acquire_at(peer, now));
+
+        let much_later = now + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(peer, much_later));
+        }
+        assert!(!limiter.try_acquire_at(peer, much_later));
+    }
+}
@@ -2,6 +2,7 @@ use crate::{bitcoin, monero};
 use anyhow::{Context, Result};
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use std::convert::TryFrom;
 use std::fmt::{Debug, Display, Formatter};
 
 /// Represents the rate at which we are willing to trade 1 XMR.
@@ -44,32 +45,40 @@ impl Rate {
 
     /// Calculate a sell quote for a given BTC amount.
     pub fn sell_quote(&self, quote: bitcoin::Amount) -> Result<monero::Amount> {
-        Self::quote(self.ask()?, quote)
+        self.apply(quote)
     }
 
-    fn quote(rate: bitcoin::Amount, quote: bitcoin::Amount) -> Result<monero::Amount> {
-        // quote (btc) = rate * base (xmr)
-        // base = quote / rate
-
-        let quote_in_sats = quote.to_sat();
-        let quote_in_btc = Decimal::from(quote_in_sats)
-            .checked_div(Decimal::from(bitcoin::Amount::ONE_BTC.to_sat()))
-            .context("Division overflow")?;
-
-        let rate_in_btc = Decimal::from(rate.to_sat())
-            .checked_div(Decimal::from(bitcoin::Amount::ONE_BTC.to_sat()))
-            .context("Division overflow")?;
+    /// Converts `btc` into the XMR it buys at the asking price.
+    ///
+    /// The conversion is done in integer piconero and rounds down, so the
+    /// XMR amount never exceeds what `btc` pays for.
+    pub fn apply(&self, btc: bitcoin::Amount) -> Result<monero::Amount> {
+        // btc = ask * xmr
+        // xmr = btc / ask
+        let ask = u128::from(self.ask()?.to_sat());
+        let btc = u128::from(btc.to_sat());
+
+        let piconero = (btc * u128::from(monero::Amount::ONE_XMR.as_piconero()))
+            .checked_div(ask)
+            .context("Cannot convert at an asking price of zero")?;
+        let piconero =
+            u64::try_from(piconero).context("Failed to fit piconero amount into a u64")?;
+
+        Ok(monero::Amount::from_piconero(piconero))
+    }
 
-        let base_in_xmr = quote_in_btc
-            .checked_div(rate_in_btc)
-            .context("Division overflow")?;
-        let base_in_piconero = base_in_xmr * Decimal::from(monero::Amount::ONE_XMR.as_piconero());
+    /// The smallest BTC amount that [`apply`](Self::apply) converts into at
+    /// least `xmr`.
+    pub fn min_btc_for(&self, xmr: monero::Amount) -> Result<bitcoin::Amount> {
+        // btc = ceil(xmr * ask)
+        let ask = u128::from(self.ask()?.to_sat());
+        let piconero = u128::from(xmr.as_piconero());
+        let one_xmr = u128::from(monero::Amount::ONE_XMR.as_piconero());
 
-        let base_in_piconero = base_in_piconero
-            .to_u64()
-            .context("Failed to fit piconero amount into a u64")?;
+        let sats = (piconero * ask + one_xmr - 1) / one_xmr;
+        let sats = u64::try_from(sats).context("Failed to fit satoshi amount into a u64")?;
 
-        Ok(monero::Amount::from_piconero(base_in_piconero))
+        Ok(bitcoin::Amount::from_sat(sats))
     }
 }
 
@@ -98,6 +107,62 @@ mod tests {
         assert_eq!(xmr_amount, monero::Amount::from_monero(1000.0).unwrap())
     }
 
+    #[test]
+    fn min_btc_for_is_the_smallest_amount_that_buys_the_xmr() {
+        let cases = [
+            // (ask in sats, piconero)
+            (250_000, 1_000_000_000_000),
+            (300_000, 1_000_000_000_000),
+            (7, 1),
+            (695_432, 177_525_320_951_581),
+        ];
+
+        for (ask, piconero) in cases {
+            let rate = Rate::new(bitcoin::Amount::from_sat(ask), ZERO_SPREAD);
+            let xmr = monero::Amount::from_piconero(piconero);
+
+            let btc = rate.min_btc_for(xmr).unwrap();
+
+            assert!(rate.apply(btc).unwrap() >= xmr);
+            assert!(rate.apply(btc - bitcoin::Amount::from_sat(1)).unwrap() < xmr);
+        }
+    }
+
+    #[test]
+    fn apply_converts_exactly_and_rounds_down() {
+        let cases = [
+            // (ask in sats, btc in sats, expected piconero)
+            (250_000, 250_000_000, 1_000_000_000_000_000),
+            (250_000, 1, 4_000_000),
+            (1_000_000, 1_000_000, 1_000_000_000_000),
+            (300_000, 10_000_000, 33_333_333_333_333),
+            (7, 3, 428_571_428_571),
+            (695_432, 123_456_789, 177_525_320_951_581),
+        ];
+
+        for (ask, btc, expected) in cases {
+            let rate = Rate::new(bitcoin::Amount::from_sat(ask), ZERO_SPREAD);
+
+            let xmr = rate.apply(bitcoin::Amount::from_sat(btc)).unwrap();
+
+            assert_eq!(
+                xmr,
+                monero::Amount::from_piconero(expected),
+                "{} sats at an ask of {} sats",
+                btc,
+                ask
+            );
+        }
+    }
+
+    #[test]
+    fn apply_fails_instead_of_overflowing() {
+        let rate = Rate::new(bitcoin::Amount::from_sat(1), ZERO_SPREAD);
+
+        assert!(rate.apply(bitcoin::Amount::ONE_BTC).is_err());
+        assert!(Rate::ZERO.apply(bitcoin::Amount::ONE_BTC).is_err());
+    }
+
     #[test]
     fn applies_spread_to_asking_price() {
         let asking_price = bitcoin::Amount::from_sat(100);
@@ -1,4 +1,5 @@
 pub mod cancel;
+pub mod emergency_shutdown;
 pub mod punish;
 pub mod redeem;
 pub mod refund;
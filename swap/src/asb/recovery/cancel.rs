@@ -11,7 +11,7 @@ pub async fn cancel(
     bitcoin_wallet: Arc<Wallet>,
     db: Arc<dyn Database>,
 ) -> Result<(Txid, AliceState)> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     let (monero_wallet_restore_blockheight, transfer_proof, state3) = match state {
 
@@ -25,6 +25,7 @@ pub async fn cancel(
         | AliceState::XmrLockTransferProofSent { monero_wallet_restore_blockheight, transfer_proof, state3 }
         // in cancel mode we do not care about the fact that we could redeem, but always wait for cancellation (leading either refund or punish)
         | AliceState::EncSigLearned { monero_wallet_restore_blockheight, transfer_proof, state3, .. }
+        | AliceState::BtcRedeemReadyToBroadcast { monero_wallet_restore_blockheight, transfer_proof, state3, .. }
         | AliceState::CancelTimelockExpired { monero_wallet_restore_blockheight, transfer_proof, state3}
         | AliceState::BtcCancelled { monero_wallet_restore_blockheight, transfer_proof, state3 }
         | AliceState::BtcRefunded { monero_wallet_restore_blockheight, transfer_proof,  state3 ,.. }
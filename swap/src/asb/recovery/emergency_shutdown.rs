@@ -0,0 +1,176 @@
+use crate::bitcoin::{ScriptStatus, Wallet};
+use crate::protocol::alice::AliceState;
+use crate::protocol::{Database, State};
+use anyhow::Result;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// What [`emergency_shutdown`] did with a swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShutdownAction {
+    /// No Monero was locked yet, the swap was safely aborted.
+    SafelyAborted,
+    /// The Monero is locked, the swap was left in `state` with the
+    /// confirmations of the Bitcoin lock transaction checkpointed. It has to
+    /// be recovered by following `recovery`.
+    Checkpointed {
+        state: String,
+        tx_lock_confirmations: u32,
+        recovery: &'static str,
+    },
+}
+
+/// The swaps that were in flight and what was done with each of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub swaps: Vec<(Uuid, ShutdownAction)>,
+}
+
+impl ShutdownReport {
+    /// Logs every swap and, for the ones that were not aborted, the steps
+    /// needed to recover them.
+    pub fn log(&self) {
+        for (swap_id, action) in &self.swaps {
+            match action {
+                ShutdownAction::SafelyAborted => {
+                    tracing::info!(%swap_id, "Safely aborted swap before locking Monero");
+                }
+                ShutdownAction::Checkpointed {
+                    state,
+                    tx_lock_confirmations,
+                    recovery,
+                } => {
+                    tracing::warn!(%swap_id, %state, %tx_lock_confirmations, "Stopped swap with locked Monero. {}", recovery);
+                }
+            }
+        }
+
+        if self.swaps.is_empty() {
+            tracing::info!("No swaps were in flight");
+        }
+    }
+}
+
+/// Takes the safest available action for every swap in flight, e.g. when the
+/// ASB is suspected to be compromised.
+///
+/// Swaps that did not lock any Monero yet are safely aborted. All other swaps
+/// keep their state and get the confirmations of the Bitcoin lock transaction
+/// checkpointed, so they can be recovered manually or by restarting the ASB.
+pub async fn emergency_shutdown(
+    db: Arc<dyn Database>,
+    bitcoin_wallet: Arc<Wallet>,
+) -> Result<ShutdownReport> {
+    let mut report = ShutdownReport::default();
+
+    for (swap_id, state) in db.all().await? {
+        let state = match state {
+            State::Alice(state) => state,
+            State::Bob(_) => continue,
+        };
+
+        if let Some(action) = shut_down_swap(db.as_ref(), &bitcoin_wallet, swap_id, &state).await? {
+            report.swaps.push((swap_id, action));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Takes the safest available action for a single swap in `state`, see
+/// [`emergency_shutdown`].
+///
+/// Returns `None` if the swap is already complete.
+pub async fn shut_down_swap<D>(
+    db: &D,
+    bitcoin_wallet: &Wallet,
+    swap_id: Uuid,
+    state: &AliceState,
+) -> Result<Option<ShutdownAction>>
+where
+    D: Database + ?Sized,
+{
+    let mut checkpoint = db.get_checkpoint(swap_id).await?.unwrap_or_default();
+
+    let state3 = match state {
+        // The Monero lock transfer may have been published without the swap
+        // recording it, aborting could lose track of the Monero
+        AliceState::BtcLocked { state3 } if checkpoint.monero_lock_attempted => state3,
+        AliceState::Started { .. }
+        | AliceState::BtcLockTransactionSeen { .. }
+        | AliceState::BtcLocked { .. } => {
+            db.insert_latest_state(swap_id, AliceState::SafelyAborted.into())
+                .await?;
+
+            return Ok(Some(ShutdownAction::SafelyAborted));
+        }
+        AliceState::XmrLockTransactionSent { state3, .. }
+        | AliceState::XmrLocked { state3, .. }
+        | AliceState::XmrLockTransferProofSent { state3, .. }
+        | AliceState::EncSigLearned { state3, .. }
+        | AliceState::BtcRedeemReadyToBroadcast { state3, .. }
+        | AliceState::BtcRedeemTransactionPublished { state3 }
+        | AliceState::CancelTimelockExpired { state3, .. }
+        | AliceState::BtcCancelled { state3, .. }
+        | AliceState::BtcRefunded { state3, .. }
+        | AliceState::BtcPunishable { state3, .. } => state3,
+        AliceState::BtcRedeemed
+        | AliceState::XmrRefunded
+        | AliceState::BtcPunished
+        | AliceState::SafelyAborted => return Ok(None),
+    };
+
+    match bitcoin_wallet.status_of_script(&state3.tx_lock).await {
+        Ok(ScriptStatus::Confirmed(confirmed)) => {
+            checkpoint.tx_lock_confirmations = checkpoint
+                .tx_lock_confirmations
+                .max(confirmed.confirmations());
+        }
+        Ok(_) => {}
+        Err(error) => {
+            tracing::warn!(%swap_id, "Failed to get status of lock transaction, keeping the last checkpoint: {:#}", error);
+        }
+    }
+    db.insert_checkpoint(swap_id, checkpoint).await?;
+
+    Ok(Some(ShutdownAction::Checkpointed {
+        state: state.to_string(),
+        tx_lock_confirmations: checkpoint.tx_lock_confirmations,
+        recovery: recovery_steps(state),
+    }))
+}
+
+fn recovery_steps(state: &AliceState) -> &'static str {
+    match state {
+        AliceState::XmrLockTransactionSent { .. }
+        | AliceState::XmrLocked { .. }
+        | AliceState::XmrLockTransferProofSent { .. } => {
+            "Restart the ASB before the cancel timelock expires to continue the swap, or run `manual-recovery cancel` and then `manual-recovery refund` once it expired"
+        }
+        AliceState::EncSigLearned { .. } => {
+            "Run `manual-recovery redeem` before the cancel timelock expires"
+        }
+        AliceState::BtcRedeemReadyToBroadcast { .. } => {
+            "Broadcast the redeem transaction before the cancel timelock expires and run `manual-recovery confirm-redeem-broadcast`, or run `manual-recovery redeem` to broadcast it from the ASB"
+        }
+        AliceState::BtcRedeemTransactionPublished { .. } => {
+            "Run `manual-recovery redeem` to wait for the redeem transaction to become final"
+        }
+        AliceState::CancelTimelockExpired { .. } => {
+            "Run `manual-recovery cancel`, then `manual-recovery refund` once Bob refunded or `manual-recovery punish` once the punish timelock expired"
+        }
+        AliceState::BtcCancelled { .. } | AliceState::BtcPunishable { .. } => {
+            "Run `manual-recovery refund` once Bob refunded or `manual-recovery punish` once the punish timelock expired"
+        }
+        AliceState::BtcRefunded { .. } => "Run `manual-recovery refund` to refund the Monero",
+        AliceState::BtcLocked { .. } => {
+            "Check the Monero wallet for a transfer of the swap's Monero amount and run `manual-recovery safely-abort` if none was published"
+        }
+        AliceState::Started { .. }
+        | AliceState::BtcLockTransactionSeen { .. }
+        | AliceState::BtcRedeemed
+        | AliceState::XmrRefunded
+        | AliceState::BtcPunished
+        | AliceState::SafelyAborted => "No recovery needed",
+    }
+}
@@ -17,7 +17,7 @@ pub async fn punish(
     bitcoin_wallet: Arc<bitcoin::Wallet>,
     db: Arc<dyn Database>,
 ) -> Result<(Txid, AliceState)> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     let state3 = match state {
         // Punish potentially possible (no knowledge of cancel transaction)
@@ -27,6 +27,7 @@ pub async fn punish(
         | AliceState::XmrLocked {state3, ..}
         | AliceState::XmrLockTransferProofSent {state3, ..}
         | AliceState::EncSigLearned {state3, ..}
+        | AliceState::BtcRedeemReadyToBroadcast {state3, ..}
         | AliceState::CancelTimelockExpired {state3, ..}
         // Punish possible due to cancel transaction already being published
         | AliceState::BtcCancelled {state3, ..}
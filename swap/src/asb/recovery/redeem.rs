@@ -1,7 +1,8 @@
-use crate::bitcoin::{Txid, Wallet};
+use crate::bitcoin::wallet::BROADCAST_SEEN_TIMEOUT;
+use crate::bitcoin::{Transaction, Txid, Wallet};
 use crate::protocol::alice::AliceState;
 use crate::protocol::Database;
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use std::convert::TryInto;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -27,18 +28,27 @@ pub async fn redeem(
     db: Arc<dyn Database>,
     finality: Finality,
 ) -> Result<(Txid, AliceState)> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     match state {
         AliceState::EncSigLearned {
             state3,
             encrypted_signature,
             ..
+        }
+        | AliceState::BtcRedeemReadyToBroadcast {
+            state3,
+            encrypted_signature,
+            ..
         } => {
             tracing::info!(%swap_id, "Trying to redeem swap");
 
+            state3.tx_lock.ensure_unspent(&bitcoin_wallet).await?;
+            state3.ensure_lock_amount(&bitcoin_wallet).await?;
             let redeem_tx = state3.signed_redeem_transaction(*encrypted_signature)?;
-            let (txid, subscription) = bitcoin_wallet.broadcast(redeem_tx, "redeem").await?;
+            let (txid, subscription) = bitcoin_wallet
+                .broadcast_with_fee(redeem_tx, "redeem", state3.tx_redeem_fee())
+                .await?;
 
             subscription.wait_until_seen().await?;
 
@@ -89,3 +99,99 @@ pub async fn redeem(
         ),
     }
 }
+
+/// Returns the signed redeem transaction of the given swap without
+/// broadcasting it, so it can be broadcast through the operator's own node.
+///
+/// Once it was broadcast, [`confirm_redeem_broadcast`] records that.
+pub async fn manual_broadcast_redeem(
+    swap_id: Uuid,
+    bitcoin_wallet: Arc<Wallet>,
+    db: Arc<dyn Database>,
+) -> Result<(Transaction, AliceState)> {
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
+
+    let (monero_wallet_restore_blockheight, transfer_proof, encrypted_signature, state3) =
+        match state {
+            AliceState::EncSigLearned {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature,
+                state3,
+            }
+            | AliceState::BtcRedeemReadyToBroadcast {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature,
+                state3,
+            } => (
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature,
+                state3,
+            ),
+            state => bail!(
+                "Cannot redeem swap {} because it is in state {} which cannot be manually redeemed",
+                swap_id,
+                state
+            ),
+        };
+
+    state3.tx_lock.ensure_unspent(&bitcoin_wallet).await?;
+    state3.ensure_lock_amount(&bitcoin_wallet).await?;
+    let redeem_tx = state3.signed_redeem_transaction(*encrypted_signature.clone())?;
+
+    tracing::info!(%swap_id, txid = %redeem_tx.txid(), "Prepared redeem transaction for manual broadcast");
+
+    let state = AliceState::BtcRedeemReadyToBroadcast {
+        monero_wallet_restore_blockheight,
+        transfer_proof,
+        encrypted_signature,
+        state3,
+    };
+    db.insert_latest_state(swap_id, state.clone().into())
+        .await?;
+
+    Ok((redeem_tx, state))
+}
+
+/// Records that the redeem transaction returned by
+/// [`manual_broadcast_redeem`] was broadcast by the operator, after checking
+/// that it can be seen.
+pub async fn confirm_redeem_broadcast(
+    swap_id: Uuid,
+    bitcoin_wallet: Arc<Wallet>,
+    db: Arc<dyn Database>,
+) -> Result<AliceState> {
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
+
+    let state3 = match state {
+        AliceState::BtcRedeemReadyToBroadcast { state3, .. } => state3,
+        state => bail!(
+            "Cannot confirm broadcast of swap {} because it is in state {} which has no redeem transaction ready to broadcast",
+            swap_id,
+            state
+        ),
+    };
+
+    let txid = state3.tx_redeem().txid();
+    bitcoin_wallet
+        .subscribe_to(state3.tx_redeem())
+        .await
+        .wait_until_seen_within(BROADCAST_SEEN_TIMEOUT)
+        .await
+        .with_context(|| {
+            format!(
+                "Refusing to confirm broadcast of redeem transaction {}, broadcast it first",
+                txid
+            )
+        })?;
+
+    tracing::info!(%swap_id, "Confirmed manual broadcast of redeem transaction");
+
+    let state = AliceState::BtcRedeemTransactionPublished { state3 };
+    db.insert_latest_state(swap_id, state.clone().into())
+        .await?;
+
+    Ok(state)
+}
@@ -29,7 +29,7 @@ pub async fn refund(
     monero_wallet: Arc<monero::Wallet>,
     db: Arc<dyn Database>,
 ) -> Result<AliceState> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     let (monero_wallet_restore_blockheight, transfer_proof, state3) = match state {
         // In case no XMR has been locked, move to Safely Aborted
@@ -42,6 +42,7 @@ pub async fn refund(
         | AliceState::XmrLocked { monero_wallet_restore_blockheight, transfer_proof, state3 }
         | AliceState::XmrLockTransferProofSent { monero_wallet_restore_blockheight, transfer_proof, state3 }
         | AliceState::EncSigLearned { monero_wallet_restore_blockheight, transfer_proof, state3, .. }
+        | AliceState::BtcRedeemReadyToBroadcast { monero_wallet_restore_blockheight, transfer_proof, state3, .. }
         | AliceState::CancelTimelockExpired { monero_wallet_restore_blockheight, transfer_proof, state3 }
 
         // Refund possible due to cancel transaction already being published
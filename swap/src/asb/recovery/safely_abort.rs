@@ -6,7 +6,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 pub async fn safely_abort(swap_id: Uuid, db: Arc<dyn Database>) -> Result<AliceState> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     match state {
         AliceState::Started { .. }
@@ -24,6 +24,7 @@ pub async fn safely_abort(swap_id: Uuid, db: Arc<dyn Database>) -> Result<AliceS
         | AliceState::XmrLocked { .. }
         | AliceState::XmrLockTransferProofSent { .. }
         | AliceState::EncSigLearned { .. }
+        | AliceState::BtcRedeemReadyToBroadcast { .. }
         | AliceState::BtcRedeemTransactionPublished { .. }
         | AliceState::CancelTimelockExpired { .. }
         | AliceState::BtcCancelled { .. }
@@ -0,0 +1,79 @@
+//! Live updates of the balance of a wallet.
+use futures::Stream;
+use tokio::sync::watch;
+
+/// Notifies subscribers whenever a wallet observes its balance changing.
+///
+/// Observing the same balance again does not produce an update.
+#[derive(Debug)]
+pub struct BalanceUpdates<A> {
+    sender: watch::Sender<Option<A>>,
+}
+
+impl<A> BalanceUpdates<A>
+where
+    A: Copy + PartialEq + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(None);
+
+        Self { sender }
+    }
+
+    /// Records the balance the wallet last observed.
+    pub fn observe(&self, balance: A) {
+        self.sender.send_if_modified(|current| {
+            if *current == Some(balance) {
+                return false;
+            }
+
+            *current = Some(balance);
+            true
+        });
+    }
+
+    /// Yields every balance observed after this call that differs from the
+    /// one observed before it.
+    ///
+    /// The stream ends once the wallet is dropped.
+    pub fn subscribe(&self) -> impl Stream<Item = A> {
+        let mut receiver = self.sender.subscribe();
+        receiver.borrow_and_update();
+
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.changed().await.ok()?;
+            let balance = (*receiver.borrow_and_update())?;
+
+            Some((balance, receiver))
+        })
+    }
+}
+
+impl<A> Default for BalanceUpdates<A>
+where
+    A: Copy + PartialEq + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn deposit_produces_exactly_one_update() {
+        let updates = BalanceUpdates::new();
+        updates.observe(50_000u64);
+        let stream = updates.subscribe();
+
+        updates.observe(50_000);
+        updates.observe(60_000);
+        updates.observe(60_000);
+        drop(updates);
+
+        assert_eq!(stream.collect::<Vec<_>>().await, vec![60_000]);
+    }
+}
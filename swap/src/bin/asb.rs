@@ -21,6 +21,7 @@ use libp2p::Swarm;
 use std::convert::TryInto;
 use std::env;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use structopt::clap;
 use structopt::clap::ErrorKind;
@@ -28,9 +29,13 @@ use swap::asb::command::{parse_args, Arguments, Command};
 use swap::asb::config::{
     initial_setup, query_user_for_initial_config, read_config, Config, ConfigNotInitialized,
 };
-use swap::asb::{cancel, punish, redeem, refund, safely_abort, EventLoop, Finality, KrakenRate};
+use swap::asb::{
+    cancel, confirm_redeem_broadcast, emergency_shutdown, manual_broadcast_redeem,
+    pending_timelocks, punish, redeem, refund, safely_abort, EventLoop, Finality, KrakenRate,
+    SWAP_CHANNEL_CAPACITY,
+};
 use swap::common::check_latest_version;
-use swap::database::open_db;
+use swap::database::{open_db, Repair};
 use swap::network::rendezvous::XmrBtcNamespace;
 use swap::network::swarm;
 use swap::protocol::alice::{run, AliceState};
@@ -95,6 +100,8 @@ async fn main() -> Result<()> {
         ));
     }
 
+    let env_config = swap::env::new(testnet, &config)?;
+
     let db = open_db(config.data.dir.join("sqlite")).await?;
 
     let seed =
@@ -170,6 +177,8 @@ async fn main() -> Result<()> {
                 &seed,
                 config.maker.min_buy_btc,
                 config.maker.max_buy_btc,
+                config.maker.max_monero_lock_fee,
+                config.maker.min_monero_output,
                 kraken_rate.clone(),
                 resume_only,
                 env_config,
@@ -192,16 +201,25 @@ async fn main() -> Result<()> {
                 );
             }
 
+            let monero_wallet = Arc::new(monero_wallet);
+            // Keeps the balance stream of the Monero wallet up to date while the ASB runs
+            let _monero_refresh =
+                monero_wallet.refresh_continuously(env_config.monero_poll_interval);
+
             let (event_loop, mut swap_receiver) = EventLoop::new(
                 swarm,
                 env_config,
                 Arc::new(bitcoin_wallet),
-                Arc::new(monero_wallet),
+                monero_wallet,
                 db,
                 kraken_rate.clone(),
                 config.maker.min_buy_btc,
                 config.maker.max_buy_btc,
+                config.maker.min_monero_output,
                 config.maker.external_bitcoin_redeem_address,
+                config.maker.quote_rate_limit,
+                config.maker.monero_reserve,
+                SWAP_CHANNEL_CAPACITY,
             )
             .unwrap();
 
@@ -252,12 +270,7 @@ async fn main() -> Result<()> {
                 }
             };
 
-            let psbt = bitcoin_wallet
-                .send_to_address(address, amount, None)
-                .await?;
-            let signed_tx = bitcoin_wallet.sign_and_finalize(psbt).await?;
-
-            bitcoin_wallet.broadcast(signed_tx, "withdraw").await?;
+            bitcoin_wallet.send(address, amount, "withdraw").await?;
         }
         Command::Balance => {
             let monero_wallet = init_monero_wallet(&config, env_config).await?;
@@ -305,24 +318,68 @@ async fn main() -> Result<()> {
         Command::Redeem {
             swap_id,
             do_not_await_finality,
+            manual_broadcast,
         } => {
             let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
 
-            let (txid, _) = redeem(
-                swap_id,
-                Arc::new(bitcoin_wallet),
-                db,
-                Finality::from_bool(do_not_await_finality),
-            )
-            .await?;
+            if manual_broadcast {
+                let (transaction, _) =
+                    manual_broadcast_redeem(swap_id, Arc::new(bitcoin_wallet), db).await?;
+                println!(
+                    "{}",
+                    ::bitcoin::consensus::encode::serialize_hex(&transaction)
+                );
+            } else {
+                let (txid, _) = redeem(
+                    swap_id,
+                    Arc::new(bitcoin_wallet),
+                    db,
+                    Finality::from_bool(do_not_await_finality),
+                )
+                .await?;
+
+                tracing::info!("Redeem transaction successfully published with id {}", txid);
+            }
+        }
+        Command::ConfirmRedeemBroadcast { swap_id } => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+
+            confirm_redeem_broadcast(swap_id, Arc::new(bitcoin_wallet), db).await?;
 
-            tracing::info!("Redeem transaction successfully published with id {}", txid);
+            tracing::info!("Redeem transaction broadcast confirmed");
         }
         Command::ExportBitcoinWallet => {
             let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
             let wallet_export = bitcoin_wallet.wallet_export("asb").await?;
             println!("{}", wallet_export.to_string())
         }
+        Command::EmergencyShutdown => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+
+            emergency_shutdown(db, Arc::new(bitcoin_wallet))
+                .await?
+                .log();
+        }
+        Command::PendingTimelocks => {
+            let bitcoin_wallet = init_bitcoin_wallet(&config, &seed, env_config).await?;
+
+            let waiters = pending_timelocks(db, Arc::new(bitcoin_wallet)).await?;
+            for waiter in &waiters {
+                tracing::info!(swaps = waiter.swap_ids.len(), timelock = %waiter.timelock, status = %waiter.status, swap_ids = ?waiter.swap_ids, "Swaps are waiting on timelock");
+            }
+            if waiters.is_empty() {
+                tracing::info!("No swaps are waiting on a timelock");
+            }
+        }
+        Command::CheckDatabase { repair } => {
+            let repair = if repair {
+                Repair::Quarantine
+            } else {
+                Repair::ReportOnly
+            };
+
+            db.check_and_repair(repair).await?.log();
+        }
     }
 
     Ok(())
@@ -335,8 +392,12 @@ async fn init_bitcoin_wallet(
 ) -> Result<bitcoin::Wallet> {
     tracing::debug!("Opening Bitcoin wallet");
     let data_dir = &config.data.dir;
-    let wallet = bitcoin::Wallet::new(
-        config.bitcoin.electrum_rpc_url.clone(),
+    let electrum_rpc_urls = std::iter::once(&config.bitcoin.electrum_rpc_url)
+        .chain(&config.bitcoin.electrum_fallback_rpc_urls)
+        .cloned()
+        .collect();
+    let wallet = bitcoin::Wallet::new_with_electrum_pool(
+        electrum_rpc_urls,
         data_dir,
         seed.derive_extended_private_key(env_config.bitcoin_network)?,
         env_config,
@@ -345,7 +406,17 @@ async fn init_bitcoin_wallet(
     .await
     .context("Failed to initialize Bitcoin wallet")?;
 
-    wallet.sync().await?;
+    let wallet = match config.bitcoin.strict_fee_tolerance_sat {
+        Some(tolerance) => wallet.with_strict_fees(bitcoin::Amount::from_sat(tolerance)),
+        None => wallet,
+    };
+
+    wallet
+        .sync_with_progress(|progress| {
+            tracing::info!(%progress, "Syncing Bitcoin wallet");
+            ControlFlow::Continue(())
+        })
+        .await?;
 
     Ok(wallet)
 }
@@ -355,14 +426,23 @@ async fn init_monero_wallet(
     env_config: swap::env::Config,
 ) -> Result<monero::Wallet> {
     tracing::debug!("Opening Monero wallet");
-    let wallet = monero::Wallet::open_or_create(
-        config.monero.wallet_rpc_url.clone(),
+    let wallet = monero::Wallet::open_or_create_protected(
+        monero_rpc::wallet::Client::new(config.monero.wallet_rpc_url.clone())?,
         DEFAULT_WALLET_NAME.to_string(),
+        config.monero.wallet_password.clone().unwrap_or_default(),
         env_config,
     )
     .await?;
 
-    Ok(wallet)
+    let wallet = match config.monero.ring_size {
+        Some(ring_size) => wallet.with_ring_size(ring_size),
+        None => wallet,
+    };
+
+    match config.monero.account_index {
+        Some(account_index) => wallet.with_account_index(account_index).await,
+        None => Ok(wallet),
+    }
 }
 
 /// Registers a hidden service for each network.
@@ -20,6 +20,7 @@ use std::cmp::min;
 use std::convert::TryInto;
 use std::env;
 use std::future::Future;
+use std::ops::ControlFlow;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -27,7 +28,7 @@ use swap::bitcoin::TxLock;
 use swap::cli::command::{parse_args_and_apply_defaults, Arguments, Command, ParseResult};
 use swap::cli::{list_sellers, EventLoop, SellerStatus};
 use swap::common::check_latest_version;
-use swap::database::open_db;
+use swap::database::{open_db, Repair};
 use swap::env::Config;
 use swap::libp2p_ext::MultiAddrExt;
 use swap::network::quote::{BidQuote, ZeroQuoteReceived};
@@ -67,8 +68,11 @@ async fn main() -> Result<()> {
             bitcoin_change_address,
             monero_receive_address,
             monero_daemon_address,
+            monero_ring_size,
+            monero_wallet_password,
             tor_socks5_port,
             namespace,
+            top_up_timeout,
         } => {
             let swap_id = Uuid::new_v4();
 
@@ -86,8 +90,14 @@ async fn main() -> Result<()> {
                 bitcoin_target_block,
             )
             .await?;
-            let (monero_wallet, _process) =
-                init_monero_wallet(data_dir, monero_daemon_address, env_config).await?;
+            let (monero_wallet, _process) = init_monero_wallet(
+                data_dir,
+                monero_daemon_address,
+                monero_ring_size,
+                monero_wallet_password,
+                env_config,
+            )
+            .await?;
             let bitcoin_wallet = Arc::new(bitcoin_wallet);
             let seller_peer_id = seller
                 .extract_peer_id()
@@ -139,7 +149,7 @@ async fn main() -> Result<()> {
             db.insert_monero_address(swap_id, monero_receive_address)
                 .await?;
 
-            let swap = Swap::new(
+            let mut swap = Swap::new(
                 db,
                 swap_id,
                 bitcoin_wallet,
@@ -150,6 +160,9 @@ async fn main() -> Result<()> {
                 bitcoin_change_address,
                 amount,
             );
+            if let Some(timeout) = top_up_timeout {
+                swap = swap.with_top_up_timeout(timeout);
+            }
 
             tokio::select! {
                 result = event_loop => {
@@ -185,6 +198,18 @@ async fn main() -> Result<()> {
                 println!("{}", table);
             }
         }
+        Command::CheckDatabase { repair } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            let repair = if repair {
+                Repair::Quarantine
+            } else {
+                Repair::ReportOnly
+            };
+
+            db.check_and_repair(repair).await?.log();
+        }
         Command::Config => {
             cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
 
@@ -223,12 +248,7 @@ async fn main() -> Result<()> {
                 }
             };
 
-            let psbt = bitcoin_wallet
-                .send_to_address(address, amount, None)
-                .await?;
-            let signed_tx = bitcoin_wallet.sign_and_finalize(psbt).await?;
-
-            bitcoin_wallet.broadcast(signed_tx, "withdraw").await?;
+            bitcoin_wallet.send(address, amount, "withdraw").await?;
         }
 
         Command::Balance {
@@ -259,6 +279,8 @@ async fn main() -> Result<()> {
             bitcoin_electrum_rpc_url,
             bitcoin_target_block,
             monero_daemon_address,
+            monero_ring_size,
+            monero_wallet_password,
             tor_socks5_port,
             namespace,
         } => {
@@ -276,8 +298,14 @@ async fn main() -> Result<()> {
                 bitcoin_target_block,
             )
             .await?;
-            let (monero_wallet, _process) =
-                init_monero_wallet(data_dir, monero_daemon_address, env_config).await?;
+            let (monero_wallet, _process) = init_monero_wallet(
+                data_dir,
+                monero_daemon_address,
+                monero_ring_size,
+                monero_wallet_password,
+                env_config,
+            )
+            .await?;
             let bitcoin_wallet = Arc::new(bitcoin_wallet);
 
             let seller_peer_id = db.get_peer_id(swap_id).await?;
@@ -328,23 +356,35 @@ async fn main() -> Result<()> {
             swap_id,
             bitcoin_electrum_rpc_url,
             bitcoin_target_block,
+            refund_address,
+            manual_broadcast,
         } => {
             cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
 
             let db = open_db(data_dir.join("sqlite")).await?;
-            let seed = Seed::from_file_or_generate(data_dir.as_path())
-                .context("Failed to read in seed file")?;
 
-            let bitcoin_wallet = init_bitcoin_wallet(
-                bitcoin_electrum_rpc_url,
-                &seed,
-                data_dir,
-                env_config,
-                bitcoin_target_block,
-            )
-            .await?;
+            if manual_broadcast {
+                let (transaction, _) = cli::manual_broadcast(swap_id, db).await?;
+                println!(
+                    "{}",
+                    ::bitcoin::consensus::encode::serialize_hex(&transaction)
+                );
+            } else {
+                let seed = Seed::from_file_or_generate(data_dir.as_path())
+                    .context("Failed to read in seed file")?;
+
+                let bitcoin_wallet = init_bitcoin_wallet(
+                    bitcoin_electrum_rpc_url,
+                    &seed,
+                    data_dir,
+                    env_config,
+                    bitcoin_target_block,
+                )
+                .await?;
 
-            cli::cancel_and_refund(swap_id, Arc::new(bitcoin_wallet), db).await?;
+                cli::cancel_and_refund(swap_id, Arc::new(bitcoin_wallet), db, refund_address)
+                    .await?;
+            }
         }
         Command::ListSellers {
             rendezvous_point,
@@ -467,6 +507,7 @@ async fn main() -> Result<()> {
                 | BobState::CancelTimelockExpired(_)
                 | BobState::BtcCancelled(_)
                 | BobState::BtcRefunded(_)
+                | BobState::ReadyToBroadcast { .. }
                 | BobState::BtcPunished { .. }
                 | BobState::SafelyAborted
                 | BobState::XmrRedeemed { .. } => {
@@ -489,6 +530,106 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Command::ConfirmBroadcast {
+            swap_id,
+            bitcoin_electrum_rpc_url,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_electrum_rpc_url,
+                &seed,
+                data_dir,
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            cli::confirm_broadcast(swap_id, Arc::new(bitcoin_wallet), db).await?;
+        }
+        Command::ExportSwap { swap_id, file } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), Some(swap_id))?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+
+            let blob = db
+                .export_swap(swap_id, &seed.derive_swap_backup_key())
+                .await?;
+            std::fs::write(&file, blob)
+                .with_context(|| format!("Failed to write swap to {}", file.display()))?;
+
+            tracing::info!(path=%file.display(), "Exported swap");
+        }
+        Command::ImportSwap { file } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let db = open_db(data_dir.join("sqlite")).await?;
+            // A freshly generated seed could never decrypt the backup
+            let seed_file = data_dir.join("seed.pem");
+            let seed = Seed::from_file(&seed_file).with_context(|| {
+                format!(
+                    "Failed to read seed file {}, importing a swap requires the seed of the machine that exported it",
+                    seed_file.display()
+                )
+            })?;
+
+            let blob = std::fs::read(&file)
+                .with_context(|| format!("Failed to read swap from {}", file.display()))?;
+            let swap_id = db
+                .import_swap(&blob, &seed.derive_swap_backup_key())
+                .await?;
+
+            tracing::info!(%swap_id, "Imported swap, resume it or refund it using the swap id");
+        }
+        Command::WatchOnlyResume {
+            lock_txid,
+            alice_bitcoin_key,
+            bob_bitcoin_key,
+            bitcoin_electrum_rpc_url,
+            bitcoin_target_block,
+        } => {
+            cli::tracing::init(debug, json, data_dir.join("logs"), None)?;
+
+            let seed = Seed::from_file_or_generate(data_dir.as_path())
+                .context("Failed to read in seed file")?;
+            let bitcoin_wallet = init_bitcoin_wallet(
+                bitcoin_electrum_rpc_url,
+                &seed,
+                data_dir.clone(),
+                env_config,
+                bitcoin_target_block,
+            )
+            .await?;
+
+            let report = cli::watch_only_resume(
+                &bitcoin_wallet,
+                lock_txid,
+                alice_bitcoin_key,
+                bob_bitcoin_key,
+                env_config.bitcoin_cancel_timelock,
+                env_config.bitcoin_punish_timelock,
+            )
+            .await?;
+
+            tracing::info!(
+                %lock_txid,
+                lock_amount = %report.lock_amount,
+                lock_status = %report.lock_status,
+                cancel_timelock = ?report.cancel_timelock,
+                punish_timelock = ?report.punish_timelock,
+                expired_timelocks = ?report.expired_timelocks,
+                outcome = %report.outcome,
+                "Reconstructed the Bitcoin lock from the chain"
+            );
+            println!("{}", report.required_material());
+        }
     };
     Ok(())
 }
@@ -513,8 +654,12 @@ async fn init_bitcoin_wallet(
     .await
     .context("Failed to initialize Bitcoin wallet")?;
 
-    tracing::debug!("Syncing bitcoin wallet");
-    wallet.sync().await?;
+    wallet
+        .sync_with_progress(|progress| {
+            tracing::info!(%progress, "Syncing bitcoin wallet");
+            ControlFlow::Continue(())
+        })
+        .await?;
 
     Ok(wallet)
 }
@@ -522,6 +667,8 @@ async fn init_bitcoin_wallet(
 async fn init_monero_wallet(
     data_dir: PathBuf,
     monero_daemon_address: Option<String>,
+    monero_ring_size: Option<monero::RingSize>,
+    monero_wallet_password: Option<String>,
     env_config: Config,
 ) -> Result<(monero::Wallet, monero::WalletRpcProcess)> {
     let network = env_config.monero_network;
@@ -534,12 +681,17 @@ async fn init_monero_wallet(
         .run(network, monero_daemon_address)
         .await?;
 
-    let monero_wallet = monero::Wallet::open_or_create(
-        monero_wallet_rpc_process.endpoint(),
+    let monero_wallet = monero::Wallet::open_or_create_protected(
+        monero_rpc::wallet::Client::new(monero_wallet_rpc_process.endpoint())?,
         MONERO_BLOCKCHAIN_MONITORING_WALLET_NAME.to_string(),
+        monero_wallet_password.unwrap_or_default(),
         env_config,
     )
     .await?;
+    let monero_wallet = match monero_ring_size {
+        Some(ring_size) => monero_wallet.with_ring_size(ring_size),
+        None => monero_wallet,
+    };
 
     Ok((monero_wallet, monero_wallet_rpc_process))
 }
@@ -607,6 +759,7 @@ where
                 "Deposit at least {} to cover the min quantity with fee!",
                 min_deposit
             );
+            tracing::info!("The swap starts once the deposit is confirmed");
             tracing::info!(
                 %deposit_address,
                 %min_deposit,
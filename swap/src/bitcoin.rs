@@ -1,3 +1,4 @@
+pub mod fee_estimator;
 pub mod wallet;
 
 mod cancel;
@@ -5,17 +6,21 @@ mod lock;
 mod punish;
 mod redeem;
 mod refund;
+mod signer;
 mod timelocks;
 
 pub use crate::bitcoin::cancel::{CancelTimelock, PunishTimelock, TxCancel};
+pub use crate::bitcoin::fee_estimator::FeeEstimator;
 pub use crate::bitcoin::lock::TxLock;
 pub use crate::bitcoin::punish::TxPunish;
 pub use crate::bitcoin::redeem::TxRedeem;
 pub use crate::bitcoin::refund::TxRefund;
-pub use crate::bitcoin::timelocks::{BlockHeight, ExpiredTimelocks};
+pub use crate::bitcoin::signer::Signer;
+pub use crate::bitcoin::timelocks::{BlockHeight, ExpiredTimelocks, TimelockStatus};
 pub use ::bitcoin::util::amount::Amount;
 pub use ::bitcoin::util::psbt::PartiallySignedTransaction;
 pub use ::bitcoin::{Address, Network, Transaction, Txid};
+pub use bdk::FeeRate;
 pub use ecdsa_fun::adaptor::EncryptedSignature;
 pub use ecdsa_fun::fun::Scalar;
 pub use ecdsa_fun::Signature;
@@ -27,7 +32,7 @@ pub use wallet::WalletBuilder;
 use crate::bitcoin::wallet::ScriptStatus;
 use ::bitcoin::hashes::hex::ToHex;
 use ::bitcoin::hashes::Hash;
-use ::bitcoin::{secp256k1, Sighash};
+use ::bitcoin::{secp256k1, PackedLockTime, Sighash, TxIn, TxOut};
 use anyhow::{bail, Context, Result};
 use bdk::miniscript::descriptor::Wsh;
 use bdk::miniscript::{Descriptor, Segwitv0};
@@ -202,10 +207,107 @@ pub fn verify_encsig(
     }
 }
 
+/// Decrypts Bob's encrypted signature with `s_a` and verifies the result
+/// against `B`.
+///
+/// A signature that was encrypted for another key fails here with
+/// [`InvalidDecryptedSignature`] instead of when satisfying the lock script.
+pub fn decrypt_signature(
+    encrypted_signature: EncryptedSignature,
+    s_a: &Scalar,
+    B: PublicKey,
+    digest: &Sighash,
+) -> Result<Signature> {
+    let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
+    let sig_b = adaptor.decrypt_signature(s_a, encrypted_signature);
+
+    if verify_sig(&B, digest, &sig_b).is_err() {
+        bail!(InvalidDecryptedSignature)
+    }
+
+    Ok(sig_b)
+}
+
+/// Checks the adaptor signature relationship the redeem transaction relies
+/// on: `encrypted_signature` is `B`'s signature on `digest` encrypted under
+/// `S_a = s_a * G`, and decrypting it with `s_a` yields a valid signature of
+/// `B`, which is returned.
+///
+/// Fails with [`InvalidEncryptedSignature`] or [`InvalidDecryptedSignature`].
+pub fn verify_and_decrypt_encsig(
+    B: PublicKey,
+    s_a: &Scalar,
+    digest: &Sighash,
+    encrypted_signature: EncryptedSignature,
+) -> Result<Signature> {
+    verify_encsig(
+        B,
+        PublicKey::from(s_a.clone()),
+        digest,
+        &encrypted_signature,
+    )
+    .context("Invalid encrypted signature received")?;
+
+    decrypt_signature(encrypted_signature, s_a, B, digest)
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Bitcoin lock output is already spent by transaction {by_txid}")]
+pub struct LockAlreadySpent {
+    pub by_txid: Txid,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Bitcoin lock output pays {actual} instead of the agreed {expected}")]
+pub struct LockUnderfunded {
+    pub expected: Amount,
+    pub actual: Amount,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Bitcoin lock output holds {actual:?} instead of the agreed {expected}")]
+pub struct AmountMismatch {
+    pub expected: Amount,
+    /// `None` if the transaction has no lock output.
+    pub actual: Option<Amount>,
+}
+
 #[derive(Clone, Copy, Debug, thiserror::Error)]
 #[error("encrypted signature is invalid")]
 pub struct InvalidEncryptedSignature;
 
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+#[error("decrypted signature is invalid, the encrypted signature was not encrypted for our key")]
+pub struct InvalidDecryptedSignature;
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+pub enum InvalidLockKeys {
+    #[error("Alice's and Bob's Bitcoin lock keys are equal")]
+    Equal,
+}
+
+/// Checks that Alice's (`A`) and Bob's (`B`) keys make a sound 2-of-2 lock,
+/// i.e. that they differ.
+///
+/// There is no need to check the encoding, a [`PublicKey`] can only hold a
+/// valid point and always serializes in compressed form.
+pub fn validate_lock_keys(A: PublicKey, B: PublicKey) -> Result<(), InvalidLockKeys> {
+    if A == B {
+        return Err(InvalidLockKeys::Equal);
+    }
+
+    Ok(())
+}
+
+/// Builds the descriptor of the 2-of-2 output the Bitcoin lock transaction
+/// pays to, given Alice's (`A`) and Bob's (`B`) public keys.
+///
+/// This allows to independently verify that the address Bob funds is
+/// controlled by exactly these two keys.
+pub fn build_lock_descriptor(A: PublicKey, B: PublicKey) -> Descriptor<bitcoin::PublicKey> {
+    build_shared_output_descriptor(A.0, B.0)
+}
+
 pub fn build_shared_output_descriptor(A: Point, B: Point) -> Descriptor<bitcoin::PublicKey> {
     const MINISCRIPT_TEMPLATE: &str = "c:and_v(v:pk(A),pk_k(B))";
 
@@ -222,6 +324,25 @@ pub fn build_shared_output_descriptor(A: Point, B: Point) -> Descriptor<bitcoin:
     Descriptor::Wsh(Wsh::new(miniscript).expect("a valid descriptor"))
 }
 
+/// Version of the transactions spending the lock and cancel outputs. The cancel
+/// and punish timelocks are relative timelocks in the input sequence, which
+/// BIP-68 only enforces from version 2 on.
+const TX_VERSION: i32 = 2;
+
+/// Builds a transaction spending the single `input` to `output`.
+///
+/// Both parties have to build the exact same transactions to exchange
+/// signatures, timelocks are therefore only expressed through the sequence of
+/// `input` and never through the absolute locktime.
+fn spending_transaction(input: TxIn, output: Vec<TxOut>) -> Transaction {
+    Transaction {
+        version: TX_VERSION,
+        lock_time: PackedLockTime(0),
+        input: vec![input],
+        output,
+    }
+}
+
 pub fn recover(S: PublicKey, sig: Signature, encsig: EncryptedSignature) -> Result<SecretKey> {
     let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
 
@@ -270,6 +391,25 @@ impl From<RpcErrorCode> for i64 {
     }
 }
 
+/// Reasons Bitcoin Core gives for rejecting a transaction whose fee is too low.
+const FEE_TOO_LOW_REJECTIONS: [&str; 3] = [
+    "min relay fee not met",
+    "mempool min fee not met",
+    "insufficient fee",
+];
+
+/// Whether `error` is the rejection of a broadcast because the transaction
+/// pays too low a fee.
+pub fn is_fee_too_low(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+
+        FEE_TOO_LOW_REJECTIONS
+            .iter()
+            .any(|rejection| message.contains(rejection))
+    })
+}
+
 pub fn parse_rpc_error_code(error: &anyhow::Error) -> anyhow::Result<i64> {
     let string = match error.downcast_ref::<bdk::Error>() {
         Some(bdk::Error::Electrum(bdk::electrum_client::Error::Protocol(
@@ -326,6 +466,87 @@ mod tests {
     use rand::rngs::OsRng;
     use uuid::Uuid;
 
+    /// Bob's key `b = 3`, Alice's Monero key `s_a = 2` and a fixed digest.
+    /// The public keys are the well-known multiples of the generator.
+    fn adaptor_vector() -> (SecretKey, Scalar, Sighash) {
+        let scalar = |n: u8| {
+            let mut bytes = [0u8; 32];
+            bytes[31] = n;
+            Scalar::from_bytes(bytes).unwrap().non_zero().unwrap()
+        };
+
+        (
+            SecretKey::from(scalar(3)),
+            scalar(2),
+            Sighash::from_inner([0x42; 32]),
+        )
+    }
+
+    #[test]
+    fn adaptor_vector_has_known_public_keys() {
+        let (b, s_a, _) = adaptor_vector();
+
+        assert_eq!(
+            b.public().0.to_bytes().to_hex(),
+            "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+        );
+        assert_eq!(
+            PublicKey::from(s_a).0.to_bytes().to_hex(),
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5"
+        );
+    }
+
+    #[test]
+    fn given_adaptor_vector_then_encsig_verifies_and_decrypts_to_valid_signature() {
+        let (b, s_a, digest) = adaptor_vector();
+        let encsig = b.encsign(PublicKey::from(s_a.clone()), digest);
+
+        assert_eq!(
+            b.encsign(PublicKey::from(s_a.clone()), digest),
+            encsig,
+            "encrypted signing is deterministic"
+        );
+
+        let sig_b = verify_and_decrypt_encsig(b.public(), &s_a, &digest, encsig.clone()).unwrap();
+
+        // Cross-check with libsecp256k1, which shares no code with ecdsa_fun
+        let secp = secp256k1::Secp256k1::verification_only();
+        let message = secp256k1::Message::from_slice(&digest.into_inner()).unwrap();
+        let B = secp256k1::PublicKey::from_slice(&b.public().0.to_bytes()).unwrap();
+        secp.verify_ecdsa(&message, &sig_b.into(), &B).unwrap();
+
+        assert_eq!(
+            decrypt_signature(encsig.clone(), &s_a, b.public(), &digest).unwrap(),
+            sig_b
+        );
+        assert_eq!(
+            recover(PublicKey::from(s_a.clone()), sig_b, encsig).unwrap(),
+            SecretKey::from(s_a)
+        );
+    }
+
+    #[test]
+    fn given_adaptor_vector_with_other_digest_then_encsig_is_invalid() {
+        let (b, s_a, digest) = adaptor_vector();
+        let encsig = b.encsign(PublicKey::from(s_a.clone()), digest);
+
+        let error =
+            verify_and_decrypt_encsig(b.public(), &s_a, &Sighash::from_inner([0x43; 32]), encsig)
+                .unwrap_err();
+
+        assert!(error.downcast_ref::<InvalidEncryptedSignature>().is_some());
+    }
+
+    #[test]
+    fn given_adaptor_vector_with_other_encryption_key_then_encsig_is_invalid() {
+        let (b, s_a, digest) = adaptor_vector();
+        let encsig = b.encsign(PublicKey::random(), digest);
+
+        let error = verify_and_decrypt_encsig(b.public(), &s_a, &digest, encsig).unwrap_err();
+
+        assert!(error.downcast_ref::<InvalidEncryptedSignature>().is_some());
+    }
+
     #[test]
     fn lock_confirmations_le_to_cancel_timelock_no_timelock_expired() {
         let tx_lock_status = ScriptStatus::from_confirmations(4);
@@ -413,6 +634,8 @@ mod tests {
             config.monero_finality_confirmations,
             spending_fee,
             spending_fee,
+            false,
+            None,
         );
 
         let message0 = bob_state0.next_message();
@@ -1,11 +1,14 @@
 use crate::bitcoin;
 use crate::bitcoin::wallet::Watchable;
 use crate::bitcoin::{
-    build_shared_output_descriptor, Address, Amount, BlockHeight, PublicKey, Transaction, TxLock,
+    build_shared_output_descriptor, spending_transaction, Address, Amount, BlockHeight, PublicKey,
+    Transaction, TxLock,
 };
+use ::bitcoin::blockdata::opcodes;
+use ::bitcoin::blockdata::script::Builder;
 use ::bitcoin::util::sighash::SighashCache;
 use ::bitcoin::{
-    EcdsaSighashType, OutPoint, PackedLockTime, Script, Sequence, Sighash, TxIn, TxOut, Txid,
+    EcdsaSighashType, OutPoint, Script, Sequence, Sighash, TxIn, TxOut, Txid, Witness,
 };
 use anyhow::Result;
 use bdk::miniscript::Descriptor;
@@ -30,6 +33,12 @@ impl CancelTimelock {
     }
 }
 
+impl From<CancelTimelock> for u32 {
+    fn from(cancel_timelock: CancelTimelock) -> Self {
+        cancel_timelock.0
+    }
+}
+
 impl Add<CancelTimelock> for BlockHeight {
     type Output = BlockHeight;
 
@@ -78,6 +87,12 @@ impl Add<PunishTimelock> for BlockHeight {
     }
 }
 
+impl From<PunishTimelock> for u32 {
+    fn from(punish_timelock: PunishTimelock) -> Self {
+        punish_timelock.0
+    }
+}
+
 impl PartialOrd<PunishTimelock> for u32 {
     fn partial_cmp(&self, other: &PunishTimelock) -> Option<Ordering> {
         self.partial_cmp(&other.0)
@@ -90,6 +105,32 @@ impl PartialEq<PunishTimelock> for u32 {
     }
 }
 
+/// The value of an anchor output, the dust limit of a P2WSH output.
+const ANCHOR_AMOUNT: u64 = 330;
+
+/// The weight an anchor output adds to a transaction.
+const ANCHOR_OUTPUT_WEIGHT: usize = 172;
+
+/// The weight of the empty script sig and the witness that spend an anchor
+/// output.
+pub const ANCHOR_SATISFACTION_WEIGHT: usize = 7;
+
+/// The witness script of an anchor output. It consists of `OP_TRUE` only, so
+/// either party can spend the output without a signature.
+fn anchor_witness_script() -> Script {
+    Builder::new().push_opcode(opcodes::OP_TRUE).into_script()
+}
+
+/// The script pubkey of an anchor output.
+pub fn anchor_script_pubkey() -> Script {
+    Script::new_v0_p2wsh(&anchor_witness_script().wscript_hash())
+}
+
+/// The witness that spends an anchor output.
+pub fn anchor_witness() -> Witness {
+    Witness::from_vec(vec![anchor_witness_script().to_bytes()])
+}
+
 #[derive(Debug)]
 pub struct TxCancel {
     inner: Transaction,
@@ -106,6 +147,38 @@ impl TxCancel {
         B: PublicKey,
         spending_fee: Amount,
     ) -> Self {
+        Self::build(tx_lock, cancel_timelock, A, B, spending_fee, false)
+    }
+
+    /// Builds the cancel transaction with an additional anchor output at index
+    /// `1`.
+    ///
+    /// The pre-signed `spending_fee` may turn out too low by the time the
+    /// cancel timelock expires. Either party can then spend the anchor output
+    /// with [`Wallet::spend_anchor`](crate::bitcoin::Wallet::spend_anchor) to
+    /// bump the fee via CPFP. The value of the anchor output is taken from the
+    /// cancel output.
+    pub fn with_anchor(
+        tx_lock: &TxLock,
+        cancel_timelock: CancelTimelock,
+        A: PublicKey,
+        B: PublicKey,
+        spending_fee: Amount,
+    ) -> Self {
+        Self::build(tx_lock, cancel_timelock, A, B, spending_fee, true)
+    }
+
+    /// Builds the cancel transaction as [`new`](TxCancel::new) does, or as
+    /// [`with_anchor`](TxCancel::with_anchor) does if `with_anchor` is set.
+    pub fn build(
+        tx_lock: &TxLock,
+        cancel_timelock: CancelTimelock,
+        A: PublicKey,
+        B: PublicKey,
+        spending_fee: Amount,
+        with_anchor: bool,
+    ) -> Self {
+        let anchor_amount = if with_anchor { ANCHOR_AMOUNT } else { 0 };
         let cancel_output_descriptor = build_shared_output_descriptor(A.0, B.0);
 
         let tx_in = TxIn {
@@ -116,16 +189,19 @@ impl TxCancel {
         };
 
         let tx_out = TxOut {
-            value: tx_lock.lock_amount().to_sat() - spending_fee.to_sat(),
+            value: tx_lock.lock_amount().to_sat() - spending_fee.to_sat() - anchor_amount,
             script_pubkey: cancel_output_descriptor.script_pubkey(),
         };
 
-        let transaction = Transaction {
-            version: 2,
-            lock_time: PackedLockTime(0),
-            input: vec![tx_in],
-            output: vec![tx_out],
-        };
+        let mut output = vec![tx_out];
+        if with_anchor {
+            output.push(TxOut {
+                value: ANCHOR_AMOUNT,
+                script_pubkey: anchor_script_pubkey(),
+            });
+        }
+
+        let transaction = spending_transaction(tx_in, output);
 
         let digest = SighashCache::new(&transaction)
             .segwit_signature_hash(
@@ -160,6 +236,16 @@ impl TxCancel {
         OutPoint::new(self.inner.txid(), 0)
     }
 
+    /// The anchor output, if the transaction was built
+    /// [`with_anchor`](TxCancel::with_anchor).
+    pub fn anchor_outpoint(&self) -> Option<OutPoint> {
+        self.inner
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == anchor_script_pubkey())
+            .map(|vout| OutPoint::new(self.inner.txid(), vout as u32))
+    }
+
     pub fn complete_as_alice(
         self,
         a: bitcoin::SecretKey,
@@ -256,17 +342,16 @@ impl TxCancel {
             script_pubkey: spend_address.script_pubkey(),
         };
 
-        Transaction {
-            version: 2,
-            lock_time: PackedLockTime(0),
-            input: vec![tx_in],
-            output: vec![tx_out],
-        }
+        spending_transaction(tx_in, vec![tx_out])
     }
 
     pub fn weight() -> usize {
         596
     }
+
+    pub fn weight_with_anchor() -> usize {
+        Self::weight() + ANCHOR_OUTPUT_WEIGHT
+    }
 }
 
 impl Watchable for TxCancel {
@@ -278,3 +363,39 @@ impl Watchable for TxCancel {
         self.output_descriptor.script_pubkey()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::WalletBuilder;
+    use ::bitcoin::PackedLockTime;
+
+    #[tokio::test]
+    async fn cancel_and_punish_express_relative_timelocks_in_sequence_and_version() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let (A, B) = (PublicKey::random(), PublicKey::random());
+        let change = wallet.new_address().await.unwrap();
+        let tx_lock = TxLock::new(&wallet, Amount::from_sat(10_000), A, B, change, None)
+            .await
+            .unwrap();
+        let cancel_timelock = CancelTimelock::new(12);
+        let punish_timelock = PunishTimelock::new(6);
+
+        let tx_cancel = TxCancel::new(&tx_lock, cancel_timelock, A, B, Amount::from_sat(1_000));
+        let tx_punish = tx_cancel.build_spend_transaction(
+            &wallet.new_address().await.unwrap(),
+            Some(punish_timelock),
+            Amount::from_sat(1_000),
+        );
+
+        for (transaction, timelock) in [(&tx_cancel.inner, 12), (&tx_punish, 6)] {
+            let sequence = transaction.input[0].sequence;
+
+            assert!(transaction.version >= 2);
+            assert!(sequence.is_relative_lock_time());
+            assert!(sequence.is_height_locked());
+            assert_eq!(sequence, Sequence::from_height(timelock));
+            assert_eq!(transaction.lock_time, PackedLockTime(0));
+        }
+    }
+}
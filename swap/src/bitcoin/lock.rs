@@ -1,13 +1,14 @@
 use crate::bitcoin::wallet::{EstimateFeeRate, Watchable};
 use crate::bitcoin::{
-    build_shared_output_descriptor, Address, Amount, PublicKey, Transaction, Wallet,
+    build_lock_descriptor, spending_transaction, validate_lock_keys, Address, Amount,
+    AmountMismatch, FeeRate, LockAlreadySpent, LockUnderfunded, PublicKey, Transaction, Wallet,
 };
 use ::bitcoin::util::psbt::PartiallySignedTransaction;
 use ::bitcoin::{OutPoint, TxIn, TxOut, Txid};
 use anyhow::{bail, Result};
 use bdk::database::BatchDatabase;
 use bdk::miniscript::Descriptor;
-use bitcoin::{PackedLockTime, Script, Sequence};
+use bitcoin::{Script, Sequence};
 use serde::{Deserialize, Serialize};
 
 const SCRIPT_SIZE: usize = 34;
@@ -26,18 +27,21 @@ impl TxLock {
         A: PublicKey,
         B: PublicKey,
         change: bitcoin::Address,
+        fee_rate_override: Option<FeeRate>,
     ) -> Result<Self>
     where
         C: EstimateFeeRate,
         D: BatchDatabase,
     {
-        let lock_output_descriptor = build_shared_output_descriptor(A.0, B.0);
+        validate_lock_keys(A, B)?;
+
+        let lock_output_descriptor = build_lock_descriptor(A, B);
         let address = lock_output_descriptor
             .address(wallet.get_network())
             .expect("can derive address from descriptor");
 
         let psbt = wallet
-            .send_to_address(address, amount, Some(change))
+            .send_to_address(address, amount, Some(change), fee_rate_override)
             .await?;
 
         Ok(Self {
@@ -57,6 +61,8 @@ impl TxLock {
         B: PublicKey,
         btc: Amount,
     ) -> Result<Self> {
+        validate_lock_keys(A, B)?;
+
         let shared_output_candidate = match psbt.unsigned_tx.output.as_slice() {
             [shared_output_candidate, _] if shared_output_candidate.value == btc.to_sat() => {
                 shared_output_candidate
@@ -83,7 +89,7 @@ impl TxLock {
             }
         };
 
-        let descriptor = build_shared_output_descriptor(A.0, B.0);
+        let descriptor = build_lock_descriptor(A, B);
         let legit_shared_output_script = descriptor.script_pubkey();
 
         if shared_output_candidate.script_pubkey != legit_shared_output_script {
@@ -104,6 +110,29 @@ impl TxLock {
         self.inner.clone().extract_tx().txid()
     }
 
+    /// The fee paid by the lock transaction, as long as the PSBT carries the
+    /// outputs spent by all of its inputs.
+    pub fn fee(&self) -> Option<Amount> {
+        let tx = &self.inner.unsigned_tx;
+
+        let mut spent = 0;
+        for (input, txin) in self.inner.inputs.iter().zip(&tx.input) {
+            spent += match (&input.witness_utxo, &input.non_witness_utxo) {
+                (Some(utxo), _) => utxo.value,
+                (None, Some(previous)) => {
+                    previous
+                        .output
+                        .get(txin.previous_output.vout as usize)?
+                        .value
+                }
+                (None, None) => return None,
+            };
+        }
+        let created = tx.output.iter().map(|output| output.value).sum::<u64>();
+
+        spent.checked_sub(created).map(Amount::from_sat)
+    }
+
     pub fn as_outpoint(&self) -> OutPoint {
         // This is fine because a transaction that has that many outputs is not
         // realistic
@@ -111,6 +140,57 @@ impl TxLock {
         OutPoint::new(self.txid(), self.lock_output_vout() as u32)
     }
 
+    /// Fails with [`LockAlreadySpent`] if a transaction in the mempool or on
+    /// the chain spends the lock output already.
+    pub async fn ensure_unspent(&self, wallet: &Wallet) -> Result<()> {
+        if let Some(by_txid) = self.spent_by(wallet).await? {
+            bail!(LockAlreadySpent { by_txid })
+        }
+
+        Ok(())
+    }
+
+    /// The id of the transaction in the mempool or on the chain that spends
+    /// the lock output, `None` if it is unspent.
+    pub async fn spent_by(&self, wallet: &Wallet) -> Result<Option<Txid>> {
+        wallet
+            .spending_transaction(self.as_outpoint(), &self.script_pubkey())
+            .await
+    }
+
+    /// Checks the lock output of the lock transaction seen on the chain
+    /// against the `agreed` amount that was negotiated for the swap and stored
+    /// separately from the transaction.
+    ///
+    /// Fails with [`LockUnderfunded`] if the lock output pays less than
+    /// `agreed` and with [`AmountMismatch`] if it pays more or is missing.
+    pub async fn ensure_amount(&self, wallet: &Wallet, agreed: Amount) -> Result<()> {
+        let transaction = wallet.get_raw_transaction(self.txid()).await?;
+
+        self.ensure_amount_in(&transaction, agreed)
+    }
+
+    fn ensure_amount_in(&self, transaction: &Transaction, agreed: Amount) -> Result<()> {
+        let script = self.script_pubkey();
+        let actual = transaction
+            .output
+            .iter()
+            .find(|output| output.script_pubkey == script)
+            .map(|output| Amount::from_sat(output.value));
+
+        match actual {
+            Some(actual) if actual == agreed => Ok(()),
+            Some(actual) if actual < agreed => bail!(LockUnderfunded {
+                expected: agreed,
+                actual
+            }),
+            actual => bail!(AmountMismatch {
+                expected: agreed,
+                actual
+            }),
+        }
+    }
+
     /// Calculate the size of the script used by this transaction.
     pub fn script_size() -> usize {
         SCRIPT_SIZE
@@ -156,12 +236,7 @@ impl TxLock {
 
         tracing::debug!(%fee, "Constructed Bitcoin spending transaction");
 
-        Transaction {
-            version: 2,
-            lock_time: PackedLockTime(0),
-            input: vec![tx_in],
-            output: vec![tx_out],
-        }
+        spending_transaction(tx_in, vec![tx_out])
     }
 
     pub fn weight() -> usize {
@@ -190,6 +265,7 @@ mod tests {
     use super::*;
     use crate::bitcoin::wallet::StaticFeeRate;
     use crate::bitcoin::WalletBuilder;
+    use crate::bitcoin::{build_shared_output_descriptor, InvalidLockKeys};
 
     #[tokio::test]
     async fn given_bob_sends_good_psbt_when_reconstructing_then_succeeeds() {
@@ -222,6 +298,19 @@ mod tests {
         result.expect("PSBT to be valid");
     }
 
+    #[tokio::test]
+    async fn fee_is_what_the_inputs_spend_beyond_the_outputs() {
+        let (A, B) = alice_and_bob();
+        let fees = 300;
+        let agreed_amount = Amount::from_sat(10000);
+        let wallet = WalletBuilder::new(agreed_amount.to_sat() + fees).build();
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt, A, B, agreed_amount).unwrap();
+
+        assert_eq!(tx_lock.fee(), Some(Amount::from_sat(fees)));
+    }
+
     #[tokio::test]
     async fn given_bob_is_sending_less_than_agreed_when_reconstructing_txlock_then_fails() {
         let (A, B) = alice_and_bob();
@@ -248,6 +337,120 @@ mod tests {
         result.expect_err("PSBT to be invalid");
     }
 
+    #[tokio::test]
+    async fn given_equal_keys_building_txlock_fails() {
+        let (A, _) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let change = wallet.new_address().await.unwrap();
+
+        let error = TxLock::new(&wallet, Amount::from_sat(10_000), A, A, change, None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<InvalidLockKeys>(),
+            Some(&InvalidLockKeys::Equal)
+        );
+    }
+
+    #[tokio::test]
+    async fn given_equal_keys_reconstructing_txlock_fails() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let error = TxLock::from_psbt(psbt, B, B, agreed_amount).unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<InvalidLockKeys>(),
+            Some(&InvalidLockKeys::Equal)
+        );
+    }
+
+    #[test]
+    fn distinct_canonical_keys_are_valid_lock_keys() {
+        let (A, B) = alice_and_bob();
+
+        assert_eq!(validate_lock_keys(A, B), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn lock_descriptor_matches_lock_output() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let change = wallet.new_address().await.unwrap();
+
+        let tx_lock = TxLock::new(&wallet, Amount::from_sat(10_000), A, B, change, None)
+            .await
+            .unwrap();
+        let descriptor = build_lock_descriptor(A, B);
+
+        assert_eq!(descriptor.script_pubkey(), tx_lock.script_pubkey());
+        assert_eq!(descriptor, tx_lock.output_descriptor);
+    }
+
+    #[tokio::test]
+    async fn lock_paying_the_agreed_amount_passes_the_amount_check() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, agreed_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, agreed_amount).unwrap();
+
+        tx_lock
+            .ensure_amount_in(&psbt.extract_tx(), agreed_amount)
+            .expect("lock to hold the agreed amount");
+    }
+
+    #[tokio::test]
+    async fn given_agreed_amount_above_lock_output_then_lock_is_underfunded() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let locked_amount = Amount::from_sat(9000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, locked_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, locked_amount).unwrap();
+        // the amount stored with the swap, e.g. `State3::btc`, differs from the lock
+        let agreed_amount = Amount::from_sat(10000);
+
+        let error = tx_lock
+            .ensure_amount_in(&psbt.extract_tx(), agreed_amount)
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<LockUnderfunded>(),
+            Some(&LockUnderfunded {
+                expected: agreed_amount,
+                actual: locked_amount,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn given_agreed_amount_below_lock_output_then_amount_mismatches() {
+        let (A, B) = alice_and_bob();
+        let wallet = WalletBuilder::new(50_000).build();
+        let locked_amount = Amount::from_sat(11000);
+
+        let psbt = bob_make_psbt(A, B, &wallet, locked_amount).await;
+        let tx_lock = TxLock::from_psbt(psbt.clone(), A, B, locked_amount).unwrap();
+        let agreed_amount = Amount::from_sat(10000);
+
+        let error = tx_lock
+            .ensure_amount_in(&psbt.extract_tx(), agreed_amount)
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<AmountMismatch>(),
+            Some(&AmountMismatch {
+                expected: agreed_amount,
+                actual: Some(locked_amount),
+            })
+        );
+    }
+
     proptest::proptest! {
         #[test]
         fn estimated_tx_lock_script_size_never_changes(a in crate::proptest::ecdsa_fun::point(), b in crate::proptest::ecdsa_fun::point()) {
@@ -269,7 +472,7 @@ mod tests {
         amount: Amount,
     ) -> PartiallySignedTransaction {
         let change = wallet.new_address().await.unwrap();
-        TxLock::new(wallet, amount, A, B, change)
+        TxLock::new(wallet, amount, A, B, change, None)
             .await
             .unwrap()
             .into()
@@ -45,6 +45,10 @@ impl TxPunish {
         }
     }
 
+    pub fn txid(&self) -> Txid {
+        self.inner.txid()
+    }
+
     pub fn digest(&self) -> Sighash {
         self.digest
     }
@@ -1,7 +1,7 @@
 use crate::bitcoin::wallet::Watchable;
 use crate::bitcoin::{
-    verify_encsig, verify_sig, Address, Amount, EmptyWitnessStack, EncryptedSignature, NoInputs,
-    NotThreeWitnesses, PublicKey, SecretKey, TooManyInputs, Transaction, TxLock,
+    verify_and_decrypt_encsig, verify_sig, Address, Amount, EmptyWitnessStack, EncryptedSignature,
+    NoInputs, NotThreeWitnesses, PublicKey, SecretKey, TooManyInputs, Transaction, TxLock,
 };
 use ::bitcoin::{Sighash, Txid};
 use anyhow::{bail, Context, Result};
@@ -9,11 +9,8 @@ use bdk::miniscript::Descriptor;
 use bitcoin::secp256k1::ecdsa;
 use bitcoin::util::sighash::SighashCache;
 use bitcoin::{EcdsaSighashType, Script};
-use ecdsa_fun::adaptor::{Adaptor, HashTranscript};
 use ecdsa_fun::fun::Scalar;
-use ecdsa_fun::nonce::Deterministic;
 use ecdsa_fun::Signature;
-use sha2::Sha256;
 use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
@@ -62,17 +59,8 @@ impl TxRedeem {
         s_a: Scalar,
         B: PublicKey,
     ) -> Result<Transaction> {
-        verify_encsig(
-            B,
-            PublicKey::from(s_a.clone()),
-            &self.digest(),
-            &encrypted_signature,
-        )
-        .context("Invalid encrypted signature received")?;
-
+        let sig_b = verify_and_decrypt_encsig(B, &s_a, &self.digest(), encrypted_signature)?;
         let sig_a = a.sign(self.digest());
-        let adaptor = Adaptor::<HashTranscript<Sha256>, Deterministic<Sha256>>::default();
-        let sig_b = adaptor.decrypt_signature(&s_a, encrypted_signature);
 
         let satisfier = {
             let mut satisfier = HashMap::with_capacity(2);
@@ -86,7 +74,8 @@ impl TxRedeem {
                 inner: B.0.into(),
             };
 
-            // The order in which these are inserted doesn't matter
+            // The order in which these are inserted doesn't matter, miniscript looks the
+            // signatures up by key, so the witness is the same for the same inputs
             satisfier.insert(
                 A,
                 ::bitcoin::EcdsaSig {
@@ -159,3 +148,78 @@ impl Watchable for TxRedeem {
         self.watch_script.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::{decrypt_signature, InvalidDecryptedSignature, WalletBuilder};
+    use ::bitcoin::hashes::Hash;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn given_encsig_for_s_a_decrypts_to_valid_signature() {
+        let b = SecretKey::new_random(&mut OsRng);
+        let s_a = Scalar::random(&mut OsRng);
+        let digest = Sighash::from_inner([42; 32]);
+        let encsig = b.encsign(PublicKey::from(s_a.clone()), digest);
+
+        let sig_b = decrypt_signature(encsig, &s_a, b.public(), &digest).unwrap();
+
+        assert!(verify_sig(&b.public(), &digest, &sig_b).is_ok());
+    }
+
+    #[test]
+    fn given_encsig_for_other_key_fails_with_invalid_decrypted_signature() {
+        let b = SecretKey::new_random(&mut OsRng);
+        let s_a = Scalar::random(&mut OsRng);
+        let other_key = SecretKey::new_random(&mut OsRng).public();
+        let digest = Sighash::from_inner([42; 32]);
+        let encsig = b.encsign(other_key, digest);
+
+        let error = decrypt_signature(encsig, &s_a, b.public(), &digest).unwrap_err();
+
+        assert!(error.downcast_ref::<InvalidDecryptedSignature>().is_some());
+    }
+
+    #[tokio::test]
+    async fn given_same_inputs_then_completed_redeem_transaction_is_identical() {
+        let a = SecretKey::new_random(&mut OsRng);
+        let b = SecretKey::new_random(&mut OsRng);
+        let s_a = Scalar::random(&mut OsRng);
+        let wallet = WalletBuilder::new(50_000).build();
+        let change = wallet.new_address().await.unwrap();
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(10_000),
+            a.public(),
+            b.public(),
+            change,
+            None,
+        )
+        .await
+        .unwrap();
+        let redeem_address = wallet.new_address().await.unwrap();
+        let tx_redeem = TxRedeem::new(&tx_lock, &redeem_address, Amount::from_sat(1_000));
+        let encsig = b.encsign(PublicKey::from(s_a.clone()), tx_redeem.digest());
+
+        // Every satisfier is a fresh `HashMap` with its own random iteration order
+        let complete = || {
+            tx_redeem
+                .clone()
+                .complete(encsig.clone(), a.clone(), s_a.clone(), b.public())
+                .unwrap()
+        };
+        let first = complete();
+
+        for _ in 0..10 {
+            let other = complete();
+
+            assert_eq!(other.input[0].witness, first.input[0].witness);
+            assert_eq!(other.wtxid(), first.wtxid());
+            assert_eq!(
+                ::bitcoin::consensus::serialize(&other),
+                ::bitcoin::consensus::serialize(&first)
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use ::bitcoin::hashes::Hash;
+use ::bitcoin::secp256k1::{ecdsa, All, Message, Secp256k1};
+use ::bitcoin::util::bip32::{Fingerprint, KeySource};
+use ::bitcoin::util::psbt::PartiallySignedTransaction;
+use ::bitcoin::util::sighash::SighashCache;
+use ::bitcoin::{EcdsaSig, EcdsaSighashType, Script, Sighash};
+use anyhow::Result;
+use bdk::signer::{InputSigner, SignerCommon, SignerError, SignerId};
+use bdk::SignOptions;
+use std::fmt;
+use std::sync::Arc;
+
+/// Signs the wallet's own inputs on behalf of the wallet, for example on a
+/// hardware wallet, so the private keys never have to be known to the host.
+///
+/// The adaptor signatures of the swap's 2-of-2 outputs are not produced
+/// through this trait.
+pub trait Signer: Send + Sync {
+    /// Produces an ECDSA signature over `sighash` with the key identified by
+    /// `key_source`.
+    fn sign(&self, sighash: Sighash, key_source: &KeySource) -> Result<ecdsa::Signature>;
+}
+
+/// Makes a [`Signer`] usable as a signer of a BDK wallet.
+pub(crate) struct ExternalSigner {
+    inner: Arc<dyn Signer>,
+    fingerprint: Fingerprint,
+}
+
+impl ExternalSigner {
+    pub(crate) fn new(inner: Arc<dyn Signer>, fingerprint: Fingerprint) -> Self {
+        Self { inner, fingerprint }
+    }
+}
+
+impl fmt::Debug for ExternalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalSigner")
+            .field("fingerprint", &self.fingerprint)
+            .finish()
+    }
+}
+
+impl SignerCommon for ExternalSigner {
+    fn id(&self, _: &Secp256k1<All>) -> SignerId {
+        SignerId::Fingerprint(self.fingerprint)
+    }
+}
+
+impl InputSigner for ExternalSigner {
+    fn sign_input(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+        input_index: usize,
+        _: &SignOptions,
+        secp: &Secp256k1<All>,
+    ) -> Result<(), SignerError> {
+        if input_index >= psbt.inputs.len() || input_index >= psbt.unsigned_tx.input.len() {
+            return Err(SignerError::InputIndexOutOfRange);
+        }
+
+        let input = &psbt.inputs[input_index];
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            return Ok(());
+        }
+
+        let value = match (&input.witness_utxo, &input.non_witness_utxo) {
+            (Some(utxo), _) => utxo.value,
+            (None, Some(tx)) => {
+                let vout = psbt.unsigned_tx.input[input_index].previous_output.vout;
+                tx.output
+                    .get(vout as usize)
+                    .ok_or(SignerError::InvalidNonWitnessUtxo)?
+                    .value
+            }
+            (None, None) => return Err(SignerError::MissingWitnessUtxo),
+        };
+
+        let keys = input
+            .bip32_derivation
+            .iter()
+            .filter(|(_, (fingerprint, _))| *fingerprint == self.fingerprint)
+            .map(|(pubkey, key_source)| (::bitcoin::PublicKey::new(*pubkey), key_source.clone()))
+            .collect::<Vec<_>>();
+
+        for (pubkey, key_source) in keys {
+            if psbt.inputs[input_index].partial_sigs.contains_key(&pubkey) {
+                continue;
+            }
+
+            // we only ever spend P2WPKH outputs of our own wallet
+            let script_code = Script::new_p2pkh(&pubkey.pubkey_hash());
+            let sighash = SighashCache::new(&psbt.unsigned_tx)
+                .segwit_signature_hash(input_index, &script_code, value, EcdsaSighashType::All)
+                .map_err(SignerError::SighashError)?;
+
+            let sig = self.inner.sign(sighash, &key_source).map_err(|error| {
+                tracing::error!("External signer failed to sign: {:#}", error);
+                SignerError::UserCanceled
+            })?;
+
+            let message = Message::from_slice(&sighash.into_inner()).expect("sighash is 32 bytes");
+            secp.verify_ecdsa(&message, &sig, &pubkey.inner)
+                .map_err(|_| SignerError::InvalidKey)?;
+
+            psbt.inputs[input_index].partial_sigs.insert(
+                pubkey,
+                EcdsaSig {
+                    sig,
+                    hash_ty: EcdsaSighashType::All,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
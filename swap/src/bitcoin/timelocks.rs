@@ -2,7 +2,9 @@ use anyhow::Context;
 use bdk::electrum_client::HeaderNotification;
 use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::ops::Add;
+use std::time::Duration;
 
 /// Represent a block height, or block number, expressed in absolute block
 /// count. E.g. The transaction was included in block #655123, 655123 block
@@ -11,6 +13,12 @@ use std::ops::Add;
 #[serde(transparent)]
 pub struct BlockHeight(u32);
 
+impl BlockHeight {
+    pub const fn new(height: u32) -> Self {
+        Self(height)
+    }
+}
+
 impl From<BlockHeight> for u32 {
     fn from(height: BlockHeight) -> Self {
         height.0
@@ -43,3 +51,124 @@ pub enum ExpiredTimelocks {
     Cancel,
     Punish,
 }
+
+/// How far a relative timelock is from expiring, e.g. to render a countdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelockStatus {
+    Expired,
+    Remaining {
+        blocks: u32,
+        /// Based on the average block time of the network.
+        estimated_time: Duration,
+    },
+}
+
+impl TimelockStatus {
+    /// Computes the status of a timelock of `timelock` blocks relative to a
+    /// transaction confirmed at `lock_height`, given `latest_block` as the
+    /// current tip.
+    ///
+    /// Like for [`Confirmed`](crate::bitcoin::wallet::Confirmed), the block
+    /// of inclusion counts as the first confirmation.
+    pub fn new(
+        lock_height: BlockHeight,
+        latest_block: BlockHeight,
+        timelock: u32,
+        avg_block_time: Duration,
+    ) -> Self {
+        let confirmations = (latest_block.0 + 1).saturating_sub(lock_height.0);
+
+        Self::from_remaining(timelock.saturating_sub(confirmations), avg_block_time)
+    }
+
+    /// The status of a timelock relative to a transaction that is not
+    /// confirmed yet, i.e. the whole timelock remains.
+    pub fn not_started(timelock: u32, avg_block_time: Duration) -> Self {
+        Self::from_remaining(timelock, avg_block_time)
+    }
+
+    /// The number of blocks until the timelock expires, zero once it expired.
+    pub fn blocks_remaining(&self) -> u32 {
+        match self {
+            TimelockStatus::Expired => 0,
+            TimelockStatus::Remaining { blocks, .. } => *blocks,
+        }
+    }
+
+    fn from_remaining(blocks: u32, avg_block_time: Duration) -> Self {
+        if blocks == 0 {
+            return TimelockStatus::Expired;
+        }
+
+        TimelockStatus::Remaining {
+            blocks,
+            estimated_time: avg_block_time * blocks,
+        }
+    }
+}
+
+impl fmt::Display for TimelockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelockStatus::Expired => write!(f, "expired"),
+            TimelockStatus::Remaining {
+                blocks,
+                estimated_time,
+            } => write!(
+                f,
+                "{} blocks / ~{} minutes remaining",
+                blocks,
+                (estimated_time.as_secs() + 59) / 60
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEN_MINUTES: Duration = Duration::from_secs(600);
+
+    #[test]
+    fn given_lock_at_known_height_then_remaining_blocks_count_from_inclusion() {
+        let lock_height = BlockHeight::new(100);
+
+        let status = TimelockStatus::new(lock_height, BlockHeight::new(110), 72, TEN_MINUTES);
+
+        assert_eq!(
+            status,
+            TimelockStatus::Remaining {
+                blocks: 61,
+                estimated_time: TEN_MINUTES * 61,
+            }
+        );
+        assert_eq!(status.to_string(), "61 blocks / ~610 minutes remaining");
+    }
+
+    #[test]
+    fn given_tip_reaches_timelock_confirmations_then_expired() {
+        let lock_height = BlockHeight::new(100);
+
+        let one_left = TimelockStatus::new(lock_height, BlockHeight::new(170), 72, TEN_MINUTES);
+        let expired = TimelockStatus::new(lock_height, BlockHeight::new(171), 72, TEN_MINUTES);
+
+        assert_eq!(
+            one_left,
+            TimelockStatus::Remaining {
+                blocks: 1,
+                estimated_time: TEN_MINUTES,
+            }
+        );
+        assert_eq!(expired, TimelockStatus::Expired);
+        assert_eq!(expired.to_string(), "expired");
+    }
+
+    #[test]
+    fn given_outdated_tip_below_lock_height_then_whole_timelock_remains() {
+        let status =
+            TimelockStatus::new(BlockHeight::new(100), BlockHeight::new(98), 72, TEN_MINUTES);
+
+        assert_eq!(status, TimelockStatus::not_started(72, TEN_MINUTES));
+    }
+}
@@ -1,18 +1,28 @@
-use crate::bitcoin::timelocks::BlockHeight;
-use crate::bitcoin::{Address, Amount, Transaction};
+use crate::balance::BalanceUpdates;
+use crate::bitcoin::cancel::{anchor_script_pubkey, anchor_witness, ANCHOR_SATISFACTION_WEIGHT};
+use crate::bitcoin::fee_estimator::FeeEstimator;
+use crate::bitcoin::signer::ExternalSigner;
+use crate::bitcoin::timelocks::{BlockHeight, TimelockStatus};
+use crate::bitcoin::{is_fee_too_low, Address, Amount, Signer, Transaction};
 use crate::env;
-use ::bitcoin::util::psbt::PartiallySignedTransaction;
-use ::bitcoin::Txid;
+use crate::jitter::jittered;
+use ::bitcoin::util::psbt::{self, PartiallySignedTransaction};
+use ::bitcoin::{OutPoint, TxOut, Txid};
 use anyhow::{bail, Context, Result};
-use bdk::blockchain::{Blockchain, ElectrumBlockchain, GetTx};
-use bdk::database::BatchDatabase;
-use bdk::electrum_client::{ElectrumApi, GetHistoryRes};
+use bdk::blockchain::electrum::ElectrumBlockchainConfig;
+use bdk::blockchain::{Blockchain, ConfigurableBlockchain, ElectrumBlockchain, GetTx};
+use bdk::database::{BatchDatabase, Database as _};
+use bdk::electrum_client::{ElectrumApi, GetHistoryRes, HeaderNotification, Param};
+use bdk::miniscript::Descriptor;
+use bdk::signer::SignerOrdering;
 use bdk::sled::Tree;
 use bdk::wallet::export::FullyNodedExport;
 use bdk::wallet::AddressIndex;
 use bdk::{FeeRate, KeychainKind, SignOptions, SyncOptions};
-use bitcoin::util::bip32::ExtendedPrivKey;
-use bitcoin::{Network, Script};
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::util::bip32::{ExtendedPrivKey, ExtendedPubKey, Fingerprint};
+use bitcoin::{BlockHash, Network, PrivateKey, Script};
+use futures::Stream;
 use reqwest::Url;
 use rust_decimal::prelude::*;
 use rust_decimal::Decimal;
@@ -20,12 +30,15 @@ use rust_decimal_macros::dec;
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops::ControlFlow;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{watch, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
 
 const SLED_TREE_NAME: &str = "default_tree";
+const SCAN_POSITIONS_TREE_NAME: &str = "scan_positions";
 
 /// Assuming we add a spread of 3% we don't want to pay more than 3% of the
 /// amount for tx fees.
@@ -33,6 +46,10 @@ const MAX_RELATIVE_TX_FEE: Decimal = dec!(0.03);
 const MAX_ABSOLUTE_TX_FEE: Decimal = dec!(100_000);
 const DUST_AMOUNT: u64 = 546;
 
+/// The number of consecutive unused addresses after which a sync stops, the
+/// same as BDK's default.
+pub const DEFAULT_STOP_GAP: usize = 20;
+
 const WALLET: &str = "wallet";
 const WALLET_OLD: &str = "wallet-old";
 
@@ -40,8 +57,16 @@ pub struct Wallet<D = Tree, C = Client> {
     client: Arc<Mutex<C>>,
     wallet: Arc<Mutex<bdk::Wallet<D>>>,
     finality_confirmations: u32,
+    max_electrum_reconnects: u32,
     network: Network,
     target_block: usize,
+    min_relay_fee_rate: FeeRate,
+    funding_min_confirmations: u32,
+    avg_block_time: Duration,
+    balance_updates: BalanceUpdates<Amount>,
+    fee_estimator: Option<Arc<dyn FeeEstimator>>,
+    strict_fee_tolerance: Option<Amount>,
+    scan_positions: Tree,
 }
 
 impl Wallet {
@@ -51,26 +76,54 @@ impl Wallet {
         xprivkey: ExtendedPrivKey,
         env_config: env::Config,
         target_block: usize,
+    ) -> Result<Self> {
+        Self::new_with_electrum_pool(
+            vec![electrum_rpc_url],
+            data_dir,
+            xprivkey,
+            env_config,
+            target_block,
+        )
+        .await
+    }
+
+    /// Same as [`Wallet::new`], but connects to the first of
+    /// `electrum_rpc_urls` that is reachable and not busy, and fails over to
+    /// the others if that server becomes unavailable later on.
+    pub async fn new_with_electrum_pool(
+        electrum_rpc_urls: Vec<Url>,
+        data_dir: impl AsRef<Path>,
+        xprivkey: ExtendedPrivKey,
+        env_config: env::Config,
+        target_block: usize,
     ) -> Result<Self> {
         let data_dir = data_dir.as_ref();
         let wallet_dir = data_dir.join(WALLET);
-        let database = bdk::sled::open(wallet_dir)?.open_tree(SLED_TREE_NAME)?;
+        let sled = bdk::sled::open(wallet_dir)?;
+        let database = sled.open_tree(SLED_TREE_NAME)?;
         let network = env_config.bitcoin_network;
 
-        let wallet = match bdk::Wallet::new(
+        let (wallet, scan_positions) = match bdk::Wallet::new(
             bdk::template::Bip84(xprivkey, KeychainKind::External),
             Some(bdk::template::Bip84(xprivkey, KeychainKind::Internal)),
             network,
             database,
         ) {
-            Ok(w) => w,
+            Ok(w) => (w, sled.open_tree(SCAN_POSITIONS_TREE_NAME)?),
             Err(e) if matches!(e, bdk::Error::ChecksumMismatch) => {
                 Self::migrate(data_dir, xprivkey, network)?
             }
-            err => err?,
+            Err(e) => return Err(e.into()),
         };
 
-        let client = Client::new(electrum_rpc_url, env_config.bitcoin_sync_interval())?;
+        let client = Client::connect_to_pool(
+            &electrum_rpc_urls,
+            env_config.bitcoin_sync_interval(),
+            env_config.bitcoin_sync_stop_gap,
+        )?
+        .with_poll_interval(env_config.bitcoin_poll_interval)
+        .with_reconnect_jitter(env_config.bitcoin_reconnect_jitter);
+        client.ensure_network(network)?;
 
         let network = wallet.network();
 
@@ -78,8 +131,79 @@ impl Wallet {
             client: Arc::new(Mutex::new(client)),
             wallet: Arc::new(Mutex::new(wallet)),
             finality_confirmations: env_config.bitcoin_finality_confirmations,
+            max_electrum_reconnects: env_config.max_electrum_reconnects,
+            network,
+            target_block,
+            min_relay_fee_rate: FeeRate::from_sat_per_vb(
+                env_config.bitcoin_min_relay_fee_rate as f32,
+            ),
+            funding_min_confirmations: env_config.funding_min_confirmations,
+            avg_block_time: env_config.bitcoin_avg_block_time,
+            balance_updates: BalanceUpdates::new(),
+            fee_estimator: None,
+            strict_fee_tolerance: None,
+            scan_positions,
+        })
+    }
+
+    /// Opens a wallet that only knows the account-level extended public key
+    /// and has all of its own inputs signed by the given [`Signer`].
+    ///
+    /// `fingerprint` is the fingerprint of the master key `xpub` was derived
+    /// from, `xpub` itself has to be derived at the BIP84 account level.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new_with_signer(
+        electrum_rpc_url: Url,
+        data_dir: impl AsRef<Path>,
+        xpub: ExtendedPubKey,
+        fingerprint: Fingerprint,
+        signer: Arc<dyn Signer>,
+        env_config: env::Config,
+        target_block: usize,
+    ) -> Result<Self> {
+        let wallet_dir = data_dir.as_ref().join(WALLET);
+        let sled = bdk::sled::open(wallet_dir)?;
+        let database = sled.open_tree(SLED_TREE_NAME)?;
+        let scan_positions = sled.open_tree(SCAN_POSITIONS_TREE_NAME)?;
+        let network = env_config.bitcoin_network;
+
+        let mut wallet = bdk::Wallet::new(
+            bdk::template::Bip84Public(xpub, fingerprint, KeychainKind::External),
+            Some(bdk::template::Bip84Public(
+                xpub,
+                fingerprint,
+                KeychainKind::Internal,
+            )),
+            network,
+            database,
+        )?;
+        add_external_signer(&mut wallet, signer, fingerprint);
+
+        let client = Client::new(
+            electrum_rpc_url,
+            env_config.bitcoin_sync_interval(),
+            env_config.bitcoin_sync_stop_gap,
+        )?
+        .with_poll_interval(env_config.bitcoin_poll_interval)
+        .with_reconnect_jitter(env_config.bitcoin_reconnect_jitter);
+        client.ensure_network(network)?;
+
+        Ok(Self {
+            client: Arc::new(Mutex::new(client)),
+            wallet: Arc::new(Mutex::new(wallet)),
+            finality_confirmations: env_config.bitcoin_finality_confirmations,
+            max_electrum_reconnects: env_config.max_electrum_reconnects,
             network,
             target_block,
+            min_relay_fee_rate: FeeRate::from_sat_per_vb(
+                env_config.bitcoin_min_relay_fee_rate as f32,
+            ),
+            funding_min_confirmations: env_config.funding_min_confirmations,
+            avg_block_time: env_config.bitcoin_avg_block_time,
+            balance_updates: BalanceUpdates::new(),
+            fee_estimator: None,
+            strict_fee_tolerance: None,
+            scan_positions,
         })
     }
 
@@ -91,13 +215,14 @@ impl Wallet {
         data_dir: &Path,
         xprivkey: ExtendedPrivKey,
         network: bitcoin::Network,
-    ) -> Result<bdk::Wallet<Tree>> {
+    ) -> Result<(bdk::Wallet<Tree>, Tree)> {
         let from = data_dir.join(WALLET);
         let to = data_dir.join(WALLET_OLD);
         std::fs::rename(from, to)?;
 
         let wallet_dir = data_dir.join(WALLET);
-        let database = bdk::sled::open(wallet_dir)?.open_tree(SLED_TREE_NAME)?;
+        let sled = bdk::sled::open(wallet_dir)?;
+        let database = sled.open_tree(SLED_TREE_NAME)?;
 
         let wallet = bdk::Wallet::new(
             bdk::template::Bip84(xprivkey, KeychainKind::External),
@@ -106,7 +231,7 @@ impl Wallet {
             database,
         )?;
 
-        Ok(wallet)
+        Ok((wallet, sled.open_tree(SCAN_POSITIONS_TREE_NAME)?))
     }
 
     /// Broadcast the given transaction to the network and emit a log statement
@@ -119,25 +244,96 @@ impl Wallet {
         transaction: Transaction,
         kind: &str,
     ) -> Result<(Txid, Subscription)> {
-        let txid = transaction.txid();
-
         // to watch for confirmations, watching a single output is enough
         let subscription = self
-            .subscribe_to((txid, transaction.output[0].script_pubkey.clone()))
+            .subscribe_to((
+                transaction.txid(),
+                transaction.output[0].script_pubkey.clone(),
+            ))
             .await;
 
-        let client = self.client.lock().await;
-        let blockchain = client.blockchain();
+        let txid = self.publish(transaction, kind).await?;
 
-        blockchain.broadcast(&transaction).with_context(|| {
-            format!("Failed to broadcast Bitcoin {} transaction {}", kind, txid)
-        })?;
+        Ok((txid, subscription))
+    }
 
-        tracing::info!(%txid, %kind, "Published Bitcoin transaction");
+    /// Broadcasts `transaction` like [`Wallet::broadcast`].
+    ///
+    /// If the wallet was built [`with_strict_fees`](Wallet::with_strict_fees)
+    /// the transaction is only broadcast if it pays `intended_fee`, otherwise
+    /// this fails with [`FeeMismatch`].
+    pub async fn broadcast_with_fee(
+        &self,
+        transaction: Transaction,
+        kind: &str,
+        intended_fee: Amount,
+    ) -> Result<(Txid, Subscription)> {
+        self.ensure_intended_fee(&transaction, kind, intended_fee)
+            .await?;
+
+        self.broadcast(transaction, kind).await
+    }
+
+    /// Broadcasts `transaction`, which has an anchor output, like
+    /// [`Wallet::broadcast_with_fee`].
+    ///
+    /// If `intended_fee` falls short of the currently estimated fee rate, the
+    /// fee is bumped via CPFP by spending the anchor output. If the
+    /// transaction is rejected for too low a fee, it is broadcast once more
+    /// together with that child.
+    pub async fn broadcast_with_anchor(
+        &self,
+        transaction: Transaction,
+        kind: &str,
+        intended_fee: Amount,
+    ) -> Result<(Txid, Subscription)> {
+        self.ensure_intended_fee(&transaction, kind, intended_fee)
+            .await?;
+
+        let subscription = self
+            .subscribe_to((
+                transaction.txid(),
+                transaction.output[0].script_pubkey.clone(),
+            ))
+            .await;
+
+        let txid = self
+            .publish_with_anchor(transaction, intended_fee, kind)
+            .await?;
 
         Ok((txid, subscription))
     }
 
+    async fn ensure_intended_fee(
+        &self,
+        transaction: &Transaction,
+        kind: &str,
+        intended_fee: Amount,
+    ) -> Result<()> {
+        let tolerance = match self.strict_fee_tolerance {
+            Some(tolerance) => tolerance,
+            None => return Ok(()),
+        };
+
+        let mut spent_outputs = Vec::with_capacity(transaction.input.len());
+        for input in &transaction.input {
+            let previous_output = input.previous_output;
+            let output = self
+                .get_raw_transaction(previous_output.txid)
+                .await?
+                .output
+                .get(usize::try_from(previous_output.vout)?)
+                .cloned()
+                .with_context(|| format!("Spent output {} does not exist", previous_output))?;
+            spent_outputs.push(output);
+        }
+
+        verify_fee(transaction, &spent_outputs, intended_fee, tolerance)
+            .with_context(|| format!("Refusing to broadcast Bitcoin {} transaction", kind))?;
+
+        Ok(())
+    }
+
     pub async fn get_raw_transaction(&self, txid: Txid) -> Result<Transaction> {
         self.get_tx(txid)
             .await?
@@ -148,31 +344,49 @@ impl Wallet {
     where
         T: Watchable,
     {
-        self.client.lock().await.status_of_script(tx)
+        self.operation().status_of_script(tx).await
     }
 
     pub async fn subscribe_to(&self, tx: impl Watchable + Send + 'static) -> Subscription {
         let txid = tx.id();
         let script = tx.script();
 
-        let sub = self
-            .client
-            .lock()
-            .await
+        let mut client_guard = self.client.lock().await;
+        let poll_interval = client_guard.poll_interval;
+        let reconnect_jitter = client_guard.reconnect_jitter;
+
+        let sub = client_guard
             .subscriptions
             .entry((txid, script.clone()))
             .or_insert_with(|| {
                 let (sender, receiver) = watch::channel(ScriptStatus::Unseen);
+                let (failed_reconnects_sender, failed_reconnects) = watch::channel(0);
                 let client = self.client.clone();
 
                 tokio::spawn(async move {
                     let mut last_status = None;
+                    let mut failed_attempts = 0;
+                    let watched = (txid, script.clone());
 
                     loop {
-                        let new_status = match client.lock().await.status_of_script(&tx) {
-                            Ok(new_status) => new_status,
+                        let status = Client::query_with_fail_over(&client, |client| {
+                            client.status_of_script(&watched)
+                        })
+                        .await;
+                        let new_status = match status {
+                            Ok(new_status) => {
+                                if failed_attempts > 0 {
+                                    failed_attempts = 0;
+                                    let _ = failed_reconnects_sender.send(failed_attempts);
+                                }
+
+                                new_status
+                            }
                             Err(error) => {
-                                tracing::warn!(%txid, "Failed to get status of script: {:#}", error);
+                                failed_attempts += 1;
+                                let _ = failed_reconnects_sender.send(failed_attempts);
+
+                                tracing::warn!(%txid, %failed_attempts, "Failed to get status of script: {:#}", error);
                                 ScriptStatus::Retrying
                             }
                         };
@@ -190,12 +404,19 @@ impl Wallet {
                             }
                         }
 
-                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        if new_status == ScriptStatus::Retrying {
+                            tokio::time::sleep(jittered(poll_interval, reconnect_jitter)).await;
+                        } else {
+                            tokio::time::sleep(poll_interval).await;
+                        }
                     }
                 });
 
                 Subscription {
                     receiver,
+                    failed_reconnects,
+                    max_electrum_reconnects: self.max_electrum_reconnects,
+                    abort_if_electrum_unavailable: false,
                     finality_confirmations: self.finality_confirmations,
                     txid,
                 }
@@ -216,6 +437,71 @@ impl Wallet {
             Err(err_msg) => Err(anyhow::Error::msg(err_msg)),
         }
     }
+
+    /// Sweeps all funds controlled by the WIF encoded private key `wif` into
+    /// a new address of this wallet, paying `fee` for the transaction.
+    ///
+    /// Funds are looked for on the P2WPKH address of compressed keys and on
+    /// the P2PKH address of uncompressed keys.
+    pub async fn sweep_from_wif(&self, wif: &str, fee: Amount) -> Result<Txid> {
+        let key = PrivateKey::from_wif(wif).context("Failed to parse WIF private key")?;
+        ensure_wif_network(key.network, self.network)?;
+
+        let descriptor = if key.compressed {
+            format!("wpkh({})", wif)
+        } else {
+            format!("pkh({})", wif)
+        };
+        let external = bdk::Wallet::new(
+            &descriptor,
+            None,
+            self.network,
+            bdk::database::MemoryDatabase::default(),
+        )?;
+        let address = self.new_address().await?;
+
+        {
+            let client = self.client.lock().await;
+            external
+                .sync(client.blockchain(), SyncOptions::default())
+                .context("Failed to find funds of WIF private key")?;
+        }
+
+        let mut tx_builder = external.build_tx();
+        tx_builder.drain_wallet();
+        tx_builder.drain_to(address.script_pubkey());
+        tx_builder.fee_absolute(fee.to_sat());
+        let (mut psbt, _) = match tx_builder.finish() {
+            Ok(psbt) => psbt,
+            Err(bdk::Error::NoUtxosSelected) => {
+                bail!("No funds to sweep on the address of the WIF private key")
+            }
+            Err(e) => bail!("Failed to build sweep transaction. {:#}", e),
+        };
+
+        if !external.sign(&mut psbt, SignOptions::default())? {
+            bail!("PSBT is not finalized")
+        }
+
+        let (txid, _) = self.broadcast(psbt.extract_tx(), "sweep").await?;
+
+        Ok(txid)
+    }
+}
+
+/// WIF private keys only distinguish mainnet from test networks, the latter
+/// are accepted for testnet, signet and regtest.
+fn ensure_wif_network(wif: Network, wallet: Network) -> Result<()> {
+    let matches = match wif {
+        Network::Bitcoin => wallet == Network::Bitcoin,
+        _ => wallet != Network::Bitcoin,
+    };
+
+    if !matches {
+        bail!(WifNetworkMismatch { wif, wallet })
+    }
+
+    Ok(())
 }
 
 fn print_status_change(txid: Txid, old: Option<ScriptStatus>, new: ScriptStatus) -> ScriptStatus {
@@ -232,15 +518,33 @@ fn print_status_change(txid: Txid, old: Option<ScriptStatus>, new: ScriptStatus)
     new
 }
 
+/// How long to look for a transaction that the user says they broadcast
+/// themselves before refusing to record it as broadcast.
+pub const BROADCAST_SEEN_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Represents a subscription to the status of a given transaction.
 #[derive(Debug, Clone)]
 pub struct Subscription {
     receiver: watch::Receiver<ScriptStatus>,
+    failed_reconnects: watch::Receiver<u32>,
+    max_electrum_reconnects: u32,
+    abort_if_electrum_unavailable: bool,
     finality_confirmations: u32,
     txid: Txid,
 }
 
 impl Subscription {
+    /// Makes waiting on this subscription fail with [`ElectrumUnavailable`]
+    /// once the status could not be fetched `max_electrum_reconnects` times
+    /// in a row.
+    ///
+    /// Only meant for states in which nothing is locked yet. Once funds are
+    /// at stake we keep trying for as long as it takes.
+    pub fn abort_if_electrum_unavailable(mut self) -> Self {
+        self.abort_if_electrum_unavailable = true;
+        self
+    }
+
     pub async fn wait_until_final(&self) -> Result<()> {
         let conf_target = self.finality_confirmations;
         let txid = self.txid;
@@ -272,6 +576,22 @@ impl Subscription {
         self.wait_until(ScriptStatus::has_been_seen).await
     }
 
+    /// Like [`Subscription::wait_until_seen`], but gives up after `timeout`.
+    ///
+    /// Used where the user claims to have broadcast a transaction themselves,
+    /// so we refuse to act on that claim unless the chain backs it up.
+    pub async fn wait_until_seen_within(&self, timeout: Duration) -> Result<()> {
+        tokio::time::timeout(timeout, self.wait_until_seen())
+            .await
+            .with_context(|| {
+                format!(
+                    "Transaction {} was not seen within {}s",
+                    self.txid,
+                    timeout.as_secs()
+                )
+            })?
+    }
+
     pub async fn wait_until_confirmed_with<T>(&self, target: T) -> Result<()>
     where
         u32: PartialOrd<T>,
@@ -281,14 +601,46 @@ impl Subscription {
             .await
     }
 
+    /// A subscription whose status and failed reconnects are whatever is
+    /// sent on the returned senders, for testing code that waits on it.
+    #[cfg(test)]
+    pub(crate) fn stub(
+        max_electrum_reconnects: u32,
+    ) -> (watch::Sender<ScriptStatus>, watch::Sender<u32>, Self) {
+        use ::bitcoin::hashes::Hash;
+
+        let (status_sender, receiver) = watch::channel(ScriptStatus::Unseen);
+        let (failed_reconnects_sender, failed_reconnects) = watch::channel(0);
+        let subscription = Self {
+            receiver,
+            failed_reconnects,
+            max_electrum_reconnects,
+            abort_if_electrum_unavailable: false,
+            finality_confirmations: 1,
+            txid: Txid::all_zeros(),
+        };
+
+        (status_sender, failed_reconnects_sender, subscription)
+    }
+
     async fn wait_until(&self, mut predicate: impl FnMut(&ScriptStatus) -> bool) -> Result<()> {
         let mut receiver = self.receiver.clone();
+        let mut failed_reconnects = self.failed_reconnects.clone();
 
         while !predicate(&receiver.borrow()) {
-            receiver
-                .changed()
-                .await
-                .context("Failed while waiting for next status update")?;
+            let attempts = *failed_reconnects.borrow();
+            if self.abort_if_electrum_unavailable && attempts >= self.max_electrum_reconnects {
+                bail!(ElectrumUnavailable { attempts })
+            }
+
+            tokio::select! {
+                changed = receiver.changed() => {
+                    changed.context("Failed while waiting for next status update")?;
+                }
+                changed = failed_reconnects.changed() => {
+                    changed.context("Failed while waiting for next status update")?;
+                }
+            }
         }
 
         Ok(())
@@ -300,6 +652,20 @@ where
     C: EstimateFeeRate,
     D: BatchDatabase,
 {
+    /// The fee rate to confirm within `target_block` blocks, as estimated by
+    /// the [`FeeEstimator`] of this wallet or else by the Electrum backend,
+    /// but never below the minimum relay fee rate.
+    async fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
+        let fee_rate = match &self.fee_estimator {
+            Some(fee_estimator) => {
+                estimate_feerate_with(fee_estimator.as_ref(), target_block).await?
+            }
+            None => self.client.lock().await.estimate_feerate(target_block)?,
+        };
+
+        Ok(at_least(fee_rate, self.min_relay_fee_rate))
+    }
+
     pub async fn sign_and_finalize(
         &self,
         mut psbt: PartiallySignedTransaction,
@@ -327,8 +693,10 @@ where
             .await
             .get_balance()
             .context("Failed to calculate Bitcoin balance")?;
+        let balance = Amount::from_sat(balance.get_total());
+        self.balance_updates.observe(balance);
 
-        Ok(Amount::from_sat(balance.get_total()))
+        Ok(balance)
     }
 
     pub async fn new_address(&self) -> Result<Address> {
@@ -358,15 +726,95 @@ where
         Ok(Amount::from_sat(fees))
     }
 
+    /// Lists the transactions of this wallet that are not confirmed yet.
+    pub async fn pending_transactions(&self) -> Result<Vec<Txid>> {
+        let pending = self
+            .wallet
+            .lock()
+            .await
+            .list_transactions(false)?
+            .into_iter()
+            .filter(|tx| tx.confirmation_time.is_none())
+            .map(|tx| tx.txid)
+            .collect();
+
+        Ok(pending)
+    }
+
+    /// Builds a partially signed transaction that double-spends all inputs of
+    /// the pending transaction `txid` back to this wallet.
+    ///
+    /// The replacement pays at least the fee of the original transaction plus
+    /// 1 sat/vB, so that nodes accept it in place of the original. Fails if
+    /// `txid` is confirmed already or does not signal replaceability.
+    pub async fn cancel_pending(&self, txid: Txid) -> Result<PartiallySignedTransaction> {
+        let wallet = self.wallet.lock().await;
+        let details = wallet
+            .get_tx(&txid, true)?
+            .with_context(|| format!("Transaction {} is not known to this wallet", txid))?;
+
+        if details.confirmation_time.is_some() {
+            bail!(TransactionConfirmed { txid })
+        }
+
+        let transaction = details
+            .transaction
+            .expect("raw transaction requested from wallet");
+        if !transaction
+            .input
+            .iter()
+            .any(|input| input.sequence.is_rbf())
+        {
+            bail!(TransactionNotReplaceable { txid })
+        }
+
+        let original_fee = details
+            .fee
+            .expect("fees are always present with Electrum backend");
+        let vsize = transaction.vsize();
+        let fee_rate = self.estimate_feerate(self.target_block).await?;
+        let fee = (original_fee + vsize as u64).max(fee_rate.fee_vb(vsize));
+
+        let change = wallet
+            .get_address(AddressIndex::New)
+            .context("Failed to get new Bitcoin address")?
+            .address
+            .script_pubkey();
+
+        let mut tx_builder = wallet.build_tx();
+        for input in &transaction.input {
+            tx_builder
+                .add_utxo(input.previous_output)
+                .with_context(|| {
+                    format!(
+                        "Input {} is not owned by this wallet",
+                        input.previous_output
+                    )
+                })?;
+        }
+        tx_builder.manually_selected_only();
+        tx_builder.drain_to(change);
+        tx_builder.enable_rbf();
+        tx_builder.fee_absolute(fee);
+        let (psbt, _) = tx_builder.finish()?;
+
+        Ok(psbt)
+    }
+
     /// Builds a partially signed transaction
     ///
     /// Ensures that the address script is at output index `0`
     /// for the partially signed transaction.
+    ///
+    /// If a `fee_rate_override` is given it is used instead of the estimated
+    /// fee rate, as long as the resulting fee stays within the maximum
+    /// allowed fee.
     pub async fn send_to_address(
         &self,
         address: Address,
         amount: Amount,
         change_override: Option<Address>,
+        fee_rate_override: Option<FeeRate>,
     ) -> Result<PartiallySignedTransaction> {
         if self.network != address.network {
             bail!("Cannot build PSBT because network of given address is {} but wallet is on network {}", address.network, self.network);
@@ -379,16 +827,28 @@ where
         }
 
         let wallet = self.wallet.lock().await;
-        let client = self.client.lock().await;
-        let fee_rate = client.estimate_feerate(self.target_block)?;
+        let fee_rate = match fee_rate_override {
+            Some(fee_rate) => fee_rate,
+            None => self.estimate_feerate(self.target_block).await?,
+        };
         let script = address.script_pubkey();
 
+        let unconfirmed = unconfirmed_utxos(&wallet, self.funding_min_confirmations)?;
+
         let mut tx_builder = wallet.build_tx();
         tx_builder.add_recipient(script.clone(), amount.to_sat());
+        tx_builder.unspendable(unconfirmed);
         tx_builder.fee_rate(fee_rate);
-        let (psbt, _details) = tx_builder.finish()?;
+        let (psbt, details) = tx_builder.finish()?;
         let mut psbt: PartiallySignedTransaction = psbt;
 
+        if fee_rate_override.is_some() {
+            let fee = details
+                .fee
+                .expect("fees are always present with Electrum backend");
+            ensure_fee_within_maximum(Amount::from_sat(fee), amount)?;
+        }
+
         match psbt.unsigned_tx.output.as_mut_slice() {
             // our primary output is the 2nd one? reverse the vectors
             [_, second_txout] if second_txout.script_pubkey == script => {
@@ -418,6 +878,100 @@ where
         Ok(psbt)
     }
 
+    /// Builds a partially signed transaction that spends exactly the given
+    /// output of this wallet to `address`, paying the fee out of it.
+    ///
+    /// No other UTXOs of the wallet are touched and no change output is
+    /// created.
+    pub async fn sweep_output(
+        &self,
+        outpoint: OutPoint,
+        address: Address,
+    ) -> Result<PartiallySignedTransaction> {
+        if self.network != address.network {
+            bail!("Cannot build PSBT because network of given address is {} but wallet is on network {}", address.network, self.network);
+        }
+
+        let wallet = self.wallet.lock().await;
+        let fee_rate = self.estimate_feerate(self.target_block).await?;
+
+        let mut tx_builder = wallet.build_tx();
+        tx_builder
+            .add_utxo(outpoint)
+            .with_context(|| format!("Output {} is not owned by this wallet", outpoint))?;
+        tx_builder.manually_selected_only();
+        tx_builder.drain_to(address.script_pubkey());
+        tx_builder.fee_rate(fee_rate);
+        let (psbt, _) = tx_builder.finish()?;
+
+        Ok(psbt)
+    }
+
+    /// Builds a partially signed transaction that spends the anchor output of
+    /// `parent` together with UTXOs of this wallet, so that the package of
+    /// both transactions pays `fee_rate`.
+    ///
+    /// `parent_fee` is the fee `parent` pays on its own. The anchor input is
+    /// finalized already, signing only adds the signatures of this wallet.
+    pub async fn spend_anchor(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+        fee_rate: FeeRate,
+    ) -> Result<PartiallySignedTransaction> {
+        let (vout, anchor) = parent
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, output)| output.script_pubkey == anchor_script_pubkey())
+            .with_context(|| format!("Transaction {} has no anchor output", parent.txid()))?;
+        let outpoint = OutPoint::new(parent.txid(), vout as u32);
+        let anchor_input = psbt::Input {
+            witness_utxo: Some(anchor.clone()),
+            non_witness_utxo: Some(parent.clone()),
+            final_script_witness: Some(anchor_witness()),
+            ..Default::default()
+        };
+
+        let wallet = self.wallet.lock().await;
+        let change = wallet
+            .get_address(AddressIndex::New)
+            .context("Failed to get new Bitcoin address")?
+            .address
+            .script_pubkey();
+
+        let build = |fee: Option<u64>| {
+            let mut tx_builder = wallet.build_tx();
+            tx_builder.add_foreign_utxo(
+                outpoint,
+                anchor_input.clone(),
+                ANCHOR_SATISFACTION_WEIGHT,
+            )?;
+            tx_builder.drain_to(change.clone());
+            match fee {
+                Some(fee) => tx_builder.fee_absolute(fee),
+                None => tx_builder.fee_rate(fee_rate),
+            };
+            tx_builder.finish()
+        };
+
+        // The child alone paying `fee_rate` tells us its size, from which we
+        // calculate what it has to pay for the package.
+        let (psbt, details) = build(None)?;
+        let child_fee = details
+            .fee
+            .expect("fees are always present with Electrum backend");
+        let package_fee = child_fee + fee_rate.fee_wu(parent.weight());
+
+        if package_fee.saturating_sub(parent_fee.to_sat()) <= child_fee {
+            return Ok(psbt);
+        }
+
+        let (psbt, _) = build(Some(package_fee - parent_fee.to_sat()))?;
+
+        Ok(psbt)
+    }
+
     /// Calculates the maximum "giveable" amount of this wallet.
     ///
     /// We define this as the maximum amount we can pay to a single output,
@@ -429,14 +983,15 @@ where
         if balance.get_total() < DUST_AMOUNT {
             return Ok(Amount::ZERO);
         }
-        let client = self.client.lock().await;
-        let min_relay_fee = client.min_relay_fee()?.to_sat();
+        let min_relay_fee = self.client.lock().await.min_relay_fee()?.to_sat();
 
         if balance.get_total() < min_relay_fee {
             return Ok(Amount::ZERO);
         }
 
-        let fee_rate = client.estimate_feerate(self.target_block)?;
+        let fee_rate = self.estimate_feerate(self.target_block).await?;
+
+        let unconfirmed = unconfirmed_utxos(&wallet, self.funding_min_confirmations)?;
 
         let mut tx_builder = wallet.build_tx();
 
@@ -444,6 +999,7 @@ where
         tx_builder.drain_to(dummy_script);
         tx_builder.fee_rate(fee_rate);
         tx_builder.drain_wallet();
+        tx_builder.unspendable(unconfirmed);
 
         let response = tx_builder.finish();
         match response {
@@ -467,61 +1023,300 @@ where
         weight: usize,
         transfer_amount: bitcoin::Amount,
     ) -> Result<bitcoin::Amount> {
-        let client = self.client.lock().await;
-        let fee_rate = client.estimate_feerate(self.target_block)?;
-        let min_relay_fee = client.min_relay_fee()?;
+        self.operation().estimate_fee(weight, transfer_amount).await
+    }
 
-        estimate_fee(weight, transfer_amount, fee_rate, min_relay_fee)
+    /// Same as [`Wallet::estimate_fee`] unless a `fee_rate_override` is
+    /// given, in which case the fee is calculated from the override instead.
+    ///
+    /// In contrast to an estimated fee, a fee resulting from an override is
+    /// not capped to the maximum allowed fee but rejected if it exceeds it.
+    pub async fn estimate_fee_with_override(
+        &self,
+        weight: usize,
+        transfer_amount: bitcoin::Amount,
+        fee_rate_override: Option<FeeRate>,
+    ) -> Result<bitcoin::Amount> {
+        self.operation()
+            .estimate_fee_with_override(weight, transfer_amount, fee_rate_override)
+            .await
     }
 }
 
-fn estimate_fee(
-    weight: usize,
-    transfer_amount: Amount,
-    fee_rate: FeeRate,
-    min_relay_fee: Amount,
-) -> Result<Amount> {
-    if transfer_amount.to_sat() <= 546 {
-        bail!("Amounts needs to be greater than Bitcoin dust amount.")
+impl<D, C> Wallet<D, C> {
+    /// Estimates fee rates with `fee_estimator` instead of the Electrum
+    /// backend.
+    pub fn with_fee_estimator(mut self, fee_estimator: impl FeeEstimator + 'static) -> Self {
+        self.fee_estimator = Some(Arc::new(fee_estimator));
+        self
     }
-    let fee_rate_svb = fee_rate.as_sat_per_vb();
-    if fee_rate_svb <= 0.0 {
-        bail!("Fee rate needs to be > 0")
+
+    /// Makes [`Wallet::broadcast_with_fee`] refuse transactions whose fee
+    /// differs from the intended fee by more than `tolerance`.
+    ///
+    /// Meant for tests and audits, fees are not checked by default.
+    pub fn with_strict_fees(mut self, tolerance: Amount) -> Self {
+        self.strict_fee_tolerance = Some(tolerance);
+        self
     }
-    if fee_rate_svb > 100_000_000.0 || min_relay_fee.to_sat() > 100_000_000 {
-        bail!("A fee_rate or min_relay_fee of > 1BTC does not make sense")
+
+    /// Starts an operation that may query the Electrum backend several times.
+    ///
+    /// Responses are cached for as long as the returned [`Operation`] lives,
+    /// so repeated queries for the same fee rate or script within the
+    /// operation hit the backend only once. The cache is dropped together
+    /// with the operation, hence a later operation never sees stale fee
+    /// estimates or confirmation counts.
+    pub fn operation(&self) -> Operation<'_, D, C> {
+        Operation {
+            wallet: self,
+            cache: ResponseCache::default(),
+        }
     }
+}
 
-    let min_relay_fee = if min_relay_fee.to_sat() == 0 {
-        // if min_relay_fee is 0 we don't fail, we just set it to 1 satoshi;
-        Amount::ONE_SAT
-    } else {
-        min_relay_fee
-    };
+/// A single operation against the Electrum backend, see [`Wallet::operation`].
+///
+/// The locks of the wallet are only taken for each query, never across an
+/// `.await`, so other tasks can use the wallet while the operation lives.
+pub struct Operation<'a, D, C> {
+    wallet: &'a Wallet<D, C>,
+    cache: ResponseCache,
+}
 
-    let weight = Decimal::from(weight);
-    let weight_factor = dec!(4.0);
-    let fee_rate = Decimal::from_f32(fee_rate_svb).context("Failed to parse fee rate")?;
+#[derive(Debug, Default)]
+struct ResponseCache {
+    fee_rate: Option<FeeRate>,
+    min_relay_fee: Option<Amount>,
+    /// The status of each script together with the chain tip it was fetched
+    /// at, so confirmation counts and the tip stay consistent.
+    script_status: HashMap<(Txid, Script), (ScriptStatus, BlockHeight)>,
+}
 
-    let sats_per_vbyte = weight / weight_factor * fee_rate;
+impl<'a, D, C> Operation<'a, D, C>
+where
+    C: EstimateFeeRate,
+    D: BatchDatabase,
+{
+    /// See [`Wallet::estimate_fee`].
+    pub async fn estimate_fee(&mut self, weight: usize, transfer_amount: Amount) -> Result<Amount> {
+        let fee_rate = self.fee_rate().await?;
+        let min_relay_fee = self.min_relay_fee().await?;
 
-    tracing::debug!(
-        %weight,
-        %fee_rate,
-        %sats_per_vbyte,
-        "Estimated fee for transaction",
-    );
+        estimate_fee(weight, transfer_amount, fee_rate, min_relay_fee)
+    }
 
-    let transfer_amount = Decimal::from(transfer_amount.to_sat());
-    let max_allowed_fee = transfer_amount * MAX_RELATIVE_TX_FEE;
-    let min_relay_fee = Decimal::from(min_relay_fee.to_sat());
+    /// See [`Wallet::estimate_fee_with_override`].
+    pub async fn estimate_fee_with_override(
+        &mut self,
+        weight: usize,
+        transfer_amount: Amount,
+        fee_rate_override: Option<FeeRate>,
+    ) -> Result<Amount> {
+        let fee_rate = match fee_rate_override {
+            Some(fee_rate) => fee_rate,
+            None => return self.estimate_fee(weight, transfer_amount).await,
+        };
+        let min_relay_fee = self.min_relay_fee().await?;
 
-    let recommended_fee = if sats_per_vbyte < min_relay_fee {
-        tracing::warn!(
-            "Estimated fee of {} is smaller than the min relay fee, defaulting to min relay fee {}",
-            sats_per_vbyte,
-            min_relay_fee
-        );
+        fee_from_override(weight, transfer_amount, fee_rate, min_relay_fee)
+    }
+
+    async fn fee_rate(&mut self) -> Result<FeeRate> {
+        if let Some(fee_rate) = self.cache.fee_rate {
+            return Ok(fee_rate);
+        }
+
+        let fee_rate = match &self.wallet.fee_estimator {
+            Some(fee_estimator) => {
+                estimate_feerate_with(fee_estimator.as_ref(), self.wallet.target_block).await?
+            }
+            None => self
+                .wallet
+                .client
+                .lock()
+                .await
+                .estimate_feerate(self.wallet.target_block)?,
+        };
+        let fee_rate = at_least(fee_rate, self.wallet.min_relay_fee_rate);
+        self.cache.fee_rate = Some(fee_rate);
+
+        Ok(fee_rate)
+    }
+
+    async fn min_relay_fee(&mut self) -> Result<Amount> {
+        if let Some(min_relay_fee) = self.cache.min_relay_fee {
+            return Ok(min_relay_fee);
+        }
+
+        let min_relay_fee = self.wallet.client.lock().await.min_relay_fee()?;
+        self.cache.min_relay_fee = Some(min_relay_fee);
+
+        Ok(min_relay_fee)
+    }
+}
+
+impl<'a, D> Operation<'a, D, Client> {
+    /// See [`Wallet::status_of_script`].
+    pub async fn status_of_script<T>(&mut self, tx: &T) -> Result<ScriptStatus>
+    where
+        T: Watchable,
+    {
+        let (status, _) = self.status_and_tip(tx).await?;
+
+        Ok(status)
+    }
+
+    /// See [`Wallet::timelock_status`].
+    pub async fn timelock_status<T>(
+        &mut self,
+        tx: &T,
+        timelock: impl Into<u32>,
+    ) -> Result<TimelockStatus>
+    where
+        T: Watchable,
+    {
+        let timelock = timelock.into();
+        let avg_block_time = self.wallet.avg_block_time;
+
+        let status = match self.status_and_tip(tx).await? {
+            (ScriptStatus::Confirmed(confirmed), latest_block) => {
+                let lock_height = u32::from(latest_block) - confirmed.depth;
+
+                TimelockStatus::new(
+                    BlockHeight::new(lock_height),
+                    latest_block,
+                    timelock,
+                    avg_block_time,
+                )
+            }
+            (ScriptStatus::Unseen | ScriptStatus::InMempool | ScriptStatus::Retrying, _) => {
+                TimelockStatus::not_started(timelock, avg_block_time)
+            }
+        };
+
+        Ok(status)
+    }
+
+    async fn status_and_tip<T>(&mut self, tx: &T) -> Result<(ScriptStatus, BlockHeight)>
+    where
+        T: Watchable,
+    {
+        let key = (tx.id(), tx.script());
+
+        if let Some(status_and_tip) = self.cache.script_status.get(&key) {
+            return Ok(*status_and_tip);
+        }
+
+        let status_and_tip = Client::query_with_fail_over(&self.wallet.client, |client| {
+            let status = client.status_of_script(tx)?;
+
+            Ok((status, client.tip.height()))
+        })
+        .await?;
+        self.cache.script_status.insert(key, status_and_tip);
+
+        Ok(status_and_tip)
+    }
+}
+
+/// The vsize of transactions that fit into a block.
+const MAX_BLOCK_VSIZE: u64 = 1_000_000;
+
+/// Estimates in how many blocks a transaction paying `fee_rate` confirms,
+/// assuming the transactions paying more than it in `histogram` get mined
+/// first.
+fn blocks_until_confirmed(histogram: &[(f64, u64)], fee_rate: f64) -> u16 {
+    let vsize_ahead = histogram
+        .iter()
+        .filter(|(histogram_fee_rate, _)| *histogram_fee_rate > fee_rate)
+        .map(|(_, vsize)| vsize)
+        .sum::<u64>();
+
+    u16::try_from(vsize_ahead / MAX_BLOCK_VSIZE + 1).unwrap_or(u16::MAX)
+}
+
+/// The confirmation targets in blocks [`Wallet::fee_scenarios`] reports on.
+pub const FEE_SCENARIO_TARGETS: [usize; 3] = [1, 3, 6];
+
+/// The cost and expected confirmation time of a transaction when paying the
+/// fee rate estimated for `target_block`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeScenario {
+    pub target_block: usize,
+    pub fee: Amount,
+    pub blocks_until_confirmed: u16,
+    pub estimated_time: Duration,
+}
+
+fn fee_scenario(
+    target_block: usize,
+    weight: usize,
+    transfer_amount: Amount,
+    fee_rate: FeeRate,
+    min_relay_fee: Amount,
+    histogram: &[(f64, u64)],
+    avg_block_time: Duration,
+) -> Result<FeeScenario> {
+    let fee = estimate_fee(weight, transfer_amount, fee_rate, min_relay_fee)?;
+    let blocks_until_confirmed =
+        blocks_until_confirmed(histogram, f64::from(fee_rate.as_sat_per_vb()));
+
+    Ok(FeeScenario {
+        target_block,
+        fee,
+        blocks_until_confirmed,
+        estimated_time: avg_block_time * u32::from(blocks_until_confirmed),
+    })
+}
+
+fn estimate_fee(
+    weight: usize,
+    transfer_amount: Amount,
+    fee_rate: FeeRate,
+    min_relay_fee: Amount,
+) -> Result<Amount> {
+    if transfer_amount.to_sat() <= 546 {
+        bail!("Amounts needs to be greater than Bitcoin dust amount.")
+    }
+    let fee_rate_svb = fee_rate.as_sat_per_vb();
+    if fee_rate_svb <= 0.0 {
+        bail!("Fee rate needs to be > 0")
+    }
+    if fee_rate_svb > 100_000_000.0 || min_relay_fee.to_sat() > 100_000_000 {
+        bail!("A fee_rate or min_relay_fee of > 1BTC does not make sense")
+    }
+
+    let min_relay_fee = if min_relay_fee.to_sat() == 0 {
+        // if min_relay_fee is 0 we don't fail, we just set it to 1 satoshi;
+        Amount::ONE_SAT
+    } else {
+        min_relay_fee
+    };
+
+    let weight = Decimal::from(weight);
+    let weight_factor = dec!(4.0);
+    let fee_rate = Decimal::from_f32(fee_rate_svb).context("Failed to parse fee rate")?;
+
+    let sats_per_vbyte = weight / weight_factor * fee_rate;
+
+    tracing::debug!(
+        %weight,
+        %fee_rate,
+        %sats_per_vbyte,
+        "Estimated fee for transaction",
+    );
+
+    let transfer_amount = Decimal::from(transfer_amount.to_sat());
+    let max_allowed_fee = transfer_amount * MAX_RELATIVE_TX_FEE;
+    let min_relay_fee = Decimal::from(min_relay_fee.to_sat());
+
+    let recommended_fee = if sats_per_vbyte < min_relay_fee {
+        tracing::warn!(
+            "Estimated fee of {} is smaller than the min relay fee, defaulting to min relay fee {}",
+            sats_per_vbyte,
+            min_relay_fee
+        );
         min_relay_fee.to_u64()
     } else if sats_per_vbyte > max_allowed_fee && sats_per_vbyte > MAX_ABSOLUTE_TX_FEE {
         tracing::warn!(
@@ -545,715 +1340,3555 @@ fn estimate_fee(
     Ok(amount)
 }
 
-impl<D> Wallet<D>
+fn fee_from_override(
+    weight: usize,
+    transfer_amount: Amount,
+    fee_rate: FeeRate,
+    min_relay_fee: Amount,
+) -> Result<Amount> {
+    if transfer_amount.to_sat() <= DUST_AMOUNT {
+        bail!("Amounts needs to be greater than Bitcoin dust amount.")
+    }
+    let fee_rate_svb = fee_rate.as_sat_per_vb();
+    if fee_rate_svb <= 0.0 {
+        bail!("Fee rate needs to be > 0")
+    }
+
+    let weight = Decimal::from(weight);
+    let fee_rate = Decimal::from_f32(fee_rate_svb).context("Failed to parse fee rate")?;
+    let sats_per_vbyte = weight / dec!(4.0) * fee_rate;
+
+    let fee = sats_per_vbyte
+        .to_u64()
+        .map(Amount::from_sat)
+        .context("Could not calculate transaction fee")?
+        .max(min_relay_fee);
+
+    ensure_fee_within_maximum(fee, transfer_amount)?;
+
+    Ok(fee)
+}
+
+/// The UTXOs of `wallet` with less than `min_confirmations` confirmations,
+/// judged by the height of the last sync.
+fn unconfirmed_utxos<D>(wallet: &bdk::Wallet<D>, min_confirmations: u32) -> Result<Vec<OutPoint>>
 where
     D: BatchDatabase,
 {
-    pub async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>> {
-        let client = self.client.lock().await;
-        let tx = client.get_tx(&txid)?;
-
-        Ok(tx)
+    if min_confirmations == 0 {
+        return Ok(Vec::new());
     }
 
-    pub async fn sync(&self) -> Result<()> {
-        let client = self.client.lock().await;
-        let blockchain = client.blockchain();
-        let sync_opts = SyncOptions::default();
-        self.wallet
-            .lock()
-            .await
-            .sync(blockchain, sync_opts)
-            .context("Failed to sync balance of Bitcoin wallet")?;
+    let synced_height = wallet
+        .database()
+        .get_sync_time()?
+        .map(|sync_time| sync_time.block_time.height);
+    let confirmation_heights = wallet
+        .list_transactions(false)?
+        .into_iter()
+        .map(|tx| (tx.txid, tx.confirmation_time.map(|time| time.height)))
+        .collect::<HashMap<_, _>>();
+
+    let unconfirmed = wallet
+        .list_unspent()?
+        .into_iter()
+        .filter(|utxo| {
+            let confirmations = match (
+                synced_height,
+                confirmation_heights
+                    .get(&utxo.outpoint.txid)
+                    .copied()
+                    .flatten(),
+            ) {
+                (Some(synced_height), Some(height)) => {
+                    Confirmed::from_inclusion_and_latest_block(height, synced_height)
+                        .confirmations()
+                }
+                _ => 0,
+            };
 
-        Ok(())
+            confirmations < min_confirmations
+        })
+        .map(|utxo| utxo.outpoint)
+        .collect();
+
+    Ok(unconfirmed)
+}
+
+async fn estimate_feerate_with(
+    fee_estimator: &dyn FeeEstimator,
+    target_block: usize,
+) -> Result<FeeRate> {
+    let target = u16::try_from(target_block).context("Target block is out of range")?;
+    let sat_per_vb = fee_estimator.sat_per_vb(target).await?;
+
+    // we do not expect fees being that high.
+    #[allow(clippy::cast_possible_truncation)]
+    Ok(FeeRate::from_sat_per_vb(sat_per_vb as f32))
+}
+
+/// Raises an estimated fee rate to the configured floor, transactions paying
+/// less would not be relayed.
+fn at_least(fee_rate: FeeRate, floor: FeeRate) -> FeeRate {
+    if fee_rate < floor {
+        floor
+    } else {
+        fee_rate
     }
 }
 
-impl<D, C> Wallet<D, C> {
-    // TODO: Get rid of this by changing bounds on bdk::Wallet
-    pub fn get_network(&self) -> bitcoin::Network {
-        self.network
+/// Runs `attempt` with the fee rate the wallet estimates (`None`). If it is
+/// rejected because the fee is too low, runs it once more with twice
+/// `fee_rate`.
+async fn retry_with_bumped_fee<T, F, Fut>(fee_rate: FeeRate, mut attempt: F) -> Result<T>
+where
+    F: FnMut(Option<FeeRate>) -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    match attempt(None).await {
+        Err(error) if is_fee_too_low(&error) => {
+            let bumped = FeeRate::from_sat_per_vb(fee_rate.as_sat_per_vb() * 2.0);
+            tracing::warn!(
+                sats_per_vbyte = bumped.as_sat_per_vb(),
+                "Transaction was rejected for too low a fee, retrying at a higher fee rate: {:#}",
+                error
+            );
+
+            attempt(Some(bumped)).await
+        }
+        result => result,
     }
 }
 
-pub trait EstimateFeeRate {
-    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate>;
-    fn min_relay_fee(&self) -> Result<bitcoin::Amount>;
+fn ensure_fee_within_maximum(fee: Amount, transfer_amount: Amount) -> Result<()> {
+    let max_fee = (Decimal::from(transfer_amount.to_sat()) * MAX_RELATIVE_TX_FEE)
+        .min(MAX_ABSOLUTE_TX_FEE)
+        .to_u64()
+        .map(Amount::from_sat)
+        .context("Could not calculate maximum transaction fee")?;
+
+    if fee > max_fee {
+        bail!(FeeAboveMaximum {
+            fee,
+            max_fee,
+            amount: transfer_amount
+        })
+    }
+
+    Ok(())
 }
 
-#[cfg(test)]
-pub struct StaticFeeRate {
-    fee_rate: FeeRate,
-    min_relay_fee: bitcoin::Amount,
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Fee of {fee} exceeds the maximum allowed fee of {max_fee} for transferring {amount}")]
+pub struct FeeAboveMaximum {
+    pub fee: Amount,
+    pub max_fee: Amount,
+    pub amount: Amount,
 }
 
-#[cfg(test)]
-impl EstimateFeeRate for StaticFeeRate {
-    fn estimate_feerate(&self, _target_block: usize) -> Result<FeeRate> {
-        Ok(self.fee_rate)
+fn add_external_signer<D>(
+    wallet: &mut bdk::Wallet<D>,
+    signer: Arc<dyn Signer>,
+    fingerprint: Fingerprint,
+) where
+    D: BatchDatabase,
+{
+    for keychain in [KeychainKind::External, KeychainKind::Internal] {
+        wallet.add_signer(
+            keychain,
+            SignerOrdering::default(),
+            Arc::new(ExternalSigner::new(signer.clone(), fingerprint)),
+        );
     }
+}
 
-    fn min_relay_fee(&self) -> Result<bitcoin::Amount> {
-        Ok(self.min_relay_fee)
-    }
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Electrum server follows the chain with genesis block {genesis}, not the one of {network}")]
+pub struct WrongChain {
+    pub network: Network,
+    pub genesis: BlockHash,
 }
 
-#[cfg(test)]
-#[derive(Debug)]
-pub struct WalletBuilder {
-    utxo_amount: u64,
-    sats_per_vb: f32,
-    min_relay_fee_sats: u64,
-    key: bitcoin::util::bip32::ExtendedPrivKey,
-    num_utxos: u8,
+#[derive(Clone, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Electrum server {url} is busy: {response}")]
+pub struct ElectrumServerBusy {
+    pub url: Url,
+    pub response: String,
 }
 
-#[cfg(test)]
-impl WalletBuilder {
-    /// Creates a new, funded wallet with sane default fees.
-    ///
-    /// Unless you are testing things related to fees, this is likely what you
-    /// want.
-    pub fn new(amount: u64) -> Self {
-        WalletBuilder {
-            utxo_amount: amount,
-            sats_per_vb: 1.0,
-            min_relay_fee_sats: 1000,
-            key: "tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m".parse().unwrap(),
-            num_utxos: 1,
-        }
-    }
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Wallet holds {have} but {need} are needed")]
+pub struct InsufficientFunds {
+    pub have: Amount,
+    pub need: Amount,
+}
 
-    pub fn with_zero_fees(self) -> Self {
-        Self {
-            sats_per_vb: 0.0,
-            min_relay_fee_sats: 0,
-            ..self
-        }
-    }
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Balance of {balance} did not reach {target} within {timeout:?}")]
+pub struct BalanceNotReached {
+    pub balance: Amount,
+    pub target: Amount,
+    pub timeout: Duration,
+}
 
-    pub fn with_fees(self, sats_per_vb: f32, min_relay_fee_sats: u64) -> Self {
-        Self {
-            sats_per_vb,
-            min_relay_fee_sats,
-            ..self
-        }
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Transaction {txid} pays a fee of {actual} instead of the intended {intended}")]
+pub struct FeeMismatch {
+    pub txid: Txid,
+    pub intended: Amount,
+    pub actual: Amount,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Transaction {txid} is confirmed already")]
+pub struct TransactionConfirmed {
+    pub txid: Txid,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Transaction {txid} does not signal replaceability")]
+pub struct TransactionNotReplaceable {
+    pub txid: Txid,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("WIF private key is for network {wif} but wallet is on network {wallet}")]
+pub struct WifNetworkMismatch {
+    pub wif: Network,
+    pub wallet: Network,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Electrum server unavailable after {attempts} failed reconnect attempts")]
+pub struct ElectrumUnavailable {
+    pub attempts: u32,
+}
+
+impl<D> Wallet<D>
+where
+    D: BatchDatabase,
+{
+    pub async fn get_tx(&self, txid: Txid) -> Result<Option<Transaction>> {
+        let client = self.client.lock().await;
+        let tx = client.get_tx(&txid)?;
+
+        Ok(tx)
     }
 
-    pub fn with_key(self, key: bitcoin::util::bip32::ExtendedPrivKey) -> Self {
-        Self { key, ..self }
+    /// Returns the id of the transaction spending `outpoint`, `None` if the
+    /// output is unspent. `script` is the script of the output.
+    ///
+    /// Transactions in the mempool count as spending the output.
+    pub async fn spending_transaction(
+        &self,
+        outpoint: OutPoint,
+        script: &Script,
+    ) -> Result<Option<Txid>> {
+        let client = self.client.lock().await;
+
+        client.spender_of(outpoint, script)
     }
 
-    pub fn with_num_utxos(self, number: u8) -> Self {
-        Self {
-            num_utxos: number,
-            ..self
+    /// Estimates in how many blocks the transaction with `txid` confirms,
+    /// given the fee histogram of the Electrum server's mempool.
+    ///
+    /// Returns `0` if the transaction is already confirmed.
+    pub async fn estimate_confirmation_blocks(&self, txid: Txid) -> Result<u16> {
+        let transaction = self.get_raw_transaction(txid).await?;
+        let script = transaction
+            .output
+            .first()
+            .context("Transaction has no outputs")?
+            .script_pubkey
+            .clone();
+
+        match self.status_of_script(&(txid, script)).await? {
+            ScriptStatus::Confirmed(_) => return Ok(0),
+            ScriptStatus::Unseen => bail!("Transaction {} is not in the mempool", txid),
+            ScriptStatus::InMempool | ScriptStatus::Retrying => {}
         }
-    }
 
-    pub fn build(self) -> Wallet<bdk::database::MemoryDatabase, StaticFeeRate> {
-        use bdk::database::{BatchOperations, MemoryDatabase, SyncTime};
-        use bdk::{testutils, BlockTime};
+        let client = self.client.lock().await;
 
-        let descriptors = testutils!(@descriptors (&format!("wpkh({}/*)", self.key)));
+        let mut input_value = 0;
+        for input in &transaction.input {
+            let previous_output = input.previous_output;
+            let previous_transaction =
+                client.get_tx(&previous_output.txid)?.with_context(|| {
+                    format!("Could not get raw tx with id: {}", previous_output.txid)
+                })?;
+
+            input_value += previous_transaction
+                .output
+                .get(previous_output.vout as usize)
+                .context("Input spends an output that does not exist")?
+                .value;
+        }
+        let output_value = transaction
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        let fee = input_value
+            .checked_sub(output_value)
+            .context("Transaction spends more than its inputs")?;
+        let fee_rate = fee as f64 / (transaction.weight() as f64 / 4.0);
 
-        let mut database = MemoryDatabase::new();
+        let histogram = client.fee_histogram()?;
 
-        for index in 0..self.num_utxos {
-            bdk::populate_test_db!(
-                &mut database,
-                testutils! {
-                    @tx ( (@external descriptors, index as u32) => self.utxo_amount ) (@confirmations 1)
-                },
-                Some(100)
-            );
-        }
-        let block_time = bdk::BlockTime {
-            height: 100,
-            timestamp: 0,
-        };
-        let sync_time = SyncTime { block_time };
-        database.set_sync_time(sync_time).unwrap();
+        Ok(blocks_until_confirmed(&histogram, fee_rate))
+    }
+
+    /// The fee a transaction of `weight` transferring `transfer_amount`, e.g.
+    /// the refund or redeem transaction of a swap, would pay at each of the
+    /// [`FEE_SCENARIO_TARGETS`] and how long it would take to confirm given
+    /// the fee histogram of the Electrum server's mempool.
+    ///
+    /// Helps deciding whether to publish a transaction now or wait for lower
+    /// fees while a timelock is still far from expiring.
+    pub async fn fee_scenarios(
+        &self,
+        weight: usize,
+        transfer_amount: Amount,
+    ) -> Result<Vec<FeeScenario>> {
+        let (min_relay_fee, histogram) = {
+            let client = self.client.lock().await;
 
-        let wallet = bdk::Wallet::new(&descriptors.0, None, Network::Regtest, database).unwrap();
+            (client.min_relay_fee()?, client.fee_histogram()?)
+        };
 
-        Wallet {
-            client: Arc::new(Mutex::new(StaticFeeRate {
-                fee_rate: FeeRate::from_sat_per_vb(self.sats_per_vb),
-                min_relay_fee: bitcoin::Amount::from_sat(self.min_relay_fee_sats),
-            })),
-            wallet: Arc::new(Mutex::new(wallet)),
-            finality_confirmations: 1,
-            network: Network::Regtest,
-            target_block: 1,
+        let mut scenarios = Vec::with_capacity(FEE_SCENARIO_TARGETS.len());
+        for &target_block in FEE_SCENARIO_TARGETS.iter() {
+            let fee_rate = self.estimate_feerate(target_block).await?;
+
+            scenarios.push(fee_scenario(
+                target_block,
+                weight,
+                transfer_amount,
+                fee_rate,
+                min_relay_fee,
+                &histogram,
+                self.avg_block_time,
+            )?);
         }
+
+        Ok(scenarios)
     }
-}
 
-/// Defines a watchable transaction.
-///
-/// For a transaction to be watchable, we need to know two things: Its
-/// transaction ID and the specific output script that is going to change.
-/// A transaction can obviously have multiple outputs but our protocol purposes,
-/// we are usually interested in a specific one.
-pub trait Watchable {
-    fn id(&self) -> Txid;
-    fn script(&self) -> Script;
-}
+    /// Reports how far `timelock`, relative to the confirmation of `tx`, is
+    /// from expiring.
+    ///
+    /// The whole timelock remains as long as `tx` is not confirmed.
+    pub async fn timelock_status<T>(
+        &self,
+        tx: &T,
+        timelock: impl Into<u32>,
+    ) -> Result<TimelockStatus>
+    where
+        T: Watchable,
+    {
+        self.operation().timelock_status(tx, timelock).await
+    }
 
-impl Watchable for (Txid, Script) {
-    fn id(&self) -> Txid {
-        self.0
+    pub async fn sync(&self) -> Result<()> {
+        let client = self.client.lock().await;
+        let blockchain = client.blockchain();
+        let sync_opts = SyncOptions::default();
+        self.wallet
+            .lock()
+            .await
+            .sync(blockchain, sync_opts)
+            .context("Failed to sync balance of Bitcoin wallet")?;
+
+        self.balance().await?;
+
+        Ok(())
     }
 
-    fn script(&self) -> Script {
-        self.1.clone()
+    /// Syncs the wallet every `interval` until the returned [`BackgroundSync`]
+    /// is stopped or dropped, so that [`Wallet::balance`] reflects new
+    /// transactions without calling [`Wallet::sync`] first.
+    ///
+    /// The background sync does not keep the wallet alive, it ends once the
+    /// wallet is dropped.
+    pub fn sync_continuously(self: &Arc<Self>, interval: Duration) -> BackgroundSync {
+        let wallet = Arc::downgrade(self);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let wallet = match wallet.upgrade() {
+                    Some(wallet) => wallet,
+                    None => return,
+                };
+                if let Err(error) = wallet.sync().await {
+                    tracing::warn!(
+                        "Failed to sync Bitcoin wallet in the background: {:#}",
+                        error
+                    );
+                }
+                drop(wallet);
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        BackgroundSync { task }
     }
 }
 
-pub struct Client {
-    electrum: bdk::electrum_client::Client,
-    blockchain: ElectrumBlockchain,
-    latest_block_height: BlockHeight,
-    last_sync: Instant,
-    sync_interval: Duration,
-    script_history: BTreeMap<Script, Vec<GetHistoryRes>>,
-    subscriptions: HashMap<(Txid, Script), Subscription>,
-}
+impl<D, C> Wallet<D, C>
+where
+    C: EstimateFeeRate + BroadcastTransaction,
+    D: BatchDatabase,
+{
+    /// Publishes `transaction` and emits a log statement if done so
+    /// successfully.
+    async fn publish(&self, transaction: Transaction, kind: &str) -> Result<Txid> {
+        let txid = transaction.txid();
 
-impl Client {
-    fn new(electrum_rpc_url: Url, interval: Duration) -> Result<Self> {
-        let config = bdk::electrum_client::ConfigBuilder::default()
-            .retry(5)
-            .build();
-        let electrum = bdk::electrum_client::Client::from_config(electrum_rpc_url.as_str(), config)
-            .context("Failed to initialize Electrum RPC client")?;
-        // Initially fetch the latest block for storing the height.
-        // We do not act on this subscription after this call.
-        let latest_block = electrum
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?;
+        self.client
+            .lock()
+            .await
+            .broadcast(&transaction)
+            .with_context(|| {
+                format!("Failed to broadcast Bitcoin {} transaction {}", kind, txid)
+            })?;
 
-        let client = bdk::electrum_client::Client::new(electrum_rpc_url.as_str())
-            .context("Failed to initialize Electrum RPC client")?;
-        let blockchain = ElectrumBlockchain::from(client);
-        let last_sync = Instant::now()
-            .checked_sub(interval)
-            .expect("no underflow since block time is only 600 secs");
+        tracing::info!(%txid, %kind, "Published Bitcoin transaction");
 
-        Ok(Self {
-            electrum,
-            blockchain,
-            latest_block_height: BlockHeight::try_from(latest_block)?,
-            last_sync,
-            sync_interval: interval,
-            script_history: Default::default(),
-            subscriptions: Default::default(),
-        })
+        Ok(txid)
     }
 
-    fn blockchain(&self) -> &ElectrumBlockchain {
-        &self.blockchain
-    }
+    /// Publishes `parent`, which pays `parent_fee`, and bumps its fee via
+    /// [`Wallet::bump_via_anchor`].
+    ///
+    /// If `parent` is rejected for too low a fee, the child spending its
+    /// anchor output is built right away and both are published as a package.
+    async fn publish_with_anchor(
+        &self,
+        parent: Transaction,
+        parent_fee: Amount,
+        kind: &str,
+    ) -> Result<Txid> {
+        let txid = match self.publish(parent.clone(), kind).await {
+            Ok(txid) => txid,
+            Err(error) if is_fee_too_low(&error) => {
+                tracing::warn!(
+                    "Bitcoin {} transaction was rejected for too low a fee, publishing it together with a spend of its anchor output: {:#}",
+                    kind,
+                    error
+                );
+
+                let txid = parent.txid();
+                let fee_rate = self.estimate_feerate(self.target_block).await?;
+                let psbt = self.spend_anchor(&parent, parent_fee, fee_rate).await?;
+                let child = self.sign_and_finalize(psbt).await?;
+                let child_txid = child.txid();
+
+                self.client
+                    .lock()
+                    .await
+                    .broadcast_package(&[parent, child])
+                    .with_context(|| {
+                        format!(
+                            "Failed to broadcast Bitcoin {} transaction {} together with anchor spend {}",
+                            kind, txid, child_txid
+                        )
+                    })?;
+
+                tracing::info!(%txid, %child_txid, %kind, "Published Bitcoin transaction together with a spend of its anchor output");
+
+                return Ok(txid);
+            }
+            Err(error) => return Err(error),
+        };
 
-    fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, bdk::Error> {
-        self.blockchain.get_tx(txid)
+        // The transaction is published already, failing to bump its fee must not fail
+        // publishing it
+        if let Err(error) = self.bump_via_anchor(&parent, parent_fee).await {
+            tracing::warn!(
+                "Failed to bump the fee of the Bitcoin {} transaction via its anchor output: {:#}",
+                kind,
+                error
+            );
+        }
+
+        Ok(txid)
     }
 
-    fn update_state(&mut self, force_sync: bool) -> Result<()> {
-        let now = Instant::now();
+    /// Bumps the fee of the published `parent` via CPFP by spending its anchor
+    /// output, if the `parent_fee` it pays falls short of the currently
+    /// estimated fee rate.
+    ///
+    /// Returns the id of the child transaction, `None` if no bump was needed.
+    pub async fn bump_via_anchor(
+        &self,
+        parent: &Transaction,
+        parent_fee: Amount,
+    ) -> Result<Option<Txid>> {
+        let fee_rate = self.estimate_feerate(self.target_block).await?;
 
-        if !force_sync && now < self.last_sync + self.sync_interval {
-            return Ok(());
+        if parent_fee.to_sat() >= fee_rate.fee_wu(parent.weight()) {
+            return Ok(None);
         }
 
-        self.last_sync = now;
-        self.update_latest_block()?;
-        self.update_script_histories()?;
+        let psbt = self.spend_anchor(parent, parent_fee, fee_rate).await?;
+        let child = self.sign_and_finalize(psbt).await?;
+        let txid = self.publish(child, "anchor spend").await?;
 
-        Ok(())
+        Ok(Some(txid))
     }
 
-    fn status_of_script<T>(&mut self, tx: &T) -> Result<ScriptStatus>
-    where
-        T: Watchable,
-    {
-        let txid = tx.id();
-        let script = tx.script();
+    /// Sends `amount` to `address` from the funds of this wallet and returns
+    /// the id of the broadcast transaction.
+    ///
+    /// If the broadcast is rejected because the fee is too low, the
+    /// transaction is built once more at twice the estimated fee rate, as long
+    /// as the fee stays within the maximum allowed for `amount`.
+    pub async fn send(&self, address: Address, amount: Amount, kind: &str) -> Result<Txid> {
+        let fee_rate = self.estimate_feerate(self.target_block).await?;
+
+        retry_with_bumped_fee(fee_rate, |fee_rate_override| {
+            let address = address.clone();
+
+            async move {
+                let psbt = self
+                    .send_to_address(address, amount, None, fee_rate_override)
+                    .await?;
+                let transaction = self.sign_and_finalize(psbt).await?;
+
+                self.publish(transaction, kind).await
+            }
+        })
+        .await
+    }
+}
 
-        if !self.script_history.contains_key(&script) {
-            self.script_history.insert(script.clone(), vec![]);
+impl<D, C> Wallet<D, C>
+where
+    C: EstimateFeeRate + ScanScripts,
+    D: BatchDatabase,
+{
+    /// Syncs the wallet like [`Wallet::sync`], but first scans its scripts
+    /// in windows of the stop gap and reports the progress to `on_progress`
+    /// after each window and once the sync itself is done.
+    ///
+    /// The sync fetches the history of every script once more, starting from
+    /// the first one, so it makes up the second half of the progress. It
+    /// reports no progress of its own and cannot be cancelled part way.
+    ///
+    /// Returning [`ControlFlow::Break`] from `on_progress` during the scan
+    /// cancels the sync with [`SyncCancelled`]. The highest used index found
+    /// and the position the scan got to are kept in the wallet database after
+    /// every window, so a later sync continues the scan from there instead of
+    /// starting from scratch. The wallet and the Electrum client are only
+    /// locked while a window is scanned, so the scan does not block other
+    /// users of the wallet until it is done.
+    pub async fn sync_with_progress(
+        &self,
+        mut on_progress: impl FnMut(SyncProgress) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let stop_gap = u32::try_from(self.client.lock().await.stop_gap())?;
 
-            // When we first subscribe to a script we want to immediately fetch its status
-            // Otherwise we would have to wait for the next sync interval, which can take a minute
-            // This would result in potentially inaccurate status updates until that next sync interval is hit
-            self.update_state(true)?;
-        } else {
-            self.update_state(false)?;
-        }
+        let (keychains, last_indexes) = {
+            let wallet = self.wallet.lock().await;
 
-        let history = self.script_history.entry(script).or_default();
+            let mut keychains = vec![KeychainKind::External];
+            if wallet.public_descriptor(KeychainKind::Internal)?.is_some() {
+                keychains.push(KeychainKind::Internal);
+            }
+            let last_indexes = keychains
+                .iter()
+                .map(|keychain| wallet.database().get_last_index(*keychain))
+                .collect::<Result<Vec<_>, _>>()?;
 
-        let history_of_tx = history
+            (keychains, last_indexes)
+        };
+
+        let mut ends = last_indexes
             .iter()
-            .filter(|entry| entry.tx_hash == txid)
+            .map(|last_index| last_index.map_or(0, |index| index + 1) + stop_gap)
             .collect::<Vec<_>>();
-
-        match history_of_tx.as_slice() {
-            [] => Ok(ScriptStatus::Unseen),
-            [remaining @ .., last] => {
-                if !remaining.is_empty() {
-                    tracing::warn!("Found more than a single history entry for script. This is highly unexpected and those history entries will be ignored")
+        let mut starts = Vec::with_capacity(keychains.len());
+        for (i, keychain) in keychains.iter().enumerate() {
+            let after_last_used = last_indexes[i].map_or(0, |index| index + 1);
+            let position = self.scan_position(*keychain)?.unwrap_or(0);
+            starts.push(after_last_used.max(position).min(ends[i]));
+        }
+        let mut scanned = starts.iter().sum::<u32>();
+
+        for (i, keychain) in keychains.iter().copied().enumerate() {
+            let mut index = starts[i];
+
+            while index < ends[i] {
+                let window_end = ends[i].min(index + stop_gap);
+                let scripts = {
+                    let wallet = self.wallet.lock().await;
+                    (index..window_end)
+                        .map(|index| script_at(&wallet, keychain, index))
+                        .collect::<Result<Vec<_>>>()?
+                };
+
+                let used = self.client.lock().await.have_history(&scripts)?;
+                if let Some(last_used) = used.iter().rposition(|used| *used) {
+                    let last_used = index + u32::try_from(last_used)?;
+                    ends[i] = ends[i].max(last_used + 1 + stop_gap);
+                    raise_last_index(&*self.wallet.lock().await, keychain, last_used)?;
                 }
+                self.set_scan_position(keychain, Some(window_end))?;
 
-                if last.height <= 0 {
-                    Ok(ScriptStatus::InMempool)
-                } else {
-                    Ok(ScriptStatus::Confirmed(
-                        Confirmed::from_inclusion_and_latest_block(
-                            u32::try_from(last.height)?,
-                            u32::from(self.latest_block_height),
-                        ),
-                    ))
+                scanned += window_end - index;
+                index = window_end;
+
+                let progress = SyncProgress {
+                    scanned,
+                    total: 2 * ends.iter().sum::<u32>(),
+                };
+                if on_progress(progress).is_break() {
+                    bail!(SyncCancelled { progress });
                 }
             }
         }
-    }
-
-    fn update_latest_block(&mut self) -> Result<()> {
-        // Fetch the latest block for storing the height.
-        // We do not act on this subscription after this call, as we cannot rely on
-        // subscription push notifications because eventually the Electrum server will
-        // close the connection and subscriptions are not automatically renewed
-        // upon renewing the connection.
-        let latest_block = self
-            .electrum
-            .block_headers_subscribe()
-            .context("Failed to subscribe to header notifications")?;
-        let latest_block_height = BlockHeight::try_from(latest_block)?;
 
-        if latest_block_height > self.latest_block_height {
-            tracing::debug!(
-                block_height = u32::from(latest_block_height),
-                "Got notification for new block"
-            );
-            self.latest_block_height = latest_block_height;
+        self.client
+            .lock()
+            .await
+            .sync_wallet(&*self.wallet.lock().await)
+            .context("Failed to sync balance of Bitcoin wallet")?;
+        for keychain in keychains {
+            self.set_scan_position(keychain, None)?;
         }
 
+        let total = 2 * ends.iter().sum::<u32>();
+        let _ = on_progress(SyncProgress {
+            scanned: total,
+            total,
+        });
+
+        self.balance().await?;
+
         Ok(())
     }
 
-    fn update_script_histories(&mut self) -> Result<()> {
-        let histories = self
-            .electrum
-            .batch_script_get_history(self.script_history.keys())
-            .context("Failed to get script histories")?;
-
-        if histories.len() != self.script_history.len() {
-            bail!(
-                "Expected {} history entries, received {}",
-                self.script_history.len(),
-                histories.len()
-            );
-        }
+    /// The position an unfinished [`Wallet::sync_with_progress`] got to on
+    /// `keychain`.
+    fn scan_position(&self, keychain: KeychainKind) -> Result<Option<u32>> {
+        self.scan_positions
+            .get(scan_position_key(keychain))?
+            .map(|position| {
+                let position = <[u8; 4]>::try_from(position.as_ref())
+                    .context("Invalid scan position in wallet database")?;
 
-        let scripts = self.script_history.keys().cloned();
-        let histories = histories.into_iter();
+                Ok(u32::from_be_bytes(position))
+            })
+            .transpose()
+    }
 
-        self.script_history = scripts.zip(histories).collect::<BTreeMap<_, _>>();
+    fn set_scan_position(&self, keychain: KeychainKind, position: Option<u32>) -> Result<()> {
+        let key = scan_position_key(keychain);
+        match position {
+            Some(position) => self
+                .scan_positions
+                .insert(key, &position.to_be_bytes()[..])?,
+            None => self.scan_positions.remove(key)?,
+        };
 
         Ok(())
     }
-}
 
-impl EstimateFeeRate for Client {
-    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
-        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L213
-        // Returned estimated fees are per BTC/kb.
-        let fee_per_byte = self.electrum.estimate_fee(target_block)?;
-        // we do not expect fees being that high.
-        #[allow(clippy::cast_possible_truncation)]
-        Ok(FeeRate::from_btc_per_kvb(fee_per_byte as f32))
+    /// The balance of the outputs with enough confirmations to fund a
+    /// transaction, see `funding_min_confirmations` of the
+    /// [environment](env::Config).
+    pub async fn spendable_balance(&self) -> Result<Amount> {
+        let wallet = self.wallet.lock().await;
+        let unconfirmed = unconfirmed_utxos(&wallet, self.funding_min_confirmations)?;
+        let sats = wallet
+            .list_unspent()?
+            .into_iter()
+            .filter(|utxo| !unconfirmed.contains(&utxo.outpoint))
+            .map(|utxo| utxo.txout.value)
+            .sum();
+
+        Ok(Amount::from_sat(sats))
     }
 
-    fn min_relay_fee(&self) -> Result<bitcoin::Amount> {
-        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L219
-        // Returned fee is in BTC/kb
-        let relay_fee = bitcoin::Amount::from_btc(self.electrum.relay_fee()?)?;
-        Ok(relay_fee)
+    /// Fails with [`InsufficientFunds`] if the
+    /// [spendable balance](Self::spendable_balance) of the wallet does not
+    /// cover `need`.
+    pub async fn ensure_sufficient_funds(&self, need: Amount) -> Result<()> {
+        let have = self.spendable_balance().await?;
+
+        if have < need {
+            bail!(InsufficientFunds { have, need })
+        }
+
+        Ok(())
+    }
+
+    /// Syncs the wallet every sync interval until its
+    /// [spendable balance](Self::spendable_balance) reaches `target` and
+    /// returns it, e.g. to wait for a deposit to confirm.
+    ///
+    /// Fails with [`BalanceNotReached`] if the balance is still below `target`
+    /// once `timeout` has passed.
+    pub async fn await_balance(&self, target: Amount, timeout: Duration) -> Result<Amount> {
+        let started = Instant::now();
+        let sync_interval = self.client.lock().await.sync_interval();
+
+        loop {
+            let client = self.client.lock().await;
+            client
+                .sync_wallet(&*self.wallet.lock().await)
+                .context("Failed to sync balance of Bitcoin wallet")?;
+            drop(client);
+
+            let balance = self.spendable_balance().await?;
+            if balance >= target {
+                return Ok(balance);
+            }
+
+            if started.elapsed() >= timeout {
+                bail!(BalanceNotReached {
+                    balance,
+                    target,
+                    timeout,
+                });
+            }
+
+            tracing::debug!(%balance, %target, "Waiting for the Bitcoin balance to reach the target");
+            tokio::time::sleep(sync_interval).await;
+        }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ScriptStatus {
-    Unseen,
-    InMempool,
-    Confirmed(Confirmed),
-    Retrying,
+/// Checks that `transaction`, spending `spent_outputs`, pays `intended` as
+/// fee, give or take `tolerance`.
+fn verify_fee(
+    transaction: &Transaction,
+    spent_outputs: &[TxOut],
+    intended: Amount,
+    tolerance: Amount,
+) -> Result<(), FeeMismatch> {
+    let spent: u64 = spent_outputs.iter().map(|output| output.value).sum();
+    let paid_out: u64 = transaction.output.iter().map(|output| output.value).sum();
+    let actual = Amount::from_sat(spent.saturating_sub(paid_out));
+
+    let difference = if actual > intended {
+        actual - intended
+    } else {
+        intended - actual
+    };
+    if difference > tolerance {
+        return Err(FeeMismatch {
+            txid: transaction.txid(),
+            intended,
+            actual,
+        });
+    }
+
+    Ok(())
 }
 
-impl ScriptStatus {
-    pub fn from_confirmations(confirmations: u32) -> Self {
-        match confirmations {
-            0 => Self::InMempool,
-            confirmations => Self::Confirmed(Confirmed::new(confirmations - 1)),
-        }
+fn scan_position_key(keychain: KeychainKind) -> &'static [u8] {
+    match keychain {
+        KeychainKind::External => b"external",
+        KeychainKind::Internal => b"internal",
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct Confirmed {
-    /// The depth of this transaction within the blockchain.
-    ///
-    /// Will be zero if the transaction is included in the latest block.
-    depth: u32,
+/// The script of `keychain` at `index` without handing out the address.
+fn script_at<D>(wallet: &bdk::Wallet<D>, keychain: KeychainKind, index: u32) -> Result<Script>
+where
+    D: BatchDatabase,
+{
+    let address = match keychain {
+        KeychainKind::External => wallet.get_address(AddressIndex::Peek(index))?,
+        KeychainKind::Internal => wallet.get_internal_address(AddressIndex::Peek(index))?,
+    };
+
+    Ok(address.script_pubkey())
 }
 
-impl Confirmed {
-    pub fn new(depth: u32) -> Self {
-        Self { depth }
+/// Marks all addresses of `keychain` up to `index` as handed out, unless
+/// the wallet already got further.
+fn raise_last_index<D>(wallet: &bdk::Wallet<D>, keychain: KeychainKind, index: u32) -> Result<()>
+where
+    D: BatchDatabase,
+{
+    let last_index = wallet.database().get_last_index(keychain)?;
+    if last_index.map_or(false, |last_index| last_index >= index) {
+        return Ok(());
     }
 
-    /// Compute the depth of a transaction based on its inclusion height and the
-    /// latest known block.
-    ///
-    /// Our information about the latest block might be outdated. To avoid an
-    /// overflow, we make sure the depth is 0 in case the inclusion height
-    /// exceeds our latest known block,
-    pub fn from_inclusion_and_latest_block(inclusion_height: u32, latest_block: u32) -> Self {
-        let depth = latest_block.saturating_sub(inclusion_height);
+    match keychain {
+        KeychainKind::External => wallet.get_address(AddressIndex::Reset(index))?,
+        KeychainKind::Internal => wallet.get_internal_address(AddressIndex::Reset(index))?,
+    };
 
-        Self { depth }
+    Ok(())
+}
+
+/// Progress of [`Wallet::sync_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// The script histories fetched so far, including the ones an earlier
+    /// scan already got past.
+    pub scanned: u32,
+    /// The script histories that will be fetched at least, twice the scripts
+    /// of the wallet as the sync after the scan fetches them again. Grows as
+    /// used scripts are found.
+    pub total: u32,
+}
+
+impl SyncProgress {
+    pub fn percent(&self) -> u8 {
+        let percent = u64::from(self.scanned) * 100 / u64::from(self.total.max(1));
+
+        u8::try_from(percent.min(100)).expect("at most 100")
     }
+}
 
-    pub fn confirmations(&self) -> u32 {
-        self.depth + 1
+impl fmt::Display for SyncProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}% ({} of {} scripts)",
+            self.percent(),
+            self.scanned,
+            self.total
+        )
     }
+}
 
-    pub fn meets_target<T>(&self, target: T) -> bool
-    where
-        u32: PartialOrd<T>,
-    {
-        self.confirmations() >= target
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Sync of Bitcoin wallet was cancelled at {progress}")]
+pub struct SyncCancelled {
+    pub progress: SyncProgress,
+}
+
+/// Handle to the background sync started by [`Wallet::sync_continuously`],
+/// the sync stops when it is dropped.
+#[derive(Debug)]
+pub struct BackgroundSync {
+    task: JoinHandle<()>,
+}
+
+impl BackgroundSync {
+    pub fn stop(self) {
+        self.task.abort();
     }
 }
 
-impl ScriptStatus {
-    /// Check if the script has any confirmations.
-    pub fn is_confirmed(&self) -> bool {
-        matches!(self, ScriptStatus::Confirmed(_))
+impl Drop for BackgroundSync {
+    fn drop(&mut self) {
+        self.task.abort();
     }
+}
 
-    /// Check if the script has met the given confirmation target.
-    pub fn is_confirmed_with<T>(&self, target: T) -> bool
-    where
-        u32: PartialOrd<T>,
-    {
-        match self {
-            ScriptStatus::Confirmed(inner) => inner.meets_target(target),
-            _ => false,
-        }
+impl<D, C> Wallet<D, C> {
+    /// Yields the balance whenever a sync observes it changing.
+    pub fn balance_stream(&self) -> impl Stream<Item = Amount> {
+        self.balance_updates.subscribe()
     }
 
-    pub fn has_been_seen(&self) -> bool {
-        matches!(self, ScriptStatus::InMempool | ScriptStatus::Confirmed(_))
+    // TODO: Get rid of this by changing bounds on bdk::Wallet
+    pub fn get_network(&self) -> bitcoin::Network {
+        self.network
     }
 }
 
-impl fmt::Display for ScriptStatus {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ScriptStatus::Unseen => write!(f, "unseen"),
-            ScriptStatus::InMempool => write!(f, "in mempool"),
-            ScriptStatus::Retrying => write!(f, "retrying"),
-            ScriptStatus::Confirmed(inner) => {
-                write!(f, "confirmed with {} blocks", inner.confirmations())
-            }
+pub trait EstimateFeeRate {
+    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate>;
+    fn min_relay_fee(&self) -> Result<bitcoin::Amount>;
+}
+
+/// The backend [`Wallet::sync_with_progress`] scans the wallet's scripts
+/// with.
+pub trait ScanScripts {
+    /// The number of consecutive unused scripts after which a scan stops.
+    fn stop_gap(&self) -> usize;
+    /// How long to wait before scanning again for new transactions.
+    fn sync_interval(&self) -> Duration;
+    /// Whether each of `scripts` has any transactions.
+    fn have_history(&self, scripts: &[Script]) -> Result<Vec<bool>>;
+    /// Updates `wallet` with the transactions of all of its scripts.
+    fn sync_wallet<D>(&self, wallet: &bdk::Wallet<D>) -> Result<()>
+    where
+        D: BatchDatabase;
+}
+
+/// The backend the wallet publishes its transactions with.
+pub trait BroadcastTransaction {
+    fn broadcast(&self, transaction: &Transaction) -> Result<()>;
+
+    /// Publishes `transactions`, each after the ones it spends from, so that
+    /// a child can pay for a parent that is rejected on its own.
+    ///
+    /// By default they are published one after another, which only gets a
+    /// rejected parent accepted if the backend accepts it by the second try.
+    fn broadcast_package(&self, transactions: &[Transaction]) -> Result<()> {
+        for transaction in transactions {
+            self.broadcast(transaction)?;
         }
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::bitcoin::{PublicKey, TxLock};
-    use crate::tracing_ext::capture_logs;
-    use bitcoin::hashes::Hash;
-    use proptest::prelude::*;
-    use tracing::level_filters::LevelFilter;
+pub struct StaticFeeRate {
+    fee_rate: FeeRate,
+    min_relay_fee: bitcoin::Amount,
+    queries: std::sync::atomic::AtomicUsize,
+    used_scripts: Vec<Script>,
+    scanned_scripts: std::sync::Mutex<Vec<Script>>,
+    /// Broadcasts paying less than this are rejected like Bitcoin Core does.
+    relay_fee_rate: Option<FeeRate>,
+    /// The outputs broadcast transactions can spend.
+    outputs: std::sync::Mutex<HashMap<OutPoint, TxOut>>,
+    published: std::sync::Mutex<Vec<Transaction>>,
+}
 
-    #[test]
-    fn given_depth_0_should_meet_confirmation_target_one() {
-        let script = ScriptStatus::Confirmed(Confirmed { depth: 0 });
+#[cfg(test)]
+impl StaticFeeRate {
+    /// The number of times the fee rate or min relay fee was queried.
+    pub fn queries(&self) -> usize {
+        self.queries.load(std::sync::atomic::Ordering::SeqCst)
+    }
 
-        let confirmed = script.is_confirmed_with(1);
+    fn record_query(&self) {
+        self.queries
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
 
-        assert!(confirmed)
+    /// The transactions that were broadcast and accepted, in order.
+    pub fn published(&self) -> Vec<Transaction> {
+        self.published.lock().unwrap().clone()
     }
+}
 
-    #[test]
-    fn given_confirmations_1_should_meet_confirmation_target_one() {
-        let script = ScriptStatus::from_confirmations(1);
+#[cfg(test)]
+impl EstimateFeeRate for StaticFeeRate {
+    fn estimate_feerate(&self, _target_block: usize) -> Result<FeeRate> {
+        self.record_query();
+        Ok(self.fee_rate)
+    }
 
-        let confirmed = script.is_confirmed_with(1);
+    fn min_relay_fee(&self) -> Result<bitcoin::Amount> {
+        self.record_query();
+        Ok(self.min_relay_fee)
+    }
+}
 
-        assert!(confirmed)
+#[cfg(test)]
+impl BroadcastTransaction for StaticFeeRate {
+    fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        self.broadcast_package(std::slice::from_ref(transaction))
     }
 
-    #[test]
-    fn given_inclusion_after_lastest_known_block_at_least_depth_0() {
-        let included_in = 10;
-        let latest_block = 9;
+    fn broadcast_package(&self, transactions: &[Transaction]) -> Result<()> {
+        let mut outputs = self.outputs.lock().unwrap().clone();
+        let mut fee = 0;
+        let mut vsize = 0;
+
+        for transaction in transactions {
+            let spent = transaction
+                .input
+                .iter()
+                .map(|input| {
+                    outputs
+                        .get(&input.previous_output)
+                        .map(|output| output.value)
+                        .with_context(|| {
+                            format!("Spent output {} is unknown", input.previous_output)
+                        })
+                })
+                .sum::<Result<u64>>()?;
+            let paid_out: u64 = transaction.output.iter().map(|output| output.value).sum();
+
+            fee += spent - paid_out;
+            vsize += transaction.vsize();
+            for (vout, output) in transaction.output.iter().enumerate() {
+                outputs.insert(
+                    OutPoint::new(transaction.txid(), vout as u32),
+                    output.clone(),
+                );
+            }
+        }
 
-        let confirmed = Confirmed::from_inclusion_and_latest_block(included_in, latest_block);
+        if let Some(relay_fee_rate) = self.relay_fee_rate {
+            let min_fee = relay_fee_rate.fee_vb(vsize);
 
-        assert_eq!(confirmed.depth, 0)
+            if fee < min_fee {
+                bail!(bdk::Error::Electrum(bdk::electrum_client::Error::Protocol(
+                    serde_json::Value::String(format!(
+                        r#"sendrawtransaction RPC error: {{"code":-26,"message":"min relay fee not met, {} < {}"}}"#,
+                        fee, min_fee
+                    ))
+                )));
+            }
+        }
+
+        *self.outputs.lock().unwrap() = outputs;
+        self.published
+            .lock()
+            .unwrap()
+            .extend_from_slice(transactions);
+
+        Ok(())
     }
+}
 
-    #[test]
-    fn given_one_BTC_and_100k_sats_per_vb_fees_should_not_hit_max() {
-        // 400 weight = 100 vbyte
-        let weight = 400;
-        let amount = bitcoin::Amount::from_sat(100_000_000);
+#[cfg(test)]
+impl ScanScripts for StaticFeeRate {
+    fn stop_gap(&self) -> usize {
+        DEFAULT_STOP_GAP
+    }
 
-        let sat_per_vb = 100.0;
-        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+    fn sync_interval(&self) -> Duration {
+        Duration::from_millis(10)
+    }
 
-        let relay_fee = bitcoin::Amount::ONE_SAT;
-        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+    fn have_history(&self, scripts: &[Script]) -> Result<Vec<bool>> {
+        self.scanned_scripts
+            .lock()
+            .unwrap()
+            .extend_from_slice(scripts);
 
-        // weight / 4.0 *  sat_per_vb
-        let should_fee = bitcoin::Amount::from_sat(10_000);
-        assert_eq!(is_fee, should_fee);
+        Ok(scripts
+            .iter()
+            .map(|script| self.used_scripts.contains(script))
+            .collect())
     }
 
-    #[test]
-    fn given_1BTC_and_1_sat_per_vb_fees_and_100ksat_min_relay_fee_should_hit_min() {
-        // 400 weight = 100 vbyte
-        let weight = 400;
-        let amount = bitcoin::Amount::from_sat(100_000_000);
+    fn sync_wallet<D>(&self, _wallet: &bdk::Wallet<D>) -> Result<()>
+    where
+        D: BatchDatabase,
+    {
+        Ok(())
+    }
+}
 
-        let sat_per_vb = 1.0;
-        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+#[cfg(test)]
+#[derive(Debug)]
+pub struct WalletBuilder {
+    utxo_amount: u64,
+    sats_per_vb: f32,
+    min_relay_fee_sats: u64,
+    min_relay_fee_rate: u64,
+    key: bitcoin::util::bip32::ExtendedPrivKey,
+    num_utxos: u8,
+    utxo_confirmations: u32,
+    funding_min_confirmations: u32,
+    pending_spend: Option<bitcoin::Sequence>,
+    relay_fee_rate: Option<f32>,
+}
 
-        let relay_fee = bitcoin::Amount::from_sat(100_000);
-        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+#[cfg(test)]
+impl WalletBuilder {
+    /// Creates a new, funded wallet with sane default fees.
+    ///
+    /// Unless you are testing things related to fees, this is likely what you
+    /// want.
+    pub fn new(amount: u64) -> Self {
+        WalletBuilder {
+            utxo_amount: amount,
+            sats_per_vb: 1.0,
+            min_relay_fee_sats: 1000,
+            min_relay_fee_rate: 0,
+            key: "tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m".parse().unwrap(),
+            num_utxos: 1,
+            utxo_confirmations: 1,
+            funding_min_confirmations: 0,
+            pending_spend: None,
+            relay_fee_rate: None,
+        }
+    }
 
-        // weight / 4.0 *  sat_per_vb would be smaller than relay fee hence we take min
-        // relay fee
-        let should_fee = bitcoin::Amount::from_sat(100_000);
-        assert_eq!(is_fee, should_fee);
+    pub fn with_zero_fees(self) -> Self {
+        Self {
+            sats_per_vb: 0.0,
+            min_relay_fee_sats: 0,
+            ..self
+        }
     }
 
-    #[test]
-    fn given_1mio_sat_and_1k_sats_per_vb_fees_should_hit_relative_max() {
-        // 400 weight = 100 vbyte
-        let weight = 400;
-        let amount = bitcoin::Amount::from_sat(1_000_000);
+    pub fn with_fees(self, sats_per_vb: f32, min_relay_fee_sats: u64) -> Self {
+        Self {
+            sats_per_vb,
+            min_relay_fee_sats,
+            ..self
+        }
+    }
 
-        let sat_per_vb = 1_000.0;
-        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+    pub fn with_min_relay_fee_rate(self, sats_per_vb: u64) -> Self {
+        Self {
+            min_relay_fee_rate: sats_per_vb,
+            ..self
+        }
+    }
 
-        let relay_fee = bitcoin::Amount::ONE_SAT;
-        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+    pub fn with_key(self, key: bitcoin::util::bip32::ExtendedPrivKey) -> Self {
+        Self { key, ..self }
+    }
 
-        // weight / 4.0 *  sat_per_vb would be greater than 3% hence we take max
-        // relative fee.
-        let should_fee = bitcoin::Amount::from_sat(30_000);
-        assert_eq!(is_fee, should_fee);
+    pub fn with_num_utxos(self, number: u8) -> Self {
+        Self {
+            num_utxos: number,
+            ..self
+        }
     }
 
-    #[test]
-    fn given_1BTC_and_4mio_sats_per_vb_fees_should_hit_total_max() {
-        // even if we send 1BTC we don't want to pay 0.3BTC in fees. This would be
-        // $1,650 at the moment.
-        let weight = 400;
-        let amount = bitcoin::Amount::from_sat(100_000_000);
+    pub fn with_utxo_confirmations(self, confirmations: u32) -> Self {
+        Self {
+            utxo_confirmations: confirmations,
+            ..self
+        }
+    }
+
+    pub fn with_funding_min_confirmations(self, confirmations: u32) -> Self {
+        Self {
+            funding_min_confirmations: confirmations,
+            ..self
+        }
+    }
+
+    /// Rejects broadcasts paying less than `sats_per_vb` with the error
+    /// Bitcoin Core gives for too low a fee.
+    pub fn with_relay_fee_rate(self, sats_per_vb: f32) -> Self {
+        Self {
+            relay_fee_rate: Some(sats_per_vb),
+            ..self
+        }
+    }
+
+    /// Spends the first UTXO of the wallet in a transaction that is not
+    /// confirmed yet, paying a fee of 1000 sats.
+    pub fn with_pending_spend(self, replaceable: bool) -> Self {
+        let sequence = if replaceable {
+            bitcoin::Sequence::ENABLE_RBF_NO_LOCKTIME
+        } else {
+            bitcoin::Sequence::MAX
+        };
+
+        Self {
+            pending_spend: Some(sequence),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> Wallet<bdk::database::MemoryDatabase, StaticFeeRate> {
+        let descriptor = format!("wpkh({}/*)", self.key);
+
+        self.build_from_descriptor(&descriptor, None)
+    }
+
+    /// Builds a wallet that only knows the public keys and signs through the
+    /// given signer.
+    ///
+    /// The signer is asked for keys derived from the key of this builder at
+    /// `m/84'/1'/0'`.
+    pub fn build_with_signer(
+        self,
+        signer: Arc<dyn Signer>,
+    ) -> Wallet<bdk::database::MemoryDatabase, StaticFeeRate> {
+        use bitcoin::secp256k1::Secp256k1;
+        use bitcoin::util::bip32::DerivationPath;
+        use std::str::FromStr;
+
+        let secp = Secp256k1::new();
+        let account = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+        let xpub =
+            ExtendedPubKey::from_priv(&secp, &self.key.derive_priv(&secp, &account).unwrap());
+        let fingerprint = self.key.fingerprint(&secp);
+        let descriptor = format!("wpkh([{}/84'/1'/0']{}/0/*)", fingerprint, xpub);
+
+        self.build_from_descriptor(&descriptor, Some((signer, fingerprint)))
+    }
+
+    fn build_from_descriptor(
+        self,
+        descriptor: &str,
+        signer: Option<(Arc<dyn Signer>, Fingerprint)>,
+    ) -> Wallet<bdk::database::MemoryDatabase, StaticFeeRate> {
+        use bdk::database::{BatchOperations, Database, MemoryDatabase, SyncTime};
+        use bdk::{testutils, BlockTime, TransactionDetails};
+
+        let descriptors = testutils!(@descriptors (descriptor));
+
+        let mut database = MemoryDatabase::new();
+
+        for index in 0..self.num_utxos {
+            bdk::populate_test_db!(
+                &mut database,
+                testutils! {
+                    @tx ( (@external descriptors, index as u32) => self.utxo_amount ) (@confirmations self.utxo_confirmations)
+                },
+                Some(100)
+            );
+        }
+        if let Some(sequence) = self.pending_spend {
+            let mut utxo = database.iter_utxos().unwrap().remove(0);
+            let transaction = Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(0),
+                input: vec![bitcoin::TxIn {
+                    previous_output: utxo.outpoint,
+                    sequence,
+                    ..Default::default()
+                }],
+                output: vec![bitcoin::TxOut {
+                    value: self.utxo_amount - 1_000,
+                    script_pubkey: Script::new(),
+                }],
+            };
+            database
+                .set_tx(&TransactionDetails {
+                    txid: transaction.txid(),
+                    transaction: Some(transaction),
+                    received: 0,
+                    sent: self.utxo_amount,
+                    fee: Some(1_000),
+                    confirmation_time: None,
+                })
+                .unwrap();
+            utxo.is_spent = true;
+            database.set_utxo(&utxo).unwrap();
+        }
+        let outputs = database
+            .iter_utxos()
+            .unwrap()
+            .into_iter()
+            .map(|utxo| (utxo.outpoint, utxo.txout))
+            .collect();
+        let block_time = bdk::BlockTime {
+            height: 100,
+            timestamp: 0,
+        };
+        let sync_time = SyncTime { block_time };
+        database.set_sync_time(sync_time).unwrap();
+
+        let mut wallet =
+            bdk::Wallet::new(&descriptors.0, None, Network::Regtest, database).unwrap();
+        if let Some((signer, fingerprint)) = signer {
+            add_external_signer(&mut wallet, signer, fingerprint);
+        }
+
+        Wallet {
+            client: Arc::new(Mutex::new(StaticFeeRate {
+                fee_rate: FeeRate::from_sat_per_vb(self.sats_per_vb),
+                min_relay_fee: bitcoin::Amount::from_sat(self.min_relay_fee_sats),
+                queries: Default::default(),
+                used_scripts: Default::default(),
+                scanned_scripts: Default::default(),
+                relay_fee_rate: self.relay_fee_rate.map(FeeRate::from_sat_per_vb),
+                outputs: std::sync::Mutex::new(outputs),
+                published: Default::default(),
+            })),
+            wallet: Arc::new(Mutex::new(wallet)),
+            finality_confirmations: 1,
+            max_electrum_reconnects: 5,
+            network: Network::Regtest,
+            target_block: 1,
+            min_relay_fee_rate: FeeRate::from_sat_per_vb(self.min_relay_fee_rate as f32),
+            funding_min_confirmations: self.funding_min_confirmations,
+            avg_block_time: Duration::from_secs(5),
+            balance_updates: BalanceUpdates::new(),
+            fee_estimator: None,
+            strict_fee_tolerance: None,
+            scan_positions: bdk::sled::Config::new()
+                .temporary(true)
+                .open()
+                .unwrap()
+                .open_tree(SCAN_POSITIONS_TREE_NAME)
+                .unwrap(),
+        }
+    }
+}
+
+/// Defines a watchable transaction.
+///
+/// For a transaction to be watchable, we need to know two things: Its
+/// transaction ID and the specific output script that is going to change.
+/// A transaction can obviously have multiple outputs but our protocol purposes,
+/// we are usually interested in a specific one.
+pub trait Watchable {
+    fn id(&self) -> Txid;
+    fn script(&self) -> Script;
+}
+
+impl Watchable for (Txid, Script) {
+    fn id(&self) -> Txid {
+        self.0
+    }
+
+    fn script(&self) -> Script {
+        self.1.clone()
+    }
+}
+
+/// The transactions of a swap that can be watched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum SwapTransaction {
+    Lock,
+    Redeem,
+    Cancel,
+    Refund,
+    Punish,
+}
+
+/// The watched transactions of a swap, whose statuses are fetched together
+/// with a single batch of Electrum requests instead of one subscription per
+/// transaction.
+#[derive(Debug, Clone, Default)]
+pub struct SwapWatchSet {
+    watched: Vec<(SwapTransaction, (Txid, Script))>,
+}
+
+impl SwapWatchSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn watch(mut self, transaction: SwapTransaction, tx: &impl Watchable) -> Self {
+        self.watched.push((transaction, (tx.id(), tx.script())));
+        self
+    }
+
+    /// The current status of every watched transaction.
+    pub async fn statuses(&self, wallet: &Wallet) -> Result<Vec<(SwapTransaction, ScriptStatus)>> {
+        Client::query_with_fail_over(&wallet.client, |client| client.statuses_of(self)).await
+    }
+
+    /// The watched transactions that are in the mempool or confirmed.
+    pub async fn seen(&self, wallet: &Wallet) -> Result<Vec<SwapTransaction>> {
+        let seen = self
+            .statuses(wallet)
+            .await?
+            .into_iter()
+            .filter(|(_, status)| status.has_been_seen())
+            .map(|(transaction, _)| transaction)
+            .collect();
+
+        Ok(seen)
+    }
+
+    /// Polls the statuses of all watched transactions in one task and sends
+    /// every status change, the task stops once the receiver is dropped.
+    pub async fn subscribe(
+        self,
+        wallet: &Wallet,
+    ) -> mpsc::UnboundedReceiver<(SwapTransaction, ScriptStatus)> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let client = wallet.client.clone();
+        let (poll_interval, reconnect_jitter) = {
+            let client = client.lock().await;
+            (client.poll_interval, client.reconnect_jitter)
+        };
+
+        tokio::spawn(async move {
+            let mut last_statuses = vec![None; self.watched.len()];
+
+            loop {
+                let statuses =
+                    Client::query_with_fail_over(&client, |client| client.statuses_of(&self)).await;
+                let delay = match statuses {
+                    Ok(statuses) => {
+                        for ((transaction, status), last) in
+                            statuses.into_iter().zip(&mut last_statuses)
+                        {
+                            if *last == Some(status) {
+                                continue;
+                            }
+                            *last = Some(status);
+
+                            if sender.send((transaction, status)).is_err() {
+                                return;
+                            }
+                        }
+
+                        poll_interval
+                    }
+                    Err(error) => {
+                        tracing::warn!("Failed to get statuses of swap transactions: {:#}", error);
+
+                        jittered(poll_interval, reconnect_jitter)
+                    }
+                };
+
+                if sender.is_closed() {
+                    return;
+                }
+
+                tokio::time::sleep(delay).await;
+            }
+        });
+
+        receiver
+    }
+}
+
+/// A transaction paying to a watched script.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Funding {
+    pub txid: Txid,
+    /// The sum of the outputs of the transaction paying to the script.
+    pub amount: Amount,
+    pub status: ScriptStatus,
+}
+
+/// Looks up the transactions funding the output of `descriptor`, for example
+/// the lock output built by
+/// [`build_lock_descriptor`](crate::bitcoin::build_lock_descriptor).
+///
+/// Only the Electrum server is queried, no wallet state is needed. An empty
+/// result means the output is not funded.
+pub async fn funding_of_descriptor(
+    electrum_rpc_url: Url,
+    descriptor: &Descriptor<bitcoin::PublicKey>,
+) -> Result<Vec<Funding>> {
+    let script = descriptor.script_pubkey();
+    let mut client = Client::new(electrum_rpc_url, Duration::ZERO, DEFAULT_STOP_GAP)?;
+
+    let history = client
+        .electrum
+        .script_get_history(&script)
+        .context("Failed to get script history")?;
+
+    let mut fundings = Vec::with_capacity(history.len());
+    for entry in history {
+        let txid = entry.tx_hash;
+        let transaction = client
+            .get_tx(&txid)?
+            .with_context(|| format!("Could not get raw tx with id: {}", txid))?;
+        let amount = transaction
+            .output
+            .iter()
+            .filter(|output| output.script_pubkey == script)
+            .map(|output| output.value)
+            .sum::<u64>();
+
+        // spending transactions show up in the history as well
+        if amount == 0 {
+            continue;
+        }
+
+        let status = client.status_of_script(&(txid, script.clone()))?;
+
+        fundings.push(Funding {
+            txid,
+            amount: Amount::from_sat(amount),
+            status,
+        });
+    }
+
+    Ok(fundings)
+}
+
+pub struct Client {
+    electrum: bdk::electrum_client::Client,
+    blockchain: ElectrumBlockchain,
+    /// The server `electrum` and `blockchain` are connected to.
+    electrum_rpc_url: Url,
+    /// The servers to fail over to, including the current one.
+    electrum_rpc_urls: Vec<Url>,
+    tip: ChainTip,
+    last_sync: Instant,
+    sync_interval: Duration,
+    /// How often transaction watchers poll the status of their transaction,
+    /// served from the cached script histories in between syncs.
+    poll_interval: Duration,
+    /// Up to how long is randomly added to the poll interval after a failed
+    /// request to the Electrum server, and waited before reconnecting.
+    reconnect_jitter: Duration,
+    script_history: BTreeMap<Script, Vec<GetHistoryRes>>,
+    subscriptions: HashMap<(Txid, Script), Subscription>,
+    stop_gap: usize,
+}
+
+impl Client {
+    /// `stop_gap` bounds both how many script histories are requested from
+    /// the Electrum server in a single batch during a sync and after how
+    /// many consecutive unused addresses the sync stops.
+    fn new(electrum_rpc_url: Url, interval: Duration, stop_gap: usize) -> Result<Self> {
+        Self::connect_to_pool(&[electrum_rpc_url], interval, stop_gap)
+    }
+
+    /// Connects to the first of `electrum_rpc_urls`, failing over to the next
+    /// one if a server is busy or unreachable.
+    ///
+    /// The remaining servers of the pool are failed over to as well if the
+    /// current server becomes busy or unreachable later on.
+    fn connect_to_pool(
+        electrum_rpc_urls: &[Url],
+        interval: Duration,
+        stop_gap: usize,
+    ) -> Result<Self> {
+        let (electrum_rpc_url, electrum, blockchain, latest_block) =
+            fail_over(electrum_rpc_urls, |url| connect(url, stop_gap))?;
+        let tip_hash = latest_block.header.block_hash();
+        let last_sync = Instant::now()
+            .checked_sub(interval)
+            .expect("no underflow since block time is only 600 secs");
+
+        Ok(Self {
+            electrum,
+            blockchain,
+            electrum_rpc_url,
+            electrum_rpc_urls: electrum_rpc_urls.to_vec(),
+            tip: ChainTip::new(BlockHeight::try_from(latest_block)?, tip_hash),
+            last_sync,
+            sync_interval: interval,
+            poll_interval: interval,
+            reconnect_jitter: Duration::ZERO,
+            script_history: Default::default(),
+            subscriptions: Default::default(),
+            stop_gap,
+        })
+    }
+
+    fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    fn with_reconnect_jitter(mut self, reconnect_jitter: Duration) -> Self {
+        self.reconnect_jitter = reconnect_jitter;
+        self
+    }
+
+    /// Connects to the next server of the pool that is reachable and not
+    /// busy, trying the current server last.
+    ///
+    /// The servers of a pool may be at slightly different chain tips. The tip
+    /// reported by the new server only moves the tip of the wallet back if it
+    /// is on another chain, see [`ChainTip::update`].
+    fn fail_over(&mut self) -> Result<()> {
+        let position = self
+            .electrum_rpc_urls
+            .iter()
+            .position(|url| url == &self.electrum_rpc_url)
+            .unwrap_or_default();
+        let mut urls = self.electrum_rpc_urls.clone();
+        urls.rotate_left(position + 1);
+
+        let stop_gap = self.stop_gap;
+        let (electrum_rpc_url, electrum, blockchain, latest_block) =
+            fail_over(&urls, |url| connect(url, stop_gap))?;
+        tracing::info!(url = %electrum_rpc_url, "Failed over to Electrum server");
+
+        self.electrum = electrum;
+        self.blockchain = blockchain;
+        self.electrum_rpc_url = electrum_rpc_url;
+        // The script histories are fetched from the new server on the next update
+        self.last_sync = Instant::now()
+            .checked_sub(self.sync_interval)
+            .unwrap_or_else(Instant::now);
+        self.update_tip(latest_block)
+    }
+
+    /// Runs `query` against `client`. If the Electrum server is busy or
+    /// unreachable, waits up to the reconnect jitter, fails over to the next
+    /// server of the pool and runs `query` once more.
+    ///
+    /// The lock is not held while waiting, so other tasks are not blocked by
+    /// the jitter.
+    async fn query_with_fail_over<T>(
+        client: &Mutex<Client>,
+        mut query: impl FnMut(&mut Client) -> Result<T>,
+    ) -> Result<T> {
+        let error = match query(&mut *client.lock().await) {
+            Err(error) if is_busy_or_unreachable(&error) => error,
+            result => return result,
+        };
+
+        let (electrum_rpc_url, reconnect_jitter) = {
+            let client = client.lock().await;
+            (client.electrum_rpc_url.clone(), client.reconnect_jitter)
+        };
+        tracing::warn!(url = %electrum_rpc_url, "Electrum server became unavailable: {:#}", error);
+        tokio::time::sleep(jittered(Duration::ZERO, reconnect_jitter)).await;
+
+        let mut client = client.lock().await;
+        // Another task may have failed over while we were waiting
+        if client.electrum_rpc_url == electrum_rpc_url {
+            client.fail_over()?;
+        }
+
+        query(&mut client)
+    }
+
+    /// Fails with [`WrongChain`] if the Electrum server follows a chain other
+    /// than the one of `network`, e.g. a mainnet server for a regtest wallet.
+    fn ensure_network(&self, network: Network) -> Result<()> {
+        let genesis = self
+            .electrum
+            .block_header(0)
+            .context("Failed to get genesis block header")?
+            .block_hash();
+
+        if genesis != genesis_block(network).block_hash() {
+            bail!(WrongChain { network, genesis })
+        }
+
+        Ok(())
+    }
+
+    fn blockchain(&self) -> &ElectrumBlockchain {
+        &self.blockchain
+    }
+
+    fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, bdk::Error> {
+        self.blockchain.get_tx(txid)
+    }
+
+    /// Finds the transaction spending `outpoint` among the transactions of
+    /// `script`, the script of the output.
+    fn spender_of(&self, outpoint: OutPoint, script: &Script) -> Result<Option<Txid>> {
+        let history = self
+            .electrum
+            .script_get_history(script)
+            .context("Failed to get script history")?;
+
+        for entry in history
+            .iter()
+            .filter(|entry| entry.tx_hash != outpoint.txid)
+        {
+            let transaction = self
+                .get_tx(&entry.tx_hash)?
+                .with_context(|| format!("Could not get raw tx with id: {}", entry.tx_hash))?;
+
+            if transaction
+                .input
+                .iter()
+                .any(|input| input.previous_output == outpoint)
+            {
+                return Ok(Some(entry.tx_hash));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn update_state(&mut self, force_sync: bool) -> Result<()> {
+        let now = Instant::now();
+
+        if !force_sync && now < self.last_sync + self.sync_interval {
+            return Ok(());
+        }
+
+        self.last_sync = now;
+
+        self.update_latest_block()?;
+        self.update_script_histories()?;
+
+        Ok(())
+    }
+
+    fn status_of_script<T>(&mut self, tx: &T) -> Result<ScriptStatus>
+    where
+        T: Watchable,
+    {
+        let txid = tx.id();
+        let script = tx.script();
+
+        if !self.script_history.contains_key(&script) {
+            self.script_history.insert(script.clone(), vec![]);
+
+            // When we first subscribe to a script we want to immediately fetch its status
+            // Otherwise we would have to wait for the next sync interval, which can take a minute
+            // This would result in potentially inaccurate status updates until that next sync interval is hit
+            self.update_state(true)?;
+        } else {
+            self.update_state(false)?;
+        }
+
+        let history = self.script_history.entry(script).or_default();
+
+        let history_of_tx = history
+            .iter()
+            .filter(|entry| entry.tx_hash == txid)
+            .collect::<Vec<_>>();
+
+        match history_of_tx.as_slice() {
+            [] => Ok(ScriptStatus::Unseen),
+            [remaining @ .., last] => {
+                if !remaining.is_empty() {
+                    tracing::warn!("Found more than a single history entry for script. This is highly unexpected and those history entries will be ignored")
+                }
+
+                if last.height <= 0 {
+                    Ok(ScriptStatus::InMempool)
+                } else {
+                    Ok(ScriptStatus::Confirmed(
+                        Confirmed::from_inclusion_and_latest_block(
+                            u32::try_from(last.height)?,
+                            u32::from(self.tip.height()),
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// The statuses of all transactions of `watch_set`, fetched with a single
+    /// update of the script histories.
+    fn statuses_of(
+        &mut self,
+        watch_set: &SwapWatchSet,
+    ) -> Result<Vec<(SwapTransaction, ScriptStatus)>> {
+        let mut new_scripts = false;
+        for (_, (_, script)) in &watch_set.watched {
+            if !self.script_history.contains_key(script) {
+                self.script_history.insert(script.clone(), vec![]);
+                new_scripts = true;
+            }
+        }
+        self.update_state(new_scripts)?;
+
+        watch_set
+            .watched
+            .iter()
+            .map(|(transaction, tx)| Ok((*transaction, self.status_of_script(tx)?)))
+            .collect()
+    }
+
+    fn update_latest_block(&mut self) -> Result<()> {
+        // Fetch the latest block for storing the height.
+        // We do not act on this subscription after this call, as we cannot rely on
+        // subscription push notifications because eventually the Electrum server will
+        // close the connection and subscriptions are not automatically renewed
+        // upon renewing the connection.
+        let latest_block = self
+            .electrum
+            .block_headers_subscribe()
+            .context("Failed to subscribe to header notifications")?;
+
+        self.update_tip(latest_block)
+    }
+
+    fn update_tip(&mut self, latest_block: HeaderNotification) -> Result<()> {
+        let hash = latest_block.header.block_hash();
+        let height = BlockHeight::try_from(latest_block)?;
+        let electrum = &self.electrum;
+
+        self.tip.update(height, hash, |height| {
+            let header = electrum
+                .block_header(usize::try_from(u32::from(height))?)
+                .context("Failed to get block header")?;
+
+            Ok(header.block_hash())
+        })
+    }
+
+    fn update_script_histories(&mut self) -> Result<()> {
+        let histories = self
+            .electrum
+            .batch_script_get_history(self.script_history.keys())
+            .context("Failed to get script histories")?;
+
+        if histories.len() != self.script_history.len() {
+            bail!(
+                "Expected {} history entries, received {}",
+                self.script_history.len(),
+                histories.len()
+            );
+        }
+
+        let scripts = self.script_history.keys().cloned();
+        let histories = histories.into_iter();
+
+        self.script_history = scripts.zip(histories).collect::<BTreeMap<_, _>>();
+
+        Ok(())
+    }
+
+    /// The fee histogram of the Electrum server's mempool as pairs of fee rate
+    /// in sat/vB and the vsize of the transactions paying it, ordered by
+    /// descending fee rate.
+    fn fee_histogram(&self) -> Result<Vec<(f64, u64)>> {
+        let histogram = self
+            .electrum
+            .raw_call("mempool.get_fee_histogram", Vec::<Param>::new())
+            .context("Failed to get fee histogram")?;
+
+        serde_json::from_value(histogram).context("Failed to parse fee histogram")
+    }
+}
+
+impl BroadcastTransaction for Client {
+    fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        self.blockchain.broadcast(transaction)?;
+
+        Ok(())
+    }
+}
+
+impl EstimateFeeRate for Client {
+    fn estimate_feerate(&self, target_block: usize) -> Result<FeeRate> {
+        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L213
+        // Returned estimated fees are per BTC/kb.
+        let fee_per_byte = self.electrum.estimate_fee(target_block)?;
+        // we do not expect fees being that high.
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(FeeRate::from_btc_per_kvb(fee_per_byte as f32))
+    }
+
+    fn min_relay_fee(&self) -> Result<bitcoin::Amount> {
+        // https://github.com/romanz/electrs/blob/f9cf5386d1b5de6769ee271df5eef324aa9491bc/src/rpc.rs#L219
+        // Returned fee is in BTC/kb
+        let relay_fee = bitcoin::Amount::from_btc(self.electrum.relay_fee()?)?;
+        Ok(relay_fee)
+    }
+}
+
+impl ScanScripts for Client {
+    fn stop_gap(&self) -> usize {
+        self.stop_gap
+    }
+
+    fn sync_interval(&self) -> Duration {
+        self.sync_interval
+    }
+
+    fn have_history(&self, scripts: &[Script]) -> Result<Vec<bool>> {
+        let histories = self
+            .electrum
+            .batch_script_get_history(scripts)
+            .context("Failed to get script histories")?;
+
+        Ok(histories
+            .iter()
+            .map(|history| !history.is_empty())
+            .collect())
+    }
+
+    fn sync_wallet<D>(&self, wallet: &bdk::Wallet<D>) -> Result<()>
+    where
+        D: BatchDatabase,
+    {
+        wallet.sync(self.blockchain(), SyncOptions::default())?;
+
+        Ok(())
+    }
+}
+
+/// What Electrum servers respond with when they refuse more clients, in
+/// lowercase.
+const BUSY_RESPONSES: [&str; 3] = [
+    "server busy",
+    "too many connections",
+    "excessive resource usage",
+];
+
+fn is_busy_response(response: &str) -> bool {
+    let response = response.to_lowercase();
+
+    BUSY_RESPONSES.iter().any(|busy| response.contains(busy))
+}
+
+/// Turns `error` into [`ElectrumServerBusy`] if the server refused us for
+/// being busy.
+fn busy_or(error: bdk::electrum_client::Error, url: &Url) -> anyhow::Error {
+    let response = match &error {
+        bdk::electrum_client::Error::Protocol(response) => response.to_string(),
+        bdk::electrum_client::Error::Message(response) => response.clone(),
+        _ => return error.into(),
+    };
+
+    if !is_busy_response(&response) {
+        return error.into();
+    }
+
+    anyhow::Error::new(ElectrumServerBusy {
+        url: url.clone(),
+        response,
+    })
+}
+
+/// Logs the banner of the Electrum server, failing with
+/// [`ElectrumServerBusy`] if the banner says that the server is busy.
+///
+/// Not every server has a banner, so failing to get one is not an error.
+fn check_banner(electrum: &bdk::electrum_client::Client, url: &Url) -> Result<()> {
+    let banner = match electrum.raw_call("server.banner", Vec::<Param>::new()) {
+        Ok(banner) => banner,
+        Err(error) => {
+            let error = busy_or(error, url);
+            if error.is::<ElectrumServerBusy>() {
+                return Err(error);
+            }
+
+            tracing::debug!(%url, "Failed to get banner of Electrum server: {:#}", error);
+            return Ok(());
+        }
+    };
+    let banner = banner
+        .as_str()
+        .map(str::to_owned)
+        .unwrap_or_else(|| banner.to_string());
+
+    if is_busy_response(&banner) {
+        bail!(ElectrumServerBusy {
+            url: url.clone(),
+            response: banner,
+        })
+    }
+
+    tracing::info!(%url, %banner, "Connected to Electrum server");
+
+    Ok(())
+}
+
+/// Tries `connect` with each of `urls` in order and returns the first
+/// connection, moving on to the next URL only if the server is busy or
+/// unreachable.
+fn fail_over<T>(urls: &[Url], mut connect: impl FnMut(&Url) -> Result<T>) -> Result<T> {
+    let mut last_error = None;
+
+    for url in urls {
+        match connect(url) {
+            Ok(connection) => return Ok(connection),
+            Err(error) if is_busy_or_unreachable(&error) => {
+                tracing::warn!(%url, "Failing over to the next Electrum server: {:#}", error);
+                last_error = Some(error);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    match last_error {
+        Some(error) => Err(error),
+        None => bail!("No Electrum server configured"),
+    }
+}
+
+/// Connects to the Electrum server at `url`, returning its latest block.
+fn connect(
+    url: &Url,
+    stop_gap: usize,
+) -> Result<(
+    Url,
+    bdk::electrum_client::Client,
+    ElectrumBlockchain,
+    HeaderNotification,
+)> {
+    let config = bdk::electrum_client::ConfigBuilder::default()
+        .retry(5)
+        .build();
+    let electrum = bdk::electrum_client::Client::from_config(url.as_str(), config)
+        .context("Failed to initialize Electrum RPC client")?;
+    check_banner(&electrum, url)?;
+    // Initially fetch the latest block for storing the height.
+    // We do not act on this subscription after this call.
+    let latest_block = electrum
+        .block_headers_subscribe()
+        .map_err(|error| busy_or(error, url))
+        .context("Failed to subscribe to header notifications")?;
+
+    let blockchain = ElectrumBlockchain::from_config(&ElectrumBlockchainConfig {
+        url: url.to_string(),
+        socks5: None,
+        retry: 1,
+        timeout: None,
+        stop_gap,
+        validate_domain: true,
+    })
+    .context("Failed to initialize Electrum RPC client")?;
+
+    Ok((url.clone(), electrum, blockchain, latest_block))
+}
+
+/// How many of the latest chain tips [`ChainTip`] remembers.
+const RECENT_TIPS: usize = 100;
+
+/// The chain tip confirmations and timelocks are computed against.
+///
+/// The tip never moves back because a server reports a lower height, e.g.
+/// after failing over to a server that has not seen the latest blocks yet.
+/// Only a reorganization, i.e. a server whose block differs from the one we
+/// saw at the same height, moves it back.
+#[derive(Debug)]
+struct ChainTip {
+    height: BlockHeight,
+    /// The hashes of the latest tips we saw, by height.
+    recent: BTreeMap<BlockHeight, BlockHash>,
+}
+
+impl ChainTip {
+    fn new(height: BlockHeight, hash: BlockHash) -> Self {
+        Self {
+            height,
+            recent: BTreeMap::from([(height, hash)]),
+        }
+    }
+
+    fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// Moves the tip to block `hash` at `height`, reported by a server that
+    /// looks up the hash of its block at a given height with `hash_at`.
+    ///
+    /// A lower height is compared with the highest tip we saw at or below
+    /// it. If the server has the same block there it just lags behind and
+    /// the tip stays where it is, otherwise the chain was reorganized and the
+    /// tip moves back. If we saw no tip that low, the server is assumed to
+    /// lag behind.
+    fn update(
+        &mut self,
+        height: BlockHeight,
+        hash: BlockHash,
+        hash_at: impl FnOnce(BlockHeight) -> Result<BlockHash>,
+    ) -> Result<()> {
+        if height < self.height {
+            let (known_height, known_hash) = match self.recent.range(..=height).next_back() {
+                Some((known_height, known_hash)) => (*known_height, *known_hash),
+                None => {
+                    tracing::debug!(
+                        block_height = u32::from(height),
+                        tip = u32::from(self.height),
+                        "Electrum server is behind our chain tip"
+                    );
+                    return Ok(());
+                }
+            };
+            let their_hash = if known_height == height {
+                hash
+            } else {
+                hash_at(known_height)?
+            };
+
+            if their_hash == known_hash {
+                tracing::debug!(
+                    block_height = u32::from(height),
+                    tip = u32::from(self.height),
+                    "Electrum server is behind our chain tip"
+                );
+                return Ok(());
+            }
+
+            tracing::warn!(
+                from = u32::from(self.height),
+                to = u32::from(height),
+                "Chain reorganization detected, moving the chain tip back"
+            );
+            self.recent.split_off(&known_height);
+        } else if height > self.height {
+            tracing::debug!(
+                block_height = u32::from(height),
+                "Got notification for new block"
+            );
+        }
+
+        self.height = height;
+        self.recent.insert(height, hash);
+        while self.recent.len() > RECENT_TIPS {
+            self.recent.pop_first();
+        }
+
+        Ok(())
+    }
+}
+
+fn is_busy_or_unreachable(error: &anyhow::Error) -> bool {
+    use bdk::electrum_client::Error;
+
+    error.downcast_ref::<ElectrumServerBusy>().is_some()
+        || matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::IOError(_) | Error::SharedIOError(_) | Error::AllAttemptsErrored(_))
+        )
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptStatus {
+    Unseen,
+    InMempool,
+    Confirmed(Confirmed),
+    Retrying,
+}
+
+impl ScriptStatus {
+    pub fn from_confirmations(confirmations: u32) -> Self {
+        match confirmations {
+            0 => Self::InMempool,
+            confirmations => Self::Confirmed(Confirmed::new(confirmations - 1)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Confirmed {
+    /// The depth of this transaction within the blockchain.
+    ///
+    /// Will be zero if the transaction is included in the latest block.
+    depth: u32,
+}
+
+impl Confirmed {
+    pub fn new(depth: u32) -> Self {
+        Self { depth }
+    }
+
+    /// Compute the depth of a transaction based on its inclusion height and the
+    /// latest known block.
+    ///
+    /// Our information about the latest block might be outdated. To avoid an
+    /// overflow, we make sure the depth is 0 in case the inclusion height
+    /// exceeds our latest known block,
+    pub fn from_inclusion_and_latest_block(inclusion_height: u32, latest_block: u32) -> Self {
+        let depth = latest_block.saturating_sub(inclusion_height);
+
+        Self { depth }
+    }
+
+    pub fn confirmations(&self) -> u32 {
+        self.depth + 1
+    }
+
+    pub fn meets_target<T>(&self, target: T) -> bool
+    where
+        u32: PartialOrd<T>,
+    {
+        self.confirmations() >= target
+    }
+}
+
+impl ScriptStatus {
+    /// Check if the script has any confirmations.
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, ScriptStatus::Confirmed(_))
+    }
+
+    /// Check if the script has met the given confirmation target.
+    pub fn is_confirmed_with<T>(&self, target: T) -> bool
+    where
+        u32: PartialOrd<T>,
+    {
+        match self {
+            ScriptStatus::Confirmed(inner) => inner.meets_target(target),
+            _ => false,
+        }
+    }
+
+    pub fn has_been_seen(&self) -> bool {
+        matches!(self, ScriptStatus::InMempool | ScriptStatus::Confirmed(_))
+    }
+}
+
+impl fmt::Display for ScriptStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptStatus::Unseen => write!(f, "unseen"),
+            ScriptStatus::InMempool => write!(f, "in mempool"),
+            ScriptStatus::Retrying => write!(f, "retrying"),
+            ScriptStatus::Confirmed(inner) => {
+                write!(f, "confirmed with {} blocks", inner.confirmations())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::{CancelTimelock, PublicKey, SecretKey, TxCancel, TxLock};
+    use crate::env::GetConfig;
+    use crate::tracing_ext::capture_logs;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::hashes::hex::ToHex;
+    use bitcoin::hashes::sha256;
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{ecdsa, Message, Secp256k1};
+    use bitcoin::util::bip32::KeySource;
+    use bitcoin::Sighash;
+    use proptest::prelude::*;
+    use rand::rngs::OsRng;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic;
+    use tracing::level_filters::LevelFilter;
+
+    #[test]
+    fn given_empty_mempool_confirms_in_next_block() {
+        assert_eq!(blocks_until_confirmed(&[], 1.0), 1);
+    }
+
+    #[test]
+    fn only_transactions_paying_more_are_ahead() {
+        let histogram = [(50.0, 1_500_000), (20.0, 1_000_000), (5.0, 3_000_000)];
+
+        assert_eq!(blocks_until_confirmed(&histogram, 100.0), 1);
+        assert_eq!(blocks_until_confirmed(&histogram, 30.0), 2);
+        assert_eq!(blocks_until_confirmed(&histogram, 10.0), 3);
+        assert_eq!(blocks_until_confirmed(&histogram, 1.0), 6);
+    }
+
+    #[test]
+    fn fee_scenarios_for_three_targets_have_distinct_fees() {
+        let histogram = [(50.0, 1_500_000), (20.0, 1_000_000), (5.0, 3_000_000)];
+        let estimates = [(1, 60.0), (3, 25.0), (6, 8.0)];
+        let avg_block_time = Duration::from_secs(600);
+
+        let scenarios = estimates
+            .iter()
+            .map(|&(target_block, sats_per_vb)| {
+                fee_scenario(
+                    target_block,
+                    400,
+                    Amount::ONE_BTC,
+                    FeeRate::from_sat_per_vb(sats_per_vb),
+                    Amount::from_sat(100),
+                    &histogram,
+                    avg_block_time,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let fees = scenarios.iter().map(|s| s.fee.to_sat()).collect::<Vec<_>>();
+        assert_eq!(fees, vec![6_000, 2_500, 800]);
+
+        let blocks = scenarios
+            .iter()
+            .map(|s| s.blocks_until_confirmed)
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![1, 2, 3]);
+        assert_eq!(scenarios[2].estimated_time, Duration::from_secs(1800));
+    }
+
+    #[test]
+    fn given_huge_backlog_estimate_saturates() {
+        let histogram = [(50.0, u64::MAX / 2)];
+
+        assert_eq!(blocks_until_confirmed(&histogram, 1.0), u16::MAX);
+    }
+
+    #[test]
+    fn given_depth_0_should_meet_confirmation_target_one() {
+        let script = ScriptStatus::Confirmed(Confirmed { depth: 0 });
+
+        let confirmed = script.is_confirmed_with(1);
+
+        assert!(confirmed)
+    }
+
+    #[test]
+    fn given_confirmations_1_should_meet_confirmation_target_one() {
+        let script = ScriptStatus::from_confirmations(1);
+
+        let confirmed = script.is_confirmed_with(1);
+
+        assert!(confirmed)
+    }
+
+    #[test]
+    fn given_inclusion_after_lastest_known_block_at_least_depth_0() {
+        let included_in = 10;
+        let latest_block = 9;
+
+        let confirmed = Confirmed::from_inclusion_and_latest_block(included_in, latest_block);
+
+        assert_eq!(confirmed.depth, 0)
+    }
+
+    #[test]
+    fn given_one_BTC_and_100k_sats_per_vb_fees_should_not_hit_max() {
+        // 400 weight = 100 vbyte
+        let weight = 400;
+        let amount = bitcoin::Amount::from_sat(100_000_000);
+
+        let sat_per_vb = 100.0;
+        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+        let relay_fee = bitcoin::Amount::ONE_SAT;
+        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+        // weight / 4.0 *  sat_per_vb
+        let should_fee = bitcoin::Amount::from_sat(10_000);
+        assert_eq!(is_fee, should_fee);
+    }
+
+    #[test]
+    fn given_1BTC_and_1_sat_per_vb_fees_and_100ksat_min_relay_fee_should_hit_min() {
+        // 400 weight = 100 vbyte
+        let weight = 400;
+        let amount = bitcoin::Amount::from_sat(100_000_000);
+
+        let sat_per_vb = 1.0;
+        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+        let relay_fee = bitcoin::Amount::from_sat(100_000);
+        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+        // weight / 4.0 *  sat_per_vb would be smaller than relay fee hence we take min
+        // relay fee
+        let should_fee = bitcoin::Amount::from_sat(100_000);
+        assert_eq!(is_fee, should_fee);
+    }
+
+    #[test]
+    fn given_1mio_sat_and_1k_sats_per_vb_fees_should_hit_relative_max() {
+        // 400 weight = 100 vbyte
+        let weight = 400;
+        let amount = bitcoin::Amount::from_sat(1_000_000);
+
+        let sat_per_vb = 1_000.0;
+        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+        let relay_fee = bitcoin::Amount::ONE_SAT;
+        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+        // weight / 4.0 *  sat_per_vb would be greater than 3% hence we take max
+        // relative fee.
+        let should_fee = bitcoin::Amount::from_sat(30_000);
+        assert_eq!(is_fee, should_fee);
+    }
+
+    #[test]
+    fn given_1BTC_and_4mio_sats_per_vb_fees_should_hit_total_max() {
+        // even if we send 1BTC we don't want to pay 0.3BTC in fees. This would be
+        // $1,650 at the moment.
+        let weight = 400;
+        let amount = bitcoin::Amount::from_sat(100_000_000);
+
+        let sat_per_vb = 4_000_000.0;
+        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+        let relay_fee = bitcoin::Amount::ONE_SAT;
+        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+        // weight / 4.0 *  sat_per_vb would be greater than 3% hence we take total
+        // max allowed fee.
+        assert_eq!(is_fee.to_sat(), MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+    }
+
+    proptest! {
+        #[test]
+        fn given_randon_amount_random_fee_and_random_relay_rate_but_fix_weight_does_not_error(
+            amount in 547u64..,
+            sat_per_vb in 1.0f32..100_000_000.0f32,
+            relay_fee in 0u64..100_000_000u64
+        ) {
+            let weight = 400;
+            let amount = bitcoin::Amount::from_sat(amount);
+
+            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+            let relay_fee = bitcoin::Amount::from_sat(relay_fee);
+            let _is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn given_amount_in_range_fix_fee_fix_relay_rate_fix_weight_fee_always_smaller_max(
+            amount in 1u64..100_000_000,
+        ) {
+            let weight = 400;
+            let amount = bitcoin::Amount::from_sat(amount);
+
+            let sat_per_vb = 100.0;
+            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+            let relay_fee = bitcoin::Amount::ONE_SAT;
+            let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+            // weight / 4 * 1_000 is always lower than MAX_ABSOLUTE_TX_FEE
+            assert!(is_fee.to_sat() < MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn given_amount_high_fix_fee_fix_relay_rate_fix_weight_fee_always_max(
+            amount in 100_000_000u64..,
+        ) {
+            let weight = 400;
+            let amount = bitcoin::Amount::from_sat(amount);
+
+            let sat_per_vb = 1_000.0;
+            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+            let relay_fee = bitcoin::Amount::ONE_SAT;
+            let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+
+            // weight / 4 * 1_000  is always higher than MAX_ABSOLUTE_TX_FEE
+            assert!(is_fee.to_sat() >= MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn given_fee_above_max_should_always_errors(
+            sat_per_vb in 100_000_000.0f32..,
+        ) {
+            let weight = 400;
+            let amount = bitcoin::Amount::from_sat(547u64);
+
+            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+
+            let relay_fee = bitcoin::Amount::from_sat(1);
+            assert!(estimate_fee(weight, amount, fee_rate, relay_fee).is_err());
+
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn given_relay_fee_above_max_should_always_errors(
+            relay_fee in 100_000_000u64..
+        ) {
+            let weight = 400;
+            let amount = bitcoin::Amount::from_sat(547u64);
+
+            let fee_rate = FeeRate::from_sat_per_vb(1.0);
+
+            let relay_fee = bitcoin::Amount::from_sat(relay_fee);
+            assert!(estimate_fee(weight, amount, fee_rate, relay_fee).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn given_no_balance_returns_amount_0() {
+        let wallet = WalletBuilder::new(0).with_fees(1.0, 1).build();
+        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+
+        assert_eq!(amount, Amount::ZERO);
+    }
+
+    #[tokio::test]
+    async fn given_balance_below_min_relay_fee_returns_amount_0() {
+        let wallet = WalletBuilder::new(1000).with_fees(1.0, 1001).build();
+        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+
+        assert_eq!(amount, Amount::ZERO);
+    }
+
+    #[tokio::test]
+    async fn given_balance_above_relay_fee_returns_amount_greater_0() {
+        let wallet = WalletBuilder::new(10_000).build();
+        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+
+        assert!(amount.to_sat() > 0);
+    }
+
+    /// This test ensures that the relevant script output of the transaction
+    /// created out of the PSBT is at index 0. This is important because
+    /// subscriptions to the transaction are on index `0` when broadcasting the
+    /// transaction.
+    #[tokio::test]
+    async fn given_amounts_with_change_outputs_when_signing_tx_then_output_index_0_is_ensured_for_script(
+    ) {
+        // This value is somewhat arbitrary but the indexation problem usually occurred
+        // on the first or second value (i.e. 547, 548) We keep the test
+        // iterations relatively low because these tests are expensive.
+        let above_dust = 547;
+        let balance = 2000;
+
+        // We don't care about fees in this test, thus use a zero fee rate
+        let wallet = WalletBuilder::new(balance).with_zero_fees().build();
+
+        // sorting is only relevant for amounts that have a change output
+        // if the change output is below dust it will be dropped by the BDK
+        for amount in above_dust..(balance - (above_dust - 1)) {
+            let (A, B) = (PublicKey::random(), PublicKey::random());
+            let change = wallet.new_address().await.unwrap();
+            let txlock = TxLock::new(
+                &wallet,
+                bitcoin::Amount::from_sat(amount),
+                A,
+                B,
+                change,
+                None,
+            )
+            .await
+            .unwrap();
+            let txlock_output = txlock.script_pubkey();
+
+            let tx = wallet.sign_and_finalize(txlock.into()).await.unwrap();
+            let tx_output = tx.output[0].script_pubkey.clone();
+
+            assert_eq!(
+                tx_output, txlock_output,
+                "Output {:?} index mismatch for amount {} and balance {}",
+                tx.output, amount, balance
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn given_external_signer_wallet_spend_is_signed_by_it() {
+        let key = "tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m".parse::<ExtendedPrivKey>().unwrap();
+        let signer = Arc::new(MockSigner {
+            key,
+            invocations: Default::default(),
+        });
+        let wallet = WalletBuilder::new(50_000)
+            .with_key(key)
+            .build_with_signer(signer.clone());
+
+        let psbt = wallet
+            .send_to_address(
+                wallet.new_address().await.unwrap(),
+                Amount::from_sat(10_000),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let transaction = wallet.sign_and_finalize(psbt).await.unwrap();
+
+        assert_eq!(signer.invocations.load(atomic::Ordering::SeqCst), 1);
+        assert!(!transaction.input[0].witness.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_sync_with_progress_resumes_at_scan_position() {
+        let wallet = WalletBuilder::new(100_000).build();
+        let scripts = {
+            let inner = wallet.wallet.lock().await;
+            (0..=25)
+                .map(|index| script_at(&inner, KeychainKind::External, index).unwrap())
+                .collect::<Vec<_>>()
+        };
+        wallet.client.lock().await.used_scripts = vec![scripts[5].clone(), scripts[24].clone()];
+
+        let mut first_run = Vec::new();
+        let error = wallet
+            .sync_with_progress(|progress| {
+                first_run.push(progress);
+                ControlFlow::Break(())
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SyncCancelled>(),
+            Some(&SyncCancelled {
+                progress: first_run[0]
+            })
+        );
+        assert_eq!(first_run.len(), 1);
+
+        wallet
+            .client
+            .lock()
+            .await
+            .scanned_scripts
+            .lock()
+            .unwrap()
+            .clear();
+        let mut second_run = Vec::new();
+        wallet
+            .sync_with_progress(|progress| {
+                second_run.push(progress);
+                ControlFlow::Continue(())
+            })
+            .await
+            .unwrap();
+
+        assert!(second_run
+            .windows(2)
+            .all(|pair| pair[0].scanned < pair[1].scanned));
+        assert!(second_run[0].scanned > first_run[0].scanned);
+        let (last, scan) = second_run.split_last().unwrap();
+        assert_eq!(scan.last().unwrap().percent(), 50);
+        assert_eq!(last.scanned, last.total);
+        assert_eq!(last.percent(), 100);
+
+        let scanned = wallet
+            .client
+            .lock()
+            .await
+            .scanned_scripts
+            .lock()
+            .unwrap()
+            .clone();
+        assert!(!scanned.contains(&scripts[19]));
+        assert!(scanned.contains(&scripts[20]));
+        assert_eq!(wallet.scan_position(KeychainKind::External).unwrap(), None);
+        assert_eq!(
+            wallet.new_address().await.unwrap().script_pubkey(),
+            scripts[25]
+        );
+    }
+
+    #[tokio::test]
+    async fn sweeping_an_output_pays_everything_minus_fee_to_given_address() {
+        let wallet = WalletBuilder::new(50_000).with_num_utxos(2).build();
+        let external = "bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw"
+            .parse::<Address>()
+            .unwrap();
+        let utxo = wallet.wallet.lock().await.list_unspent().unwrap()[0].clone();
+
+        let psbt = wallet
+            .sweep_output(utxo.outpoint, external.clone())
+            .await
+            .unwrap();
+        let transaction = wallet.sign_and_finalize(psbt).await.unwrap();
+
+        assert_eq!(transaction.input.len(), 1);
+        assert_eq!(transaction.input[0].previous_output, utxo.outpoint);
+        match transaction.output.as_slice() {
+            [output] => {
+                assert_eq!(output.script_pubkey, external.script_pubkey());
+                assert!(output.value < 50_000);
+            }
+            _ => panic!("expected exactly one output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn given_address_of_other_network_sweeping_fails() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let mut mainnet = "bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw"
+            .parse::<Address>()
+            .unwrap();
+        mainnet.network = Network::Bitcoin;
+        let utxo = wallet.wallet.lock().await.list_unspent().unwrap()[0].clone();
+
+        let result = wallet.sweep_output(utxo.outpoint, mainnet).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn given_amount_above_balance_returns_insufficient_funds() {
+        let wallet = WalletBuilder::new(50_000).build();
+
+        let error = wallet
+            .ensure_sufficient_funds(Amount::from_sat(60_000))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<InsufficientFunds>(),
+            Some(&InsufficientFunds {
+                have: Amount::from_sat(50_000),
+                need: Amount::from_sat(60_000),
+            })
+        );
+    }
+
+    #[test]
+    fn given_intended_fee_paid_verify_fee_succeeds() {
+        let transaction = transaction_paying(9_000);
+
+        let result = verify_fee(
+            &transaction,
+            &[output_of(10_000)],
+            Amount::from_sat(1_000),
+            Amount::ZERO,
+        );
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn given_miscomputed_fee_strict_mode_fails() {
+        let transaction = transaction_paying(9_000);
+
+        let result = verify_fee(
+            &transaction,
+            &[output_of(10_000)],
+            Amount::from_sat(900),
+            Amount::ZERO,
+        );
+
+        assert_eq!(
+            result,
+            Err(FeeMismatch {
+                txid: transaction.txid(),
+                intended: Amount::from_sat(900),
+                actual: Amount::from_sat(1_000),
+            })
+        );
+    }
+
+    #[test]
+    fn given_fee_within_tolerance_verify_fee_succeeds() {
+        let transaction = transaction_paying(9_000);
+
+        let result = verify_fee(
+            &transaction,
+            &[output_of(10_000)],
+            Amount::from_sat(1_050),
+            Amount::from_sat(50),
+        );
+
+        assert_eq!(result, Ok(()));
+    }
+
+    fn transaction_paying(value: u64) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime::ZERO,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![output_of(value)],
+        }
+    }
+
+    fn output_of(value: u64) -> TxOut {
+        TxOut {
+            value,
+            script_pubkey: Script::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn given_balance_at_target_await_balance_returns_it() {
+        let wallet = WalletBuilder::new(50_000).build();
+
+        let balance = wallet
+            .await_balance(Amount::from_sat(50_000), Duration::from_millis(50))
+            .await
+            .unwrap();
+
+        assert_eq!(balance, Amount::from_sat(50_000));
+    }
+
+    #[tokio::test]
+    async fn given_min_confirmations_of_one_unconfirmed_utxo_does_not_reach_target() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_utxo_confirmations(0)
+            .with_funding_min_confirmations(1)
+            .build();
+
+        let error = wallet
+            .await_balance(Amount::from_sat(50_000), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<BalanceNotReached>(),
+            Some(&BalanceNotReached {
+                balance: Amount::ZERO,
+                target: Amount::from_sat(50_000),
+                timeout: Duration::from_millis(50),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn given_balance_below_target_await_balance_fails_after_timeout() {
+        let wallet = WalletBuilder::new(50_000).build();
+
+        let started = Instant::now();
+        let error = wallet
+            .await_balance(Amount::from_sat(60_000), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(
+            error.downcast_ref::<BalanceNotReached>(),
+            Some(&BalanceNotReached {
+                balance: Amount::from_sat(50_000),
+                target: Amount::from_sat(60_000),
+                timeout: Duration::from_millis(50),
+            })
+        );
+    }
+
+    #[test]
+    fn given_busy_response_fail_over_connects_to_next_server_in_pool() {
+        let busy: Url = "tcp://busy.example:50001".parse().unwrap();
+        let idle: Url = "tcp://idle.example:50001".parse().unwrap();
+
+        let connected = fail_over(&[busy.clone(), idle.clone()], |url| {
+            if url == &busy {
+                let response = serde_json::json!({
+                    "code": -101,
+                    "message": "Server busy - too many connections"
+                });
+                return Err(busy_or(
+                    bdk::electrum_client::Error::Protocol(response),
+                    url,
+                ));
+            }
+
+            Ok(url.clone())
+        })
+        .unwrap();
+
+        assert_eq!(connected, idle);
+    }
+
+    #[test]
+    fn given_server_behind_our_tip_then_tip_does_not_move_back() {
+        let mut tip = ChainTip::new(BlockHeight::new(100), block_hash(100));
+        tip.update(BlockHeight::new(110), block_hash(110), |_| unreachable!())
+            .unwrap();
+
+        tip.update(BlockHeight::new(105), block_hash(105), |height| {
+            assert_eq!(height, BlockHeight::new(100));
+            Ok(block_hash(100))
+        })
+        .unwrap();
+
+        assert_eq!(tip.height(), BlockHeight::new(110));
+    }
+
+    #[test]
+    fn given_server_on_other_chain_at_lower_height_then_tip_moves_back() {
+        let mut tip = ChainTip::new(BlockHeight::new(100), block_hash(100));
+        tip.update(BlockHeight::new(110), block_hash(110), |_| unreachable!())
+            .unwrap();
+
+        tip.update(BlockHeight::new(105), block_hash(105), |_| {
+            Ok(block_hash(0xaa))
+        })
+        .unwrap();
+
+        assert_eq!(tip.height(), BlockHeight::new(105));
+    }
+
+    #[test]
+    fn given_failover_to_server_behind_then_height_does_not_regress() {
+        let ahead = electrum_server(Network::Regtest, 110, HashMap::new());
+        let behind = electrum_server(Network::Regtest, 105, HashMap::new());
+        let mut client =
+            Client::connect_to_pool(&[ahead, behind.clone()], Duration::ZERO, DEFAULT_STOP_GAP)
+                .unwrap();
+        assert_eq!(client.tip.height(), BlockHeight::new(110));
+
+        client.fail_over().unwrap();
+        client.update_state(true).unwrap();
+
+        assert_eq!(client.electrum_rpc_url, behind);
+        assert_eq!(client.tip.height(), BlockHeight::new(110));
+    }
+
+    #[tokio::test]
+    async fn given_busy_server_then_query_fails_over_and_runs_again() {
+        let busy = electrum_server(Network::Regtest, 100, HashMap::new());
+        let idle = electrum_server(Network::Regtest, 100, HashMap::new());
+        let client = Mutex::new(
+            Client::connect_to_pool(&[busy, idle.clone()], Duration::ZERO, DEFAULT_STOP_GAP)
+                .unwrap()
+                .with_reconnect_jitter(Duration::from_millis(10)),
+        );
+        let mut attempts = 0;
+
+        let queried = Client::query_with_fail_over(&client, |client| {
+            attempts += 1;
+            if attempts == 1 {
+                let response = serde_json::json!({
+                    "code": -101,
+                    "message": "Server busy - too many connections"
+                });
+                return Err(busy_or(
+                    bdk::electrum_client::Error::Protocol(response),
+                    &client.electrum_rpc_url,
+                ));
+            }
+
+            Ok(client.electrum_rpc_url.clone())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(queried, idle);
+    }
+
+    #[test]
+    fn given_other_error_fail_over_does_not_try_next_server() {
+        let wrong_chain: Url = "tcp://mainnet.example:50001".parse().unwrap();
+        let mut attempts = 0;
+
+        let error = fail_over(
+            &[wrong_chain, "tcp://idle.example:50001".parse().unwrap()],
+            |_| -> Result<()> {
+                attempts += 1;
+                bail!(WrongChain {
+                    network: Network::Regtest,
+                    genesis: genesis_block(Network::Bitcoin).block_hash(),
+                })
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(attempts, 1);
+        assert!(error.is::<WrongChain>());
+    }
+
+    #[tokio::test]
+    async fn given_withdrawal_rejected_for_low_fee_then_retries_at_bumped_fee_rate() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_fees(2.0, 1000)
+            .with_relay_fee_rate(3.0)
+            .build();
+        let address = wallet.new_address().await.unwrap();
+
+        let txid = wallet
+            .send(address, Amount::from_sat(50_000), "withdraw")
+            .await
+            .unwrap();
+
+        let published = wallet.client.lock().await.published();
+        let transaction = match published.as_slice() {
+            [transaction] => transaction,
+            _ => panic!("expected exactly one published transaction"),
+        };
+        let outputs = transaction
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        let fee = 100_000 - outputs;
+        assert_eq!(transaction.txid(), txid);
+        assert!(fee >= FeeRate::from_sat_per_vb(4.0).fee_vb(transaction.vsize()));
+    }
+
+    #[tokio::test]
+    async fn given_bumped_withdrawal_fee_above_maximum_then_fails() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_fees(2.0, 1000)
+            .with_relay_fee_rate(5.0)
+            .build();
+        let address = wallet.new_address().await.unwrap();
+
+        let error = wallet
+            .send(address, Amount::from_sat(10_000), "withdraw")
+            .await
+            .unwrap_err();
+
+        assert!(error.is::<FeeAboveMaximum>());
+        assert!(wallet.client.lock().await.published().is_empty());
+    }
+
+    #[tokio::test]
+    async fn given_cancel_rejected_for_low_fee_then_published_together_with_anchor_spend() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_fees(10.0, 1000)
+            .with_relay_fee_rate(5.0)
+            .build();
+        let a = SecretKey::new_random(&mut OsRng);
+        let b = SecretKey::new_random(&mut OsRng);
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(10_000),
+            a.public(),
+            b.public(),
+            wallet.new_address().await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        let signed_lock = wallet
+            .sign_and_finalize(tx_lock.clone().into())
+            .await
+            .unwrap();
+        wallet.publish(signed_lock, "lock").await.unwrap();
+        // 1 sat/vB, rejected on its own
+        let cancel_fee = Amount::from_sat(TxCancel::weight_with_anchor() as u64 / 4);
+        let tx_cancel = TxCancel::with_anchor(
+            &tx_lock,
+            CancelTimelock::new(1),
+            a.public(),
+            b.public(),
+            cancel_fee,
+        );
+        let anchor = tx_cancel.anchor_outpoint().unwrap();
+        let sig_b = b.sign(tx_cancel.digest());
+        let cancel = tx_cancel.complete_as_alice(a, b.public(), sig_b).unwrap();
+
+        let txid = wallet
+            .publish_with_anchor(cancel.clone(), cancel_fee, "cancel")
+            .await
+            .unwrap();
+
+        let published = wallet.client.lock().await.published();
+        match published.as_slice() {
+            [_, parent, child] => {
+                assert_eq!(parent, &cancel);
+                assert!(child
+                    .input
+                    .iter()
+                    .any(|input| input.previous_output == anchor));
+            }
+            _ => panic!("expected the cancel transaction to be published with a child"),
+        }
+        assert_eq!(txid, cancel.txid());
+    }
+
+    #[tokio::test]
+    async fn given_cancel_accepted_below_estimated_fee_rate_then_bumped_via_anchor() {
+        let wallet = WalletBuilder::new(50_000)
+            .with_fees(10.0, 1000)
+            .with_relay_fee_rate(1.0)
+            .build();
+        let a = SecretKey::new_random(&mut OsRng);
+        let b = SecretKey::new_random(&mut OsRng);
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(10_000),
+            a.public(),
+            b.public(),
+            wallet.new_address().await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        let signed_lock = wallet
+            .sign_and_finalize(tx_lock.clone().into())
+            .await
+            .unwrap();
+        wallet.publish(signed_lock, "lock").await.unwrap();
+        let cancel_fee = Amount::from_sat(TxCancel::weight_with_anchor() as u64 / 2);
+        let tx_cancel = TxCancel::with_anchor(
+            &tx_lock,
+            CancelTimelock::new(1),
+            a.public(),
+            b.public(),
+            cancel_fee,
+        );
+        let anchor = tx_cancel.anchor_outpoint().unwrap();
+        let sig_b = b.sign(tx_cancel.digest());
+        let cancel = tx_cancel.complete_as_alice(a, b.public(), sig_b).unwrap();
+
+        wallet
+            .publish_with_anchor(cancel.clone(), cancel_fee, "cancel")
+            .await
+            .unwrap();
+
+        let published = wallet.client.lock().await.published();
+        match published.as_slice() {
+            [_, parent, child] => {
+                assert_eq!(parent, &cancel);
+                assert!(child
+                    .input
+                    .iter()
+                    .any(|input| input.previous_output == anchor));
+            }
+            _ => panic!("expected the cancel transaction to be bumped by a child"),
+        }
+    }
+
+    #[tokio::test]
+    async fn given_other_broadcast_error_then_does_not_retry() {
+        let mut attempts = 0;
+
+        let error = retry_with_bumped_fee(FeeRate::from_sat_per_vb(2.0), |_| {
+            attempts += 1;
+
+            async { Err::<(), _>(anyhow::anyhow!("bad-txns-inputs-missingorspent")) }
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(attempts, 1);
+        assert!(!is_fee_too_low(&error));
+    }
+
+    #[test]
+    fn busy_banner_is_detected_regardless_of_case() {
+        assert!(is_busy_response("SERVER BUSY, please try again later"));
+        assert!(!is_busy_response("Welcome to electrs"));
+    }
+
+    #[tokio::test]
+    async fn given_amount_within_balance_funds_are_sufficient() {
+        let wallet = WalletBuilder::new(50_000).build();
+
+        let result = wallet
+            .ensure_sufficient_funds(Amount::from_sat(50_000))
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn anchor_spend_pays_fee_rate_for_package_of_low_fee_cancel() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let a = SecretKey::new_random(&mut OsRng);
+        let b = SecretKey::new_random(&mut OsRng);
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(10_000),
+            a.public(),
+            b.public(),
+            wallet.new_address().await.unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        let parent_fee = Amount::from_sat(200);
+        let tx_cancel = TxCancel::with_anchor(
+            &tx_lock,
+            CancelTimelock::new(1),
+            a.public(),
+            b.public(),
+            parent_fee,
+        );
+        let anchor = tx_cancel.anchor_outpoint().unwrap();
+        let sig_b = b.sign(tx_cancel.digest());
+        let parent = tx_cancel.complete_as_alice(a, b.public(), sig_b).unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(20.0);
+
+        let psbt = wallet
+            .spend_anchor(&parent, parent_fee, fee_rate)
+            .await
+            .unwrap();
+        let inputs = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.as_ref().unwrap().value)
+            .sum::<u64>();
+        let child = wallet.sign_and_finalize(psbt).await.unwrap();
+        let outputs = child.output.iter().map(|output| output.value).sum::<u64>();
+        let child_fee = inputs - outputs;
+
+        assert!(child
+            .input
+            .iter()
+            .any(|input| input.previous_output == anchor));
+        assert!(
+            child_fee + parent_fee.to_sat() >= fee_rate.fee_wu(parent.weight() + child.weight())
+        );
+    }
+
+    #[tokio::test]
+    async fn can_override_change_address() {
+        let wallet = WalletBuilder::new(50_000).build();
+        let custom_change = "bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw"
+            .parse::<Address>()
+            .unwrap();
+
+        let psbt = wallet
+            .send_to_address(
+                wallet.new_address().await.unwrap(),
+                Amount::from_sat(10_000),
+                Some(custom_change.clone()),
+                None,
+            )
+            .await
+            .unwrap();
+        let transaction = wallet.sign_and_finalize(psbt).await.unwrap();
+
+        match transaction.output.as_slice() {
+            [first, change] => {
+                assert_eq!(first.value, 10_000);
+                assert_eq!(change.script_pubkey, custom_change.script_pubkey());
+            }
+            _ => panic!("expected exactly two outputs"),
+        }
+    }
+
+    #[tokio::test]
+    async fn given_fee_rate_override_lock_transaction_pays_overridden_fee_rate() {
+        let wallet = WalletBuilder::new(1_000_000).with_fees(1.0, 1).build();
+        let (A, B) = (PublicKey::random(), PublicKey::random());
+        let change = wallet.new_address().await.unwrap();
+
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(500_000),
+            A,
+            B,
+            change,
+            Some(FeeRate::from_sat_per_vb(10.0)),
+        )
+        .await
+        .unwrap();
+        let transaction = wallet.sign_and_finalize(tx_lock.into()).await.unwrap();
+
+        let spent = transaction
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        let fee = 1_000_000 - spent;
+        let vsize = transaction.vsize() as u64;
+
+        assert!(
+            fee >= 10 * vsize && fee < 11 * vsize,
+            "expected fee of {} sats to pay 10 sats/vB for {} vbytes",
+            fee,
+            vsize
+        );
+    }
+
+    struct FixedFeeEstimator(f64);
+
+    #[async_trait::async_trait]
+    impl FeeEstimator for FixedFeeEstimator {
+        async fn sat_per_vb(&self, _target: u16) -> Result<f64> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn given_fee_estimator_lock_transaction_pays_its_fee_rate() {
+        let wallet = WalletBuilder::new(1_000_000)
+            .with_fees(1.0, 1)
+            .build()
+            .with_fee_estimator(FixedFeeEstimator(20.0));
+        let (A, B) = (PublicKey::random(), PublicKey::random());
+        let change = wallet.new_address().await.unwrap();
+
+        let tx_lock = TxLock::new(&wallet, Amount::from_sat(500_000), A, B, change, None)
+            .await
+            .unwrap();
+        let transaction = wallet.sign_and_finalize(tx_lock.into()).await.unwrap();
+
+        let spent = transaction
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        let fee = 1_000_000 - spent;
+        let vsize = transaction.vsize() as u64;
+
+        assert!(
+            fee >= 20 * vsize && fee < 21 * vsize,
+            "expected fee of {} sats to pay 20 sats/vB for {} vbytes",
+            fee,
+            vsize
+        );
+    }
+
+    #[tokio::test]
+    async fn given_estimate_below_min_relay_fee_rate_lock_transaction_pays_floor() {
+        let wallet = WalletBuilder::new(1_000_000)
+            .with_fees(1.0, 1)
+            .with_min_relay_fee_rate(10)
+            .build();
+        let (A, B) = (PublicKey::random(), PublicKey::random());
+        let change = wallet.new_address().await.unwrap();
+
+        let tx_lock = TxLock::new(&wallet, Amount::from_sat(500_000), A, B, change, None)
+            .await
+            .unwrap();
+        let transaction = wallet.sign_and_finalize(tx_lock.into()).await.unwrap();
+
+        let spent = transaction
+            .output
+            .iter()
+            .map(|output| output.value)
+            .sum::<u64>();
+        let fee = 1_000_000 - spent;
+        let vsize = transaction.vsize() as u64;
+
+        assert!(
+            fee >= 10 * vsize,
+            "expected fee of {} sats to pay at least 10 sats/vB for {} vbytes",
+            fee,
+            vsize
+        );
+    }
+
+    #[tokio::test]
+    async fn given_estimate_below_min_relay_fee_rate_estimated_fee_uses_floor() {
+        let wallet = WalletBuilder::new(1_000_000)
+            .with_fees(1.0, 1)
+            .with_min_relay_fee_rate(10)
+            .build();
+
+        let fee = wallet
+            .estimate_fee(400, Amount::from_sat(100_000))
+            .await
+            .unwrap();
+
+        assert_eq!(fee, Amount::from_sat(1_000));
+    }
 
-        let sat_per_vb = 4_000_000.0;
-        let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+    #[tokio::test]
+    async fn given_min_confirmations_of_one_unconfirmed_utxo_is_not_sufficient_funds() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_utxo_confirmations(0)
+            .with_funding_min_confirmations(1)
+            .build();
 
-        let relay_fee = bitcoin::Amount::ONE_SAT;
-        let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+        let error = wallet
+            .ensure_sufficient_funds(Amount::from_sat(60_000))
+            .await
+            .unwrap_err();
 
-        // weight / 4.0 *  sat_per_vb would be greater than 3% hence we take total
-        // max allowed fee.
-        assert_eq!(is_fee.to_sat(), MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+        assert_eq!(
+            error.downcast_ref::<InsufficientFunds>(),
+            Some(&InsufficientFunds {
+                have: Amount::ZERO,
+                need: Amount::from_sat(60_000),
+            })
+        );
     }
 
-    proptest! {
-        #[test]
-        fn given_randon_amount_random_fee_and_random_relay_rate_but_fix_weight_does_not_error(
-            amount in 547u64..,
-            sat_per_vb in 1.0f32..100_000_000.0f32,
-            relay_fee in 0u64..100_000_000u64
-        ) {
-            let weight = 400;
-            let amount = bitcoin::Amount::from_sat(amount);
-
-            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+    #[tokio::test]
+    async fn given_min_confirmations_of_one_unconfirmed_utxo_is_not_used_for_funding() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_utxo_confirmations(0)
+            .with_funding_min_confirmations(1)
+            .build();
+        let address = wallet.new_address().await.unwrap();
 
-            let relay_fee = bitcoin::Amount::from_sat(relay_fee);
-            let _is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+        let result = wallet
+            .send_to_address(address, Amount::from_sat(50_000), None, None)
+            .await;
+        let max_giveable = wallet.max_giveable(TxLock::script_size()).await.unwrap();
 
-        }
+        assert!(result.is_err());
+        assert_eq!(max_giveable, Amount::ZERO);
     }
 
-    proptest! {
-        #[test]
-        fn given_amount_in_range_fix_fee_fix_relay_rate_fix_weight_fee_always_smaller_max(
-            amount in 1u64..100_000_000,
-        ) {
-            let weight = 400;
-            let amount = bitcoin::Amount::from_sat(amount);
+    #[tokio::test]
+    async fn given_min_confirmations_of_one_confirmed_utxo_is_used_for_funding() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_utxo_confirmations(1)
+            .with_funding_min_confirmations(1)
+            .build();
+        let address = wallet.new_address().await.unwrap();
 
-            let sat_per_vb = 100.0;
-            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+        let result = wallet
+            .send_to_address(address, Amount::from_sat(50_000), None, None)
+            .await;
+        let max_giveable = wallet.max_giveable(TxLock::script_size()).await.unwrap();
 
-            let relay_fee = bitcoin::Amount::ONE_SAT;
-            let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+        assert!(result.is_ok());
+        assert!(max_giveable > Amount::ZERO);
+    }
 
-            // weight / 4 * 1_000 is always lower than MAX_ABSOLUTE_TX_FEE
-            assert!(is_fee.to_sat() < MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+    #[test]
+    fn wif_of_test_network_is_accepted_on_all_test_networks_only() {
+        for wallet in [Network::Testnet, Network::Signet, Network::Regtest] {
+            assert!(ensure_wif_network(Network::Testnet, wallet).is_ok());
+            assert!(ensure_wif_network(Network::Bitcoin, wallet).is_err());
         }
+
+        assert!(ensure_wif_network(Network::Bitcoin, Network::Bitcoin).is_ok());
+        assert_eq!(
+            ensure_wif_network(Network::Testnet, Network::Bitcoin)
+                .unwrap_err()
+                .downcast_ref::<WifNetworkMismatch>(),
+            Some(&WifNetworkMismatch {
+                wif: Network::Testnet,
+                wallet: Network::Bitcoin
+            })
+        );
     }
 
-    proptest! {
-        #[test]
-        fn given_amount_high_fix_fee_fix_relay_rate_fix_weight_fee_always_max(
-            amount in 100_000_000u64..,
-        ) {
-            let weight = 400;
-            let amount = bitcoin::Amount::from_sat(amount);
+    #[tokio::test]
+    async fn given_replaceable_pending_spend_cancel_pays_everything_back_to_wallet() {
+        let wallet = WalletBuilder::new(100_000).with_pending_spend(true).build();
 
-            let sat_per_vb = 1_000.0;
-            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+        let pending = wallet.pending_transactions().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        let original = wallet
+            .wallet
+            .lock()
+            .await
+            .get_tx(&pending[0], true)
+            .unwrap()
+            .unwrap()
+            .transaction
+            .unwrap();
 
-            let relay_fee = bitcoin::Amount::ONE_SAT;
-            let is_fee = estimate_fee(weight, amount, fee_rate, relay_fee).unwrap();
+        let psbt = wallet.cancel_pending(pending[0]).await.unwrap();
+        let replacement = wallet.sign_and_finalize(psbt).await.unwrap();
 
-            // weight / 4 * 1_000  is always higher than MAX_ABSOLUTE_TX_FEE
-            assert!(is_fee.to_sat() >= MAX_ABSOLUTE_TX_FEE.to_u64().unwrap());
+        assert_eq!(replacement.input.len(), 1);
+        assert_eq!(
+            replacement.input[0].previous_output,
+            original.input[0].previous_output
+        );
+        assert!(replacement.input[0].sequence.is_rbf());
+        match replacement.output.as_slice() {
+            [output] => {
+                assert!(wallet
+                    .wallet
+                    .lock()
+                    .await
+                    .is_mine(&output.script_pubkey)
+                    .unwrap());
+                let fee = 100_000 - output.value;
+                assert!(
+                    fee >= 1_000 + original.vsize() as u64,
+                    "expected fee of {} sats to exceed the original fee by 1 sat/vB",
+                    fee
+                );
+            }
+            _ => panic!("expected exactly one output"),
         }
     }
 
-    proptest! {
-        #[test]
-        fn given_fee_above_max_should_always_errors(
-            sat_per_vb in 100_000_000.0f32..,
-        ) {
-            let weight = 400;
-            let amount = bitcoin::Amount::from_sat(547u64);
-
-            let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
+    #[tokio::test]
+    async fn given_non_replaceable_pending_spend_cancel_is_rejected() {
+        let wallet = WalletBuilder::new(100_000)
+            .with_pending_spend(false)
+            .build();
+        let pending = wallet.pending_transactions().await.unwrap();
 
-            let relay_fee = bitcoin::Amount::from_sat(1);
-            assert!(estimate_fee(weight, amount, fee_rate, relay_fee).is_err());
+        let error = wallet.cancel_pending(pending[0]).await.unwrap_err();
 
-        }
+        assert_eq!(
+            error.downcast_ref::<TransactionNotReplaceable>(),
+            Some(&TransactionNotReplaceable { txid: pending[0] })
+        );
     }
 
-    proptest! {
-        #[test]
-        fn given_relay_fee_above_max_should_always_errors(
-            relay_fee in 100_000_000u64..
-        ) {
-            let weight = 400;
-            let amount = bitcoin::Amount::from_sat(547u64);
+    #[tokio::test]
+    async fn given_confirmed_transaction_cancel_is_rejected() {
+        let wallet = WalletBuilder::new(100_000).build();
+        assert!(wallet.pending_transactions().await.unwrap().is_empty());
+        let txid = wallet.wallet.lock().await.list_transactions(false).unwrap()[0].txid;
 
-            let fee_rate = FeeRate::from_sat_per_vb(1.0);
+        let error = wallet.cancel_pending(txid).await.unwrap_err();
 
-            let relay_fee = bitcoin::Amount::from_sat(relay_fee);
-            assert!(estimate_fee(weight, amount, fee_rate, relay_fee).is_err());
-        }
+        assert_eq!(
+            error.downcast_ref::<TransactionConfirmed>(),
+            Some(&TransactionConfirmed { txid })
+        );
     }
 
     #[tokio::test]
-    async fn given_no_balance_returns_amount_0() {
-        let wallet = WalletBuilder::new(0).with_fees(1.0, 1).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+    async fn given_fee_rate_override_estimated_fee_uses_override() {
+        let wallet = WalletBuilder::new(1_000_000).with_fees(1.0, 1).build();
+
+        let fee = wallet
+            .estimate_fee_with_override(
+                400,
+                Amount::from_sat(100_000),
+                Some(FeeRate::from_sat_per_vb(10.0)),
+            )
+            .await
+            .unwrap();
 
-        assert_eq!(amount, Amount::ZERO);
+        assert_eq!(fee, Amount::from_sat(1_000));
     }
 
     #[tokio::test]
-    async fn given_balance_below_min_relay_fee_returns_amount_0() {
-        let wallet = WalletBuilder::new(1000).with_fees(1.0, 1001).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
+    async fn given_fee_rate_override_above_maximum_fee_is_rejected() {
+        let wallet = WalletBuilder::new(1_000_000).with_fees(1.0, 1).build();
+        let (A, B) = (PublicKey::random(), PublicKey::random());
+        let change = wallet.new_address().await.unwrap();
+        let fee_rate = FeeRate::from_sat_per_vb(1_000.0);
+
+        let estimate_error = wallet
+            .estimate_fee_with_override(400, Amount::from_sat(100_000), Some(fee_rate))
+            .await
+            .unwrap_err();
+        let lock_error = TxLock::new(
+            &wallet,
+            Amount::from_sat(500_000),
+            A,
+            B,
+            change,
+            Some(fee_rate),
+        )
+        .await
+        .unwrap_err();
 
-        assert_eq!(amount, Amount::ZERO);
+        assert!(estimate_error.downcast_ref::<FeeAboveMaximum>().is_some());
+        assert!(lock_error.downcast_ref::<FeeAboveMaximum>().is_some());
     }
 
     #[tokio::test]
-    async fn given_balance_above_relay_fee_returns_amount_greater_0() {
-        let wallet = WalletBuilder::new(10_000).build();
-        let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
-
-        assert!(amount.to_sat() > 0);
+    async fn duplicate_queries_within_one_operation_hit_the_cache() {
+        let wallet = WalletBuilder::new(100_000).with_fees(10.0, 1).build();
+        let amount = Amount::from_sat(100_000);
+
+        let mut operation = wallet.operation();
+        let first = operation.estimate_fee(400, amount).await.unwrap();
+        let second = operation.estimate_fee(400, amount).await.unwrap();
+        let other_weight = operation.estimate_fee(800, amount).await.unwrap();
+
+        assert_eq!(first, second);
+        assert!(other_weight > first);
+        assert_eq!(wallet.client.lock().await.queries(), 2);
     }
 
-    /// This test ensures that the relevant script output of the transaction
-    /// created out of the PSBT is at index 0. This is important because
-    /// subscriptions to the transaction are on index `0` when broadcasting the
-    /// transaction.
     #[tokio::test]
-    async fn given_amounts_with_change_outputs_when_signing_tx_then_output_index_0_is_ensured_for_script(
-    ) {
-        // This value is somewhat arbitrary but the indexation problem usually occurred
-        // on the first or second value (i.e. 547, 548) We keep the test
-        // iterations relatively low because these tests are expensive.
-        let above_dust = 547;
-        let balance = 2000;
+    async fn operation_does_not_hold_the_client_lock() {
+        let wallet = WalletBuilder::new(100_000).build();
+        let amount = Amount::from_sat(100_000);
 
-        // We don't care about fees in this test, thus use a zero fee rate
-        let wallet = WalletBuilder::new(balance).with_zero_fees().build();
+        let mut operation = wallet.operation();
+        operation.estimate_fee(400, amount).await.unwrap();
 
-        // sorting is only relevant for amounts that have a change output
-        // if the change output is below dust it will be dropped by the BDK
-        for amount in above_dust..(balance - (above_dust - 1)) {
-            let (A, B) = (PublicKey::random(), PublicKey::random());
-            let change = wallet.new_address().await.unwrap();
-            let txlock = TxLock::new(&wallet, bitcoin::Amount::from_sat(amount), A, B, change)
+        let estimate =
+            tokio::time::timeout(Duration::from_secs(1), wallet.estimate_fee(400, amount))
                 .await
-                .unwrap();
-            let txlock_output = txlock.script_pubkey();
+                .expect("client lock to be free while the operation lives");
+        assert_eq!(
+            estimate.unwrap(),
+            operation.estimate_fee(400, amount).await.unwrap()
+        );
+    }
 
-            let tx = wallet.sign_and_finalize(txlock.into()).await.unwrap();
-            let tx_output = tx.output[0].script_pubkey.clone();
+    #[tokio::test]
+    async fn cache_is_not_shared_across_operations() {
+        let wallet = WalletBuilder::new(100_000).build();
+        let amount = Amount::from_sat(100_000);
 
-            assert_eq!(
-                tx_output, txlock_output,
-                "Output {:?} index mismatch for amount {} and balance {}",
-                tx.output, amount, balance
-            );
-        }
+        wallet.estimate_fee(400, amount).await.unwrap();
+        wallet.estimate_fee(400, amount).await.unwrap();
+
+        assert_eq!(wallet.client.lock().await.queries(), 4);
     }
 
     #[tokio::test]
-    async fn can_override_change_address() {
-        let wallet = WalletBuilder::new(50_000).build();
-        let custom_change = "bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw"
-            .parse::<Address>()
-            .unwrap();
+    async fn pre_lock_subscription_aborts_after_max_electrum_reconnects() {
+        let (_status_sender, failed_reconnects_sender, subscription) = subscription(3);
+        let subscription = subscription.abort_if_electrum_unavailable();
 
-        let psbt = wallet
-            .send_to_address(
-                wallet.new_address().await.unwrap(),
-                Amount::from_sat(10_000),
-                Some(custom_change.clone()),
-            )
+        tokio::spawn(async move {
+            for attempt in 1..=3 {
+                failed_reconnects_sender.send(attempt).unwrap();
+            }
+        });
+
+        let error = subscription.wait_until_seen().await.unwrap_err();
+
+        assert_eq!(
+            error.downcast::<ElectrumUnavailable>().unwrap(),
+            ElectrumUnavailable { attempts: 3 }
+        );
+    }
+
+    #[tokio::test]
+    async fn post_lock_subscription_keeps_retrying_beyond_max_electrum_reconnects() {
+        let (status_sender, failed_reconnects_sender, subscription) = subscription(3);
+        failed_reconnects_sender.send(10).unwrap();
+
+        let wait_until_seen = tokio::spawn(async move { subscription.wait_until_seen().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!wait_until_seen.is_finished());
+
+        status_sender.send(ScriptStatus::InMempool).unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), wait_until_seen)
             .await
+            .expect("subscription to resolve once the server is back")
             .unwrap();
-        let transaction = wallet.sign_and_finalize(psbt).await.unwrap();
 
-        match transaction.output.as_slice() {
-            [first, change] => {
-                assert_eq!(first.value, 10_000);
-                assert_eq!(change.script_pubkey, custom_change.script_pubkey());
-            }
-            _ => panic!("expected exactly two outputs"),
-        }
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn wait_until_seen_within_fails_if_transaction_stays_unseen() {
+        let (_status_sender, _failed_reconnects_sender, subscription) = subscription(3);
+
+        let error = subscription
+            .wait_until_seen_within(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains("was not seen within"));
+    }
+
+    #[tokio::test]
+    async fn wait_until_seen_within_succeeds_once_transaction_is_seen() {
+        let (status_sender, _failed_reconnects_sender, subscription) = subscription(3);
+        status_sender.send(ScriptStatus::InMempool).unwrap();
+
+        let result = subscription
+            .wait_until_seen_within(Duration::from_secs(5))
+            .await;
+
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -1287,10 +4922,206 @@ DEBUG swap::bitcoin::wallet: Bitcoin transaction status changed txid=00000000000
         )
     }
 
+    fn subscription(
+        max_electrum_reconnects: u32,
+    ) -> (
+        watch::Sender<ScriptStatus>,
+        watch::Sender<u32>,
+        Subscription,
+    ) {
+        Subscription::stub(max_electrum_reconnects)
+    }
+
     fn confs(confirmations: u32) -> ScriptStatus {
         ScriptStatus::from_confirmations(confirmations)
     }
 
+    /// Signs with keys derived from `key`, as a hardware wallet would.
+    struct MockSigner {
+        key: ExtendedPrivKey,
+        invocations: atomic::AtomicUsize,
+    }
+
+    impl Signer for MockSigner {
+        fn sign(&self, sighash: Sighash, key_source: &KeySource) -> Result<ecdsa::Signature> {
+            self.invocations.fetch_add(1, atomic::Ordering::SeqCst);
+
+            let secp = Secp256k1::new();
+            let key = self.key.derive_priv(&secp, &key_source.1)?;
+            let message = Message::from_slice(&sighash.into_inner())?;
+
+            Ok(secp.sign_ecdsa(&message, &key.private_key))
+        }
+    }
+
+    #[test]
+    fn given_electrum_server_of_other_chain_then_fails_with_wrong_chain() {
+        let client = Client::new(
+            electrum_server_of(Network::Bitcoin),
+            Duration::ZERO,
+            DEFAULT_STOP_GAP,
+        )
+        .unwrap();
+
+        let error = client.ensure_network(Network::Regtest).unwrap_err();
+
+        assert_eq!(
+            error.downcast::<WrongChain>().unwrap(),
+            WrongChain {
+                network: Network::Regtest,
+                genesis: genesis_block(Network::Bitcoin).block_hash()
+            }
+        );
+    }
+
+    #[test]
+    fn given_electrum_server_of_same_chain_then_network_is_accepted() {
+        let client = Client::new(
+            electrum_server_of(Network::Regtest),
+            Duration::ZERO,
+            DEFAULT_STOP_GAP,
+        )
+        .unwrap();
+
+        client.ensure_network(Network::Regtest).unwrap();
+    }
+
+    #[tokio::test]
+    async fn watchers_poll_at_the_poll_interval_of_the_environment() {
+        let env_config = env::Mainnet::get_config();
+        let data_dir = tempfile::tempdir().unwrap();
+        let xprivkey = ExtendedPrivKey::new_master(Network::Bitcoin, &[7; 32]).unwrap();
+
+        let wallet = Wallet::new(
+            electrum_server_of(Network::Bitcoin),
+            data_dir.path(),
+            xprivkey,
+            env_config,
+            1,
+        )
+        .await
+        .unwrap();
+
+        let client = wallet.client.lock().await;
+        assert_eq!(client.poll_interval, Duration::from_secs(5));
+        assert_eq!(client.sync_interval, env_config.bitcoin_sync_interval());
+    }
+
+    #[test]
+    fn watch_set_reports_which_of_its_transactions_appeared() {
+        let lock = (Txid::from_slice(&[1; 32]).unwrap(), Script::from(vec![1]));
+        let redeem = (Txid::from_slice(&[2; 32]).unwrap(), Script::from(vec![2]));
+        let cancel = (Txid::from_slice(&[3; 32]).unwrap(), Script::from(vec![3]));
+        let histories = HashMap::from([
+            (
+                electrum_script_hash(&lock.1),
+                serde_json::json!([{ "height": 5, "tx_hash": lock.0.to_string() }]),
+            ),
+            (
+                electrum_script_hash(&redeem.1),
+                serde_json::json!([{ "height": 0, "tx_hash": redeem.0.to_string() }]),
+            ),
+        ]);
+        let mut client = Client::new(
+            electrum_server(Network::Regtest, 10, histories),
+            Duration::ZERO,
+            DEFAULT_STOP_GAP,
+        )
+        .unwrap();
+        let watch_set = SwapWatchSet::new()
+            .watch(SwapTransaction::Lock, &lock)
+            .watch(SwapTransaction::Redeem, &redeem)
+            .watch(SwapTransaction::Cancel, &cancel);
+
+        let statuses = client.statuses_of(&watch_set).unwrap();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (
+                    SwapTransaction::Lock,
+                    ScriptStatus::Confirmed(Confirmed::new(5))
+                ),
+                (SwapTransaction::Redeem, ScriptStatus::InMempool),
+                (SwapTransaction::Cancel, ScriptStatus::Unseen),
+            ]
+        );
+    }
+
+    fn electrum_server_of(network: Network) -> Url {
+        electrum_server(network, 0, HashMap::new())
+    }
+
+    /// Serves every connection like an Electrum server of `network` at
+    /// `height` that knows `histories`, keyed by Electrum script hash. Every
+    /// block it serves is the genesis block.
+    fn electrum_server(
+        network: Network,
+        height: u32,
+        histories: HashMap<String, serde_json::Value>,
+    ) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let header = serialize_hex(&genesis_block(network).header);
+        let histories = Arc::new(histories);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let header = header.clone();
+                let histories = histories.clone();
+
+                std::thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    let respond = |request: &serde_json::Value| {
+                        let result = match request["method"].as_str() {
+                            Some("blockchain.headers.subscribe") => {
+                                serde_json::json!({ "height": height, "hex": header })
+                            }
+                            Some("blockchain.scripthash.get_history") => request["params"][0]
+                                .as_str()
+                                .and_then(|script_hash| histories.get(script_hash).cloned())
+                                .unwrap_or_else(|| serde_json::json!([])),
+                            _ => serde_json::json!(header),
+                        };
+
+                        serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": result,
+                        })
+                    };
+
+                    for line in BufReader::new(stream).lines() {
+                        let response = match serde_json::from_str(&line.unwrap()).unwrap() {
+                            serde_json::Value::Array(batch) => {
+                                serde_json::Value::Array(batch.iter().map(respond).collect())
+                            }
+                            request => respond(&request),
+                        };
+
+                        writeln!(writer, "{}", response).unwrap();
+                    }
+                });
+            }
+        });
+
+        url
+    }
+
+    fn block_hash(byte: u8) -> BlockHash {
+        BlockHash::from_inner([byte; 32])
+    }
+
+    fn electrum_script_hash(script: &Script) -> String {
+        let mut hash = sha256::Hash::hash(script.as_bytes()).into_inner();
+        hash.reverse();
+
+        hash.to_hex()
+    }
+
     proptest::proptest! {
         #[test]
         fn funding_never_fails_with_insufficient_funds(funding_amount in 3000u32.., num_utxos in 1..5u8, sats_per_vb in 1.0..500.0f32, key in crate::proptest::bitcoin::extended_priv_key(), alice in crate::proptest::ecdsa_fun::point(), bob in crate::proptest::ecdsa_fun::point()) {
@@ -1300,7 +5131,7 @@ DEBUG swap::bitcoin::wallet: Bitcoin transaction status changed txid=00000000000
                 let wallet = WalletBuilder::new(funding_amount as u64).with_key(key).with_num_utxos(num_utxos).with_fees(sats_per_vb, 1000).build();
 
                 let amount = wallet.max_giveable(TxLock::script_size()).await.unwrap();
-                let psbt: PartiallySignedTransaction = TxLock::new(&wallet, amount, PublicKey::from(alice), PublicKey::from(bob), wallet.new_address().await.unwrap()).await.unwrap().into();
+                let psbt: PartiallySignedTransaction = TxLock::new(&wallet, amount, PublicKey::from(alice), PublicKey::from(bob), wallet.new_address().await.unwrap(), None).await.unwrap().into();
                 let result = wallet.sign_and_finalize(psbt).await;
 
                 result.expect("transaction to be signed");
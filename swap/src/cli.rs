@@ -1,15 +1,22 @@
 mod behaviour;
 pub mod cancel_and_refund;
 pub mod command;
+pub mod defaults;
 mod event_loop;
 mod list_sellers;
+pub mod split_swap;
 pub mod tracing;
 pub mod transport;
+pub mod watch_only;
 
 pub use behaviour::{Behaviour, OutEvent};
-pub use cancel_and_refund::{cancel, cancel_and_refund, refund};
-pub use event_loop::{EventLoop, EventLoopHandle};
+pub use cancel_and_refund::{
+    cancel, cancel_and_refund, confirm_broadcast, manual_broadcast, refund,
+};
+pub use event_loop::{AbortHandle, EventLoop, EventLoopHandle};
 pub use list_sellers::{list_sellers, Seller, Status as SellerStatus};
+pub use split_swap::SplitSwap;
+pub use watch_only::{watch_only_resume, LockOutcome, WatchOnlyReport};
 
 #[cfg(test)]
 mod tests {
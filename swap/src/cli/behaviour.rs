@@ -1,12 +1,14 @@
+use crate::network::price_curve::{self, PriceCurve};
 use crate::network::quote::BidQuote;
 use crate::network::rendezvous::XmrBtcNamespace;
 use crate::network::swap_setup::bob;
-use crate::network::{encrypted_signature, quote, redial, transfer_proof};
+use crate::network::{
+    encrypted_signature, identify, quote, redial, resend_transfer_proof, transfer_proof,
+};
 use crate::protocol::bob::State2;
 use crate::{bitcoin, env};
 use anyhow::{anyhow, Error, Result};
 use libp2p::core::Multiaddr;
-use libp2p::identify::{Identify, IdentifyConfig, IdentifyEvent};
 use libp2p::ping::{Ping, PingConfig, PingEvent};
 use libp2p::request_response::{RequestId, ResponseChannel};
 use libp2p::{identity, NetworkBehaviour, PeerId};
@@ -19,18 +21,30 @@ pub enum OutEvent {
         id: RequestId,
         response: BidQuote,
     },
+    PriceCurveReceived {
+        id: RequestId,
+        response: PriceCurve,
+    },
     SwapSetupCompleted(Box<Result<State2>>),
     TransferProofReceived {
         msg: Box<transfer_proof::Request>,
         channel: ResponseChannel<()>,
         peer: PeerId,
     },
+    TransferProofResent {
+        id: RequestId,
+        response: resend_transfer_proof::Response,
+    },
     EncryptedSignatureAcknowledged {
         id: RequestId,
     },
     AllRedialAttemptsExhausted {
         peer: PeerId,
     },
+    PeerIdentified {
+        peer: PeerId,
+        agent_version: String,
+    },
     Failure {
         peer: PeerId,
         error: Error,
@@ -62,11 +76,13 @@ impl OutEvent {
 #[allow(missing_debug_implementations)]
 pub struct Behaviour {
     pub quote: quote::Behaviour,
+    pub price_curve: price_curve::Behaviour,
     pub swap_setup: bob::Behaviour,
     pub transfer_proof: transfer_proof::Behaviour,
+    pub resend_transfer_proof: resend_transfer_proof::Behaviour,
     pub encrypted_signature: encrypted_signature::Behaviour,
     pub redial: redial::Behaviour,
-    pub identify: Identify,
+    pub identify: identify::Behaviour,
 
     /// Ping behaviour that ensures that the underlying network connection is
     /// still alive. If the ping fails a connection close event will be
@@ -81,26 +97,28 @@ impl Behaviour {
         bitcoin_wallet: Arc<bitcoin::Wallet>,
         identify_params: (identity::Keypair, XmrBtcNamespace),
     ) -> Self {
-        let agentVersion = format!("cli/{} ({})", env!("CARGO_PKG_VERSION"), identify_params.1);
-        let protocolVersion = "/comit/xmr/btc/1.0.0".to_string();
-        let identifyConfig = IdentifyConfig::new(protocolVersion, identify_params.0.public())
-            .with_agent_version(agentVersion);
+        let (identity, namespace) = identify_params;
 
         Self {
             quote: quote::cli(),
+            price_curve: price_curve::cli(env_config.message_compression),
             swap_setup: bob::Behaviour::new(env_config, bitcoin_wallet),
-            transfer_proof: transfer_proof::bob(),
-            encrypted_signature: encrypted_signature::bob(),
+            transfer_proof: transfer_proof::bob(env_config.message_compression),
+            resend_transfer_proof: resend_transfer_proof::bob(env_config.message_compression),
+            encrypted_signature: encrypted_signature::bob(env_config.message_compression),
             redial: redial::Behaviour::new(alice, Duration::from_secs(2)),
             ping: Ping::new(PingConfig::new().with_keep_alive(true)),
-            identify: Identify::new(identifyConfig),
+            identify: identify::cli(&identity, namespace),
         }
     }
 
     /// Add a known address for the given peer
     pub fn add_address(&mut self, peer_id: PeerId, address: Multiaddr) {
         self.quote.add_address(&peer_id, address.clone());
+        self.price_curve.add_address(&peer_id, address.clone());
         self.transfer_proof.add_address(&peer_id, address.clone());
+        self.resend_transfer_proof
+            .add_address(&peer_id, address.clone());
         self.encrypted_signature.add_address(&peer_id, address);
     }
 }
@@ -110,9 +128,3 @@ impl From<PingEvent> for OutEvent {
         OutEvent::Other
     }
 }
-
-impl From<IdentifyEvent> for OutEvent {
-    fn from(_: IdentifyEvent) -> Self {
-        OutEvent::Other
-    }
-}
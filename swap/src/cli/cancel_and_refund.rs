@@ -1,9 +1,9 @@
-use crate::bitcoin::wallet::Subscription;
+use crate::bitcoin::wallet::{Subscription, BROADCAST_SEEN_TIMEOUT};
 use crate::bitcoin::{parse_rpc_error_code, RpcErrorCode, Wallet};
-use crate::protocol::bob::BobState;
+use crate::protocol::bob::{BobState, ManualBroadcast};
 use crate::protocol::Database;
-use anyhow::{bail, Result};
-use bitcoin::Txid;
+use anyhow::{bail, Context, Result};
+use bitcoin::{Address, Transaction, Txid};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -11,12 +11,13 @@ pub async fn cancel_and_refund(
     swap_id: Uuid,
     bitcoin_wallet: Arc<Wallet>,
     db: Arc<dyn Database>,
+    refund_address: Option<Address>,
 ) -> Result<BobState> {
     if let Err(err) = cancel(swap_id, bitcoin_wallet.clone(), db.clone()).await {
         tracing::info!(%err, "Could not submit cancel transaction");
     };
 
-    let state = match refund(swap_id, bitcoin_wallet, db).await {
+    let state = match refund(swap_id, bitcoin_wallet, db, refund_address).await {
         Ok(s) => s,
         Err(e) => bail!(e),
     };
@@ -30,7 +31,7 @@ pub async fn cancel(
     bitcoin_wallet: Arc<Wallet>,
     db: Arc<dyn Database>,
 ) -> Result<(Txid, Subscription, BobState)> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     let state6 = match state {
         BobState::BtcLocked { state3, .. } => state3.cancel(),
@@ -38,12 +39,13 @@ pub async fn cancel(
         BobState::XmrLocked(state4) => state4.cancel(),
         BobState::EncSigSent(state4) => state4.cancel(),
         BobState::CancelTimelockExpired(state6) => state6,
-        BobState::BtcRefunded(state6) => state6,
         BobState::BtcCancelled(state6) => state6,
+        BobState::ReadyToBroadcast { state, .. } => state,
 
         BobState::Started { .. }
         | BobState::SwapSetupCompleted(_)
         | BobState::BtcRedeemed(_)
+        | BobState::BtcRefunded(_)
         | BobState::XmrRedeemed { .. }
         | BobState::BtcPunished { .. }
         | BobState::SafelyAborted => bail!(
@@ -77,12 +79,32 @@ pub async fn cancel(
     Ok((txid, subscription, state))
 }
 
+/// Publishes the refund transaction of the given swap.
+///
+/// The refund transaction is pre-signed by Alice and therefore always pays to
+/// the refund address agreed upon during swap setup. If `refund_address` is
+/// given, the refunded output is forwarded to it in a follow-up transaction.
+/// This only works if the agreed upon refund address belongs to the internal
+/// wallet. The swap is only marked as refunded once the forward succeeded, so
+/// a failed forward can be retried by running the refund again.
 pub async fn refund(
     swap_id: Uuid,
     bitcoin_wallet: Arc<Wallet>,
     db: Arc<dyn Database>,
+    refund_address: Option<Address>,
 ) -> Result<BobState> {
-    let state = db.get_state(swap_id).await?.try_into()?;
+    if let Some(address) = refund_address.as_ref() {
+        let network = bitcoin_wallet.get_network();
+        if address.network != network {
+            bail!(
+                "Cannot refund to {} because it is not an address on network {}",
+                address,
+                network
+            );
+        }
+    }
+
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
 
     let state6 = match state {
         BobState::BtcLocked { state3, .. } => state3.cancel(),
@@ -91,6 +113,7 @@ pub async fn refund(
         BobState::EncSigSent(state4) => state4.cancel(),
         BobState::CancelTimelockExpired(state6) => state6,
         BobState::BtcCancelled(state6) => state6,
+        BobState::ReadyToBroadcast { state, .. } => state,
         BobState::Started { .. }
         | BobState::SwapSetupCompleted(_)
         | BobState::BtcRedeemed(_)
@@ -105,7 +128,36 @@ pub async fn refund(
     };
 
     tracing::info!(%swap_id, "Manually refunding swap");
-    state6.publish_refund_btc(bitcoin_wallet.as_ref()).await?;
+
+    // A previous run may have published the refund and then failed to forward
+    // it, in which case we only retry the forward
+    let tx_refund = state6.signed_refund_transaction()?;
+    let tx_refund_status = bitcoin_wallet
+        .status_of_script(&(tx_refund.txid(), tx_refund.output[0].script_pubkey.clone()))
+        .await?;
+    if tx_refund_status.has_been_seen() {
+        tracing::info!(%swap_id, txid = %tx_refund.txid(), "Refund transaction was already published");
+    } else {
+        state6.publish_refund_btc(bitcoin_wallet.as_ref()).await?;
+    }
+
+    // Only save the terminal state once the forward went through, otherwise
+    // there would be no way to retry it
+    if let Some(address) = refund_address {
+        let refund_output = state6.refund_output()?;
+
+        bitcoin_wallet.sync().await?;
+        let psbt = bitcoin_wallet
+            .sweep_output(refund_output, address.clone())
+            .await
+            .context("Failed to forward refunded Bitcoin to external address")?;
+        let transaction = bitcoin_wallet.sign_and_finalize(psbt).await?;
+        bitcoin_wallet
+            .broadcast(transaction, "refund forward")
+            .await?;
+
+        tracing::info!(%swap_id, %address, "Forwarded refunded Bitcoin to external address");
+    }
 
     let state = BobState::BtcRefunded(state6);
     db.insert_latest_state(swap_id, state.clone().into())
@@ -113,3 +165,93 @@ pub async fn refund(
 
     Ok(state)
 }
+
+/// Returns the next transaction to cancel or refund the given swap without
+/// broadcasting it, so it can be broadcast through the user's own node.
+///
+/// The cancel transaction is returned until the broadcast of it is confirmed
+/// using [`confirm_broadcast`], the refund transaction afterwards.
+pub async fn manual_broadcast(
+    swap_id: Uuid,
+    db: Arc<dyn Database>,
+) -> Result<(Transaction, BobState)> {
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
+
+    let (state6, transaction) = match state {
+        BobState::BtcLocked { state3, .. } => (state3.cancel(), ManualBroadcast::Cancel),
+        BobState::XmrLockProofReceived { state, .. } => (state.cancel(), ManualBroadcast::Cancel),
+        BobState::XmrLocked(state4) => (state4.cancel(), ManualBroadcast::Cancel),
+        BobState::EncSigSent(state4) => (state4.cancel(), ManualBroadcast::Cancel),
+        BobState::CancelTimelockExpired(state6) => (state6, ManualBroadcast::Cancel),
+        BobState::BtcCancelled(state6) => (state6, ManualBroadcast::Refund),
+        BobState::ReadyToBroadcast { state, transaction } => (state, transaction),
+        BobState::Started { .. }
+        | BobState::SwapSetupCompleted(_)
+        | BobState::BtcRedeemed(_)
+        | BobState::BtcRefunded(_)
+        | BobState::XmrRedeemed { .. }
+        | BobState::BtcPunished { .. }
+        | BobState::SafelyAborted => bail!(
+            "Cannot cancel or refund swap {} because it is in state {} which is not refundable.",
+            swap_id,
+            state
+        ),
+    };
+
+    let signed_tx = state6.signed_transaction(transaction)?;
+    tracing::info!(%swap_id, txid = %signed_tx.txid(), "Prepared {} transaction for manual broadcast", transaction);
+
+    let state = BobState::ReadyToBroadcast {
+        state: state6,
+        transaction,
+    };
+    db.insert_latest_state(swap_id, state.clone().into())
+        .await?;
+
+    Ok((signed_tx, state))
+}
+
+/// Records that the transaction returned by [`manual_broadcast`] was
+/// broadcast by the user, after checking that it can be seen.
+pub async fn confirm_broadcast(
+    swap_id: Uuid,
+    bitcoin_wallet: Arc<Wallet>,
+    db: Arc<dyn Database>,
+) -> Result<BobState> {
+    let state = db.get_incomplete_state(swap_id).await?.try_into()?;
+
+    let (state6, transaction) = match state {
+        BobState::ReadyToBroadcast { state, transaction } => (state, transaction),
+        state => bail!(
+            "Cannot confirm broadcast of swap {} because it is in state {} which has no transaction ready to broadcast.",
+            swap_id,
+            state
+        ),
+    };
+
+    let signed_tx = state6.signed_transaction(transaction)?;
+    let txid = signed_tx.txid();
+    bitcoin_wallet
+        .subscribe_to((txid, signed_tx.output[0].script_pubkey.clone()))
+        .await
+        .wait_until_seen_within(BROADCAST_SEEN_TIMEOUT)
+        .await
+        .with_context(|| {
+            format!(
+                "Refusing to confirm broadcast of {} transaction {}, broadcast it first",
+                transaction, txid
+            )
+        })?;
+
+    let state = match transaction {
+        ManualBroadcast::Cancel => BobState::BtcCancelled(state6),
+        ManualBroadcast::Refund => BobState::BtcRefunded(state6),
+    };
+
+    tracing::info!(%swap_id, %state, "Confirmed manual broadcast");
+
+    db.insert_latest_state(swap_id, state.clone().into())
+        .await?;
+
+    Ok(state)
+}
@@ -1,15 +1,19 @@
-use crate::bitcoin::Amount;
+use crate::bitcoin::{Amount, PublicKey};
+use crate::cli::defaults::Defaults;
 use crate::env::GetConfig;
 use crate::fs::system_data_dir;
+use crate::monero::RingSize;
 use crate::network::rendezvous::XmrBtcNamespace;
 use crate::{env, monero};
 use anyhow::{bail, Context, Result};
-use bitcoin::{Address, AddressType};
+use bitcoin::{Address, AddressType, Txid};
+use ecdsa_fun::fun::Point;
 use libp2p::core::Multiaddr;
 use serde::Serialize;
 use std::ffi::OsString;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 use structopt::{clap, StructOpt};
 use url::Url;
 use uuid::Uuid;
@@ -64,8 +68,10 @@ where
     let json = args.json;
     let is_testnet = args.testnet;
     let data = args.data;
+    let disable_compression = args.disable_compression;
+    let cancel_anchor = args.cancel_anchor;
 
-    let arguments = match args.cmd {
+    let mut arguments = match args.cmd {
         RawCommand::BuyXmr {
             seller: Seller { seller },
             bitcoin,
@@ -73,20 +79,31 @@ where
             monero,
             monero_receive_address,
             tor: Tor { tor_socks5_port },
+            top_up_timeout,
         } => {
             let (bitcoin_electrum_rpc_url, bitcoin_target_block) =
                 bitcoin.apply_defaults(is_testnet)?;
+            let data_dir = data::data_dir_from(data, is_testnet)?;
+            let defaults = Defaults::read(&data_dir)?;
+            let monero_receive_address = monero_receive_address
+                .or(defaults.monero_receive_address)
+                .context("No --receive-address given and no default configured")?;
             let monero_receive_address =
                 validate_monero_address(monero_receive_address, is_testnet)?;
+            let bitcoin_change_address = bitcoin_change_address
+                .or(defaults.bitcoin_change_address)
+                .context("No --change-address given and no default configured")?;
             let bitcoin_change_address =
                 validate_bitcoin_address(bitcoin_change_address, is_testnet)?;
             let monero_daemon_address = monero.monero_daemon_address;
+            let monero_ring_size = monero.monero_ring_size;
+            let monero_wallet_password = monero.monero_wallet_password;
 
             Arguments {
                 env_config: env_config_from(is_testnet),
                 debug,
                 json,
-                data_dir: data::data_dir_from(data, is_testnet)?,
+                data_dir,
                 cmd: Command::BuyXmr {
                     seller,
                     bitcoin_electrum_rpc_url,
@@ -94,8 +111,11 @@ where
                     bitcoin_change_address,
                     monero_receive_address,
                     monero_daemon_address,
+                    monero_ring_size,
+                    monero_wallet_password,
                     tor_socks5_port,
                     namespace: XmrBtcNamespace::from_is_testnet(is_testnet),
+                    top_up_timeout: top_up_timeout.map(Duration::from_secs),
                 },
             }
         }
@@ -106,6 +126,13 @@ where
             data_dir: data::data_dir_from(data, is_testnet)?,
             cmd: Command::History,
         },
+        RawCommand::CheckDatabase { repair } => Arguments {
+            env_config: env_config_from(is_testnet),
+            debug,
+            json,
+            data_dir: data::data_dir_from(data, is_testnet)?,
+            cmd: Command::CheckDatabase { repair },
+        },
         RawCommand::Config => Arguments {
             env_config: env_config_from(is_testnet),
             debug,
@@ -164,6 +191,8 @@ where
             let (bitcoin_electrum_rpc_url, bitcoin_target_block) =
                 bitcoin.apply_defaults(is_testnet)?;
             let monero_daemon_address = monero.monero_daemon_address;
+            let monero_ring_size = monero.monero_ring_size;
+            let monero_wallet_password = monero.monero_wallet_password;
 
             Arguments {
                 env_config: env_config_from(is_testnet),
@@ -175,6 +204,8 @@ where
                     bitcoin_electrum_rpc_url,
                     bitcoin_target_block,
                     monero_daemon_address,
+                    monero_ring_size,
+                    monero_wallet_password,
                     tor_socks5_port,
                     namespace: XmrBtcNamespace::from_is_testnet(is_testnet),
                 },
@@ -183,9 +214,18 @@ where
         RawCommand::CancelAndRefund {
             swap_id: SwapId { swap_id },
             bitcoin,
+            refund_address,
+            manual_broadcast,
         } => {
+            if manual_broadcast && refund_address.is_some() {
+                bail!("Cannot forward the refunded Bitcoin if the refund transaction is broadcast manually")
+            }
+
             let (bitcoin_electrum_rpc_url, bitcoin_target_block) =
                 bitcoin.apply_defaults(is_testnet)?;
+            let refund_address = refund_address
+                .map(|address| bitcoin_address(address, is_testnet))
+                .transpose()?;
 
             Arguments {
                 env_config: env_config_from(is_testnet),
@@ -196,6 +236,8 @@ where
                     swap_id,
                     bitcoin_electrum_rpc_url,
                     bitcoin_target_block,
+                    refund_address,
+                    manual_broadcast,
                 },
             }
         }
@@ -237,7 +279,65 @@ where
                 swap_id: swap_id.swap_id,
             },
         },
+        RawCommand::ConfirmBroadcast { swap_id, bitcoin } => {
+            let (bitcoin_electrum_rpc_url, bitcoin_target_block) =
+                bitcoin.apply_defaults(is_testnet)?;
+
+            Arguments {
+                env_config: env_config_from(is_testnet),
+                debug,
+                json,
+                data_dir: data::data_dir_from(data, is_testnet)?,
+                cmd: Command::ConfirmBroadcast {
+                    swap_id: swap_id.swap_id,
+                    bitcoin_electrum_rpc_url,
+                    bitcoin_target_block,
+                },
+            }
+        }
+        RawCommand::ExportSwap { swap_id, file } => Arguments {
+            env_config: env_config_from(is_testnet),
+            debug,
+            json,
+            data_dir: data::data_dir_from(data, is_testnet)?,
+            cmd: Command::ExportSwap {
+                swap_id: swap_id.swap_id,
+                file,
+            },
+        },
+        RawCommand::ImportSwap { file } => Arguments {
+            env_config: env_config_from(is_testnet),
+            debug,
+            json,
+            data_dir: data::data_dir_from(data, is_testnet)?,
+            cmd: Command::ImportSwap { file },
+        },
+        RawCommand::WatchOnlyResume {
+            lock_txid,
+            alice_bitcoin_key,
+            bob_bitcoin_key,
+            bitcoin,
+        } => {
+            let (bitcoin_electrum_rpc_url, bitcoin_target_block) =
+                bitcoin.apply_defaults(is_testnet)?;
+
+            Arguments {
+                env_config: env_config_from(is_testnet),
+                debug,
+                json,
+                data_dir: data::data_dir_from(data, is_testnet)?,
+                cmd: Command::WatchOnlyResume {
+                    lock_txid,
+                    alice_bitcoin_key,
+                    bob_bitcoin_key,
+                    bitcoin_electrum_rpc_url,
+                    bitcoin_target_block,
+                },
+            }
+        }
     };
+    arguments.env_config.message_compression = !disable_compression;
+    arguments.env_config.cancel_anchor = cancel_anchor;
 
     Ok(ParseResult::Arguments(Box::new(arguments)))
 }
@@ -251,10 +351,16 @@ pub enum Command {
         bitcoin_change_address: bitcoin::Address,
         monero_receive_address: monero::Address,
         monero_daemon_address: Option<String>,
+        monero_ring_size: Option<RingSize>,
+        monero_wallet_password: Option<String>,
         tor_socks5_port: u16,
         namespace: XmrBtcNamespace,
+        top_up_timeout: Option<Duration>,
     },
     History,
+    CheckDatabase {
+        repair: bool,
+    },
     Config,
     WithdrawBtc {
         bitcoin_electrum_rpc_url: Url,
@@ -271,6 +377,8 @@ pub enum Command {
         bitcoin_electrum_rpc_url: Url,
         bitcoin_target_block: usize,
         monero_daemon_address: Option<String>,
+        monero_ring_size: Option<RingSize>,
+        monero_wallet_password: Option<String>,
         tor_socks5_port: u16,
         namespace: XmrBtcNamespace,
     },
@@ -278,6 +386,8 @@ pub enum Command {
         swap_id: Uuid,
         bitcoin_electrum_rpc_url: Url,
         bitcoin_target_block: usize,
+        refund_address: Option<Address>,
+        manual_broadcast: bool,
     },
     ListSellers {
         rendezvous_point: Multiaddr,
@@ -291,6 +401,25 @@ pub enum Command {
     MoneroRecovery {
         swap_id: Uuid,
     },
+    ConfirmBroadcast {
+        swap_id: Uuid,
+        bitcoin_electrum_rpc_url: Url,
+        bitcoin_target_block: usize,
+    },
+    ExportSwap {
+        swap_id: Uuid,
+        file: PathBuf,
+    },
+    ImportSwap {
+        file: PathBuf,
+    },
+    WatchOnlyResume {
+        lock_txid: Txid,
+        alice_bitcoin_key: PublicKey,
+        bob_bitcoin_key: PublicKey,
+        bitcoin_electrum_rpc_url: Url,
+        bitcoin_target_block: usize,
+    },
 }
 
 #[derive(structopt::StructOpt, Debug)]
@@ -319,6 +448,20 @@ struct RawArguments {
     #[structopt(long, help = "Activate debug logging")]
     debug: bool,
 
+    #[structopt(
+        long,
+        help = "Do not compress messages to the seller, even if it supports compression",
+        global = true
+    )]
+    disable_compression: bool,
+
+    #[structopt(
+        long,
+        help = "Ask the seller for an anchor output on the cancel transaction of new swaps, so the cancel transaction can be fee-bumped via CPFP",
+        global = true
+    )]
+    cancel_anchor: bool,
+
     #[structopt(
         short,
         long = "json",
@@ -342,24 +485,38 @@ enum RawCommand {
 
         #[structopt(
             long = "change-address",
-            help = "The bitcoin address where any form of change or excess funds should be sent to"
+            help = "The bitcoin address where any form of change or excess funds should be sent to. Defaults to the bitcoin_change_address in defaults.toml in the data directory."
         )]
-        bitcoin_change_address: bitcoin::Address,
+        bitcoin_change_address: Option<bitcoin::Address>,
 
         #[structopt(flatten)]
         monero: Monero,
 
         #[structopt(long = "receive-address",
-            help = "The monero address where you would like to receive monero",
+            help = "The monero address where you would like to receive monero. Defaults to the monero_receive_address in defaults.toml in the data directory.",
             parse(try_from_str = parse_monero_address)
         )]
-        monero_receive_address: monero::Address,
+        monero_receive_address: Option<monero::Address>,
 
         #[structopt(flatten)]
         tor: Tor,
+
+        #[structopt(
+            long = "top-up-timeout",
+            help = "If the Bitcoin wallet cannot cover the swap amount and the lock fee when the swap is about to be set up, wait up to this many seconds for a deposit instead of failing."
+        )]
+        top_up_timeout: Option<u64>,
     },
     /// Show a list of past, ongoing and completed swaps
     History,
+    /// Check the database for records that cannot be read, e.g. after a crash
+    CheckDatabase {
+        #[structopt(
+            long = "repair",
+            help = "Move unreadable swap states to a quarantine table and delete orphaned peer addresses, so that the remaining swaps can be resumed."
+        )]
+        repair: bool,
+    },
     #[structopt(about = "Prints the current config")]
     Config,
     #[structopt(about = "Allows withdrawing BTC from the internal Bitcoin wallet.")]
@@ -402,6 +559,18 @@ enum RawCommand {
 
         #[structopt(flatten)]
         bitcoin: Bitcoin,
+
+        #[structopt(
+            long = "refund-address",
+            help = "Optionally forward the refunded Bitcoin to this address instead of keeping it in the internal wallet."
+        )]
+        refund_address: Option<Address>,
+
+        #[structopt(
+            long = "manual-broadcast",
+            help = "Print the signed cancel or refund transaction instead of broadcasting it. Run `confirm-broadcast` once you have broadcast it yourself."
+        )]
+        manual_broadcast: bool,
     },
     /// Discover and list sellers (i.e. ASB providers)
     ListSellers {
@@ -426,6 +595,64 @@ enum RawCommand {
         #[structopt(flatten)]
         swap_id: SwapId,
     },
+    /// Confirm that the transaction printed by `cancel-and-refund
+    /// --manual-broadcast` was broadcast. Fails unless the transaction can be
+    /// seen on the network.
+    ConfirmBroadcast {
+        #[structopt(flatten)]
+        swap_id: SwapId,
+
+        #[structopt(flatten)]
+        bitcoin: Bitcoin,
+    },
+    /// Write a swap to a file encrypted with a key derived from the seed, to
+    /// resume or refund it on another machine that uses the same seed
+    ExportSwap {
+        #[structopt(flatten)]
+        swap_id: SwapId,
+
+        #[structopt(
+            long = "file",
+            help = "The file to write the encrypted swap to",
+            parse(from_os_str)
+        )]
+        file: PathBuf,
+    },
+    /// Import a swap written by `export-swap` into the database. Requires the
+    /// seed of the machine the swap was exported from to be copied to
+    /// `seed.pem` in the data directory first.
+    ImportSwap {
+        #[structopt(
+            long = "file",
+            help = "The file written by `export-swap`",
+            parse(from_os_str)
+        )]
+        file: PathBuf,
+    },
+    /// Reports the state of a swap's Bitcoin lock from the chain alone, given
+    /// the lock transaction id and the Bitcoin public keys of both parties.
+    /// This is a last resort if the swap's database is lost.
+    WatchOnlyResume {
+        #[structopt(long = "lock-txid", help = "The id of the Bitcoin lock transaction")]
+        lock_txid: Txid,
+
+        #[structopt(
+            long = "alice-bitcoin-key",
+            help = "Alice's compressed Bitcoin public key of the swap, hex encoded",
+            parse(try_from_str = parse_bitcoin_public_key)
+        )]
+        alice_bitcoin_key: PublicKey,
+
+        #[structopt(
+            long = "bob-bitcoin-key",
+            help = "Bob's compressed Bitcoin public key of the swap, hex encoded",
+            parse(try_from_str = parse_bitcoin_public_key)
+        )]
+        bob_bitcoin_key: PublicKey,
+
+        #[structopt(flatten)]
+        bitcoin: Bitcoin,
+    },
 }
 
 #[derive(structopt::StructOpt, Debug)]
@@ -435,6 +662,19 @@ struct Monero {
         help = "Specify to connect to a monero daemon of your choice: <host>:<port>. If none is specified, we will connect to a public node."
     )]
     monero_daemon_address: Option<String>,
+
+    #[structopt(
+        long = "monero-ring-size",
+        help = "Ring size of the Monero transactions of the swap. If none is specified, the default of the monero-wallet-rpc is used.",
+        parse(try_from_str = parse_ring_size)
+    )]
+    monero_ring_size: Option<RingSize>,
+
+    #[structopt(
+        long = "monero-wallet-password",
+        help = "Password the Monero wallets of the CLI are created and opened with. If none is specified, the wallets are not protected by a password."
+    )]
+    monero_wallet_password: Option<String>,
 }
 
 #[derive(structopt::StructOpt, Debug)]
@@ -594,6 +834,24 @@ fn parse_monero_address(s: &str) -> Result<monero::Address> {
     })
 }
 
+fn parse_ring_size(s: &str) -> Result<RingSize> {
+    let ring_size =
+        u32::from_str(s).with_context(|| format!("Failed to parse {} as a ring size", s))?;
+
+    Ok(RingSize::new(ring_size)?)
+}
+
+fn parse_bitcoin_public_key(s: &str) -> Result<PublicKey> {
+    let key = ::bitcoin::PublicKey::from_str(s)
+        .with_context(|| format!("Failed to parse {} as a Bitcoin public key", s))?;
+
+    if !key.compressed {
+        bail!("Bitcoin public key {} is not compressed", s)
+    }
+
+    Ok(PublicKey::from(Point::from(key.inner)))
+}
+
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
 #[error("Invalid monero address provided, expected address on network {expected:?} but address provided is on {actual:?}")]
 pub struct MoneroAddressNetworkMismatch {
@@ -627,6 +885,11 @@ mod tests {
     const MULTI_ADDRESS: &str =
         "/ip4/127.0.0.1/tcp/9939/p2p/12D3KooWCdMKjesXMJz1SiZ7HgotrxuqhQJbP5sgBm2BwP1cqThi";
     const SWAP_ID: &str = "ea030832-3be9-454f-bb98-5ea9a788406b";
+    const LOCK_TXID: &str = "2b2d4e4a6a7e7ac5c0ad4a5b3f2d0e0b0f9c8e7a6d5c4b3a291807f6e5d4c3b2";
+    const ALICE_BITCOIN_KEY: &str =
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const BOB_BITCOIN_KEY: &str =
+        "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
 
     #[test]
     fn given_buy_xmr_on_mainnet_then_defaults_to_mainnet() {
@@ -791,6 +1054,290 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_refund_with_refund_address_then_refund_address_set() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--testnet",
+            "refund",
+            "--swap-id",
+            SWAP_ID,
+            "--refund-address",
+            BITCOIN_TESTNET_ADDRESS,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        let expected = match Arguments::refund_testnet_defaults().cmd {
+            Command::CancelAndRefund {
+                swap_id,
+                bitcoin_electrum_rpc_url,
+                bitcoin_target_block,
+                ..
+            } => Command::CancelAndRefund {
+                swap_id,
+                bitcoin_electrum_rpc_url,
+                bitcoin_target_block,
+                refund_address: Some(Address::from_str(BITCOIN_TESTNET_ADDRESS).unwrap()),
+                manual_broadcast: false,
+            },
+            _ => unreachable!(),
+        };
+        match args {
+            ParseResult::Arguments(args) => assert_eq!(args.cmd, expected),
+            _ => panic!("Expected arguments"),
+        }
+    }
+
+    #[test]
+    fn given_refund_with_manual_broadcast_then_manual_broadcast_set() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--testnet",
+            "refund",
+            "--swap-id",
+            SWAP_ID,
+            "--manual-broadcast",
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        match args {
+            ParseResult::Arguments(args) => assert!(matches!(
+                args.cmd,
+                Command::CancelAndRefund {
+                    manual_broadcast: true,
+                    refund_address: None,
+                    ..
+                }
+            )),
+            _ => panic!("Expected arguments"),
+        }
+    }
+
+    #[test]
+    fn given_buy_xmr_without_addresses_then_configured_defaults_are_used() {
+        let data_dir = tempfile::tempdir().unwrap();
+        write_defaults(data_dir.path().join(MAINNET));
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--data-base-dir",
+            data_dir.path().to_str().unwrap(),
+            "buy-xmr",
+            "--seller",
+            MULTI_ADDRESS,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        let (bitcoin_change_address, monero_receive_address) = buy_xmr_addresses(args);
+        assert_eq!(
+            bitcoin_change_address,
+            BITCOIN_MAINNET_ADDRESS.parse().unwrap()
+        );
+        assert_eq!(
+            monero_receive_address,
+            MONERO_MAINNET_ADDRESS.parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn given_buy_xmr_with_addresses_then_configured_defaults_are_overridden() {
+        let change_address = "bc1qh4zjxrqe3trzg7s6m7y67q2jzrw3ru5mx3z7j3";
+        let receive_address = "498AVruCDWgP9Az9LjMm89VWjrBrSZ2W2K3HFBiyzzrRjUJWUcCVxvY1iitfuKoek2FdX6MKGAD9Qb1G1P8QgR5jPmmt3Vj";
+        let data_dir = tempfile::tempdir().unwrap();
+        write_defaults(data_dir.path().join(MAINNET));
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--data-base-dir",
+            data_dir.path().to_str().unwrap(),
+            "buy-xmr",
+            "--receive-address",
+            receive_address,
+            "--change-address",
+            change_address,
+            "--seller",
+            MULTI_ADDRESS,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        let (bitcoin_change_address, monero_receive_address) = buy_xmr_addresses(args);
+        assert_eq!(bitcoin_change_address, change_address.parse().unwrap());
+        assert_eq!(monero_receive_address, receive_address.parse().unwrap());
+    }
+
+    #[test]
+    fn given_buy_xmr_without_addresses_and_no_defaults_then_fails() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--data-base-dir",
+            data_dir.path().to_str().unwrap(),
+            "buy-xmr",
+            "--seller",
+            MULTI_ADDRESS,
+        ];
+
+        assert!(parse_args_and_apply_defaults(raw_ars).is_err());
+    }
+
+    fn write_defaults(data_dir: PathBuf) {
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(
+            data_dir.join("defaults.toml"),
+            format!(
+                "bitcoin_change_address = \"{}\"\nmonero_receive_address = \"{}\"\n",
+                BITCOIN_MAINNET_ADDRESS, MONERO_MAINNET_ADDRESS
+            ),
+        )
+        .unwrap();
+    }
+
+    fn buy_xmr_addresses(args: ParseResult) -> (bitcoin::Address, monero::Address) {
+        match args {
+            ParseResult::Arguments(args) => match args.cmd {
+                Command::BuyXmr {
+                    bitcoin_change_address,
+                    monero_receive_address,
+                    ..
+                } => (bitcoin_change_address, monero_receive_address),
+                cmd => panic!("expected buy-xmr command, got {:?}", cmd),
+            },
+            result => panic!("expected arguments, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn given_manual_broadcast_with_refund_address_then_fails() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--testnet",
+            "refund",
+            "--swap-id",
+            SWAP_ID,
+            "--manual-broadcast",
+            "--refund-address",
+            BITCOIN_TESTNET_ADDRESS,
+        ];
+
+        assert!(parse_args_and_apply_defaults(raw_ars).is_err());
+    }
+
+    #[test]
+    fn given_confirm_broadcast_then_swap_id_set() {
+        let raw_ars = vec![BINARY_NAME, "confirm-broadcast", "--swap-id", SWAP_ID];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        match args {
+            ParseResult::Arguments(args) => assert_eq!(
+                args.cmd,
+                Command::ConfirmBroadcast {
+                    swap_id: Uuid::from_str(SWAP_ID).unwrap(),
+                    bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL).unwrap(),
+                    bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET,
+                }
+            ),
+            _ => panic!("Expected arguments"),
+        }
+    }
+
+    #[test]
+    fn given_export_and_import_swap_then_swap_id_and_file_set() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "export-swap",
+            "--swap-id",
+            SWAP_ID,
+            "--file",
+            "swap.backup",
+        ];
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+        match args {
+            ParseResult::Arguments(args) => assert_eq!(
+                args.cmd,
+                Command::ExportSwap {
+                    swap_id: Uuid::from_str(SWAP_ID).unwrap(),
+                    file: PathBuf::from("swap.backup"),
+                }
+            ),
+            _ => panic!("Expected arguments"),
+        }
+
+        let raw_ars = vec![BINARY_NAME, "import-swap", "--file", "swap.backup"];
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+        match args {
+            ParseResult::Arguments(args) => assert_eq!(
+                args.cmd,
+                Command::ImportSwap {
+                    file: PathBuf::from("swap.backup"),
+                }
+            ),
+            _ => panic!("Expected arguments"),
+        }
+    }
+
+    #[test]
+    fn given_watch_only_resume_then_lock_txid_and_keys_set() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "watch-only-resume",
+            "--lock-txid",
+            LOCK_TXID,
+            "--alice-bitcoin-key",
+            ALICE_BITCOIN_KEY,
+            "--bob-bitcoin-key",
+            BOB_BITCOIN_KEY,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+
+        match args {
+            ParseResult::Arguments(args) => assert_eq!(
+                args.cmd,
+                Command::WatchOnlyResume {
+                    lock_txid: Txid::from_str(LOCK_TXID).unwrap(),
+                    alice_bitcoin_key: parse_bitcoin_public_key(ALICE_BITCOIN_KEY).unwrap(),
+                    bob_bitcoin_key: parse_bitcoin_public_key(BOB_BITCOIN_KEY).unwrap(),
+                    bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL).unwrap(),
+                    bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET,
+                }
+            ),
+            _ => panic!("Expected arguments"),
+        }
+    }
+
+    #[test]
+    fn given_uncompressed_bitcoin_key_then_fails() {
+        let uncompressed = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+        assert!(parse_bitcoin_public_key(uncompressed).is_err());
+    }
+
+    #[test]
+    fn given_refund_on_testnet_with_mainnet_refund_address_then_fails() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--testnet",
+            "refund",
+            "--swap-id",
+            SWAP_ID,
+            "--refund-address",
+            BITCOIN_MAINNET_ADDRESS,
+        ];
+
+        let err = parse_args_and_apply_defaults(raw_ars).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<BitcoinAddressNetworkMismatch>().unwrap(),
+            &BitcoinAddressNetworkMismatch {
+                expected: bitcoin::Network::Testnet,
+                actual: bitcoin::Network::Bitcoin
+            }
+        );
+    }
+
     #[test]
     fn given_with_data_dir_then_data_dir_set() {
         let data_dir = "/some/path/to/dir";
@@ -965,6 +1512,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_disable_compression_then_messages_are_not_compressed() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--disable-compression",
+            "resume",
+            "--swap-id",
+            SWAP_ID,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+        assert_eq!(
+            args,
+            ParseResult::Arguments(
+                Arguments::resume_mainnet_defaults()
+                    .without_compression()
+                    .into_boxed()
+            )
+        );
+    }
+
+    #[test]
+    fn given_cancel_anchor_then_cancel_anchor_requested() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "--cancel-anchor",
+            "resume",
+            "--swap-id",
+            SWAP_ID,
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+        assert_eq!(
+            args,
+            ParseResult::Arguments(
+                Arguments::resume_mainnet_defaults()
+                    .with_cancel_anchor()
+                    .into_boxed()
+            )
+        );
+    }
+
+    #[test]
+    fn given_top_up_timeout_then_buy_xmr_waits_for_deposit() {
+        let raw_ars = vec![
+            BINARY_NAME,
+            "buy-xmr",
+            "--change-address",
+            BITCOIN_MAINNET_ADDRESS,
+            "--receive-address",
+            MONERO_MAINNET_ADDRESS,
+            "--seller",
+            MULTI_ADDRESS,
+            "--top-up-timeout",
+            "600",
+        ];
+
+        let args = parse_args_and_apply_defaults(raw_ars).unwrap();
+        assert_eq!(
+            args,
+            ParseResult::Arguments(
+                Arguments::buy_xmr_mainnet_defaults()
+                    .with_top_up_timeout(Duration::from_secs(600))
+                    .into_boxed()
+            )
+        );
+    }
+
     #[test]
     fn given_with_json_then_json_set() {
         let raw_ars = vec![
@@ -1159,8 +1774,11 @@ mod tests {
                     monero_receive_address: monero::Address::from_str(MONERO_STAGENET_ADDRESS)
                         .unwrap(),
                     monero_daemon_address: None,
+                    monero_ring_size: None,
+                    monero_wallet_password: None,
                     tor_socks5_port: DEFAULT_SOCKS5_PORT,
                     namespace: XmrBtcNamespace::Testnet,
+                    top_up_timeout: None,
                 },
             }
         }
@@ -1179,8 +1797,11 @@ mod tests {
                     monero_receive_address: monero::Address::from_str(MONERO_MAINNET_ADDRESS)
                         .unwrap(),
                     monero_daemon_address: None,
+                    monero_ring_size: None,
+                    monero_wallet_password: None,
                     tor_socks5_port: DEFAULT_SOCKS5_PORT,
                     namespace: XmrBtcNamespace::Mainnet,
+                    top_up_timeout: None,
                 },
             }
         }
@@ -1197,6 +1818,8 @@ mod tests {
                         .unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET_TESTNET,
                     monero_daemon_address: None,
+                    monero_ring_size: None,
+                    monero_wallet_password: None,
                     tor_socks5_port: DEFAULT_SOCKS5_PORT,
                     namespace: XmrBtcNamespace::Testnet,
                 },
@@ -1214,6 +1837,8 @@ mod tests {
                     bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL).unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET,
                     monero_daemon_address: None,
+                    monero_ring_size: None,
+                    monero_wallet_password: None,
                     tor_socks5_port: DEFAULT_SOCKS5_PORT,
                     namespace: XmrBtcNamespace::Mainnet,
                 },
@@ -1231,6 +1856,8 @@ mod tests {
                     bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL_TESTNET)
                         .unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET_TESTNET,
+                    refund_address: None,
+                    manual_broadcast: false,
                 },
             }
         }
@@ -1245,6 +1872,8 @@ mod tests {
                     swap_id: Uuid::from_str(SWAP_ID).unwrap(),
                     bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL).unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET,
+                    refund_address: None,
+                    manual_broadcast: false,
                 },
             }
         }
@@ -1260,6 +1889,8 @@ mod tests {
                     bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL_TESTNET)
                         .unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET_TESTNET,
+                    refund_address: None,
+                    manual_broadcast: false,
                 },
             }
         }
@@ -1274,6 +1905,8 @@ mod tests {
                     swap_id: Uuid::from_str(SWAP_ID).unwrap(),
                     bitcoin_electrum_rpc_url: Url::from_str(DEFAULT_ELECTRUM_RPC_URL).unwrap(),
                     bitcoin_target_block: DEFAULT_BITCOIN_CONFIRMATION_TARGET,
+                    refund_address: None,
+                    manual_broadcast: false,
                 },
             }
         }
@@ -1293,6 +1926,23 @@ mod tests {
             self
         }
 
+        pub fn without_compression(mut self) -> Self {
+            self.env_config.message_compression = false;
+            self
+        }
+
+        pub fn with_cancel_anchor(mut self) -> Self {
+            self.env_config.cancel_anchor = true;
+            self
+        }
+
+        pub fn with_top_up_timeout(mut self, timeout: Duration) -> Self {
+            if let Command::BuyXmr { top_up_timeout, .. } = &mut self.cmd {
+                *top_up_timeout = Some(timeout);
+            }
+            self
+        }
+
         pub fn into_boxed(self) -> Box<Self> {
             Box::new(self)
         }
@@ -0,0 +1,35 @@
+use crate::monero;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const FILE_NAME: &str = "defaults.toml";
+
+/// Addresses to use for a swap if none are given on the command line.
+///
+/// They are read from `defaults.toml` in the data directory, so a user that
+/// always swaps to the same destination doesn't have to pass them every time.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    pub bitcoin_change_address: Option<bitcoin::Address>,
+    pub monero_receive_address: Option<monero::Address>,
+}
+
+impl Defaults {
+    /// Reads the defaults from the given data directory, a missing file means
+    /// there are no defaults.
+    pub fn read(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(FILE_NAME);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read defaults from {}", path.display()))?;
+
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse defaults from {}", path.display()))
+    }
+}
@@ -2,9 +2,12 @@ use crate::bitcoin::EncryptedSignature;
 use crate::cli::behaviour::{Behaviour, OutEvent};
 use crate::monero;
 use crate::network::encrypted_signature;
+use crate::network::price_curve::{self, PriceCurve};
 use crate::network::quote::BidQuote;
+use crate::network::resend_transfer_proof;
 use crate::network::swap_setup::bob::NewSwap;
 use crate::protocol::bob::State2;
+use crate::protocol::{swap_span, Role};
 use anyhow::{Context, Result};
 use futures::future::{BoxFuture, OptionFuture};
 use futures::{FutureExt, StreamExt};
@@ -13,7 +16,10 @@ use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::SwarmEvent;
 use libp2p::{PeerId, Swarm};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::watch;
+use tracing::Instrument;
 use uuid::Uuid;
 
 #[allow(missing_debug_implementations)]
@@ -24,16 +30,30 @@ pub struct EventLoop {
 
     // these streams represents outgoing requests that we have to make
     quote_requests: bmrng::RequestReceiverStream<(), BidQuote>,
+    price_curve_requests: bmrng::RequestReceiverStream<price_curve::Request, PriceCurve>,
     encrypted_signatures: bmrng::RequestReceiverStream<EncryptedSignature, ()>,
+    transfer_proof_resend_requests: bmrng::RequestReceiverStream<(), Option<monero::TransferProof>>,
     swap_setup_requests: bmrng::RequestReceiverStream<NewSwap, Result<State2>>,
 
     // these represents requests that are currently in-flight.
     // once we get a response to a matching [`RequestId`], we will use the responder to relay the
     // response.
     inflight_quote_requests: HashMap<RequestId, bmrng::Responder<BidQuote>>,
+    inflight_price_curve_requests: HashMap<RequestId, bmrng::Responder<PriceCurve>>,
     inflight_encrypted_signature_requests: HashMap<RequestId, bmrng::Responder<()>>,
+    inflight_transfer_proof_resend_requests:
+        HashMap<RequestId, bmrng::Responder<Option<monero::TransferProof>>>,
     inflight_swap_setup: Option<bmrng::Responder<Result<State2>>>,
 
+    /// The encrypted signature the swap asked us to send while we were not
+    /// connected to Alice, sent as soon as we are connected again.
+    ///
+    /// While there is an encrypted signature waiting to be delivered, Bob's
+    /// Bitcoin is locked. We then keep redialling Alice and do not stop on
+    /// communication errors, it is up to the swap to give up once its cancel
+    /// timelock approaches.
+    pending_encrypted_signature: Option<(EncryptedSignature, bmrng::Responder<()>)>,
+
     /// The sender we will use to relay incoming transfer proofs.
     transfer_proof: bmrng::RequestSender<monero::TransferProof, ()>,
     /// The future representing the successful handling of an incoming transfer
@@ -56,6 +76,8 @@ impl EventLoop {
         let transfer_proof = bmrng::channel_with_timeout(1, Duration::from_secs(60));
         let encrypted_signature = bmrng::channel(1);
         let quote = bmrng::channel_with_timeout(1, Duration::from_secs(60));
+        let price_curve = bmrng::channel_with_timeout(1, Duration::from_secs(60));
+        let resend_transfer_proof = bmrng::channel_with_timeout(1, Duration::from_secs(60));
 
         let event_loop = EventLoop {
             swap_id,
@@ -66,8 +88,13 @@ impl EventLoop {
             encrypted_signatures: encrypted_signature.1.into(),
             quote_requests: quote.1.into(),
             inflight_quote_requests: HashMap::default(),
+            price_curve_requests: price_curve.1.into(),
+            inflight_price_curve_requests: HashMap::default(),
             inflight_swap_setup: None,
             inflight_encrypted_signature_requests: HashMap::default(),
+            pending_encrypted_signature: None,
+            transfer_proof_resend_requests: resend_transfer_proof.1.into(),
+            inflight_transfer_proof_resend_requests: HashMap::default(),
             pending_transfer_proof: OptionFuture::from(None),
         };
 
@@ -76,12 +103,20 @@ impl EventLoop {
             transfer_proof: transfer_proof.1,
             encrypted_signature: encrypted_signature.0,
             quote: quote.0,
+            price_curve: price_curve.0,
+            resend_transfer_proof: resend_transfer_proof.0,
         };
 
         Ok((event_loop, handle))
     }
 
-    pub async fn run(mut self) {
+    pub async fn run(self) {
+        let span = swap_span(self.swap_id, Role::Bob);
+
+        self.run_loop().instrument(span).await
+    }
+
+    async fn run_loop(mut self) {
         match self.swarm.dial(DialOpts::from(self.alice_peer_id)) {
             Ok(()) => {}
             Err(e) => {
@@ -91,6 +126,8 @@ impl EventLoop {
         }
 
         loop {
+            self.send_pending_encrypted_signature();
+
             // Note: We are making very elaborate use of `select!` macro's feature here. Make sure to read the documentation thoroughly: https://docs.rs/tokio/1.4.0/tokio/macro.select.html
             tokio::select! {
                 swarm_event = self.swarm.select_next_some() => {
@@ -100,6 +137,11 @@ impl EventLoop {
                                 let _ = responder.respond(response);
                             }
                         }
+                        SwarmEvent::Behaviour(OutEvent::PriceCurveReceived { id, response }) => {
+                            if let Some(responder) = self.inflight_price_curve_requests.remove(&id) {
+                                let _ = responder.respond(response);
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::SwapSetupCompleted(response)) => {
                             if let Some(responder) = self.inflight_swap_setup.take() {
                                 let _ = responder.respond(*response);
@@ -141,16 +183,39 @@ impl EventLoop {
                                 channel
                             }.boxed()));
                         }
+                        SwarmEvent::Behaviour(OutEvent::TransferProofResent { id, response }) => {
+                            if let Some(responder) = self.inflight_transfer_proof_resend_requests.remove(&id) {
+                                let _ = responder.respond(response.tx_lock_proof);
+                            }
+                        }
                         SwarmEvent::Behaviour(OutEvent::EncryptedSignatureAcknowledged { id }) => {
                             if let Some(responder) = self.inflight_encrypted_signature_requests.remove(&id) {
                                 let _ = responder.respond(());
                             }
                         }
                         SwarmEvent::Behaviour(OutEvent::AllRedialAttemptsExhausted { peer }) if peer == self.alice_peer_id => {
+                            if self.is_delivering_encrypted_signature() {
+                                tracing::warn!("Exhausted all re-dial attempts to Alice, continuing to redial until the encrypted signature is delivered");
+                                self.swarm.behaviour_mut().redial.restart();
+                                continue;
+                            }
+
                             tracing::error!("Exhausted all re-dial attempts to Alice");
                             return;
                         }
+                        SwarmEvent::Behaviour(OutEvent::PeerIdentified { peer, agent_version }) if peer == self.alice_peer_id => {
+                            tracing::debug!(%agent_version, "Alice identified");
+                        }
                         SwarmEvent::Behaviour(OutEvent::Failure { peer, error }) => {
+                            if self.is_delivering_encrypted_signature() {
+                                tracing::warn!(%peer, "Communication error while delivering the encrypted signature: {:#}", error);
+
+                                // Dropping the responders tells the swap that the encrypted
+                                // signature was not acknowledged, it sends it again
+                                self.inflight_encrypted_signature_requests.clear();
+                                continue;
+                            }
+
                             tracing::warn!(%peer, "Communication error: {:#}", error);
                             return;
                         }
@@ -186,18 +251,26 @@ impl EventLoop {
                     let id = self.swarm.behaviour_mut().quote.send_request(&self.alice_peer_id, ());
                     self.inflight_quote_requests.insert(id, responder);
                 },
+                Some((request, responder)) = self.price_curve_requests.next().fuse(), if self.is_connected_to_alice() => {
+                    let id = self.swarm.behaviour_mut().price_curve.send_request(&self.alice_peer_id, request);
+                    self.inflight_price_curve_requests.insert(id, responder);
+                },
                 Some((swap, responder)) = self.swap_setup_requests.next().fuse(), if self.is_connected_to_alice() => {
                     self.swarm.behaviour_mut().swap_setup.start(self.alice_peer_id, swap).await;
                     self.inflight_swap_setup = Some(responder);
                 },
-                Some((tx_redeem_encsig, responder)) = self.encrypted_signatures.next().fuse(), if self.is_connected_to_alice() => {
-                    let request = encrypted_signature::Request {
+                // The encrypted signature is taken even if we are not connected, so we know to keep
+                // redialling Alice until it is delivered.
+                Some((tx_redeem_encsig, responder)) = self.encrypted_signatures.next().fuse(), if self.pending_encrypted_signature.is_none() => {
+                    self.pending_encrypted_signature = Some((tx_redeem_encsig, responder));
+                },
+                Some(((), responder)) = self.transfer_proof_resend_requests.next().fuse(), if self.is_connected_to_alice() => {
+                    let request = resend_transfer_proof::Request {
                         swap_id: self.swap_id,
-                        tx_redeem_encsig
                     };
 
-                    let id = self.swarm.behaviour_mut().encrypted_signature.send_request(&self.alice_peer_id, request);
-                    self.inflight_encrypted_signature_requests.insert(id, responder);
+                    let id = self.swarm.behaviour_mut().resend_transfer_proof.send_request(&self.alice_peer_id, request);
+                    self.inflight_transfer_proof_resend_requests.insert(id, responder);
                 },
 
                 Some(response_channel) = &mut self.pending_transfer_proof => {
@@ -212,6 +285,87 @@ impl EventLoop {
     fn is_connected_to_alice(&self) -> bool {
         self.swarm.is_connected(&self.alice_peer_id)
     }
+
+    fn is_delivering_encrypted_signature(&self) -> bool {
+        matches!(&self.pending_encrypted_signature, Some((_, responder)) if !responder.is_closed())
+            || !self.inflight_encrypted_signature_requests.is_empty()
+    }
+
+    fn send_pending_encrypted_signature(&mut self) {
+        if !self.is_connected_to_alice() {
+            return;
+        }
+
+        let (tx_redeem_encsig, responder) = match self.pending_encrypted_signature.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        // The swap gave up on delivering the encrypted signature, e.g. because the cancel
+        // timelock is about to expire. Alice must not learn it anymore.
+        if responder.is_closed() {
+            tracing::debug!("Dropping encrypted signature the swap no longer waits for");
+            return;
+        }
+
+        let request = encrypted_signature::Request {
+            swap_id: self.swap_id,
+            tx_redeem_encsig,
+        };
+
+        let id = self
+            .swarm
+            .behaviour_mut()
+            .encrypted_signature
+            .send_request(&self.alice_peer_id, request);
+        self.inflight_encrypted_signature_requests
+            .insert(id, responder);
+    }
+}
+
+/// Aborts a single running swap by its id, e.g. one leg of a
+/// [`SplitSwap`](crate::cli::SplitSwap), while other swaps keep running.
+///
+/// Swaps are registered with
+/// [`Swap::with_abort_handle`](crate::protocol::bob::Swap::with_abort_handle).
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    swaps: Arc<Mutex<HashMap<Uuid, watch::Sender<bool>>>>,
+}
+
+impl AbortHandle {
+    /// Stops the swap `swap_id` without affecting any other swap.
+    ///
+    /// A swap that was not set up yet is safely aborted. A swap that locked
+    /// its Bitcoin stops in its latest state and can be resumed later to
+    /// redeem, or to cancel and refund. A swap that is currently publishing
+    /// its lock transaction is stopped once the lock is recorded.
+    ///
+    /// Returns `false` if no swap with that id is running.
+    pub fn abort_swap(&self, swap_id: Uuid) -> bool {
+        let mut swaps = self
+            .swaps
+            .lock()
+            .expect("abort requests are never poisoned");
+
+        match swaps.remove(&swap_id) {
+            Some(sender) => sender.send(true).is_ok(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn register(&self, swap_id: Uuid) -> watch::Receiver<bool> {
+        let (sender, receiver) = watch::channel(false);
+
+        let mut swaps = self
+            .swaps
+            .lock()
+            .expect("abort requests are never poisoned");
+        swaps.retain(|_, sender| !sender.is_closed());
+        swaps.insert(swap_id, sender);
+
+        receiver
+    }
 }
 
 #[derive(Debug)]
@@ -220,6 +374,8 @@ pub struct EventLoopHandle {
     transfer_proof: bmrng::RequestReceiver<monero::TransferProof, ()>,
     encrypted_signature: bmrng::RequestSender<EncryptedSignature, ()>,
     quote: bmrng::RequestSender<(), BidQuote>,
+    price_curve: bmrng::RequestSender<price_curve::Request, PriceCurve>,
+    resend_transfer_proof: bmrng::RequestSender<(), Option<monero::TransferProof>>,
 }
 
 impl EventLoopHandle {
@@ -227,23 +383,65 @@ impl EventLoopHandle {
         self.swap_setup.send_receive(swap).await?
     }
 
-    pub async fn recv_transfer_proof(&mut self) -> Result<monero::TransferProof> {
-        let (transfer_proof, responder) = self
-            .transfer_proof
-            .recv()
-            .await
-            .context("Failed to receive transfer proof")?;
-        responder
-            .respond(())
-            .context("Failed to acknowledge receipt of transfer proof")?;
+    /// Waits for Alice to send the transfer proof.
+    ///
+    /// In case her message got lost, e.g. because Bob was offline, Alice is
+    /// asked to send the proof once more every `resend_interval`.
+    pub async fn recv_transfer_proof(
+        &mut self,
+        resend_interval: Duration,
+    ) -> Result<monero::TransferProof> {
+        let transfer_proof = &mut self.transfer_proof;
+        let resend_transfer_proof = &mut self.resend_transfer_proof;
+
+        let received = async {
+            let (transfer_proof, responder) = transfer_proof
+                .recv()
+                .await
+                .context("Failed to receive transfer proof")?;
+            responder
+                .respond(())
+                .context("Failed to acknowledge receipt of transfer proof")?;
+
+            Ok(transfer_proof)
+        };
+        let resent = async {
+            loop {
+                tokio::time::sleep(resend_interval).await;
 
-        Ok(transfer_proof)
+                match resend_transfer_proof.send_receive(()).await {
+                    Ok(Some(transfer_proof)) => return transfer_proof,
+                    Ok(None) => tracing::debug!("Alice has not locked the Monero yet"),
+                    Err(error) => {
+                        tracing::debug!("Failed to ask Alice for the transfer proof: {:#}", error)
+                    }
+                }
+            }
+        };
+
+        tokio::select! {
+            result = received => result,
+            transfer_proof = resent => {
+                tracing::info!("Alice sent the transfer proof again on request");
+
+                Ok(transfer_proof)
+            }
+        }
     }
 
     pub async fn request_quote(&mut self) -> Result<BidQuote> {
         Ok(self.quote.send_receive(()).await?)
     }
 
+    /// Requests the XMR Alice offers for BTC amounts in the given range, see
+    /// [`price_curve::Request`].
+    pub async fn request_price_curve(
+        &mut self,
+        request: price_curve::Request,
+    ) -> Result<PriceCurve> {
+        Ok(self.price_curve.send_receive(request).await?)
+    }
+
     pub async fn send_encrypted_signature(
         &mut self,
         tx_redeem_encsig: EncryptedSignature,
@@ -0,0 +1,418 @@
+use crate::network::quote::BidQuote;
+use crate::network::rendezvous::XmrBtcNamespace;
+use crate::network::swarm;
+use crate::protocol::bob::{self, BobState};
+use crate::protocol::Database;
+use crate::{bitcoin, cli, env, monero};
+use anyhow::{anyhow, bail, Context, Result};
+use libp2p::{identity, Multiaddr, PeerId};
+use std::cmp::min;
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Runs Bob's side of a single large swap as several independent swaps
+/// (legs) against different sellers.
+#[allow(missing_debug_implementations)]
+pub struct SplitSwap {
+    pub db: Arc<dyn Database + Send + Sync>,
+    pub bitcoin_wallet: Arc<bitcoin::Wallet>,
+    pub monero_wallet: Arc<monero::Wallet>,
+    pub env_config: env::Config,
+    pub identity: identity::Keypair,
+    pub namespace: XmrBtcNamespace,
+    pub tor_socks5_port: u16,
+    pub monero_receive_address: monero::Address,
+    /// Aborts a single leg by its swap id.
+    pub abort_handle: cli::AbortHandle,
+}
+
+/// The portion of a split swap that is executed against a single seller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leg {
+    pub swap_id: Uuid,
+    pub seller: PeerId,
+    pub btc_amount: bitcoin::Amount,
+}
+
+/// The aggregated result of all legs of a split swap.
+///
+/// A failed leg does not affect the other legs, they run to completion
+/// independently.
+#[derive(Debug)]
+pub struct Outcome {
+    pub completed: Vec<(Leg, BobState)>,
+    pub failed: Vec<(Leg, anyhow::Error)>,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Sellers are only willing to buy {available} of the requested {requested}")]
+pub struct InsufficientLiquidity {
+    pub requested: bitcoin::Amount,
+    pub available: bitcoin::Amount,
+}
+
+struct Candidate {
+    swap_id: Uuid,
+    seller: PeerId,
+    quote: BidQuote,
+    event_loop: JoinHandle<()>,
+    event_loop_handle: cli::EventLoopHandle,
+}
+
+impl SplitSwap {
+    /// Splits `total_amount` across the given sellers and runs one swap per
+    /// seller that gets a portion of it.
+    ///
+    /// Sellers that do not respond with a quote are skipped. The legs lock
+    /// their Bitcoin one after another to not spend the same coins twice,
+    /// afterwards they continue concurrently.
+    ///
+    /// If a leg cannot be started, the legs that were not started yet are
+    /// abandoned and reported as failed. Legs that already run are not
+    /// interrupted, because they may have locked Bitcoin, and are reported
+    /// once they finished.
+    pub async fn split_swap(
+        &self,
+        total_amount: bitcoin::Amount,
+        makers: Vec<(PeerId, Multiaddr)>,
+    ) -> Result<Outcome> {
+        let mut candidates = Vec::new();
+
+        for (seller, address) in makers {
+            if let Err(error) = self.add_candidate(&mut candidates, seller, address).await {
+                abort_all(candidates).await;
+                return Err(error);
+            }
+        }
+
+        let quotes = candidates.iter().map(|c| c.quote).collect::<Vec<_>>();
+        let amounts = match split_amount(total_amount, &quotes) {
+            Ok(amounts) => amounts,
+            Err(error) => {
+                abort_all(candidates).await;
+                return Err(error);
+            }
+        };
+
+        let mut outcome = Outcome {
+            completed: Vec::new(),
+            failed: Vec::new(),
+        };
+        let mut legs = Vec::new();
+
+        for (candidate, btc_amount) in candidates.into_iter().zip(amounts) {
+            if btc_amount == bitcoin::Amount::ZERO {
+                abort_and_join(candidate.event_loop).await;
+                continue;
+            }
+
+            let leg = Leg {
+                swap_id: candidate.swap_id,
+                seller: candidate.seller,
+                btc_amount,
+            };
+            legs.push((leg, candidate));
+        }
+
+        let mut running = Vec::new();
+        let mut start_failure = None;
+
+        for (leg, candidate) in legs {
+            if let Some(error) = &start_failure {
+                abort_and_join(candidate.event_loop).await;
+                outcome.failed.push((
+                    leg,
+                    anyhow!("Leg of split swap was not started: {:#}", error),
+                ));
+                continue;
+            }
+
+            tracing::info!(swap_id = %leg.swap_id, seller = %leg.seller, amount = %leg.btc_amount, "Starting leg of split swap");
+
+            match self.start_leg(leg, candidate).await {
+                Ok(leg_run) => {
+                    self.wait_until_btc_locked(leg.swap_id, &leg_run).await;
+                    running.push((leg, leg_run));
+
+                    if let Err(error) = self.bitcoin_wallet.sync().await {
+                        start_failure = Some(error);
+                    }
+                }
+                Err(error) => {
+                    let error = error.context("Failed to start leg of split swap");
+                    tracing::error!(swap_id = %leg.swap_id, seller = %leg.seller, "{:#}", error);
+                    outcome.failed.push((leg, error));
+
+                    start_failure =
+                        Some(anyhow!("An earlier leg of the split swap failed to start"));
+                }
+            }
+        }
+
+        for (leg, leg_run) in running {
+            match leg_run.await.context("Leg of split swap panicked") {
+                Ok(Ok(state)) => outcome.completed.push((leg, state)),
+                Ok(Err(error)) | Err(error) => {
+                    tracing::error!(swap_id = %leg.swap_id, seller = %leg.seller, "Leg of split swap failed: {:#}", error);
+                    outcome.failed.push((leg, error));
+                }
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Requests a quote from `seller` and keeps it as a candidate for a leg
+    /// if it responds.
+    async fn add_candidate(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        seller: PeerId,
+        address: Multiaddr,
+    ) -> Result<()> {
+        let swap_id = Uuid::new_v4();
+        let (event_loop, mut event_loop_handle) = self
+            .new_event_loop(swap_id, seller, address.clone())
+            .await?;
+        let event_loop = tokio::spawn(event_loop.run());
+
+        match event_loop_handle.request_quote().await {
+            Ok(quote) => {
+                candidates.push(Candidate {
+                    swap_id,
+                    seller,
+                    quote,
+                    event_loop,
+                    event_loop_handle,
+                });
+                self.db.insert_address(seller, address).await?;
+            }
+            Err(error) => {
+                tracing::warn!(%seller, "Skipping seller, failed to request quote: {:#}", error);
+                abort_and_join(event_loop).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the swap of the given leg in the background.
+    async fn start_leg(
+        &self,
+        leg: Leg,
+        candidate: Candidate,
+    ) -> Result<JoinHandle<Result<BobState>>> {
+        let swap = match self.new_swap(leg, candidate.event_loop_handle).await {
+            Ok(swap) => swap,
+            Err(error) => {
+                abort_and_join(candidate.event_loop).await;
+                return Err(error);
+            }
+        };
+        let event_loop = candidate.event_loop;
+
+        Ok(tokio::spawn(async move {
+            tokio::select! {
+                result = event_loop => {
+                    result.context("EventLoop panicked")?;
+                    bail!("EventLoop stopped before the swap finished")
+                },
+                result = bob::run(swap) => result,
+            }
+        }))
+    }
+
+    async fn new_swap(
+        &self,
+        leg: Leg,
+        event_loop_handle: cli::EventLoopHandle,
+    ) -> Result<bob::Swap> {
+        self.db.insert_peer_id(leg.swap_id, leg.seller).await?;
+        self.db
+            .insert_monero_address(leg.swap_id, self.monero_receive_address)
+            .await?;
+
+        Ok(bob::Swap::new(
+            self.db.clone(),
+            leg.swap_id,
+            self.bitcoin_wallet.clone(),
+            self.monero_wallet.clone(),
+            self.env_config,
+            event_loop_handle,
+            self.monero_receive_address,
+            self.bitcoin_wallet.new_address().await?,
+            leg.btc_amount,
+        )
+        .with_abort_handle(&self.abort_handle))
+    }
+
+    async fn new_event_loop(
+        &self,
+        swap_id: Uuid,
+        seller: PeerId,
+        address: Multiaddr,
+    ) -> Result<(cli::EventLoop, cli::EventLoopHandle)> {
+        let behaviour = cli::Behaviour::new(
+            seller,
+            self.env_config,
+            self.bitcoin_wallet.clone(),
+            (self.identity.clone(), self.namespace),
+        );
+        let mut swarm = swarm::cli(self.identity.clone(), self.tor_socks5_port, behaviour).await?;
+        swarm.behaviour_mut().add_address(seller, address);
+
+        cli::EventLoop::new(swap_id, swarm, seller)
+    }
+
+    /// Waits until the given leg published its Bitcoin lock transaction or
+    /// stopped, whatever happens first.
+    async fn wait_until_btc_locked(&self, swap_id: Uuid, leg_run: &JoinHandle<Result<BobState>>) {
+        loop {
+            if leg_run.is_finished() {
+                return;
+            }
+
+            let state: Option<BobState> = match self.db.get_state(swap_id).await {
+                Ok(state) => state.try_into().ok(),
+                Err(_) => None,
+            };
+
+            if !matches!(
+                state,
+                None | Some(BobState::Started { .. }) | Some(BobState::SwapSetupCompleted(..))
+            ) {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// Stops the event loops of candidates that do not get a leg.
+async fn abort_all(candidates: Vec<Candidate>) {
+    for candidate in candidates {
+        abort_and_join(candidate.event_loop).await;
+    }
+}
+
+async fn abort_and_join(event_loop: JoinHandle<()>) {
+    event_loop.abort();
+    let _ = event_loop.await;
+}
+
+/// Splits `total` across the sellers of the given quotes, cheapest seller
+/// first, respecting each seller's minimum and maximum quantity.
+///
+/// Returns the amount per seller in the order of `quotes`, sellers that are
+/// not needed get [`bitcoin::Amount::ZERO`].
+pub fn split_amount(total: bitcoin::Amount, quotes: &[BidQuote]) -> Result<Vec<bitcoin::Amount>> {
+    let mut cheapest_first = (0..quotes.len()).collect::<Vec<_>>();
+    cheapest_first.sort_by_key(|&index| quotes[index].price);
+
+    let mut amounts = vec![bitcoin::Amount::ZERO; quotes.len()];
+    let mut remaining = total;
+
+    for index in cheapest_first {
+        let quote = quotes[index];
+        let amount = min(remaining, quote.max_quantity);
+
+        if amount == bitcoin::Amount::ZERO || amount < quote.min_quantity {
+            continue;
+        }
+
+        amounts[index] = amount;
+        remaining -= amount;
+    }
+
+    if remaining > bitcoin::Amount::ZERO {
+        bail!(InsufficientLiquidity {
+            requested: total,
+            available: total - remaining,
+        })
+    }
+
+    Ok(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_two_sellers_total_is_satisfied_by_two_legs() {
+        let quotes = [quote(2_000, 0, 60_000_000), quote(1_000, 0, 60_000_000)];
+
+        let amounts = split_amount(bitcoin::Amount::from_sat(100_000_000), &quotes).unwrap();
+
+        // the cheaper seller is filled up first
+        assert_eq!(
+            amounts,
+            vec![
+                bitcoin::Amount::from_sat(40_000_000),
+                bitcoin::Amount::from_sat(60_000_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_single_seller_can_satisfy_total_other_seller_is_not_used() {
+        let quotes = [quote(1_000, 0, 200_000_000), quote(2_000, 0, 200_000_000)];
+
+        let amounts = split_amount(bitcoin::Amount::from_sat(100_000_000), &quotes).unwrap();
+
+        assert_eq!(
+            amounts,
+            vec![
+                bitcoin::Amount::from_sat(100_000_000),
+                bitcoin::Amount::ZERO
+            ]
+        );
+    }
+
+    #[test]
+    fn given_remainder_below_min_quantity_seller_is_skipped() {
+        let quotes = [
+            quote(1_000, 0, 90_000_000),
+            quote(2_000, 20_000_000, 100_000_000),
+            quote(3_000, 0, 100_000_000),
+        ];
+
+        let amounts = split_amount(bitcoin::Amount::from_sat(100_000_000), &quotes).unwrap();
+
+        assert_eq!(
+            amounts,
+            vec![
+                bitcoin::Amount::from_sat(90_000_000),
+                bitcoin::Amount::ZERO,
+                bitcoin::Amount::from_sat(10_000_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_not_enough_liquidity_returns_error() {
+        let quotes = [quote(1_000, 0, 30_000_000), quote(2_000, 0, 30_000_000)];
+
+        let error = split_amount(bitcoin::Amount::from_sat(100_000_000), &quotes).unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<InsufficientLiquidity>(),
+            Some(&InsufficientLiquidity {
+                requested: bitcoin::Amount::from_sat(100_000_000),
+                available: bitcoin::Amount::from_sat(60_000_000),
+            })
+        );
+    }
+
+    fn quote(price: u64, min_quantity: u64, max_quantity: u64) -> BidQuote {
+        BidQuote {
+            price: bitcoin::Amount::from_sat(price),
+            min_quantity: bitcoin::Amount::from_sat(min_quantity),
+            max_quantity: bitcoin::Amount::from_sat(max_quantity),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::bitcoin::wallet::ScriptStatus;
+use crate::bitcoin::{
+    build_lock_descriptor, current_epoch, validate_lock_keys, Amount, CancelTimelock,
+    ExpiredTimelocks, PublicKey, PunishTimelock, TimelockStatus, Wallet,
+};
+use anyhow::{Context, Result};
+use bitcoin::{OutPoint, Txid};
+use std::fmt;
+
+/// The state of a swap's Bitcoin lock as reconstructed from the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchOnlyReport {
+    pub lock_amount: Amount,
+    pub lock_status: ScriptStatus,
+    /// How far the cancel timelock, relative to the lock transaction, is
+    /// from expiring.
+    pub cancel_timelock: TimelockStatus,
+    /// How far the punish timelock, relative to the cancel transaction, is
+    /// from expiring. `None` as long as the lock is not cancelled.
+    pub punish_timelock: Option<TimelockStatus>,
+    pub expired_timelocks: ExpiredTimelocks,
+    pub outcome: LockOutcome,
+}
+
+/// What happened to the Bitcoin lock output so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockOutcome {
+    Unspent,
+    Redeemed { tx_redeem: Txid },
+    Cancelled { tx_cancel: Txid },
+    Refunded { tx_cancel: Txid, tx_refund: Txid },
+    Punished { tx_cancel: Txid, tx_punish: Txid },
+}
+
+impl WatchOnlyReport {
+    /// The private material needed to act on the swap from here on.
+    pub fn required_material(&self) -> &'static str {
+        match (self.outcome, self.expired_timelocks) {
+            (LockOutcome::Unspent, ExpiredTimelocks::None) => {
+                "Alice needs Bob's encrypted signature, her Bitcoin secret key and her Monero key share to redeem; Bob has to wait for the cancel timelock"
+            }
+            (LockOutcome::Unspent, _) => {
+                "Publishing the cancel transaction needs Bob's Bitcoin secret key and Alice's signature on the cancel transaction"
+            }
+            (LockOutcome::Redeemed { .. }, _) => {
+                "Bob needs his Monero key share and his Bitcoin secret key to extract Alice's Monero key share from the redeem transaction"
+            }
+            (LockOutcome::Cancelled { .. }, ExpiredTimelocks::Punish) => {
+                "Bob needs his Bitcoin secret key and Alice's encrypted refund signature to refund; Alice can punish with her Bitcoin secret key and Bob's punish signature"
+            }
+            (LockOutcome::Cancelled { .. }, _) => {
+                "Bob needs his Bitcoin secret key and Alice's encrypted refund signature to refund before the punish timelock expires"
+            }
+            (LockOutcome::Refunded { .. }, _) => {
+                "Alice needs her Monero key share and her Bitcoin secret key to extract Bob's Monero key share from the refund transaction"
+            }
+            (LockOutcome::Punished { .. }, _) => {
+                "Nothing is left to recover, Alice punished Bob"
+            }
+        }
+    }
+}
+
+impl fmt::Display for LockOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockOutcome::Unspent => write!(f, "unspent"),
+            LockOutcome::Redeemed { tx_redeem } => write!(f, "redeemed by {}", tx_redeem),
+            LockOutcome::Cancelled { tx_cancel } => write!(f, "cancelled by {}", tx_cancel),
+            LockOutcome::Refunded {
+                tx_cancel,
+                tx_refund,
+            } => write!(f, "cancelled by {}, refunded by {}", tx_cancel, tx_refund),
+            LockOutcome::Punished {
+                tx_cancel,
+                tx_punish,
+            } => write!(f, "cancelled by {}, punished by {}", tx_cancel, tx_punish),
+        }
+    }
+}
+
+/// Reconstructs the state of a swap's Bitcoin lock from the chain, given only
+/// the id of the lock transaction and the public keys of Alice (`A`) and Bob
+/// (`B`).
+///
+/// This is a last resort if the swap's database is lost: it does not need any
+/// private material and tells which of it is still needed to act.
+pub async fn watch_only_resume(
+    bitcoin_wallet: &Wallet,
+    lock_txid: Txid,
+    A: PublicKey,
+    B: PublicKey,
+    cancel_timelock: CancelTimelock,
+    punish_timelock: PunishTimelock,
+) -> Result<WatchOnlyReport> {
+    validate_lock_keys(A, B)?;
+
+    // The cancel output pays to the same 2-of-2 as the lock output
+    let shared_script = build_lock_descriptor(A, B).script_pubkey();
+
+    let tx_lock = bitcoin_wallet.get_raw_transaction(lock_txid).await?;
+    let (vout, lock_output) = tx_lock
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, output)| output.script_pubkey == shared_script)
+        .with_context(|| {
+            format!(
+                "Transaction {} does not pay to the 2-of-2 of the given keys",
+                lock_txid
+            )
+        })?;
+    let lock = (lock_txid, shared_script.clone());
+
+    let lock_status = bitcoin_wallet.status_of_script(&lock).await?;
+    let cancel_timelock_status = bitcoin_wallet
+        .timelock_status(&lock, cancel_timelock)
+        .await?;
+
+    let lock_spender = bitcoin_wallet
+        .spending_transaction(
+            OutPoint::new(lock_txid, u32::try_from(vout)?),
+            &shared_script,
+        )
+        .await?;
+
+    let (outcome, tx_cancel_status, punish_timelock_status) = match lock_spender {
+        None => (LockOutcome::Unspent, ScriptStatus::Unseen, None),
+        Some(spender) => {
+            let transaction = bitcoin_wallet.get_raw_transaction(spender).await?;
+            let is_cancel = transaction
+                .output
+                .first()
+                .map_or(false, |output| output.script_pubkey == shared_script);
+
+            if is_cancel {
+                let tx_cancel = spender;
+                let cancel = (tx_cancel, shared_script.clone());
+
+                let tx_cancel_status = bitcoin_wallet.status_of_script(&cancel).await?;
+                let punish_timelock_status = bitcoin_wallet
+                    .timelock_status(&cancel, punish_timelock)
+                    .await?;
+
+                let outcome = match bitcoin_wallet
+                    .spending_transaction(OutPoint::new(tx_cancel, 0), &shared_script)
+                    .await?
+                {
+                    None => LockOutcome::Cancelled { tx_cancel },
+                    Some(spender) => {
+                        let transaction = bitcoin_wallet.get_raw_transaction(spender).await?;
+
+                        // Only the punish transaction is subject to a relative timelock
+                        if transaction
+                            .input
+                            .iter()
+                            .any(|input| input.sequence.is_relative_lock_time())
+                        {
+                            LockOutcome::Punished {
+                                tx_cancel,
+                                tx_punish: spender,
+                            }
+                        } else {
+                            LockOutcome::Refunded {
+                                tx_cancel,
+                                tx_refund: spender,
+                            }
+                        }
+                    }
+                };
+
+                (outcome, tx_cancel_status, Some(punish_timelock_status))
+            } else {
+                (
+                    LockOutcome::Redeemed { tx_redeem: spender },
+                    ScriptStatus::Unseen,
+                    None,
+                )
+            }
+        }
+    };
+
+    Ok(WatchOnlyReport {
+        lock_amount: Amount::from_sat(lock_output.value),
+        lock_status,
+        cancel_timelock: cancel_timelock_status,
+        punish_timelock: punish_timelock_status,
+        expired_timelocks: current_epoch(
+            cancel_timelock,
+            punish_timelock,
+            lock_status,
+            tx_cancel_status,
+        ),
+        outcome,
+    })
+}
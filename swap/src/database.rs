@@ -1,5 +1,8 @@
 pub use alice::Alice;
+pub use backup::{DecryptionFailed, OnCollision, StateEntry, SwapAlreadyExists, SwapBackup};
 pub use bob::Bob;
+pub use receipt::{SwapNotFinished, SwapReceipt};
+pub use repair::{Repair, RepairReport};
 pub use sqlite::SqliteDatabase;
 
 use crate::fs::ensure_directory_exists;
@@ -11,7 +14,10 @@ use std::path::Path;
 use std::sync::Arc;
 
 mod alice;
+pub(crate) mod backup;
 mod bob;
+pub(crate) mod receipt;
+mod repair;
 mod sqlite;
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
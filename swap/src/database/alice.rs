@@ -42,6 +42,12 @@ pub enum Alice {
         encrypted_signature: EncryptedSignature,
         state3: alice::State3,
     },
+    BtcRedeemReadyToBroadcast {
+        monero_wallet_restore_blockheight: BlockHeight,
+        transfer_proof: TransferProof,
+        encrypted_signature: EncryptedSignature,
+        state3: alice::State3,
+    },
     BtcRedeemTransactionPublished {
         state3: alice::State3,
     },
@@ -128,6 +134,17 @@ impl From<AliceState> for Alice {
                 state3: state3.as_ref().clone(),
                 encrypted_signature: encrypted_signature.as_ref().clone(),
             },
+            AliceState::BtcRedeemReadyToBroadcast {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature,
+                state3,
+            } => Alice::BtcRedeemReadyToBroadcast {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature: encrypted_signature.as_ref().clone(),
+                state3: state3.as_ref().clone(),
+            },
             AliceState::BtcRedeemTransactionPublished { state3 } => {
                 Alice::BtcRedeemTransactionPublished {
                     state3: state3.as_ref().clone(),
@@ -229,6 +246,17 @@ impl From<Alice> for AliceState {
                 state3: Box::new(state),
                 encrypted_signature: Box::new(encrypted_signature),
             },
+            Alice::BtcRedeemReadyToBroadcast {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature,
+                state3,
+            } => AliceState::BtcRedeemReadyToBroadcast {
+                monero_wallet_restore_blockheight,
+                transfer_proof,
+                encrypted_signature: Box::new(encrypted_signature),
+                state3: Box::new(state3),
+            },
             Alice::BtcRedeemTransactionPublished { state3 } => {
                 AliceState::BtcRedeemTransactionPublished {
                     state3: Box::new(state3),
@@ -297,6 +325,9 @@ impl fmt::Display for Alice {
                 f.write_str("Monero lock transfer proof sent")
             }
             Alice::EncSigLearned { .. } => f.write_str("Encrypted signature learned"),
+            Alice::BtcRedeemReadyToBroadcast { .. } => {
+                f.write_str("Bitcoin redeem transaction ready to broadcast")
+            }
             Alice::BtcRedeemTransactionPublished { .. } => {
                 f.write_str("Bitcoin redeem transaction published")
             }
@@ -0,0 +1,214 @@
+use crate::database::Swap;
+use crate::monero;
+use crate::protocol::{Database, State, SwapKeys, SwapMetadata};
+use anyhow::{bail, Context, Result};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use libp2p::{Multiaddr, PeerId};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use uuid::Uuid;
+
+/// Everything the database knows about a single swap, as written to a
+/// backup.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SwapBackup {
+    pub swap_id: Uuid,
+    /// The latest state of the swap.
+    pub state: Swap,
+    /// All states the swap went through, oldest first. Empty in backups that
+    /// only hold the latest state.
+    #[serde(default)]
+    pub history: Vec<StateEntry>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub peer_id: Option<PeerId>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub peer_addresses: Vec<Multiaddr>,
+    pub monero_address: Option<monero::Address>,
+    #[serde(default)]
+    pub swap_keys: Option<SwapKeys>,
+    #[serde(default)]
+    pub transfer_proof: Option<String>,
+    #[serde(default)]
+    pub metadata: SwapMetadata,
+}
+
+/// A state a swap went through.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StateEntry {
+    /// When the swap entered the state, in the format the database stores it.
+    pub entered_at: String,
+    pub state: Swap,
+}
+
+/// What to do when an imported swap already exists in the database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnCollision {
+    /// Keep the existing swap and don't import the one from the backup.
+    Skip,
+    /// Abort the import without importing anything.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Swap {swap_id} already exists in the database")]
+pub struct SwapAlreadyExists {
+    pub swap_id: Uuid,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Failed to decrypt swap backup, it was encrypted with a different key or is corrupted")]
+pub struct DecryptionFailed;
+
+const NONCE_LENGTH: usize = 12;
+
+pub async fn export<D>(db: &D, writer: &mut (dyn Write + Send)) -> Result<Vec<Uuid>>
+where
+    D: Database + ?Sized,
+{
+    let mut backups = Vec::new();
+
+    for (swap_id, state) in db.all().await? {
+        backups.push(backup_of(db, swap_id, state).await?);
+    }
+
+    serde_json::to_writer_pretty(writer, &backups).context("Failed to write swap backup")?;
+
+    Ok(backups.into_iter().map(|backup| backup.swap_id).collect())
+}
+
+pub async fn import<D>(
+    db: &D,
+    reader: &mut (dyn Read + Send),
+    on_collision: OnCollision,
+) -> Result<Vec<Uuid>>
+where
+    D: Database + ?Sized,
+{
+    let backups: Vec<SwapBackup> =
+        serde_json::from_reader(reader).context("Failed to read swap backup")?;
+
+    let existing = db
+        .all()
+        .await?
+        .into_iter()
+        .map(|(swap_id, _)| swap_id)
+        .collect::<HashSet<_>>();
+
+    // Check all swaps upfront to not leave a partial import behind
+    if on_collision == OnCollision::Error {
+        if let Some(backup) = backups.iter().find(|b| existing.contains(&b.swap_id)) {
+            bail!(SwapAlreadyExists {
+                swap_id: backup.swap_id
+            })
+        }
+    }
+
+    let backups = backups
+        .into_iter()
+        .filter(|backup| {
+            let swap_id = backup.swap_id;
+            let exists = existing.contains(&swap_id);
+            if exists {
+                tracing::info!(%swap_id, "Skipping import of swap that already exists");
+            }
+
+            !exists
+        })
+        .collect::<Vec<_>>();
+    let imported = backups.iter().map(|backup| backup.swap_id).collect();
+
+    db.restore(backups).await?;
+
+    Ok(imported)
+}
+
+/// Writes everything needed to resume or recover the swap with `swap_id` on
+/// another machine as a single blob encrypted with `key`.
+///
+/// The blob contains the swap's state, which holds the keys, the Monero
+/// restore height and the lock transaction.
+pub async fn export_encrypted<D>(db: &D, swap_id: Uuid, key: &[u8; 32]) -> Result<Vec<u8>>
+where
+    D: Database + ?Sized,
+{
+    let state = db.get_state(swap_id).await?;
+    let backup = backup_of(db, swap_id, state).await?;
+    let plaintext = serde_json::to_vec(&backup).context("Failed to serialize swap backup")?;
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt swap backup"))?;
+
+    Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+}
+
+/// Imports a swap previously written by [`export_encrypted`], returning its
+/// id.
+pub async fn import_encrypted<D>(db: &D, blob: &[u8], key: &[u8; 32]) -> Result<Uuid>
+where
+    D: Database + ?Sized,
+{
+    if blob.len() < NONCE_LENGTH {
+        bail!(DecryptionFailed)
+    }
+    let (nonce, ciphertext) = blob.split_at(NONCE_LENGTH);
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| DecryptionFailed)?;
+    let backup: SwapBackup =
+        serde_json::from_slice(&plaintext).context("Failed to read swap backup")?;
+    let swap_id = backup.swap_id;
+
+    if db.all().await?.iter().any(|(id, _)| *id == swap_id) {
+        bail!(SwapAlreadyExists { swap_id })
+    }
+
+    db.restore(vec![backup]).await?;
+
+    Ok(swap_id)
+}
+
+async fn backup_of<D>(db: &D, swap_id: Uuid, state: State) -> Result<SwapBackup>
+where
+    D: Database + ?Sized,
+{
+    let peer_id = db.get_peer_id(swap_id).await.ok();
+    let peer_addresses = match peer_id {
+        Some(peer_id) => db.get_addresses(peer_id).await?,
+        None => Vec::new(),
+    };
+    let monero_address = db.get_monero_address(swap_id).await.ok();
+    let swap_keys = db.swap_keys(swap_id).await.ok();
+    let transfer_proof = db.transfer_proof(swap_id).await.ok();
+    let metadata = db.get_metadata(swap_id).await?;
+    let history = db
+        .get_states(swap_id)
+        .await?
+        .into_iter()
+        .map(|(entered_at, state)| StateEntry {
+            entered_at: entered_at.to_string(),
+            state: state.into(),
+        })
+        .collect();
+
+    Ok(SwapBackup {
+        swap_id,
+        state: state.into(),
+        history,
+        peer_id,
+        peer_addresses,
+        monero_address,
+        swap_keys,
+        transfer_proof,
+        metadata,
+    })
+}
@@ -36,6 +36,10 @@ pub enum Bob {
     BtcRedeemed(bob::State5),
     CancelTimelockExpired(bob::State6),
     BtcCancelled(bob::State6),
+    ReadyToBroadcast {
+        state6: bob::State6,
+        transaction: bob::ManualBroadcast,
+    },
     Done(BobEndState),
 }
 
@@ -79,6 +83,10 @@ impl From<BobState> for Bob {
             BobState::BtcRedeemed(state5) => Bob::BtcRedeemed(state5),
             BobState::CancelTimelockExpired(state6) => Bob::CancelTimelockExpired(state6),
             BobState::BtcCancelled(state6) => Bob::BtcCancelled(state6),
+            BobState::ReadyToBroadcast { state, transaction } => Bob::ReadyToBroadcast {
+                state6: state,
+                transaction,
+            },
             BobState::BtcRefunded(state6) => Bob::Done(BobEndState::BtcRefunded(Box::new(state6))),
             BobState::XmrRedeemed { tx_lock_id } => {
                 Bob::Done(BobEndState::XmrRedeemed { tx_lock_id })
@@ -123,6 +131,13 @@ impl From<Bob> for BobState {
             Bob::BtcRedeemed(state5) => BobState::BtcRedeemed(state5),
             Bob::CancelTimelockExpired(state6) => BobState::CancelTimelockExpired(state6),
             Bob::BtcCancelled(state6) => BobState::BtcCancelled(state6),
+            Bob::ReadyToBroadcast {
+                state6,
+                transaction,
+            } => BobState::ReadyToBroadcast {
+                state: state6,
+                transaction,
+            },
             Bob::Done(end_state) => match end_state {
                 BobEndState::SafelyAborted => BobState::SafelyAborted,
                 BobEndState::XmrRedeemed { tx_lock_id } => BobState::XmrRedeemed { tx_lock_id },
@@ -145,6 +160,9 @@ impl fmt::Display for Bob {
             Bob::XmrLocked { .. } => f.write_str("Monero locked"),
             Bob::CancelTimelockExpired(_) => f.write_str("Cancel timelock is expired"),
             Bob::BtcCancelled(_) => f.write_str("Bitcoin refundable"),
+            Bob::ReadyToBroadcast { transaction, .. } => {
+                write!(f, "Ready to broadcast {} transaction", transaction)
+            }
             Bob::BtcRedeemed(_) => f.write_str("Monero redeemable"),
             Bob::Done(end_state) => write!(f, "Done: {}", end_state),
             Bob::EncSigSent { .. } => f.write_str("Encrypted signature sent"),
@@ -0,0 +1,204 @@
+use crate::monero::TxHash;
+use crate::protocol::alice::AliceState;
+use crate::protocol::bob::BobState;
+use crate::protocol::{Database, State, SwapMetadata, SwapTransactions};
+use crate::{bitcoin, monero};
+use anyhow::{bail, Context, Result};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Summary of a finished swap, assembled from the states it went through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapReceipt {
+    pub swap_id: Uuid,
+    /// The state the swap finished in.
+    pub status: String,
+    pub btc_amount: bitcoin::Amount,
+    pub xmr_amount: monero::Amount,
+    /// The price of one XMR.
+    pub price: bitcoin::Amount,
+    pub tx_lock_id: bitcoin::Txid,
+    pub tx_redeem_id: Option<bitcoin::Txid>,
+    pub tx_cancel_id: Option<bitcoin::Txid>,
+    pub tx_refund_id: Option<bitcoin::Txid>,
+    /// Only known to Alice.
+    pub tx_punish_id: Option<bitcoin::Txid>,
+    pub xmr_lock_tx_hash: Option<TxHash>,
+    /// The fees of all Bitcoin transactions the swap published, including the
+    /// lock transaction if its PSBT tells the value of the spent outputs.
+    pub bitcoin_fees: bitcoin::Amount,
+    /// The fee of the Monero lock transaction, only known to Alice.
+    pub monero_lock_fee: Option<monero::Amount>,
+    /// The fee of sweeping the redeemed Monero, only known to Bob.
+    pub monero_redeem_fee: Option<monero::Amount>,
+    pub started_at: OffsetDateTime,
+    pub finished_at: OffsetDateTime,
+    /// The metadata attached with [`Database::set_metadata`].
+    pub metadata: SwapMetadata,
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Swap {swap_id} is not finished yet")]
+pub struct SwapNotFinished {
+    pub swap_id: Uuid,
+}
+
+/// Which of the transactions agreed on during the swap setup were published,
+/// judging by the state the swap finished in.
+#[derive(Default)]
+struct Published {
+    redeem: bool,
+    cancel: bool,
+    refund: bool,
+    punish: bool,
+}
+
+pub async fn receipt<D>(db: &D, swap_id: Uuid) -> Result<SwapReceipt>
+where
+    D: Database + ?Sized,
+{
+    let states = db.get_states(swap_id).await?;
+
+    let (started_at, _) = states
+        .first()
+        .with_context(|| format!("No state in database for swap: {}", swap_id))?;
+    let (finished_at, final_state) = states.last().expect("not empty");
+
+    if !final_state.swap_finished() {
+        bail!(SwapNotFinished { swap_id })
+    }
+
+    let transactions = states
+        .iter()
+        .find_map(|(_, state)| swap_transactions(state))
+        .with_context(|| format!("Swap {} finished before Bitcoin was locked", swap_id))?;
+    let xmr_lock_tx_hash = states.iter().find_map(|(_, state)| xmr_lock_tx_hash(state));
+    let published = published(final_state);
+    let status = match final_state {
+        State::Alice(state) => state.to_string(),
+        State::Bob(state) => state.to_string(),
+    };
+
+    let metadata = db.get_metadata(swap_id).await?;
+    let checkpoint = db.get_checkpoint(swap_id).await?.unwrap_or_default();
+
+    let SwapTransactions {
+        btc,
+        xmr,
+        tx_lock,
+        tx_redeem,
+        tx_cancel,
+        tx_refund,
+        tx_punish,
+    } = transactions;
+
+    let spending = [
+        (published.redeem, Some(tx_redeem)),
+        (published.cancel, Some(tx_cancel)),
+        (published.refund, Some(tx_refund)),
+        (published.punish, tx_punish),
+    ]
+    .map(|(published, transaction)| transaction.filter(|_| published));
+    let bitcoin_fees = spending
+        .iter()
+        .flatten()
+        .map(|(_, fee)| *fee)
+        .chain(tx_lock.fee())
+        .fold(bitcoin::Amount::ZERO, |total, fee| total + fee);
+    let [tx_redeem_id, tx_cancel_id, tx_refund_id, tx_punish_id] =
+        spending.map(|transaction| transaction.map(|(txid, _)| txid));
+
+    Ok(SwapReceipt {
+        swap_id,
+        status,
+        btc_amount: btc,
+        xmr_amount: xmr,
+        price: price(btc, xmr),
+        tx_lock_id: tx_lock.txid(),
+        tx_redeem_id,
+        tx_cancel_id,
+        tx_refund_id,
+        tx_punish_id,
+        xmr_lock_tx_hash,
+        bitcoin_fees,
+        monero_lock_fee: checkpoint.monero_lock_fee,
+        monero_redeem_fee: checkpoint.monero_redeem_fee,
+        started_at: *started_at,
+        finished_at: *finished_at,
+        metadata,
+    })
+}
+
+fn swap_transactions(state: &State) -> Option<SwapTransactions> {
+    match state {
+        State::Alice(state) => state.state3().map(|state3| state3.swap_transactions()),
+        State::Bob(BobState::BtcLocked { state3, .. })
+        | State::Bob(BobState::XmrLockProofReceived { state: state3, .. }) => {
+            Some(state3.swap_transactions())
+        }
+        State::Bob(_) => None,
+    }
+}
+
+fn xmr_lock_tx_hash(state: &State) -> Option<TxHash> {
+    match state {
+        State::Alice(state) => state.transfer_proof().map(|proof| proof.tx_hash()),
+        State::Bob(BobState::XmrLockProofReceived {
+            lock_transfer_proof,
+            ..
+        }) => Some(lock_transfer_proof.tx_hash()),
+        State::Bob(_) => None,
+    }
+}
+
+fn published(final_state: &State) -> Published {
+    match final_state {
+        State::Alice(AliceState::BtcRedeemed) | State::Bob(BobState::XmrRedeemed { .. }) => {
+            Published {
+                redeem: true,
+                ..Published::default()
+            }
+        }
+        State::Alice(AliceState::XmrRefunded) | State::Bob(BobState::BtcRefunded(..)) => {
+            Published {
+                cancel: true,
+                refund: true,
+                ..Published::default()
+            }
+        }
+        State::Alice(AliceState::BtcPunished) | State::Bob(BobState::BtcPunished { .. }) => {
+            Published {
+                cancel: true,
+                punish: true,
+                ..Published::default()
+            }
+        }
+        _ => Published::default(),
+    }
+}
+
+fn price(btc: bitcoin::Amount, xmr: monero::Amount) -> bitcoin::Amount {
+    if xmr == monero::Amount::ZERO {
+        return bitcoin::Amount::ZERO;
+    }
+
+    let sats = u128::from(btc.to_sat()) * u128::from(monero::Amount::ONE_XMR.as_piconero())
+        / u128::from(xmr.as_piconero());
+
+    bitcoin::Amount::from_sat(u64::try_from(sats).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_is_btc_per_whole_xmr() {
+        let price = price(
+            bitcoin::Amount::from_sat(500_000),
+            monero::Amount::parse_monero("0.5").unwrap(),
+        );
+
+        assert_eq!(price, bitcoin::Amount::from_sat(1_000_000));
+    }
+}
@@ -0,0 +1,57 @@
+/// Whether [`Database::check_and_repair`] only reports what it finds or also
+/// moves it out of the way.
+///
+/// [`Database::check_and_repair`]: crate::protocol::Database::check_and_repair
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repair {
+    ReportOnly,
+    /// Move unreadable states to the `quarantined_swap_states` table and
+    /// delete orphaned peer addresses.
+    Quarantine,
+}
+
+/// The records that keep the database from being read in full.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Swaps whose latest state cannot be read. Resuming them from an
+    /// earlier state is not safe, so all of their states are quarantined and
+    /// the swaps have to be looked at manually.
+    pub corrupt_swaps: Vec<String>,
+    /// Ids of states that cannot be read, of swaps whose latest state is
+    /// fine.
+    pub corrupt_states: Vec<i64>,
+    /// Peer addresses, as `(peer_id, address)`, of peers no swap was made
+    /// with.
+    pub orphaned_addresses: Vec<(String, String)>,
+    /// Whether the records above were quarantined.
+    pub repaired: bool,
+}
+
+impl RepairReport {
+    pub fn is_consistent(&self) -> bool {
+        self.corrupt_swaps.is_empty()
+            && self.corrupt_states.is_empty()
+            && self.orphaned_addresses.is_empty()
+    }
+
+    /// Logs every record found and what was done about it.
+    pub fn log(&self) {
+        for swap_id in &self.corrupt_swaps {
+            tracing::warn!(%swap_id, "Latest state of swap cannot be read, the swap has to be recovered manually");
+        }
+        for state_id in &self.corrupt_states {
+            tracing::warn!(%state_id, "Earlier state of a swap cannot be read");
+        }
+        for (peer_id, address) in &self.orphaned_addresses {
+            tracing::info!(%peer_id, %address, "Found address of a peer no swap was made with");
+        }
+
+        if self.is_consistent() {
+            tracing::info!("Database is consistent");
+        } else if self.repaired {
+            tracing::info!("Quarantined unreadable states and deleted orphaned peer addresses");
+        } else {
+            tracing::info!("Run again with --repair to quarantine these records");
+        }
+    }
+}
@@ -1,14 +1,15 @@
-use crate::database::Swap;
+use crate::database::{Repair, RepairReport, Swap, SwapBackup};
 use crate::monero::Address;
-use crate::protocol::{Database, State};
+use crate::protocol::{Checkpoint, Database, State, SwapKeys, SwapMetadata};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use libp2p::{Multiaddr, PeerId};
 use sqlx::sqlite::Sqlite;
-use sqlx::{Pool, SqlitePool};
+use sqlx::{Pool, SqliteConnection, SqlitePool};
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 use std::str::FromStr;
-use time::OffsetDateTime;
+use time::{format_description, OffsetDateTime};
 use uuid::Uuid;
 
 pub struct SqliteDatabase {
@@ -38,23 +39,7 @@ impl Database for SqliteDatabase {
     async fn insert_peer_id(&self, swap_id: Uuid, peer_id: PeerId) -> Result<()> {
         let mut conn = self.pool.acquire().await?;
 
-        let swap_id = swap_id.to_string();
-        let peer_id = peer_id.to_string();
-
-        sqlx::query!(
-            r#"
-        insert into peers (
-            swap_id,
-            peer_id
-            ) values (?, ?);
-        "#,
-            swap_id,
-            peer_id
-        )
-        .execute(&mut conn)
-        .await?;
-
-        Ok(())
+        insert_peer_id(&mut conn, swap_id, peer_id).await
     }
 
     async fn get_peer_id(&self, swap_id: Uuid) -> Result<PeerId> {
@@ -80,23 +65,7 @@ impl Database for SqliteDatabase {
     async fn insert_monero_address(&self, swap_id: Uuid, address: Address) -> Result<()> {
         let mut conn = self.pool.acquire().await?;
 
-        let swap_id = swap_id.to_string();
-        let address = address.to_string();
-
-        sqlx::query!(
-            r#"
-        insert into monero_addresses (
-            swap_id,
-            address
-            ) values (?, ?);
-        "#,
-            swap_id,
-            address
-        )
-        .execute(&mut conn)
-        .await?;
-
-        Ok(())
+        insert_monero_address(&mut conn, swap_id, address).await
     }
 
     async fn get_monero_address(&self, swap_id: Uuid) -> Result<Address> {
@@ -123,71 +92,93 @@ impl Database for SqliteDatabase {
     async fn insert_address(&self, peer_id: PeerId, address: Multiaddr) -> Result<()> {
         let mut conn = self.pool.acquire().await?;
 
-        let peer_id = peer_id.to_string();
-        let address = address.to_string();
+        insert_address(&mut conn, peer_id, address).await
+    }
 
-        sqlx::query!(
+    async fn get_addresses(&self, peer_id: PeerId) -> Result<Vec<Multiaddr>> {
+        let mut conn = self.pool.acquire().await?;
+
+        get_addresses(&mut conn, peer_id).await
+    }
+
+    async fn insert_latest_state(&self, swap_id: Uuid, state: State) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        let entered_at = OffsetDateTime::now_utc();
+
+        insert_state(
+            &mut conn,
+            swap_id,
+            entered_at.to_string(),
+            Swap::from(state),
+        )
+        .await
+    }
+
+    async fn get_state(&self, swap_id: Uuid) -> Result<State> {
+        let mut conn = self.pool.acquire().await?;
+        let swap_id = swap_id.to_string();
+        let row = sqlx::query!(
             r#"
-        insert into peer_addresses (
-            peer_id,
-            address
-            ) values (?, ?);
+           SELECT state
+           FROM swap_states
+           WHERE swap_id = ?
+           ORDER BY id desc
+           LIMIT 1;
+
         "#,
-            peer_id,
-            address
+            swap_id
         )
-        .execute(&mut conn)
+        .fetch_all(&mut conn)
         .await?;
 
-        Ok(())
+        let row = row
+            .first()
+            .context(format!("No state in database for swap: {}", swap_id))?;
+        let swap: Swap = serde_json::from_str(&row.state)?;
+
+        Ok(swap.into())
     }
 
-    async fn get_addresses(&self, peer_id: PeerId) -> Result<Vec<Multiaddr>> {
+    async fn get_states(&self, swap_id: Uuid) -> Result<Vec<(OffsetDateTime, State)>> {
         let mut conn = self.pool.acquire().await?;
-
-        let peer_id = peer_id.to_string();
-
+        let swap_id = swap_id.to_string();
         let rows = sqlx::query!(
             r#"
-        SELECT address
-        FROM peer_addresses
-        WHERE peer_id = ?
+           SELECT entered_at, state
+           FROM swap_states
+           WHERE swap_id = ?
+           ORDER BY id
         "#,
-            peer_id,
+            swap_id
         )
         .fetch_all(&mut conn)
         .await?;
 
-        let addresses = rows
-            .iter()
+        rows.iter()
             .map(|row| {
-                let multiaddr = Multiaddr::from_str(&row.address)?;
-                Ok(multiaddr)
-            })
-            .collect::<Result<Vec<Multiaddr>>>();
+                let entered_at = parse_entered_at(&row.entered_at)?;
+                let swap: Swap = serde_json::from_str(&row.state)?;
 
-        addresses
+                Ok((entered_at, swap.into()))
+            })
+            .collect()
     }
 
-    async fn insert_latest_state(&self, swap_id: Uuid, state: State) -> Result<()> {
+    async fn insert_checkpoint(&self, swap_id: Uuid, checkpoint: Checkpoint) -> Result<()> {
         let mut conn = self.pool.acquire().await?;
-        let entered_at = OffsetDateTime::now_utc();
 
         let swap_id = swap_id.to_string();
-        let swap = serde_json::to_string(&Swap::from(state))?;
-        let entered_at = entered_at.to_string();
+        let checkpoint = serde_json::to_string(&checkpoint)?;
 
         sqlx::query!(
             r#"
-            insert into swap_states (
+            insert or replace into swap_checkpoints (
                 swap_id,
-                entered_at,
-                state
-                ) values (?, ?, ?);
+                checkpoint
+                ) values (?, ?);
         "#,
             swap_id,
-            entered_at,
-            swap
+            checkpoint
         )
         .execute(&mut conn)
         .await?;
@@ -195,29 +186,107 @@ impl Database for SqliteDatabase {
         Ok(())
     }
 
-    async fn get_state(&self, swap_id: Uuid) -> Result<State> {
+    async fn get_checkpoint(&self, swap_id: Uuid) -> Result<Option<Checkpoint>> {
         let mut conn = self.pool.acquire().await?;
+
         let swap_id = swap_id.to_string();
+
         let row = sqlx::query!(
             r#"
-           SELECT state
-           FROM swap_states
-           WHERE swap_id = ?
-           ORDER BY id desc
-           LIMIT 1;
+        SELECT checkpoint
+        FROM swap_checkpoints
+        WHERE swap_id = ?
+        "#,
+            swap_id
+        )
+        .fetch_optional(&mut conn)
+        .await?;
 
+        let checkpoint = row
+            .map(|row| serde_json::from_str(&row.checkpoint))
+            .transpose()?;
+
+        Ok(checkpoint)
+    }
+
+    async fn insert_swap_keys(&self, swap_id: Uuid, keys: SwapKeys) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        insert_swap_keys(&mut conn, swap_id, keys).await
+    }
+
+    async fn swap_keys(&self, swap_id: Uuid) -> Result<SwapKeys> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+
+        let row = sqlx::query!(
+            r#"
+        SELECT keys
+        FROM swap_keys
+        WHERE swap_id = ?
         "#,
             swap_id
         )
-        .fetch_all(&mut conn)
+        .fetch_one(&mut conn)
         .await?;
 
-        let row = row
-            .first()
-            .context(format!("No state in database for swap: {}", swap_id))?;
-        let swap: Swap = serde_json::from_str(&row.state)?;
+        let keys = serde_json::from_str(&row.keys)?;
 
-        Ok(swap.into())
+        Ok(keys)
+    }
+
+    async fn insert_transfer_proof(&self, swap_id: Uuid, proof: String) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        insert_transfer_proof(&mut conn, swap_id, proof).await
+    }
+
+    async fn transfer_proof(&self, swap_id: Uuid) -> Result<String> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+
+        let row = sqlx::query!(
+            r#"
+        SELECT proof
+        FROM transfer_proofs
+        WHERE swap_id = ?
+        "#,
+            swap_id
+        )
+        .fetch_one(&mut conn)
+        .await?;
+
+        Ok(row.proof)
+    }
+
+    async fn set_metadata(&self, swap_id: Uuid, metadata: SwapMetadata) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+
+        set_metadata(&mut conn, swap_id, metadata).await
+    }
+
+    async fn get_metadata(&self, swap_id: Uuid) -> Result<SwapMetadata> {
+        let mut conn = self.pool.acquire().await?;
+
+        let swap_id = swap_id.to_string();
+
+        let row = sqlx::query!(
+            r#"
+        SELECT metadata
+        FROM swap_metadata
+        WHERE swap_id = ?
+        "#,
+            swap_id
+        )
+        .fetch_optional(&mut conn)
+        .await?;
+
+        match row {
+            Some(row) => Ok(serde_json::from_str(&row.metadata)?),
+            None => Ok(SwapMetadata::new()),
+        }
     }
 
     async fn all(&self) -> Result<Vec<(Uuid, State)>> {
@@ -249,13 +318,386 @@ impl Database for SqliteDatabase {
 
         result
     }
+
+    async fn restore(&self, backups: Vec<SwapBackup>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for backup in backups {
+            let swap_id = backup.swap_id;
+
+            if let Some(peer_id) = backup.peer_id {
+                insert_peer_id(&mut tx, swap_id, peer_id).await?;
+
+                let known_addresses = get_addresses(&mut tx, peer_id).await?;
+                for address in backup.peer_addresses {
+                    if !known_addresses.contains(&address) {
+                        insert_address(&mut tx, peer_id, address).await?;
+                    }
+                }
+            }
+            if let Some(monero_address) = backup.monero_address {
+                insert_monero_address(&mut tx, swap_id, monero_address).await?;
+            }
+            if let Some(swap_keys) = backup.swap_keys {
+                insert_swap_keys(&mut tx, swap_id, swap_keys).await?;
+            }
+            if let Some(transfer_proof) = backup.transfer_proof {
+                insert_transfer_proof(&mut tx, swap_id, transfer_proof).await?;
+            }
+            if !backup.metadata.is_empty() {
+                set_metadata(&mut tx, swap_id, backup.metadata).await?;
+            }
+
+            if backup.history.is_empty() {
+                let entered_at = OffsetDateTime::now_utc().to_string();
+                insert_state(&mut tx, swap_id, entered_at, backup.state).await?;
+            }
+            for entry in backup.history {
+                parse_entered_at(&entry.entered_at)?;
+                insert_state(&mut tx, swap_id, entry.entered_at, entry.state).await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn check_and_repair(&self, repair: Repair) -> Result<RepairReport> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query!(
+            r#"
+           SELECT id, swap_id, entered_at, state
+           FROM swap_states
+           ORDER BY id
+        "#
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        let mut latest = HashMap::new();
+        let mut unreadable = HashMap::new();
+        for row in &rows {
+            latest.insert(row.swap_id.as_str(), row.id);
+
+            if let Err(error) = read_state_row(&row.swap_id, &row.entered_at, &row.state) {
+                unreadable.insert(row.id, format!("{:#}", error));
+            }
+        }
+
+        let corrupt_swaps = latest
+            .into_iter()
+            .filter(|(_, id)| unreadable.contains_key(id))
+            .map(|(swap_id, _)| swap_id.to_owned())
+            .collect::<BTreeSet<_>>();
+        let corrupt_states = rows
+            .iter()
+            .filter(|row| unreadable.contains_key(&row.id) && !corrupt_swaps.contains(&row.swap_id))
+            .map(|row| row.id)
+            .collect::<Vec<_>>();
+
+        let orphaned_addresses = sqlx::query!(
+            r#"
+        SELECT peer_id, address
+        FROM peer_addresses
+        WHERE peer_id NOT IN (SELECT peer_id FROM peers)
+        "#
+        )
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.peer_id, row.address))
+        .collect();
+
+        let report = RepairReport {
+            corrupt_swaps: corrupt_swaps.iter().cloned().collect(),
+            corrupt_states,
+            orphaned_addresses,
+            repaired: repair == Repair::Quarantine,
+        };
+
+        if repair == Repair::ReportOnly {
+            return Ok(report);
+        }
+
+        let quarantined = rows.iter().filter_map(|row| {
+            match (
+                unreadable.get(&row.id),
+                corrupt_swaps.contains(&row.swap_id),
+            ) {
+                (Some(error), _) => Some((row.id, error.clone())),
+                (None, true) => Some((row.id, "Latest state of swap cannot be read".to_owned())),
+                (None, false) => None,
+            }
+        });
+        for (id, reason) in quarantined {
+            sqlx::query!(
+                r#"
+            insert into quarantined_swap_states (
+                id,
+                swap_id,
+                entered_at,
+                state,
+                reason
+                ) SELECT id, swap_id, entered_at, state, ?
+                FROM swap_states
+                WHERE id = ?;
+        "#,
+                reason,
+                id
+            )
+            .execute(&mut tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+            DELETE FROM swap_states
+            WHERE id = ?;
+        "#,
+                id
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+        DELETE FROM peer_addresses
+        WHERE peer_id NOT IN (SELECT peer_id FROM peers)
+        "#
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(report)
+    }
+}
+
+/// Appends `swap` to the states of the swap, as entered at `entered_at`.
+async fn insert_state(
+    conn: &mut SqliteConnection,
+    swap_id: Uuid,
+    entered_at: String,
+    swap: Swap,
+) -> Result<()> {
+    let swap_id = swap_id.to_string();
+    let swap = serde_json::to_string(&swap)?;
+
+    sqlx::query!(
+        r#"
+            insert into swap_states (
+                swap_id,
+                entered_at,
+                state
+                ) values (?, ?, ?);
+        "#,
+        swap_id,
+        entered_at,
+        swap
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_peer_id(conn: &mut SqliteConnection, swap_id: Uuid, peer_id: PeerId) -> Result<()> {
+    let swap_id = swap_id.to_string();
+    let peer_id = peer_id.to_string();
+
+    sqlx::query!(
+        r#"
+        insert into peers (
+            swap_id,
+            peer_id
+            ) values (?, ?);
+        "#,
+        swap_id,
+        peer_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_monero_address(
+    conn: &mut SqliteConnection,
+    swap_id: Uuid,
+    address: Address,
+) -> Result<()> {
+    let swap_id = swap_id.to_string();
+    let address = address.to_string();
+
+    sqlx::query!(
+        r#"
+        insert into monero_addresses (
+            swap_id,
+            address
+            ) values (?, ?);
+        "#,
+        swap_id,
+        address
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_address(
+    conn: &mut SqliteConnection,
+    peer_id: PeerId,
+    address: Multiaddr,
+) -> Result<()> {
+    let peer_id = peer_id.to_string();
+    let address = address.to_string();
+
+    sqlx::query!(
+        r#"
+        insert into peer_addresses (
+            peer_id,
+            address
+            ) values (?, ?);
+        "#,
+        peer_id,
+        address
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn get_addresses(conn: &mut SqliteConnection, peer_id: PeerId) -> Result<Vec<Multiaddr>> {
+    let peer_id = peer_id.to_string();
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT address
+        FROM peer_addresses
+        WHERE peer_id = ?
+        "#,
+        peer_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let addresses = rows
+        .iter()
+        .map(|row| {
+            let multiaddr = Multiaddr::from_str(&row.address)?;
+            Ok(multiaddr)
+        })
+        .collect::<Result<Vec<Multiaddr>>>();
+
+    addresses
+}
+
+async fn insert_swap_keys(
+    conn: &mut SqliteConnection,
+    swap_id: Uuid,
+    keys: SwapKeys,
+) -> Result<()> {
+    let swap_id = swap_id.to_string();
+    let keys = serde_json::to_string(&keys)?;
+
+    sqlx::query!(
+        r#"
+            insert or replace into swap_keys (
+                swap_id,
+                keys
+                ) values (?, ?);
+        "#,
+        swap_id,
+        keys
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_transfer_proof(
+    conn: &mut SqliteConnection,
+    swap_id: Uuid,
+    proof: String,
+) -> Result<()> {
+    let swap_id = swap_id.to_string();
+
+    sqlx::query!(
+        r#"
+            insert or replace into transfer_proofs (
+                swap_id,
+                proof
+                ) values (?, ?);
+        "#,
+        swap_id,
+        proof
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn set_metadata(
+    conn: &mut SqliteConnection,
+    swap_id: Uuid,
+    metadata: SwapMetadata,
+) -> Result<()> {
+    let swap_id = swap_id.to_string();
+    let metadata = serde_json::to_string(&metadata)?;
+
+    sqlx::query!(
+        r#"
+            insert or replace into swap_metadata (
+                swap_id,
+                metadata
+                ) values (?, ?);
+        "#,
+        swap_id,
+        metadata
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Reads a row of the `swap_states` table the way the other queries do.
+fn read_state_row(swap_id: &str, entered_at: &str, state: &str) -> Result<State> {
+    Uuid::from_str(swap_id).with_context(|| format!("Invalid swap id {}", swap_id))?;
+    parse_entered_at(entered_at)?;
+    let swap: Swap = serde_json::from_str(state).context("Failed to deserialize state")?;
+
+    Ok(swap.into())
+}
+
+/// Parses `entered_at`, which is stored in the `Display` format of
+/// [`OffsetDateTime`].
+fn parse_entered_at(entered_at: &str) -> Result<OffsetDateTime> {
+    let format = format_description::parse(
+        "[year]-[month]-[day] [hour padding:none]:[minute]:[second].[subsecond] \
+         [offset_hour sign:mandatory]:[offset_minute]:[offset_second]",
+    )?;
+
+    OffsetDateTime::parse(entered_at, &format)
+        .with_context(|| format!("Failed to parse time {} a state was entered", entered_at))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::{DecryptionFailed, OnCollision, SwapAlreadyExists};
     use crate::protocol::alice::AliceState;
     use crate::protocol::bob::BobState;
+    use crate::protocol::AlreadyCompleted;
+    use crate::{bitcoin, monero};
+    use rand::rngs::OsRng;
     use std::fs::File;
     use tempfile::tempdir;
 
@@ -279,6 +721,155 @@ mod tests {
         assert_eq!(state_1, state_1_loaded);
     }
 
+    #[tokio::test]
+    async fn states_are_returned_oldest_first_with_time_entered() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+        let before = OffsetDateTime::now_utc();
+
+        db.insert_latest_state(swap_id, State::Alice(AliceState::BtcRedeemed))
+            .await
+            .unwrap();
+        db.insert_latest_state(swap_id, State::Alice(AliceState::SafelyAborted))
+            .await
+            .unwrap();
+        db.insert_latest_state(Uuid::new_v4(), State::Alice(AliceState::BtcPunished))
+            .await
+            .unwrap();
+
+        let states = db.get_states(swap_id).await.unwrap();
+
+        assert_eq!(
+            states.iter().map(|(_, state)| state).collect::<Vec<_>>(),
+            vec![
+                &State::Alice(AliceState::BtcRedeemed),
+                &State::Alice(AliceState::SafelyAborted)
+            ]
+        );
+        assert!(before <= states[0].0 && states[0].0 <= states[1].0);
+    }
+
+    #[test]
+    fn entered_at_round_trips_through_its_stored_format() {
+        for time in [OffsetDateTime::UNIX_EPOCH, OffsetDateTime::now_utc()] {
+            assert_eq!(parse_entered_at(&time.to_string()).unwrap(), time);
+        }
+    }
+
+    #[tokio::test]
+    async fn latest_checkpoint_replaces_previous_one() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+
+        assert_eq!(db.get_checkpoint(swap_id).await.unwrap(), None);
+
+        db.insert_checkpoint(
+            swap_id,
+            Checkpoint {
+                tx_lock_confirmations: 1,
+                ..Checkpoint::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.insert_checkpoint(
+            swap_id,
+            Checkpoint {
+                tx_lock_confirmations: 2,
+                ..Checkpoint::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            db.get_checkpoint(swap_id).await.unwrap(),
+            Some(Checkpoint {
+                tx_lock_confirmations: 2,
+                ..Checkpoint::default()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn given_finished_swap_then_incomplete_state_is_already_completed() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+
+        db.insert_latest_state(swap_id, State::Alice(AliceState::BtcRedeemed))
+            .await
+            .unwrap();
+        let error = db.get_incomplete_state(swap_id).await.unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<AlreadyCompleted>(),
+            Some(&AlreadyCompleted {
+                swap_id,
+                final_state: State::Alice(AliceState::BtcRedeemed),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn swap_keys_are_stored_per_swap() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+        let monero_key = || {
+            monero::PublicKey::from_private_key(&monero::PrivateKey::from_scalar(
+                monero::Scalar::random(&mut OsRng),
+            ))
+        };
+        let keys = SwapKeys {
+            alice_bitcoin: bitcoin::PublicKey::random(),
+            bob_bitcoin: bitcoin::PublicKey::random(),
+            alice_monero: monero_key(),
+            bob_monero: monero_key(),
+            monero_view: monero_key(),
+        };
+
+        db.insert_swap_keys(swap_id, keys).await.unwrap();
+
+        assert_eq!(db.swap_keys(swap_id).await.unwrap(), keys);
+        assert!(db.swap_keys(Uuid::new_v4()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transfer_proofs_are_stored_per_swap() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+
+        db.insert_transfer_proof(swap_id, "OutProofV2first".to_owned())
+            .await
+            .unwrap();
+        db.insert_transfer_proof(swap_id, "OutProofV2second".to_owned())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.transfer_proof(swap_id).await.unwrap(),
+            "OutProofV2second"
+        );
+        assert!(db.transfer_proof(Uuid::new_v4()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn metadata_is_replaced_per_swap_and_empty_if_not_set() {
+        let db = setup_test_db().await.unwrap();
+        let swap_id = Uuid::new_v4();
+        let metadata = SwapMetadata::from([("order_id".to_owned(), "1234".to_owned())]);
+
+        db.set_metadata(
+            swap_id,
+            SwapMetadata::from([("note".to_owned(), "first".to_owned())]),
+        )
+        .await
+        .unwrap();
+        db.set_metadata(swap_id, metadata.clone()).await.unwrap();
+
+        assert_eq!(db.get_metadata(swap_id).await.unwrap(), metadata);
+        assert!(db.get_metadata(Uuid::new_v4()).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_retrieve_all_latest_states() {
         let db = setup_test_db().await.unwrap();
@@ -370,6 +961,219 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn exported_swap_can_be_imported_with_its_history_into_fresh_db() -> Result<()> {
+        let db = setup_test_db().await?;
+
+        let swap_id = Uuid::new_v4();
+        let peer_id = PeerId::random();
+        let multiaddr = "/ip4/127.0.0.1".parse::<Multiaddr>()?;
+        let monero_address = "53gEuGZUhP9JMEBZoGaFNzhwEgiG7hwQdMCqFxiyiTeFPmkbt1mAoNybEUvYBKHcnrSgxnVWgZsTvRBaHBNXPa8tHiCU51a".parse()?;
+        let state = State::Bob(BobState::Started {
+            btc_amount: bitcoin::Amount::from_sat(100_000),
+            change_address: "bcrt1q08pfqpsyrt7acllzyjm8q5qsz5capvyahm49rw".parse()?,
+        });
+
+        db.insert_peer_id(swap_id, peer_id).await?;
+        db.insert_address(peer_id, multiaddr.clone()).await?;
+        db.insert_monero_address(swap_id, monero_address).await?;
+        db.insert_latest_state(swap_id, state.clone()).await?;
+        let state = State::Bob(BobState::SafelyAborted);
+        db.insert_latest_state(swap_id, state.clone()).await?;
+        let metadata = SwapMetadata::from([("order_id".to_owned(), "1234".to_owned())]);
+        db.set_metadata(swap_id, metadata.clone()).await?;
+
+        let mut backup = Vec::new();
+        let exported = db.export(&mut backup).await?;
+        assert_eq!(exported, vec![swap_id]);
+
+        let fresh_db = setup_test_db().await?;
+        let imported = fresh_db
+            .import(&mut backup.as_slice(), OnCollision::Error)
+            .await?;
+
+        assert_eq!(imported, vec![swap_id]);
+        assert_eq!(fresh_db.get_state(swap_id).await?, state);
+        assert_eq!(
+            fresh_db.get_states(swap_id).await?,
+            db.get_states(swap_id).await?
+        );
+        assert_eq!(fresh_db.get_peer_id(swap_id).await?, peer_id);
+        assert_eq!(fresh_db.get_addresses(peer_id).await?, vec![multiaddr]);
+        assert_eq!(fresh_db.get_monero_address(swap_id).await?, monero_address);
+        assert_eq!(fresh_db.get_metadata(swap_id).await?, metadata);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn given_import_fails_halfway_then_no_swap_is_imported() -> Result<()> {
+        let db = setup_test_db().await?;
+        let first_swap = Uuid::new_v4();
+        let second_swap = Uuid::new_v4();
+        db.insert_latest_state(first_swap, State::Bob(BobState::SafelyAborted))
+            .await?;
+        db.insert_latest_state(second_swap, State::Bob(BobState::SafelyAborted))
+            .await?;
+        let mut backup = Vec::new();
+        db.export(&mut backup).await?;
+
+        let mut backups: Vec<SwapBackup> = serde_json::from_slice(&backup)?;
+        backups
+            .iter_mut()
+            .find(|backup| backup.swap_id == second_swap)
+            .expect("second swap to be exported")
+            .history[0]
+            .entered_at = "yesterday".to_owned();
+        let backup = serde_json::to_vec(&backups)?;
+
+        let fresh_db = setup_test_db().await?;
+        let result = fresh_db
+            .import(&mut backup.as_slice(), OnCollision::Error)
+            .await;
+
+        assert!(result.is_err());
+        assert!(fresh_db.all().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encrypted_swap_export_round_trips_only_with_same_key() -> Result<()> {
+        let db = setup_test_db().await?;
+        let swap_id = Uuid::new_v4();
+        let state = State::Bob(BobState::SafelyAborted);
+        db.insert_latest_state(swap_id, state.clone()).await?;
+        db.insert_latest_state(Uuid::new_v4(), state.clone())
+            .await?;
+
+        let key = [1u8; 32];
+        let blob = db.export_swap(swap_id, &key).await?;
+
+        let fresh_db = setup_test_db().await?;
+        let error = fresh_db.import_swap(&blob, &[2u8; 32]).await.unwrap_err();
+        let imported = fresh_db.import_swap(&blob, &key).await?;
+
+        assert_eq!(
+            error.downcast_ref::<DecryptionFailed>(),
+            Some(&DecryptionFailed)
+        );
+        assert_eq!(imported, swap_id);
+        assert_eq!(fresh_db.all().await?, vec![(swap_id, state)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn given_colliding_swap_import_skips_or_errors() -> Result<()> {
+        let db = setup_test_db().await?;
+        let swap_id = Uuid::new_v4();
+        db.insert_latest_state(swap_id, State::Bob(BobState::SafelyAborted))
+            .await?;
+        let mut backup = Vec::new();
+        db.export(&mut backup).await?;
+
+        let error = db
+            .import(&mut backup.as_slice(), OnCollision::Error)
+            .await
+            .unwrap_err();
+        let skipped = db.import(&mut backup.as_slice(), OnCollision::Skip).await?;
+
+        assert_eq!(
+            error.downcast_ref::<SwapAlreadyExists>(),
+            Some(&SwapAlreadyExists { swap_id })
+        );
+        assert!(skipped.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn repair_quarantines_corrupt_records_and_keeps_good_ones() -> Result<()> {
+        let db = setup_test_db().await?;
+
+        let good_swap = Uuid::new_v4();
+        db.insert_latest_state(good_swap, State::Alice(AliceState::SafelyAborted))
+            .await?;
+        db.insert_latest_state(good_swap, State::Alice(AliceState::BtcRedeemed))
+            .await?;
+        let corrupt_swap = Uuid::new_v4();
+        db.insert_latest_state(corrupt_swap, State::Alice(AliceState::SafelyAborted))
+            .await?;
+        insert_raw_state(&db, corrupt_swap, "{\"Alice\":").await?;
+        let swap_with_corrupt_history = Uuid::new_v4();
+        let corrupt_state = insert_raw_state(&db, swap_with_corrupt_history, "garbage").await?;
+        db.insert_latest_state(
+            swap_with_corrupt_history,
+            State::Bob(BobState::SafelyAborted),
+        )
+        .await?;
+
+        let known_peer = PeerId::random();
+        let unknown_peer = PeerId::random();
+        let address = "/ip4/127.0.0.1/tcp/9939".parse::<Multiaddr>()?;
+        db.insert_peer_id(good_swap, known_peer).await?;
+        db.insert_address(known_peer, address.clone()).await?;
+        db.insert_address(unknown_peer, address.clone()).await?;
+
+        let report = db.check_and_repair(Repair::ReportOnly).await?;
+        assert!(!report.repaired);
+        assert!(db.all().await.is_err());
+
+        let report = db.check_and_repair(Repair::Quarantine).await?;
+        assert_eq!(
+            report,
+            RepairReport {
+                corrupt_swaps: vec![corrupt_swap.to_string()],
+                corrupt_states: vec![corrupt_state],
+                orphaned_addresses: vec![(unknown_peer.to_string(), address.to_string())],
+                repaired: true,
+            }
+        );
+
+        let mut swaps = db.all().await?;
+        swaps.sort_by_key(|(swap_id, _)| *swap_id);
+        let mut expected = vec![
+            (good_swap, State::Alice(AliceState::BtcRedeemed)),
+            (
+                swap_with_corrupt_history,
+                State::Bob(BobState::SafelyAborted),
+            ),
+        ];
+        expected.sort_by_key(|(swap_id, _)| *swap_id);
+        assert_eq!(swaps, expected);
+        assert_eq!(db.get_states(good_swap).await?.len(), 2);
+        assert_eq!(db.get_states(swap_with_corrupt_history).await?.len(), 1);
+        assert!(db.get_state(corrupt_swap).await.is_err());
+        assert_eq!(db.get_addresses(known_peer).await?, vec![address]);
+        assert!(db.get_addresses(unknown_peer).await?.is_empty());
+
+        let quarantined: i64 = sqlx::query_scalar("SELECT count(*) FROM quarantined_swap_states")
+            .fetch_one(&db.pool)
+            .await?;
+        assert_eq!(quarantined, 3);
+        assert!(db
+            .check_and_repair(Repair::ReportOnly)
+            .await?
+            .is_consistent());
+
+        Ok(())
+    }
+
+    /// Inserts a state the way a crash in the middle of a write could leave
+    /// it, returning its id.
+    async fn insert_raw_state(db: &SqliteDatabase, swap_id: Uuid, state: &str) -> Result<i64> {
+        let result =
+            sqlx::query("insert into swap_states (swap_id, entered_at, state) values (?, ?, ?)")
+                .bind(swap_id.to_string())
+                .bind(OffsetDateTime::now_utc().to_string())
+                .bind(state)
+                .execute(&db.pool)
+                .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
     async fn setup_test_db() -> Result<SqliteDatabase> {
         let temp_db = tempdir().unwrap().into_path().join("tempdb");
 
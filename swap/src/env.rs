@@ -1,5 +1,7 @@
 use crate::asb;
+use crate::bitcoin::wallet::DEFAULT_STOP_GAP;
 use crate::bitcoin::{CancelTimelock, PunishTimelock};
+use anyhow::{bail, Result};
 use serde::Serialize;
 use std::cmp::max;
 use std::time::Duration;
@@ -13,21 +15,57 @@ pub struct Config {
     pub bitcoin_avg_block_time: Duration,
     pub bitcoin_cancel_timelock: CancelTimelock,
     pub bitcoin_punish_timelock: PunishTimelock,
+    /// How many blocks Alice waits after the punish timelock expired before
+    /// punishing automatically.
+    pub punish_grace_blocks: u32,
     pub bitcoin_network: bitcoin::Network,
+    /// How often confirmation watchers poll the status of Bitcoin
+    /// transactions. The wallet itself only re-syncs every
+    /// [`Config::bitcoin_sync_interval`].
+    pub bitcoin_poll_interval: Duration,
+    /// After how many consecutive unused addresses the Bitcoin wallet stops
+    /// looking for transactions during a sync.
+    pub bitcoin_sync_stop_gap: usize,
+    /// The lowest fee rate in sat/vB the Bitcoin wallet pays, no matter how
+    /// low the estimate of the Electrum server is.
+    pub bitcoin_min_relay_fee_rate: u64,
+    /// How many confirmations an output of the Bitcoin wallet needs before it
+    /// is used to fund a transaction.
+    pub funding_min_confirmations: u32,
+    /// After how many failed attempts in a row to reach the Electrum server
+    /// a swap that has not locked any funds yet is aborted.
+    pub max_electrum_reconnects: u32,
     pub monero_avg_block_time: Duration,
     pub monero_finality_confirmations: u64,
     #[serde(with = "monero_network")]
     pub monero_network: monero::Network,
+    /// How often confirmation watchers poll the status of Monero
+    /// transactions.
+    pub monero_poll_interval: Duration,
+    /// How many times Alice attempts the transfer that locks her Monero.
+    pub monero_lock_transfer_attempts: u32,
+    /// After how long a single attempt to transfer the Monero lock is given
+    /// up on.
+    pub monero_lock_transfer_timeout: Duration,
+    /// Up to how long is randomly added to the delay before a failed request
+    /// to the Electrum server is retried or the connection is re-established,
+    /// so that clients of a shared server do not all reconnect at once.
+    pub bitcoin_reconnect_jitter: Duration,
+    /// The same as `bitcoin_reconnect_jitter`, for `monero-wallet-rpc`.
+    pub monero_reconnect_jitter: Duration,
+    /// Whether messages to the other party are compressed if it supports
+    /// compression too.
+    pub message_compression: bool,
+    /// Whether the cancel transaction of new swaps gets an anchor output, so
+    /// it can be fee-bumped via CPFP when it is published. Bob asks for the
+    /// anchor, Alice only agrees to it if this is set.
+    pub cancel_anchor: bool,
 }
 
 impl Config {
     pub fn bitcoin_sync_interval(&self) -> Duration {
         sync_interval(self.bitcoin_avg_block_time)
     }
-
-    pub fn monero_sync_interval(&self) -> Duration {
-        sync_interval(self.monero_avg_block_time)
-    }
 }
 
 pub trait GetConfig {
@@ -52,10 +90,23 @@ impl GetConfig for Mainnet {
             bitcoin_avg_block_time: 10.std_minutes(),
             bitcoin_cancel_timelock: CancelTimelock::new(72),
             bitcoin_punish_timelock: PunishTimelock::new(72),
+            punish_grace_blocks: 0,
             bitcoin_network: bitcoin::Network::Bitcoin,
+            bitcoin_poll_interval: 5.std_seconds(),
+            bitcoin_sync_stop_gap: DEFAULT_STOP_GAP,
+            bitcoin_min_relay_fee_rate: 1,
+            funding_min_confirmations: 1,
+            max_electrum_reconnects: 10,
             monero_avg_block_time: 2.std_minutes(),
             monero_finality_confirmations: 10,
             monero_network: monero::Network::Mainnet,
+            monero_poll_interval: sync_interval(2.std_minutes()),
+            monero_lock_transfer_attempts: 3,
+            monero_lock_transfer_timeout: 2.std_minutes(),
+            bitcoin_reconnect_jitter: 10.std_seconds(),
+            monero_reconnect_jitter: 10.std_seconds(),
+            message_compression: true,
+            cancel_anchor: false,
         }
     }
 }
@@ -69,10 +120,23 @@ impl GetConfig for Testnet {
             bitcoin_avg_block_time: 10.std_minutes(),
             bitcoin_cancel_timelock: CancelTimelock::new(12),
             bitcoin_punish_timelock: PunishTimelock::new(6),
+            punish_grace_blocks: 0,
             bitcoin_network: bitcoin::Network::Testnet,
+            bitcoin_poll_interval: 5.std_seconds(),
+            bitcoin_sync_stop_gap: DEFAULT_STOP_GAP,
+            bitcoin_min_relay_fee_rate: 1,
+            funding_min_confirmations: 1,
+            max_electrum_reconnects: 10,
             monero_avg_block_time: 2.std_minutes(),
             monero_finality_confirmations: 10,
             monero_network: monero::Network::Stagenet,
+            monero_poll_interval: sync_interval(2.std_minutes()),
+            monero_lock_transfer_attempts: 3,
+            monero_lock_transfer_timeout: 2.std_minutes(),
+            bitcoin_reconnect_jitter: 10.std_seconds(),
+            monero_reconnect_jitter: 10.std_seconds(),
+            message_compression: true,
+            cancel_anchor: false,
         }
     }
 }
@@ -86,10 +150,23 @@ impl GetConfig for Regtest {
             bitcoin_avg_block_time: 5.std_seconds(),
             bitcoin_cancel_timelock: CancelTimelock::new(100),
             bitcoin_punish_timelock: PunishTimelock::new(50),
+            punish_grace_blocks: 0,
             bitcoin_network: bitcoin::Network::Regtest,
+            bitcoin_poll_interval: sync_interval(5.std_seconds()),
+            bitcoin_sync_stop_gap: DEFAULT_STOP_GAP,
+            bitcoin_min_relay_fee_rate: 1,
+            funding_min_confirmations: 0,
+            max_electrum_reconnects: 5,
             monero_avg_block_time: 1.std_seconds(),
             monero_finality_confirmations: 10,
             monero_network: monero::Network::Mainnet, // yes this is strange
+            monero_poll_interval: sync_interval(1.std_seconds()),
+            monero_lock_transfer_attempts: 3,
+            monero_lock_transfer_timeout: 30.std_seconds(),
+            bitcoin_reconnect_jitter: 1.std_seconds(),
+            monero_reconnect_jitter: 1.std_seconds(),
+            message_compression: true,
+            cancel_anchor: false,
         }
     }
 }
@@ -98,7 +175,9 @@ fn sync_interval(avg_block_time: Duration) -> Duration {
     max(avg_block_time / 10, Duration::from_secs(1))
 }
 
-pub fn new(is_testnet: bool, asb_config: &asb::config::Config) -> Config {
+/// The configuration of the network, overlaid with the options set in the
+/// config file of the ASB (or their `ASB__` environment variable overrides).
+pub fn new(is_testnet: bool, asb_config: &asb::config::Config) -> Result<Config> {
     let env_config = if is_testnet {
         Testnet::get_config()
     } else {
@@ -115,14 +194,123 @@ pub fn new(is_testnet: bool, asb_config: &asb::config::Config) -> Config {
             env_config
         };
 
-    if let Some(monero_finality_confirmations) = asb_config.monero.finality_confirmations {
+    let env_config = if let Some(punish_grace_blocks) = asb_config.bitcoin.punish_grace_blocks {
         Config {
-            monero_finality_confirmations,
+            punish_grace_blocks,
             ..env_config
         }
     } else {
         env_config
+    };
+
+    let env_config = if let Some(bitcoin_sync_stop_gap) = asb_config.bitcoin.sync_stop_gap {
+        Config {
+            bitcoin_sync_stop_gap,
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    let env_config =
+        if let Some(monero_finality_confirmations) = asb_config.monero.finality_confirmations {
+            Config {
+                monero_finality_confirmations,
+                ..env_config
+            }
+        } else {
+            env_config
+        };
+
+    let env_config =
+        if let Some(monero_lock_transfer_attempts) = asb_config.monero.lock_transfer_attempts {
+            Config {
+                monero_lock_transfer_attempts,
+                ..env_config
+            }
+        } else {
+            env_config
+        };
+
+    let env_config = if let Some(timeout_secs) = asb_config.monero.lock_transfer_timeout_secs {
+        Config {
+            monero_lock_transfer_timeout: Duration::from_secs(timeout_secs),
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    let env_config = if let Some(jitter_secs) = asb_config.bitcoin.reconnect_jitter_secs {
+        Config {
+            bitcoin_reconnect_jitter: Duration::from_secs(jitter_secs),
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    let env_config = if let Some(jitter_secs) = asb_config.monero.reconnect_jitter_secs {
+        Config {
+            monero_reconnect_jitter: Duration::from_secs(jitter_secs),
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    let env_config = if let Some(message_compression) = asb_config.network.compression {
+        Config {
+            message_compression,
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    let env_config = if let Some(cancel_anchor) = asb_config.bitcoin.cancel_anchor {
+        Config {
+            cancel_anchor,
+            ..env_config
+        }
+    } else {
+        env_config
+    };
+
+    validate(&env_config)?;
+
+    Ok(env_config)
+}
+
+/// Checks that a swap can succeed with `config`.
+pub fn validate(config: &Config) -> Result<()> {
+    if config.bitcoin_finality_confirmations >= config.bitcoin_cancel_timelock {
+        bail!(CancelTimelockTooShort {
+            cancel_timelock: config.bitcoin_cancel_timelock.into(),
+            finality_confirmations: config.bitcoin_finality_confirmations,
+        });
+    }
+
+    if config.monero_lock_transfer_attempts == 0 {
+        bail!(NotPositive {
+            option: "number of Monero lock transfer attempts"
+        });
     }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("The cancel timelock of {cancel_timelock} blocks must be longer than the {finality_confirmations} confirmations the Bitcoin lock transaction needs to be final")]
+pub struct CancelTimelockTooShort {
+    pub cancel_timelock: u32,
+    pub finality_confirmations: u32,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("The {option} must be greater than zero")]
+pub struct NotPositive {
+    pub option: &'static str,
 }
 
 mod monero_network {
@@ -159,4 +347,38 @@ mod tests {
 
         assert_eq!(interval, Duration::from_secs(10))
     }
+
+    #[test]
+    fn bitcoin_watchers_poll_more_often_than_the_wallet_syncs_on_mainnet() {
+        let config = Mainnet::get_config();
+
+        assert_eq!(config.bitcoin_poll_interval, Duration::from_secs(5));
+        assert_eq!(config.bitcoin_sync_interval(), Duration::from_secs(60));
+        assert_eq!(config.monero_poll_interval, Duration::from_secs(12));
+    }
+
+    #[test]
+    fn cancel_timelock_within_finality_confirmations_is_invalid() {
+        let config = Config {
+            bitcoin_finality_confirmations: 12,
+            ..Testnet::get_config()
+        };
+
+        let error = validate(&config).unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<CancelTimelockTooShort>(),
+            Some(&CancelTimelockTooShort {
+                cancel_timelock: 12,
+                finality_confirmations: 12,
+            })
+        );
+    }
+
+    #[test]
+    fn default_configs_are_valid() {
+        validate(&Mainnet::get_config()).unwrap();
+        validate(&Testnet::get_config()).unwrap();
+        validate(&Regtest::get_config()).unwrap();
+    }
 }
@@ -0,0 +1,42 @@
+//! Randomized delays for retrying requests to servers that many swap
+//! clients share, e.g. a public Electrum server.
+//!
+//! Clients that lose their connection at the same time would otherwise all
+//! retry at the same time as well and overload the server again as soon as
+//! it comes back.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// Returns `delay` plus a random duration of at most `max_jitter`.
+pub fn jittered(delay: Duration, max_jitter: Duration) -> Duration {
+    delay + rand::thread_rng().gen_range(Duration::ZERO..=max_jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn jittered_delays_vary_within_bounds() {
+        let delay = Duration::from_secs(10);
+        let max_jitter = Duration::from_secs(5);
+
+        let delays = (0..100)
+            .map(|_| jittered(delay, max_jitter))
+            .collect::<Vec<_>>();
+
+        assert!(delays
+            .iter()
+            .all(|jittered| *jittered >= delay && *jittered <= delay + max_jitter));
+        assert!(delays.iter().collect::<HashSet<_>>().len() > 1);
+    }
+
+    #[test]
+    fn no_jitter_keeps_delay() {
+        let delay = Duration::from_secs(10);
+
+        assert_eq!(jittered(delay, Duration::ZERO), delay);
+    }
+}
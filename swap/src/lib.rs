@@ -17,12 +17,14 @@
 )]
 
 pub mod asb;
+pub mod balance;
 pub mod bitcoin;
 pub mod cli;
 pub mod common;
 pub mod database;
 pub mod env;
 pub mod fs;
+pub mod jitter;
 pub mod kraken;
 pub mod libp2p_ext;
 pub mod monero;
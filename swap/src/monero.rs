@@ -227,6 +227,83 @@ pub struct InsufficientFunds {
     pub actual: Amount,
 }
 
+/// The Monero lock transaction does not pay the expected amount to the address
+/// derived from the swap's keys, the Monero could not be redeemed.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Monero transaction {txid} sends {actual} instead of {expected} to lock address {address}")]
+pub struct LockDestinationMismatch {
+    pub txid: TxHash,
+    pub address: Address,
+    pub expected: Amount,
+    pub actual: Amount,
+}
+
+/// The transaction proof does not prove that the transaction pays the
+/// address.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Transaction proof for Monero transaction {txid} is invalid")]
+pub struct InvalidTxProof {
+    pub txid: TxHash,
+}
+
+/// The number of ring members, the real input and its decoys, of every input
+/// of a Monero transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct RingSize(u32);
+
+impl RingSize {
+    /// Since the v15 hard fork the network only accepts rings of exactly 16
+    /// members, the range is kept so it can be relaxed should that change.
+    pub const MIN: u32 = 16;
+    pub const MAX: u32 = 16;
+
+    pub fn new(ring_size: u32) -> Result<Self, RingSizeOutOfRange> {
+        if !(Self::MIN..=Self::MAX).contains(&ring_size) {
+            return Err(RingSizeOutOfRange { ring_size });
+        }
+
+        Ok(Self(ring_size))
+    }
+
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+impl TryFrom<u32> for RingSize {
+    type Error = RingSizeOutOfRange;
+
+    fn try_from(ring_size: u32) -> Result<Self, Self::Error> {
+        Self::new(ring_size)
+    }
+}
+
+impl From<RingSize> for u32 {
+    fn from(ring_size: RingSize) -> Self {
+        ring_size.0
+    }
+}
+
+impl fmt::Display for RingSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The password does not unlock the Monero wallet.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Wrong password for Monero wallet {name}")]
+pub struct WrongWalletPassword {
+    pub name: String,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Ring size {ring_size} is outside of the allowed range {min} to {max}", min = RingSize::MIN, max = RingSize::MAX)]
+pub struct RingSizeOutOfRange {
+    pub ring_size: u32,
+}
+
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 #[error("Overflow, cannot convert {0} to u64")]
 pub struct OverflowError(pub String);
@@ -324,6 +401,13 @@ pub mod monero_amount {
 mod tests {
     use super::*;
 
+    #[test]
+    fn ring_size_outside_of_allowed_range_is_rejected() {
+        assert_eq!(RingSize::new(11), Err(RingSizeOutOfRange { ring_size: 11 }));
+        assert_eq!(RingSize::new(17), Err(RingSizeOutOfRange { ring_size: 17 }));
+        assert_eq!(RingSize::new(16).map(|ring_size| ring_size.get()), Ok(16));
+    }
+
     #[test]
     fn display_monero_min() {
         let min_pics = 1;
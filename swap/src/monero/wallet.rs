@@ -1,14 +1,23 @@
+use crate::balance::BalanceUpdates;
 use crate::env::Config;
+use crate::jitter::jittered;
 use crate::monero::{
-    Amount, InsufficientFunds, PrivateViewKey, PublicViewKey, TransferProof, TxHash,
+    Amount, InsufficientFunds, InvalidTxProof, LockDestinationMismatch, PrivateViewKey,
+    PublicViewKey, RingSize, TransferProof, TxHash, WrongWalletPassword,
 };
 use ::monero::{Address, Network, PrivateKey, PublicKey};
-use anyhow::{Context, Result};
-use monero_rpc::wallet::{BlockHeight, MoneroWalletRpc as _, Refreshed};
+use anyhow::{bail, Context, Result};
+use futures::Stream;
+use monero_rpc::wallet::{
+    BlockHeight, Destination, MoneroWalletRpc as _, Refreshed, DEFAULT_RING_SIZE,
+};
 use monero_rpc::{jsonrpc, wallet};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tokio::time::Interval;
 use url::Url;
 
@@ -19,26 +28,66 @@ pub struct Wallet {
     name: String,
     main_address: monero::Address,
     sync_interval: Duration,
+    lock_transfer_attempts: u32,
+    lock_transfer_timeout: Duration,
+    reconnect_jitter: Duration,
+    ring_size: Option<RingSize>,
+    account_index: u32,
+    /// Unlocks the wallet and protects the wallets generated from keys, empty
+    /// if the wallet has no password.
+    password: Mutex<String>,
+    /// Whether the wallet loaded in `monero-wallet-rpc` is the main wallet
+    /// rather than one generated from the keys of a swap. Only changed while
+    /// holding the lock on `inner`.
+    main_wallet_loaded: AtomicBool,
+    balance_updates: BalanceUpdates<Amount>,
 }
 
+/// The error code of `monero-wallet-rpc` for a password that does not unlock
+/// the wallet.
+const INVALID_PASSWORD_ERROR_CODE: i64 = -22;
+
 impl Wallet {
     /// Connect to a wallet RPC and load the given wallet by name.
     pub async fn open_or_create(url: Url, name: String, env_config: Config) -> Result<Self> {
         let client = wallet::Client::new(url)?;
 
-        match client.open_wallet(name.clone()).await {
+        Self::open_or_create_protected(client, name, String::new(), env_config).await
+    }
+
+    /// Load the given wallet by name, unlocking it with `password`. A wallet
+    /// that does not exist yet is created and protected with `password`.
+    ///
+    /// Fails with [`WrongWalletPassword`] if the wallet exists but `password`
+    /// does not unlock it.
+    pub async fn open_or_create_protected(
+        client: wallet::Client,
+        name: String,
+        password: String,
+        env_config: Config,
+    ) -> Result<Self> {
+        match client.open_wallet(name.clone(), password.clone()).await {
+            Err(error) if is_wrong_password(&error) => {
+                bail!(WrongWalletPassword { name })
+            }
             Err(error) => {
                 tracing::debug!(%error, "Open wallet response error");
-                client.create_wallet(name.clone(), "English".to_owned()).await.context(
-                    "Unable to create Monero wallet, please ensure that the monero-wallet-rpc is available",
-                )?;
+                client
+                    .create_wallet(name.clone(), "English".to_owned(), password.clone())
+                    .await
+                    .context(
+                        "Unable to create Monero wallet, please ensure that the monero-wallet-rpc is available",
+                    )?;
 
                 tracing::debug!(monero_wallet_name = %name, "Created Monero wallet");
             }
             Ok(_) => tracing::debug!(monero_wallet_name = %name, "Opened Monero wallet"),
         }
 
-        Self::connect(client, name, env_config).await
+        let wallet = Self::connect(client, name, env_config).await?;
+        *wallet.password.lock().await = password;
+
+        Ok(wallet)
     }
 
     /// Connects to a wallet RPC where a wallet is already loaded.
@@ -50,22 +99,124 @@ impl Wallet {
             network: env_config.monero_network,
             name,
             main_address,
-            sync_interval: env_config.monero_sync_interval(),
+            sync_interval: env_config.monero_poll_interval,
+            lock_transfer_attempts: env_config.monero_lock_transfer_attempts,
+            lock_transfer_timeout: env_config.monero_lock_transfer_timeout,
+            reconnect_jitter: env_config.monero_reconnect_jitter,
+            ring_size: None,
+            account_index: 0,
+            password: Mutex::new(String::new()),
+            main_wallet_loaded: AtomicBool::new(true),
+            balance_updates: BalanceUpdates::new(),
         })
     }
 
-    /// Re-open the wallet using the internally stored name.
-    pub async fn re_open(&self) -> Result<()> {
-        self.inner
+    /// A wallet whose RPC is not reachable, every call to it fails.
+    #[cfg(test)]
+    pub fn unreachable(url: Url, network: Network) -> Self {
+        Self {
+            inner: Mutex::new(wallet::Client::new(url).expect("valid url")),
+            network,
+            name: "unreachable".to_owned(),
+            main_address: monero::Address::standard(
+                network,
+                PublicKey::from_private_key(
+                    &PrivateKey::from_slice(&[1u8; 32]).expect("valid key"),
+                ),
+                PublicKey::from_private_key(
+                    &PrivateKey::from_slice(&[1u8; 32]).expect("valid key"),
+                ),
+            ),
+            sync_interval: Duration::from_secs(1),
+            lock_transfer_attempts: 1,
+            lock_transfer_timeout: Duration::from_secs(1),
+            reconnect_jitter: Duration::ZERO,
+            ring_size: None,
+            account_index: 0,
+            password: Mutex::new(String::new()),
+            main_wallet_loaded: AtomicBool::new(true),
+            balance_updates: BalanceUpdates::new(),
+        }
+    }
+
+    /// Use `ring_size` for the transfers and sweeps of this wallet instead of
+    /// the default ring size of `monero-wallet-rpc`.
+    pub fn with_ring_size(mut self, ring_size: RingSize) -> Self {
+        self.ring_size = Some(ring_size);
+        self
+    }
+
+    /// Use the account at `account_index` of the loaded wallet for balances,
+    /// transfers and the main address instead of the primary account.
+    pub async fn with_account_index(mut self, account_index: u32) -> Result<Self> {
+        let address = self
+            .inner
             .lock()
             .await
-            .open_wallet(self.name.clone())
-            .await?;
-        Ok(())
+            .get_address(account_index)
+            .await
+            .with_context(|| format!("Failed to get address of account {}", account_index))?;
+
+        self.main_address = monero::Address::from_str(address.address.as_str())?;
+        self.account_index = account_index;
+        Ok(self)
     }
 
+    fn ring_size(&self) -> u32 {
+        self.ring_size.map_or(DEFAULT_RING_SIZE, u32::from)
+    }
+
+    /// Re-open the wallet using the internally stored name.
+    pub async fn re_open(&self) -> Result<()> {
+        self.open(self.name.clone()).await
+    }
+
+    /// Open the wallet `filename` with the password of this wallet.
+    ///
+    /// Fails with [`WrongWalletPassword`] if the password does not unlock it.
     pub async fn open(&self, filename: String) -> Result<()> {
-        self.inner.lock().await.open_wallet(filename).await?;
+        let password = self.password.lock().await.clone();
+        let inner = self.inner.lock().await;
+
+        match inner.open_wallet(filename.clone(), password).await {
+            Err(error) if is_wrong_password(&error) => {
+                bail!(WrongWalletPassword { name: filename })
+            }
+            result => {
+                result?;
+            }
+        }
+        self.main_wallet_loaded
+            .store(filename == self.name, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Change the password of the loaded wallet from `old_password` to
+    /// `new_password`.
+    ///
+    /// Wallets generated from keys afterwards are protected with the new
+    /// password as well.
+    pub async fn change_password(&self, old_password: String, new_password: String) -> Result<()> {
+        match self
+            .inner
+            .lock()
+            .await
+            .change_wallet_password(old_password, new_password.clone())
+            .await
+        {
+            Err(error) if is_wrong_password(&error) => {
+                bail!(WrongWalletPassword {
+                    name: self.name.clone()
+                })
+            }
+            result => {
+                result.context("Failed to change password of Monero wallet")?;
+            }
+        }
+
+        *self.password.lock().await = new_password;
+
         Ok(())
     }
 
@@ -91,6 +242,7 @@ impl Wallet {
             .close_wallet()
             .await
             .context("Failed to close wallet")?;
+        self.main_wallet_loaded.store(false, Ordering::SeqCst);
 
         let _ = wallet
             .generate_from_keys(
@@ -99,7 +251,7 @@ impl Wallet {
                 private_spend_key.to_string(),
                 PrivateKey::from(private_view_key).to_string(),
                 restore_height.height,
-                String::from(""),
+                self.password.lock().await.clone(),
                 true,
             )
             .await
@@ -130,6 +282,7 @@ impl Wallet {
         // Close the default wallet before generating the other wallet to ensure that
         // it saves its state correctly
         let _ = wallet.close_wallet().await?;
+        self.main_wallet_loaded.store(false, Ordering::SeqCst);
 
         let _ = wallet
             .generate_from_keys(
@@ -138,14 +291,17 @@ impl Wallet {
                 private_spend_key.to_string(),
                 PrivateKey::from(private_view_key).to_string(),
                 restore_height.height,
-                String::from(""),
+                self.password.lock().await.clone(),
                 true,
             )
             .await?;
 
         // Try to send all the funds from the generated wallet to the default wallet
         match wallet.refresh().await {
-            Ok(_) => match wallet.sweep_all(self.main_address.to_string()).await {
+            Ok(_) => match wallet
+                .sweep_all(self.main_address.to_string(), self.ring_size())
+                .await
+            {
                 Ok(sweep_all) => {
                     for tx in sweep_all.tx_hash_list {
                         tracing::info!(
@@ -166,14 +322,22 @@ impl Wallet {
             }
         }
 
-        let _ = wallet.open_wallet(self.name.clone()).await?;
+        let _ = wallet
+            .open_wallet(self.name.clone(), self.password.lock().await.clone())
+            .await?;
+        self.main_wallet_loaded.store(true, Ordering::SeqCst);
 
         Ok(())
     }
 
-    pub async fn transfer(&self, request: TransferRequest) -> Result<TransferProof> {
-        let inner = self.inner.lock().await;
-
+    /// Transfers the Monero of `request` and returns the proof of the
+    /// transfer.
+    ///
+    /// Attempts rejected by `monero-wallet-rpc` are retried up to the
+    /// configured number of attempts, each attempt is given up on after the
+    /// configured timeout.
+    /// Sends the transfer, returns its proof and the fee paid.
+    pub async fn transfer(&self, request: TransferRequest) -> Result<(TransferProof, Amount)> {
         let TransferRequest {
             public_spend_key,
             public_view_key,
@@ -183,9 +347,20 @@ impl Wallet {
         let destination_address =
             Address::standard(self.network, public_spend_key, public_view_key.into());
 
-        let res = inner
-            .transfer_single(0, amount.as_piconero(), &destination_address.to_string())
-            .await?;
+        let res = transfer_with_retry(
+            &self.inner,
+            self.account_index,
+            Destination {
+                amount: amount.as_piconero(),
+                address: destination_address.to_string(),
+            },
+            self.ring_size(),
+            self.lock_transfer_attempts,
+            self.lock_transfer_timeout,
+            self.sync_interval,
+            self.reconnect_jitter,
+        )
+        .await?;
 
         tracing::debug!(
             %amount,
@@ -194,11 +369,31 @@ impl Wallet {
             "Successfully initiated Monero transfer"
         );
 
-        Ok(TransferProof::new(
+        let proof = TransferProof::new(
             TxHash(res.tx_hash),
             res.tx_key
                 .context("Missing tx_key in `transfer` response")?,
-        ))
+        );
+
+        Ok((proof, Amount::from_piconero(res.fee)))
+    }
+
+    /// Estimates the fee of a transfer of `amount` by building the
+    /// transaction to our own main address without publishing it.
+    pub async fn estimate_transfer_fee(&self, amount: Amount) -> Result<Amount> {
+        let fee = self
+            .inner
+            .lock()
+            .await
+            .estimate_transfer_fee(
+                self.account_index,
+                amount.as_piconero(),
+                &self.main_address.to_string(),
+            )
+            .await
+            .context("Failed to estimate Monero transfer fee")?;
+
+        Ok(Amount::from_piconero(fee))
     }
 
     pub async fn watch_for_transfer(&self, request: WatchRequest) -> Result<(), InsufficientFunds> {
@@ -230,27 +425,132 @@ impl Wallet {
             conf_target,
             check_interval,
             self.name.clone(),
+            self.password.lock().await.clone(),
+            self.reconnect_jitter,
         )
         .await?;
 
         Ok(())
     }
 
-    pub async fn sweep_all(&self, address: Address) -> Result<Vec<TxHash>> {
+    /// Checks once, without waiting for confirmations, that the transaction
+    /// of `transfer_proof` sends at least `expected` to `address`.
+    pub async fn verify_lock_destination(
+        &self,
+        transfer_proof: &TransferProof,
+        address: Address,
+        expected: Amount,
+    ) -> Result<()> {
+        let txid = transfer_proof.tx_hash();
+
+        let received = self
+            .inner
+            .lock()
+            .await
+            .check_tx_key(
+                txid.to_string(),
+                transfer_proof.tx_key.to_string(),
+                address.to_string(),
+            )
+            .await
+            .with_context(|| format!("Failed to check Monero transaction {}", txid))?
+            .received;
+        let actual = Amount::from_piconero(received);
+
+        if actual < expected {
+            bail!(LockDestinationMismatch {
+                txid,
+                address,
+                expected,
+                actual,
+            })
+        }
+
+        Ok(())
+    }
+
+    /// Proves that our transaction `txid` pays `address`, in the format of
+    /// `get_tx_proof`.
+    ///
+    /// Anybody can check the proof with [`Wallet::check_tx_proof`] or
+    /// `check_tx_proof <txid> <address> <signature_file>` in
+    /// monero-wallet-cli, without any of our keys.
+    pub async fn tx_proof(&self, txid: &TxHash, address: Address) -> Result<String> {
+        let tx_proof = self
+            .inner
+            .lock()
+            .await
+            .get_tx_proof(txid.to_string(), address.to_string(), String::new())
+            .await
+            .with_context(|| format!("Failed to prove Monero transaction {}", txid))?;
+
+        Ok(tx_proof.signature)
+    }
+
+    /// Checks a proof created by [`Wallet::tx_proof`], returning the amount
+    /// transaction `txid` pays `address`.
+    pub async fn check_tx_proof(
+        &self,
+        txid: &TxHash,
+        address: Address,
+        tx_proof: String,
+    ) -> Result<Amount> {
+        let check = self
+            .inner
+            .lock()
+            .await
+            .check_tx_proof(
+                txid.to_string(),
+                address.to_string(),
+                String::new(),
+                tx_proof,
+            )
+            .await
+            .with_context(|| format!("Failed to check proof of Monero transaction {}", txid))?;
+
+        if !check.good {
+            bail!(InvalidTxProof { txid: txid.clone() })
+        }
+
+        Ok(Amount::from_piconero(check.received))
+    }
+
+    /// Sweeps the unlocked balance to `address`, returns the hashes of the
+    /// transactions and the fee paid for all of them.
+    pub async fn sweep_all(&self, address: Address) -> Result<(Vec<TxHash>, Amount)> {
         let sweep_all = self
             .inner
             .lock()
             .await
-            .sweep_all(address.to_string())
+            .sweep_all(address.to_string(), self.ring_size())
             .await?;
 
         let tx_hashes = sweep_all.tx_hash_list.into_iter().map(TxHash).collect();
-        Ok(tx_hashes)
+        let fee = Amount::from_piconero(sweep_all.fee_list.iter().sum());
+        Ok((tx_hashes, fee))
     }
 
-    /// Get the balance of the primary account.
+    /// Get the balance of the configured account of the loaded wallet.
     pub async fn get_balance(&self) -> Result<wallet::GetBalance> {
-        Ok(self.inner.lock().await.get_balance(0).await?)
+        let inner = self.inner.lock().await;
+        let balance = inner.get_balance(self.account_index).await?;
+
+        // The wallets generated from the keys of a swap are not the user's balance
+        if self.main_wallet_loaded.load(Ordering::SeqCst) {
+            self.balance_updates
+                .observe(Amount::from_piconero(balance.balance));
+        }
+
+        Ok(balance)
+    }
+
+    /// Yields the balance of the configured account of the main wallet
+    /// whenever a refresh observes it changing.
+    ///
+    /// See [`Wallet::refresh_continuously`] to refresh the wallet in the
+    /// background.
+    pub fn balance_stream(&self) -> impl Stream<Item = Amount> {
+        self.balance_updates.subscribe()
     }
 
     pub async fn block_height(&self) -> Result<BlockHeight> {
@@ -261,8 +561,93 @@ impl Wallet {
         self.main_address
     }
 
+    /// Creates a subaddress of the configured account labelled `label`, e.g.
+    /// `swap-<id>` to tell which swap incoming Monero belongs to.
+    pub async fn create_subaddress(&self, label: String) -> Result<Address> {
+        let created = self
+            .inner
+            .lock()
+            .await
+            .create_address(self.account_index, label.clone())
+            .await
+            .with_context(|| format!("Failed to create subaddress labelled {}", label))?;
+
+        Ok(Address::from_str(created.address.as_str())?)
+    }
+
+    /// Looks up the subaddress of the configured account labelled `label`,
+    /// `None` if there is none.
+    pub async fn address_by_label(&self, label: &str) -> Result<Option<Address>> {
+        let addresses = self
+            .inner
+            .lock()
+            .await
+            .get_address(self.account_index)
+            .await?
+            .addresses;
+
+        addresses
+            .into_iter()
+            .find(|subaddress| subaddress.label == label)
+            .map(|subaddress| Address::from_str(subaddress.address.as_str()))
+            .transpose()
+            .map_err(Into::into)
+    }
+
     pub async fn refresh(&self) -> Result<Refreshed> {
-        Ok(self.inner.lock().await.refresh().await?)
+        let refreshed = self.inner.lock().await.refresh().await?;
+        self.get_balance().await?;
+
+        Ok(refreshed)
+    }
+
+    /// Refreshes the wallet every `interval` until the returned
+    /// [`BackgroundRefresh`] is stopped or dropped, so that
+    /// [`Wallet::balance_stream`] picks up incoming and outgoing transfers.
+    ///
+    /// The background refresh does not keep the wallet alive, it ends once
+    /// the wallet is dropped.
+    pub fn refresh_continuously(self: &Arc<Self>, interval: Duration) -> BackgroundRefresh {
+        let wallet = Arc::downgrade(self);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let wallet = match wallet.upgrade() {
+                    Some(wallet) => wallet,
+                    None => return,
+                };
+                if let Err(error) = wallet.refresh().await {
+                    tracing::warn!(
+                        "Failed to refresh Monero wallet in the background: {:#}",
+                        error
+                    );
+                }
+                drop(wallet);
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        BackgroundRefresh { task }
+    }
+}
+
+/// Handle to the background refresh started by
+/// [`Wallet::refresh_continuously`], the refresh stops when it is dropped.
+#[derive(Debug)]
+pub struct BackgroundRefresh {
+    task: JoinHandle<()>,
+}
+
+impl BackgroundRefresh {
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for BackgroundRefresh {
+    fn drop(&mut self) {
+        self.task.abort();
     }
 }
 
@@ -282,6 +667,78 @@ pub struct WatchRequest {
     pub expected: Amount,
 }
 
+/// Transfers to `destination`, making up to `attempts` attempts that are
+/// `retry_interval` plus a random duration of at most `retry_jitter` apart.
+///
+/// Only attempts that `monero-wallet-rpc` answered with an error are retried.
+/// An attempt that timed out may still be published, retrying it could
+/// transfer the Monero twice.
+#[allow(clippy::too_many_arguments)]
+async fn transfer_with_retry<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::Client> + Sync>(
+    client: &Mutex<C>,
+    account_index: u32,
+    destination: Destination,
+    ring_size: u32,
+    attempts: u32,
+    timeout: Duration,
+    retry_interval: Duration,
+    retry_jitter: Duration,
+) -> Result<wallet::Transfer> {
+    let mut attempt = 1;
+
+    loop {
+        // Only the transfer itself is timed, waiting for other users of the wallet is not
+        // a sign that the transfer is stuck
+        let wallet = client.lock().await;
+        let transfer = tokio::time::timeout(
+            timeout,
+            wallet.transfer(
+                account_index,
+                vec![destination.clone()],
+                true,
+                false,
+                ring_size,
+            ),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Monero transfer did not complete within {} seconds",
+                timeout.as_secs()
+            )
+        })?;
+        drop(wallet);
+
+        match transfer {
+            Ok(transfer) => return Ok(transfer),
+            Err(error) if attempt < attempts => {
+                tracing::warn!(
+                    attempt,
+                    attempts,
+                    "Failed to transfer Monero, retrying: {:#}",
+                    error
+                );
+                tokio::time::sleep(jittered(retry_interval, retry_jitter)).await;
+                attempt += 1;
+            }
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to transfer Monero in {} attempts", attempt))
+            }
+        }
+    }
+}
+
+fn is_wrong_password(error: &jsonrpc::Error<reqwest::Error>) -> bool {
+    match error {
+        jsonrpc::Error::JsonRpc(jsonrpc::JsonRpcError { code, message, .. }) => {
+            *code == INVALID_PASSWORD_ERROR_CODE
+                || message.to_lowercase().contains("invalid password")
+        }
+        _ => false,
+    }
+}
+
 async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::Client> + Sync>(
     client: &Mutex<C>,
     transfer_proof: TransferProof,
@@ -290,15 +747,37 @@ async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::
     conf_target: u64,
     mut check_interval: Interval,
     wallet_name: String,
+    wallet_password: String,
+    reconnect_jitter: Duration,
 ) -> Result<(), InsufficientFunds> {
     let mut seen_confirmations = 0u64;
+    let mut failed = false;
+    let mut reopen_wallet = false;
 
     while seen_confirmations < conf_target {
         check_interval.tick().await; // tick() at the beginning of the loop so every `continue` tick()s as well
 
+        // Swaps sharing a `monero-wallet-rpc` that failed at the same time do not all
+        // reconnect at once
+        if failed {
+            tokio::time::sleep(jittered(Duration::ZERO, reconnect_jitter)).await;
+        }
+
         let txid = transfer_proof.tx_hash().to_string();
         let client = client.lock().await;
 
+        if reopen_wallet {
+            tracing::debug!(
+                "Opening wallet `{}` because no wallet is loaded",
+                wallet_name
+            );
+            let _ = client
+                .open_wallet(wallet_name.clone(), wallet_password.clone())
+                .await;
+        }
+        failed = false;
+        reopen_wallet = false;
+
         let tx = match client
             .check_tx_key(
                 txid.clone(),
@@ -315,15 +794,13 @@ async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::
             })) => {
                 tracing::debug!(message, ?data);
                 tracing::warn!(%txid, message, "`monero-wallet-rpc` failed to fetch transaction, may need to be restarted");
+                failed = true;
                 continue;
             }
             // TODO: Implement this using a generic proxy for each function call once https://github.com/thomaseizinger/rust-jsonrpc-client/issues/47 is fixed.
             Err(jsonrpc::Error::JsonRpc(jsonrpc::JsonRpcError { code: -13, .. })) => {
-                tracing::debug!(
-                    "Opening wallet `{}` because no wallet is loaded",
-                    wallet_name
-                );
-                let _ = client.open_wallet(wallet_name.clone()).await;
+                failed = true;
+                reopen_wallet = true;
                 continue;
             }
             Err(other) => {
@@ -331,6 +808,7 @@ async fn wait_for_confirmations<C: monero_rpc::wallet::MoneroWalletRpc<reqwest::
                     %txid,
                     "Failed to retrieve tx from blockchain: {:#}", other
                 );
+                failed = true;
                 continue; // treating every error as transient and retrying
                           // is obviously wrong but the jsonrpc client is
                           // too primitive to differentiate between all the
@@ -369,6 +847,19 @@ mod tests {
     use std::sync::atomic::{AtomicU32, Ordering};
     use tracing::metadata::LevelFilter;
 
+    #[test]
+    fn invalid_password_errors_are_recognized() {
+        let invalid_password_code =
+            json_rpc_error((INVALID_PASSWORD_ERROR_CODE, "Invalid password".to_owned()));
+        let invalid_password_message =
+            json_rpc_error((-1, "Failed to open wallet: invalid password".to_owned()));
+        let other = json_rpc_error((-1, "Failed to open wallet".to_owned()));
+
+        assert!(is_wrong_password(&invalid_password_code));
+        assert!(is_wrong_password(&invalid_password_message));
+        assert!(!is_wrong_password(&other));
+    }
+
     #[tokio::test]
     async fn given_exact_confirmations_does_not_fetch_tx_again() {
         let client = Mutex::new(DummyClient::new(vec![Ok(CheckTxKey {
@@ -385,7 +876,9 @@ mod tests {
             Amount::from_piconero(100),
             10,
             tokio::time::interval(Duration::from_millis(10)),
-            "foo-wallet".to_owned()
+            "foo-wallet".to_owned(),
+            String::new(),
+            Duration::ZERO,
         )
         .await;
 
@@ -400,6 +893,53 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn polls_for_confirmations_at_configured_interval() {
+        let poll_interval = Duration::from_millis(100);
+        let client = Mutex::new(DummyClient::new(vec![
+            Ok(CheckTxKey {
+                confirmations: 1,
+                received: 100,
+            }),
+            Ok(CheckTxKey {
+                confirmations: 5,
+                received: 100,
+            }),
+            Ok(CheckTxKey {
+                confirmations: 10,
+                received: 100,
+            }),
+        ]));
+
+        let started = std::time::Instant::now();
+        let result = wait_for_confirmations(
+            &client,
+            TransferProof::new(TxHash("<FOO>".to_owned()), PrivateKey {
+                scalar: crate::monero::Scalar::random(&mut rand::thread_rng())
+            }),
+            "53H3QthYLckeCXh9u38vohb2gZ4QgEG3FMWHNxccR6MqV1LdDVYwF1FKsRJPj4tTupWLf9JtGPBcn2MVN6c9oR7p5Uf7JdJ".parse().unwrap(),
+            Amount::from_piconero(100),
+            10,
+            tokio::time::interval(poll_interval),
+            "foo-wallet".to_owned(),
+            String::new(),
+            Duration::ZERO,
+        )
+        .await;
+
+        // the first tick completes immediately, every further poll waits for the interval
+        assert!(result.is_ok());
+        assert!(started.elapsed() >= poll_interval * 2);
+        assert_eq!(
+            client
+                .lock()
+                .await
+                .check_tx_key_invocations
+                .load(Ordering::SeqCst),
+            3
+        );
+    }
+
     #[tokio::test]
     async fn visual_log_check() {
         let writer = capture_logs(LevelFilter::INFO);
@@ -436,7 +976,9 @@ mod tests {
             Amount::from_piconero(100),
             5,
             tokio::time::interval(Duration::from_millis(10)),
-            "foo-wallet".to_owned()
+            "foo-wallet".to_owned(),
+            String::new(),
+            Duration::ZERO,
         )
         .await
         .unwrap();
@@ -483,7 +1025,9 @@ mod tests {
             Amount::from_piconero(100),
             5,
             tokio::time::interval(Duration::from_millis(10)),
-            "foo-wallet".to_owned()
+            "foo-wallet".to_owned(),
+            String::new(),
+            Duration::ZERO,
         )
         .await
         .unwrap();
@@ -506,14 +1050,189 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
         );
     }
 
+    #[tokio::test]
+    async fn retries_transfer_rejected_by_wallet_rpc() {
+        let client = Mutex::new(DummyClient::new(vec![]).with_transfer_responses(vec![
+            Err((-4, "not enough unlocked money".to_owned())),
+            Ok(transfer("<FOO>")),
+        ]));
+
+        let transfer = transfer_with_retry(
+            &client,
+            0,
+            destination(),
+            DEFAULT_RING_SIZE,
+            3,
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(transfer.tx_hash, "<FOO>");
+        assert_eq!(
+            client
+                .lock()
+                .await
+                .transfer_invocations
+                .load(Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_transfer_after_configured_attempts() {
+        let client = Mutex::new(DummyClient::new(vec![]).with_transfer_responses(vec![
+            Err((-4, "not enough unlocked money".to_owned())),
+            Err((-4, "not enough unlocked money".to_owned())),
+            Ok(transfer("<FOO>")),
+        ]));
+
+        let result = transfer_with_retry(
+            &client,
+            0,
+            destination(),
+            DEFAULT_RING_SIZE,
+            2,
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(
+            client
+                .lock()
+                .await
+                .transfer_invocations
+                .load(Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn waiting_for_the_wallet_does_not_count_towards_transfer_timeout() {
+        let client = Arc::new(Mutex::new(
+            DummyClient::new(vec![]).with_transfer_responses(vec![Ok(transfer("<FOO>"))]),
+        ));
+        let busy = client.clone().lock_owned().await;
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            drop(busy);
+        });
+
+        let transfer = transfer_with_retry(
+            &client,
+            0,
+            destination(),
+            DEFAULT_RING_SIZE,
+            1,
+            Duration::from_millis(50),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+        release.await.unwrap();
+
+        assert_eq!(transfer.tx_hash, "<FOO>");
+    }
+
+    #[tokio::test]
+    async fn forwards_ring_size_to_wallet_rpc() {
+        let client = Mutex::new(
+            DummyClient::new(vec![]).with_transfer_responses(vec![Ok(transfer("<FOO>"))]),
+        );
+        let ring_size = RingSize::new(16).unwrap();
+
+        transfer_with_retry(
+            &client,
+            0,
+            destination(),
+            ring_size.into(),
+            1,
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            client
+                .lock()
+                .await
+                .transfer_ring_size
+                .load(Ordering::SeqCst),
+            16
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_account_index_to_wallet_rpc() {
+        let client = Mutex::new(
+            DummyClient::new(vec![]).with_transfer_responses(vec![Ok(transfer("<FOO>"))]),
+        );
+
+        transfer_with_retry(
+            &client,
+            3,
+            destination(),
+            DEFAULT_RING_SIZE,
+            1,
+            Duration::from_secs(10),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            client
+                .lock()
+                .await
+                .transfer_account_index
+                .load(Ordering::SeqCst),
+            3
+        );
+    }
+
+    fn destination() -> Destination {
+        Destination {
+            amount: 100,
+            address: "53H3QthYLckeCXh9u38vohb2gZ4QgEG3FMWHNxccR6MqV1LdDVYwF1FKsRJPj4tTupWLf9JtGPBcn2MVN6c9oR7p5Uf7JdJ".to_owned(),
+        }
+    }
+
+    fn transfer(tx_hash: &str) -> wallet::Transfer {
+        wallet::Transfer {
+            amount: 100,
+            fee: 1,
+            multisig_txset: String::new(),
+            tx_blob: String::new(),
+            tx_hash: tx_hash.to_owned(),
+            tx_key: Some(PrivateKey {
+                scalar: crate::monero::Scalar::random(&mut rand::thread_rng()),
+            }),
+            tx_metadata: String::new(),
+            unsigned_txset: String::new(),
+        }
+    }
+
     type ErrorCode = i64;
     type ErrorMessage = String;
 
     struct DummyClient {
         check_tx_key_responses: Vec<Result<wallet::CheckTxKey, (ErrorCode, ErrorMessage)>>,
+        transfer_responses: Vec<Result<wallet::Transfer, (ErrorCode, ErrorMessage)>>,
 
         check_tx_key_invocations: AtomicU32,
         open_wallet_invocations: AtomicU32,
+        transfer_invocations: AtomicU32,
+        transfer_ring_size: AtomicU32,
+        transfer_account_index: AtomicU32,
     }
 
     impl DummyClient {
@@ -522,17 +1241,42 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
         ) -> Self {
             Self {
                 check_tx_key_responses,
+                transfer_responses: vec![],
                 check_tx_key_invocations: Default::default(),
                 open_wallet_invocations: Default::default(),
+                transfer_invocations: Default::default(),
+                transfer_ring_size: Default::default(),
+                transfer_account_index: Default::default(),
+            }
+        }
+
+        fn with_transfer_responses(
+            self,
+            transfer_responses: Vec<Result<wallet::Transfer, (ErrorCode, ErrorMessage)>>,
+        ) -> Self {
+            Self {
+                transfer_responses,
+                ..self
             }
         }
     }
 
+    fn json_rpc_error(
+        (code, message): (ErrorCode, ErrorMessage),
+    ) -> monero_rpc::jsonrpc::Error<reqwest::Error> {
+        monero_rpc::jsonrpc::Error::JsonRpc(monero_rpc::jsonrpc::JsonRpcError {
+            code,
+            message,
+            data: None,
+        })
+    }
+
     #[async_trait::async_trait]
     impl monero_rpc::wallet::MoneroWalletRpc<reqwest::Client> for DummyClient {
         async fn open_wallet(
             &self,
             _: String,
+            _: String,
         ) -> Result<wallet::WalletOpened, monero_rpc::jsonrpc::Error<reqwest::Error>> {
             self.open_wallet_invocations.fetch_add(1, Ordering::SeqCst);
 
@@ -549,13 +1293,25 @@ DEBUG swap::monero::wallet: Opening wallet `foo-wallet` because no wallet is loa
 
             self.check_tx_key_responses[index as usize]
                 .clone()
-                .map_err(|(code, message)| {
-                    monero_rpc::jsonrpc::Error::JsonRpc(monero_rpc::jsonrpc::JsonRpcError {
-                        code,
-                        message,
-                        data: None,
-                    })
-                })
+                .map_err(json_rpc_error)
+        }
+
+        async fn transfer(
+            &self,
+            account_index: u32,
+            _: Vec<Destination>,
+            _: bool,
+            _: bool,
+            ring_size: u32,
+        ) -> Result<wallet::Transfer, monero_rpc::jsonrpc::Error<reqwest::Error>> {
+            let index = self.transfer_invocations.fetch_add(1, Ordering::SeqCst);
+            self.transfer_ring_size.store(ring_size, Ordering::SeqCst);
+            self.transfer_account_index
+                .store(account_index, Ordering::SeqCst);
+
+            self.transfer_responses[index as usize]
+                .clone()
+                .map_err(json_rpc_error)
         }
 
         async fn send_request<P>(
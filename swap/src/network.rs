@@ -1,11 +1,15 @@
 mod impl_from_rr_event;
 
 pub mod cbor_request_response;
+pub mod compression;
 pub mod encrypted_signature;
+pub mod identify;
 pub mod json_pull_codec;
+pub mod price_curve;
 pub mod quote;
 pub mod redial;
 pub mod rendezvous;
+pub mod resend_transfer_proof;
 pub mod swap_setup;
 pub mod swarm;
 pub mod tor_transport;
@@ -1,7 +1,8 @@
+use crate::network::compression::{compress, decompress};
 use async_trait::async_trait;
 use futures::prelude::*;
 use libp2p::core::upgrade;
-use libp2p::request_response::{ProtocolName, RequestResponseCodec};
+use libp2p::request_response::{ProtocolName, ProtocolSupport, RequestResponseCodec};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
@@ -11,6 +12,68 @@ use std::marker::PhantomData;
 /// Message receive buffer.
 pub const BUF_SIZE: usize = 1024 * 1024;
 
+/// Suffix of the name of a protocol whose messages are zstd-compressed.
+const COMPRESSED_SUFFIX: &[u8] = b"/zstd";
+
+/// A protocol `P`, spoken either with plain or with compressed messages.
+///
+/// Which one is used is negotiated when a substream is opened, see
+/// [`Compressible::protocols`].
+#[derive(Clone, Debug)]
+pub struct Compressible<P> {
+    name: Vec<u8>,
+    phantom: PhantomData<P>,
+}
+
+impl<P> Compressible<P>
+where
+    P: ProtocolName,
+{
+    pub fn plain(protocol: P) -> Self {
+        Self {
+            name: protocol.protocol_name().to_vec(),
+            phantom: PhantomData::default(),
+        }
+    }
+
+    pub fn compressed(protocol: P) -> Self {
+        let mut name = protocol.protocol_name().to_vec();
+        name.extend_from_slice(COMPRESSED_SUFFIX);
+
+        Self {
+            name,
+            phantom: PhantomData::default(),
+        }
+    }
+
+    /// The protocols to pass to the request-response behaviour for `protocol`.
+    ///
+    /// If `compression` is enabled the compressed protocol is offered first,
+    /// a peer that does not support it falls back to plain messages.
+    pub fn protocols(
+        protocol: P,
+        support: ProtocolSupport,
+        compression: bool,
+    ) -> Vec<(Self, ProtocolSupport)>
+    where
+        P: Clone,
+    {
+        let plain = (Self::plain(protocol.clone()), support.clone());
+
+        if compression {
+            vec![(Self::compressed(protocol), support), plain]
+        } else {
+            vec![plain]
+        }
+    }
+}
+
+impl<P> ProtocolName for Compressible<P> {
+    fn protocol_name(&self) -> &[u8] {
+        &self.name
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct CborCodec<P, Req, Res> {
     phantom: PhantomData<(P, Req, Res)>,
@@ -35,11 +98,15 @@ where
     type Request = Req;
     type Response = Res;
 
-    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    async fn read_request<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let message = upgrade::read_length_prefixed(io, BUF_SIZE).await?;
+        let message = read_message(protocol, io).await?;
         let mut de = serde_cbor::Deserializer::from_slice(&message);
         let msg = Req::deserialize(&mut de)
             .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
@@ -49,13 +116,13 @@ where
 
     async fn read_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
     ) -> io::Result<Self::Response>
     where
         T: AsyncRead + Unpin + Send,
     {
-        let message = upgrade::read_length_prefixed(io, BUF_SIZE).await?;
+        let message = read_message(protocol, io).await?;
         let mut de = serde_cbor::Deserializer::from_slice(&message);
         let msg = Res::deserialize(&mut de)
             .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
@@ -65,7 +132,7 @@ where
 
     async fn write_request<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         req: Self::Request,
     ) -> io::Result<()>
@@ -75,14 +142,14 @@ where
         let bytes =
             serde_cbor::to_vec(&req).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-        upgrade::write_length_prefixed(io, &bytes).await?;
+        write_message(protocol, io, bytes).await?;
 
         Ok(())
     }
 
     async fn write_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         res: Self::Response,
     ) -> io::Result<()>
@@ -91,8 +158,159 @@ where
     {
         let bytes = serde_cbor::to_vec(&res)
             .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
-        upgrade::write_length_prefixed(io, &bytes).await?;
+        write_message(protocol, io, bytes).await?;
 
         Ok(())
     }
 }
+
+fn is_compressed(protocol: &impl ProtocolName) -> bool {
+    protocol.protocol_name().ends_with(COMPRESSED_SUFFIX)
+}
+
+async fn read_message<T>(protocol: &impl ProtocolName, io: &mut T) -> io::Result<Vec<u8>>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let message = upgrade::read_length_prefixed(io, BUF_SIZE).await?;
+
+    if is_compressed(protocol) {
+        return decompress(&message, BUF_SIZE);
+    }
+
+    Ok(message)
+}
+
+async fn write_message<T>(
+    protocol: &impl ProtocolName,
+    io: &mut T,
+    bytes: Vec<u8>,
+) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    let bytes = if is_compressed(protocol) {
+        compress(&bytes)?
+    } else {
+        bytes
+    };
+
+    upgrade::write_length_prefixed(io, &bytes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test::{new_swarm, SwarmExt};
+    use futures::StreamExt;
+    use libp2p::request_response::{
+        RequestResponse, RequestResponseConfig, RequestResponseEvent, RequestResponseMessage,
+    };
+    use libp2p::swarm::SwarmEvent;
+    use std::time::Duration;
+
+    #[derive(Clone, Copy, Debug)]
+    struct EchoProtocol;
+
+    impl ProtocolName for EchoProtocol {
+        fn protocol_name(&self) -> &[u8] {
+            b"/comit/xmr/btc/echo/1.0.0"
+        }
+    }
+
+    type Behaviour = RequestResponse<CborCodec<Compressible<EchoProtocol>, String, String>>;
+
+    fn behaviour(compression: bool) -> Behaviour {
+        Behaviour::new(
+            CborCodec::default(),
+            Compressible::protocols(EchoProtocol, ProtocolSupport::Full, compression),
+            RequestResponseConfig::default(),
+        )
+    }
+
+    fn large_message() -> String {
+        "xmr-btc-swap transfer proof ".repeat(30_000)
+    }
+
+    #[tokio::test]
+    async fn given_compression_on_both_ends_then_large_message_round_trips() {
+        let message = large_message();
+
+        let echoed = echo(message.clone(), true, true).await;
+
+        assert_eq!(echoed, message);
+    }
+
+    #[tokio::test]
+    async fn given_peer_without_compression_then_falls_back_to_plain_messages() {
+        let message = large_message();
+
+        let echoed = echo(message.clone(), true, false).await;
+
+        assert_eq!(echoed, message);
+    }
+
+    #[test]
+    fn compressed_protocol_is_offered_first() {
+        let names = Compressible::protocols(EchoProtocol, ProtocolSupport::Full, true)
+            .into_iter()
+            .map(|(protocol, _)| String::from_utf8(protocol.protocol_name().to_vec()).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                "/comit/xmr/btc/echo/1.0.0/zstd".to_owned(),
+                "/comit/xmr/btc/echo/1.0.0".to_owned()
+            ]
+        );
+    }
+
+    /// Sends `message` from a dialer to a listener that echoes it back.
+    async fn echo(message: String, dialer_compression: bool, listener_compression: bool) -> String {
+        let mut listener = new_swarm(|_, _| behaviour(listener_compression));
+        let mut dialer = new_swarm(|_, _| behaviour(dialer_compression));
+        listener.listen_on_random_memory_address().await;
+        dialer.block_on_connection(&mut listener).await;
+
+        let listener_peer_id = *listener.local_peer_id();
+        tokio::spawn(async move {
+            loop {
+                if let SwarmEvent::Behaviour(RequestResponseEvent::Message {
+                    message:
+                        RequestResponseMessage::Request {
+                            request, channel, ..
+                        },
+                    ..
+                }) = listener.select_next_some().await
+                {
+                    listener
+                        .behaviour_mut()
+                        .send_response(channel, request)
+                        .unwrap();
+                }
+            }
+        });
+
+        dialer
+            .behaviour_mut()
+            .send_request(&listener_peer_id, message);
+
+        tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                match dialer.select_next_some().await {
+                    SwarmEvent::Behaviour(RequestResponseEvent::Message {
+                        message: RequestResponseMessage::Response { response, .. },
+                        ..
+                    }) => return response,
+                    SwarmEvent::Behaviour(RequestResponseEvent::OutboundFailure {
+                        error, ..
+                    }) => panic!("Request failed: {:?}", error),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+}
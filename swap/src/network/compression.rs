@@ -0,0 +1,69 @@
+use std::io;
+
+/// The log2 of the largest distance a match may reach back when compressing.
+///
+/// A small window keeps the memory needed to decompress a message low, the
+/// messages are small enough to not benefit from a larger one.
+const WINDOW_LOG: u32 = 17;
+
+/// Compresses a message with zstd.
+pub fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut compressor = zstd::bulk::Compressor::new(zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    compressor.set_parameter(zstd::zstd_safe::CParameter::WindowLog(WINDOW_LOG))?;
+
+    compressor.compress(bytes)
+}
+
+/// Decompresses a message, refusing to inflate it beyond `max_size` bytes or
+/// to use a window larger than the one messages are compressed with.
+pub fn decompress(bytes: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+    let mut decompressor = zstd::bulk::Decompressor::new()?;
+    decompressor.window_log_max(WINDOW_LOG)?;
+
+    decompressor.decompress(bytes, max_size).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to decompress message: {}", error),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIZE: usize = 1024 * 1024;
+
+    #[test]
+    fn repetitive_message_shrinks_when_compressed() {
+        let message = "xmr-btc-swap transfer proof ".repeat(30_000).into_bytes();
+
+        let compressed = compress(&message).unwrap();
+
+        assert!(compressed.len() < message.len() / 10);
+        assert_eq!(decompress(&compressed, MAX_SIZE).unwrap(), message);
+    }
+
+    #[test]
+    fn refuses_to_decompress_beyond_maximum_size() {
+        let compressed = compress(&vec![0; MAX_SIZE + 1]).unwrap();
+
+        let error = decompress(&compressed, MAX_SIZE).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn refuses_to_decompress_with_a_larger_window() {
+        let message = (0..MAX_SIZE).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut compressor = zstd::bulk::Compressor::new(zstd::DEFAULT_COMPRESSION_LEVEL).unwrap();
+        compressor
+            .set_parameter(zstd::zstd_safe::CParameter::WindowLog(WINDOW_LOG + 3))
+            .unwrap();
+        let compressed = compressor.compress(&message).unwrap();
+
+        let error = decompress(&compressed, MAX_SIZE).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
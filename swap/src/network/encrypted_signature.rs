@@ -1,4 +1,4 @@
-use crate::network::cbor_request_response::CborCodec;
+use crate::network::cbor_request_response::{CborCodec, Compressible};
 use crate::{asb, cli};
 use libp2p::core::ProtocolName;
 use libp2p::request_response::{
@@ -13,7 +13,8 @@ const PROTOCOL: &str = "/comit/xmr/btc/encrypted_signature/1.0.0";
 type OutEvent = RequestResponseEvent<Request, ()>;
 type Message = RequestResponseMessage<Request, ()>;
 
-pub type Behaviour = RequestResponse<CborCodec<EncryptedSignatureProtocol, Request, ()>>;
+pub type Behaviour =
+    RequestResponse<CborCodec<Compressible<EncryptedSignatureProtocol>, Request, ()>>;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct EncryptedSignatureProtocol;
@@ -30,18 +31,26 @@ pub struct Request {
     pub tx_redeem_encsig: crate::bitcoin::EncryptedSignature,
 }
 
-pub fn alice() -> Behaviour {
+pub fn alice(compression: bool) -> Behaviour {
     Behaviour::new(
         CborCodec::default(),
-        vec![(EncryptedSignatureProtocol, ProtocolSupport::Inbound)],
+        Compressible::protocols(
+            EncryptedSignatureProtocol,
+            ProtocolSupport::Inbound,
+            compression,
+        ),
         RequestResponseConfig::default(),
     )
 }
 
-pub fn bob() -> Behaviour {
+pub fn bob(compression: bool) -> Behaviour {
     Behaviour::new(
         CborCodec::default(),
-        vec![(EncryptedSignatureProtocol, ProtocolSupport::Outbound)],
+        Compressible::protocols(
+            EncryptedSignatureProtocol,
+            ProtocolSupport::Outbound,
+            compression,
+        ),
         RequestResponseConfig::default(),
     )
 }
@@ -0,0 +1,135 @@
+use crate::network::rendezvous::XmrBtcNamespace;
+use crate::{asb, cli};
+use libp2p::identify::{Identify, IdentifyConfig, IdentifyEvent};
+use libp2p::identity;
+
+const PROTOCOL_VERSION: &str = "/comit/xmr/btc/1.0.0";
+
+pub type Behaviour = Identify;
+
+/// Constructs a new instance of the `identify` behaviour to be used by the
+/// ASB.
+pub fn asb(identity: &identity::Keypair, namespace: XmrBtcNamespace) -> Behaviour {
+    new("asb", identity, namespace)
+}
+
+/// Constructs a new instance of the `identify` behaviour to be used by the
+/// CLI.
+pub fn cli(identity: &identity::Keypair, namespace: XmrBtcNamespace) -> Behaviour {
+    new("cli", identity, namespace)
+}
+
+/// The agent string we report to other peers, e.g. `asb/0.12.3
+/// (xmr-btc-swap-mainnet)`.
+pub fn agent_version(role: &str, namespace: XmrBtcNamespace) -> String {
+    format!("{}/{} ({})", role, env!("CARGO_PKG_VERSION"), namespace)
+}
+
+fn new(role: &str, identity: &identity::Keypair, namespace: XmrBtcNamespace) -> Behaviour {
+    let config = IdentifyConfig::new(PROTOCOL_VERSION.to_string(), identity.public())
+        .with_agent_version(agent_version(role, namespace));
+
+    Identify::new(config)
+}
+
+impl From<IdentifyEvent> for asb::OutEvent {
+    fn from(event: IdentifyEvent) -> Self {
+        match event {
+            IdentifyEvent::Received { peer_id, info } => asb::OutEvent::PeerIdentified {
+                peer: peer_id,
+                agent_version: info.agent_version,
+            },
+            _ => asb::OutEvent::Other,
+        }
+    }
+}
+
+impl From<IdentifyEvent> for cli::OutEvent {
+    fn from(event: IdentifyEvent) -> Self {
+        match event {
+            IdentifyEvent::Received { peer_id, info } => cli::OutEvent::PeerIdentified {
+                peer: peer_id,
+                agent_version: info.agent_version,
+            },
+            _ => cli::OutEvent::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test::{new_swarm, SwarmExt};
+    use futures::StreamExt;
+    use libp2p::swarm::{Swarm, SwarmEvent};
+    use libp2p::PeerId;
+
+    #[tokio::test]
+    async fn identify_exchange_surfaces_agent_version_of_both_roles() {
+        let mut asb = new_swarm(|_, identity| super::asb(&identity, XmrBtcNamespace::Testnet));
+        let mut cli = new_swarm(|_, identity| super::cli(&identity, XmrBtcNamespace::Testnet));
+        asb.listen_on_random_memory_address().await;
+        cli.block_on_connection(&mut asb).await;
+
+        let asb_peer_id = *asb.local_peer_id();
+        let cli_peer_id = *cli.local_peer_id();
+
+        let (asb_received, cli_received) = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            futures::future::join(next_received(&mut asb), next_received(&mut cli)),
+        )
+        .await
+        .unwrap();
+
+        match asb::OutEvent::from(asb_received) {
+            asb::OutEvent::PeerIdentified {
+                peer,
+                agent_version,
+            } => {
+                assert_eq!(peer, cli_peer_id);
+                assert_eq!(
+                    agent_version,
+                    format!("cli/{} (xmr-btc-swap-testnet)", env!("CARGO_PKG_VERSION"))
+                );
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+        match cli::OutEvent::from(cli_received) {
+            cli::OutEvent::PeerIdentified {
+                peer,
+                agent_version,
+            } => {
+                assert_eq!(peer, asb_peer_id);
+                assert_eq!(
+                    agent_version,
+                    format!("asb/{} (xmr-btc-swap-testnet)", env!("CARGO_PKG_VERSION"))
+                );
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn other_identify_events_are_not_surfaced() {
+        let peer_id = PeerId::random();
+
+        assert!(matches!(
+            asb::OutEvent::from(IdentifyEvent::Sent { peer_id }),
+            asb::OutEvent::Other
+        ));
+        assert!(matches!(
+            cli::OutEvent::from(IdentifyEvent::Pushed { peer_id }),
+            cli::OutEvent::Other
+        ));
+    }
+
+    async fn next_received(swarm: &mut Swarm<Behaviour>) -> IdentifyEvent {
+        loop {
+            if let SwarmEvent::Behaviour(event @ IdentifyEvent::Received { .. }) =
+                swarm.select_next_some().await
+            {
+                return event;
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::asb::Rate;
+use crate::network::cbor_request_response::{CborCodec, Compressible};
+use crate::network::quote::BidQuote;
+use crate::{asb, bitcoin, cli, monero};
+use anyhow::Result;
+use libp2p::core::ProtocolName;
+use libp2p::request_response::{
+    ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+    RequestResponseMessage,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+const PROTOCOL: &str = "/comit/xmr/btc/price-curve/1.0.0";
+type OutEvent = RequestResponseEvent<Request, PriceCurve>;
+type Message = RequestResponseMessage<Request, PriceCurve>;
+
+pub type Behaviour =
+    RequestResponse<CborCodec<Compressible<PriceCurveProtocol>, Request, PriceCurve>>;
+
+/// The maximum number of points a price curve is sampled at, regardless of
+/// how many were requested.
+pub const MAX_SAMPLES: u32 = 50;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PriceCurveProtocol;
+
+impl ProtocolName for PriceCurveProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        PROTOCOL.as_bytes()
+    }
+}
+
+/// Asks the maker for the XMR it offers for BTC amounts between `min` and
+/// `max`, sampled at `samples` evenly spaced amounts.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Request {
+    #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
+    pub min: bitcoin::Amount,
+    #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
+    pub max: bitcoin::Amount,
+    pub samples: u32,
+}
+
+/// The XMR the maker offers for a BTC amount.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PricePoint {
+    #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
+    pub btc: bitcoin::Amount,
+    pub xmr: monero::Amount,
+}
+
+/// The points of the price curve, ordered by ascending BTC amount.
+///
+/// Only amounts the maker is currently willing to buy are part of the curve,
+/// an empty curve means none of the requested amounts are.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PriceCurve {
+    pub points: Vec<PricePoint>,
+}
+
+impl PriceCurve {
+    /// Samples the curve for `request` at the given `rate`, limited to the
+    /// quantities of the maker's current `quote`.
+    pub fn sample(rate: &Rate, quote: &BidQuote, request: &Request) -> Result<Self> {
+        let min = request.min.max(quote.min_quantity);
+        let max = request.max.min(quote.max_quantity);
+
+        if min > max || max == bitcoin::Amount::ZERO {
+            return Ok(Self::default());
+        }
+
+        let samples = request.samples.clamp(1, MAX_SAMPLES);
+        let mut amounts = Vec::with_capacity(samples as usize);
+
+        if samples == 1 {
+            amounts.push(min);
+        } else {
+            let range = u128::from((max - min).to_sat());
+
+            for i in 0..samples {
+                let offset = range * u128::from(i) / u128::from(samples - 1);
+                let amount = min + bitcoin::Amount::from_sat(offset as u64);
+
+                if amounts.last() != Some(&amount) {
+                    amounts.push(amount);
+                }
+            }
+        }
+
+        let points = amounts
+            .into_iter()
+            .map(|btc| {
+                Ok(PricePoint {
+                    btc,
+                    xmr: rate.sell_quote(btc)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { points })
+    }
+}
+
+/// Constructs a new instance of the `price_curve` behaviour to be used by the
+/// ASB.
+pub fn asb(compression: bool) -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        Compressible::protocols(PriceCurveProtocol, ProtocolSupport::Inbound, compression),
+        RequestResponseConfig::default(),
+    )
+}
+
+/// Constructs a new instance of the `price_curve` behaviour to be used by the
+/// CLI.
+pub fn cli(compression: bool) -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        Compressible::protocols(PriceCurveProtocol, ProtocolSupport::Outbound, compression),
+        RequestResponseConfig::default(),
+    )
+}
+
+impl From<(PeerId, Message)> for asb::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request {
+                request, channel, ..
+            } => Self::PriceCurveRequested {
+                request,
+                channel,
+                peer,
+            },
+            Message::Response { .. } => Self::unexpected_response(peer),
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, asb::OutEvent, PROTOCOL);
+
+impl From<(PeerId, Message)> for cli::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request { .. } => Self::unexpected_request(peer),
+            Message::Response {
+                response,
+                request_id,
+            } => Self::PriceCurveReceived {
+                id: request_id,
+                response,
+            },
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, cli::OutEvent, PROTOCOL);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    const TWO_PERCENT: Decimal = Decimal::from_parts(2, 0, 0, false, 2);
+
+    fn quote(min: f64, max: f64) -> BidQuote {
+        BidQuote {
+            price: bitcoin::Amount::from_btc(0.0025).unwrap(),
+            min_quantity: bitcoin::Amount::from_btc(min).unwrap(),
+            max_quantity: bitcoin::Amount::from_btc(max).unwrap(),
+        }
+    }
+
+    fn request(min: f64, max: f64, samples: u32) -> Request {
+        Request {
+            min: bitcoin::Amount::from_btc(min).unwrap(),
+            max: bitcoin::Amount::from_btc(max).unwrap(),
+            samples,
+        }
+    }
+
+    #[test]
+    fn curve_is_limited_to_min_and_max_quantity_and_applies_spread() {
+        let ask = bitcoin::Amount::from_btc(0.0025).unwrap();
+        let rate = Rate::new(ask, TWO_PERCENT);
+
+        let curve = PriceCurve::sample(&rate, &quote(0.001, 0.01), &request(0.0, 1.0, 10)).unwrap();
+
+        assert_eq!(curve.points.len(), 10);
+        assert_eq!(
+            curve.points[0].btc,
+            bitcoin::Amount::from_btc(0.001).unwrap()
+        );
+        assert_eq!(
+            curve.points[9].btc,
+            bitcoin::Amount::from_btc(0.01).unwrap()
+        );
+        for point in &curve.points {
+            let without_spread = Rate::new(ask, Decimal::ZERO).sell_quote(point.btc).unwrap();
+
+            assert_eq!(point.xmr, rate.sell_quote(point.btc).unwrap());
+            assert!(point.xmr < without_spread);
+        }
+        assert!(curve.points.windows(2).all(|w| w[0].btc < w[1].btc));
+    }
+
+    #[test]
+    fn number_of_points_is_bounded() {
+        let rate = Rate::new(bitcoin::Amount::from_btc(0.0025).unwrap(), TWO_PERCENT);
+
+        let curve =
+            PriceCurve::sample(&rate, &quote(0.001, 0.01), &request(0.0, 1.0, 1_000_000)).unwrap();
+
+        assert_eq!(curve.points.len(), MAX_SAMPLES as usize);
+    }
+
+    #[test]
+    fn given_range_outside_of_limits_curve_is_empty() {
+        let rate = Rate::new(bitcoin::Amount::from_btc(0.0025).unwrap(), TWO_PERCENT);
+
+        let curve = PriceCurve::sample(&rate, &quote(0.001, 0.01), &request(0.1, 1.0, 10)).unwrap();
+
+        assert!(curve.points.is_empty());
+    }
+}
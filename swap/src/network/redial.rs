@@ -45,6 +45,13 @@ impl Behaviour {
         }
     }
 
+    /// Starts re-dialling the peer all over again, e.g. after all attempts were
+    /// exhausted but we still need a connection.
+    pub fn restart(&mut self) {
+        self.backoff.reset();
+        self.sleep = Some(Box::pin(tokio::time::sleep(self.backoff.initial_interval)));
+    }
+
     pub fn until_next_redial(&self) -> Option<Duration> {
         let until_next_redial = self
             .sleep
@@ -0,0 +1,96 @@
+use crate::network::cbor_request_response::{CborCodec, Compressible};
+use crate::{asb, cli, monero};
+use libp2p::core::ProtocolName;
+use libp2p::request_response::{
+    ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+    RequestResponseMessage,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const PROTOCOL: &str = "/comit/xmr/btc/resend_transfer_proof/1.0.0";
+type OutEvent = RequestResponseEvent<Request, Response>;
+type Message = RequestResponseMessage<Request, Response>;
+
+pub type Behaviour =
+    RequestResponse<CborCodec<Compressible<ResendTransferProofProtocol>, Request, Response>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResendTransferProofProtocol;
+
+impl ProtocolName for ResendTransferProofProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        PROTOCOL.as_bytes()
+    }
+}
+
+/// Asks Alice for the transfer proof of the Monero lock transaction of the
+/// given swap once more, in case Bob lost the one she sent.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Request {
+    pub swap_id: Uuid,
+}
+
+/// The transfer proof of the swap, `None` if Alice has not locked the Monero
+/// yet or does not know a swap with this id for the requesting peer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub tx_lock_proof: Option<monero::TransferProof>,
+}
+
+pub fn alice(compression: bool) -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        Compressible::protocols(
+            ResendTransferProofProtocol,
+            ProtocolSupport::Inbound,
+            compression,
+        ),
+        RequestResponseConfig::default(),
+    )
+}
+
+pub fn bob(compression: bool) -> Behaviour {
+    Behaviour::new(
+        CborCodec::default(),
+        Compressible::protocols(
+            ResendTransferProofProtocol,
+            ProtocolSupport::Outbound,
+            compression,
+        ),
+        RequestResponseConfig::default(),
+    )
+}
+
+impl From<(PeerId, Message)> for asb::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request {
+                request, channel, ..
+            } => Self::TransferProofResendRequested {
+                swap_id: request.swap_id,
+                channel,
+                peer,
+            },
+            Message::Response { .. } => Self::unexpected_response(peer),
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, asb::OutEvent, PROTOCOL);
+
+impl From<(PeerId, Message)> for cli::OutEvent {
+    fn from((peer, message): (PeerId, Message)) -> Self {
+        match message {
+            Message::Request { .. } => Self::unexpected_request(peer),
+            Message::Response {
+                response,
+                request_id,
+            } => Self::TransferProofResent {
+                id: request_id,
+                response,
+            },
+        }
+    }
+}
+crate::impl_from_rr_event!(OutEvent, cli::OutEvent, PROTOCOL);
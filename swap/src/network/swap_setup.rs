@@ -1,7 +1,7 @@
 use crate::monero;
+use crate::network::compression;
 use anyhow::{Context, Result};
 use libp2p::core::upgrade;
-use libp2p::swarm::NegotiatedSubstream;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
@@ -12,29 +12,93 @@ pub const BUF_SIZE: usize = 1024 * 1024;
 
 pub mod protocol {
     use futures::future;
-    use libp2p::core::upgrade::{from_fn, FromFnUpgrade};
-    use libp2p::core::Endpoint;
+    use libp2p::core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
     use libp2p::swarm::NegotiatedSubstream;
+    use std::ops::{Deref, DerefMut};
     use void::Void;
 
-    pub fn new() -> SwapSetup {
-        from_fn(
-            b"/comit/xmr/btc/swap_setup/1.0.0",
-            Box::new(|socket, _| future::ready(Ok(socket))),
-        )
+    const PROTOCOL: &[u8] = b"/comit/xmr/btc/swap_setup/1.0.0";
+    /// The same protocol with zstd-compressed messages.
+    const COMPRESSED_PROTOCOL: &[u8] = b"/comit/xmr/btc/swap_setup/1.0.0/zstd";
+
+    /// If `compression` is enabled the compressed protocol is offered first,
+    /// a peer that does not support it falls back to plain messages.
+    pub fn new(compression: bool) -> SwapSetup {
+        SwapSetup { compression }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    pub struct SwapSetup {
+        compression: bool,
     }
 
-    pub type SwapSetup = FromFnUpgrade<
-        &'static [u8],
-        Box<
-            dyn Fn(
-                    NegotiatedSubstream,
-                    Endpoint,
-                ) -> future::Ready<Result<NegotiatedSubstream, Void>>
-                + Send
-                + 'static,
-        >,
-    >;
+    impl UpgradeInfo for SwapSetup {
+        type Info = &'static [u8];
+        type InfoIter = Vec<&'static [u8]>;
+
+        fn protocol_info(&self) -> Self::InfoIter {
+            if self.compression {
+                vec![COMPRESSED_PROTOCOL, PROTOCOL]
+            } else {
+                vec![PROTOCOL]
+            }
+        }
+    }
+
+    impl InboundUpgrade<NegotiatedSubstream> for SwapSetup {
+        type Output = Substream;
+        type Error = Void;
+        type Future = future::Ready<Result<Substream, Void>>;
+
+        fn upgrade_inbound(self, socket: NegotiatedSubstream, info: Self::Info) -> Self::Future {
+            future::ready(Ok(Substream::new(socket, info)))
+        }
+    }
+
+    impl OutboundUpgrade<NegotiatedSubstream> for SwapSetup {
+        type Output = Substream;
+        type Error = Void;
+        type Future = future::Ready<Result<Substream, Void>>;
+
+        fn upgrade_outbound(self, socket: NegotiatedSubstream, info: Self::Info) -> Self::Future {
+            future::ready(Ok(Substream::new(socket, info)))
+        }
+    }
+
+    /// A substream of the swap setup protocol, remembering whether messages
+    /// are compressed on it.
+    #[derive(Debug)]
+    pub struct Substream {
+        inner: NegotiatedSubstream,
+        compressed: bool,
+    }
+
+    impl Substream {
+        fn new(inner: NegotiatedSubstream, protocol: &[u8]) -> Self {
+            Self {
+                inner,
+                compressed: protocol == COMPRESSED_PROTOCOL,
+            }
+        }
+
+        pub fn is_compressed(&self) -> bool {
+            self.compressed
+        }
+    }
+
+    impl Deref for Substream {
+        type Target = NegotiatedSubstream;
+
+        fn deref(&self) -> &Self::Target {
+            &self.inner
+        }
+    }
+
+    impl DerefMut for Substream {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.inner
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -61,6 +125,7 @@ pub enum SpotPriceResponse {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum SpotPriceError {
     NoSwapsAccepted,
+    TemporarilyUnavailable,
     AmountBelowMinimum {
         #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
         min: bitcoin::Amount,
@@ -86,13 +151,18 @@ pub enum SpotPriceError {
     Other,
 }
 
-pub async fn read_cbor_message<T>(substream: &mut NegotiatedSubstream) -> Result<T>
+pub async fn read_cbor_message<T>(substream: &mut protocol::Substream) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    let bytes = upgrade::read_length_prefixed(substream, BUF_SIZE)
+    let bytes = upgrade::read_length_prefixed(&mut **substream, BUF_SIZE)
         .await
         .context("Failed to read length-prefixed message from stream")?;
+    let bytes = if substream.is_compressed() {
+        compression::decompress(&bytes, BUF_SIZE).context("Failed to decompress message")?
+    } else {
+        bytes
+    };
     let mut de = serde_cbor::Deserializer::from_slice(&bytes);
     let message =
         T::deserialize(&mut de).context("Failed to deserialize bytes into message using CBOR")?;
@@ -100,15 +170,41 @@ where
     Ok(message)
 }
 
-pub async fn write_cbor_message<T>(substream: &mut NegotiatedSubstream, message: T) -> Result<()>
+pub async fn write_cbor_message<T>(substream: &mut protocol::Substream, message: T) -> Result<()>
 where
     T: Serialize,
 {
     let bytes =
         serde_cbor::to_vec(&message).context("Failed to serialize message as bytes using CBOR")?;
-    upgrade::write_length_prefixed(substream, &bytes)
+    let bytes = if substream.is_compressed() {
+        compression::compress(&bytes).context("Failed to compress message")?
+    } else {
+        bytes
+    };
+    upgrade::write_length_prefixed(&mut **substream, &bytes)
         .await
         .context("Failed to write bytes as length-prefixed message")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::upgrade::UpgradeInfo;
+
+    #[test]
+    fn compressed_protocol_is_offered_first() {
+        assert_eq!(
+            protocol::new(true).protocol_info(),
+            vec![
+                b"/comit/xmr/btc/swap_setup/1.0.0/zstd".as_ref(),
+                b"/comit/xmr/btc/swap_setup/1.0.0".as_ref()
+            ]
+        );
+        assert_eq!(
+            protocol::new(false).protocol_info(),
+            vec![b"/comit/xmr/btc/swap_setup/1.0.0".as_ref()]
+        );
+    }
+}
@@ -1,5 +1,4 @@
-use crate::asb::LatestRate;
-use crate::monero::Amount;
+use crate::asb::{available_for_quote, LatestRate};
 use crate::network::swap_setup;
 use crate::network::swap_setup::{
     protocol, BlockchainNetwork, SpotPriceError, SpotPriceRequest, SpotPriceResponse,
@@ -13,12 +12,14 @@ use futures::{AsyncWriteExt, FutureExt};
 use libp2p::core::connection::ConnectionId;
 use libp2p::core::upgrade;
 use libp2p::swarm::{
-    KeepAlive, NegotiatedSubstream, NetworkBehaviour, NetworkBehaviourAction, PollParameters,
-    ProtocolsHandler, ProtocolsHandlerEvent, ProtocolsHandlerUpgrErr, SubstreamProtocol,
+    KeepAlive, NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler,
+    ProtocolsHandlerEvent, ProtocolsHandlerUpgrErr, SubstreamProtocol,
 };
 use libp2p::{Multiaddr, PeerId};
 use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::task::Poll;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
@@ -28,7 +29,8 @@ use void::Void;
 #[allow(clippy::large_enum_variant)]
 pub enum OutEvent {
     Initiated {
-        send_wallet_snapshot: bmrng::RequestReceiver<bitcoin::Amount, WalletSnapshot>,
+        send_wallet_snapshot:
+            bmrng::RequestReceiver<(bitcoin::Amount, monero::Amount), WalletSnapshot>,
     },
     Completed {
         peer_id: PeerId,
@@ -44,7 +46,12 @@ pub enum OutEvent {
 #[derive(Debug)]
 pub struct WalletSnapshot {
     balance: monero_rpc::wallet::GetBalance,
-    lock_fee: monero::Amount,
+    /// The part of the unlocked balance that is not promised to other swaps
+    /// or kept in reserve, see [`available_for_quote`].
+    available: monero::Amount,
+    /// The estimated fee of locking the quoted amount, `None` if the available
+    /// balance does not cover the amount.
+    lock_fee: Option<monero::Amount>,
 
     // TODO: Consider using the same address for punish and redeem (they are mutually exclusive, so
     // effectively the address will only be used once)
@@ -61,8 +68,25 @@ impl WalletSnapshot {
         monero_wallet: &monero::Wallet,
         external_redeem_address: &Option<bitcoin::Address>,
         transfer_amount: bitcoin::Amount,
+        lock_amount: monero::Amount,
+        reserved_in_flight: monero::Amount,
+        reserve: monero::Amount,
     ) -> Result<Self> {
         let balance = monero_wallet.get_balance().await?;
+        let available = available_for_quote(
+            monero::Amount::from_piconero(balance.unlocked_balance),
+            reserved_in_flight,
+            reserve,
+        );
+        let lock_fee = if available >= lock_amount {
+            let fee = monero_wallet
+                .estimate_transfer_fee(lock_amount)
+                .await
+                .context("Failed to estimate the Monero lock fee")?;
+            Some(fee)
+        } else {
+            None
+        };
         let redeem_address = external_redeem_address
             .clone()
             .unwrap_or(bitcoin_wallet.new_address().await?);
@@ -70,16 +94,18 @@ impl WalletSnapshot {
             .clone()
             .unwrap_or(bitcoin_wallet.new_address().await?);
 
-        let redeem_fee = bitcoin_wallet
+        let mut operation = bitcoin_wallet.operation();
+        let redeem_fee = operation
             .estimate_fee(bitcoin::TxRedeem::weight(), transfer_amount)
             .await?;
-        let punish_fee = bitcoin_wallet
+        let punish_fee = operation
             .estimate_fee(bitcoin::TxPunish::weight(), transfer_amount)
             .await?;
 
         Ok(Self {
             balance,
-            lock_fee: monero::MONERO_FEE,
+            available,
+            lock_fee,
             redeem_address,
             punish_address,
             redeem_fee,
@@ -118,16 +144,21 @@ pub struct Behaviour<LR> {
     events: VecDeque<OutEvent>,
     min_buy: bitcoin::Amount,
     max_buy: bitcoin::Amount,
+    max_lock_fee: Option<monero::Amount>,
+    min_monero_output: Option<monero::Amount>,
     env_config: env::Config,
 
     latest_rate: LR,
     resume_only: bool,
+    paused: Arc<AtomicBool>,
 }
 
 impl<LR> Behaviour<LR> {
     pub fn new(
         min_buy: bitcoin::Amount,
         max_buy: bitcoin::Amount,
+        max_lock_fee: Option<monero::Amount>,
+        min_monero_output: Option<monero::Amount>,
         env_config: env::Config,
         latest_rate: LR,
         resume_only: bool,
@@ -136,11 +167,20 @@ impl<LR> Behaviour<LR> {
             events: Default::default(),
             min_buy,
             max_buy,
+            max_lock_fee,
+            min_monero_output,
             env_config,
             latest_rate,
             resume_only,
+            paused: Default::default(),
         }
     }
+
+    /// The flag that makes the behaviour decline new swaps while set. Unlike
+    /// `resume_only` it can be toggled while the ASB is running.
+    pub fn paused(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
 }
 
 impl<LR> NetworkBehaviour for Behaviour<LR>
@@ -154,9 +194,12 @@ where
         Handler::new(
             self.min_buy,
             self.max_buy,
+            self.max_lock_fee,
+            self.min_monero_output,
             self.env_config,
             self.latest_rate.clone(),
             self.resume_only,
+            self.paused.clone(),
         )
     }
 
@@ -209,10 +252,13 @@ pub struct Handler<LR> {
 
     min_buy: bitcoin::Amount,
     max_buy: bitcoin::Amount,
+    max_lock_fee: Option<monero::Amount>,
+    min_monero_output: Option<monero::Amount>,
     env_config: env::Config,
 
     latest_rate: LR,
     resume_only: bool,
+    paused: Arc<AtomicBool>,
 
     timeout: Duration,
     keep_alive: KeepAlive,
@@ -222,18 +268,24 @@ impl<LR> Handler<LR> {
     fn new(
         min_buy: bitcoin::Amount,
         max_buy: bitcoin::Amount,
+        max_lock_fee: Option<monero::Amount>,
+        min_monero_output: Option<monero::Amount>,
         env_config: env::Config,
         latest_rate: LR,
         resume_only: bool,
+        paused: Arc<AtomicBool>,
     ) -> Self {
         Self {
             inbound_stream: OptionFuture::from(None),
             events: Default::default(),
             min_buy,
             max_buy,
+            max_lock_fee,
+            min_monero_output,
             env_config,
             latest_rate,
             resume_only,
+            paused,
             timeout: Duration::from_secs(120),
             keep_alive: KeepAlive::Until(Instant::now() + Duration::from_secs(10)),
         }
@@ -243,7 +295,7 @@ impl<LR> Handler<LR> {
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum HandlerOutEvent {
-    Initiated(bmrng::RequestReceiver<bitcoin::Amount, WalletSnapshot>),
+    Initiated(bmrng::RequestReceiver<(bitcoin::Amount, monero::Amount), WalletSnapshot>),
     Completed(Result<(Uuid, State3)>),
 }
 
@@ -260,23 +312,26 @@ where
     type OutboundOpenInfo = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
-        SubstreamProtocol::new(protocol::new(), ())
+        SubstreamProtocol::new(protocol::new(self.env_config.message_compression), ())
     }
 
     fn inject_fully_negotiated_inbound(
         &mut self,
-        mut substream: NegotiatedSubstream,
+        mut substream: protocol::Substream,
         _: Self::InboundOpenInfo,
     ) {
         self.keep_alive = KeepAlive::Yes;
 
-        let (sender, receiver) = bmrng::channel_with_timeout::<bitcoin::Amount, WalletSnapshot>(
-            1,
-            Duration::from_secs(5),
-        );
+        let (sender, receiver) = bmrng::channel_with_timeout::<
+            (bitcoin::Amount, monero::Amount),
+            WalletSnapshot,
+        >(1, Duration::from_secs(5));
         let resume_only = self.resume_only;
+        let paused = self.paused.clone();
         let min_buy = self.min_buy;
         let max_buy = self.max_buy;
+        let max_lock_fee = self.max_lock_fee;
+        let min_monero_output = self.min_monero_output;
         let latest_rate = self.latest_rate.latest_rate();
         let env_config = self.env_config;
 
@@ -285,11 +340,6 @@ where
                 .await
                 .context("Failed to read spot price request")?;
 
-            let wallet_snapshot = sender
-                .send_receive(request.btc)
-                .await
-                .context("Failed to receive wallet snapshot")?;
-
             // wrap all of these into another future so we can `return` from all the
             // different blocks
             let validate = async {
@@ -297,6 +347,10 @@ where
                     return Err(Error::ResumeOnlyMode);
                 };
 
+                if paused.load(Ordering::SeqCst) {
+                    return Err(Error::Paused);
+                }
+
                 let blockchain_network = BlockchainNetwork {
                     bitcoin: env_config.bitcoin_network,
                     monero: env_config.monero_network,
@@ -330,27 +384,62 @@ where
                     .sell_quote(btc)
                     .map_err(Error::SellQuoteCalculationFailed)?;
 
-                let unlocked = Amount::from_piconero(wallet_snapshot.balance.unlocked_balance);
-                if unlocked < xmr + wallet_snapshot.lock_fee {
+                if let Some(min) = min_monero_output {
+                    if xmr < min {
+                        let min_buy = rate
+                            .min_btc_for(min)
+                            .map_err(Error::SellQuoteCalculationFailed)?;
+
+                        return Err(Error::XmrAmountBelowMinimum {
+                            min,
+                            xmr,
+                            min_buy,
+                            buy: btc,
+                        });
+                    }
+                }
+
+                let wallet_snapshot = sender
+                    .send_receive((btc, xmr))
+                    .await
+                    .map_err(|e| Error::WalletSnapshotFailed(anyhow!(e)))?;
+
+                let lock_fee = match wallet_snapshot.lock_fee {
+                    Some(lock_fee) => lock_fee,
+                    None => {
+                        return Err(Error::BalanceTooLow {
+                            balance: wallet_snapshot.balance,
+                            buy: btc,
+                        })
+                    }
+                };
+
+                if let Some(max) = max_lock_fee {
+                    if lock_fee > max {
+                        return Err(Error::LockFeeTooHigh { fee: lock_fee, max });
+                    }
+                }
+
+                if wallet_snapshot.available < xmr + lock_fee {
                     return Err(Error::BalanceTooLow {
                         balance: wallet_snapshot.balance,
                         buy: btc,
                     });
                 }
 
-                Ok(xmr)
+                Ok((xmr, wallet_snapshot))
             };
 
             let result = validate.await;
 
             swap_setup::write_cbor_message(
                 &mut substream,
-                SpotPriceResponse::from_result_ref(&result),
+                SpotPriceResponse::from_result_ref(result.as_ref().map(|(xmr, _)| *xmr)),
             )
             .await
             .context("Failed to write spot price response")?;
 
-            let xmr = result?;
+            let (xmr, wallet_snapshot) = result?;
 
             let state0 = State0::new(
                 request.btc,
@@ -465,9 +554,9 @@ where
 }
 
 impl SpotPriceResponse {
-    pub fn from_result_ref(result: &Result<monero::Amount, Error>) -> Self {
+    pub fn from_result_ref(result: Result<monero::Amount, &Error>) -> Self {
         match result {
-            Ok(amount) => SpotPriceResponse::Xmr(*amount),
+            Ok(amount) => SpotPriceResponse::Xmr(amount),
             Err(error) => SpotPriceResponse::Error(error.to_error_response()),
         }
     }
@@ -477,6 +566,8 @@ impl SpotPriceResponse {
 pub enum Error {
     #[error("ASB is running in resume-only mode")]
     ResumeOnlyMode,
+    #[error("ASB is paused and does not accept new swaps")]
+    Paused,
     #[error("Amount {buy} below minimum {min}")]
     AmountBelowMinimum {
         min: bitcoin::Amount,
@@ -492,10 +583,24 @@ pub enum Error {
         balance: monero_rpc::wallet::GetBalance,
         buy: bitcoin::Amount,
     },
+    #[error("Monero amount {xmr} for {buy} below minimum Monero output {min}, which takes at least {min_buy}")]
+    XmrAmountBelowMinimum {
+        min: monero::Amount,
+        xmr: monero::Amount,
+        min_buy: bitcoin::Amount,
+        buy: bitcoin::Amount,
+    },
+    #[error("Estimated Monero lock fee {fee} above maximum {max}")]
+    LockFeeTooHigh {
+        fee: monero::Amount,
+        max: monero::Amount,
+    },
     #[error("Failed to fetch latest rate")]
     LatestRateFetchFailed(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     #[error("Failed to calculate quote")]
     SellQuoteCalculationFailed(#[source] anyhow::Error),
+    #[error("Failed to receive wallet snapshot")]
+    WalletSnapshotFailed(#[source] anyhow::Error),
     #[error("Blockchain networks did not match, we are on {asb:?}, but request from {cli:?}")]
     BlockchainNetworkMismatch {
         cli: BlockchainNetwork,
@@ -507,7 +612,11 @@ impl Error {
     pub fn to_error_response(&self) -> SpotPriceError {
         match self {
             Error::ResumeOnlyMode => SpotPriceError::NoSwapsAccepted,
-            Error::AmountBelowMinimum { min, buy } => SpotPriceError::AmountBelowMinimum {
+            Error::Paused | Error::LockFeeTooHigh { .. } => SpotPriceError::TemporarilyUnavailable,
+            Error::AmountBelowMinimum { min, buy }
+            | Error::XmrAmountBelowMinimum {
+                min_buy: min, buy, ..
+            } => SpotPriceError::AmountBelowMinimum {
                 min: *min,
                 buy: *buy,
             },
@@ -522,9 +631,9 @@ impl Error {
                     asb: *asb,
                 }
             }
-            Error::LatestRateFetchFailed(_) | Error::SellQuoteCalculationFailed(_) => {
-                SpotPriceError::Other
-            }
+            Error::LatestRateFetchFailed(_)
+            | Error::SellQuoteCalculationFailed(_)
+            | Error::WalletSnapshotFailed(_) => SpotPriceError::Other,
         }
     }
 }
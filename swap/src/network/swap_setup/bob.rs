@@ -2,7 +2,7 @@ use crate::network::swap_setup::{
     protocol, read_cbor_message, write_cbor_message, BlockchainNetwork, SpotPriceError,
     SpotPriceRequest, SpotPriceResponse,
 };
-use crate::protocol::bob::{State0, State2};
+use crate::protocol::bob::{encsig_deadline, State0, State2};
 use crate::protocol::{Message1, Message3};
 use crate::{bitcoin, cli, env, monero};
 use anyhow::Result;
@@ -11,9 +11,8 @@ use futures::{AsyncWriteExt, FutureExt};
 use libp2p::core::connection::ConnectionId;
 use libp2p::core::upgrade;
 use libp2p::swarm::{
-    KeepAlive, NegotiatedSubstream, NetworkBehaviour, NetworkBehaviourAction, NotifyHandler,
-    PollParameters, ProtocolsHandler, ProtocolsHandlerEvent, ProtocolsHandlerUpgrErr,
-    SubstreamProtocol,
+    KeepAlive, NetworkBehaviour, NetworkBehaviourAction, NotifyHandler, PollParameters,
+    ProtocolsHandler, ProtocolsHandlerEvent, ProtocolsHandlerUpgrErr, SubstreamProtocol,
 };
 use libp2p::{Multiaddr, PeerId};
 use std::collections::VecDeque;
@@ -123,7 +122,18 @@ pub struct NewSwap {
     pub btc: bitcoin::Amount,
     pub tx_refund_fee: bitcoin::Amount,
     pub tx_cancel_fee: bitcoin::Amount,
+    /// Whether to ask for an anchor output on the cancel transaction.
+    pub cancel_anchor: bool,
+    pub tx_lock_fee_rate: Option<bitcoin::FeeRate>,
     pub bitcoin_refund_address: bitcoin::Address,
+    /// The least amount of XMR Bob accepts to receive per BTC.
+    pub min_rate: Option<monero::Amount>,
+    /// The Monero lock confirmations to wait for, never fewer than the
+    /// `monero_finality_confirmations` of the environment.
+    pub monero_finality_confirmations: Option<u64>,
+    /// How many blocks before the cancel timelock Bob stops trying to send the
+    /// encrypted signature, bounds `monero_finality_confirmations`.
+    pub encsig_deadline_margin: u32,
 }
 
 #[derive(Debug)]
@@ -148,7 +158,7 @@ impl ProtocolsHandler for Handler {
 
     fn inject_fully_negotiated_outbound(
         &mut self,
-        mut substream: NegotiatedSubstream,
+        mut substream: protocol::Substream,
         info: Self::OutboundOpenInfo,
     ) {
         let bitcoin_wallet = self.bitcoin_wallet.clone();
@@ -168,6 +178,7 @@ impl ProtocolsHandler for Handler {
             .await?;
 
             let xmr = Result::from(read_cbor_message::<SpotPriceResponse>(&mut substream).await?)?;
+            check_min_rate(info.btc, xmr, info.min_rate)?;
 
             let state0 = State0::new(
                 info.swap_id,
@@ -177,9 +188,15 @@ impl ProtocolsHandler for Handler {
                 env_config.bitcoin_cancel_timelock,
                 env_config.bitcoin_punish_timelock,
                 info.bitcoin_refund_address,
-                env_config.monero_finality_confirmations,
+                monero_finality_confirmations(
+                    info.monero_finality_confirmations,
+                    info.encsig_deadline_margin,
+                    &env_config,
+                ),
                 info.tx_refund_fee,
                 info.tx_cancel_fee,
+                info.cancel_anchor,
+                info.tx_lock_fee_rate,
             );
 
             write_cbor_message(&mut substream, state0.next_message()).await?;
@@ -239,7 +256,10 @@ impl ProtocolsHandler for Handler {
         if let Some(new_swap) = self.new_swaps.pop_front() {
             self.keep_alive = KeepAlive::Yes;
             return Poll::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
-                protocol: SubstreamProtocol::new(protocol::new(), new_swap),
+                protocol: SubstreamProtocol::new(
+                    protocol::new(self.env_config.message_compression),
+                    new_swap,
+                ),
             });
         }
 
@@ -265,6 +285,8 @@ impl From<SpotPriceResponse> for Result<monero::Amount, Error> {
 pub enum Error {
     #[error("Seller currently does not accept incoming swap requests, please try again later")]
     NoSwapsAccepted,
+    #[error("Seller is temporarily unavailable, please try again later")]
+    TemporarilyUnavailable,
     #[error("Seller refused to buy {buy} because the minimum configured buy limit is {min}")]
     AmountBelowMinimum {
         min: bitcoin::Amount,
@@ -277,7 +299,6 @@ pub enum Error {
     },
     #[error("Seller's XMR balance is currently too low to fulfill the swap request to buy {buy}, please try again later")]
     BalanceTooLow { buy: bitcoin::Amount },
-
     #[error("Seller blockchain network {asb:?} setup did not match your blockchain network setup {cli:?}")]
     BlockchainNetworkMismatch {
         cli: BlockchainNetwork,
@@ -287,16 +308,97 @@ pub enum Error {
     #[error("Failed to complete swap setup within {seconds}s")]
     Timeout { seconds: u64 },
 
+    #[error(
+        "Seller offered {xmr} for {btc} which is below the minimum rate of {min_rate} per BTC"
+    )]
+    RateBelowMinimum {
+        btc: bitcoin::Amount,
+        xmr: monero::Amount,
+        min_rate: monero::Amount,
+    },
+
     /// To be used for errors that cannot be explained on the CLI side (e.g.
     /// rate update problems on the seller side)
     #[error("Seller encountered a problem, please try again later.")]
     Other,
 }
 
+/// The Monero lock confirmations a swap waits for: `per_swap` if given, but
+/// never fewer than the `monero_finality_confirmations` of the environment
+/// and never more than fit in before the encrypted signature deadline.
+fn monero_finality_confirmations(
+    per_swap: Option<u64>,
+    encsig_deadline_margin: u32,
+    env_config: &env::Config,
+) -> u64 {
+    let floor = env_config.monero_finality_confirmations;
+    let ceiling =
+        monero_blocks_until_encsig_deadline(encsig_deadline_margin, env_config).max(floor);
+
+    match per_swap {
+        Some(confirmations) if confirmations < floor => {
+            tracing::warn!(
+                requested = confirmations,
+                minimum = floor,
+                "Raising Monero finality confirmations of swap to the minimum"
+            );
+            floor
+        }
+        Some(confirmations) if confirmations > ceiling => {
+            tracing::warn!(
+                requested = confirmations,
+                maximum = ceiling,
+                "Lowering Monero finality confirmations of swap to what fits in before the encrypted signature deadline"
+            );
+            ceiling
+        }
+        Some(confirmations) => confirmations,
+        None => floor,
+    }
+}
+
+/// How many Monero blocks are mined on average between the Bitcoin lock
+/// transaction becoming final, which is when Alice locks the Monero, and the
+/// deadline for the encrypted signature. Waiting for more confirmations than
+/// that, Bob would never send the encrypted signature in time.
+fn monero_blocks_until_encsig_deadline(
+    encsig_deadline_margin: u32,
+    env_config: &env::Config,
+) -> u64 {
+    let deadline = encsig_deadline(env_config.bitcoin_cancel_timelock, encsig_deadline_margin);
+    let bitcoin_blocks = deadline.saturating_sub(env_config.bitcoin_finality_confirmations);
+    let until_deadline = env_config.bitcoin_avg_block_time * bitcoin_blocks;
+    let blocks = until_deadline.as_millis() / env_config.monero_avg_block_time.as_millis().max(1);
+
+    u64::try_from(blocks).unwrap_or(u64::MAX)
+}
+
+/// Fails if Alice offers less than `min_rate` XMR per BTC for `btc`.
+fn check_min_rate(
+    btc: bitcoin::Amount,
+    xmr: monero::Amount,
+    min_rate: Option<monero::Amount>,
+) -> Result<(), Error> {
+    let min_rate = match min_rate {
+        Some(min_rate) => min_rate,
+        None => return Ok(()),
+    };
+
+    let offered = u128::from(xmr.as_piconero()) * u128::from(bitcoin::Amount::ONE_BTC.to_sat());
+    let required = u128::from(min_rate.as_piconero()) * u128::from(btc.to_sat());
+
+    if offered < required {
+        return Err(Error::RateBelowMinimum { btc, xmr, min_rate });
+    }
+
+    Ok(())
+}
+
 impl From<SpotPriceError> for Error {
     fn from(error: SpotPriceError) -> Self {
         match error {
             SpotPriceError::NoSwapsAccepted => Error::NoSwapsAccepted,
+            SpotPriceError::TemporarilyUnavailable => Error::TemporarilyUnavailable,
             SpotPriceError::AmountBelowMinimum { min, buy } => {
                 Error::AmountBelowMinimum { min, buy }
             }
@@ -311,3 +413,126 @@ impl From<SpotPriceError> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::GetConfig;
+    use crate::protocol::bob::DEFAULT_ENCSIG_DEADLINE_MARGIN;
+
+    #[test]
+    fn given_quote_below_min_rate_then_fails() {
+        let btc = bitcoin::Amount::from_btc(0.5).unwrap();
+        let xmr = monero::Amount::from_monero(49.0).unwrap();
+        let min_rate = monero::Amount::from_monero(100.0).unwrap();
+
+        let result = check_min_rate(btc, xmr, Some(min_rate));
+
+        assert_eq!(result, Err(Error::RateBelowMinimum { btc, xmr, min_rate }));
+    }
+
+    #[test]
+    fn given_quote_at_or_above_min_rate_then_succeeds() {
+        let btc = bitcoin::Amount::from_btc(0.5).unwrap();
+        let min_rate = monero::Amount::from_monero(100.0).unwrap();
+
+        let at = monero::Amount::from_monero(50.0).unwrap();
+        let above = monero::Amount::from_monero(51.0).unwrap();
+
+        assert_eq!(check_min_rate(btc, at, Some(min_rate)), Ok(()));
+        assert_eq!(check_min_rate(btc, above, Some(min_rate)), Ok(()));
+    }
+
+    #[test]
+    fn given_no_min_rate_then_any_quote_succeeds() {
+        let btc = bitcoin::Amount::from_btc(0.5).unwrap();
+
+        assert_eq!(check_min_rate(btc, monero::Amount::ZERO, None), Ok(()));
+    }
+
+    #[test]
+    fn given_per_swap_confirmations_above_floor_then_they_are_used() {
+        let env_config = env::Testnet::get_config();
+
+        assert_eq!(
+            monero_finality_confirmations(Some(25), DEFAULT_ENCSIG_DEADLINE_MARGIN, &env_config),
+            25
+        );
+    }
+
+    #[test]
+    fn given_per_swap_confirmations_below_floor_then_floor_is_used() {
+        let env_config = env::Testnet::get_config();
+
+        assert_eq!(
+            monero_finality_confirmations(Some(3), DEFAULT_ENCSIG_DEADLINE_MARGIN, &env_config),
+            10
+        );
+    }
+
+    #[test]
+    fn given_no_per_swap_confirmations_then_floor_is_used() {
+        let env_config = env::Testnet::get_config();
+
+        assert_eq!(
+            monero_finality_confirmations(None, DEFAULT_ENCSIG_DEADLINE_MARGIN, &env_config),
+            10
+        );
+    }
+
+    #[test]
+    fn given_per_swap_confirmations_beyond_encsig_deadline_then_they_are_capped() {
+        // The encsig deadline is 3 blocks before the cancel timelock of 12, the
+        // lock transaction is final after 1. That leaves 8 Bitcoin blocks of 10
+        // minutes, Monero blocks take 2.
+        let env_config = env::Testnet::get_config();
+        let confirmations = |per_swap| {
+            monero_finality_confirmations(
+                Some(per_swap),
+                DEFAULT_ENCSIG_DEADLINE_MARGIN,
+                &env_config,
+            )
+        };
+
+        assert_eq!(confirmations(40), 40);
+        assert_eq!(confirmations(41), 40);
+        assert_eq!(confirmations(u64::MAX), 40);
+    }
+
+    #[test]
+    fn given_per_swap_confirmations_at_limit_then_encsig_can_be_sent_before_deadline() {
+        for env_config in [env::Mainnet::get_config(), env::Testnet::get_config()] {
+            let confirmations = monero_finality_confirmations(
+                Some(u64::MAX),
+                DEFAULT_ENCSIG_DEADLINE_MARGIN,
+                &env_config,
+            );
+
+            // Alice locks the Monero once the Bitcoin lock transaction is final,
+            // Bob sends the encrypted signature once the Monero lock is final
+            let monero_final = env_config.bitcoin_avg_block_time
+                * env_config.bitcoin_finality_confirmations
+                + env_config.monero_avg_block_time * u32::try_from(confirmations).unwrap();
+            let deadline = env_config.bitcoin_avg_block_time
+                * encsig_deadline(
+                    env_config.bitcoin_cancel_timelock,
+                    DEFAULT_ENCSIG_DEADLINE_MARGIN,
+                );
+
+            assert!(monero_final <= deadline);
+        }
+    }
+
+    #[test]
+    fn given_cancel_timelock_shorter_than_floor_then_floor_is_used() {
+        let env_config = env::Config {
+            bitcoin_cancel_timelock: bitcoin::CancelTimelock::new(1),
+            ..env::Testnet::get_config()
+        };
+
+        assert_eq!(
+            monero_finality_confirmations(Some(25), DEFAULT_ENCSIG_DEADLINE_MARGIN, &env_config),
+            10
+        );
+    }
+}
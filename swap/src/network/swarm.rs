@@ -2,7 +2,7 @@ use crate::asb::{LatestRate, RendezvousNode};
 use crate::libp2p_ext::MultiAddrExt;
 use crate::network::rendezvous::XmrBtcNamespace;
 use crate::seed::Seed;
-use crate::{asb, bitcoin, cli, env, tor};
+use crate::{asb, bitcoin, cli, env, monero, tor};
 use anyhow::Result;
 use libp2p::swarm::{NetworkBehaviour, SwarmBuilder};
 use libp2p::{identity, Multiaddr, Swarm};
@@ -13,6 +13,8 @@ pub fn asb<LR>(
     seed: &Seed,
     min_buy: bitcoin::Amount,
     max_buy: bitcoin::Amount,
+    max_monero_lock_fee: Option<monero::Amount>,
+    min_monero_output: Option<monero::Amount>,
     latest_rate: LR,
     resume_only: bool,
     env_config: env::Config,
@@ -38,6 +40,8 @@ where
     let behaviour = asb::Behaviour::new(
         min_buy,
         max_buy,
+        max_monero_lock_fee,
+        min_monero_output,
         latest_rate,
         resume_only,
         env_config,
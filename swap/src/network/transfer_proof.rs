@@ -1,4 +1,4 @@
-use crate::network::cbor_request_response::CborCodec;
+use crate::network::cbor_request_response::{CborCodec, Compressible};
 use crate::{asb, cli, monero};
 use libp2p::core::ProtocolName;
 use libp2p::request_response::{
@@ -13,7 +13,7 @@ const PROTOCOL: &str = "/comit/xmr/btc/transfer_proof/1.0.0";
 type OutEvent = RequestResponseEvent<Request, ()>;
 type Message = RequestResponseMessage<Request, ()>;
 
-pub type Behaviour = RequestResponse<CborCodec<TransferProofProtocol, Request, ()>>;
+pub type Behaviour = RequestResponse<CborCodec<Compressible<TransferProofProtocol>, Request, ()>>;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct TransferProofProtocol;
@@ -30,18 +30,22 @@ pub struct Request {
     pub tx_lock_proof: monero::TransferProof,
 }
 
-pub fn alice() -> Behaviour {
+pub fn alice(compression: bool) -> Behaviour {
     Behaviour::new(
         CborCodec::default(),
-        vec![(TransferProofProtocol, ProtocolSupport::Outbound)],
+        Compressible::protocols(
+            TransferProofProtocol,
+            ProtocolSupport::Outbound,
+            compression,
+        ),
         RequestResponseConfig::default(),
     )
 }
 
-pub fn bob() -> Behaviour {
+pub fn bob(compression: bool) -> Behaviour {
     Behaviour::new(
         CborCodec::default(),
-        vec![(TransferProofProtocol, ProtocolSupport::Inbound)],
+        Compressible::protocols(TransferProofProtocol, ProtocolSupport::Inbound, compression),
         RequestResponseConfig::default(),
     )
 }
@@ -1,17 +1,23 @@
+use crate::database::{OnCollision, Repair, RepairReport, SwapBackup, SwapReceipt};
 use crate::protocol::alice::swap::is_complete as alice_is_complete;
 use crate::protocol::alice::AliceState;
 use crate::protocol::bob::swap::is_complete as bob_is_complete;
 use crate::protocol::bob::BobState;
-use crate::{bitcoin, monero};
-use anyhow::Result;
+use crate::{bitcoin, database, monero};
+use anyhow::{bail, Result};
 use async_trait::async_trait;
+use bdk::miniscript::Descriptor;
 use conquer_once::Lazy;
 use libp2p::{Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use sigma_fun::ext::dl_secp256k1_ed25519_eq::{CrossCurveDLEQ, CrossCurveDLEQProof};
 use sigma_fun::HashTranscript;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::fmt;
+use std::io::{Read, Write};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 pub mod alice;
@@ -26,6 +32,20 @@ pub static CROSS_CURVE_PROOF_SYSTEM: Lazy<
     )
 });
 
+/// The side of the swap we are running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Role {
+    Alice,
+    Bob,
+}
+
+/// Span that attaches the swap id and our role to every log line emitted
+/// while running a swap, so logs of concurrent swaps can be told apart.
+pub fn swap_span(swap_id: Uuid, role: Role) -> tracing::Span {
+    tracing::info_span!("swap", %swap_id, %role)
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message0 {
     swap_id: Uuid,
@@ -39,6 +59,9 @@ pub struct Message0 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    /// Whether Bob asks for an anchor output on the cancel transaction.
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -89,6 +112,15 @@ impl State {
     }
 }
 
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            State::Alice(state) => state.fmt(f),
+            State::Bob(state) => state.fmt(f),
+        }
+    }
+}
+
 impl From<AliceState> for State {
     fn from(alice: AliceState) -> Self {
         Self::Alice(alice)
@@ -109,6 +141,15 @@ pub struct NotAlice;
 #[error("Not in the role of Bob")]
 pub struct NotBob;
 
+/// Returned when a command that would advance or recover a swap is run on a
+/// swap that already reached a final state.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("Swap {swap_id} is already completed in state {final_state}")]
+pub struct AlreadyCompleted {
+    pub swap_id: Uuid,
+    pub final_state: State,
+}
+
 impl TryInto<BobState> for State {
     type Error = NotBob;
 
@@ -131,6 +172,84 @@ impl TryInto<AliceState> for State {
     }
 }
 
+/// Progress observed while waiting in a state that is not part of the state
+/// itself.
+///
+/// Checkpoints are recorded periodically so this progress does not have to be
+/// observed again after a restart.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The confirmations of the Bitcoin lock transaction last observed.
+    pub tx_lock_confirmations: u32,
+    /// Whether Alice started the transfer that locks her Monero.
+    ///
+    /// Recorded before the transfer is sent, the transfer may have been
+    /// published even if the swap did not get to record its transfer proof.
+    #[serde(default)]
+    pub monero_lock_attempted: bool,
+    /// The fee Alice paid for the Monero lock transaction.
+    #[serde(default)]
+    pub monero_lock_fee: Option<monero::Amount>,
+    /// The fee Bob paid to sweep the redeemed Monero to his wallet.
+    #[serde(default)]
+    pub monero_redeem_fee: Option<monero::Amount>,
+}
+
+impl Checkpoint {
+    /// Whether the lock transaction was already observed with at least
+    /// `finality_confirmations` before the checkpoint was recorded.
+    pub fn tx_lock_is_final(&self, finality_confirmations: u32) -> bool {
+        self.tx_lock_confirmations >= finality_confirmations
+    }
+}
+
+/// The public keys both parties contributed to a swap, recorded to verify the
+/// swap independently afterwards.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SwapKeys {
+    /// Alice's key of the Bitcoin lock output.
+    pub alice_bitcoin: bitcoin::PublicKey,
+    /// Bob's key of the Bitcoin lock output.
+    pub bob_bitcoin: bitcoin::PublicKey,
+    /// Alice's share of the public spend key of the Monero lock output.
+    pub alice_monero: monero::PublicKey,
+    /// Bob's share of the public spend key of the Monero lock output.
+    pub bob_monero: monero::PublicKey,
+    /// The public view key of the Monero lock output.
+    pub monero_view: monero::PublicKey,
+}
+
+impl SwapKeys {
+    pub fn lock_descriptor(&self) -> Descriptor<::bitcoin::PublicKey> {
+        bitcoin::build_lock_descriptor(self.alice_bitcoin, self.bob_bitcoin)
+    }
+
+    pub fn monero_lock_address(&self, network: monero::Network) -> monero::Address {
+        monero::Address::standard(
+            network,
+            self.alice_monero + self.bob_monero,
+            self.monero_view,
+        )
+    }
+}
+
+/// The amounts of a swap and the Bitcoin transactions it may publish, as
+/// agreed on during the swap setup, each with the fee it pays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapTransactions {
+    pub btc: bitcoin::Amount,
+    pub xmr: monero::Amount,
+    pub tx_lock: bitcoin::TxLock,
+    pub tx_redeem: (bitcoin::Txid, bitcoin::Amount),
+    pub tx_cancel: (bitcoin::Txid, bitcoin::Amount),
+    pub tx_refund: (bitcoin::Txid, bitcoin::Amount),
+    /// Only Alice knows the address the punish transaction pays to.
+    pub tx_punish: Option<(bitcoin::Txid, bitcoin::Amount)>,
+}
+
+/// User-defined key-value pairs attached to a swap.
+pub type SwapMetadata = BTreeMap<String, String>;
+
 #[async_trait]
 pub trait Database {
     async fn insert_peer_id(&self, swap_id: Uuid, peer_id: PeerId) -> Result<()>;
@@ -141,5 +260,96 @@ pub trait Database {
     async fn get_addresses(&self, peer_id: PeerId) -> Result<Vec<Multiaddr>>;
     async fn insert_latest_state(&self, swap_id: Uuid, state: State) -> Result<()>;
     async fn get_state(&self, swap_id: Uuid) -> Result<State>;
+    /// All states the swap went through, oldest first, with the time it
+    /// entered each of them.
+    async fn get_states(&self, swap_id: Uuid) -> Result<Vec<(OffsetDateTime, State)>>;
+    async fn insert_checkpoint(&self, swap_id: Uuid, checkpoint: Checkpoint) -> Result<()>;
+    async fn get_checkpoint(&self, swap_id: Uuid) -> Result<Option<Checkpoint>>;
+    async fn insert_swap_keys(&self, swap_id: Uuid, keys: SwapKeys) -> Result<()>;
+    async fn swap_keys(&self, swap_id: Uuid) -> Result<SwapKeys>;
+    async fn insert_transfer_proof(&self, swap_id: Uuid, proof: String) -> Result<()>;
+    /// The proof that Alice's Monero lock transaction pays the lock address,
+    /// in the format of monero-wallet-cli's `get_tx_proof`.
+    async fn transfer_proof(&self, swap_id: Uuid) -> Result<String>;
+    /// Replaces the metadata an integrator attached to the swap, e.g. the id
+    /// of an order in their own records.
+    async fn set_metadata(&self, swap_id: Uuid, metadata: SwapMetadata) -> Result<()>;
+    /// The metadata attached to the swap, empty if none was set.
+    async fn get_metadata(&self, swap_id: Uuid) -> Result<SwapMetadata>;
     async fn all(&self) -> Result<Vec<(Uuid, State)>>;
+    /// Inserts the swaps of `backups` in a single transaction, so that a
+    /// failure halfway leaves none of them behind.
+    async fn restore(&self, backups: Vec<SwapBackup>) -> Result<()>;
+
+    /// Returns the latest state of the swap with `swap_id`, failing with
+    /// [`AlreadyCompleted`] if the swap already reached a final state.
+    async fn get_incomplete_state(&self, swap_id: Uuid) -> Result<State> {
+        let state = self.get_state(swap_id).await?;
+
+        if state.swap_finished() {
+            bail!(AlreadyCompleted {
+                swap_id,
+                final_state: state,
+            });
+        }
+
+        Ok(state)
+    }
+    /// Looks for records that cannot be read, e.g. after a crash, and with
+    /// [`Repair::Quarantine`] moves them out of the way so that the remaining
+    /// swaps can be listed and resumed again.
+    async fn check_and_repair(&self, repair: Repair) -> Result<RepairReport>;
+
+    /// Writes all swaps as JSON to `writer`, returning the ids of the
+    /// exported swaps.
+    async fn export(&self, writer: &mut (dyn Write + Send)) -> Result<Vec<Uuid>> {
+        database::backup::export(self, writer).await
+    }
+
+    /// Reads swaps previously written by [`Database::export`] from `reader`,
+    /// returning the ids of the imported swaps.
+    async fn import(
+        &self,
+        reader: &mut (dyn Read + Send),
+        on_collision: OnCollision,
+    ) -> Result<Vec<Uuid>> {
+        database::backup::import(self, reader, on_collision).await
+    }
+
+    /// Exports the swap with `swap_id` as a single blob encrypted with `key`,
+    /// e.g. the one derived by [`Seed::derive_swap_backup_key`].
+    ///
+    /// [`Seed::derive_swap_backup_key`]: crate::seed::Seed::derive_swap_backup_key
+    async fn export_swap(&self, swap_id: Uuid, key: &[u8; 32]) -> Result<Vec<u8>> {
+        database::backup::export_encrypted(self, swap_id, key).await
+    }
+
+    /// Imports a swap exported by [`Database::export_swap`] with the same
+    /// `key`, returning its id.
+    async fn import_swap(&self, blob: &[u8], key: &[u8; 32]) -> Result<Uuid> {
+        database::backup::import_encrypted(self, blob, key).await
+    }
+
+    /// Summarizes the finished swap with `swap_id` for the user's records.
+    async fn receipt(&self, swap_id: Uuid) -> Result<SwapReceipt> {
+        database::receipt::receipt(self, swap_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_checkpoint_with_final_confirmations_lock_is_not_observed_again() {
+        let checkpoint = Checkpoint {
+            tx_lock_confirmations: 3,
+            ..Checkpoint::default()
+        };
+
+        assert!(checkpoint.tx_lock_is_final(3));
+        assert!(checkpoint.tx_lock_is_final(1));
+        assert!(!checkpoint.tx_lock_is_final(4));
+        assert!(!Checkpoint::default().tx_lock_is_final(1));
+    }
 }
@@ -7,7 +7,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 pub use self::state::*;
-pub use self::swap::{run, run_until};
+pub use self::swap::{run, run_until, MoneroLockAlreadyAttempted};
 
 pub mod state;
 pub mod swap;
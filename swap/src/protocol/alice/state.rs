@@ -6,7 +6,10 @@ use crate::env::Config;
 use crate::monero::wallet::{TransferRequest, WatchRequest};
 use crate::monero::TransferProof;
 use crate::monero_ext::ScalarExt;
-use crate::protocol::{Message0, Message1, Message2, Message3, Message4, CROSS_CURVE_PROOF_SYSTEM};
+use crate::protocol::{
+    Message0, Message1, Message2, Message3, Message4, SwapKeys, SwapTransactions,
+    CROSS_CURVE_PROOF_SYSTEM,
+};
 use crate::{bitcoin, monero};
 use anyhow::{anyhow, bail, Context, Result};
 use monero_rpc::wallet::BlockHeight;
@@ -48,6 +51,14 @@ pub enum AliceState {
         encrypted_signature: Box<bitcoin::EncryptedSignature>,
         state3: Box<State3>,
     },
+    /// The redeem transaction was handed to the operator to broadcast it
+    /// through their own node.
+    BtcRedeemReadyToBroadcast {
+        monero_wallet_restore_blockheight: BlockHeight,
+        transfer_proof: TransferProof,
+        encrypted_signature: Box<bitcoin::EncryptedSignature>,
+        state3: Box<State3>,
+    },
     BtcRedeemTransactionPublished {
         state3: Box<State3>,
     },
@@ -92,6 +103,9 @@ impl fmt::Display for AliceState {
                 write!(f, "xmr lock transfer proof sent")
             }
             AliceState::EncSigLearned { .. } => write!(f, "encrypted signature is learned"),
+            AliceState::BtcRedeemReadyToBroadcast { .. } => {
+                write!(f, "bitcoin redeem transaction is ready to broadcast")
+            }
             AliceState::BtcRedeemTransactionPublished { .. } => {
                 write!(f, "bitcoin redeem transaction published")
             }
@@ -107,6 +121,60 @@ impl fmt::Display for AliceState {
     }
 }
 
+impl AliceState {
+    /// The transfer proof of the Monero lock transaction, `None` before Alice
+    /// locked the Monero and once the swap is finished.
+    pub fn transfer_proof(&self) -> Option<&TransferProof> {
+        match self {
+            AliceState::XmrLockTransactionSent { transfer_proof, .. }
+            | AliceState::XmrLocked { transfer_proof, .. }
+            | AliceState::XmrLockTransferProofSent { transfer_proof, .. }
+            | AliceState::EncSigLearned { transfer_proof, .. }
+            | AliceState::BtcRedeemReadyToBroadcast { transfer_proof, .. }
+            | AliceState::BtcCancelled { transfer_proof, .. }
+            | AliceState::BtcRefunded { transfer_proof, .. }
+            | AliceState::BtcPunishable { transfer_proof, .. }
+            | AliceState::CancelTimelockExpired { transfer_proof, .. } => Some(transfer_proof),
+            _ => None,
+        }
+    }
+
+    /// The Monero Alice agreed to lock but has not sent yet, so it still
+    /// counts towards her unlocked balance.
+    pub fn xmr_to_lock(&self) -> Option<monero::Amount> {
+        match self {
+            AliceState::Started { state3 }
+            | AliceState::BtcLockTransactionSeen { state3 }
+            | AliceState::BtcLocked { state3 } => Some(state3.xmr),
+            _ => None,
+        }
+    }
+
+    /// The state agreed on during the swap setup, `None` once the swap is
+    /// finished.
+    pub fn state3(&self) -> Option<&State3> {
+        match self {
+            AliceState::Started { state3 }
+            | AliceState::BtcLockTransactionSeen { state3 }
+            | AliceState::BtcLocked { state3 }
+            | AliceState::XmrLockTransactionSent { state3, .. }
+            | AliceState::XmrLocked { state3, .. }
+            | AliceState::XmrLockTransferProofSent { state3, .. }
+            | AliceState::EncSigLearned { state3, .. }
+            | AliceState::BtcRedeemReadyToBroadcast { state3, .. }
+            | AliceState::BtcRedeemTransactionPublished { state3 }
+            | AliceState::BtcCancelled { state3, .. }
+            | AliceState::BtcRefunded { state3, .. }
+            | AliceState::BtcPunishable { state3, .. }
+            | AliceState::CancelTimelockExpired { state3, .. } => Some(state3),
+            AliceState::BtcRedeemed
+            | AliceState::BtcPunished
+            | AliceState::SafelyAborted
+            | AliceState::XmrRefunded => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct State0 {
     a: bitcoin::SecretKey,
@@ -123,6 +191,7 @@ pub struct State0 {
     punish_address: bitcoin::Address,
     tx_redeem_fee: bitcoin::Amount,
     tx_punish_fee: bitcoin::Amount,
+    cancel_anchor: bool,
 }
 
 impl State0 {
@@ -163,10 +232,15 @@ impl State0 {
             punish_timelock: env_config.bitcoin_punish_timelock,
             tx_redeem_fee,
             tx_punish_fee,
+            cancel_anchor: env_config.cancel_anchor,
         }
     }
 
     pub fn receive(self, msg: Message0) -> Result<(Uuid, State1)> {
+        if msg.cancel_anchor && !self.cancel_anchor {
+            bail!("Bob asked for an anchor output on the cancel transaction, which is disabled")
+        }
+
         let valid = CROSS_CURVE_PROOF_SYSTEM.verify(
             &msg.dleq_proof_s_b,
             (
@@ -208,6 +282,7 @@ impl State0 {
                 tx_punish_fee: self.tx_punish_fee,
                 tx_refund_fee: msg.tx_refund_fee,
                 tx_cancel_fee: msg.tx_cancel_fee,
+                cancel_anchor: msg.cancel_anchor,
             },
         ))
     }
@@ -236,6 +311,7 @@ pub struct State1 {
     tx_punish_fee: bitcoin::Amount,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    cancel_anchor: bool,
 }
 
 impl State1 {
@@ -276,6 +352,7 @@ impl State1 {
             tx_punish_fee: self.tx_punish_fee,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         })
     }
 }
@@ -300,16 +377,18 @@ pub struct State2 {
     tx_punish_fee: bitcoin::Amount,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    cancel_anchor: bool,
 }
 
 impl State2 {
     pub fn next_message(&self) -> Message3 {
-        let tx_cancel = bitcoin::TxCancel::new(
+        let tx_cancel = bitcoin::TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
 
         let tx_refund =
@@ -329,12 +408,13 @@ impl State2 {
     }
 
     pub fn receive(self, msg: Message4) -> Result<State3> {
-        let tx_cancel = bitcoin::TxCancel::new(
+        let tx_cancel = bitcoin::TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
         bitcoin::verify_sig(&self.B, &tx_cancel.digest(), &msg.tx_cancel_sig)
             .context("Failed to verify cancel transaction")?;
@@ -368,6 +448,7 @@ impl State2 {
             tx_punish_fee: self.tx_punish_fee,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         })
     }
 }
@@ -399,9 +480,29 @@ pub struct State3 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 impl State3 {
+    pub fn swap_keys(&self) -> SwapKeys {
+        SwapKeys {
+            alice_bitcoin: self.a.public(),
+            bob_bitcoin: self.B,
+            alice_monero: monero::PublicKey::from_private_key(&monero::PrivateKey {
+                scalar: self.s_a,
+            }),
+            bob_monero: self.S_b_monero,
+            monero_view: monero::PublicKey::from(self.v.public()),
+        }
+    }
+
+    /// Fails if the lock output seen on the chain does not hold the Bitcoin
+    /// amount agreed on for this swap, see [`bitcoin::TxLock::ensure_amount`].
+    pub async fn ensure_lock_amount(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<()> {
+        self.tx_lock.ensure_amount(bitcoin_wallet, self.btc).await
+    }
+
     pub async fn expired_timelocks(
         &self,
         bitcoin_wallet: &bitcoin::Wallet,
@@ -419,6 +520,20 @@ impl State3 {
         ))
     }
 
+    pub fn swap_transactions(&self) -> SwapTransactions {
+        let tx_punish = self.tx_punish();
+
+        SwapTransactions {
+            btc: self.btc,
+            xmr: self.xmr,
+            tx_lock: self.tx_lock.clone(),
+            tx_redeem: (self.tx_redeem().txid(), self.tx_redeem_fee),
+            tx_cancel: (self.tx_cancel().txid(), self.tx_cancel_fee),
+            tx_refund: (self.tx_refund().txid(), self.tx_refund_fee),
+            tx_punish: Some((tx_punish.txid(), self.tx_punish_fee)),
+        }
+    }
+
     pub fn lock_xmr_transfer_request(&self) -> TransferRequest {
         let S_a = monero::PublicKey::from_private_key(&monero::PrivateKey { scalar: self.s_a });
 
@@ -451,12 +566,13 @@ impl State3 {
     }
 
     pub fn tx_cancel(&self) -> TxCancel {
-        TxCancel::new(
+        TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.a.public(),
             self.B,
             self.tx_cancel_fee,
+            self.cancel_anchor,
         )
     }
 
@@ -464,6 +580,10 @@ impl State3 {
         bitcoin::TxRefund::new(&self.tx_cancel(), &self.refund_address, self.tx_refund_fee)
     }
 
+    pub fn tx_redeem_fee(&self) -> bitcoin::Amount {
+        self.tx_redeem_fee
+    }
+
     pub fn tx_redeem(&self) -> TxRedeem {
         TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee)
     }
@@ -497,7 +617,16 @@ impl State3 {
 
     pub async fn submit_tx_cancel(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<Txid> {
         let transaction = self.signed_cancel_transaction()?;
-        let (tx_id, _) = bitcoin_wallet.broadcast(transaction, "cancel").await?;
+        let (tx_id, _) = if self.cancel_anchor {
+            bitcoin_wallet
+                .broadcast_with_anchor(transaction, "cancel", self.tx_cancel_fee)
+                .await?
+        } else {
+            bitcoin_wallet
+                .broadcast_with_fee(transaction, "cancel", self.tx_cancel_fee)
+                .await?
+        };
+
         Ok(tx_id)
     }
 
@@ -532,7 +661,9 @@ impl State3 {
     pub async fn punish_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<Txid> {
         let signed_tx_punish = self.signed_punish_transaction()?;
 
-        let (txid, subscription) = bitcoin_wallet.broadcast(signed_tx_punish, "punish").await?;
+        let (txid, subscription) = bitcoin_wallet
+            .broadcast_with_fee(signed_tx_punish, "punish", self.tx_punish_fee)
+            .await?;
         subscription.wait_until_final().await?;
 
         Ok(txid)
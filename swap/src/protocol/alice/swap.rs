@@ -1,13 +1,19 @@
 //! Run an XMR/BTC swap in the role of Alice.
 //! Alice holds XMR and wishes receive BTC.
-use crate::asb::{EventLoopHandle, LatestRate};
-use crate::bitcoin::ExpiredTimelocks;
+use crate::asb::{shut_down_swap, EventLoopHandle, LatestRate, ShutdownAction};
+use crate::bitcoin::wallet::{SwapTransaction, SwapWatchSet};
+use crate::bitcoin::{ExpiredTimelocks, LockAlreadySpent, LockUnderfunded, ScriptStatus};
 use crate::env::Config;
-use crate::protocol::alice::{AliceState, Swap};
+use crate::monero::wallet::TransferRequest;
+use crate::monero::TransferProof;
+use crate::protocol::alice::{AliceState, State3, Swap};
+use crate::protocol::{swap_span, Checkpoint, Database, Role, State};
 use crate::{bitcoin, monero};
 use anyhow::{bail, Context, Result};
+use std::time::Duration;
 use tokio::select;
 use tokio::time::timeout;
+use tracing::Instrument;
 use uuid::Uuid;
 
 pub async fn run<LR>(swap: Swap, rate_service: LR) -> Result<AliceState>
@@ -17,8 +23,22 @@ where
     run_until(swap, |_| false, rate_service).await
 }
 
-#[tracing::instrument(name = "swap", skip(swap,exit_early,rate_service), fields(id = %swap.swap_id), err)]
 pub async fn run_until<LR>(
+    swap: Swap,
+    exit_early: fn(&AliceState) -> bool,
+    rate_service: LR,
+) -> Result<AliceState>
+where
+    LR: LatestRate + Clone,
+{
+    let span = swap_span(swap.swap_id, Role::Alice);
+
+    advance_until(swap, exit_early, rate_service)
+        .instrument(span)
+        .await
+}
+
+async fn advance_until<LR>(
     mut swap: Swap,
     exit_early: fn(&AliceState) -> bool,
     rate_service: LR,
@@ -27,18 +47,102 @@ where
     LR: LatestRate + Clone,
 {
     let mut current_state = swap.state;
+    let checkpoint = swap
+        .db
+        .get_checkpoint(swap.swap_id)
+        .await?
+        .unwrap_or_default();
+
+    if let Some(reconciled) = reconcile(
+        swap.swap_id,
+        &current_state,
+        swap.db.as_ref(),
+        swap.bitcoin_wallet.as_ref(),
+    )
+    .await?
+    {
+        swap.db
+            .insert_latest_state(swap.swap_id, reconciled.clone().into())
+            .await?;
+        current_state = reconciled;
+    }
 
     while !is_complete(&current_state) && !exit_early(&current_state) {
-        current_state = next_state(
-            swap.swap_id,
-            current_state,
-            &mut swap.event_loop_handle,
-            swap.bitcoin_wallet.as_ref(),
-            swap.monero_wallet.as_ref(),
-            &swap.env_config,
-            rate_service.clone(),
-        )
-        .await?;
+        let tx_lock = match &current_state {
+            AliceState::BtcLockTransactionSeen { state3 } => Some(state3.tx_lock.clone()),
+            _ => None,
+        };
+
+        // Aborting while the Monero is being sent could lose track of the
+        // transfer, the swap is stopped once the transfer is recorded instead.
+        let abortable = !matches!(current_state, AliceState::BtcLocked { .. });
+        let aborted = swap.event_loop_handle.aborted();
+        let previous_state = current_state.clone();
+
+        let next_state = async {
+            let next_state = next_state(
+                swap.swap_id,
+                current_state,
+                &mut swap.event_loop_handle,
+                swap.bitcoin_wallet.as_ref(),
+                swap.monero_wallet.as_ref(),
+                &swap.env_config,
+                rate_service.clone(),
+                swap.db.as_ref(),
+                checkpoint,
+            );
+
+            match tx_lock {
+                Some(tx_lock) => {
+                    select! {
+                        next_state = next_state => next_state,
+                        _ = checkpoint_lock_confirmations(
+                            swap.db.as_ref(),
+                            swap.swap_id,
+                            swap.bitcoin_wallet.as_ref(),
+                            tx_lock,
+                            swap.env_config.bitcoin_poll_interval,
+                        ) => unreachable!("checkpointing never completes"),
+                    }
+                }
+                None => next_state.await,
+            }
+        };
+
+        let next_state = select! {
+            next_state = next_state => Some(next_state?),
+            _ = aborted, if abortable => None,
+        };
+
+        current_state = match next_state {
+            Some(next_state) => next_state,
+            None => {
+                return abort(
+                    swap.swap_id,
+                    previous_state,
+                    swap.db.as_ref(),
+                    swap.bitcoin_wallet.as_ref(),
+                )
+                .await
+            }
+        };
+
+        if let AliceState::XmrLockTransactionSent {
+            transfer_proof,
+            state3,
+            ..
+        } = &current_state
+        {
+            store_transfer_proof(
+                swap.swap_id,
+                swap.db.as_ref(),
+                swap.monero_wallet.as_ref(),
+                &swap.env_config,
+                transfer_proof,
+                state3,
+            )
+            .await;
+        }
 
         swap.db
             .insert_latest_state(swap.swap_id, current_state.clone().into())
@@ -48,6 +152,204 @@ where
     Ok(current_state)
 }
 
+/// Stops the swap in `state` after it was aborted by its id, see
+/// [`AbortHandle::abort_swap`](crate::asb::AbortHandle::abort_swap).
+async fn abort(
+    swap_id: Uuid,
+    state: AliceState,
+    db: &(dyn Database + Send + Sync),
+    bitcoin_wallet: &bitcoin::Wallet,
+) -> Result<AliceState> {
+    match shut_down_swap(db, bitcoin_wallet, swap_id, &state).await? {
+        Some(ShutdownAction::SafelyAborted) => {
+            tracing::info!("Swap aborted before locking Monero");
+
+            Ok(AliceState::SafelyAborted)
+        }
+        Some(ShutdownAction::Checkpointed {
+            tx_lock_confirmations,
+            recovery,
+            ..
+        }) => {
+            tracing::warn!(%tx_lock_confirmations, "Swap aborted with locked Monero. {}", recovery);
+
+            Ok(state)
+        }
+        None => Ok(state),
+    }
+}
+
+/// Stores the `get_tx_proof` proof of the Monero lock transaction, so that
+/// Bob can verify the lock in any wallet.
+///
+/// The Monero is locked at this point, failing to create or store the proof
+/// must not stop the swap.
+async fn store_transfer_proof(
+    swap_id: Uuid,
+    db: &(dyn Database + Send + Sync),
+    monero_wallet: &monero::Wallet,
+    env_config: &Config,
+    transfer_proof: &TransferProof,
+    state3: &State3,
+) {
+    let TransferRequest {
+        public_spend_key,
+        public_view_key,
+        ..
+    } = state3.lock_xmr_transfer_request();
+    let address = monero::Address::standard(
+        env_config.monero_network,
+        public_spend_key,
+        public_view_key.into(),
+    );
+
+    let result = async {
+        let proof = monero_wallet
+            .tx_proof(&transfer_proof.tx_hash(), address)
+            .await?;
+        db.insert_transfer_proof(swap_id, proof).await
+    }
+    .await;
+
+    if let Err(error) = result {
+        tracing::warn!(%swap_id, "Failed to store proof of the Monero lock transaction: {:#}", error);
+    }
+}
+
+/// Once the cancel timelock expired, the redeem and the cancel transaction
+/// race for the lock output. A swap resumed in the state of the transaction
+/// that lost continues from the one that spent the lock output instead.
+///
+/// Returns `None` if the state matches what happened on chain.
+async fn reconcile(
+    swap_id: Uuid,
+    state: &AliceState,
+    db: &(dyn Database + Send + Sync),
+    bitcoin_wallet: &bitcoin::Wallet,
+) -> Result<Option<AliceState>> {
+    match state {
+        AliceState::BtcRedeemTransactionPublished { state3 } => {
+            let by_txid = match state3.tx_lock.spent_by(bitcoin_wallet).await? {
+                Some(by_txid) if by_txid == state3.tx_cancel().txid() => by_txid,
+                _ => return Ok(None),
+            };
+
+            let cancelled = db
+                .get_states(swap_id)
+                .await?
+                .into_iter()
+                .rev()
+                .find_map(|(_, state)| match state {
+                    State::Alice(AliceState::EncSigLearned {
+                        monero_wallet_restore_blockheight,
+                        transfer_proof,
+                        state3,
+                        ..
+                    }) => Some(AliceState::BtcCancelled {
+                        monero_wallet_restore_blockheight,
+                        transfer_proof,
+                        state3,
+                    }),
+                    _ => None,
+                })
+                .with_context(|| {
+                    format!(
+                        "Bitcoin lock output was spent by the cancel transaction {} but swap {} never learned the encrypted signature",
+                        by_txid, swap_id
+                    )
+                })?;
+
+            tracing::warn!(%by_txid, "Bitcoin lock output was spent by the cancel transaction, not redeeming");
+
+            Ok(Some(cancelled))
+        }
+        AliceState::CancelTimelockExpired { state3, .. }
+        | AliceState::BtcCancelled { state3, .. } => {
+            match state3.tx_lock.spent_by(bitcoin_wallet).await? {
+                Some(by_txid) if by_txid == state3.tx_redeem().txid() => {
+                    tracing::warn!(%by_txid, "Bitcoin lock output was spent by the redeem transaction, not cancelling");
+
+                    Ok(Some(AliceState::BtcRedeemTransactionPublished {
+                        state3: state3.clone(),
+                    }))
+                }
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Records the confirmations of the lock transaction every `interval`, so they
+/// don't have to be observed again after a restart.
+async fn checkpoint_lock_confirmations(
+    db: &(dyn Database + Send + Sync),
+    swap_id: Uuid,
+    bitcoin_wallet: &bitcoin::Wallet,
+    tx_lock: bitcoin::TxLock,
+    interval: Duration,
+) {
+    let mut checkpoint = match db.get_checkpoint(swap_id).await {
+        Ok(checkpoint) => checkpoint.unwrap_or_default(),
+        Err(error) => {
+            tracing::warn!("Failed to load checkpoint: {:#}", error);
+            Checkpoint::default()
+        }
+    };
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let confirmations = match bitcoin_wallet.status_of_script(&tx_lock).await {
+            Ok(ScriptStatus::Confirmed(confirmed)) => confirmed.confirmations(),
+            Ok(_) => continue,
+            Err(error) => {
+                tracing::debug!("Failed to get status of lock transaction: {:#}", error);
+                continue;
+            }
+        };
+
+        if confirmations > checkpoint.tx_lock_confirmations {
+            // The swap records the Monero lock in the same checkpoint, it must not be
+            // overwritten with the one loaded before
+            if let Ok(Some(latest)) = db.get_checkpoint(swap_id).await {
+                checkpoint = latest;
+            }
+            checkpoint.tx_lock_confirmations = checkpoint.tx_lock_confirmations.max(confirmations);
+
+            if let Err(error) = db.insert_checkpoint(swap_id, checkpoint).await {
+                tracing::warn!("Failed to record checkpoint: {:#}", error);
+            }
+        }
+    }
+}
+
+/// Whether the lock transaction is final on chain right now.
+///
+/// A checkpoint alone is not enough to skip waiting for the lock transaction,
+/// it may have been reorged out since or the checkpoint may be stale.
+async fn tx_lock_is_final(
+    bitcoin_wallet: &bitcoin::Wallet,
+    state3: &State3,
+    env_config: &Config,
+) -> Result<bool> {
+    let status = bitcoin_wallet.status_of_script(&state3.tx_lock).await?;
+
+    Ok(status.is_confirmed_with(env_config.bitcoin_finality_confirmations))
+}
+
+/// The transfer that locks the Monero was started before, but the swap did not
+/// record its transfer proof.
+///
+/// The transfer may have been published, attempting it again could send the
+/// Monero twice.
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("The Monero lock transfer of swap {swap_id} was already attempted and may have been published. Check the Monero wallet for a transfer of the swap's Monero amount and run `manual-recovery safely-abort` if none was published")]
+pub struct MoneroLockAlreadyAttempted {
+    pub swap_id: Uuid,
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn next_state<LR>(
     swap_id: Uuid,
     state: AliceState,
@@ -56,6 +358,8 @@ async fn next_state<LR>(
     monero_wallet: &monero::Wallet,
     env_config: &Config,
     mut rate_service: LR,
+    db: &(dyn Database + Send + Sync),
+    checkpoint: Checkpoint,
 ) -> Result<AliceState>
 where
     LR: LatestRate,
@@ -68,13 +372,17 @@ where
 
     Ok(match state {
         AliceState::Started { state3 } => {
-            let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
-            match timeout(
-                env_config.bitcoin_lock_mempool_timeout,
-                tx_lock_status.wait_until_seen(),
-            )
-            .await
-            {
+            let tx_lock_status = bitcoin_wallet
+                .subscribe_to(state3.tx_lock.clone())
+                .await
+                .abort_if_electrum_unavailable();
+            let lock_seen = async {
+                tx_lock_status.wait_until_seen().await?;
+                // Compare what was locked against the agreed amount before we lock our side
+                state3.ensure_lock_amount(bitcoin_wallet).await
+            };
+
+            match timeout(env_config.bitcoin_lock_mempool_timeout, lock_seen).await {
                 Err(_) => {
                     tracing::info!(
                         minutes = %env_config.bitcoin_lock_mempool_timeout.as_secs_f64() / 60.0,
@@ -82,14 +390,34 @@ where
                     );
                     AliceState::SafelyAborted
                 }
-                Ok(res) => {
-                    res?;
-                    AliceState::BtcLockTransactionSeen { state3 }
-                }
+                Ok(Ok(())) => AliceState::BtcLockTransactionSeen { state3 },
+                Ok(Err(error)) => match error.downcast_ref::<LockUnderfunded>() {
+                    Some(underfunded) => {
+                        tracing::warn!(
+                            "Not locking Monero because the Bitcoin lock is underfunded: {}",
+                            underfunded
+                        );
+                        AliceState::SafelyAborted
+                    }
+                    None => return Err(error),
+                },
             }
         }
         AliceState::BtcLockTransactionSeen { state3 } => {
-            let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+            if checkpoint.tx_lock_is_final(env_config.bitcoin_finality_confirmations)
+                && tx_lock_is_final(bitcoin_wallet, &state3, env_config).await?
+            {
+                tracing::info!(
+                    confirmations = %checkpoint.tx_lock_confirmations,
+                    "Bitcoin lock transaction was already final before the restart"
+                );
+                return Ok(AliceState::BtcLocked { state3 });
+            }
+
+            let tx_lock_status = bitcoin_wallet
+                .subscribe_to(state3.tx_lock.clone())
+                .await
+                .abort_if_electrum_unavailable();
             match timeout(
                 env_config.bitcoin_lock_confirmed_timeout,
                 tx_lock_status.wait_until_final(),
@@ -111,16 +439,49 @@ where
             }
         }
         AliceState::BtcLocked { state3 } => {
+            if checkpoint.monero_lock_attempted {
+                return Err(MoneroLockAlreadyAttempted { swap_id }.into());
+            }
+
             match state3.expired_timelocks(bitcoin_wallet).await? {
                 ExpiredTimelocks::None => {
                     // Record the current monero wallet block height so we don't have to scan from
                     // block 0 for scenarios where we create a refund wallet.
                     let monero_wallet_restore_blockheight = monero_wallet.block_height().await?;
 
-                    let transfer_proof = monero_wallet
+                    // The transfer may be published even if we crash before recording the transfer
+                    // proof, so the attempt is recorded first to never send the Monero twice.
+                    let checkpoint = db.get_checkpoint(swap_id).await?.unwrap_or_default();
+                    db.insert_checkpoint(
+                        swap_id,
+                        Checkpoint {
+                            monero_lock_attempted: true,
+                            ..checkpoint
+                        },
+                    )
+                    .await?;
+
+                    let (transfer_proof, lock_fee) = monero_wallet
                         .transfer(state3.lock_xmr_transfer_request())
                         .await?;
 
+                    // The fee is only recorded for the receipt, failing to record it must not
+                    // stop the swap with the Monero sent
+                    let record_lock_fee = async {
+                        let checkpoint = db.get_checkpoint(swap_id).await?.unwrap_or_default();
+                        db.insert_checkpoint(
+                            swap_id,
+                            Checkpoint {
+                                monero_lock_fee: Some(lock_fee),
+                                ..checkpoint
+                            },
+                        )
+                        .await
+                    };
+                    if let Err(error) = record_lock_fee.await {
+                        tracing::warn!(%lock_fee, "Failed to record the Monero lock fee: {:#}", error);
+                    }
+
                     AliceState::XmrLockTransactionSent {
                         monero_wallet_restore_blockheight,
                         transfer_proof,
@@ -164,6 +525,7 @@ where
             state3,
         } => {
             let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+            let tx_cancel_status = bitcoin_wallet.subscribe_to(state3.tx_cancel()).await;
 
             tokio::select! {
                 result = event_loop_handle.send_transfer_proof(transfer_proof.clone()) => {
@@ -183,6 +545,16 @@ where
                         state3,
                     }
                 }
+                result = tx_cancel_status.wait_until_seen() => {
+                    result?;
+                    tracing::info!("Bob published the cancel transaction");
+
+                    AliceState::CancelTimelockExpired {
+                        monero_wallet_restore_blockheight,
+                        transfer_proof,
+                        state3,
+                    }
+                }
             }
         }
         AliceState::XmrLockTransferProofSent {
@@ -191,6 +563,7 @@ where
             state3,
         } => {
             let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+            let tx_cancel_status = bitcoin_wallet.subscribe_to(state3.tx_cancel()).await;
 
             select! {
                 biased; // make sure the cancel timelock expiry future is polled first
@@ -203,6 +576,16 @@ where
                         state3,
                     }
                 }
+                result = tx_cancel_status.wait_until_seen() => {
+                    result?;
+                    tracing::info!("Bob published the cancel transaction");
+
+                    AliceState::CancelTimelockExpired {
+                        monero_wallet_restore_blockheight,
+                        transfer_proof,
+                        state3,
+                    }
+                }
                 enc_sig = event_loop_handle.recv_encrypted_signature() => {
                     tracing::info!("Received encrypted signature");
 
@@ -221,18 +604,45 @@ where
             encrypted_signature,
             state3,
         } => match state3.expired_timelocks(bitcoin_wallet).await? {
-            ExpiredTimelocks::None => {
-                let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
-                match state3.signed_redeem_transaction(*encrypted_signature) {
-                    Ok(tx) => match bitcoin_wallet.broadcast(tx, "redeem").await {
-                        Ok((_, subscription)) => match subscription.wait_until_seen().await {
-                            Ok(_) => AliceState::BtcRedeemTransactionPublished { state3 },
-                            Err(e) => {
-                                bail!("Waiting for Bitcoin redeem transaction to be in mempool failed with {}! The redeem transaction was published, but it is not ensured that the transaction was included! You're screwed.", e)
+            ExpiredTimelocks::None => match state3.tx_lock.ensure_unspent(bitcoin_wallet).await {
+                Ok(()) => {
+                    state3.ensure_lock_amount(bitcoin_wallet).await?;
+
+                    let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+                    match state3.signed_redeem_transaction(*encrypted_signature) {
+                        Ok(tx) => match bitcoin_wallet
+                            .broadcast_with_fee(tx, "redeem", state3.tx_redeem_fee())
+                            .await
+                        {
+                            Ok((_, subscription)) => match subscription.wait_until_seen().await {
+                                Ok(_) => AliceState::BtcRedeemTransactionPublished { state3 },
+                                Err(e) => {
+                                    bail!("Waiting for Bitcoin redeem transaction to be in mempool failed with {}! The redeem transaction was published, but it is not ensured that the transaction was included! You're screwed.", e)
+                                }
+                            },
+                            Err(error) => {
+                                tracing::error!(
+                                    "Failed to publish redeem transaction: {:#}",
+                                    error
+                                );
+                                tx_lock_status
+                                    .wait_until_confirmed_with(state3.cancel_timelock)
+                                    .await?;
+
+                                AliceState::CancelTimelockExpired {
+                                    monero_wallet_restore_blockheight,
+                                    transfer_proof,
+                                    state3,
+                                }
                             }
                         },
                         Err(error) => {
-                            tracing::error!("Failed to publish redeem transaction: {:#}", error);
+                            tracing::error!("Failed to construct redeem transaction: {:#}", error);
+                            tracing::info!(
+                                timelock = %state3.cancel_timelock,
+                                "Waiting for cancellation timelock to expire",
+                            );
+
                             tx_lock_status
                                 .wait_until_confirmed_with(state3.cancel_timelock)
                                 .await?;
@@ -243,32 +653,62 @@ where
                                 state3,
                             }
                         }
-                    },
-                    Err(error) => {
-                        tracing::error!("Failed to construct redeem transaction: {:#}", error);
-                        tracing::info!(
-                            timelock = %state3.cancel_timelock,
-                            "Waiting for cancellation timelock to expire",
-                        );
+                    }
+                }
+                Err(error) => {
+                    let LockAlreadySpent { by_txid } = error.downcast()?;
 
-                        tx_lock_status
-                            .wait_until_confirmed_with(state3.cancel_timelock)
-                            .await?;
+                    if by_txid == state3.tx_redeem().txid() {
+                        AliceState::BtcRedeemTransactionPublished { state3 }
+                    } else if by_txid == state3.tx_cancel().txid() {
+                        tracing::warn!(%by_txid, "Bitcoin lock output was spent by the cancel transaction, not redeeming");
 
-                        AliceState::CancelTimelockExpired {
+                        AliceState::BtcCancelled {
                             monero_wallet_restore_blockheight,
                             transfer_proof,
                             state3,
                         }
+                    } else {
+                        bail!(LockAlreadySpent { by_txid })
                     }
                 }
-            }
+            },
             _ => AliceState::CancelTimelockExpired {
                 monero_wallet_restore_blockheight,
                 transfer_proof,
                 state3,
             },
         },
+        AliceState::BtcRedeemReadyToBroadcast {
+            monero_wallet_restore_blockheight,
+            transfer_proof,
+            state3,
+            ..
+        } => {
+            // The operator broadcasts the redeem transaction through their own node, we only
+            // watch for it
+            let tx_redeem_status = bitcoin_wallet.subscribe_to(state3.tx_redeem()).await;
+            let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+
+            tracing::info!(txid = %state3.tx_redeem().txid(), "Waiting for redeem transaction to be broadcast");
+
+            select! {
+                seen_redeem = tx_redeem_status.wait_until_seen() => {
+                    seen_redeem.context("Failed to monitor redeem transaction")?;
+
+                    AliceState::BtcRedeemTransactionPublished { state3 }
+                }
+                result = tx_lock_status.wait_until_confirmed_with(state3.cancel_timelock) => {
+                    result?;
+
+                    AliceState::CancelTimelockExpired {
+                        monero_wallet_restore_blockheight,
+                        transfer_proof,
+                        state3,
+                    }
+                }
+            }
+        }
         AliceState::BtcRedeemTransactionPublished { state3 } => {
             let subscription = bitcoin_wallet.subscribe_to(state3.tx_redeem()).await;
 
@@ -308,31 +748,43 @@ where
             transfer_proof,
             state3,
         } => {
-            let tx_refund_status = bitcoin_wallet.subscribe_to(state3.tx_refund()).await;
-            let tx_cancel_status = bitcoin_wallet.subscribe_to(state3.tx_cancel()).await;
-
-            select! {
-                seen_refund = tx_refund_status.wait_until_seen() => {
-                    seen_refund.context("Failed to monitor refund transaction")?;
-
-                    let published_refund_tx = bitcoin_wallet.get_raw_transaction(state3.tx_refund().txid()).await?;
-                    let spend_key = state3.extract_monero_private_key(published_refund_tx)?;
+            let mut statuses = SwapWatchSet::new()
+                .watch(SwapTransaction::Refund, &state3.tx_refund())
+                .watch(SwapTransaction::Cancel, &state3.tx_cancel())
+                .subscribe(bitcoin_wallet)
+                .await;
+
+            // Give Bob some more time to refund before punishing him. Manual punishing is not
+            // affected by this.
+            let punish_after = u32::from(state3.punish_timelock) + env_config.punish_grace_blocks;
+
+            loop {
+                match statuses
+                    .recv()
+                    .await
+                    .context("Stopped watching the refund and cancel transactions")?
+                {
+                    (SwapTransaction::Refund, status) if status.has_been_seen() => {
+                        let published_refund_tx = bitcoin_wallet
+                            .get_raw_transaction(state3.tx_refund().txid())
+                            .await?;
+                        let spend_key = state3.extract_monero_private_key(published_refund_tx)?;
 
-                    AliceState::BtcRefunded {
-                        monero_wallet_restore_blockheight,
-                        transfer_proof,
-                        spend_key,
-                        state3,
+                        break AliceState::BtcRefunded {
+                            monero_wallet_restore_blockheight,
+                            transfer_proof,
+                            spend_key,
+                            state3,
+                        };
                     }
-                }
-                result = tx_cancel_status.wait_until_confirmed_with(state3.punish_timelock) => {
-                    result?;
-
-                    AliceState::BtcPunishable {
-                        monero_wallet_restore_blockheight,
-                        transfer_proof,
-                        state3,
+                    (SwapTransaction::Cancel, status) if status.is_confirmed_with(punish_after) => {
+                        break AliceState::BtcPunishable {
+                            monero_wallet_restore_blockheight,
+                            transfer_proof,
+                            state3,
+                        };
                     }
+                    _ => {}
                 }
             }
         }
@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use ::bitcoin::consensus::encode::serialize_hex;
+use anyhow::{bail, Context, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use tokio::sync::{mpsc, watch};
 use uuid::Uuid;
 
 use crate::protocol::Database;
@@ -9,6 +13,7 @@ use crate::{bitcoin, cli, env, monero};
 pub use self::state::*;
 pub use self::swap::{run, run_until};
 use std::convert::TryInto;
+use std::time::Duration;
 
 pub mod state;
 pub mod swap;
@@ -22,6 +27,57 @@ pub struct Swap {
     pub env_config: env::Config,
     pub id: Uuid,
     pub monero_receive_address: monero::Address,
+    pub fee_rate_override: Option<bitcoin::FeeRate>,
+    pub min_rate: Option<monero::Amount>,
+    pub monero_finality_confirmations: Option<u64>,
+    pub encsig_deadline_margin: Option<u32>,
+    pub top_up_timeout: Option<Duration>,
+    pub point_of_no_return: Option<mpsc::UnboundedSender<PointOfNoReturnReached>>,
+    pub cancel_timelock_expired: Option<mpsc::UnboundedSender<CancelTimelockExpired>>,
+    pub abort: Option<watch::Receiver<bool>>,
+}
+
+/// How many blocks before the cancel timelock of the Bitcoin lock transaction
+/// Bob gives up on delivering the encrypted signature to Alice.
+///
+/// Until then Bob keeps redialling Alice if she disconnects after the Monero
+/// was locked. Once the lock transaction has `cancel_timelock - margin`
+/// confirmations, Bob no longer sends the encrypted signature, waits for the
+/// cancel timelock and then cancels and refunds. That way Alice cannot learn
+/// the encrypted signature and redeem right when Bob can cancel.
+///
+/// The margin never exceeds a quarter of the cancel timelock, see
+/// [`encsig_deadline`].
+pub const DEFAULT_ENCSIG_DEADLINE_MARGIN: u32 = 6;
+
+/// The number of confirmations of the Bitcoin lock transaction after which Bob
+/// gives up on delivering the encrypted signature.
+///
+/// The `margin` is capped at a quarter of the `cancel_timelock` so that short
+/// timelocks, e.g. on testnet, still leave Bob most of the window to reach
+/// Alice.
+pub fn encsig_deadline(cancel_timelock: bitcoin::CancelTimelock, margin: u32) -> u32 {
+    let cancel_timelock = u32::from(cancel_timelock);
+
+    cancel_timelock - margin.min(cancel_timelock / 4)
+}
+
+/// Emitted right before Bob publishes the Bitcoin lock transaction. Up to
+/// this point the swap can be abandoned without any funds being moved,
+/// afterwards the Bitcoin can only be recovered through cancel and refund.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointOfNoReturnReached {
+    pub swap_id: Uuid,
+    pub tx_lock_id: bitcoin::Txid,
+}
+
+/// Emitted once the Bitcoin lock transaction reached the cancel timelock, so
+/// the swap can be cancelled. Sent whether or not the swap goes on to cancel
+/// by itself, e.g. to prompt a user who cancels manually before Alice can
+/// punish. Not sent if the swap redeemed or started cancelling before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CancelTimelockExpired {
+    pub swap_id: Uuid,
 }
 
 impl Swap {
@@ -49,9 +105,130 @@ impl Swap {
             env_config,
             id,
             monero_receive_address,
+            fee_rate_override: None,
+            min_rate: None,
+            monero_finality_confirmations: None,
+            encsig_deadline_margin: None,
+            top_up_timeout: None,
+            point_of_no_return: None,
+            cancel_timelock_expired: None,
+            abort: None,
         }
     }
 
+    /// Pay the given fee rate for the Bitcoin transactions whose fees Bob
+    /// chooses (lock, cancel and refund) instead of the estimated fee rate.
+    ///
+    /// The override only takes effect before the swap setup, afterwards the
+    /// fees are fixed.
+    pub fn with_fee_rate_override(mut self, fee_rate: bitcoin::FeeRate) -> Self {
+        self.fee_rate_override = Some(fee_rate);
+        self
+    }
+
+    /// Abort the swap before locking any Bitcoin if Alice offers less than
+    /// `min_rate` XMR per BTC.
+    pub fn with_min_rate(mut self, min_rate: monero::Amount) -> Self {
+        self.min_rate = Some(min_rate);
+        self
+    }
+
+    /// Wait for `confirmations` of the Monero lock transaction before sending
+    /// the encrypted signature, e.g. to require deeper finality for a large
+    /// swap.
+    ///
+    /// Values below the `monero_finality_confirmations` of the [`env::Config`]
+    /// are raised to it. Values beyond the Monero blocks mined on average
+    /// between the Bitcoin lock becoming final and the encrypted signature
+    /// deadline (see [`Swap::with_encsig_deadline_margin`]) are lowered to
+    /// those. The override only takes effect before the swap setup,
+    /// afterwards the confirmations are fixed.
+    pub fn with_monero_finality_confirmations(mut self, confirmations: u64) -> Self {
+        self.monero_finality_confirmations = Some(confirmations);
+        self
+    }
+
+    /// Give up on delivering the encrypted signature `blocks` before the
+    /// cancel timelock expires instead of
+    /// [`DEFAULT_ENCSIG_DEADLINE_MARGIN`] blocks before, see
+    /// [`DEFAULT_ENCSIG_DEADLINE_MARGIN`] for what happens then.
+    pub fn with_encsig_deadline_margin(mut self, blocks: u32) -> Self {
+        self.encsig_deadline_margin = Some(blocks);
+        self
+    }
+
+    /// If the Bitcoin wallet cannot cover the swap amount and the lock fee
+    /// yet, wait for up to `timeout` for a deposit before setting up the swap
+    /// instead of failing right away.
+    pub fn with_top_up_timeout(mut self, timeout: Duration) -> Self {
+        self.top_up_timeout = Some(timeout);
+        self
+    }
+
+    /// Send a [`PointOfNoReturnReached`] event on `sender` right before the
+    /// Bitcoin lock transaction is published, e.g. to show a final warning.
+    pub fn with_point_of_no_return_listener(
+        mut self,
+        sender: mpsc::UnboundedSender<PointOfNoReturnReached>,
+    ) -> Self {
+        self.point_of_no_return = Some(sender);
+        self
+    }
+
+    /// Send a [`CancelTimelockExpired`] event on `sender` once the Bitcoin
+    /// lock transaction reached the cancel timelock.
+    pub fn with_cancel_timelock_listener(
+        mut self,
+        sender: mpsc::UnboundedSender<CancelTimelockExpired>,
+    ) -> Self {
+        self.cancel_timelock_expired = Some(sender);
+        self
+    }
+
+    /// Let `abort_handle` stop this swap by its id, see
+    /// [`cli::AbortHandle::abort_swap`].
+    pub fn with_abort_handle(mut self, abort_handle: &cli::AbortHandle) -> Self {
+        self.abort = Some(abort_handle.register(self.id));
+        self
+    }
+
+    /// The smallest amount of Bitcoin for which swapping at the current quote
+    /// of Alice beats buying Monero at `reference_price`, e.g. the mid price
+    /// of an exchange, in BTC per XMR.
+    ///
+    /// Bob pays the swap amount plus the fee of the Bitcoin lock transaction
+    /// and receives the Monero bought at the quoted price minus the fee of
+    /// moving it out of the swap wallet, valued at `reference_price`. A quote
+    /// below the reference price makes up for the fees from this amount on.
+    /// If the maker's spread puts the quote at or above the reference price,
+    /// no amount breaks even and this fails.
+    pub async fn break_even_amount(
+        &mut self,
+        reference_price: bitcoin::Amount,
+    ) -> Result<bitcoin::Amount> {
+        let quote = self.event_loop_handle.request_quote().await?;
+
+        // Estimated for the largest amount, the fee is capped relative to the
+        // amount and would be underestimated for small ones
+        let tx_lock_fee = self
+            .bitcoin_wallet
+            .estimate_fee_with_override(
+                bitcoin::TxLock::weight(),
+                quote.max_quantity,
+                self.fee_rate_override,
+            )
+            .await?;
+        // The fee of a Monero transaction depends on its size, not on the
+        // amount, so a transfer of a single piconero costs the same as the
+        // sweep out of the swap wallet
+        let monero_fee = self
+            .monero_wallet
+            .estimate_transfer_fee(monero::Amount::from_piconero(1))
+            .await?;
+
+        break_even_amount(quote.price, reference_price, tx_lock_fee, monero_fee)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn from_db(
         db: Arc<dyn Database + Send + Sync>,
@@ -62,7 +239,7 @@ impl Swap {
         event_loop_handle: cli::EventLoopHandle,
         monero_receive_address: monero::Address,
     ) -> Result<Self> {
-        let state = db.get_state(id).await?.try_into()?;
+        let state = db.get_incomplete_state(id).await?.try_into()?;
 
         Ok(Self {
             state,
@@ -73,6 +250,136 @@ impl Swap {
             env_config,
             id,
             monero_receive_address,
+            fee_rate_override: None,
+            min_rate: None,
+            monero_finality_confirmations: None,
+            encsig_deadline_margin: None,
+            top_up_timeout: None,
+            point_of_no_return: None,
+            cancel_timelock_expired: None,
+            abort: None,
         })
     }
+
+    /// The fully signed cancel and refund transactions of swap `swap_id` as
+    /// hex, for the user to keep in custody and broadcast through any node
+    /// once the cancel timelock expired, even if the CLI is gone by then.
+    ///
+    /// Both are available as soon as the Bitcoin is locked. Exporting them
+    /// does not change the state of the swap.
+    pub async fn export_recovery_transactions(
+        db: Arc<dyn Database + Send + Sync>,
+        swap_id: Uuid,
+    ) -> Result<Vec<(ManualBroadcast, String)>> {
+        let state = db.get_incomplete_state(swap_id).await?.try_into()?;
+
+        let state6 = match state {
+            BobState::BtcLocked { state3, .. } => state3.cancel(),
+            BobState::XmrLockProofReceived { state, .. } => state.cancel(),
+            BobState::XmrLocked(state4) | BobState::EncSigSent(state4) => state4.cancel(),
+            BobState::CancelTimelockExpired(state6)
+            | BobState::BtcCancelled(state6)
+            | BobState::ReadyToBroadcast { state: state6, .. } => state6,
+            BobState::Started { .. }
+            | BobState::SwapSetupCompleted(_)
+            | BobState::BtcRedeemed(_)
+            | BobState::BtcRefunded(_)
+            | BobState::XmrRedeemed { .. }
+            | BobState::BtcPunished { .. }
+            | BobState::SafelyAborted => bail!(
+                "Cannot export recovery transactions of swap {} because it is in state {} which has no Bitcoin locked.",
+                swap_id,
+                state
+            ),
+        };
+
+        [ManualBroadcast::Cancel, ManualBroadcast::Refund]
+            .into_iter()
+            .map(|transaction| {
+                let signed_tx = state6.signed_transaction(transaction)?;
+                Ok((transaction, serialize_hex(&signed_tx)))
+            })
+            .collect()
+    }
+}
+
+/// The smallest amount for which the Monero bought at `quoted_price`, minus
+/// `monero_fee` and valued at `reference_price`, is worth more than the
+/// amount plus `tx_lock_fee`.
+fn break_even_amount(
+    quoted_price: bitcoin::Amount,
+    reference_price: bitcoin::Amount,
+    tx_lock_fee: bitcoin::Amount,
+    monero_fee: monero::Amount,
+) -> Result<bitcoin::Amount> {
+    if quoted_price >= reference_price {
+        bail!(
+            "No amount breaks even at a quoted price of {} per XMR, which is not below the reference price of {}",
+            quoted_price,
+            reference_price
+        );
+    }
+
+    let quoted_price = Decimal::from(quoted_price.to_sat());
+    let reference_price = Decimal::from(reference_price.to_sat());
+    let monero_fee_in_sat =
+        monero_fee.as_piconero_decimal() * reference_price / Decimal::from(monero::PICONERO_OFFSET);
+    let fees = Decimal::from(tx_lock_fee.to_sat()) + monero_fee_in_sat;
+
+    // Every satoshi swapped gains `reference_price / quoted_price - 1`
+    let break_even = (fees * quoted_price / (reference_price - quoted_price))
+        .floor()
+        .to_u64()
+        .context("Break-even amount does not fit into a Bitcoin amount")?;
+
+    Ok(bitcoin::Amount::from_sat(break_even + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encsig_deadline_margin_is_capped_at_a_quarter_of_the_cancel_timelock() {
+        assert_eq!(encsig_deadline(bitcoin::CancelTimelock::new(72), 6), 66);
+        assert_eq!(encsig_deadline(bitcoin::CancelTimelock::new(12), 6), 9);
+        assert_eq!(encsig_deadline(bitcoin::CancelTimelock::new(10), 2), 8);
+        assert_eq!(encsig_deadline(bitcoin::CancelTimelock::new(3), 6), 3);
+    }
+
+    #[test]
+    fn break_even_gain_from_quote_below_reference_covers_fees() {
+        // 0.005 BTC per XMR at the exchange, the seller quotes 2% less. The
+        // Monero fee of 0.000016 XMR is worth 8 sat at the reference price.
+        let reference_price = bitcoin::Amount::from_sat(500_000);
+        let quoted_price = bitcoin::Amount::from_sat(490_000);
+
+        let amount = break_even_amount(
+            quoted_price,
+            reference_price,
+            bitcoin::Amount::from_sat(2_000),
+            monero::MONERO_FEE,
+        )
+        .unwrap();
+
+        // 98_392 sat buy Monero worth 100_400 sat at the reference price, minus
+        // the Monero fee that is just the amount plus the lock fee
+        assert_eq!(amount, bitcoin::Amount::from_sat(98_393));
+    }
+
+    #[test]
+    fn given_spread_above_reference_price_no_amount_breaks_even() {
+        let reference_price = bitcoin::Amount::from_sat(500_000);
+        // The maker adds a 2% spread on top of the reference price
+        let quoted_price = bitcoin::Amount::from_sat(510_000);
+
+        let result = break_even_amount(
+            quoted_price,
+            reference_price,
+            bitcoin::Amount::from_sat(2_000),
+            monero::MONERO_FEE,
+        );
+
+        assert!(result.is_err());
+    }
 }
@@ -7,7 +7,11 @@ use crate::monero;
 use crate::monero::wallet::WatchRequest;
 use crate::monero::{monero_private_key, TransferProof};
 use crate::monero_ext::ScalarExt;
-use crate::protocol::{Message0, Message1, Message2, Message3, Message4, CROSS_CURVE_PROOF_SYSTEM};
+use crate::protocol::{
+    Message0, Message1, Message2, Message3, Message4, SwapKeys, SwapTransactions,
+    CROSS_CURVE_PROOF_SYSTEM,
+};
+use ::bitcoin::OutPoint;
 use anyhow::{anyhow, bail, Context, Result};
 use bdk::database::BatchDatabase;
 use ecdsa_fun::adaptor::{Adaptor, HashTranscript};
@@ -43,6 +47,12 @@ pub enum BobState {
     CancelTimelockExpired(State6),
     BtcCancelled(State6),
     BtcRefunded(State6),
+    /// The cancel or refund transaction was handed to the user to broadcast
+    /// it through their own node.
+    ReadyToBroadcast {
+        state: State6,
+        transaction: ManualBroadcast,
+    },
     XmrRedeemed {
         tx_lock_id: bitcoin::Txid,
     },
@@ -67,6 +77,9 @@ impl fmt::Display for BobState {
             BobState::CancelTimelockExpired(..) => write!(f, "cancel timelock is expired"),
             BobState::BtcCancelled(..) => write!(f, "btc is cancelled"),
             BobState::BtcRefunded(..) => write!(f, "btc is refunded"),
+            BobState::ReadyToBroadcast { transaction, .. } => {
+                write!(f, "{} transaction is ready to broadcast", transaction)
+            }
             BobState::XmrRedeemed { .. } => write!(f, "xmr is redeemed"),
             BobState::BtcPunished { .. } => write!(f, "btc is punished"),
             BobState::SafelyAborted => write!(f, "safely aborted"),
@@ -74,6 +87,14 @@ impl fmt::Display for BobState {
     }
 }
 
+/// A transaction that is broadcast by the user instead of the CLI.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ManualBroadcast {
+    Cancel,
+    Refund,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct State0 {
     swap_id: Uuid,
@@ -91,6 +112,8 @@ pub struct State0 {
     min_monero_confirmations: u64,
     tx_refund_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    cancel_anchor: bool,
+    tx_lock_fee_rate: Option<bitcoin::FeeRate>,
 }
 
 impl State0 {
@@ -106,6 +129,8 @@ impl State0 {
         min_monero_confirmations: u64,
         tx_refund_fee: bitcoin::Amount,
         tx_cancel_fee: bitcoin::Amount,
+        cancel_anchor: bool,
+        tx_lock_fee_rate: Option<bitcoin::FeeRate>,
     ) -> Self {
         let b = bitcoin::SecretKey::new_random(rng);
 
@@ -132,6 +157,8 @@ impl State0 {
             min_monero_confirmations,
             tx_refund_fee,
             tx_cancel_fee,
+            cancel_anchor,
+            tx_lock_fee_rate,
         }
     }
 
@@ -146,6 +173,7 @@ impl State0 {
             refund_address: self.refund_address.clone(),
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         }
     }
 
@@ -179,6 +207,7 @@ impl State0 {
             msg.A,
             self.b.public(),
             self.refund_address.clone(),
+            self.tx_lock_fee_rate,
         )
         .await?;
         let v = msg.v_a + self.v_b;
@@ -202,6 +231,7 @@ impl State0 {
             tx_refund_fee: self.tx_refund_fee,
             tx_punish_fee: msg.tx_punish_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         })
     }
 }
@@ -226,6 +256,7 @@ pub struct State1 {
     tx_refund_fee: bitcoin::Amount,
     tx_punish_fee: bitcoin::Amount,
     tx_cancel_fee: bitcoin::Amount,
+    cancel_anchor: bool,
 }
 
 impl State1 {
@@ -236,12 +267,13 @@ impl State1 {
     }
 
     pub fn receive(self, msg: Message3) -> Result<State2> {
-        let tx_cancel = TxCancel::new(
+        let tx_cancel = TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
         let tx_refund =
             bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
@@ -275,6 +307,7 @@ impl State1 {
             tx_refund_fee: self.tx_refund_fee,
             tx_punish_fee: self.tx_punish_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         })
     }
 }
@@ -305,16 +338,31 @@ pub struct State2 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 impl State2 {
+    pub fn swap_keys(&self) -> SwapKeys {
+        SwapKeys {
+            alice_bitcoin: self.A,
+            bob_bitcoin: self.b.public(),
+            alice_monero: self.S_a_monero,
+            bob_monero: monero::PublicKey::from_private_key(&monero::PrivateKey::from_scalar(
+                self.s_b,
+            )),
+            monero_view: monero::PublicKey::from(self.v.public()),
+        }
+    }
+
     pub fn next_message(&self) -> Message4 {
-        let tx_cancel = TxCancel::new(
+        let tx_cancel = TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
         let tx_cancel_sig = self.b.sign(tx_cancel.digest());
         let tx_punish = bitcoin::TxPunish::new(
@@ -352,6 +400,7 @@ impl State2 {
                 tx_redeem_fee: self.tx_redeem_fee,
                 tx_refund_fee: self.tx_refund_fee,
                 tx_cancel_fee: self.tx_cancel_fee,
+                cancel_anchor: self.cancel_anchor,
             },
             self.tx_lock,
         ))
@@ -381,16 +430,14 @@ pub struct State3 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 impl State3 {
     pub fn lock_xmr_watch_request(&self, transfer_proof: TransferProof) -> WatchRequest {
-        let S_b_monero =
-            monero::PublicKey::from_private_key(&monero::PrivateKey::from_scalar(self.s_b));
-        let S = self.S_a_monero + S_b_monero;
-
         WatchRequest {
-            public_spend_key: S,
+            public_spend_key: self.shared_public_spend_key(),
             public_view_key: self.v.public(),
             transfer_proof,
             conf_target: self.min_monero_confirmations,
@@ -398,6 +445,23 @@ impl State3 {
         }
     }
 
+    /// The address Alice has to lock the Monero to, derived from both parties'
+    /// public spend key shares and the shared view key.
+    pub fn lock_xmr_address(&self, network: monero::Network) -> monero::Address {
+        monero::Address::standard(
+            network,
+            self.shared_public_spend_key(),
+            monero::PublicKey::from(self.v.public()),
+        )
+    }
+
+    fn shared_public_spend_key(&self) -> monero::PublicKey {
+        let S_b_monero =
+            monero::PublicKey::from_private_key(&monero::PrivateKey::from_scalar(self.s_b));
+
+        self.S_a_monero + S_b_monero
+    }
+
     pub fn xmr_locked(self, monero_wallet_restore_blockheight: BlockHeight) -> State4 {
         State4 {
             A: self.A,
@@ -416,6 +480,7 @@ impl State3 {
             tx_redeem_fee: self.tx_redeem_fee,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         }
     }
 
@@ -432,6 +497,7 @@ impl State3 {
             tx_refund_encsig: self.tx_refund_encsig.clone(),
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         }
     }
 
@@ -439,17 +505,40 @@ impl State3 {
         self.tx_lock.txid()
     }
 
-    pub async fn current_epoch(
-        &self,
-        bitcoin_wallet: &bitcoin::Wallet,
-    ) -> Result<ExpiredTimelocks> {
-        let tx_cancel = TxCancel::new(
+    pub fn tx_cancel(&self) -> TxCancel {
+        TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
-        );
+            self.cancel_anchor,
+        )
+    }
+
+    pub fn swap_transactions(&self) -> SwapTransactions {
+        let tx_cancel = self.tx_cancel();
+        let tx_redeem =
+            bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+        let tx_refund =
+            bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
+
+        SwapTransactions {
+            btc: self.tx_lock.lock_amount(),
+            xmr: self.xmr,
+            tx_lock: self.tx_lock.clone(),
+            tx_redeem: (tx_redeem.txid(), self.tx_redeem_fee),
+            tx_cancel: (tx_cancel.txid(), self.tx_cancel_fee),
+            tx_refund: (tx_refund.txid(), self.tx_refund_fee),
+            tx_punish: None,
+        }
+    }
+
+    pub async fn current_epoch(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<ExpiredTimelocks> {
+        let tx_cancel = self.tx_cancel();
 
         let tx_lock_status = bitcoin_wallet.status_of_script(&self.tx_lock).await?;
         let tx_cancel_status = bitcoin_wallet.status_of_script(&tx_cancel).await?;
@@ -484,18 +573,21 @@ pub struct State4 {
     tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     tx_cancel_fee: bitcoin::Amount,
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 impl State4 {
+    pub fn tx_redeem(&self) -> bitcoin::TxRedeem {
+        bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee)
+    }
+
     pub fn tx_redeem_encsig(&self) -> bitcoin::EncryptedSignature {
-        let tx_redeem =
-            bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
-        self.b.encsign(self.S_a_bitcoin, tx_redeem.digest())
+        self.b.encsign(self.S_a_bitcoin, self.tx_redeem().digest())
     }
 
     pub async fn watch_for_redeem_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<State5> {
-        let tx_redeem =
-            bitcoin::TxRedeem::new(&self.tx_lock, &self.redeem_address, self.tx_redeem_fee);
+        let tx_redeem = self.tx_redeem();
         let tx_redeem_encsig = self.b.encsign(self.S_a_bitcoin, tx_redeem.digest());
 
         bitcoin_wallet
@@ -520,17 +612,22 @@ impl State4 {
         })
     }
 
-    pub async fn expired_timelock(
-        &self,
-        bitcoin_wallet: &bitcoin::Wallet,
-    ) -> Result<ExpiredTimelocks> {
-        let tx_cancel = TxCancel::new(
+    pub fn tx_cancel(&self) -> TxCancel {
+        TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
-        );
+            self.cancel_anchor,
+        )
+    }
+
+    pub async fn expired_timelock(
+        &self,
+        bitcoin_wallet: &bitcoin::Wallet,
+    ) -> Result<ExpiredTimelocks> {
+        let tx_cancel = self.tx_cancel();
 
         let tx_lock_status = bitcoin_wallet.status_of_script(&self.tx_lock).await?;
         let tx_cancel_status = bitcoin_wallet.status_of_script(&tx_cancel).await?;
@@ -556,6 +653,7 @@ impl State4 {
             tx_refund_encsig: self.tx_refund_encsig,
             tx_refund_fee: self.tx_refund_fee,
             tx_cancel_fee: self.tx_cancel_fee,
+            cancel_anchor: self.cancel_anchor,
         }
     }
 }
@@ -578,6 +676,18 @@ impl State5 {
         (s, self.v)
     }
 
+    /// The address the Monero was locked to, derived from the spend key
+    /// shares of both parties.
+    pub fn lock_xmr_address(&self, network: monero::Network) -> monero::Address {
+        let (s, v) = self.xmr_keys();
+
+        monero::Address::standard(
+            network,
+            monero::PublicKey::from_private_key(&s),
+            monero::PublicKey::from(v.public()),
+        )
+    }
+
     pub fn tx_lock_id(&self) -> bitcoin::Txid {
         self.tx_lock.txid()
     }
@@ -598,6 +708,8 @@ pub struct State6 {
     pub tx_refund_fee: bitcoin::Amount,
     #[serde(with = "::bitcoin::util::amount::serde::as_sat")]
     pub tx_cancel_fee: bitcoin::Amount,
+    #[serde(default)]
+    cancel_anchor: bool,
 }
 
 impl State6 {
@@ -605,12 +717,13 @@ impl State6 {
         &self,
         bitcoin_wallet: &bitcoin::Wallet,
     ) -> Result<ExpiredTimelocks> {
-        let tx_cancel = TxCancel::new(
+        let tx_cancel = TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
 
         let tx_lock_status = bitcoin_wallet.status_of_script(&self.tx_lock).await?;
@@ -628,12 +741,13 @@ impl State6 {
         &self,
         bitcoin_wallet: &bitcoin::Wallet,
     ) -> Result<Transaction> {
-        let tx_cancel = bitcoin::TxCancel::new(
+        let tx_cancel = bitcoin::TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
 
         let tx = bitcoin_wallet.get_raw_transaction(tx_cancel.txid()).await?;
@@ -645,35 +759,71 @@ impl State6 {
         &self,
         bitcoin_wallet: &bitcoin::Wallet,
     ) -> Result<(Txid, Subscription)> {
-        let transaction = bitcoin::TxCancel::new(
+        let transaction = self.signed_cancel_transaction()?;
+
+        if self.cancel_anchor {
+            bitcoin_wallet
+                .broadcast_with_anchor(transaction, "cancel", self.tx_cancel_fee)
+                .await
+        } else {
+            bitcoin_wallet
+                .broadcast_with_fee(transaction, "cancel", self.tx_cancel_fee)
+                .await
+        }
+    }
+
+    pub fn signed_cancel_transaction(&self) -> Result<Transaction> {
+        bitcoin::TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         )
         .complete_as_bob(self.A, self.b.clone(), self.tx_cancel_sig_a.clone())
-        .context("Failed to complete Bitcoin cancel transaction")?;
+        .context("Failed to complete Bitcoin cancel transaction")
+    }
 
-        let (tx_id, subscription) = bitcoin_wallet.broadcast(transaction, "cancel").await?;
+    /// The output of the refund transaction that pays to the refund address
+    /// agreed upon during swap setup.
+    pub fn refund_output(&self) -> Result<OutPoint> {
+        let tx_refund = self.signed_refund_transaction()?;
+        let refund_script = self.refund_address.script_pubkey();
+        let vout = tx_refund
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == refund_script)
+            .context("Refund transaction does not pay to the refund address")?;
+
+        Ok(OutPoint::new(tx_refund.txid(), vout as u32))
+    }
 
-        Ok((tx_id, subscription))
+    /// The fully signed transaction to hand to the user for broadcasting.
+    pub fn signed_transaction(&self, transaction: ManualBroadcast) -> Result<Transaction> {
+        match transaction {
+            ManualBroadcast::Cancel => self.signed_cancel_transaction(),
+            ManualBroadcast::Refund => self.signed_refund_transaction(),
+        }
     }
 
     pub async fn publish_refund_btc(&self, bitcoin_wallet: &bitcoin::Wallet) -> Result<()> {
         let signed_tx_refund = self.signed_refund_transaction()?;
-        bitcoin_wallet.broadcast(signed_tx_refund, "refund").await?;
+        bitcoin_wallet
+            .broadcast_with_fee(signed_tx_refund, "refund", self.tx_refund_fee)
+            .await?;
 
         Ok(())
     }
 
     pub fn signed_refund_transaction(&self) -> Result<Transaction> {
-        let tx_cancel = bitcoin::TxCancel::new(
+        let tx_cancel = bitcoin::TxCancel::build(
             &self.tx_lock,
             self.cancel_timelock,
             self.A,
             self.b.public(),
             self.tx_cancel_fee,
+            self.cancel_anchor,
         );
         let tx_refund =
             bitcoin::TxRefund::new(&tx_cancel, &self.refund_address, self.tx_refund_fee);
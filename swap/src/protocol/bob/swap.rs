@@ -1,13 +1,58 @@
-use crate::bitcoin::{ExpiredTimelocks, TxCancel, TxRefund};
+use crate::bitcoin::wallet::{ScriptStatus, Subscription, SwapTransaction, SwapWatchSet};
+use crate::bitcoin::{CancelTimelock, ExpiredTimelocks, TxCancel, TxLock, TxRefund};
 use crate::cli::EventLoopHandle;
+use crate::database::SwapAlreadyExists;
 use crate::network::swap_setup::bob::NewSwap;
 use crate::protocol::bob;
 use crate::protocol::bob::state::*;
+use crate::protocol::bob::{
+    encsig_deadline, CancelTimelockExpired, PointOfNoReturnReached, DEFAULT_ENCSIG_DEADLINE_MARGIN,
+};
+use crate::protocol::{swap_span, Checkpoint, Database, Role, State};
 use crate::{bitcoin, monero};
 use anyhow::{bail, Context, Result};
+use monero_rpc::wallet::BlockHeight;
+use std::time::Duration;
 use tokio::select;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
 use uuid::Uuid;
 
+/// How long Bob waits for the transfer proof before asking Alice to send it
+/// again, and again after every unsuccessful attempt.
+const TRANSFER_PROOF_RESEND_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The wallet to redeem the Monero from could neither be generated from the
+/// swap's keys nor opened.
+///
+/// The keys are deliberately not part of the error, they would otherwise end
+/// up in the logs. `swap monero-recovery` prints them on demand.
+#[derive(Clone, Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Failed to import the Monero wallet of swap {swap_id} with address {address}, import it manually using the keys printed by `swap monero-recovery --swap-id {swap_id}`")]
+pub struct MoneroWalletImportFailed {
+    pub swap_id: Uuid,
+    pub address: monero::Address,
+}
+
+impl MoneroWalletImportFailed {
+    pub fn new(
+        swap_id: Uuid,
+        network: monero::Network,
+        spend_key: monero::PrivateKey,
+        view_key: monero::PrivateViewKey,
+    ) -> Self {
+        let address = monero::Address::standard(
+            network,
+            monero::PublicKey::from_private_key(&spend_key),
+            monero::PublicKey::from(view_key.public()),
+        );
+
+        Self { swap_id, address }
+    }
+}
+
 pub fn is_complete(state: &BobState) -> bool {
     matches!(
         state,
@@ -24,37 +69,294 @@ pub async fn run(swap: bob::Swap) -> Result<BobState> {
 }
 
 pub async fn run_until(
+    swap: bob::Swap,
+    is_target_state: fn(&BobState) -> bool,
+) -> Result<BobState> {
+    let span = swap_span(swap.id, Role::Bob);
+
+    advance_until(swap, is_target_state).instrument(span).await
+}
+
+async fn advance_until(
     mut swap: bob::Swap,
     is_target_state: fn(&BobState) -> bool,
 ) -> Result<BobState> {
     let mut current_state = swap.state;
 
+    // The id of a new swap can be supplied by the caller, it must not belong to a swap we
+    // already know about.
+    if matches!(current_state, BobState::Started { .. }) && swap.db.get_state(swap.id).await.is_ok()
+    {
+        bail!(SwapAlreadyExists { swap_id: swap.id })
+    }
+
+    if let Some(reconciled) = reconcile(
+        swap.id,
+        &current_state,
+        swap.db.as_ref(),
+        swap.bitcoin_wallet.as_ref(),
+    )
+    .await?
+    {
+        swap.db
+            .insert_latest_state(swap.id, reconciled.clone().into())
+            .await?;
+        current_state = reconciled;
+    }
+
+    let mut cancel_timelock_watcher: Option<JoinHandle<()>> = None;
+
     while !is_target_state(&current_state) {
-        current_state = next_state(
-            swap.id,
-            current_state.clone(),
-            &mut swap.event_loop_handle,
-            swap.bitcoin_wallet.as_ref(),
-            swap.monero_wallet.as_ref(),
-            swap.monero_receive_address,
-        )
-        .await?;
+        if let Some(sender) = swap.cancel_timelock_expired.as_ref() {
+            if let (None, Some((tx_lock, cancel_timelock))) = (
+                cancel_timelock_watcher.as_ref(),
+                locked_until_cancel(&current_state),
+            ) {
+                let tx_lock_status = swap.bitcoin_wallet.subscribe_to(tx_lock.clone()).await;
+                cancel_timelock_watcher = Some(tokio::spawn(notify_cancel_timelock_expired(
+                    swap.id,
+                    tx_lock_status,
+                    cancel_timelock,
+                    sender.clone(),
+                )));
+            }
+        }
+
+        // Aborting while the lock transaction is being published could lose
+        // track of the locked Bitcoin, the swap is stopped once the lock is
+        // recorded instead.
+        let abortable = !matches!(current_state, BobState::SwapSetupCompleted(..));
 
+        let next_state = select! {
+            next_state = next_state(
+                swap.id,
+                current_state.clone(),
+                &mut swap.event_loop_handle,
+                swap.db.as_ref(),
+                swap.bitcoin_wallet.as_ref(),
+                swap.monero_wallet.as_ref(),
+                swap.monero_receive_address,
+                swap.fee_rate_override,
+                swap.min_rate,
+                swap.monero_finality_confirmations,
+                swap.encsig_deadline_margin
+                    .unwrap_or(DEFAULT_ENCSIG_DEADLINE_MARGIN),
+                swap.top_up_timeout,
+                swap.env_config.cancel_anchor,
+                swap.point_of_no_return.as_ref(),
+            ) => Some(next_state?),
+            _ = aborted(swap.abort.clone()), if abortable => None,
+        };
+
+        match next_state {
+            Some(next_state) => current_state = next_state,
+            None => {
+                current_state = abort(swap.id, current_state, swap.db.as_ref()).await?;
+                break;
+            }
+        }
+
+        if let BobState::SwapSetupCompleted(state2) = &current_state {
+            swap.db
+                .insert_swap_keys(swap.id, state2.swap_keys())
+                .await?;
+        }
         swap.db
             .insert_latest_state(swap.id, current_state.clone().into())
             .await?;
+
+        // Once the swap redeemed or started cancelling there is nothing left to notify about
+        if locked_until_cancel(&current_state).is_none() {
+            if let Some(watcher) = cancel_timelock_watcher.take() {
+                watcher.abort();
+            }
+        }
+    }
+
+    if let Some(watcher) = cancel_timelock_watcher {
+        watcher.abort();
     }
 
     Ok(current_state)
 }
 
+/// Completes once the swap was aborted through its
+/// [`AbortHandle`](crate::cli::AbortHandle), never completes if it cannot be
+/// aborted.
+async fn aborted(abort: Option<watch::Receiver<bool>>) {
+    if let Some(mut abort) = abort {
+        while !*abort.borrow_and_update() {
+            if abort.changed().await.is_err() {
+                break;
+            }
+        }
+
+        if *abort.borrow() {
+            return;
+        }
+    }
+
+    std::future::pending().await
+}
+
+/// Stops the swap in `state` after it was aborted by its id, see
+/// [`AbortHandle::abort_swap`](crate::cli::AbortHandle::abort_swap).
+async fn abort(
+    swap_id: Uuid,
+    state: BobState,
+    db: &(dyn Database + Send + Sync),
+) -> Result<BobState> {
+    match state {
+        BobState::Started { .. } => {
+            db.insert_latest_state(swap_id, BobState::SafelyAborted.into())
+                .await?;
+            tracing::info!("Swap aborted before it was set up");
+
+            Ok(BobState::SafelyAborted)
+        }
+        state => {
+            tracing::warn!(%state, "Swap aborted, resume it to continue");
+
+            Ok(state)
+        }
+    }
+}
+
+/// Alice can redeem once the encrypted signature was sent, even after the
+/// cancel timelock expired. A swap resumed in a cancel state continues as
+/// redeemed if it was her redeem transaction that spent the lock output.
+///
+/// Returns `None` if the state matches what happened on chain.
+async fn reconcile(
+    swap_id: Uuid,
+    state: &BobState,
+    db: &(dyn Database + Send + Sync),
+    bitcoin_wallet: &bitcoin::Wallet,
+) -> Result<Option<BobState>> {
+    let state4 = match state {
+        BobState::EncSigSent(state4) => state4.clone(),
+        BobState::CancelTimelockExpired(_) | BobState::BtcCancelled(_) => {
+            let encsig_sent = db.get_states(swap_id).await?.into_iter().rev().find_map(
+                |(_, state)| match state {
+                    State::Bob(BobState::EncSigSent(state4)) => Some(state4),
+                    _ => None,
+                },
+            );
+
+            match encsig_sent {
+                Some(state4) => state4,
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    match state4.tx_lock.spent_by(bitcoin_wallet).await? {
+        Some(by_txid) if by_txid == state4.tx_redeem().txid() => {
+            tracing::warn!(%by_txid, "Bitcoin lock output was spent by the redeem transaction, not cancelling");
+
+            let state5 = state4.watch_for_redeem_btc(bitcoin_wallet).await?;
+            Ok(Some(BobState::BtcRedeemed(state5)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// The lock transaction and cancel timelock of a swap whose Bitcoin is
+/// locked and that has not started cancelling yet.
+fn locked_until_cancel(state: &BobState) -> Option<(&TxLock, CancelTimelock)> {
+    match state {
+        BobState::BtcLocked { state3, .. }
+        | BobState::XmrLockProofReceived { state: state3, .. } => {
+            Some((&state3.tx_lock, state3.cancel_timelock))
+        }
+        BobState::XmrLocked(state4) | BobState::EncSigSent(state4) => {
+            Some((&state4.tx_lock, state4.cancel_timelock))
+        }
+        _ => None,
+    }
+}
+
+/// Sends a [`CancelTimelockExpired`] event once `tx_lock_status` reached the
+/// cancel timelock, independent of what the swap does in the meantime. Stops
+/// early if nobody listens anymore.
+async fn notify_cancel_timelock_expired(
+    swap_id: Uuid,
+    tx_lock_status: Subscription,
+    cancel_timelock: CancelTimelock,
+    sender: UnboundedSender<CancelTimelockExpired>,
+) {
+    select! {
+        result = tx_lock_status.wait_until_confirmed_with(cancel_timelock) => match result {
+            Ok(()) => {
+                tracing::info!(%swap_id, "Cancel timelock expired, the swap can be cancelled");
+                let _ = sender.send(CancelTimelockExpired { swap_id });
+            }
+            Err(error) => {
+                tracing::warn!(%swap_id, "Stopped watching for the cancel timelock: {:#}", error);
+            }
+        },
+        _ = sender.closed() => {}
+    }
+}
+
+/// Whether Alice published the cancel transaction already, in which case Bob
+/// neither waits for the cancel timelock nor publishes it himself.
+async fn is_cancel_published(
+    bitcoin_wallet: &bitcoin::Wallet,
+    tx_cancel: &TxCancel,
+) -> Result<bool> {
+    let published = bitcoin_wallet
+        .status_of_script(tx_cancel)
+        .await?
+        .has_been_seen();
+
+    if published {
+        tracing::info!("Alice published the cancel transaction");
+    }
+
+    Ok(published)
+}
+
+/// Waits until the lock transaction reached the cancel timelock or Alice
+/// published the cancel transaction, and returns which of the two happened.
+async fn wait_until_cancellable(
+    statuses: &mut UnboundedReceiver<(SwapTransaction, ScriptStatus)>,
+    cancel_timelock: CancelTimelock,
+) -> Result<SwapTransaction> {
+    loop {
+        match statuses
+            .recv()
+            .await
+            .context("Stopped watching the lock and cancel transactions")?
+        {
+            (SwapTransaction::Cancel, status) if status.has_been_seen() => {
+                return Ok(SwapTransaction::Cancel)
+            }
+            (SwapTransaction::Lock, status) if status.is_confirmed_with(cancel_timelock) => {
+                return Ok(SwapTransaction::Lock)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn next_state(
     swap_id: Uuid,
     state: BobState,
     event_loop_handle: &mut EventLoopHandle,
+    db: &(dyn Database + Send + Sync),
     bitcoin_wallet: &bitcoin::Wallet,
     monero_wallet: &monero::Wallet,
     monero_receive_address: monero::Address,
+    fee_rate_override: Option<bitcoin::FeeRate>,
+    min_rate: Option<monero::Amount>,
+    monero_finality_confirmations: Option<u64>,
+    encsig_deadline_margin: u32,
+    top_up_timeout: Option<Duration>,
+    cancel_anchor: bool,
+    point_of_no_return: Option<&UnboundedSender<PointOfNoReturnReached>>,
 ) -> Result<BobState> {
     tracing::debug!(%state, "Advancing state");
 
@@ -63,20 +365,56 @@ async fn next_state(
             btc_amount,
             change_address,
         } => {
-            let tx_refund_fee = bitcoin_wallet
-                .estimate_fee(TxRefund::weight(), btc_amount)
+            let mut operation = bitcoin_wallet.operation();
+            let tx_refund_fee = operation
+                .estimate_fee_with_override(TxRefund::weight(), btc_amount, fee_rate_override)
                 .await?;
-            let tx_cancel_fee = bitcoin_wallet
-                .estimate_fee(TxCancel::weight(), btc_amount)
+            let tx_cancel_weight = if cancel_anchor {
+                TxCancel::weight_with_anchor()
+            } else {
+                TxCancel::weight()
+            };
+            let tx_cancel_fee = operation
+                .estimate_fee_with_override(tx_cancel_weight, btc_amount, fee_rate_override)
+                .await?;
+            let tx_lock_fee = operation
+                .estimate_fee_with_override(TxLock::weight(), btc_amount, fee_rate_override)
                 .await?;
 
+            // Fail early instead of during coin selection after the swap has been set up
+            let need = btc_amount + tx_lock_fee;
+            match top_up_timeout {
+                Some(timeout) => {
+                    let have = bitcoin_wallet.spendable_balance().await?;
+                    if have < need {
+                        tracing::info!(
+                            %have,
+                            %need,
+                            missing = %(need - have),
+                            "Waiting for a confirmed deposit to top up the Bitcoin wallet"
+                        );
+                    }
+
+                    bitcoin_wallet
+                        .await_balance(need, timeout)
+                        .await
+                        .context("No deposit topped up the Bitcoin wallet in time")?;
+                }
+                None => bitcoin_wallet.ensure_sufficient_funds(need).await?,
+            }
+
             let state2 = event_loop_handle
                 .setup_swap(NewSwap {
                     swap_id,
                     btc: btc_amount,
                     tx_refund_fee,
                     tx_cancel_fee,
+                    cancel_anchor,
+                    tx_lock_fee_rate: fee_rate_override,
                     bitcoin_refund_address: change_address,
+                    min_rate,
+                    monero_finality_confirmations,
+                    encsig_deadline_margin,
                 })
                 .await?;
 
@@ -102,6 +440,14 @@ async fn next_state(
                 .sign_and_finalize(tx_lock.clone().into())
                 .await
                 .context("Failed to sign Bitcoin lock transaction")?;
+
+            if let Some(sender) = point_of_no_return {
+                // Nobody listening anymore is no reason not to lock the Bitcoin
+                let _ = sender.send(PointOfNoReturnReached {
+                    swap_id,
+                    tx_lock_id: signed_tx.txid(),
+                });
+            }
             let (..) = bitcoin_wallet.broadcast(signed_tx, "lock").await?;
 
             BobState::BtcLocked {
@@ -116,9 +462,13 @@ async fn next_state(
             monero_wallet_restore_blockheight,
         } => {
             let tx_lock_status = bitcoin_wallet.subscribe_to(state3.tx_lock.clone()).await;
+            let tx_cancel_status = bitcoin_wallet.subscribe_to(state3.tx_cancel()).await;
 
-            if let ExpiredTimelocks::None = state3.current_epoch(bitcoin_wallet).await? {
-                let transfer_proof_watcher = event_loop_handle.recv_transfer_proof();
+            if is_cancel_published(bitcoin_wallet, &state3.tx_cancel()).await? {
+                BobState::BtcCancelled(state3.cancel())
+            } else if let ExpiredTimelocks::None = state3.current_epoch(bitcoin_wallet).await? {
+                let transfer_proof_watcher =
+                    event_loop_handle.recv_transfer_proof(TRANSFER_PROOF_RESEND_INTERVAL);
                 let cancel_timelock_expires =
                     tx_lock_status.wait_until_confirmed_with(state3.cancel_timelock);
 
@@ -143,6 +493,12 @@ async fn next_state(
                         let state4 = state3.cancel();
                         BobState::CancelTimelockExpired(state4)
                     },
+                    result = tx_cancel_status.wait_until_seen() => {
+                        result?;
+                        tracing::info!("Alice published the cancel transaction");
+
+                        BobState::BtcCancelled(state3.cancel())
+                    },
                 }
             } else {
                 let state4 = state3.cancel();
@@ -155,8 +511,11 @@ async fn next_state(
             monero_wallet_restore_blockheight,
         } => {
             let tx_lock_status = bitcoin_wallet.subscribe_to(state.tx_lock.clone()).await;
+            let tx_cancel_status = bitcoin_wallet.subscribe_to(state.tx_cancel()).await;
 
-            if let ExpiredTimelocks::None = state.current_epoch(bitcoin_wallet).await? {
+            if is_cancel_published(bitcoin_wallet, &state.tx_cancel()).await? {
+                BobState::BtcCancelled(state.cancel())
+            } else if let ExpiredTimelocks::None = state.current_epoch(bitcoin_wallet).await? {
                 let watch_request = state.lock_xmr_watch_request(lock_transfer_proof);
 
                 select! {
@@ -177,6 +536,12 @@ async fn next_state(
                         result?;
                         BobState::CancelTimelockExpired(state.cancel())
                     }
+                    result = tx_cancel_status.wait_until_seen() => {
+                        result?;
+                        tracing::info!("Alice published the cancel transaction");
+
+                        BobState::BtcCancelled(state.cancel())
+                    }
                 }
             } else {
                 BobState::CancelTimelockExpired(state.cancel())
@@ -184,39 +549,79 @@ async fn next_state(
         }
         BobState::XmrLocked(state) => {
             let tx_lock_status = bitcoin_wallet.subscribe_to(state.tx_lock.clone()).await;
+            let tx_cancel_status = bitcoin_wallet.subscribe_to(state.tx_cancel()).await;
 
-            if let ExpiredTimelocks::None = state.expired_timelock(bitcoin_wallet).await? {
+            if is_cancel_published(bitcoin_wallet, &state.tx_cancel()).await? {
+                BobState::BtcCancelled(state.cancel())
+            } else if let ExpiredTimelocks::None = state.expired_timelock(bitcoin_wallet).await? {
                 // Alice has locked Xmr
                 // Bob sends Alice his key
 
+                let encsig_deadline =
+                    encsig_deadline(state.cancel_timelock, encsig_deadline_margin);
+
                 select! {
                     result = event_loop_handle.send_encrypted_signature(state.tx_redeem_encsig()) => {
                         match result {
                             Ok(_) => BobState::EncSigSent(state),
-                            Err(bmrng::error::RequestError::RecvError | bmrng::error::RequestError::SendError(_)) => bail!("Failed to communicate encrypted signature through event loop channel"),
+                            Err(bmrng::error::RequestError::RecvError) => {
+                                // The event loop dropped the request without Alice acknowledging it
+                                tracing::warn!("Failed to deliver the encrypted signature, sending it again");
+
+                                BobState::XmrLocked(state)
+                            }
+                            Err(bmrng::error::RequestError::SendError(_)) => {
+                                tracing::warn!(timelock = %state.cancel_timelock, "Failed to communicate encrypted signature through event loop channel, waiting for cancel timelock to expire");
+
+                                tx_lock_status.wait_until_confirmed_with(state.cancel_timelock).await?;
+
+                                BobState::CancelTimelockExpired(state.cancel())
+                            }
                             Err(bmrng::error::RequestError::RecvTimeoutError) => unreachable!("We construct the channel with no timeout"),
                         }
                     },
-                    result = tx_lock_status.wait_until_confirmed_with(state.cancel_timelock) => {
+                    result = tx_lock_status.wait_until_confirmed_with(encsig_deadline) => {
                         result?;
+                        tracing::warn!(timelock = %state.cancel_timelock, margin = encsig_deadline_margin, "Could not deliver the encrypted signature before the cancel timelock approached, waiting for it to expire to refund");
+
+                        tx_lock_status.wait_until_confirmed_with(state.cancel_timelock).await?;
+
                         BobState::CancelTimelockExpired(state.cancel())
                     }
+                    result = tx_cancel_status.wait_until_seen() => {
+                        result?;
+                        tracing::info!("Alice published the cancel transaction");
+
+                        BobState::BtcCancelled(state.cancel())
+                    }
                 }
             } else {
                 BobState::CancelTimelockExpired(state.cancel())
             }
         }
         BobState::EncSigSent(state) => {
-            let tx_lock_status = bitcoin_wallet.subscribe_to(state.tx_lock.clone()).await;
+            let mut statuses = SwapWatchSet::new()
+                .watch(SwapTransaction::Lock, &state.tx_lock)
+                .watch(SwapTransaction::Cancel, &state.tx_cancel())
+                .subscribe(bitcoin_wallet)
+                .await;
 
-            if let ExpiredTimelocks::None = state.expired_timelock(bitcoin_wallet).await? {
+            if is_cancel_published(bitcoin_wallet, &state.tx_cancel()).await? {
+                BobState::BtcCancelled(state.cancel())
+            } else if let ExpiredTimelocks::None = state.expired_timelock(bitcoin_wallet).await? {
                 select! {
                     state5 = state.watch_for_redeem_btc(bitcoin_wallet) => {
                         BobState::BtcRedeemed(state5?)
                     },
-                    result = tx_lock_status.wait_until_confirmed_with(state.cancel_timelock) => {
-                        result?;
-                        BobState::CancelTimelockExpired(state.cancel())
+                    transaction = wait_until_cancellable(&mut statuses, state.cancel_timelock) => {
+                        match transaction? {
+                            SwapTransaction::Cancel => {
+                                tracing::info!("Alice published the cancel transaction");
+
+                                BobState::BtcCancelled(state.cancel())
+                            }
+                            _ => BobState::CancelTimelockExpired(state.cancel()),
+                        }
                     }
                 }
             } else {
@@ -226,35 +631,41 @@ async fn next_state(
         BobState::BtcRedeemed(state) => {
             let (spend_key, view_key) = state.xmr_keys();
 
-            let wallet_file_name = swap_id.to_string();
-            if let Err(e) = monero_wallet
-                .create_from_and_load(
-                    wallet_file_name.clone(),
-                    spend_key,
-                    view_key,
-                    state.monero_wallet_restore_blockheight,
-                )
-                .await
-            {
-                // In case we failed to refresh/sweep, when resuming the wallet might already
-                // exist! This is a very unlikely scenario, but if we don't take care of it we
-                // might not be able to ever transfer the Monero.
-                tracing::warn!("Failed to generate monero wallet from keys: {:#}", e);
-                tracing::info!(%wallet_file_name,
-                    "Falling back to trying to open the the wallet if it already exists",
-                );
-                monero_wallet.open(wallet_file_name).await?;
-            }
+            import_redeem_wallet(
+                monero_wallet,
+                swap_id,
+                monero_receive_address.network,
+                spend_key,
+                view_key,
+                state.monero_wallet_restore_blockheight,
+            )
+            .await?;
 
             // Ensure that the generated wallet is synced so we have a proper balance
             monero_wallet.refresh().await?;
             // Sweep (transfer all funds) to the given address
-            let tx_hashes = monero_wallet.sweep_all(monero_receive_address).await?;
+            let (tx_hashes, redeem_fee) = monero_wallet.sweep_all(monero_receive_address).await?;
 
             for tx_hash in tx_hashes {
                 tracing::info!(%monero_receive_address, txid=%tx_hash.0, "Successfully transferred XMR to wallet");
             }
 
+            // The fee is only recorded for the receipt, the Monero is redeemed either way
+            let record_redeem_fee = async {
+                let checkpoint = db.get_checkpoint(swap_id).await?.unwrap_or_default();
+                db.insert_checkpoint(
+                    swap_id,
+                    Checkpoint {
+                        monero_redeem_fee: Some(redeem_fee),
+                        ..checkpoint
+                    },
+                )
+                .await
+            };
+            if let Err(error) = record_redeem_fee.await {
+                tracing::warn!(%redeem_fee, "Failed to record the Monero redeem fee: {:#}", error);
+            }
+
             BobState::XmrRedeemed {
                 tx_lock_id: state.tx_lock_id(),
             }
@@ -286,9 +697,151 @@ async fn next_state(
                 }
             }
         }
+        BobState::ReadyToBroadcast { state, transaction } => {
+            // The user broadcasts the transaction through their own node, we only watch for it
+            let signed_tx = state.signed_transaction(transaction)?;
+            let txid = signed_tx.txid();
+
+            tracing::info!(%txid, "Waiting for {} transaction to be broadcast", transaction);
+
+            bitcoin_wallet
+                .subscribe_to((txid, signed_tx.output[0].script_pubkey.clone()))
+                .await
+                .wait_until_seen()
+                .await?;
+
+            match transaction {
+                ManualBroadcast::Cancel => BobState::BtcCancelled(state),
+                ManualBroadcast::Refund => BobState::BtcRefunded(state),
+            }
+        }
         BobState::BtcRefunded(state4) => BobState::BtcRefunded(state4),
         BobState::BtcPunished { tx_lock_id } => BobState::BtcPunished { tx_lock_id },
         BobState::SafelyAborted => BobState::SafelyAborted,
         BobState::XmrRedeemed { tx_lock_id } => BobState::XmrRedeemed { tx_lock_id },
     })
 }
+
+/// Generate the wallet to redeem the Monero from using the swap's keys, or
+/// open it if it was already generated on a previous run.
+async fn import_redeem_wallet(
+    monero_wallet: &monero::Wallet,
+    swap_id: Uuid,
+    network: monero::Network,
+    spend_key: monero::PrivateKey,
+    view_key: monero::PrivateViewKey,
+    restore_height: BlockHeight,
+) -> Result<()> {
+    let wallet_file_name = swap_id.to_string();
+    if let Err(e) = monero_wallet
+        .create_from_and_load(
+            wallet_file_name.clone(),
+            spend_key,
+            view_key,
+            restore_height,
+        )
+        .await
+    {
+        // In case we failed to refresh/sweep, when resuming the wallet might already
+        // exist! This is a very unlikely scenario, but if we don't take care of it we
+        // might not be able to ever transfer the Monero.
+        tracing::warn!("Failed to generate monero wallet from keys: {:#}", e);
+        tracing::info!(%wallet_file_name,
+            "Falling back to trying to open the the wallet if it already exists",
+        );
+        if let Err(e) = monero_wallet.open(wallet_file_name).await {
+            tracing::warn!("Failed to open monero wallet: {:#}", e);
+
+            // The keys are part of the BtcRedeemed state which is already persisted,
+            // point the user to the command that prints them.
+            let error = MoneroWalletImportFailed::new(swap_id, network, spend_key, view_key);
+            tracing::error!(
+                %swap_id,
+                address = %error.address,
+                "Failed to import Monero redeem wallet, print the keys to import it manually using `swap monero-recovery --swap-id {}`",
+                swap_id
+            );
+            bail!(error)
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::wallet::ScriptStatus;
+    use crate::tracing_ext::capture_logs;
+    use tokio::sync::mpsc;
+    use tokio::sync::mpsc::error::TryRecvError;
+    use tracing::metadata::LevelFilter;
+
+    #[tokio::test]
+    async fn cancel_timelock_expired_is_sent_once_lock_reaches_cancel_timelock() -> Result<()> {
+        let swap_id = Uuid::new_v4();
+        let cancel_timelock = CancelTimelock::new(3);
+        let (status, _failed_reconnects, tx_lock_status) = Subscription::stub(0);
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let watcher = tokio::spawn(notify_cancel_timelock_expired(
+            swap_id,
+            tx_lock_status,
+            cancel_timelock,
+            sender,
+        ));
+
+        for confirmations in 0..3 {
+            status.send(ScriptStatus::from_confirmations(confirmations))?;
+            tokio::task::yield_now().await;
+            assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        }
+
+        status.send(ScriptStatus::from_confirmations(3))?;
+        watcher.await?;
+
+        assert_eq!(receiver.try_recv(), Ok(CancelTimelockExpired { swap_id }));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_wallet_import_does_not_log_keys() {
+        let writer = capture_logs(LevelFilter::DEBUG);
+
+        let swap_id = Uuid::new_v4();
+        let spend_key = monero::PrivateKey {
+            scalar: monero::Scalar::random(&mut rand::thread_rng()),
+        };
+        let view_key = monero::PrivateViewKey::new_random(&mut rand::thread_rng());
+        // nothing listens on this port, generating and opening the wallet both fail
+        let monero_wallet = monero::Wallet::unreachable(
+            "http://127.0.0.1:1".parse().unwrap(),
+            monero::Network::Stagenet,
+        );
+
+        let error = import_redeem_wallet(
+            &monero_wallet,
+            swap_id,
+            monero::Network::Stagenet,
+            spend_key,
+            view_key,
+            BlockHeight { height: 1_234 },
+        )
+        .await
+        .unwrap_err();
+
+        let error = error.downcast::<MoneroWalletImportFailed>().unwrap();
+        let message = error.to_string();
+        let logs = writer.captured();
+        let view_key = monero::PrivateKey::from(view_key);
+
+        assert!(message.contains(&error.address.to_string()));
+        assert!(message.contains(&format!("swap monero-recovery --swap-id {}", swap_id)));
+        assert!(logs.contains(&error.address.to_string()));
+        for output in [&message, &logs] {
+            assert!(!output.contains(&spend_key.to_string()));
+            assert!(!output.contains(&view_key.to_string()));
+        }
+    }
+}
@@ -30,6 +30,14 @@ impl Seed {
         Ok(Seed(bytes))
     }
 
+    /// A seed that is always the same for the same `tag`, so that tests can
+    /// pin keys, addresses and peer ids.
+    ///
+    /// Never use it for real funds, anyone who knows `tag` knows the seed.
+    pub fn deterministic(tag: &[u8]) -> Self {
+        Seed(sha256::Hash::hash(tag).into_inner())
+    }
+
     pub fn derive_extended_private_key(
         &self,
         network: bitcoin::Network,
@@ -56,6 +64,12 @@ impl Seed {
         esk.to_bytes().into()
     }
 
+    /// The key swap backups are encrypted with, so they can be restored on
+    /// any machine that has the same seed.
+    pub fn derive_swap_backup_key(&self) -> [u8; 32] {
+        self.derive(b"SWAP_BACKUP").bytes()
+    }
+
     pub fn from_file_or_generate(data_dir: &Path) -> Result<Self, Error> {
         let file_path_buf = data_dir.join("seed.pem");
         let file_path = Path::new(&file_path_buf);
@@ -92,7 +106,9 @@ impl Seed {
         self.0
     }
 
-    fn from_file<D>(seed_file: D) -> Result<Self, Error>
+    /// Reads the seed from `seed_file`, failing if there is none instead of
+    /// generating a new one.
+    pub fn from_file<D>(seed_file: D) -> Result<Self, Error>
     where
         D: AsRef<OsStr>,
     {
@@ -171,6 +187,8 @@ pub enum Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ::bitcoin::secp256k1::Secp256k1;
+    use libp2p::PeerId;
     use std::env::temp_dir;
 
     #[test]
@@ -183,6 +201,30 @@ mod tests {
         let _seed = Seed::from(*b"this string is exactly 32 bytes!");
     }
 
+    #[test]
+    fn same_bytes_always_yield_same_peer_id_and_bitcoin_address() {
+        let peer_id = |seed: &Seed| PeerId::from(seed.derive_libp2p_identity().public());
+        let address = |seed: &Seed| {
+            let xprivkey = seed
+                .derive_extended_private_key(bitcoin::Network::Regtest)
+                .unwrap();
+            let public_key =
+                bitcoin::PublicKey::new(xprivkey.private_key.public_key(&Secp256k1::new()));
+
+            bitcoin::Address::p2wpkh(&public_key, bitcoin::Network::Regtest).unwrap()
+        };
+
+        let seed = Seed::deterministic(b"alice");
+        let same_bytes = Seed::from(sha256::Hash::hash(b"alice").into_inner());
+        let other = Seed::deterministic(b"bob");
+
+        assert_eq!(seed, Seed::deterministic(b"alice"));
+        assert_eq!(peer_id(&seed), peer_id(&same_bytes));
+        assert_eq!(address(&seed), address(&same_bytes));
+        assert_ne!(peer_id(&seed), peer_id(&other));
+        assert_ne!(address(&seed), address(&other));
+    }
+
     #[test]
     fn seed_from_pem_works() {
         use base64::engine::general_purpose;
@@ -253,4 +295,12 @@ dWWSQ0nRGt2hOPDO+35NKhQEjBQxPh/v7n0CAwEAAQJBAOGaBAyuw0ICyENy5NsO
         let rinsed = Seed::from_file(tmpfile).expect("Read from temp file");
         assert_eq!(seed.0, rinsed.0);
     }
+
+    #[test]
+    fn from_file_does_not_generate_missing_seed() {
+        let tmpfile = temp_dir().join("missing_seed.pem");
+
+        assert!(Seed::from_file(&tmpfile).is_err());
+        assert!(!tmpfile.exists());
+    }
 }
@@ -0,0 +1,59 @@
+pub mod harness;
+
+use harness::bob_run_until::is_xmr_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database, State};
+
+/// Two swaps are in flight, aborting the first one by its id leaves its
+/// locked Monero checkpointed and lets the second one finish.
+#[tokio::test]
+async fn given_two_swaps_when_one_is_aborted_then_the_other_continues() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap_1, bob_join_handle_1) = ctx.bob_swap().await;
+        let bob_swap_1 = tokio::spawn(bob::run_until(bob_swap_1, is_xmr_locked));
+
+        let alice_swap_1 = ctx.alice_next_swap().await;
+        let aborted_swap_id = alice_swap_1.swap_id;
+        let alice_db = alice_swap_1.db.clone();
+        let alice_swap_1 = tokio::spawn(alice::run(alice_swap_1, FixedRate::default()));
+
+        let bob_state_1 = bob_swap_1.await??;
+        assert!(matches!(bob_state_1, BobState::XmrLocked { .. }));
+
+        // Without Bob the first swap waits for the encrypted signature
+        bob_join_handle_1.abort();
+
+        let (bob_swap_2, _) = ctx.bob_swap().await;
+        let bob_swap_2 = tokio::spawn(bob::run(bob_swap_2));
+
+        let alice_swap_2 = ctx.alice_next_swap().await;
+        let alice_swap_2 = tokio::spawn(alice::run(alice_swap_2, FixedRate::default()));
+
+        let abort_handle = ctx.alice_abort_handle();
+        assert!(abort_handle.abort_swap(aborted_swap_id));
+
+        let alice_state_1 = alice_swap_1.await??;
+        assert!(matches!(
+            alice_state_1,
+            AliceState::XmrLocked { .. } | AliceState::XmrLockTransferProofSent { .. }
+        ));
+        assert_eq!(
+            alice_db.get_state(aborted_swap_id).await?,
+            State::Alice(alice_state_1)
+        );
+        assert!(alice_db.get_checkpoint(aborted_swap_id).await?.is_some());
+        assert!(!abort_handle.abort_swap(aborted_swap_id));
+
+        let bob_state_2 = bob_swap_2.await??;
+        ctx.assert_bob_redeemed(bob_state_2).await;
+
+        let alice_state_2 = alice_swap_2.await??;
+        assert!(matches!(alice_state_2, AliceState::BtcRedeemed));
+
+        Ok(())
+    })
+    .await;
+}
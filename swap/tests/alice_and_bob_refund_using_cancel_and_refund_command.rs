@@ -60,7 +60,8 @@ async fn given_alice_and_bob_manually_refund_after_funds_locked_both_refund() {
 
         // Bob manually refunds
         bob_join_handle.abort();
-        let bob_state = cli::refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db).await?;
+        let bob_state =
+            cli::refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db, None).await?;
 
         ctx.assert_bob_refunded(bob_state).await;
 
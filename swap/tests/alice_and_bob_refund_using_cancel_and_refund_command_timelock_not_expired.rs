@@ -69,7 +69,7 @@ async fn given_alice_and_bob_manually_cancel_when_timelock_not_expired_errors()
         assert!(matches!(bob_swap.state, BobState::BtcLocked { .. }));
 
         // Bob tries but fails to manually refund
-        let error = cli::refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db)
+        let error = cli::refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db, None)
             .await
             .unwrap_err();
         assert_eq!(
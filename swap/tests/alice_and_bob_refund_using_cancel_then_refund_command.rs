@@ -51,7 +51,7 @@ async fn given_alice_and_bob_manually_cancel_and_refund_after_funds_locked_both_
         // Bob manually cancels and refunds
         bob_join_handle.abort();
         let bob_state =
-            cli::cancel_and_refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db).await?;
+            cli::cancel_and_refund(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db, None).await?;
 
         ctx.assert_bob_refunded(bob_state).await;
 
@@ -0,0 +1,133 @@
+pub mod harness;
+
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::Transaction;
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::{BobState, ManualBroadcast};
+use swap::protocol::{alice, bob};
+use swap::{asb, cli};
+
+#[tokio::test]
+async fn given_bob_broadcasts_cancel_and_refund_manually_both_refund() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let alice_state = alice_swap.await??;
+        assert!(matches!(
+            alice_state,
+            AliceState::XmrLockTransactionSent { .. }
+        ));
+
+        let (bob_swap, bob_join_handle) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+
+        // Ensure cancel timelock is expired
+        if let BobState::BtcLocked { state3, .. } = bob_swap.state.clone() {
+            bob_swap
+                .bitcoin_wallet
+                .subscribe_to(state3.tx_lock)
+                .await
+                .wait_until_confirmed_with(state3.cancel_timelock)
+                .await?;
+        } else {
+            panic!("Bob in unexpected state {}", bob_swap.state);
+        }
+        bob_join_handle.abort();
+
+        // Bob broadcasts the cancel transaction himself
+        let (tx_cancel, state) = cli::manual_broadcast(bob_swap.id, bob_swap.db.clone()).await?;
+        assert!(matches!(
+            state,
+            BobState::ReadyToBroadcast {
+                transaction: ManualBroadcast::Cancel,
+                ..
+            }
+        ));
+        let tx_cancel = roundtrip_hex(&tx_cancel);
+        bob_swap
+            .bitcoin_wallet
+            .broadcast(tx_cancel, "cancel")
+            .await?
+            .1
+            .wait_until_seen()
+            .await?;
+
+        let state = cli::confirm_broadcast(
+            bob_swap.id,
+            bob_swap.bitcoin_wallet.clone(),
+            bob_swap.db.clone(),
+        )
+        .await?;
+        assert!(matches!(state, BobState::BtcCancelled(..)));
+
+        // Bob broadcasts the refund transaction himself
+        let (tx_refund, state) = cli::manual_broadcast(bob_swap.id, bob_swap.db.clone()).await?;
+        assert!(matches!(
+            state,
+            BobState::ReadyToBroadcast {
+                transaction: ManualBroadcast::Refund,
+                ..
+            }
+        ));
+        let tx_refund = roundtrip_hex(&tx_refund);
+        bob_swap
+            .bitcoin_wallet
+            .broadcast(tx_refund, "refund")
+            .await?
+            .1
+            .wait_until_seen()
+            .await?;
+
+        let bob_state =
+            cli::confirm_broadcast(bob_swap.id, bob_swap.bitcoin_wallet, bob_swap.db).await?;
+        ctx.assert_bob_refunded(bob_state).await;
+
+        // manually refund Alice's swap
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_state = asb::refund(
+            alice_swap.swap_id,
+            alice_swap.bitcoin_wallet,
+            alice_swap.monero_wallet,
+            alice_swap.db,
+        )
+        .await?;
+
+        ctx.assert_alice_refunded(alice_state).await;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Passes the transaction through its hex encoding, as the user would, and
+/// checks that every input is signed.
+fn roundtrip_hex(transaction: &Transaction) -> Transaction {
+    let bytes = Vec::<u8>::from_hex(&serialize_hex(transaction)).unwrap();
+    let transaction: Transaction = deserialize(&bytes).unwrap();
+
+    assert!(transaction
+        .input
+        .iter()
+        .all(|input| !input.witness.is_empty()));
+
+    transaction
+}
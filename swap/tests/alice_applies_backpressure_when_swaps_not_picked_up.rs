@@ -0,0 +1,32 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::protocol::bob;
+
+#[tokio::test]
+async fn given_stalled_swap_consumer_then_alice_quotes_zero_until_swap_is_picked_up() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        ctx.restart_alice_with_swap_channel_capacity(1).await;
+
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+
+        // Nobody picks up the swap Alice started, which fills the channel
+        bob::run_until(bob_swap, is_btc_locked).await?;
+
+        let (mut other_bob_swap, _) = ctx.bob_swap().await;
+        let backlogged_quote = other_bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(backlogged_quote.max_quantity, bitcoin::Amount::ZERO);
+
+        // The started swap was held back, not dropped
+        let alice_swap = ctx.alice_next_swap().await;
+        assert_eq!(alice_swap.swap_id, bob_swap_id);
+
+        let quote = other_bob_swap.event_loop_handle.request_quote().await?;
+        assert!(quote.max_quantity > bitcoin::Amount::ZERO);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,56 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::monero;
+use swap::network::swap_setup::bob::Error;
+use swap::protocol::{alice, bob};
+use tokio::join;
+
+/// The swap amount of the harness converts to 1 XMR. With a minimum Monero
+/// output above that Alice quotes and declines everything below the Bitcoin
+/// the minimum takes, before Bob locks his Bitcoin. With a minimum below it
+/// the swap goes through.
+#[tokio::test]
+async fn given_monero_amount_below_minimum_output_then_swap_is_declined() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        ctx.restart_alice_with_min_monero_output(Some(monero::Amount::from_monero(2.0)?))
+            .await;
+        let min_buy = ctx.btc_amount() * 2;
+
+        let (mut bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let quote = bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(quote.min_quantity, min_buy);
+
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let balance_before = bob_bitcoin_wallet.balance().await?;
+
+        let error = bob::run(bob_swap).await.unwrap_err();
+        assert!(matches!(
+            error.downcast::<Error>()?,
+            Error::AmountBelowMinimum { min, buy }
+                if min == min_buy && buy == ctx.btc_amount()
+        ));
+        bob_join_handle.abort();
+
+        bob_bitcoin_wallet.sync().await?;
+        assert_eq!(bob_bitcoin_wallet.balance().await?, balance_before);
+
+        ctx.restart_alice_with_min_monero_output(Some(monero::Amount::from_monero(0.5)?))
+            .await;
+
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let (bob_state, alice_state) = join!(bob_swap, alice_swap);
+
+        ctx.assert_alice_redeemed(alice_state??).await;
+        ctx.assert_bob_redeemed(bob_state??).await;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,37 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::monero;
+use swap::network::swap_setup::bob::Error;
+use swap::protocol::bob;
+
+/// Alice has enough unlocked Monero for the swap, but keeps most of it in
+/// reserve. She declines the swap before Bob locks his Bitcoin.
+#[tokio::test]
+async fn given_monero_kept_in_reserve_then_swap_is_declined() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let unlocked = ctx.alice_unlocked_xmr_balance().await?;
+        assert!(unlocked > ctx.xmr_amount());
+        let reserve = monero::Amount::from_piconero(
+            unlocked.as_piconero() - ctx.xmr_amount().as_piconero() / 2,
+        );
+        ctx.restart_alice_with_monero_reserve(Some(reserve)).await;
+
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let balance_before = bob_bitcoin_wallet.balance().await?;
+
+        let error = bob::run(bob_swap).await.unwrap_err();
+        assert!(matches!(
+            error.downcast::<Error>()?,
+            Error::BalanceTooLow { buy } if buy == ctx.btc_amount()
+        ));
+        bob_join_handle.abort();
+
+        bob_bitcoin_wallet.sync().await?;
+        assert_eq!(bob_bitcoin_wallet.balance().await?, balance_before);
+
+        Ok(())
+    })
+    .await;
+}
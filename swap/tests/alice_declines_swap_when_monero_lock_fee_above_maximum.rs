@@ -0,0 +1,34 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::monero;
+use swap::network::swap_setup::bob::Error;
+use swap::protocol::bob;
+
+/// Any real Monero transaction pays more than one piconero, so with that
+/// maximum Alice declines during the swap setup and Bob never locks his
+/// Bitcoin.
+#[tokio::test]
+async fn given_monero_lock_fee_above_maximum_then_swap_is_declined_before_btc_lock() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        ctx.restart_alice_with_max_monero_lock_fee(Some(monero::Amount::from_piconero(1)))
+            .await;
+
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let balance_before = bob_bitcoin_wallet.balance().await?;
+
+        let error = bob::run(bob_swap).await.unwrap_err();
+        assert!(matches!(
+            error.downcast::<Error>()?,
+            Error::TemporarilyUnavailable
+        ));
+        bob_join_handle.abort();
+
+        bob_bitcoin_wallet.sync().await?;
+        assert_eq!(bob_bitcoin_wallet.balance().await?, balance_before);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,53 @@
+pub mod harness;
+
+use harness::alice_run_until::is_btc_locked;
+use harness::bob_run_until;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::{AliceState, MoneroLockAlreadyAttempted};
+use swap::protocol::{alice, bob, Checkpoint};
+
+/// Alice restarts after she started the Monero lock transfer but before she
+/// recorded it. She does not transfer the Monero again.
+#[tokio::test]
+async fn given_recorded_lock_attempt_alice_does_not_transfer_monero_again_on_resume() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, bob_run_until::is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let swap_id = alice_swap.swap_id;
+        let alice_state = alice::run_until(alice_swap, is_btc_locked, FixedRate::default()).await?;
+        assert!(matches!(alice_state, AliceState::BtcLocked { .. }));
+        bob_swap.await??;
+
+        ctx.insert_alice_checkpoint(
+            swap_id,
+            Checkpoint {
+                monero_lock_attempted: true,
+                ..Checkpoint::default()
+            },
+        )
+        .await;
+        let xmr_balance_before_resume = ctx.alice_unlocked_xmr_balance().await?;
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        assert!(matches!(alice_swap.state, AliceState::BtcLocked { .. }));
+
+        let error = alice::run(alice_swap, FixedRate::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast::<MoneroLockAlreadyAttempted>()?,
+            MoneroLockAlreadyAttempted { swap_id }
+        );
+        assert_eq!(
+            ctx.alice_unlocked_xmr_balance().await?,
+            xmr_balance_before_resume
+        );
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,84 @@
+pub mod harness;
+
+use harness::alice_run_until::{is_btc_locked, is_xmr_lock_transaction_sent};
+use harness::bob_run_until;
+use harness::SlowCancelConfig;
+use swap::asb;
+use swap::asb::{FixedRate, ShutdownAction};
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob, Database, State};
+
+/// One swap has locked Monero, the other one only Bitcoin. The emergency
+/// shutdown aborts the latter and leaves the former in its state with the
+/// lock confirmations checkpointed.
+#[tokio::test]
+async fn given_swaps_before_and_after_monero_lock_then_shutdown_aborts_or_checkpoints_them() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, bob_run_until::is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let post_lock_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+        let alice_state = alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        )
+        .await?;
+        bob_swap.await??;
+        bob_join_handle.abort();
+
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, bob_run_until::is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let pre_lock_swap_id = alice_swap.swap_id;
+        let pre_lock_state =
+            alice::run_until(alice_swap, is_btc_locked, FixedRate::default()).await?;
+        assert!(matches!(pre_lock_state, AliceState::BtcLocked { .. }));
+        bob_swap.await??;
+
+        let report =
+            asb::emergency_shutdown(alice_db.clone(), alice_bitcoin_wallet.clone()).await?;
+
+        let action = |swap_id| {
+            report
+                .swaps
+                .iter()
+                .find(|(id, _)| *id == swap_id)
+                .map(|(_, action)| action.clone())
+        };
+        assert_eq!(
+            action(pre_lock_swap_id),
+            Some(ShutdownAction::SafelyAborted)
+        );
+        assert_eq!(
+            alice_db.get_state(pre_lock_swap_id).await?,
+            State::Alice(AliceState::SafelyAborted)
+        );
+
+        let tx_lock_confirmations = match action(post_lock_swap_id) {
+            Some(ShutdownAction::Checkpointed {
+                tx_lock_confirmations,
+                ..
+            }) => tx_lock_confirmations,
+            other => panic!("Swap with locked Monero was not checkpointed: {:?}", other),
+        };
+        assert!(tx_lock_confirmations > 0);
+        let checkpoint = alice_db
+            .get_checkpoint(post_lock_swap_id)
+            .await?
+            .expect("checkpoint of the swap with locked Monero");
+        assert_eq!(checkpoint.tx_lock_confirmations, tx_lock_confirmations);
+        assert!(checkpoint.monero_lock_attempted);
+        assert_eq!(
+            alice_db.get_state(post_lock_swap_id).await?,
+            State::Alice(alice_state)
+        );
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,95 @@
+pub mod harness;
+
+use harness::alice_run_until::{is_btc_lock_transaction_seen, is_btc_locked};
+use harness::{bob_run_until, ChainParams, SlowCancelConfig, TestContext};
+use std::sync::Arc;
+use swap::asb::{pending_timelocks, FixedRate, PendingTimelock};
+use swap::bitcoin::TimelockStatus;
+use swap::env::GetConfig;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob, Database};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Two swaps lock their Bitcoin in the same block and a third one four blocks
+/// later. Alice lists the first two in one group that becomes cancellable
+/// four blocks before the third one.
+#[tokio::test]
+async fn given_swaps_locked_at_different_heights_then_pending_timelocks_groups_them_soonest_first()
+{
+    let chain_params = ChainParams {
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |mut ctx| async move {
+        let (first_swap_id, alice_db, first_swap) = start_swap(&mut ctx).await;
+        let (second_swap_id, _, second_swap) = start_swap(&mut ctx).await;
+        harness::wait_until_state(&alice_db, first_swap_id, is_btc_lock_transaction_seen).await;
+        harness::wait_until_state(&alice_db, second_swap_id, is_btc_lock_transaction_seen).await;
+        ctx.mine_blocks(1).await?;
+        assert!(matches!(first_swap.await??, AliceState::BtcLocked { .. }));
+        assert!(matches!(second_swap.await??, AliceState::BtcLocked { .. }));
+
+        ctx.mine_blocks(3).await?;
+
+        let (third_swap_id, _, third_swap) = start_swap(&mut ctx).await;
+        harness::wait_until_state(&alice_db, third_swap_id, is_btc_lock_transaction_seen).await;
+        ctx.mine_blocks(1).await?;
+        assert!(matches!(third_swap.await??, AliceState::BtcLocked { .. }));
+
+        let waiters = pending_timelocks(alice_db, ctx.alice_bitcoin_wallet()).await?;
+
+        let cancel_timelock = u32::from(SlowCancelConfig::get_config().bitcoin_cancel_timelock);
+        let mut locked_first = vec![first_swap_id, second_swap_id];
+        locked_first.sort();
+        assert_eq!(
+            waiters
+                .iter()
+                .map(|waiter| (
+                    waiter.timelock,
+                    waiter.status.blocks_remaining(),
+                    waiter.swap_ids.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (PendingTimelock::Cancel, cancel_timelock - 5, locked_first),
+                (
+                    PendingTimelock::Cancel,
+                    cancel_timelock - 1,
+                    vec![third_swap_id]
+                ),
+            ]
+        );
+        assert!(matches!(
+            waiters[0].status,
+            TimelockStatus::Remaining { .. }
+        ));
+
+        Ok(())
+    })
+    .await;
+}
+
+/// Starts a swap that runs until Alice saw the Bitcoin lock confirmed.
+async fn start_swap(
+    ctx: &mut TestContext,
+) -> (
+    Uuid,
+    Arc<dyn Database + Send + Sync>,
+    JoinHandle<anyhow::Result<AliceState>>,
+) {
+    let (bob_swap, _) = ctx.bob_swap().await;
+    tokio::spawn(bob::run_until(bob_swap, bob_run_until::is_btc_locked));
+
+    let alice_swap = ctx.alice_next_swap().await;
+    let swap_id = alice_swap.swap_id;
+    let db = alice_swap.db.clone();
+    let alice_swap = tokio::spawn(alice::run_until(
+        alice_swap,
+        is_btc_locked,
+        FixedRate::default(),
+    ));
+
+    (swap_id, db, alice_swap)
+}
@@ -0,0 +1,93 @@
+pub mod harness;
+
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::Transaction;
+use harness::alice_run_until::is_encsig_learned;
+use harness::SlowCancelConfig;
+use swap::asb;
+use swap::asb::{Finality, FixedRate};
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob};
+
+/// Bob locks Btc and Alice locks Xmr. After learning the encsig Alice takes the
+/// signed redeem transaction and broadcasts it herself.
+#[tokio::test]
+async fn alice_manually_redeems_using_manual_broadcast() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_encsig_learned,
+            FixedRate::default(),
+        ));
+
+        let alice_state = alice_swap.await??;
+        assert!(matches!(alice_state, AliceState::EncSigLearned { .. }));
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let (tx_redeem, alice_state) = asb::manual_broadcast_redeem(
+            alice_swap.swap_id,
+            alice_swap.bitcoin_wallet.clone(),
+            alice_swap.db.clone(),
+        )
+        .await?;
+        assert!(matches!(
+            alice_state,
+            AliceState::BtcRedeemReadyToBroadcast { .. }
+        ));
+
+        let tx_redeem = roundtrip_hex(&tx_redeem);
+        alice_swap
+            .bitcoin_wallet
+            .broadcast(tx_redeem, "redeem")
+            .await?
+            .1
+            .wait_until_seen()
+            .await?;
+
+        let alice_state = asb::confirm_redeem_broadcast(
+            alice_swap.swap_id,
+            alice_swap.bitcoin_wallet.clone(),
+            alice_swap.db.clone(),
+        )
+        .await?;
+        assert!(matches!(
+            alice_state,
+            AliceState::BtcRedeemTransactionPublished { .. }
+        ));
+
+        let (_, alice_state) = asb::redeem(
+            alice_swap.swap_id,
+            alice_swap.bitcoin_wallet,
+            alice_swap.db,
+            Finality::Await,
+        )
+        .await?;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}
+
+/// Passes the transaction through its hex encoding, as the operator would, and
+/// checks that every input is signed.
+fn roundtrip_hex(transaction: &Transaction) -> Transaction {
+    let bytes = Vec::<u8>::from_hex(&serialize_hex(transaction)).unwrap();
+    let transaction: Transaction = deserialize(&bytes).unwrap();
+
+    assert!(transaction
+        .input
+        .iter()
+        .all(|input| !input.witness.is_empty()));
+
+    transaction
+}
@@ -0,0 +1,58 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::bitcoin;
+use swap::network::swap_setup::bob::Error;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Alice pauses while a swap is running. New quote and swap requests are
+/// declined, the running swap completes. After resuming Alice accepts new
+/// swaps again.
+#[tokio::test]
+async fn given_alice_paused_then_new_swaps_are_declined_but_running_swap_completes() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let pause_handle = ctx.alice_pause_handle();
+        pause_handle.pause();
+
+        let (mut declined_swap, declined_join_handle) = ctx.bob_swap().await;
+        let quote = declined_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(quote.max_quantity, bitcoin::Amount::ZERO);
+
+        let error = bob::run(declined_swap).await.unwrap_err();
+        assert!(matches!(
+            error.downcast::<Error>()?,
+            Error::TemporarilyUnavailable
+        ));
+        declined_join_handle.abort();
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        pause_handle.resume();
+
+        let (mut bob_swap, _) = ctx.bob_swap().await;
+        let quote = bob_swap.event_loop_handle.request_quote().await?;
+        assert_ne!(quote.max_quantity, bitcoin::Amount::ZERO);
+
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+        let _alice_swap = ctx.alice_next_swap().await;
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,63 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastPunishWithShortGraceConfig;
+use swap::asb::FixedRate;
+use swap::bitcoin::wallet::ScriptStatus;
+use swap::env::GetConfig;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Bob locks Btc and Alice locks Xmr. Bob does not act. Alice punishes on
+/// her own, but only once the grace period after the punish timelock passed.
+#[tokio::test]
+async fn alice_punishes_automatically_once_grace_period_passed() {
+    harness::setup_test(FastPunishWithShortGraceConfig, |mut ctx| async move {
+        let (bob_swap, _bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let alice_state = alice_swap.await??;
+        let state3 = match alice_state {
+            AliceState::XmrLockTransactionSent { state3, .. } => state3,
+            _ => panic!("Alice in unexpected state {}", alice_state),
+        };
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_state = alice::run(alice_swap, FixedRate::default()).await?;
+        ctx.assert_alice_punished(alice_state).await;
+
+        let punish_after = u32::from(state3.punish_timelock)
+            + FastPunishWithShortGraceConfig::get_config().punish_grace_blocks;
+        let cancel_confirmations = match alice_bitcoin_wallet
+            .status_of_script(&state3.tx_cancel())
+            .await?
+        {
+            ScriptStatus::Confirmed(confirmed) => confirmed.confirmations(),
+            status => panic!("Cancel transaction not confirmed: {:?}", status),
+        };
+        assert!(
+            cancel_confirmations >= punish_after,
+            "Alice punished {} blocks after cancel, before the grace period passed after {}",
+            cancel_confirmations,
+            punish_after
+        );
+
+        Ok(())
+    })
+    .await;
+}
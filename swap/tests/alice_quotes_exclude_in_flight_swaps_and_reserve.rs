@@ -0,0 +1,44 @@
+pub mod harness;
+
+use harness::alice_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::{available_for_quote, FixedRate};
+use swap::monero;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob};
+
+#[tokio::test]
+async fn given_swap_in_flight_then_max_quote_drops_by_its_monero_and_the_reserve() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let reserve = monero::Amount::from_piconero(ctx.xmr_amount().as_piconero() / 2);
+        ctx.restart_alice_with_monero_reserve(Some(reserve)).await;
+        let unlocked = ctx.alice_unlocked_xmr_balance().await?;
+
+        let (mut bob_swap, _) = ctx.bob_swap().await;
+        let idle_quote = bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(
+            Some(idle_quote.max_quantity),
+            available_for_quote(unlocked, monero::Amount::ZERO, reserve)
+                .max_bitcoin_for_price(idle_quote.price)
+        );
+
+        // Alice agreed to lock her Monero but has not sent it yet
+        tokio::spawn(bob::run(bob_swap));
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_state = alice::run_until(alice_swap, is_btc_locked, FixedRate::default()).await?;
+        assert!(matches!(alice_state, AliceState::BtcLocked { .. }));
+        assert_eq!(ctx.alice_unlocked_xmr_balance().await?, unlocked);
+
+        let (mut other_bob_swap, _) = ctx.bob_swap().await;
+        let busy_quote = other_bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(
+            Some(busy_quote.max_quantity),
+            available_for_quote(unlocked, ctx.xmr_amount(), reserve)
+                .max_bitcoin_for_price(busy_quote.price)
+        );
+        assert!(busy_quote.max_quantity < idle_quote.max_quantity);
+
+        Ok(())
+    })
+    .await;
+}
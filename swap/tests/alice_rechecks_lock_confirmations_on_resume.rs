@@ -0,0 +1,71 @@
+pub mod harness;
+
+use harness::alice_run_until::{is_btc_lock_transaction_seen, is_btc_locked};
+use harness::{ChainParams, SlowCancelConfig};
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob, Checkpoint};
+use tokio::time::timeout;
+
+/// Alice restarts with a checkpoint that claims the lock transaction is final
+/// while it is actually still unconfirmed. She does not lock the Monero until
+/// the lock transaction is final on chain.
+#[tokio::test]
+async fn given_stale_checkpoint_alice_waits_for_lock_to_be_final_on_resume() {
+    let chain_params = ChainParams {
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let _bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let swap_id = alice_swap.swap_id;
+        let alice_state = alice::run_until(
+            alice_swap,
+            is_btc_lock_transaction_seen,
+            FixedRate::default(),
+        )
+        .await?;
+        assert!(matches!(
+            alice_state,
+            AliceState::BtcLockTransactionSeen { .. }
+        ));
+
+        ctx.insert_alice_checkpoint(
+            swap_id,
+            Checkpoint {
+                tx_lock_confirmations: 100,
+                ..Checkpoint::default()
+            },
+        )
+        .await;
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        assert!(matches!(
+            alice_swap.state,
+            AliceState::BtcLockTransactionSeen { .. }
+        ));
+        let mut alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_btc_locked,
+            FixedRate::default(),
+        ));
+
+        // no blocks are mined, the lock transaction stays unconfirmed
+        assert!(timeout(Duration::from_secs(15), &mut alice_swap)
+            .await
+            .is_err());
+
+        ctx.mine_blocks(1).await?;
+        let alice_state = alice_swap.await??;
+        assert!(matches!(alice_state, AliceState::BtcLocked { .. }));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,72 @@
+pub mod harness;
+
+use harness::alice_run_until::is_encsig_learned;
+use harness::bob_run_until::is_encsig_sent;
+use harness::FastCancelConfig;
+use swap::asb;
+use swap::asb::{Finality, FixedRate};
+use swap::bitcoin::LockAlreadySpent;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob};
+
+/// Alice learns the encrypted signature but the cancel transaction spends the
+/// lock output before she redeems. The manual redeem reports which
+/// transaction spent the lock instead of failing to broadcast.
+#[tokio::test]
+async fn given_lock_spent_by_cancel_when_redeeming_then_reports_lock_already_spent() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_encsig_sent));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_encsig_learned,
+            FixedRate::default(),
+        ));
+
+        let alice_state = alice_swap.await??;
+        let state3 = if let AliceState::EncSigLearned { state3, .. } = alice_state {
+            state3
+        } else {
+            panic!("Alice in unexpected state {}", alice_state);
+        };
+        bob_swap.await??;
+
+        alice_bitcoin_wallet
+            .subscribe_to(state3.tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(state3.cancel_timelock)
+            .await?;
+        state3
+            .submit_tx_cancel(alice_bitcoin_wallet.as_ref())
+            .await?;
+        alice_bitcoin_wallet
+            .subscribe_to(state3.tx_cancel())
+            .await
+            .wait_until_seen()
+            .await?;
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let error = asb::redeem(
+            alice_swap.swap_id,
+            alice_swap.bitcoin_wallet,
+            alice_swap.db,
+            Finality::NotAwait,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<LockAlreadySpent>(),
+            Some(&LockAlreadySpent {
+                by_txid: state3.tx_cancel().txid()
+            })
+        );
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,52 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use std::sync::{Arc, Mutex};
+use swap::asb::{FixedRate, LatestRate, Rate};
+use swap::bitcoin;
+
+/// A rate service that fails until a rate is set, like one that cannot reach
+/// the exchange yet.
+#[derive(Clone, Debug, Default)]
+struct StubRate(Arc<Mutex<Option<Rate>>>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("Exchange is unreachable")]
+struct Unreachable;
+
+impl StubRate {
+    fn set(&self, rate: Rate) {
+        *self.0.lock().unwrap() = Some(rate);
+    }
+}
+
+impl LatestRate for StubRate {
+    type Error = Unreachable;
+
+    fn latest_rate(&mut self) -> Result<Rate, Self::Error> {
+        self.0.lock().unwrap().ok_or(Unreachable)
+    }
+}
+
+#[tokio::test]
+async fn given_rate_unavailable_then_quotes_are_rejected_until_rate_is_back() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let rate = StubRate::default();
+        ctx.restart_alice_with_rate(rate.clone()).await;
+
+        let (mut bob_swap, _) = ctx.bob_swap().await;
+        let quote = bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(quote.price, bitcoin::Amount::ZERO);
+        assert_eq!(quote.max_quantity, bitcoin::Amount::ZERO);
+
+        let fixed_rate = FixedRate::default().value();
+        rate.set(fixed_rate);
+
+        let quote = bob_swap.event_loop_handle.request_quote().await?;
+        assert_eq!(quote.price, fixed_rate.ask()?);
+        assert_ne!(quote.max_quantity, bitcoin::Amount::ZERO);
+
+        Ok(())
+    })
+    .await;
+}
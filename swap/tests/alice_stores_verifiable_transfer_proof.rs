@@ -0,0 +1,54 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::env::GetConfig;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob, State};
+
+/// After a completed swap, the transfer proof Alice stored proves in any
+/// wallet that her Monero lock transaction paid the lock address.
+#[tokio::test]
+async fn given_completed_swap_then_stored_transfer_proof_verifies() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_monero_wallet = alice_swap.monero_wallet.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let tx_hash = alice_db
+            .get_states(alice_swap_id)
+            .await?
+            .into_iter()
+            .find_map(|(_, state)| match state {
+                State::Alice(AliceState::XmrLockTransactionSent { transfer_proof, .. }) => {
+                    Some(transfer_proof.tx_hash())
+                }
+                _ => None,
+            })
+            .expect("Alice locked the Monero");
+        let address = alice_db
+            .swap_keys(alice_swap_id)
+            .await?
+            .monero_lock_address(SlowCancelConfig::get_config().monero_network);
+
+        let proof = alice_db.transfer_proof(alice_swap_id).await?;
+        let received = alice_monero_wallet
+            .check_tx_proof(&tx_hash, address, proof)
+            .await?;
+
+        assert_eq!(received, ctx.xmr_amount());
+
+        Ok(())
+    })
+    .await;
+}
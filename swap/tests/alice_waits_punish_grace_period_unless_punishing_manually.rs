@@ -0,0 +1,73 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastPunishWithGraceConfig;
+use std::convert::TryInto;
+use swap::asb;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database};
+
+/// Bob locks Btc and Alice locks Xmr. Bob does not act. Once the punish
+/// timelock expired Alice does not punish automatically during the grace
+/// period, but punishing manually works right away.
+#[tokio::test]
+async fn alice_waits_punish_grace_period_unless_punishing_manually() {
+    harness::setup_test(FastPunishWithGraceConfig, |mut ctx| async move {
+        let (bob_swap, _bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let alice_state = alice_swap.await??;
+        let state3 = match alice_state {
+            AliceState::XmrLockTransactionSent { state3, .. } => state3,
+            _ => panic!("Alice in unexpected state {}", alice_state),
+        };
+
+        // Alice cancels on her own and then waits for the grace period to pass
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let swap_id = alice_swap.swap_id;
+        let db = alice_swap.db.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        alice_bitcoin_wallet
+            .subscribe_to(state3.tx_cancel())
+            .await
+            .wait_until_confirmed_with(u32::from(state3.punish_timelock) + 5)
+            .await?;
+
+        let alice_state: AliceState = db.get_state(swap_id).await?.try_into()?;
+        assert!(
+            matches!(alice_state, AliceState::BtcCancelled { .. }),
+            "Alice punished before the grace period passed, state: {}",
+            alice_state
+        );
+
+        // manual punish
+
+        alice_swap.abort();
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let (_, alice_state) =
+            asb::punish(alice_swap.swap_id, alice_swap.bitcoin_wallet, alice_swap.db).await?;
+        ctx.assert_alice_punished(alice_state).await;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,35 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use std::time::Duration;
+use swap::bitcoin::wallet::BalanceNotReached;
+
+#[tokio::test]
+async fn given_deposit_await_balance_returns_exact_target() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let wallet = ctx.new_bitcoin_wallet(b"await balance").await;
+        let target = bitcoin::Amount::from_sat(150_000);
+
+        ctx.mint(wallet.new_address().await?, target).await?;
+        let balance = wallet
+            .await_balance(target, Duration::from_secs(30))
+            .await?;
+        assert_eq!(balance, target);
+
+        let error = wallet
+            .await_balance(target * 2, Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<BalanceNotReached>(),
+            Some(&BalanceNotReached {
+                balance: target,
+                target: target * 2,
+                timeout: Duration::from_secs(1),
+            })
+        );
+
+        Ok(())
+    })
+    .await;
+}
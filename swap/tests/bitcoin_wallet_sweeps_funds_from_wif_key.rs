@@ -0,0 +1,71 @@
+pub mod harness;
+
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::{Address, Network, PrivateKey};
+use harness::SlowCancelConfig;
+use rand::rngs::OsRng;
+use swap::bitcoin::wallet::WifNetworkMismatch;
+use swap::bitcoin::{Amount, SecretKey, Wallet};
+use swap::env::GetConfig;
+use swap::seed::Seed;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn given_funded_wif_key_sweeps_funds_into_wallet() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let env_config = SlowCancelConfig::get_config();
+        let key = PrivateKey::from_slice(
+            &SecretKey::new_random(&mut OsRng).to_bytes(),
+            Network::Testnet,
+        )?;
+        let address = Address::p2wpkh(
+            &key.public_key(&Secp256k1::new()),
+            env_config.bitcoin_network,
+        )?;
+
+        let funder = ctx.bob_bitcoin_wallet();
+        let amount = Amount::from_sat(100_000);
+        let psbt = funder.send_to_address(address, amount, None, None).await?;
+        let transaction = funder.sign_and_finalize(psbt).await?;
+        let (_, subscription) = funder.broadcast(transaction, "funding").await?;
+        subscription.wait_until_final().await?;
+
+        let data_dir = tempdir()?;
+        let xprivkey = Seed::random()?.derive_extended_private_key(env_config.bitcoin_network)?;
+        let wallet = Wallet::new(
+            ctx.electrum_rpc_url(),
+            data_dir.path(),
+            xprivkey,
+            env_config,
+            1,
+        )
+        .await?;
+
+        let mainnet_key = PrivateKey {
+            network: Network::Bitcoin,
+            ..key
+        };
+        let error = wallet
+            .sweep_from_wif(&mainnet_key.to_wif(), Amount::from_sat(1_000))
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<WifNetworkMismatch>().is_some());
+
+        let fee = Amount::from_sat(1_000);
+        let txid = wallet.sweep_from_wif(&key.to_wif(), fee).await?;
+        let script = wallet.get_raw_transaction(txid).await?.output[0]
+            .script_pubkey
+            .clone();
+        wallet
+            .subscribe_to((txid, script))
+            .await
+            .wait_until_final()
+            .await?;
+
+        wallet.sync().await?;
+        assert_eq!(wallet.balance().await?, amount - fee);
+
+        Ok(())
+    })
+    .await;
+}
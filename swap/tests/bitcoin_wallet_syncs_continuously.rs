@@ -0,0 +1,54 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use swap::bitcoin::{Amount, Wallet};
+use swap::env::GetConfig;
+use swap::seed::Seed;
+use tempfile::tempdir;
+use tokio::time::{sleep, timeout};
+
+/// A wallet syncing in the background sees a deposit without being synced
+/// explicitly.
+#[tokio::test]
+async fn given_continuous_sync_then_deposit_is_reflected_without_explicit_sync() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let env_config = SlowCancelConfig::get_config();
+        let xprivkey = Seed::random()?.derive_extended_private_key(env_config.bitcoin_network)?;
+        let data_dir = tempdir()?;
+        let wallet = Arc::new(
+            Wallet::new(
+                ctx.electrum_rpc_url(),
+                data_dir.path(),
+                xprivkey,
+                env_config,
+                1,
+            )
+            .await?,
+        );
+        let background_sync = wallet.sync_continuously(Duration::from_millis(500));
+
+        let funder = ctx.bob_bitcoin_wallet();
+        let amount = Amount::from_sat(100_000);
+        let psbt = funder
+            .send_to_address(wallet.new_address().await?, amount, None, None)
+            .await?;
+        let transaction = funder.sign_and_finalize(psbt).await?;
+        funder.broadcast(transaction, "deposit").await?;
+
+        timeout(Duration::from_secs(30), async {
+            while wallet.balance().await? != amount {
+                sleep(Duration::from_millis(100)).await;
+            }
+
+            anyhow::Ok(())
+        })
+        .await??;
+
+        background_sync.stop();
+
+        Ok(())
+    })
+    .await;
+}
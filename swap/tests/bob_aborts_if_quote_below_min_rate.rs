@@ -0,0 +1,27 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::monero;
+use swap::network::swap_setup::bob::Error;
+use swap::protocol::bob;
+
+#[tokio::test]
+async fn given_quote_below_min_rate_bob_aborts_before_locking_btc() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = bob_swap.with_min_rate(monero::Amount::from_monero(1_000_000.0)?);
+
+        let error = bob::run(bob_swap).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast::<Error>()?,
+            Error::RateBelowMinimum { .. }
+        ));
+        assert!(bob_db.get_state(bob_swap_id).await.is_err());
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,64 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use std::convert::TryInto;
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::cli::AbortHandle;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database, State};
+
+/// Two swaps are in flight, aborting the first one by its id leaves it in its
+/// latest state with the Bitcoin locked and lets the second one finish.
+#[tokio::test]
+async fn given_two_swaps_when_one_is_aborted_then_the_other_continues() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let abort_handle = AbortHandle::default();
+
+        let (bob_swap_1, _) = ctx.bob_swap().await;
+        let aborted_swap_id = bob_swap_1.id;
+        let bob_db = bob_swap_1.db.clone();
+        let bob_swap_1 = tokio::spawn(bob::run(bob_swap_1.with_abort_handle(&abort_handle)));
+
+        // Alice never runs the first swap, Bob waits for the Monero lock
+        let _alice_swap_1 = ctx.alice_next_swap().await;
+        loop {
+            let state: Option<BobState> = bob_db
+                .get_state(aborted_swap_id)
+                .await
+                .ok()
+                .and_then(|state| state.try_into().ok());
+            if let Some(BobState::BtcLocked { .. }) = state {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        let (bob_swap_2, _) = ctx.bob_swap().await;
+        let bob_swap_2 = tokio::spawn(bob::run(bob_swap_2.with_abort_handle(&abort_handle)));
+
+        let alice_swap_2 = ctx.alice_next_swap().await;
+        let alice_swap_2 = tokio::spawn(alice::run(alice_swap_2, FixedRate::default()));
+
+        assert!(abort_handle.abort_swap(aborted_swap_id));
+
+        let bob_state_1 = bob_swap_1.await??;
+        assert!(matches!(bob_state_1, BobState::BtcLocked { .. }));
+        assert_eq!(
+            bob_db.get_state(aborted_swap_id).await?,
+            State::Bob(bob_state_1)
+        );
+        assert!(!abort_handle.abort_swap(aborted_swap_id));
+
+        let bob_state_2 = bob_swap_2.await??;
+        ctx.assert_bob_redeemed(bob_state_2).await;
+
+        let alice_state_2 = alice_swap_2.await??;
+        assert!(matches!(alice_state_2, AliceState::BtcRedeemed));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,48 @@
+pub mod harness;
+
+use harness::alice_run_until::is_transfer_proof_sent;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob};
+
+/// Bob locks Btc and Alice locks Xmr, then Alice goes offline before Bob could
+/// send her the encsig. Alice comes back well before the cancel timelock
+/// approaches, Bob's redial delivers the encsig and both parties redeem.
+#[tokio::test]
+async fn given_alice_reconnects_before_encsig_deadline_then_both_redeem() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_transfer_proof_sent,
+            FixedRate::default(),
+        ));
+
+        let alice_state = alice_swap.await??;
+        assert!(matches!(
+            alice_state,
+            AliceState::XmrLockTransferProofSent { .. }
+        ));
+        ctx.stop_alice();
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        assert!(matches!(
+            alice_swap.state,
+            AliceState::XmrLockTransferProofSent { .. }
+        ));
+
+        let alice_state = alice::run(alice_swap, FixedRate::default()).await?;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,56 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::bob::{BobState, PointOfNoReturnReached};
+use swap::protocol::{alice, bob};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+
+/// The event is sent before the lock transaction is published, so it is
+/// already waiting once Bob reached `BtcLocked`, and it is not sent again when
+/// the swap continues from there.
+#[tokio::test]
+async fn given_bob_locks_btc_then_point_of_no_return_is_emitted_once_before() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = bob_swap.with_point_of_no_return_listener(sender.clone());
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        let tx_lock_id = if let BobState::BtcLocked { state3, .. } = bob_state {
+            state3.tx_lock.txid()
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        };
+
+        assert_eq!(
+            receiver.try_recv()?,
+            PointOfNoReturnReached {
+                swap_id: bob_swap_id,
+                tx_lock_id
+            }
+        );
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_state = bob::run(bob_swap.with_point_of_no_return_listener(sender)).await?;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,77 @@
+pub mod harness;
+
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::Transaction;
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::bob::{BobState, ManualBroadcast};
+use swap::protocol::{alice, bob};
+
+#[tokio::test]
+async fn given_btc_locked_exported_refund_spends_cancel_and_is_accepted_after_timelock() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        alice_swap.await??;
+        bob_join_handle.abort();
+
+        let exported = bob::Swap::export_recovery_transactions(bob_db, bob_swap_id).await?;
+
+        let (tx_cancel, tx_refund) = match exported.as_slice() {
+            [(ManualBroadcast::Cancel, tx_cancel), (ManualBroadcast::Refund, tx_refund)] => {
+                (from_hex(tx_cancel), from_hex(tx_refund))
+            }
+            other => panic!("Unexpected recovery transactions {:?}", other),
+        };
+        assert!(tx_refund
+            .input
+            .iter()
+            .all(|input| input.previous_output.txid == tx_cancel.txid()));
+
+        // Keep the exported transactions until the cancel timelock expired
+        if let BobState::BtcLocked { state3, .. } = bob_state {
+            bob_bitcoin_wallet
+                .subscribe_to(state3.tx_lock)
+                .await
+                .wait_until_confirmed_with(state3.cancel_timelock)
+                .await?;
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        }
+
+        bob_bitcoin_wallet
+            .broadcast(tx_cancel, "cancel")
+            .await?
+            .1
+            .wait_until_seen()
+            .await?;
+        bob_bitcoin_wallet
+            .broadcast(tx_refund, "refund")
+            .await?
+            .1
+            .wait_until_seen()
+            .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+fn from_hex(transaction: &str) -> Transaction {
+    deserialize(&Vec::<u8>::from_hex(transaction).unwrap()).unwrap()
+}
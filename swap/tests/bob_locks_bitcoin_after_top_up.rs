@@ -0,0 +1,42 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::bitcoin;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, State};
+use tokio::join;
+
+#[tokio::test]
+async fn given_swap_slightly_above_balance_bob_locks_bitcoin_after_top_up() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let shortfall = bitcoin::Amount::from_sat(10_000);
+        let (bob_swap, _, bob_bitcoin_wallet) = ctx
+            .bob_swap_short_of_funds(shortfall, Duration::from_secs(60))
+            .await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        // Let Bob find out that he cannot cover the lock transaction yet
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let bob_states = bob_db.get_states(bob_swap_id).await.unwrap_or_default();
+        assert!(!bob_states
+            .iter()
+            .any(|(_, state)| matches!(state, State::Bob(BobState::SwapSetupCompleted(..)))));
+        ctx.mint(bob_bitcoin_wallet.new_address().await?, shortfall * 2)
+            .await?;
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let (bob_state, alice_state) = join!(bob_swap, alice_swap);
+
+        ctx.assert_alice_redeemed(alice_state??).await;
+        assert!(matches!(bob_state??, BobState::XmrRedeemed { .. }));
+
+        Ok(())
+    })
+    .await;
+}
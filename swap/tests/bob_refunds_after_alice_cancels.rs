@@ -0,0 +1,83 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::{ChainParams, SlowCancelConfig};
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, State};
+use tokio::time::timeout;
+
+/// Bob locks Btc and Alice locks Xmr but never sends the transfer proof.
+/// Alice publishes the cancel transaction once the cancel timelock expired,
+/// Bob notices it and refunds without publishing the cancel transaction
+/// himself.
+#[tokio::test]
+async fn given_alice_publishes_cancel_then_bob_refunds() {
+    let chain_params = ChainParams {
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+        let mut alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        // no blocks are mined in the background, confirm the lock transaction
+        let alice_state = loop {
+            ctx.mine_blocks(1).await?;
+
+            if let Ok(alice_state) = timeout(Duration::from_secs(5), &mut alice_swap).await {
+                break alice_state??;
+            }
+        };
+        let state3 = if let AliceState::XmrLockTransactionSent { state3, .. } = alice_state {
+            state3
+        } else {
+            panic!("Alice in unexpected state {}", alice_state);
+        };
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        // Bob is stopped, so only Alice can publish the cancel transaction
+        ctx.mine_blocks(u32::from(state3.cancel_timelock)).await?;
+        alice_bitcoin_wallet
+            .subscribe_to(state3.tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(state3.cancel_timelock)
+            .await?;
+        state3
+            .submit_tx_cancel(alice_bitcoin_wallet.as_ref())
+            .await?;
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_db = bob_swap.db.clone();
+        let bob_state = bob::run(bob_swap).await?;
+        ctx.mine_blocks(1).await?;
+        ctx.assert_bob_refunded(bob_state).await;
+
+        let bob_states = bob_db.get_states(bob_swap_id).await?;
+        assert!(bob_states
+            .iter()
+            .any(|(_, state)| matches!(state, State::Bob(BobState::BtcCancelled(..)))));
+        assert!(!bob_states
+            .iter()
+            .any(|(_, state)| matches!(state, State::Bob(BobState::CancelTimelockExpired(..)))));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,65 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastCancelConfig;
+use std::sync::Arc;
+use swap::asb::FixedRate;
+use swap::cli;
+use swap::database::SqliteDatabase;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database};
+use tempfile::tempdir;
+
+/// Bob exports the swap after locking Btc and imports it into a fresh
+/// database, e.g. on another machine, from where he refunds.
+#[tokio::test]
+async fn given_swap_exported_after_btc_locked_bob_refunds_from_fresh_db() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        alice_swap.await??;
+        bob_join_handle.abort();
+
+        let key = ctx.bob_swap_backup_key();
+        let blob = bob_db.export_swap(bob_swap_id, &key).await?;
+
+        let data_dir = tempdir()?;
+        let db_path = data_dir.path().join("sqlite");
+        std::fs::File::create(&db_path)?;
+        let fresh_db = Arc::new(SqliteDatabase::open(&db_path).await?);
+        let imported = fresh_db.import_swap(&blob, &key).await?;
+        assert_eq!(imported, bob_swap_id);
+
+        // Ensure cancel timelock is expired
+        if let BobState::BtcLocked { state3, .. } = bob_state {
+            bob_bitcoin_wallet
+                .subscribe_to(state3.tx_lock)
+                .await
+                .wait_until_confirmed_with(state3.cancel_timelock)
+                .await?;
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        }
+
+        let bob_state =
+            cli::cancel_and_refund(bob_swap_id, bob_bitcoin_wallet, fresh_db, None).await?;
+
+        ctx.assert_bob_refunded(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}
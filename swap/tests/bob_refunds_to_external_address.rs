@@ -0,0 +1,121 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::{FastCancelConfig, TestContext};
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::cli;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Bob refunds manually and gives an external address, the refunded Bitcoin
+/// end up in the wallet the address belongs to.
+#[tokio::test]
+async fn given_external_refund_address_then_refund_is_forwarded_to_it() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let bob_swap = bob_swap_with_expired_cancel_timelock(&mut ctx).await?;
+
+        let external_wallet = ctx.new_bitcoin_wallet(b"external refund").await;
+        let external_address = external_wallet.new_address().await?;
+
+        let bob_state = cli::cancel_and_refund(
+            bob_swap.id,
+            bob_swap.bitcoin_wallet,
+            bob_swap.db,
+            Some(external_address),
+        )
+        .await?;
+        assert!(matches!(bob_state, BobState::BtcRefunded(..)));
+
+        let forwarded = external_wallet
+            .await_balance(bitcoin::Amount::from_sat(1), Duration::from_secs(60))
+            .await?;
+        assert!(forwarded < ctx.btc_amount());
+
+        Ok(())
+    })
+    .await
+}
+
+/// The refund was published but the forward to the external address did not
+/// go through, so the swap is still cancelled. Running the refund again only
+/// forwards the refunded Bitcoin.
+#[tokio::test]
+async fn given_refund_published_but_not_forwarded_then_refund_retries_forward() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let bob_swap = bob_swap_with_expired_cancel_timelock(&mut ctx).await?;
+
+        let (_, _, bob_state) = cli::cancel(
+            bob_swap.id,
+            bob_swap.bitcoin_wallet.clone(),
+            bob_swap.db.clone(),
+        )
+        .await?;
+        let state6 = match bob_state {
+            BobState::BtcCancelled(state6) => state6,
+            state => panic!("Bob in unexpected state {}", state),
+        };
+        state6.publish_refund_btc(&bob_swap.bitcoin_wallet).await?;
+
+        let external_wallet = ctx.new_bitcoin_wallet(b"external refund").await;
+        let external_address = external_wallet.new_address().await?;
+
+        let bob_state = cli::refund(
+            bob_swap.id,
+            bob_swap.bitcoin_wallet,
+            bob_swap.db,
+            Some(external_address),
+        )
+        .await?;
+        assert!(matches!(bob_state, BobState::BtcRefunded(..)));
+
+        let forwarded = external_wallet
+            .await_balance(bitcoin::Amount::from_sat(1), Duration::from_secs(60))
+            .await?;
+        assert!(forwarded < ctx.btc_amount());
+
+        Ok(())
+    })
+    .await
+}
+
+async fn bob_swap_with_expired_cancel_timelock(ctx: &mut TestContext) -> anyhow::Result<bob::Swap> {
+    let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+    let bob_swap_id = bob_swap.id;
+    let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+    let alice_swap = ctx.alice_next_swap().await;
+    let alice_swap = tokio::spawn(alice::run_until(
+        alice_swap,
+        is_xmr_lock_transaction_sent,
+        FixedRate::default(),
+    ));
+
+    let bob_state = bob_swap.await??;
+    assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+    let alice_state = alice_swap.await??;
+    assert!(matches!(
+        alice_state,
+        AliceState::XmrLockTransactionSent { .. }
+    ));
+
+    let (bob_swap, bob_join_handle) = ctx
+        .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+        .await;
+    if let BobState::BtcLocked { state3, .. } = bob_swap.state.clone() {
+        bob_swap
+            .bitcoin_wallet
+            .subscribe_to(state3.tx_lock)
+            .await
+            .wait_until_confirmed_with(state3.cancel_timelock)
+            .await?;
+    } else {
+        panic!("Bob in unexpected state {}", bob_swap.state);
+    }
+    bob_join_handle.abort();
+
+    Ok(bob_swap)
+}
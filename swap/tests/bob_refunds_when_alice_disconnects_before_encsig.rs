@@ -0,0 +1,39 @@
+pub mod harness;
+
+use harness::alice_run_until::is_transfer_proof_sent;
+use harness::FastCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob};
+
+/// Bob locks Btc and Alice locks Xmr, then Alice goes offline before Bob could
+/// send her the encsig. Bob keeps redialling until the cancel timelock
+/// approaches, then gives up on the encsig and refunds once the cancel timelock
+/// expired.
+#[tokio::test]
+async fn given_alice_disconnects_before_encsig_then_bob_refunds_near_cancel_timelock() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap.with_encsig_deadline_margin(2)));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_transfer_proof_sent,
+            FixedRate::default(),
+        ));
+
+        let alice_state = alice_swap.await??;
+        assert!(matches!(
+            alice_state,
+            AliceState::XmrLockTransferProofSent { .. }
+        ));
+        ctx.stop_alice();
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_refunded(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}
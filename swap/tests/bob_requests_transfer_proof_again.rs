@@ -0,0 +1,59 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::{is_btc_locked, is_lock_proof_received};
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Alice locks the Monero but Bob never receives the transfer proof. After a
+/// restart Bob waits for it in vain, asks for it again and Alice resends it
+/// from her state.
+#[tokio::test]
+async fn given_bob_lost_transfer_proof_when_requesting_it_then_alice_resends_it() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let alice_state = alice_swap.await??;
+        let transfer_proof =
+            if let AliceState::XmrLockTransactionSent { transfer_proof, .. } = alice_state {
+                transfer_proof
+            } else {
+                panic!("Alice in unexpected state {}", alice_state);
+            };
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        assert!(matches!(bob_swap.state, BobState::BtcLocked { .. }));
+
+        let bob_state = bob::run_until(bob_swap, is_lock_proof_received).await?;
+
+        if let BobState::XmrLockProofReceived {
+            lock_transfer_proof,
+            ..
+        } = bob_state
+        {
+            assert_eq!(lock_transfer_proof, transfer_proof);
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        }
+
+        Ok(())
+    })
+    .await;
+}
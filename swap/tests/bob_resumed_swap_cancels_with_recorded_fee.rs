@@ -0,0 +1,69 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastCancelConfig;
+use swap::asb::FixedRate;
+use swap::cli;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// The fee of the cancel transaction is agreed on and signed during the swap
+/// setup. After resuming from the database Bob publishes exactly that
+/// transaction, not one paying a freshly estimated fee.
+#[tokio::test]
+async fn given_bob_resumes_swap_then_cancel_pays_fee_recorded_at_start() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        let state3 = if let BobState::BtcLocked { state3, .. } = bob_state {
+            state3
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        };
+        let recorded_tx_cancel = state3.tx_cancel();
+        let recorded_fee = state3.tx_lock.lock_amount() - recorded_tx_cancel.amount();
+        let _ = alice_swap.await??;
+
+        let (bob_swap, bob_join_handle) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let resumed_state3 = if let BobState::BtcLocked { state3, .. } = bob_swap.state.clone() {
+            state3
+        } else {
+            panic!("Bob in unexpected state {}", bob_swap.state);
+        };
+        assert_eq!(resumed_state3.cancel().tx_cancel_fee, recorded_fee);
+
+        bob_swap
+            .bitcoin_wallet
+            .subscribe_to(resumed_state3.tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(resumed_state3.cancel_timelock)
+            .await?;
+
+        bob_join_handle.abort();
+        let (txid, subscription, _) =
+            cli::cancel(bob_swap.id, bob_swap.bitcoin_wallet.clone(), bob_swap.db).await?;
+        subscription.wait_until_seen().await?;
+        assert_eq!(txid, recorded_tx_cancel.txid());
+
+        let published = bob_swap.bitcoin_wallet.get_raw_transaction(txid).await?;
+        let published_fee =
+            resumed_state3.tx_lock.lock_amount().to_sat() - published.output[0].value;
+        assert_eq!(published_fee, recorded_fee.to_sat());
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,45 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Neither maker buys the full amount on its own, so the swap is split into
+/// two legs that run concurrently, one per maker, and both complete.
+#[tokio::test]
+async fn given_two_makers_total_is_satisfied_by_two_concurrent_legs() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let leg_amount = ctx.btc_amount();
+        let mut second_alice = ctx.start_second_alice(leg_amount).await;
+
+        let split_swap = ctx.bob_split_swap().await;
+        let makers = vec![
+            (second_alice.peer_id, second_alice.address.clone()),
+            ctx.alice_maker(),
+        ];
+        let outcome =
+            tokio::spawn(async move { split_swap.split_swap(leg_amount * 2, makers).await });
+
+        // the legs lock their Bitcoin one after another, in the order of the makers
+        let second_alice_swap = second_alice.next_swap().await;
+        let second_alice_swap = tokio::spawn(alice::run(second_alice_swap, FixedRate::default()));
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let outcome = outcome.await??;
+        assert!(outcome.failed.is_empty(), "{:?}", outcome.failed);
+        assert_eq!(outcome.completed.len(), 2);
+        for (leg, state) in &outcome.completed {
+            assert_eq!(leg.btc_amount, leg_amount);
+            assert!(matches!(state, BobState::XmrRedeemed { .. }));
+        }
+
+        assert!(matches!(alice_swap.await??, AliceState::BtcRedeemed));
+        assert!(matches!(second_alice_swap.await??, AliceState::BtcRedeemed));
+
+        Ok(())
+    })
+    .await;
+}
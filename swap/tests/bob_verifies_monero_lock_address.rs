@@ -0,0 +1,64 @@
+pub mod harness;
+
+use harness::bob_run_until::is_lock_proof_received;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::env::GetConfig;
+use swap::monero::LockDestinationMismatch;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// The address derived from the key shares in Bob's state is where Alice
+/// locked the Monero, any other address does not receive the locked amount.
+#[tokio::test]
+async fn given_completed_swap_derived_lock_address_matches_lock_destination() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let network = SlowCancelConfig::get_config().monero_network;
+
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_monero_wallet = bob_swap.monero_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_lock_proof_received));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        let (state3, transfer_proof) = if let BobState::XmrLockProofReceived {
+            state,
+            lock_transfer_proof,
+            ..
+        } = bob_state
+        {
+            (state, lock_transfer_proof)
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        };
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_state = bob::run(bob_swap).await?;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let xmr = state3
+            .lock_xmr_watch_request(transfer_proof.clone())
+            .expected;
+        let lock_address = state3.lock_xmr_address(network);
+        bob_monero_wallet
+            .verify_lock_destination(&transfer_proof, lock_address, xmr)
+            .await?;
+
+        let error = bob_monero_wallet
+            .verify_lock_destination(&transfer_proof, bob_monero_wallet.get_main_address(), xmr)
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<LockDestinationMismatch>().is_some());
+
+        Ok(())
+    })
+    .await;
+}
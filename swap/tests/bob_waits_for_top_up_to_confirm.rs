@@ -0,0 +1,48 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::{ChainParams, ConfirmedFundingConfig};
+use std::time::Duration;
+use swap::bitcoin;
+use swap::protocol::bob::BobState;
+use swap::protocol::{bob, State};
+
+/// Only confirmed outputs fund the lock transaction. Bob keeps waiting for a
+/// top-up while it is in the mempool and sets up the swap once it confirmed.
+#[tokio::test]
+async fn given_unconfirmed_top_up_bob_waits_until_it_confirms() {
+    let chain_params = ChainParams {
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(ConfirmedFundingConfig, chain_params, |mut ctx| async move {
+        let shortfall = bitcoin::Amount::from_sat(10_000);
+        let (bob_swap, _, bob_bitcoin_wallet) = ctx
+            .bob_swap_short_of_funds(shortfall, Duration::from_secs(120))
+            .await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        ctx.send_unconfirmed(bob_bitcoin_wallet.new_address().await?, shortfall * 2)
+            .await?;
+
+        // Give Bob enough syncs to see the top-up in the mempool
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(bob_bitcoin_wallet.balance().await? >= ctx.btc_amount() + shortfall);
+        let bob_states = bob_db.get_states(bob_swap_id).await.unwrap_or_default();
+        assert!(!bob_states
+            .iter()
+            .any(|(_, state)| matches!(state, State::Bob(BobState::SwapSetupCompleted(..)))));
+        assert!(!bob_swap.is_finished());
+
+        ctx.mine_blocks(1).await?;
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,92 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_lock_transaction_sent;
+use harness::bob_run_until::is_btc_locked;
+use harness::FastCancelConfig;
+use swap::asb::FixedRate;
+use swap::bitcoin::{ExpiredTimelocks, TimelockStatus};
+use swap::cli::{watch_only_resume, LockOutcome};
+use swap::env::GetConfig;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database};
+
+#[tokio::test]
+async fn given_lock_txid_and_public_keys_reports_lock_amount_confirmations_and_timelocks() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_xmr_lock_transaction_sent,
+            FixedRate::default(),
+        ));
+
+        let bob_state = bob_swap.await??;
+        alice_swap.await??;
+        bob_join_handle.abort();
+
+        let tx_lock = match bob_state {
+            BobState::BtcLocked { state3, .. } => state3.tx_lock,
+            _ => panic!("Bob in unexpected state {}", bob_state),
+        };
+        // Only the public keys and the lock transaction id survived
+        let keys = bob_db.swap_keys(bob_swap_id).await?;
+        let env_config = FastCancelConfig::get_config();
+
+        bob_bitcoin_wallet
+            .subscribe_to(tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(1)
+            .await?;
+
+        let report = watch_only_resume(
+            &bob_bitcoin_wallet,
+            tx_lock.txid(),
+            keys.alice_bitcoin,
+            keys.bob_bitcoin,
+            env_config.bitcoin_cancel_timelock,
+            env_config.bitcoin_punish_timelock,
+        )
+        .await?;
+
+        assert_eq!(report.lock_amount, tx_lock.lock_amount());
+        assert!(report.lock_status.is_confirmed_with(1));
+        assert!(matches!(
+            report.cancel_timelock,
+            TimelockStatus::Remaining { .. }
+        ));
+        assert_eq!(report.expired_timelocks, ExpiredTimelocks::None);
+        assert_eq!(report.outcome, LockOutcome::Unspent);
+
+        bob_bitcoin_wallet
+            .subscribe_to(tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(env_config.bitcoin_cancel_timelock)
+            .await?;
+
+        let report = watch_only_resume(
+            &bob_bitcoin_wallet,
+            tx_lock.txid(),
+            keys.alice_bitcoin,
+            keys.bob_bitcoin,
+            env_config.bitcoin_cancel_timelock,
+            env_config.bitcoin_punish_timelock,
+        )
+        .await?;
+
+        assert!(report
+            .lock_status
+            .is_confirmed_with(env_config.bitcoin_cancel_timelock));
+        assert_eq!(report.cancel_timelock, TimelockStatus::Expired);
+        assert_eq!(report.expired_timelocks, ExpiredTimelocks::Cancel);
+        assert_eq!(report.outcome, LockOutcome::Unspent);
+
+        Ok(())
+    })
+    .await
+}
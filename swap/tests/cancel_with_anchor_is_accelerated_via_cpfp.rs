@@ -0,0 +1,104 @@
+pub mod harness;
+
+use harness::{ChainParams, SlowCancelConfig};
+use rand::rngs::OsRng;
+use std::time::Duration;
+use swap::bitcoin::wallet::ScriptStatus;
+use swap::bitcoin::{Amount, CancelTimelock, SecretKey, TxCancel, TxLock, Wallet};
+use swap::env::{Config, GetConfig, Regtest};
+use swap::seed::Seed;
+use tempfile::tempdir;
+
+/// Above the fee rate the cancel transaction is signed with, so the cancel
+/// transaction is not mined on its own.
+const BLOCK_MIN_FEE_RATE: u64 = 5;
+
+#[tokio::test]
+async fn given_cancel_below_block_min_fee_rate_anchor_spend_gets_it_confirmed() {
+    let chain_params = ChainParams {
+        block_min_fee_rate: Some(BLOCK_MIN_FEE_RATE),
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |ctx| async move {
+        // The wallet never estimates less than 25 sat/vB, so bumping the cancel
+        // transaction outbids the block minimum
+        let env_config = Config {
+            bitcoin_min_relay_fee_rate: 25,
+            ..Regtest::get_config()
+        };
+        let data_dir = tempdir()?;
+        let wallet = Wallet::new(
+            ctx.electrum_rpc_url(),
+            data_dir.path(),
+            Seed::random()?.derive_extended_private_key(env_config.bitcoin_network)?,
+            env_config,
+            1,
+        )
+        .await?;
+        let funding = Amount::from_sat(1_000_000);
+        ctx.mint(wallet.new_address().await?, funding).await?;
+        wallet
+            .await_balance(funding, Duration::from_secs(60))
+            .await?;
+
+        let a = SecretKey::new_random(&mut OsRng);
+        let b = SecretKey::new_random(&mut OsRng);
+        let tx_lock = TxLock::new(
+            &wallet,
+            Amount::from_sat(100_000),
+            a.public(),
+            b.public(),
+            wallet.new_address().await?,
+            None,
+        )
+        .await?;
+        let signed_lock = wallet.sign_and_finalize(tx_lock.clone().into()).await?;
+        let (_, lock_subscription) = wallet.broadcast(signed_lock, "lock").await?;
+        lock_subscription.wait_until_final().await?;
+
+        // 1 sat/vB, the minimum relay fee rate
+        let cancel_fee = Amount::from_sat(TxCancel::weight_with_anchor() as u64 / 4);
+        let tx_cancel = TxCancel::with_anchor(
+            &tx_lock,
+            CancelTimelock::new(1),
+            a.public(),
+            b.public(),
+            cancel_fee,
+        );
+        let anchor = tx_cancel
+            .anchor_outpoint()
+            .expect("cancel to have an anchor");
+        let sig_b = b.sign(tx_cancel.digest());
+        let signed_cancel = tx_cancel.complete_as_alice(a, b.public(), sig_b)?;
+        let (cancel_txid, cancel_subscription) =
+            wallet.broadcast(signed_cancel.clone(), "cancel").await?;
+        cancel_subscription.wait_until_seen().await?;
+
+        let height = ctx.bitcoin_block_height().await?;
+        while ctx.bitcoin_block_height().await? < height + 3 {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        wallet.sync().await?;
+        let cancel_output = (cancel_txid, signed_cancel.output[0].script_pubkey.clone());
+        assert_eq!(
+            wallet.status_of_script(&cancel_output).await?,
+            ScriptStatus::InMempool
+        );
+
+        let child_txid = wallet
+            .bump_via_anchor(&signed_cancel, cancel_fee)
+            .await?
+            .expect("cancel fee to be below the estimated fee rate");
+        cancel_subscription.wait_until_final().await?;
+
+        let child = wallet.get_raw_transaction(child_txid).await?;
+        assert!(child
+            .input
+            .iter()
+            .any(|input| input.previous_output == anchor));
+
+        Ok(())
+    })
+    .await;
+}
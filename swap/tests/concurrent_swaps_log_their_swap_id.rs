@@ -0,0 +1,81 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use harness::{alice_run_until, bob_run_until};
+use swap::asb::FixedRate;
+use swap::protocol::{alice, bob};
+use swap::tracing_ext::capture_logs;
+use tracing_subscriber::filter::LevelFilter;
+
+/// Two swaps run side by side up to the Bitcoin lock, every line Alice's and
+/// Bob's state machines and Bob's event loops log carries the id of the swap
+/// it belongs to and the role.
+#[tokio::test]
+async fn given_two_concurrent_swaps_then_their_logs_carry_swap_id_and_role() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let writer = capture_logs(LevelFilter::DEBUG);
+
+        let (bob_swap_1, _) = ctx.bob_swap().await;
+        let swap_id_1 = bob_swap_1.id;
+        let bob_swap_1 = tokio::spawn(bob::run_until(bob_swap_1, bob_run_until::is_btc_locked));
+        let alice_swap_1 = ctx.alice_next_swap().await;
+
+        let (bob_swap_2, _) = ctx.bob_swap().await;
+        let swap_id_2 = bob_swap_2.id;
+        let bob_swap_2 = tokio::spawn(bob::run_until(bob_swap_2, bob_run_until::is_btc_locked));
+        let alice_swap_2 = ctx.alice_next_swap().await;
+
+        let alice_swap_1 = tokio::spawn(alice::run_until(
+            alice_swap_1,
+            alice_run_until::is_btc_locked,
+            FixedRate::default(),
+        ));
+        let alice_swap_2 = tokio::spawn(alice::run_until(
+            alice_swap_2,
+            alice_run_until::is_btc_locked,
+            FixedRate::default(),
+        ));
+
+        bob_swap_1.await??;
+        bob_swap_2.await??;
+        alice_swap_1.await??;
+        alice_swap_2.await??;
+
+        let captured = writer.captured();
+        let span_of = |swap_id, role| format!("swap{{swap_id={} role={}}}", swap_id, role);
+        let spans = [
+            span_of(swap_id_1, "alice"),
+            span_of(swap_id_1, "bob"),
+            span_of(swap_id_2, "alice"),
+            span_of(swap_id_2, "bob"),
+        ];
+        let targets = [
+            (" swap::protocol::alice::swap: ", "alice"),
+            (" swap::protocol::bob::swap: ", "bob"),
+            (" swap::cli::event_loop: ", "bob"),
+        ];
+
+        for line in captured.lines() {
+            let role = match targets.iter().find(|(target, _)| line.contains(target)) {
+                Some((_, role)) => *role,
+                None => continue,
+            };
+
+            assert!(
+                [swap_id_1, swap_id_2]
+                    .iter()
+                    .filter(|swap_id| line.contains(&span_of(**swap_id, role)))
+                    .count()
+                    == 1,
+                "log line does not carry exactly one swap id: {}",
+                line
+            );
+        }
+        for span in spans {
+            assert!(captured.contains(&span), "no log line carries {}", span);
+        }
+
+        Ok(())
+    })
+    .await;
+}
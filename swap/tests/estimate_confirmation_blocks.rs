@@ -0,0 +1,34 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::bitcoin::Amount;
+
+#[tokio::test]
+async fn given_almost_empty_mempool_transaction_confirms_in_next_block() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let wallet = ctx.bob_bitcoin_wallet();
+
+        let psbt = wallet
+            .send_to_address(
+                wallet.new_address().await?,
+                Amount::from_sat(100_000),
+                None,
+                None,
+            )
+            .await?;
+        let transaction = wallet.sign_and_finalize(psbt).await?;
+        let (txid, subscription) = wallet.broadcast(transaction, "test").await?;
+
+        // Blocks are mined every second, the transaction might be confirmed already
+        let blocks = wallet.estimate_confirmation_blocks(txid).await?;
+        assert!(blocks <= 1, "expected at most 1 block, got {}", blocks);
+
+        subscription.wait_until_final().await?;
+
+        let blocks = wallet.estimate_confirmation_blocks(txid).await?;
+        assert_eq!(blocks, 0);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,50 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use rand::rngs::OsRng;
+use swap::bitcoin::wallet::funding_of_descriptor;
+use swap::bitcoin::{build_lock_descriptor, Amount, SecretKey, TxLock};
+
+#[tokio::test]
+async fn given_funded_lock_address_reports_amount_and_confirmations() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let wallet = ctx.bob_bitcoin_wallet();
+        let alice_key = SecretKey::new_random(&mut OsRng).public();
+        let bob_key = SecretKey::new_random(&mut OsRng).public();
+        let descriptor = build_lock_descriptor(alice_key, bob_key);
+
+        let fundings = funding_of_descriptor(ctx.electrum_rpc_url(), &descriptor).await?;
+        assert!(fundings.is_empty());
+
+        let amount = Amount::from_sat(100_000);
+        let tx_lock = TxLock::new(
+            &wallet,
+            amount,
+            alice_key,
+            bob_key,
+            wallet.new_address().await?,
+            None,
+        )
+        .await?;
+        let signed_lock = wallet.sign_and_finalize(tx_lock.into()).await?;
+        let (txid, subscription) = wallet.broadcast(signed_lock, "lock").await?;
+        subscription.wait_until_final().await?;
+
+        let fundings = funding_of_descriptor(ctx.electrum_rpc_url(), &descriptor).await?;
+
+        match fundings.as_slice() {
+            [funding] => {
+                assert_eq!(funding.txid, txid);
+                assert_eq!(funding.amount, amount);
+                assert!(funding.status.is_confirmed());
+            }
+            _ => panic!(
+                "expected exactly one funding transaction, got {:?}",
+                fundings
+            ),
+        }
+
+        Ok(())
+    })
+    .await;
+}
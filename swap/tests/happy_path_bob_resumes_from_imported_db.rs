@@ -0,0 +1,41 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database};
+
+/// Bob moves his database to a fresh one through an export and an import
+/// after locking the Bitcoin, resumes the swap from it and redeems.
+#[tokio::test]
+async fn given_bob_imports_swap_into_fresh_db_after_btc_locked_then_swap_completes() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+        let history = bob_db.get_states(bob_swap_id).await?;
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_imported_db(bob_join_handle, bob_swap_id)
+            .await;
+        assert_eq!(bob_swap.db.get_states(bob_swap_id).await?, history);
+        assert!(matches!(bob_swap.state, BobState::BtcLocked { .. }));
+
+        let bob_state = bob::run(bob_swap).await?;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,52 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::{alice, bob, Database};
+use swap::{bitcoin, monero};
+use tokio::join;
+
+#[tokio::test]
+async fn given_happy_path_swap_then_receipt_lists_lock_and_redeem_with_fees() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_db = alice_swap.db.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let (bob_state, alice_state) = join!(bob_swap, alice_swap);
+        ctx.assert_alice_redeemed(alice_state??).await;
+        ctx.assert_bob_redeemed(bob_state??).await;
+
+        let bob_receipt = bob_db.receipt(bob_swap_id).await?;
+        let alice_receipt = alice_db.receipt(bob_swap_id).await?;
+
+        let tx_redeem_id = bob_receipt.tx_redeem_id.expect("redeem transaction");
+        bob_bitcoin_wallet
+            .get_raw_transaction(bob_receipt.tx_lock_id)
+            .await?;
+        bob_bitcoin_wallet.get_raw_transaction(tx_redeem_id).await?;
+        assert_eq!(bob_receipt.tx_cancel_id, None);
+        assert_eq!(bob_receipt.tx_refund_id, None);
+        assert!(bob_receipt.bitcoin_fees > bitcoin::Amount::ZERO);
+        assert!(bob_receipt.monero_redeem_fee > Some(monero::Amount::ZERO));
+        assert_eq!(bob_receipt.monero_lock_fee, None);
+        assert!(bob_receipt.started_at < bob_receipt.finished_at);
+
+        assert_eq!(alice_receipt.tx_lock_id, bob_receipt.tx_lock_id);
+        assert_eq!(alice_receipt.tx_redeem_id, Some(tx_redeem_id));
+        assert_eq!(alice_receipt.xmr_lock_tx_hash, bob_receipt.xmr_lock_tx_hash);
+        assert!(alice_receipt.xmr_lock_tx_hash.is_some());
+        assert!(alice_receipt.bitcoin_fees > bitcoin::Amount::ZERO);
+        assert!(alice_receipt.monero_lock_fee > Some(monero::Amount::ZERO));
+        assert_eq!(alice_receipt.monero_redeem_fee, None);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,51 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::database::SwapAlreadyExists;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn given_caller_supplied_swap_id_swap_persists_and_resumes_under_that_id() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let swap_id = Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+
+        let (bob_swap, bob_join_handle) = ctx.bob_swap_with_id(swap_id).await;
+        assert_eq!(bob_swap.id, swap_id);
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        // A new swap must not reuse the id of an existing one
+        bob_join_handle.abort();
+        let (duplicate_swap, duplicate_join_handle) = ctx.bob_swap_with_id(swap_id).await;
+        let error = bob::run(duplicate_swap).await.unwrap_err();
+        assert_eq!(
+            error.downcast::<SwapAlreadyExists>()?,
+            SwapAlreadyExists { swap_id }
+        );
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(duplicate_join_handle, swap_id)
+            .await;
+        assert_eq!(bob_swap.id, swap_id);
+        assert!(matches!(bob_swap.state, BobState::BtcLocked { .. }));
+
+        let bob_state = bob::run(bob_swap).await?;
+
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        Ok(())
+    })
+    .await;
+}
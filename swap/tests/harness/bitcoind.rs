@@ -83,23 +83,26 @@ impl Bitcoind {
         self.volume = Some(volume);
         self
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct BitcoindArgs;
 
-impl Default for BitcoindArgs {
-    fn default() -> Self {
-        BitcoindArgs
+    /// Only mines transactions that pay at least `sat_per_vb`, on their own or
+    /// together with an unconfirmed child.
+    pub fn with_block_min_fee_rate(mut self, sat_per_vb: u64) -> Self {
+        self.args.block_min_fee_rate = Some(sat_per_vb);
+        self
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct BitcoindArgs {
+    block_min_fee_rate: Option<u64>,
+}
+
 impl IntoIterator for BitcoindArgs {
     type Item = String;
     type IntoIter = ::std::vec::IntoIter<String>;
 
     fn into_iter(self) -> <Self as IntoIterator>::IntoIter {
-        let args = vec![
+        let mut args = vec![
             "-server".to_string(),
             "-regtest".to_string(),
             "-listen=1".to_string(),
@@ -117,6 +120,14 @@ impl IntoIterator for BitcoindArgs {
             "-rest".to_string(),
         ];
 
+        if let Some(sat_per_vb) = self.block_min_fee_rate {
+            // in BTC/kvB
+            args.push(format!(
+                "-blockmintxfee={:.8}",
+                sat_per_vb as f64 / 100_000.0
+            ));
+        }
+
         args.into_iter()
     }
 }
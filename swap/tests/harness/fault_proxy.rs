@@ -0,0 +1,187 @@
+//! A TCP proxy between Bob and Alice that tests can command to disturb the
+//! libp2p connections between them, e.g. to drop the connection while Bob
+//! sends the encrypted signature.
+//!
+//! The proxy only sees the encrypted stream, so the protocol phase at which a
+//! fault hits is up to the test: run a party until the phase, inject the
+//! fault, then let the swap continue.
+
+use anyhow::{bail, Context, Result};
+use libp2p::core::Multiaddr;
+use libp2p::multiaddr::Protocol;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Forward all traffic unchanged.
+    None,
+    /// Close all open connections and refuse new ones.
+    Drop,
+    /// Hold every chunk of traffic back for the given duration before
+    /// forwarding it.
+    Delay(Duration),
+    /// Forward every chunk of traffic twice.
+    ///
+    /// libp2p detects the duplicated frames and closes the connection.
+    Duplicate,
+    /// Hold every other chunk of traffic back and forward it after the next
+    /// one.
+    ///
+    /// libp2p detects the swapped frames and closes the connection. A chunk
+    /// that is held back is only forwarded once more traffic arrives.
+    Reorder,
+}
+
+pub struct FaultProxy {
+    address: Multiaddr,
+    fault: watch::Sender<Fault>,
+    task: JoinHandle<()>,
+}
+
+impl FaultProxy {
+    /// Starts forwarding the connections to a free local port to `target`,
+    /// which has to be an `/ip4/.../tcp/...` address.
+    pub async fn start(target: Multiaddr) -> Result<Self> {
+        let target = socket_addr(&target)?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = format!("/ip4/127.0.0.1/tcp/{}", listener.local_addr()?.port())
+            .parse()
+            .expect("valid multiaddr");
+        let (fault, fault_receiver) = watch::channel(Fault::None);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let inbound = match listener.accept().await {
+                    Ok((inbound, _)) => inbound,
+                    Err(error) => {
+                        tracing::warn!("Fault proxy failed to accept connection: {:#}", error);
+                        continue;
+                    }
+                };
+
+                if *fault_receiver.borrow() == Fault::Drop {
+                    tracing::debug!("Fault proxy refused connection");
+                    continue;
+                }
+
+                let fault = fault_receiver.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = forward(inbound, target, fault).await {
+                        tracing::debug!("Fault proxy closed connection: {:#}", error);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            fault,
+            task,
+        })
+    }
+
+    /// The address to dial instead of the target.
+    pub fn address(&self) -> Multiaddr {
+        self.address.clone()
+    }
+
+    /// Applies `fault` to all open and future connections until another fault
+    /// is injected.
+    pub fn inject(&self, fault: Fault) {
+        tracing::info!(?fault, "Injecting network fault");
+        let _ = self.fault.send(fault);
+    }
+
+    /// Forwards all traffic unchanged again.
+    pub fn heal(&self) {
+        self.inject(Fault::None)
+    }
+}
+
+impl Drop for FaultProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn socket_addr(address: &Multiaddr) -> Result<SocketAddr> {
+    match address.iter().collect::<Vec<_>>().as_slice() {
+        [Protocol::Ip4(ip), Protocol::Tcp(port)] => Ok(SocketAddr::from((*ip, *port))),
+        _ => bail!(
+            "Fault proxy only supports /ip4/.../tcp/... addresses, got {}",
+            address
+        ),
+    }
+}
+
+/// Forwards traffic between `inbound` and `target` in both directions until
+/// either side closes the connection or the connection is dropped.
+async fn forward(
+    inbound: TcpStream,
+    target: SocketAddr,
+    mut fault: watch::Receiver<Fault>,
+) -> Result<()> {
+    let outbound = TcpStream::connect(target)
+        .await
+        .context("Failed to connect to target")?;
+
+    let (inbound_read, inbound_write) = inbound.into_split();
+    let (outbound_read, outbound_write) = outbound.into_split();
+
+    tokio::select! {
+        result = pipe(inbound_read, outbound_write, fault.clone()) => result,
+        result = pipe(outbound_read, inbound_write, fault.clone()) => result,
+        _ = fault.wait_for(|fault| *fault == Fault::Drop) => {
+            bail!("Connection dropped")
+        }
+    }
+}
+
+async fn pipe(
+    mut from: OwnedReadHalf,
+    mut to: OwnedWriteHalf,
+    fault: watch::Receiver<Fault>,
+) -> Result<()> {
+    let mut buffer = vec![0u8; 8192];
+    let mut held_back: Option<Vec<u8>> = None;
+
+    loop {
+        let read = from.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        let chunk = &buffer[..read];
+
+        let current_fault = *fault.borrow();
+        if current_fault != Fault::Reorder {
+            if let Some(previous) = held_back.take() {
+                to.write_all(&previous).await?;
+            }
+        }
+
+        match current_fault {
+            Fault::None | Fault::Drop => to.write_all(chunk).await?,
+            Fault::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                to.write_all(chunk).await?;
+            }
+            Fault::Duplicate => {
+                to.write_all(chunk).await?;
+                to.write_all(chunk).await?;
+            }
+            Fault::Reorder => match held_back.take() {
+                None => held_back = Some(chunk.to_vec()),
+                Some(previous) => {
+                    to.write_all(chunk).await?;
+                    to.write_all(&previous).await?;
+                }
+            },
+        }
+    }
+}
@@ -1,9 +1,12 @@
 mod bitcoind;
 mod electrs;
+pub mod fault_proxy;
+pub mod self_test;
 
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use bitcoin_harness::{BitcoindRpcApi, Client};
+use fault_proxy::{Fault, FaultProxy};
 use futures::Future;
 use get_port::get_port;
 use libp2p::core::Multiaddr;
@@ -14,16 +17,16 @@ use std::fmt;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use swap::asb::FixedRate;
+use swap::asb::{AbortHandle, FixedRate, LatestRate, PauseHandle, SWAP_CHANNEL_CAPACITY};
 use swap::bitcoin::{CancelTimelock, PunishTimelock, TxCancel, TxPunish, TxRedeem, TxRefund};
-use swap::database::SqliteDatabase;
+use swap::database::{OnCollision, SqliteDatabase};
 use swap::env::{Config, GetConfig};
 use swap::fs::ensure_directory_exists;
 use swap::network::rendezvous::XmrBtcNamespace;
 use swap::network::swarm;
 use swap::protocol::alice::{AliceState, Swap};
 use swap::protocol::bob::BobState;
-use swap::protocol::{alice, bob};
+use swap::protocol::{alice, bob, Checkpoint, Database, State};
 use swap::seed::Seed;
 use swap::{asb, bitcoin, cli, env, monero};
 use tempfile::{tempdir, NamedTempFile};
@@ -32,12 +35,50 @@ use testcontainers::{Container, Docker, RunArgs};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
 use tokio::task::JoinHandle;
-use tokio::time::{interval, timeout};
+use tokio::time::timeout;
 use tracing_subscriber::util::SubscriberInitExt;
 use url::Url;
 use uuid::Uuid;
 
-pub async fn setup_test<T, F, C>(_config: C, testfn: T)
+pub async fn setup_test<T, F, C>(config: C, testfn: T)
+where
+    T: Fn(TestContext) -> F,
+    F: Future<Output = Result<()>>,
+    C: GetConfig,
+{
+    setup_test_with_chain(config, ChainParams::default(), testfn).await
+}
+
+/// How the harness initializes the Bitcoin chain.
+#[derive(Debug, Clone, Copy)]
+pub struct ChainParams {
+    /// Blocks mined before any coinbase output becomes spendable.
+    pub maturity_blocks: u32,
+    /// Number of spendable coinbase outputs the chain starts with, the
+    /// starting balances of all wallets are funded from them.
+    pub spendable_coinbase_outputs: u32,
+    /// Mine a block every second once the chain is initialized. If `false`
+    /// tests control the chain through [`TestContext::mine_blocks`].
+    pub mine_in_background: bool,
+    /// The lowest fee rate in sat/vB at which transactions are mined, on their
+    /// own or together with an unconfirmed child. bitcoind's default if `None`.
+    pub block_min_fee_rate: Option<u64>,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        Self {
+            maturity_blocks: 101,
+            spendable_coinbase_outputs: 5,
+            mine_in_background: true,
+            block_min_fee_rate: None,
+        }
+    }
+}
+
+/// Same as [`setup_test`] with the Bitcoin chain initialized according to
+/// `chain_params`.
+pub async fn setup_test_with_chain<T, F, C>(_config: C, chain_params: ChainParams, testfn: T)
 where
     T: Fn(TestContext) -> F,
     F: Future<Output = Result<()>>,
@@ -52,11 +93,11 @@ where
 
     let env_config = C::get_config();
 
-    let (monero, containers) = init_containers(&cli).await;
+    let (monero, containers) = init_containers(&cli, chain_params).await;
     monero.init_miner().await.unwrap();
 
     let btc_amount = bitcoin::Amount::from_sat(1_000_000);
-    let xmr_amount = monero::Amount::from_monero(btc_amount.to_btc() / FixedRate::RATE).unwrap();
+    let xmr_amount = FixedRate::default().value().apply(btc_amount).unwrap();
 
     let alice_starting_balances =
         StartingBalances::new(bitcoin::Amount::ZERO, xmr_amount, Some(10));
@@ -66,7 +107,9 @@ where
         .get_host_port(electrs::RPC_PORT)
         .expect("Could not map electrs rpc port");
 
-    let alice_seed = Seed::random().unwrap();
+    let electrum_rpc_url = Url::parse(&format!("tcp://@localhost:{}", electrs_rpc_port)).unwrap();
+
+    let alice_seed = Seed::deterministic(b"alice");
     let (alice_bitcoin_wallet, alice_monero_wallet) = init_test_wallets(
         MONERO_WALLET_NAME_ALICE,
         containers.bitcoind_url.clone(),
@@ -92,15 +135,26 @@ where
         env_config,
         alice_bitcoin_wallet.clone(),
         alice_monero_wallet.clone(),
+        FixedRate::default(),
+        bitcoin::Amount::from_sat(u64::MAX),
+        None,
+        None,
+        None,
+        SWAP_CHANNEL_CAPACITY,
     )
     .await;
 
-    let bob_seed = Seed::random().unwrap();
+    // Bob dials Alice through the proxy, so tests can disturb their connection
+    let fault_proxy = FaultProxy::start(alice_listen_address.clone())
+        .await
+        .expect("Failed to start fault proxy");
+
+    let bob_seed = Seed::deterministic(b"bob");
     let bob_starting_balances = StartingBalances::new(btc_amount * 10, monero::Amount::ZERO, None);
 
     let (bob_bitcoin_wallet, bob_monero_wallet) = init_test_wallets(
         MONERO_WALLET_NAME_BOB,
-        containers.bitcoind_url,
+        containers.bitcoind_url.clone(),
         &monero,
         bob_starting_balances.clone(),
         tempdir().unwrap().path(),
@@ -111,11 +165,11 @@ where
     .await;
 
     let bob_params = BobParams {
-        seed: Seed::random().unwrap(),
+        seed: Seed::deterministic(b"bob swaps"),
         db_path: NamedTempFile::new().unwrap().path().to_path_buf(),
         bitcoin_wallet: bob_bitcoin_wallet.clone(),
         monero_wallet: bob_monero_wallet.clone(),
-        alice_address: alice_listen_address.clone(),
+        alice_address: fault_proxy.address(),
         alice_peer_id: alice_handle.peer_id,
         env_config,
     };
@@ -124,6 +178,8 @@ where
 
     let test = TestContext {
         env_config,
+        bitcoind_url: containers.bitcoind_url.clone(),
+        electrum_rpc_url,
         btc_amount,
         xmr_amount,
         alice_seed,
@@ -134,22 +190,29 @@ where
         alice_monero_wallet,
         alice_swap_handle,
         alice_handle,
+        fault_proxy,
         bob_params,
         bob_starting_balances,
         bob_bitcoin_wallet,
         bob_monero_wallet,
+        assert_timeout: AssertTimeout::default(),
     };
 
     testfn(test).await.unwrap()
 }
 
-async fn init_containers(cli: &Cli) -> (Monero, Containers<'_>) {
+async fn init_containers(cli: &Cli, chain_params: ChainParams) -> (Monero, Containers<'_>) {
     let prefix = random_prefix();
     let bitcoind_name = format!("{}_{}", prefix, "bitcoind");
-    let (bitcoind, bitcoind_url) =
-        init_bitcoind_container(cli, prefix.clone(), bitcoind_name.clone(), prefix.clone())
-            .await
-            .expect("could not init bitcoind");
+    let (bitcoind, bitcoind_url) = init_bitcoind_container(
+        cli,
+        prefix.clone(),
+        bitcoind_name.clone(),
+        prefix.clone(),
+        chain_params,
+    )
+    .await
+    .expect("could not init bitcoind");
     let electrs = init_electrs_container(cli, prefix.clone(), bitcoind_name, prefix)
         .await
         .expect("could not init electrs");
@@ -175,8 +238,12 @@ async fn init_bitcoind_container(
     volume: String,
     name: String,
     network: String,
+    chain_params: ChainParams,
 ) -> Result<(Container<'_, Cli, bitcoind::Bitcoind>, Url)> {
-    let image = bitcoind::Bitcoind::default().with_volume(volume);
+    let mut image = bitcoind::Bitcoind::default().with_volume(volume);
+    if let Some(block_min_fee_rate) = chain_params.block_min_fee_rate {
+        image = image.with_block_min_fee_rate(block_min_fee_rate);
+    }
 
     let run_args = RunArgs::default().with_name(name).with_network(network);
 
@@ -195,7 +262,7 @@ async fn init_bitcoind_container(
         Url::parse(&input).unwrap()
     };
 
-    init_bitcoind(bitcoind_url.clone(), 5).await?;
+    init_bitcoind(bitcoind_url.clone(), chain_params).await?;
 
     Ok((docker, bitcoind_url.clone()))
 }
@@ -219,14 +286,24 @@ pub async fn init_electrs_container(
     Ok(docker)
 }
 
-async fn start_alice(
+#[allow(clippy::too_many_arguments)]
+async fn start_alice<LR>(
     seed: &Seed,
     db_path: PathBuf,
     listen_address: Multiaddr,
     env_config: Config,
     bitcoin_wallet: Arc<bitcoin::Wallet>,
     monero_wallet: Arc<monero::Wallet>,
-) -> (AliceApplicationHandle, Receiver<alice::Swap>) {
+    latest_rate: LR,
+    max_buy: bitcoin::Amount,
+    max_monero_lock_fee: Option<monero::Amount>,
+    min_monero_output: Option<monero::Amount>,
+    monero_reserve: Option<monero::Amount>,
+    swap_channel_capacity: usize,
+) -> (AliceApplicationHandle, Receiver<alice::Swap>)
+where
+    LR: LatestRate + Send + 'static + fmt::Debug + Clone,
+{
     if let Some(parent_dir) = db_path.parent() {
         ensure_directory_exists(parent_dir).unwrap();
     }
@@ -236,15 +313,15 @@ async fn start_alice(
     let db = Arc::new(SqliteDatabase::open(db_path.as_path()).await.unwrap());
 
     let min_buy = bitcoin::Amount::from_sat(u64::MIN);
-    let max_buy = bitcoin::Amount::from_sat(u64::MAX);
-    let latest_rate = FixedRate::default();
     let resume_only = false;
 
     let mut swarm = swarm::asb(
         seed,
         min_buy,
         max_buy,
-        latest_rate,
+        max_monero_lock_fee,
+        min_monero_output,
+        latest_rate.clone(),
         resume_only,
         env_config,
         XmrBtcNamespace::Testnet,
@@ -259,17 +336,31 @@ async fn start_alice(
         bitcoin_wallet,
         monero_wallet,
         db,
-        FixedRate::default(),
+        latest_rate,
         min_buy,
         max_buy,
+        min_monero_output,
         None,
+        None,
+        monero_reserve,
+        swap_channel_capacity,
     )
     .unwrap();
 
     let peer_id = event_loop.peer_id();
+    let pause_handle = event_loop.pause_handle();
+    let abort_handle = event_loop.abort_handle();
     let handle = tokio::spawn(event_loop.run());
 
-    (AliceApplicationHandle { handle, peer_id }, swap_handle)
+    (
+        AliceApplicationHandle {
+            handle,
+            peer_id,
+            pause_handle,
+            abort_handle,
+        },
+        swap_handle,
+    )
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -317,7 +408,10 @@ async fn init_test_wallets(
         1,
     )
     .await
-    .expect("could not init btc wallet");
+    .expect("could not init btc wallet")
+    // Every cancel, redeem, refund and punish of the tests has to pay exactly
+    // the fee that was negotiated.
+    .with_strict_fees(bitcoin::Amount::ZERO);
 
     if starting_balances.btc != bitcoin::Amount::ZERO {
         mint(
@@ -328,25 +422,10 @@ async fn init_test_wallets(
         .await
         .expect("could not mint btc starting balance");
 
-        let mut interval = interval(Duration::from_secs(1u64));
-        let mut retries = 0u8;
-        let max_retries = 30u8;
-        loop {
-            retries += 1;
-            btc_wallet.sync().await.unwrap();
-
-            let btc_balance = btc_wallet.balance().await.unwrap();
-
-            if btc_balance == starting_balances.btc {
-                break;
-            } else if retries == max_retries {
-                panic!(
-                    "Bitcoin wallet initialization failed, reached max retries upon balance sync"
-                )
-            }
-
-            interval.tick().await;
-        }
+        btc_wallet
+            .await_balance(starting_balances.btc, Duration::from_secs(30))
+            .await
+            .expect("Bitcoin wallet initialization failed, starting balance did not arrive");
     }
 
     (Arc::new(btc_wallet), Arc::new(xmr_wallet))
@@ -442,8 +521,14 @@ impl BobParams {
         &self,
         btc_amount: bitcoin::Amount,
     ) -> Result<(bob::Swap, cli::EventLoop)> {
-        let swap_id = Uuid::new_v4();
+        self.new_swap_with_id(btc_amount, Uuid::new_v4()).await
+    }
 
+    pub async fn new_swap_with_id(
+        &self,
+        btc_amount: bitcoin::Amount,
+        swap_id: Uuid,
+    ) -> Result<(bob::Swap, cli::EventLoop)> {
         let (event_loop, handle) = self.new_eventloop(swap_id).await?;
 
         if let Some(parent_dir) = self.db_path.parent() {
@@ -503,6 +588,8 @@ impl BobApplicationHandle {
 pub struct AliceApplicationHandle {
     handle: JoinHandle<()>,
     peer_id: PeerId,
+    pause_handle: PauseHandle,
+    abort_handle: AbortHandle,
 }
 
 impl AliceApplicationHandle {
@@ -511,8 +598,33 @@ impl AliceApplicationHandle {
     }
 }
 
+/// An additional ASB started by [`TestContext::start_second_alice`].
+pub struct SecondAlice {
+    pub peer_id: PeerId,
+    pub address: Multiaddr,
+    handle: AliceApplicationHandle,
+    swap_handle: Receiver<alice::Swap>,
+}
+
+impl SecondAlice {
+    pub async fn next_swap(&mut self) -> alice::Swap {
+        timeout(Duration::from_secs(20), self.swap_handle.recv())
+            .await
+            .expect("No swap of the second Alice within 20 seconds")
+            .unwrap()
+    }
+}
+
+impl Drop for SecondAlice {
+    fn drop(&mut self) {
+        self.handle.abort()
+    }
+}
+
 pub struct TestContext {
     env_config: Config,
+    bitcoind_url: Url,
+    electrum_rpc_url: Url,
 
     btc_amount: bitcoin::Amount,
     xmr_amount: monero::Amount,
@@ -527,14 +639,201 @@ pub struct TestContext {
     alice_swap_handle: mpsc::Receiver<Swap>,
     alice_handle: AliceApplicationHandle,
 
+    fault_proxy: FaultProxy,
+
     bob_params: BobParams,
     bob_starting_balances: StartingBalances,
     bob_bitcoin_wallet: Arc<bitcoin::Wallet>,
     bob_monero_wallet: Arc<monero::Wallet>,
+
+    assert_timeout: AssertTimeout,
 }
 
 impl TestContext {
+    /// Sets how long the `assert_*` helpers wait for balances to settle.
+    pub fn set_assert_timeout(&mut self, assert_timeout: AssertTimeout) {
+        self.assert_timeout = assert_timeout;
+    }
+
+    /// Mines `blocks` blocks, for tests set up without background mining.
+    pub async fn mine_blocks(&self, blocks: u32) -> Result<()> {
+        let bitcoind_client = Client::new(self.bitcoind_url.clone());
+
+        let reward_address = bitcoind_client
+            .with_wallet(BITCOIN_TEST_WALLET_NAME)?
+            .getnewaddress(None, None)
+            .await?;
+        bitcoind_client
+            .generatetoaddress(blocks, reward_address)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sends `amount` to `address` from the coinbase outputs and confirms it.
+    pub async fn mint(&self, address: bitcoin::Address, amount: bitcoin::Amount) -> Result<()> {
+        mint(self.bitcoind_url.clone(), address, amount).await
+    }
+
+    /// Sends `amount` to `address` from the coinbase outputs without mining a
+    /// block, the transaction stays unconfirmed until the next one.
+    pub async fn send_unconfirmed(
+        &self,
+        address: bitcoin::Address,
+        amount: bitcoin::Amount,
+    ) -> Result<()> {
+        Client::new(self.bitcoind_url.clone())
+            .send_to_address(BITCOIN_TEST_WALLET_NAME, address, amount)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn bitcoin_block_height(&self) -> Result<u32> {
+        let block_count = Client::new(self.bitcoind_url.clone())
+            .getblockcount()
+            .await?;
+
+        Ok(block_count)
+    }
+
+    pub fn electrum_rpc_url(&self) -> Url {
+        self.electrum_rpc_url.clone()
+    }
+
+    pub fn btc_amount(&self) -> bitcoin::Amount {
+        self.btc_amount
+    }
+
+    pub fn xmr_amount(&self) -> monero::Amount {
+        self.xmr_amount
+    }
+
+    /// The Monero Alice can spend right now, which her quotes are based on.
+    pub async fn alice_unlocked_xmr_balance(&self) -> Result<monero::Amount> {
+        let balance = self.alice_monero_wallet.get_balance().await?;
+
+        Ok(monero::Amount::from_piconero(balance.unlocked_balance))
+    }
+
+    pub fn alice_bitcoin_wallet(&self) -> Arc<bitcoin::Wallet> {
+        self.alice_bitcoin_wallet.clone()
+    }
+
+    pub fn bob_bitcoin_wallet(&self) -> Arc<bitcoin::Wallet> {
+        self.bob_bitcoin_wallet.clone()
+    }
+
+    pub fn bob_swap_backup_key(&self) -> [u8; 32] {
+        self.bob_params.seed.derive_swap_backup_key()
+    }
+
+    pub fn alice_pause_handle(&self) -> PauseHandle {
+        self.alice_handle.pause_handle.clone()
+    }
+
+    pub fn alice_abort_handle(&self) -> AbortHandle {
+        self.alice_handle.abort_handle.clone()
+    }
+
+    /// Disturbs the connections between Bob and Alice with `fault` until
+    /// [`TestContext::heal_network`] is called.
+    pub fn inject_network_fault(&self, fault: Fault) {
+        self.fault_proxy.inject(fault)
+    }
+
+    pub fn heal_network(&self) {
+        self.fault_proxy.heal()
+    }
+
+    /// Stops Alice's ASB, closing all connections to it.
+    pub fn stop_alice(&self) {
+        self.alice_handle.abort()
+    }
+
     pub async fn restart_alice(&mut self) {
+        self.restart_alice_with_max_monero_lock_fee(None).await
+    }
+
+    /// Restarts Alice so that she declines swaps whose estimated Monero lock
+    /// fee exceeds `max_monero_lock_fee`.
+    pub async fn restart_alice_with_max_monero_lock_fee(
+        &mut self,
+        max_monero_lock_fee: Option<monero::Amount>,
+    ) {
+        self.start_alice_again(
+            FixedRate::default(),
+            max_monero_lock_fee,
+            None,
+            None,
+            SWAP_CHANNEL_CAPACITY,
+        )
+        .await
+    }
+
+    /// Restarts Alice so that she declines swaps whose Monero amount is below
+    /// `min_monero_output`.
+    pub async fn restart_alice_with_min_monero_output(
+        &mut self,
+        min_monero_output: Option<monero::Amount>,
+    ) {
+        self.start_alice_again(
+            FixedRate::default(),
+            None,
+            min_monero_output,
+            None,
+            SWAP_CHANNEL_CAPACITY,
+        )
+        .await
+    }
+
+    /// Restarts Alice so that she keeps `monero_reserve` out of her quotes.
+    pub async fn restart_alice_with_monero_reserve(
+        &mut self,
+        monero_reserve: Option<monero::Amount>,
+    ) {
+        self.start_alice_again(
+            FixedRate::default(),
+            None,
+            None,
+            monero_reserve,
+            SWAP_CHANNEL_CAPACITY,
+        )
+        .await
+    }
+
+    /// Restarts Alice so that at most `swap_channel_capacity` started swaps
+    /// wait to be picked up with [`alice_next_swap`](Self::alice_next_swap).
+    pub async fn restart_alice_with_swap_channel_capacity(&mut self, swap_channel_capacity: usize) {
+        self.start_alice_again(
+            FixedRate::default(),
+            None,
+            None,
+            None,
+            swap_channel_capacity,
+        )
+        .await
+    }
+
+    /// Restarts Alice so that she makes quotes from `latest_rate`.
+    pub async fn restart_alice_with_rate<LR>(&mut self, latest_rate: LR)
+    where
+        LR: LatestRate + Send + 'static + fmt::Debug + Clone,
+    {
+        self.start_alice_again(latest_rate, None, None, None, SWAP_CHANNEL_CAPACITY)
+            .await
+    }
+
+    async fn start_alice_again<LR>(
+        &mut self,
+        latest_rate: LR,
+        max_monero_lock_fee: Option<monero::Amount>,
+        min_monero_output: Option<monero::Amount>,
+        monero_reserve: Option<monero::Amount>,
+        swap_channel_capacity: usize,
+    ) where
+        LR: LatestRate + Send + 'static + fmt::Debug + Clone,
+    {
         self.alice_handle.abort();
 
         let (alice_handle, alice_swap_handle) = start_alice(
@@ -544,6 +843,12 @@ impl TestContext {
             self.env_config,
             self.alice_bitcoin_wallet.clone(),
             self.alice_monero_wallet.clone(),
+            latest_rate,
+            bitcoin::Amount::from_sat(u64::MAX),
+            max_monero_lock_fee,
+            min_monero_output,
+            monero_reserve,
+            swap_channel_capacity,
         )
         .await;
 
@@ -551,6 +856,87 @@ impl TestContext {
         self.alice_swap_handle = alice_swap_handle;
     }
 
+    /// Starts another ASB that shares Alice's wallets but has its own
+    /// identity and database, for tests with several makers. It buys at most
+    /// `max_buy` per swap.
+    pub async fn start_second_alice(&self, max_buy: bitcoin::Amount) -> SecondAlice {
+        let seed = Seed::deterministic(b"second alice");
+        let address: Multiaddr = format!(
+            "/ip4/127.0.0.1/tcp/{}",
+            get_port().expect("Failed to find a free port")
+        )
+        .parse()
+        .expect("failed to parse second Alice's address");
+
+        let (handle, swap_handle) = start_alice(
+            &seed,
+            NamedTempFile::new().unwrap().path().to_path_buf(),
+            address.clone(),
+            self.env_config,
+            self.alice_bitcoin_wallet.clone(),
+            self.alice_monero_wallet.clone(),
+            FixedRate::default(),
+            max_buy,
+            None,
+            None,
+            None,
+            SWAP_CHANNEL_CAPACITY,
+        )
+        .await;
+
+        SecondAlice {
+            peer_id: handle.peer_id,
+            address,
+            handle,
+            swap_handle,
+        }
+    }
+
+    /// Alice's peer id and the address under which Bob reaches her.
+    pub fn alice_maker(&self) -> (PeerId, Multiaddr) {
+        (
+            self.bob_params.alice_peer_id,
+            self.bob_params.alice_address.clone(),
+        )
+    }
+
+    /// Bob's side of a swap split across several makers, using Bob's wallets
+    /// and database.
+    pub async fn bob_split_swap(&self) -> cli::SplitSwap {
+        let db_path = &self.bob_params.db_path;
+        if let Some(parent_dir) = db_path.parent() {
+            ensure_directory_exists(parent_dir).unwrap();
+        }
+        if !db_path.exists() {
+            tokio::fs::File::create(db_path).await.unwrap();
+        }
+
+        cli::SplitSwap {
+            db: Arc::new(SqliteDatabase::open(db_path).await.unwrap()),
+            bitcoin_wallet: self.bob_bitcoin_wallet.clone(),
+            monero_wallet: self.bob_monero_wallet.clone(),
+            env_config: self.env_config,
+            identity: self.bob_params.seed.derive_libp2p_identity(),
+            namespace: XmrBtcNamespace::Testnet,
+            tor_socks5_port: get_port().expect(
+                "We don't care about Tor in the tests so we get a free port to disable it.",
+            ),
+            monero_receive_address: self.bob_monero_wallet.get_main_address(),
+            abort_handle: Default::default(),
+        }
+    }
+
+    /// Overwrites the checkpoint Alice recorded for the given swap, e.g. to
+    /// simulate a stale checkpoint.
+    pub async fn insert_alice_checkpoint(&self, swap_id: Uuid, checkpoint: Checkpoint) {
+        SqliteDatabase::open(&self.alice_db_path)
+            .await
+            .unwrap()
+            .insert_checkpoint(swap_id, checkpoint)
+            .await
+            .unwrap();
+    }
+
     pub async fn alice_next_swap(&mut self) -> alice::Swap {
         timeout(Duration::from_secs(20), self.alice_swap_handle.recv())
             .await
@@ -559,7 +945,17 @@ impl TestContext {
     }
 
     pub async fn bob_swap(&mut self) -> (bob::Swap, BobApplicationHandle) {
-        let (swap, event_loop) = self.bob_params.new_swap(self.btc_amount).await.unwrap();
+        self.bob_swap_with_id(Uuid::new_v4()).await
+    }
+
+    /// Sets up a new swap for Bob under the given swap id instead of a random
+    /// one.
+    pub async fn bob_swap_with_id(&mut self, swap_id: Uuid) -> (bob::Swap, BobApplicationHandle) {
+        let (swap, event_loop) = self
+            .bob_params
+            .new_swap_with_id(self.btc_amount, swap_id)
+            .await
+            .unwrap();
 
         // ensure the wallet is up to date for concurrent swap tests
         swap.bitcoin_wallet.sync().await.unwrap();
@@ -569,6 +965,59 @@ impl TestContext {
         (swap, BobApplicationHandle(join_handle))
     }
 
+    /// Sets up a new swap for Bob from a fresh Bitcoin wallet that holds
+    /// `shortfall` less than the swap amount and waits for up to
+    /// `top_up_timeout` for a deposit before setting up the swap.
+    ///
+    /// Returns the wallet as well, so tests can top it up.
+    pub async fn bob_swap_short_of_funds(
+        &mut self,
+        shortfall: bitcoin::Amount,
+        top_up_timeout: Duration,
+    ) -> (bob::Swap, BobApplicationHandle, Arc<bitcoin::Wallet>) {
+        let bitcoin_wallet = self.new_bitcoin_wallet(b"bob top-up").await;
+        self.mint(
+            bitcoin_wallet.new_address().await.unwrap(),
+            self.btc_amount - shortfall,
+        )
+        .await
+        .unwrap();
+        let bitcoin_wallet = Arc::new(bitcoin_wallet);
+
+        let bob_params = BobParams {
+            seed: Seed::deterministic(b"bob swaps"),
+            db_path: self.bob_params.db_path.clone(),
+            bitcoin_wallet: bitcoin_wallet.clone(),
+            monero_wallet: self.bob_monero_wallet.clone(),
+            alice_address: self.bob_params.alice_address.clone(),
+            alice_peer_id: self.bob_params.alice_peer_id,
+            env_config: self.env_config,
+        };
+        let (swap, event_loop) = bob_params.new_swap(self.btc_amount).await.unwrap();
+        swap.bitcoin_wallet.sync().await.unwrap();
+        let swap = swap.with_top_up_timeout(top_up_timeout);
+
+        let join_handle = tokio::spawn(event_loop.run());
+
+        (swap, BobApplicationHandle(join_handle), bitcoin_wallet)
+    }
+
+    /// An empty Bitcoin wallet derived from `seed`, connected to the harness'
+    /// Electrum server.
+    pub async fn new_bitcoin_wallet(&self, seed: &[u8]) -> bitcoin::Wallet {
+        bitcoin::Wallet::new(
+            self.electrum_rpc_url.clone(),
+            tempdir().unwrap().path(),
+            Seed::deterministic(seed)
+                .derive_extended_private_key(self.env_config.bitcoin_network)
+                .unwrap(),
+            self.env_config,
+            1,
+        )
+        .await
+        .unwrap()
+    }
+
     pub async fn stop_and_resume_bob_from_db(
         &mut self,
         join_handle: BobApplicationHandle,
@@ -583,6 +1032,52 @@ impl TestContext {
         (swap, BobApplicationHandle(join_handle))
     }
 
+    /// Stops Bob and moves his swaps into a fresh database through an export
+    /// and an import, like when migrating to another machine, then resumes
+    /// the swap from the fresh database.
+    pub async fn stop_and_resume_bob_from_imported_db(
+        &mut self,
+        join_handle: BobApplicationHandle,
+        swap_id: Uuid,
+    ) -> (bob::Swap, BobApplicationHandle) {
+        join_handle.abort();
+
+        let mut backup = Vec::new();
+        SqliteDatabase::open(&self.bob_params.db_path)
+            .await
+            .unwrap()
+            .export(&mut backup)
+            .await
+            .unwrap();
+
+        let fresh_db_path = tempdir().unwrap().into_path().join("sqlite");
+        tokio::fs::File::create(&fresh_db_path).await.unwrap();
+        SqliteDatabase::open(&fresh_db_path)
+            .await
+            .unwrap()
+            .import(&mut backup.as_slice(), OnCollision::Error)
+            .await
+            .unwrap();
+        self.bob_params.db_path = fresh_db_path;
+
+        let (swap, event_loop) = self.bob_params.new_swap_from_db(swap_id).await.unwrap();
+
+        let join_handle = tokio::spawn(event_loop.run());
+
+        (swap, BobApplicationHandle(join_handle))
+    }
+
+    pub async fn try_resume_bob_from_db(
+        &mut self,
+        swap_id: Uuid,
+    ) -> Result<(bob::Swap, BobApplicationHandle)> {
+        let (swap, event_loop) = self.bob_params.new_swap_from_db(swap_id).await?;
+
+        let join_handle = tokio::spawn(event_loop.run());
+
+        Ok((swap, BobApplicationHandle(join_handle)))
+    }
+
     pub async fn assert_alice_redeemed(&mut self, state: AliceState) {
         assert!(matches!(state, AliceState::BtcRedeemed));
 
@@ -590,6 +1085,7 @@ impl TestContext {
             self.alice_bitcoin_wallet.as_ref(),
             Ordering::Equal,
             self.alice_redeemed_btc_balance().await,
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -598,6 +1094,7 @@ impl TestContext {
             self.alice_monero_wallet.as_ref(),
             Ordering::Less,
             self.alice_redeemed_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -610,6 +1107,7 @@ impl TestContext {
             self.alice_bitcoin_wallet.as_ref(),
             Ordering::Equal,
             self.alice_refunded_btc_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -619,6 +1117,7 @@ impl TestContext {
             self.alice_monero_wallet.as_ref(),
             Ordering::Greater,
             self.alice_refunded_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -631,6 +1130,7 @@ impl TestContext {
             self.alice_bitcoin_wallet.as_ref(),
             Ordering::Equal,
             self.alice_punished_btc_balance().await,
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -639,6 +1139,7 @@ impl TestContext {
             self.alice_monero_wallet.as_ref(),
             Ordering::Less,
             self.alice_punished_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -649,6 +1150,7 @@ impl TestContext {
             self.bob_bitcoin_wallet.as_ref(),
             Ordering::Equal,
             self.bob_redeemed_btc_balance(state).await.unwrap(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -660,6 +1162,7 @@ impl TestContext {
             self.bob_monero_wallet.as_ref(),
             Ordering::Greater,
             self.bob_redeemed_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -701,6 +1204,7 @@ impl TestContext {
             self.bob_monero_wallet.as_ref(),
             Ordering::Equal,
             self.bob_refunded_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -711,6 +1215,7 @@ impl TestContext {
             self.bob_bitcoin_wallet.as_ref(),
             Ordering::Equal,
             self.bob_punished_btc_balance(state).await.unwrap(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -719,6 +1224,7 @@ impl TestContext {
             self.bob_monero_wallet.as_ref(),
             Ordering::Equal,
             self.bob_punished_xmr_balance(),
+            self.assert_timeout,
         )
         .await
         .unwrap();
@@ -804,10 +1310,58 @@ impl TestContext {
     }
 }
 
-async fn assert_eventual_balance<A: fmt::Display + PartialOrd>(
+/// How long to wait for an eventual assertion to hold and how often to
+/// re-check it in the meantime.
+#[derive(Debug, Clone, Copy)]
+pub struct AssertTimeout {
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for AssertTimeout {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Waits until the latest state `db` recorded for `swap_id` matches
+/// `is_state`, while the swap keeps running. Lets a test inject a network
+/// fault at a protocol phase without stopping either party.
+pub async fn wait_until_state<S>(
+    db: &Arc<dyn Database + Send + Sync>,
+    swap_id: Uuid,
+    is_state: fn(&S) -> bool,
+) where
+    State: TryInto<S>,
+{
+    let reached = async {
+        loop {
+            let state = db
+                .get_state(swap_id)
+                .await
+                .ok()
+                .and_then(|state| state.try_into().ok());
+            if state.as_ref().map_or(false, is_state) {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    };
+
+    timeout(Duration::from_secs(5 * 60), reached)
+        .await
+        .expect("swap to reach the state within 5 minutes");
+}
+
+pub async fn assert_eventual_balance<A: fmt::Display + PartialOrd>(
     wallet: &impl Wallet<Amount = A>,
     ordering: Ordering,
     expected: A,
+    assert_timeout: AssertTimeout,
 ) -> Result<()> {
     let ordering_str = match ordering {
         Ordering::Less => "less than",
@@ -819,7 +1373,7 @@ async fn assert_eventual_balance<A: fmt::Display + PartialOrd>(
 
     let assertion = async {
         while current_balance.partial_cmp(&expected).unwrap() != ordering {
-            tokio::time::sleep(Duration::from_millis(500)).await;
+            tokio::time::sleep(assert_timeout.poll_interval).await;
 
             wallet.refresh().await?;
             current_balance = wallet.get_balance().await?;
@@ -835,17 +1389,12 @@ async fn assert_eventual_balance<A: fmt::Display + PartialOrd>(
         Result::<_, anyhow::Error>::Ok(())
     };
 
-    let timeout = Duration::from_secs(10);
-
-    tokio::time::timeout(timeout, assertion)
+    tokio::time::timeout(assert_timeout.timeout, assertion)
         .await
         .with_context(|| {
             format!(
-                "Expected balance to be {} {} after at most {}s but was {}",
-                ordering_str,
-                expected,
-                timeout.as_secs(),
-                current_balance
+                "Expected balance to be {} {} after at most {:?} but was {}",
+                ordering_str, expected, assert_timeout.timeout, current_balance
             )
         })??;
 
@@ -853,19 +1402,107 @@ async fn assert_eventual_balance<A: fmt::Display + PartialOrd>(
 }
 
 #[async_trait]
-trait Wallet {
+pub trait Wallet {
     type Amount;
 
-    async fn refresh(&self) -> Result<()>;
+    async fn refresh(&self) -> Result<(), RefreshError>;
     async fn get_balance(&self) -> Result<Self::Amount>;
 }
 
+/// Why a [`Wallet`] failed to refresh, so tests can tell a node that is down
+/// from one that misbehaves.
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshError {
+    #[error("Failed to reach the node of the wallet")]
+    Connectivity(#[source] anyhow::Error),
+    #[error("The node of the wallet rejected our credentials")]
+    Authentication(#[source] anyhow::Error),
+    #[error("The node of the wallet responded unexpectedly")]
+    Protocol(#[source] anyhow::Error),
+}
+
+impl RefreshError {
+    /// Classifies `error` by the first cause in its chain that tells
+    /// something about the connection to the node; anything else is a
+    /// protocol error.
+    pub fn classify(error: anyhow::Error) -> Self {
+        if error.chain().any(is_authentication_error) {
+            return Self::Authentication(error);
+        }
+
+        if error.chain().any(is_connectivity_error) {
+            return Self::Connectivity(error);
+        }
+
+        Self::Protocol(error)
+    }
+}
+
+fn is_authentication_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    let is_unauthorized = |error: &reqwest::Error| {
+        matches!(
+            error.status(),
+            Some(reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN)
+        )
+    };
+
+    if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+        return is_unauthorized(error);
+    }
+
+    if let Some(monero_rpc::jsonrpc::Error::Client(error)) =
+        cause.downcast_ref::<monero_rpc::jsonrpc::Error<reqwest::Error>>()
+    {
+        return is_unauthorized(error);
+    }
+
+    false
+}
+
+fn is_connectivity_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    let is_unreachable = |error: &reqwest::Error| error.is_connect() || error.is_timeout();
+
+    if cause.is::<std::io::Error>() {
+        return true;
+    }
+
+    if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+        return is_unreachable(error);
+    }
+
+    if let Some(monero_rpc::jsonrpc::Error::Client(error)) =
+        cause.downcast_ref::<monero_rpc::jsonrpc::Error<reqwest::Error>>()
+    {
+        return is_unreachable(error);
+    }
+
+    if let Some(bdk::Error::Electrum(error)) = cause.downcast_ref::<bdk::Error>() {
+        return is_electrum_connectivity_error(error);
+    }
+
+    if let Some(error) = cause.downcast_ref::<bdk::electrum_client::Error>() {
+        return is_electrum_connectivity_error(error);
+    }
+
+    false
+}
+
+fn is_electrum_connectivity_error(error: &bdk::electrum_client::Error) -> bool {
+    use bdk::electrum_client::Error;
+
+    match error {
+        Error::IOError(_) | Error::SharedIOError(_) => true,
+        Error::AllAttemptsErrored(errors) => errors.iter().all(is_electrum_connectivity_error),
+        _ => false,
+    }
+}
+
 #[async_trait]
 impl Wallet for monero::Wallet {
     type Amount = monero::Amount;
 
-    async fn refresh(&self) -> Result<()> {
-        self.refresh().await?;
+    async fn refresh(&self) -> Result<(), RefreshError> {
+        self.refresh().await.map_err(RefreshError::classify)?;
 
         Ok(())
     }
@@ -881,8 +1518,8 @@ impl Wallet for monero::Wallet {
 impl Wallet for bitcoin::Wallet {
     type Amount = bitcoin::Amount;
 
-    async fn refresh(&self) -> Result<()> {
-        self.sync().await
+    async fn refresh(&self) -> Result<(), RefreshError> {
+        self.sync().await.map_err(RefreshError::classify)
     }
 
     async fn get_balance(&self) -> Result<Self::Amount> {
@@ -913,7 +1550,7 @@ async fn mine(bitcoind_client: Client, reward_address: bitcoin::Address) -> Resu
     }
 }
 
-async fn init_bitcoind(node_url: Url, spendable_quantity: u32) -> Result<Client> {
+async fn init_bitcoind(node_url: Url, chain_params: ChainParams) -> Result<Client> {
     let bitcoind_client = Client::new(node_url.clone());
 
     bitcoind_client
@@ -926,9 +1563,14 @@ async fn init_bitcoind(node_url: Url, spendable_quantity: u32) -> Result<Client>
         .await?;
 
     bitcoind_client
-        .generatetoaddress(101 + spendable_quantity, reward_address.clone())
+        .generatetoaddress(
+            chain_params.maturity_blocks + chain_params.spendable_coinbase_outputs,
+            reward_address.clone(),
+        )
         .await?;
-    tokio::spawn(mine(bitcoind_client.clone(), reward_address));
+    if chain_params.mine_in_background {
+        tokio::spawn(mine(bitcoind_client.clone(), reward_address));
+    }
     Ok(bitcoind_client)
 }
 
@@ -964,10 +1606,26 @@ struct Containers<'a> {
 pub mod alice_run_until {
     use swap::protocol::alice::AliceState;
 
+    pub fn is_btc_lock_transaction_seen(state: &AliceState) -> bool {
+        matches!(state, AliceState::BtcLockTransactionSeen { .. })
+    }
+
+    pub fn is_btc_locked(state: &AliceState) -> bool {
+        matches!(state, AliceState::BtcLocked { .. })
+    }
+
     pub fn is_xmr_lock_transaction_sent(state: &AliceState) -> bool {
         matches!(state, AliceState::XmrLockTransactionSent { .. })
     }
 
+    pub fn is_xmr_locked(state: &AliceState) -> bool {
+        matches!(state, AliceState::XmrLocked { .. })
+    }
+
+    pub fn is_transfer_proof_sent(state: &AliceState) -> bool {
+        matches!(state, AliceState::XmrLockTransferProofSent { .. })
+    }
+
     pub fn is_encsig_learned(state: &AliceState) -> bool {
         matches!(state, AliceState::EncSigLearned { .. })
     }
@@ -1004,6 +1662,19 @@ impl GetConfig for SlowCancelConfig {
     }
 }
 
+/// Like [`SlowCancelConfig`], but only confirmed outputs fund transactions,
+/// as on mainnet and testnet.
+pub struct ConfirmedFundingConfig;
+
+impl GetConfig for ConfirmedFundingConfig {
+    fn get_config() -> Config {
+        Config {
+            funding_min_confirmations: 1,
+            ..SlowCancelConfig::get_config()
+        }
+    }
+}
+
 pub struct FastCancelConfig;
 
 impl GetConfig for FastCancelConfig {
@@ -1026,3 +1697,25 @@ impl GetConfig for FastPunishConfig {
         }
     }
 }
+
+pub struct FastPunishWithGraceConfig;
+
+impl GetConfig for FastPunishWithGraceConfig {
+    fn get_config() -> Config {
+        Config {
+            punish_grace_blocks: 1_000,
+            ..FastPunishConfig::get_config()
+        }
+    }
+}
+
+pub struct FastPunishWithShortGraceConfig;
+
+impl GetConfig for FastPunishWithShortGraceConfig {
+    fn get_config() -> Config {
+        Config {
+            punish_grace_blocks: 10,
+            ..FastPunishConfig::get_config()
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! A complete swap on the regtest stack of the harness, to check that a build
+//! and its environment work before trusting them with real funds.
+
+use super::TestContext;
+use anyhow::Result;
+use std::fmt;
+use std::time::{Duration, Instant};
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// How long each party may take to finish the swap before it counts as
+/// failed.
+pub const SELF_TEST_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug)]
+pub struct SelfTestReport {
+    pub alice: PartyReport,
+    pub bob: PartyReport,
+}
+
+impl SelfTestReport {
+    pub fn is_success(&self) -> bool {
+        self.alice.outcome == Outcome::Redeemed && self.bob.outcome == Outcome::Redeemed
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = if self.is_success() {
+            "succeeded"
+        } else {
+            "failed"
+        };
+
+        write!(
+            f,
+            "Self test {}, Alice: {}, Bob: {}",
+            result, self.alice, self.bob
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct PartyReport {
+    pub outcome: Outcome,
+    /// Time from the start of the self test until the party finished.
+    pub took: Duration,
+}
+
+impl fmt::Display for PartyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} after {:.1}s", self.outcome, self.took.as_secs_f64())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The party received the asset it swapped for.
+    Redeemed,
+    /// The swap ended in the given state without redeeming.
+    Finished(String),
+    Failed(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Redeemed => write!(f, "redeemed"),
+            Outcome::Finished(state) => write!(f, "finished in state '{}'", state),
+            Outcome::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+impl TestContext {
+    /// Runs a swap between Alice and a new Bob to completion and reports how
+    /// it ended for each of them, instead of panicking on the first failure.
+    pub async fn self_test(&mut self) -> SelfTestReport {
+        let started = Instant::now();
+
+        let (bob_swap, _bob_handle) = self.bob_swap().await;
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = self.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let (alice, bob) = tokio::join!(
+            party_report(started, alice_swap, |state| matches!(
+                state,
+                AliceState::BtcRedeemed
+            )),
+            party_report(started, bob_swap, |state| matches!(
+                state,
+                BobState::XmrRedeemed { .. }
+            )),
+        );
+
+        let report = SelfTestReport { alice, bob };
+        tracing::info!("{}", report);
+
+        report
+    }
+}
+
+async fn party_report<S>(
+    started: Instant,
+    swap: JoinHandle<Result<S>>,
+    is_redeemed: fn(&S) -> bool,
+) -> PartyReport
+where
+    S: fmt::Display,
+{
+    let outcome = match timeout(SELF_TEST_TIMEOUT, swap).await {
+        Ok(Ok(Ok(state))) if is_redeemed(&state) => Outcome::Redeemed,
+        Ok(Ok(Ok(state))) => Outcome::Finished(state.to_string()),
+        Ok(Ok(Err(error))) => Outcome::Failed(format!("{:#}", error)),
+        Ok(Err(error)) => Outcome::Failed(format!("swap task panicked: {}", error)),
+        Err(_) => Outcome::Failed("timed out".to_string()),
+    };
+
+    PartyReport {
+        outcome,
+        took: started.elapsed(),
+    }
+}
@@ -0,0 +1,57 @@
+pub mod harness;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use harness::{assert_eventual_balance, AssertTimeout, RefreshError, Wallet};
+use std::cmp::Ordering;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+
+/// A wallet whose balance grows by one on every refresh.
+#[derive(Default)]
+struct CountingWallet {
+    balance: AtomicU64,
+}
+
+#[async_trait]
+impl Wallet for CountingWallet {
+    type Amount = u64;
+
+    async fn refresh(&self) -> Result<(), RefreshError> {
+        self.balance.fetch_add(1, AtomicOrdering::SeqCst);
+
+        Ok(())
+    }
+
+    async fn get_balance(&self) -> Result<Self::Amount> {
+        Ok(self.balance.load(AtomicOrdering::SeqCst))
+    }
+}
+
+#[tokio::test]
+async fn given_custom_timeout_assertion_fails_after_that_timeout() {
+    let wallet = CountingWallet::default();
+    let assert_timeout = AssertTimeout {
+        timeout: Duration::from_millis(200),
+        poll_interval: Duration::from_millis(50),
+    };
+
+    let started = Instant::now();
+    let result = assert_eventual_balance(&wallet, Ordering::Equal, 1_000, assert_timeout).await;
+
+    assert!(result.is_err());
+    assert!(started.elapsed() < AssertTimeout::default().timeout);
+}
+
+#[tokio::test]
+async fn given_custom_poll_interval_assertion_succeeds_once_balance_is_reached() {
+    let wallet = CountingWallet::default();
+    let assert_timeout = AssertTimeout {
+        timeout: Duration::from_secs(1),
+        poll_interval: Duration::from_millis(10),
+    };
+
+    let result = assert_eventual_balance(&wallet, Ordering::Equal, 5, assert_timeout).await;
+
+    assert!(result.is_ok());
+}
@@ -0,0 +1,118 @@
+pub mod harness;
+
+use harness::fault_proxy::{Fault, FaultProxy};
+use libp2p::core::Multiaddr;
+use libp2p::multiaddr::Protocol;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn given_no_fault_proxy_forwards_traffic() {
+    let (proxy, target) = start_proxy().await;
+    let (mut client, mut server) = connect(&proxy, &target).await;
+
+    client.write_all(b"foo").await.unwrap();
+    server.write_all(b"bar").await.unwrap();
+
+    assert_eq!(read(&mut server, 3).await, b"foo");
+    assert_eq!(read(&mut client, 3).await, b"bar");
+}
+
+#[tokio::test]
+async fn given_delay_fault_proxy_forwards_traffic_late() {
+    let (proxy, target) = start_proxy().await;
+    let (mut client, mut server) = connect(&proxy, &target).await;
+    proxy.inject(Fault::Delay(Duration::from_millis(500)));
+
+    let sent = Instant::now();
+    client.write_all(b"foo").await.unwrap();
+
+    assert_eq!(read(&mut server, 3).await, b"foo");
+    assert!(sent.elapsed() >= Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn given_duplicate_fault_proxy_forwards_traffic_twice() {
+    let (proxy, target) = start_proxy().await;
+    let (mut client, mut server) = connect(&proxy, &target).await;
+    proxy.inject(Fault::Duplicate);
+
+    client.write_all(b"foo").await.unwrap();
+
+    assert_eq!(read(&mut server, 6).await, b"foofoo");
+}
+
+#[tokio::test]
+async fn given_reorder_fault_proxy_swaps_chunks() {
+    let (proxy, target) = start_proxy().await;
+    let (mut client, mut server) = connect(&proxy, &target).await;
+    proxy.inject(Fault::Reorder);
+
+    client.write_all(b"foo").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    client.write_all(b"bar").await.unwrap();
+
+    assert_eq!(read(&mut server, 6).await, b"barfoo");
+}
+
+#[tokio::test]
+async fn given_drop_fault_proxy_closes_and_refuses_connections_until_healed() {
+    let (proxy, target) = start_proxy().await;
+    let (mut client, mut server) = connect(&proxy, &target).await;
+
+    proxy.inject(Fault::Drop);
+
+    assert_eq!(client.read(&mut [0u8; 1]).await.unwrap(), 0);
+    assert_eq!(server.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+    let mut refused = TcpStream::connect(socket_addr(&proxy.address()))
+        .await
+        .unwrap();
+    assert_eq!(refused.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+    proxy.heal();
+    let (mut client, mut server) = connect(&proxy, &target).await;
+
+    client.write_all(b"foo").await.unwrap();
+
+    assert_eq!(read(&mut server, 3).await, b"foo");
+}
+
+async fn start_proxy() -> (FaultProxy, TcpListener) {
+    let target = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let address = format!("/ip4/127.0.0.1/tcp/{}", target.local_addr().unwrap().port())
+        .parse()
+        .unwrap();
+    let proxy = FaultProxy::start(address).await.unwrap();
+
+    (proxy, target)
+}
+
+/// Returns both ends of a new connection through `proxy` to `target`.
+async fn connect(proxy: &FaultProxy, target: &TcpListener) -> (TcpStream, TcpStream) {
+    let client = TcpStream::connect(socket_addr(&proxy.address()))
+        .await
+        .unwrap();
+    let (server, _) = target.accept().await.unwrap();
+
+    (client, server)
+}
+
+async fn read(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; len];
+    tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut buffer))
+        .await
+        .expect("no data within 5 seconds")
+        .unwrap();
+
+    buffer
+}
+
+fn socket_addr(address: &Multiaddr) -> SocketAddr {
+    match address.iter().collect::<Vec<_>>().as_slice() {
+        [Protocol::Ip4(ip), Protocol::Tcp(port)] => SocketAddr::from((*ip, *port)),
+        _ => panic!("unexpected proxy address {}", address),
+    }
+}
@@ -0,0 +1,46 @@
+pub mod harness;
+
+use harness::{assert_eventual_balance, AssertTimeout, ChainParams, SlowCancelConfig};
+use std::cmp::Ordering;
+use swap::bitcoin::{Amount, Wallet};
+use swap::env::GetConfig;
+use swap::seed::Seed;
+use tempfile::tempdir;
+
+/// Starts from a single spendable coinbase output and funds a fresh wallet
+/// with exactly one small UTXO.
+#[tokio::test]
+async fn given_single_spendable_coinbase_then_wallet_with_one_utxo_can_be_created() {
+    let chain_params = ChainParams {
+        spendable_coinbase_outputs: 1,
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |ctx| async move {
+        // Bob's starting balance is the only funding confirmed by a block
+        assert_eq!(
+            ctx.bitcoin_block_height().await?,
+            chain_params.maturity_blocks + chain_params.spendable_coinbase_outputs + 1
+        );
+
+        let env_config = SlowCancelConfig::get_config();
+        let xprivkey = Seed::random()?.derive_extended_private_key(env_config.bitcoin_network)?;
+        let data_dir = tempdir()?;
+        let wallet = Wallet::new(
+            ctx.electrum_rpc_url(),
+            data_dir.path(),
+            xprivkey,
+            env_config,
+            1,
+        )
+        .await?;
+
+        let amount = Amount::from_sat(10_000);
+        ctx.mint(wallet.new_address().await?, amount).await?;
+        assert_eventual_balance(&wallet, Ordering::Equal, amount, AssertTimeout::default()).await?;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,33 @@
+pub mod harness;
+
+use bdk::electrum_client::ElectrumApi;
+use get_port::get_port;
+use harness::RefreshError;
+use monero_rpc::wallet::{Client, MoneroWalletRpc};
+
+#[tokio::test]
+async fn given_unreachable_monero_wallet_rpc_refresh_error_is_connectivity() {
+    let port = get_port().expect("Failed to find a free port");
+    let client = Client::localhost(port).unwrap();
+
+    let error = client.refresh().await.unwrap_err();
+
+    assert!(matches!(
+        RefreshError::classify(error.into()),
+        RefreshError::Connectivity(_)
+    ));
+}
+
+#[tokio::test]
+async fn given_unreachable_electrum_server_refresh_error_is_connectivity() {
+    let port = get_port().expect("Failed to find a free port");
+
+    let error = bdk::electrum_client::Client::new(&format!("tcp://127.0.0.1:{}", port))
+        .and_then(|client| client.block_headers_subscribe())
+        .unwrap_err();
+
+    assert!(matches!(
+        RefreshError::classify(bdk::Error::Electrum(error).into()),
+        RefreshError::Connectivity(_)
+    ));
+}
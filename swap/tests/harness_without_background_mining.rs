@@ -0,0 +1,29 @@
+pub mod harness;
+
+use harness::{ChainParams, SlowCancelConfig};
+use std::time::Duration;
+use swap::bitcoin::Amount;
+
+/// Without background mining the chain only grows when the test mines blocks,
+/// the wallets are funded nonetheless.
+#[tokio::test]
+async fn given_background_mining_disabled_then_chain_only_grows_on_demand() {
+    let chain_params = ChainParams {
+        mine_in_background: false,
+        ..ChainParams::default()
+    };
+
+    harness::setup_test_with_chain(SlowCancelConfig, chain_params, |ctx| async move {
+        assert!(ctx.bob_bitcoin_wallet().balance().await? > Amount::ZERO);
+
+        let height = ctx.bitcoin_block_height().await?;
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(ctx.bitcoin_block_height().await?, height);
+
+        ctx.mine_blocks(3).await?;
+        assert_eq!(ctx.bitcoin_block_height().await?, height + 3);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,38 @@
+use monero_harness::Monero;
+use swap::env::{GetConfig, Regtest};
+use swap::monero;
+use testcontainers::clients::Cli;
+use uuid::Uuid;
+
+const WALLET_NAME: &str = "labels";
+
+#[tokio::test]
+async fn labelled_subaddress_can_be_looked_up_by_label() {
+    let tc = Cli::default();
+    let (harness, _monerod_container, _wallet_containers) =
+        Monero::new(&tc, vec![WALLET_NAME]).await.unwrap();
+
+    let wallet = monero::Wallet::connect(
+        harness.wallet(WALLET_NAME).unwrap().client().clone(),
+        WALLET_NAME.to_owned(),
+        Regtest::get_config(),
+    )
+    .await
+    .unwrap();
+
+    let label = format!("swap-{}", Uuid::new_v4());
+    let subaddress = wallet.create_subaddress(label.clone()).await.unwrap();
+
+    assert_ne!(subaddress, wallet.get_main_address());
+    assert_eq!(
+        wallet.address_by_label(&label).await.unwrap(),
+        Some(subaddress)
+    );
+    assert_eq!(
+        wallet
+            .address_by_label(&format!("swap-{}", Uuid::new_v4()))
+            .await
+            .unwrap(),
+        None
+    );
+}
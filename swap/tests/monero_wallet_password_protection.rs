@@ -0,0 +1,59 @@
+use monero_harness::Monero;
+use monero_rpc::wallet::MoneroWalletRpc as _;
+use swap::env::{GetConfig, Regtest};
+use swap::monero;
+use swap::monero::WrongWalletPassword;
+use testcontainers::clients::Cli;
+
+const WALLET_NAME: &str = "password";
+const PROTECTED_WALLET_NAME: &str = "protected";
+
+#[tokio::test]
+async fn protected_wallet_only_opens_with_its_password() {
+    let tc = Cli::default();
+    let (harness, _monerod_container, _wallet_containers) =
+        Monero::new(&tc, vec![WALLET_NAME]).await.unwrap();
+    let client = harness.wallet(WALLET_NAME).unwrap().client().clone();
+    client.close_wallet().await.unwrap();
+
+    let wallet = open(&client, "correct horse").await.unwrap();
+    let address = wallet.get_main_address();
+    client.close_wallet().await.unwrap();
+
+    let error = open(&client, "wrong").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<WrongWalletPassword>(),
+        Some(&WrongWalletPassword {
+            name: PROTECTED_WALLET_NAME.to_owned()
+        }),
+        "wrong password should be reported as such"
+    );
+
+    let wallet = open(&client, "correct horse").await.unwrap();
+    assert_eq!(wallet.get_main_address(), address);
+
+    wallet
+        .change_password("correct horse".to_owned(), "battery staple".to_owned())
+        .await
+        .unwrap();
+    client.close_wallet().await.unwrap();
+
+    let error = open(&client, "correct horse").await.unwrap_err();
+    assert!(error.downcast_ref::<WrongWalletPassword>().is_some());
+
+    let wallet = open(&client, "battery staple").await.unwrap();
+    assert_eq!(wallet.get_main_address(), address);
+}
+
+async fn open(
+    client: &monero_rpc::wallet::Client,
+    password: &str,
+) -> anyhow::Result<monero::Wallet> {
+    monero::Wallet::open_or_create_protected(
+        client.clone(),
+        PROTECTED_WALLET_NAME.to_owned(),
+        password.to_owned(),
+        Regtest::get_config(),
+    )
+    .await
+}
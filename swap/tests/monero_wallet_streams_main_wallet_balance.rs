@@ -0,0 +1,71 @@
+use futures::StreamExt;
+use monero_harness::Monero;
+use monero_rpc::wallet::BlockHeight;
+use std::sync::Arc;
+use std::time::Duration;
+use swap::env::{GetConfig, Regtest};
+use swap::monero;
+use testcontainers::clients::Cli;
+use tokio::time::timeout;
+
+const WALLET_NAME: &str = "balance";
+
+/// The background refresh publishes deposits to the main wallet, the balance
+/// of a wallet generated from the keys of a swap is not published.
+#[tokio::test]
+async fn balance_stream_follows_main_wallet_only() {
+    let deposit = 1_000_000_000_000;
+
+    let tc = Cli::default();
+    let (harness, _monerod_container, _wallet_containers) =
+        Monero::new(&tc, vec![WALLET_NAME]).await.unwrap();
+    harness.init_and_start_miner().await.unwrap();
+
+    let wallet = Arc::new(
+        monero::Wallet::connect(
+            harness.wallet(WALLET_NAME).unwrap().client().clone(),
+            WALLET_NAME.to_owned(),
+            Regtest::get_config(),
+        )
+        .await
+        .unwrap(),
+    );
+    let mut balances = Box::pin(wallet.balance_stream());
+    let _refresh = wallet.refresh_continuously(Duration::from_secs(1));
+
+    harness
+        .wallet("miner")
+        .unwrap()
+        .transfer(&wallet.get_main_address().to_string(), deposit)
+        .await
+        .unwrap();
+
+    let balance = timeout(Duration::from_secs(120), balances.next())
+        .await
+        .expect("deposit to be published by the background refresh")
+        .unwrap();
+    assert_eq!(balance, monero::Amount::from_piconero(deposit));
+
+    // a swap's wallet with nothing in it, as loaded when redeeming
+    let spend_key = monero::PrivateKey {
+        scalar: monero::Scalar::random(&mut rand::thread_rng()),
+    };
+    let view_key = monero::PrivateViewKey::new_random(&mut rand::thread_rng());
+    wallet
+        .create_from_and_load(
+            "swap".to_owned(),
+            spend_key,
+            view_key,
+            BlockHeight { height: 0 },
+        )
+        .await
+        .unwrap();
+    wallet.refresh().await.unwrap();
+
+    assert!(
+        timeout(Duration::from_secs(5), balances.next())
+            .await
+            .is_err(),
+        "balance of the swap's wallet must not be published"
+    );
+}
@@ -0,0 +1,56 @@
+use monero_harness::Monero;
+use monero_rpc::wallet::MoneroWalletRpc as _;
+use swap::env::{GetConfig, Regtest};
+use swap::monero;
+use testcontainers::clients::Cli;
+
+const WALLET_NAME: &str = "account";
+
+#[tokio::test]
+async fn balance_and_address_target_configured_account_index() {
+    let fund_account = 1_000_000_000_000;
+
+    let tc = Cli::default();
+    let (harness, _monerod_container, _wallet_containers) =
+        Monero::new(&tc, vec![WALLET_NAME]).await.unwrap();
+    harness.init_and_start_miner().await.unwrap();
+
+    let client = harness.wallet(WALLET_NAME).unwrap().client().clone();
+    let account = client.create_account("swaps".to_owned()).await.unwrap();
+    harness
+        .wallet("miner")
+        .unwrap()
+        .transfer(&account.address, fund_account)
+        .await
+        .unwrap();
+
+    let wallet = monero::Wallet::connect(client, WALLET_NAME.to_owned(), Regtest::get_config())
+        .await
+        .unwrap()
+        .with_account_index(account.account_index)
+        .await
+        .unwrap();
+    wallet.refresh().await.unwrap();
+
+    assert_eq!(
+        wallet.get_main_address().to_string(),
+        account.address,
+        "main address should belong to the configured account"
+    );
+    assert_eq!(
+        wallet.get_balance().await.unwrap().balance,
+        fund_account,
+        "balance should be the one of the configured account"
+    );
+
+    let primary = monero::Wallet::connect(
+        harness.wallet(WALLET_NAME).unwrap().client().clone(),
+        WALLET_NAME.to_owned(),
+        Regtest::get_config(),
+    )
+    .await
+    .unwrap();
+
+    assert_ne!(primary.get_main_address(), wallet.get_main_address());
+    assert_eq!(primary.get_balance().await.unwrap().balance, 0);
+}
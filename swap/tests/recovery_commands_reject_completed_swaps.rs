@@ -0,0 +1,126 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::{Finality, FixedRate};
+use swap::protocol::{alice, bob, AlreadyCompleted, Database, State};
+use swap::{asb, cli};
+use tokio::join;
+
+/// Once both parties completed the swap, every recovery command and resuming
+/// the swap fail with `AlreadyCompleted` without touching the swap.
+#[tokio::test]
+async fn given_completed_swap_recovery_commands_report_already_completed() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+        let alice_monero_wallet = alice_swap.monero_wallet.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let (bob_state, alice_state) = join!(bob_swap, alice_swap);
+        let (bob_state, alice_state) = (bob_state??, alice_state??);
+        ctx.assert_alice_redeemed(alice_state.clone()).await;
+        ctx.assert_bob_redeemed(bob_state.clone()).await;
+
+        let alice_completed = AlreadyCompleted {
+            swap_id: alice_swap_id,
+            final_state: State::Alice(alice_state),
+        };
+        let alice_errors = vec![
+            asb::redeem(
+                alice_swap_id,
+                alice_bitcoin_wallet.clone(),
+                alice_db.clone(),
+                Finality::NotAwait,
+            )
+            .await
+            .unwrap_err(),
+            asb::cancel(
+                alice_swap_id,
+                alice_bitcoin_wallet.clone(),
+                alice_db.clone(),
+            )
+            .await
+            .unwrap_err(),
+            asb::refund(
+                alice_swap_id,
+                alice_bitcoin_wallet.clone(),
+                alice_monero_wallet,
+                alice_db.clone(),
+            )
+            .await
+            .unwrap_err(),
+            asb::punish(alice_swap_id, alice_bitcoin_wallet, alice_db.clone())
+                .await
+                .unwrap_err(),
+            asb::safely_abort(alice_swap_id, alice_db.clone())
+                .await
+                .unwrap_err(),
+        ];
+        for error in alice_errors {
+            assert_eq!(
+                error.downcast_ref::<AlreadyCompleted>(),
+                Some(&alice_completed)
+            );
+        }
+        assert_eq!(
+            alice_db.get_state(alice_swap_id).await?,
+            alice_completed.final_state
+        );
+
+        let bob_completed = AlreadyCompleted {
+            swap_id: bob_swap_id,
+            final_state: State::Bob(bob_state),
+        };
+        let bob_errors = vec![
+            cli::cancel(bob_swap_id, bob_bitcoin_wallet.clone(), bob_db.clone())
+                .await
+                .unwrap_err(),
+            cli::refund(
+                bob_swap_id,
+                bob_bitcoin_wallet.clone(),
+                bob_db.clone(),
+                None,
+            )
+            .await
+            .unwrap_err(),
+            cli::manual_broadcast(bob_swap_id, bob_db.clone())
+                .await
+                .unwrap_err(),
+            cli::confirm_broadcast(bob_swap_id, bob_bitcoin_wallet, bob_db.clone())
+                .await
+                .unwrap_err(),
+        ];
+        for error in bob_errors {
+            assert_eq!(
+                error.downcast_ref::<AlreadyCompleted>(),
+                Some(&bob_completed)
+            );
+        }
+
+        bob_join_handle.abort();
+        let error = ctx
+            .try_resume_bob_from_db(bob_swap_id)
+            .await
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<AlreadyCompleted>(),
+            Some(&bob_completed)
+        );
+        assert_eq!(
+            bob_db.get_state(bob_swap_id).await?,
+            bob_completed.final_state
+        );
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,104 @@
+pub mod harness;
+
+use harness::alice_run_until::is_encsig_learned;
+use harness::bob_run_until::is_encsig_sent;
+use harness::FastCancelConfig;
+use swap::asb;
+use swap::asb::{Finality, FixedRate};
+use swap::protocol::alice::AliceState;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database, State};
+
+/// Once the cancel timelock expired Alice redeems while Bob cancels, only one
+/// of the two transactions can spend the lock output. The party that lost the
+/// race resumes and finishes the swap according to the transaction that won.
+#[tokio::test]
+async fn given_redeem_and_cancel_race_then_resumed_swap_follows_the_confirmed_transaction() {
+    harness::setup_test(FastCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_encsig_sent));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run_until(
+            alice_swap,
+            is_encsig_learned,
+            FixedRate::default(),
+        ));
+
+        let alice_state = alice_swap.await??;
+        let state3 = if let AliceState::EncSigLearned { state3, .. } = alice_state {
+            state3
+        } else {
+            panic!("Alice in unexpected state {}", alice_state);
+        };
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::EncSigSent { .. }));
+
+        let (bob_swap, bob_join_handle) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_db = bob_swap.db.clone();
+        bob_swap
+            .bitcoin_wallet
+            .subscribe_to(state3.tx_lock.clone())
+            .await
+            .wait_until_confirmed_with(state3.cancel_timelock)
+            .await?;
+
+        ctx.restart_alice().await;
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_bitcoin_wallet = alice_swap.bitcoin_wallet.clone();
+
+        let (alice_redeemed, bob_cancelled) = tokio::join!(
+            asb::redeem(
+                alice_swap.swap_id,
+                alice_swap.bitcoin_wallet,
+                alice_swap.db,
+                Finality::NotAwait,
+            ),
+            bob::run(bob_swap),
+        );
+
+        let spent_by = state3.tx_lock.spent_by(&alice_bitcoin_wallet).await?;
+
+        if spent_by == Some(state3.tx_redeem().txid()) {
+            let (_, alice_state) = alice_redeemed?;
+            ctx.assert_alice_redeemed(alice_state.clone()).await;
+
+            let (bob_swap, _) = ctx
+                .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+                .await;
+            let bob_state = bob::run(bob_swap).await?;
+            assert!(matches!(bob_state, BobState::XmrRedeemed { .. }));
+            ctx.assert_bob_redeemed(bob_state.clone()).await;
+
+            assert_eq!(
+                alice_db.get_state(alice_swap_id).await?,
+                State::Alice(alice_state)
+            );
+            assert_eq!(bob_db.get_state(bob_swap_id).await?, State::Bob(bob_state));
+        } else {
+            assert_eq!(spent_by, Some(state3.tx_cancel().txid()));
+            let bob_state = bob_cancelled?;
+            assert!(matches!(bob_state, BobState::BtcRefunded { .. }));
+
+            ctx.restart_alice().await;
+            let alice_swap = ctx.alice_next_swap().await;
+            let alice_state = alice::run(alice_swap, FixedRate::default()).await?;
+            ctx.assert_alice_refunded(alice_state.clone()).await;
+            ctx.assert_bob_refunded(bob_state.clone()).await;
+
+            assert_eq!(
+                alice_db.get_state(alice_swap_id).await?,
+                State::Alice(alice_state)
+            );
+            assert_eq!(bob_db.get_state(bob_swap_id).await?, State::Bob(bob_state));
+        }
+
+        Ok(())
+    })
+    .await;
+}
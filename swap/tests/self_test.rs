@@ -0,0 +1,19 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+
+/// Run with `cargo test --package swap --test self_test` to check that a
+/// build works with the regtest Bitcoin and Monero stack of the harness.
+#[tokio::test]
+async fn self_test_reports_success_on_healthy_environment() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let report = ctx.self_test().await;
+
+        assert!(report.is_success(), "{}", report);
+        assert!(report.alice.took > std::time::Duration::ZERO);
+        assert!(report.bob.took > std::time::Duration::ZERO);
+
+        Ok(())
+    })
+    .await;
+}
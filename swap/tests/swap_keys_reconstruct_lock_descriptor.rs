@@ -0,0 +1,47 @@
+pub mod harness;
+
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob};
+
+/// Both parties record the same public keys, which reproduce the output the
+/// Bitcoin was locked in after the swap completed.
+#[tokio::test]
+async fn given_completed_swap_then_stored_keys_reconstruct_lock_descriptor() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_bitcoin_wallet = bob_swap.bitcoin_wallet.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        let tx_lock_id = if let BobState::XmrRedeemed { tx_lock_id } = bob_state {
+            tx_lock_id
+        } else {
+            panic!("Bob in unexpected state {}", bob_state);
+        };
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let bob_keys = bob_db.swap_keys(bob_swap_id).await?;
+        let alice_keys = alice_db.swap_keys(alice_swap_id).await?;
+        assert_eq!(bob_keys, alice_keys);
+
+        let tx_lock = bob_bitcoin_wallet.get_raw_transaction(tx_lock_id).await?;
+        let lock_script = bob_keys.lock_descriptor().script_pubkey();
+        assert!(tx_lock
+            .output
+            .iter()
+            .any(|output| output.script_pubkey == lock_script));
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,52 @@
+pub mod harness;
+
+use harness::bob_run_until::is_btc_locked;
+use harness::SlowCancelConfig;
+use swap::asb::FixedRate;
+use swap::database::SwapBackup;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, Database, SwapMetadata};
+
+#[tokio::test]
+async fn given_metadata_when_swap_is_resumed_then_metadata_is_kept_and_exported() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, bob_join_handle) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run_until(bob_swap, is_btc_locked));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        let bob_state = bob_swap.await??;
+        assert!(matches!(bob_state, BobState::BtcLocked { .. }));
+
+        let metadata = SwapMetadata::from([
+            ("order_id".to_owned(), "order-42".to_owned()),
+            ("note".to_owned(), "first customer".to_owned()),
+        ]);
+        bob_db.set_metadata(bob_swap_id, metadata.clone()).await?;
+
+        let (bob_swap, _) = ctx
+            .stop_and_resume_bob_from_db(bob_join_handle, bob_swap_id)
+            .await;
+        let bob_state = bob::run(bob_swap).await?;
+        ctx.assert_bob_redeemed(bob_state).await;
+        ctx.assert_alice_redeemed(alice_swap.await??).await;
+
+        assert_eq!(bob_db.get_metadata(bob_swap_id).await?, metadata);
+        assert_eq!(bob_db.receipt(bob_swap_id).await?.metadata, metadata);
+
+        let mut export = Vec::new();
+        bob_db.export(&mut export).await?;
+        let backups: Vec<SwapBackup> = serde_json::from_slice(&export)?;
+        let backup = backups
+            .iter()
+            .find(|backup| backup.swap_id == bob_swap_id)
+            .expect("swap to be exported");
+        assert_eq!(backup.metadata, metadata);
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,75 @@
+pub mod harness;
+
+use harness::bob_run_until::{is_lock_proof_received, is_xmr_locked};
+use harness::fault_proxy::Fault;
+use harness::SlowCancelConfig;
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::protocol::bob::BobState;
+use swap::protocol::{alice, bob, State};
+
+/// The connection between Bob and Alice breaks while Bob waits for the Monero
+/// lock to confirm, so sending the encrypted signature fails. Bob keeps
+/// redialling and sends it once the network is back, both redeem.
+#[tokio::test]
+async fn given_connection_breaks_before_encsig_then_bob_sends_it_after_reconnecting() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        harness::wait_until_state(&bob_db, bob_swap_id, is_lock_proof_received).await;
+        // Duplicated frames fail decryption, libp2p closes the live connection
+        // and every redial
+        ctx.inject_network_fault(Fault::Duplicate);
+
+        harness::wait_until_state(&bob_db, bob_swap_id, is_xmr_locked).await;
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(matches!(
+            bob_db.get_state(bob_swap_id).await?,
+            State::Bob(BobState::XmrLocked(..))
+        ));
+        ctx.heal_network();
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        Ok(())
+    })
+    .await;
+}
+
+/// Every chunk of traffic between Bob and Alice is held back while Bob sends
+/// the encrypted signature, the swap still completes over the slow
+/// connection.
+#[tokio::test]
+async fn given_slow_connection_during_encsig_then_both_redeem() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        harness::wait_until_state(&bob_db, bob_swap_id, is_lock_proof_received).await;
+        ctx.inject_network_fault(Fault::Delay(Duration::from_millis(500)));
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        Ok(())
+    })
+    .await;
+}
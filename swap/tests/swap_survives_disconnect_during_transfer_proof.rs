@@ -0,0 +1,48 @@
+pub mod harness;
+
+use harness::alice_run_until::is_xmr_locked;
+use harness::bob_run_until::is_btc_locked;
+use harness::fault_proxy::Fault;
+use harness::SlowCancelConfig;
+use std::time::Duration;
+use swap::asb::FixedRate;
+use swap::protocol::alice::AliceState;
+use swap::protocol::{alice, bob, State};
+
+/// The connection between Bob and Alice drops while Bob waits for the
+/// transfer proof, so Alice cannot send it once she locked the Monero. Alice
+/// sends the transfer proof once Bob reconnected, both redeem.
+#[tokio::test]
+async fn given_connection_drops_before_transfer_proof_then_alice_sends_it_after_reconnecting() {
+    harness::setup_test(SlowCancelConfig, |mut ctx| async move {
+        let (bob_swap, _) = ctx.bob_swap().await;
+        let bob_swap_id = bob_swap.id;
+        let bob_db = bob_swap.db.clone();
+        let bob_swap = tokio::spawn(bob::run(bob_swap));
+
+        let alice_swap = ctx.alice_next_swap().await;
+        let alice_swap_id = alice_swap.swap_id;
+        let alice_db = alice_swap.db.clone();
+        let alice_swap = tokio::spawn(alice::run(alice_swap, FixedRate::default()));
+
+        harness::wait_until_state(&bob_db, bob_swap_id, is_btc_locked).await;
+        ctx.inject_network_fault(Fault::Drop);
+
+        harness::wait_until_state(&alice_db, alice_swap_id, is_xmr_locked).await;
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(matches!(
+            alice_db.get_state(alice_swap_id).await?,
+            State::Alice(AliceState::XmrLocked { .. })
+        ));
+        ctx.heal_network();
+
+        let alice_state = alice_swap.await??;
+        ctx.assert_alice_redeemed(alice_state).await;
+
+        let bob_state = bob_swap.await??;
+        ctx.assert_bob_redeemed(bob_state).await;
+
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,72 @@
+pub mod harness;
+
+use bdk::database::MemoryDatabase;
+use bdk::wallet::AddressIndex;
+use bdk::KeychainKind;
+use harness::SlowCancelConfig;
+use swap::bitcoin::{Amount, Wallet};
+use swap::env::{Config, GetConfig};
+use swap::seed::Seed;
+use tempfile::tempdir;
+
+const STOP_GAP: usize = 5;
+
+/// Funds the first address after a gap of `STOP_GAP` unused addresses. A
+/// wallet syncing with that stop gap stops before reaching it, a wallet with
+/// a larger stop gap finds it.
+#[tokio::test]
+async fn given_funds_beyond_stop_gap_then_sync_does_not_find_them() {
+    harness::setup_test(SlowCancelConfig, |ctx| async move {
+        let env_config = Config {
+            bitcoin_sync_stop_gap: STOP_GAP,
+            ..SlowCancelConfig::get_config()
+        };
+        let xprivkey = Seed::random()?.derive_extended_private_key(env_config.bitcoin_network)?;
+
+        let address = bdk::Wallet::new(
+            bdk::template::Bip84(xprivkey, KeychainKind::External),
+            None,
+            env_config.bitcoin_network,
+            MemoryDatabase::default(),
+        )?
+        .get_address(AddressIndex::Peek(STOP_GAP as u32))?
+        .address;
+
+        let funder = ctx.bob_bitcoin_wallet();
+        let amount = Amount::from_sat(100_000);
+        let psbt = funder.send_to_address(address, amount, None, None).await?;
+        let transaction = funder.sign_and_finalize(psbt).await?;
+        let (_, subscription) = funder.broadcast(transaction, "funding").await?;
+        subscription.wait_until_final().await?;
+
+        let data_dir = tempdir()?;
+        let wallet = Wallet::new(
+            ctx.electrum_rpc_url(),
+            data_dir.path(),
+            xprivkey,
+            env_config,
+            1,
+        )
+        .await?;
+        wallet.sync().await?;
+        assert_eq!(wallet.balance().await?, Amount::ZERO);
+
+        let data_dir = tempdir()?;
+        let wallet = Wallet::new(
+            ctx.electrum_rpc_url(),
+            data_dir.path(),
+            xprivkey,
+            Config {
+                bitcoin_sync_stop_gap: STOP_GAP + 1,
+                ..env_config
+            },
+            1,
+        )
+        .await?;
+        wallet.sync().await?;
+        assert_eq!(wallet.balance().await?, amount);
+
+        Ok(())
+    })
+    .await;
+}